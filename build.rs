@@ -0,0 +1,73 @@
+/// Embed build metadata for `pip version --verbose`
+///
+/// Sets PIP_RS_GIT_DESCRIBE, PIP_RS_BUILD_DATE (from SOURCE_DATE_EPOCH when
+/// set, for reproducible builds), PIP_RS_RUSTC_VERSION, PIP_RS_TARGET,
+/// PIP_RS_PROFILE and PIP_RS_FEATURES.
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    // Only watch git state that exists; a missing path would rebuild every time
+    for path in [".git/HEAD", ".git/refs/heads", ".git/refs/tags", ".git/packed-refs"] {
+        if Path::new(path).exists() {
+            println!("cargo:rerun-if-changed={}", path);
+        }
+    }
+
+    let describe = command_output("git", &["describe", "--tags", "--always"]).unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=PIP_RS_GIT_DESCRIBE={}", describe);
+
+    let epoch = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()));
+    println!("cargo:rustc-env=PIP_RS_BUILD_DATE={}", rfc3339_utc(epoch));
+
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let rustc_version = command_output(&rustc, &["--version"]).unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=PIP_RS_RUSTC_VERSION={}", rustc_version);
+
+    for (name, var) in [("PIP_RS_TARGET", "TARGET"), ("PIP_RS_PROFILE", "PROFILE")] {
+        println!("cargo:rustc-env={}={}", name, std::env::var(var).unwrap_or_else(|_| "unknown".to_string()));
+    }
+
+    let mut features: Vec<String> = std::env::vars()
+        .filter_map(|(key, _)| key.strip_prefix("CARGO_FEATURE_").map(|f| f.to_lowercase().replace('_', "-")))
+        .collect();
+    features.sort();
+    println!("cargo:rustc-env=PIP_RS_FEATURES={}", features.join(","));
+}
+
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    let text = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    (output.status.success() && !text.is_empty()).then_some(text)
+}
+
+/// `YYYY-MM-DDTHH:MM:SSZ` for seconds since the epoch
+fn rfc3339_utc(epoch: u64) -> String {
+    let days = (epoch / 86_400) as i64;
+    let secs = epoch % 86_400;
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3_600,
+        secs % 3_600 / 60,
+        secs % 60
+    )
+}
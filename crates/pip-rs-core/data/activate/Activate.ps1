@@ -0,0 +1,50 @@
+# Dot-source this file from PowerShell: `. {{bin_name}}\Activate.ps1`
+# Running scripts may first need `Set-ExecutionPolicy -Scope Process Bypass`.
+
+function global:deactivate ([switch] $NonDestructive) {
+    if (Test-Path -Path Function:_OLD_VIRTUAL_PROMPT) {
+        Copy-Item -Path Function:_OLD_VIRTUAL_PROMPT -Destination Function:prompt
+        Remove-Item -Path Function:_OLD_VIRTUAL_PROMPT
+    }
+    if (Test-Path -Path Env:_OLD_VIRTUAL_PYTHONHOME) {
+        Copy-Item -Path Env:_OLD_VIRTUAL_PYTHONHOME -Destination Env:PYTHONHOME
+        Remove-Item -Path Env:_OLD_VIRTUAL_PYTHONHOME
+    }
+    if (Test-Path -Path Env:_OLD_VIRTUAL_PATH) {
+        Copy-Item -Path Env:_OLD_VIRTUAL_PATH -Destination Env:PATH
+        Remove-Item -Path Env:_OLD_VIRTUAL_PATH
+    }
+    if (Test-Path -Path Env:VIRTUAL_ENV) {
+        Remove-Item -Path Env:VIRTUAL_ENV
+    }
+    if (Test-Path -Path Env:VIRTUAL_ENV_PROMPT) {
+        Remove-Item -Path Env:VIRTUAL_ENV_PROMPT
+    }
+    if (-not $NonDestructive) {
+        Remove-Item -Path Function:deactivate
+    }
+}
+
+# Clear out anything left from a previously activated environment
+deactivate -NonDestructive
+
+$env:VIRTUAL_ENV = {{virtual_env}}
+$env:VIRTUAL_ENV_PROMPT = {{prompt}}
+
+if (-not $env:VIRTUAL_ENV_DISABLE_PROMPT) {
+    function global:_OLD_VIRTUAL_PROMPT { "" }
+    Copy-Item -Path Function:prompt -Destination Function:_OLD_VIRTUAL_PROMPT
+
+    function global:prompt {
+        Write-Host -NoNewline -ForegroundColor Green "($env:VIRTUAL_ENV_PROMPT) "
+        _OLD_VIRTUAL_PROMPT
+    }
+}
+
+if (Test-Path -Path Env:PYTHONHOME) {
+    Copy-Item -Path Env:PYTHONHOME -Destination Env:_OLD_VIRTUAL_PYTHONHOME
+    Remove-Item -Path Env:PYTHONHOME
+}
+
+Copy-Item -Path Env:PATH -Destination Env:_OLD_VIRTUAL_PATH
+$env:PATH = (Join-Path $env:VIRTUAL_ENV "{{bin_name}}") + [System.IO.Path]::PathSeparator + $env:PATH
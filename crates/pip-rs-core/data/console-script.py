@@ -0,0 +1,17 @@
+{{shebang}}
+# -*- coding: utf-8 -*-
+# pip-rs console script template v{{template_version}}
+# pip-rs-entry-point: {{name}} = {{module}}:{{function}} [{{package}}]
+import sys
+try:
+    from {{module}} import {{function}}
+except ModuleNotFoundError as exc:
+    # Only a missing owning package gets the friendly message; import
+    # errors from inside an installed package propagate as usual
+    _module = {{module_literal}}
+    if not exc.name or not (_module == exc.name or _module.startswith(exc.name + ".")):
+        raise
+    sys.stderr.write({{message_literal}} + "\n")
+    sys.exit(1)
+if __name__ == '__main__':
+    sys.exit({{function}}())
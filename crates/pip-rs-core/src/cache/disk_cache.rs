@@ -1,17 +1,61 @@
+use super::error::CacheError;
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 use std::time::{SystemTime, Duration};
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
 
+/// Extension of the sidecar holding an entry's HTTP caching headers
+const META_EXTENSION: &str = "meta";
+
+/// HTTP caching headers stored next to a cached response
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntryMeta {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+    /// Freshness lifetime from `Cache-Control: max-age` (0 for `no-cache`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age_secs: Option<u64>,
+}
+
+impl EntryMeta {
+    /// Whether the entry can be revalidated with a conditional request
+    pub fn has_validator(&self) -> bool {
+        self.etag.is_some() || self.last_modified.is_some()
+    }
+}
+
+/// A cached response with its headers and age, however old
+#[derive(Debug, Clone)]
+pub struct CachedEntry {
+    pub body: Vec<u8>,
+    pub meta: EntryMeta,
+    pub age: Duration,
+}
+
+impl CachedEntry {
+    /// Fresh per its `max-age`; without one, entries with a validator are
+    /// always revalidated and the others live for `fallback_ttl`
+    pub fn is_fresh(&self, fallback_ttl: Duration) -> bool {
+        match self.meta.max_age_secs {
+            Some(max_age) => self.age < Duration::from_secs(max_age),
+            None if self.meta.has_validator() => false,
+            None => self.age < fallback_ttl,
+        }
+    }
+}
+
 pub struct DiskCache {
     cache_dir: PathBuf,
     ttl: Duration,
 }
 
 impl DiskCache {
-    pub fn new(cache_dir: &Path, ttl: Duration) -> Result<Self> {
-        std::fs::create_dir_all(cache_dir)?;
+    pub fn new(cache_dir: &Path, ttl: Duration) -> Result<Self, CacheError> {
+        std::fs::create_dir_all(cache_dir).map_err(|e| CacheError::io("create", cache_dir, e))?;
         Ok(Self {
             cache_dir: cache_dir.to_path_buf(),
             ttl,
@@ -26,39 +70,112 @@ impl DiskCache {
     }
 
     fn get_cache_path(&self, key: &str) -> PathBuf {
+        let path = self.entry_path(key);
+        if let Some(subdir) = path.parent() {
+            let _ = std::fs::create_dir_all(subdir);
+        }
+        path
+    }
+
+    /// File that holds (or would hold) the entry for `key`
+    pub fn entry_path(&self, key: &str) -> PathBuf {
         let hash = Self::hash_key(key);
         // Use first 2 chars as subdirectory for better filesystem performance
         let subdir = &hash[..2];
         let mut path = self.cache_dir.clone();
         path.push(subdir);
-        let _ = std::fs::create_dir_all(&path);
         path.push(&hash);
         path
     }
 
-    pub fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+    pub fn get(&self, key: &str) -> Result<Option<Vec<u8>>, CacheError> {
         let path = self.get_cache_path(key);
         if !path.exists() {
             return Ok(None);
         }
 
-        let metadata = std::fs::metadata(&path)?;
-        let modified = metadata.modified()?;
-        if SystemTime::now().duration_since(modified)? > self.ttl {
+        let read_error = |e| CacheError::io("read", &path, e);
+        let modified = std::fs::metadata(&path).and_then(|m| m.modified()).map_err(read_error)?;
+        // A timestamp in the future counts as just written
+        if SystemTime::now().duration_since(modified).unwrap_or_default() > self.ttl {
             // Cache expired
             let _ = std::fs::remove_file(&path);
+            let _ = std::fs::remove_file(Self::meta_path(&path));
             return Ok(None);
         }
 
-        Ok(Some(std::fs::read(&path)?))
+        std::fs::read(&path).map(Some).map_err(read_error)
     }
 
-    pub fn set(&self, key: &str, value: &[u8]) -> Result<()> {
+    /// Entry for `key` only if it was written within `max_age`; unlike
+    /// `get`, a stale entry is left in place for callers with a longer TTL
+    pub fn get_within(&self, key: &str, max_age: Duration) -> Option<Vec<u8>> {
+        let path = self.entry_path(key);
+        let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+        if SystemTime::now().duration_since(modified).ok()? > max_age {
+            return None;
+        }
+        std::fs::read(&path).ok()
+    }
+
+    /// Size of the entry for `key`, however old
+    pub fn entry_size(&self, key: &str) -> Option<u64> {
+        std::fs::metadata(self.entry_path(key)).ok().map(|m| m.len())
+    }
+
+    pub fn set(&self, key: &str, value: &[u8]) -> Result<(), CacheError> {
         let path = self.get_cache_path(key);
-        std::fs::write(&path, value)?;
+        std::fs::write(&path, value).map_err(|e| CacheError::io("write", &path, e))?;
+        let _ = std::fs::remove_file(Self::meta_path(&path));
         Ok(())
     }
 
+    /// Store a response together with its caching headers
+    pub fn set_with_meta(&self, key: &str, value: &[u8], meta: &EntryMeta) -> Result<(), CacheError> {
+        let path = self.get_cache_path(key);
+        std::fs::write(&path, value).map_err(|e| CacheError::io("write", &path, e))?;
+        let meta_path = Self::meta_path(&path);
+        let headers = serde_json::to_vec(meta).map_err(std::io::Error::from);
+        headers
+            .and_then(|headers| std::fs::write(&meta_path, headers))
+            .map_err(|e| CacheError::io("write", &meta_path, e))
+    }
+
+    /// Entry for `key` with its headers and age, however old; freshness
+    /// is left to the caller
+    pub fn lookup(&self, key: &str) -> Option<CachedEntry> {
+        let path = self.entry_path(key);
+        let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+        let body = std::fs::read(&path).ok()?;
+        let meta = std::fs::read(Self::meta_path(&path))
+            .ok()
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default();
+        Some(CachedEntry {
+            body,
+            meta,
+            age: SystemTime::now().duration_since(modified).unwrap_or_default(),
+        })
+    }
+
+    /// Mark the entry for `key` as just validated (a 304 response),
+    /// keeping its body and taking any caching headers the server resent
+    pub fn refresh(&self, key: &str, meta: &EntryMeta) -> Result<(), CacheError> {
+        let Some(entry) = self.lookup(key) else {
+            return Ok(());
+        };
+        let meta = EntryMeta {
+            etag: meta.etag.clone().or(entry.meta.etag),
+            last_modified: meta.last_modified.clone().or(entry.meta.last_modified),
+            max_age_secs: meta.max_age_secs.or(entry.meta.max_age_secs),
+        };
+        self.set_with_meta(key, &entry.body, &meta)
+    }
+
+    fn meta_path(entry: &Path) -> PathBuf {
+        entry.with_extension(META_EXTENSION)
+    }
+
     /// Get or fetch with async closure
     pub async fn get_or_fetch<F, Fut>(&self, key: &str, fetch: F) -> Result<Vec<u8>>
     where
@@ -75,49 +192,49 @@ impl DiskCache {
     }
 
     #[allow(dead_code)]
-    pub fn clear(&self) -> Result<()> {
-        std::fs::remove_dir_all(&self.cache_dir)?;
-        std::fs::create_dir_all(&self.cache_dir)?;
-        Ok(())
+    pub fn clear(&self) -> Result<(), CacheError> {
+        std::fs::remove_dir_all(&self.cache_dir).map_err(|e| CacheError::io("remove", &self.cache_dir, e))?;
+        std::fs::create_dir_all(&self.cache_dir).map_err(|e| CacheError::io("create", &self.cache_dir, e))
     }
 
-    /// Get cache statistics
-    #[allow(dead_code)]
+    /// Number of entries and bytes on disk, header sidecars included;
+    /// expiry isn't considered
     pub fn stats(&self) -> CacheStats {
-        let mut total_size = 0u64;
-        let mut file_count = 0u64;
-        
-        if let Ok(entries) = std::fs::read_dir(&self.cache_dir) {
-            for entry in entries.flatten() {
-                if let Ok(meta) = entry.metadata() {
-                    if meta.is_file() {
-                        total_size += meta.len();
-                        file_count += 1;
-                    } else if meta.is_dir() {
-                        // Count files in subdirectory
-                        if let Ok(subentries) = std::fs::read_dir(entry.path()) {
-                            for subentry in subentries.flatten() {
-                                if let Ok(submeta) = subentry.metadata() {
-                                    if submeta.is_file() {
-                                        total_size += submeta.len();
-                                        file_count += 1;
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
+        let mut stats = CacheStats::default();
+        for file in self.files() {
+            let Ok(meta) = file.metadata() else { continue };
+            if !meta.is_file() {
+                continue;
+            }
+            stats.total_size += meta.len();
+            if file.path().extension().is_none_or(|ext| ext != META_EXTENSION) {
+                stats.entries += 1;
             }
         }
-        
-        CacheStats { total_size, file_count }
+        stats
+    }
+
+    /// Every stored body, expired or not, in no particular order
+    pub fn bodies(&self) -> impl Iterator<Item = Vec<u8>> {
+        self.files()
+            .map(|file| file.path())
+            .filter(|path| path.is_file() && path.extension().is_none_or(|ext| ext != META_EXTENSION))
+            .filter_map(|path| std::fs::read(path).ok())
+    }
+
+    fn files(&self) -> impl Iterator<Item = std::fs::DirEntry> {
+        std::fs::read_dir(&self.cache_dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .flat_map(|subdir| std::fs::read_dir(subdir.path()).into_iter().flatten().flatten())
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
 pub struct CacheStats {
+    pub entries: u64,
     pub total_size: u64,
-    pub file_count: u64,
 }
 
 #[cfg(test)]
@@ -145,6 +262,17 @@ mod tests {
         assert_eq!(result, None);
     }
 
+    #[test]
+    fn test_errors_name_the_file() {
+        let dir = tempdir().unwrap();
+        let blocker = dir.path().join("cache");
+        std::fs::write(&blocker, b"").unwrap();
+        let Err(CacheError::Io { path, operation, .. }) = DiskCache::new(&blocker, Duration::from_secs(3600)) else {
+            panic!("a file where the cache directory goes");
+        };
+        assert_eq!((path, operation), (blocker, "create"));
+    }
+
     #[test]
     fn test_hash_key() {
         let hash1 = DiskCache::hash_key("https://pypi.org/pypi/requests/json");
@@ -154,4 +282,53 @@ mod tests {
         assert_eq!(hash1, hash2);
         assert_ne!(hash1, hash3);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_entry_meta_and_freshness() {
+        let dir = tempdir().unwrap();
+        let cache = DiskCache::new(dir.path(), Duration::from_secs(3600)).unwrap();
+        let ttl = Duration::from_secs(600);
+
+        cache.set("plain", b"body").unwrap();
+        let entry = cache.lookup("plain").unwrap();
+        assert_eq!(entry.meta, EntryMeta::default());
+        assert!(entry.is_fresh(ttl));
+        assert!(!entry.is_fresh(Duration::ZERO));
+
+        // With a validator and no max-age, every use revalidates
+        let meta = EntryMeta { etag: Some("\"abc\"".to_string()), ..Default::default() };
+        cache.set_with_meta("validated", b"v1", &meta).unwrap();
+        assert!(!cache.lookup("validated").unwrap().is_fresh(ttl));
+
+        // A 304 keeps the body and adds the headers it carried
+        let revalidated = EntryMeta { max_age_secs: Some(900), ..Default::default() };
+        cache.refresh("validated", &revalidated).unwrap();
+        let entry = cache.lookup("validated").unwrap();
+        assert_eq!(entry.body, b"v1");
+        assert_eq!(entry.meta.etag.as_deref(), Some("\"abc\""));
+        assert!(entry.is_fresh(ttl));
+
+        // A plain write drops stale headers
+        cache.set("validated", b"v2").unwrap();
+        assert_eq!(cache.lookup("validated").unwrap().meta, EntryMeta::default());
+    }
+
+    #[test]
+    fn test_stats_and_bodies_skip_sidecars() {
+        let dir = tempdir().unwrap();
+        let cache = DiskCache::new(dir.path(), Duration::from_secs(3600)).unwrap();
+        assert_eq!(cache.stats(), CacheStats::default());
+
+        cache.set("plain", b"1234").unwrap();
+        cache.set_with_meta("validated", b"12", &EntryMeta { etag: Some("\"a\"".to_string()), ..Default::default() })
+            .unwrap();
+        let stats = cache.stats();
+        assert_eq!(stats.entries, 2);
+        let sidecar = std::fs::metadata(DiskCache::meta_path(&cache.entry_path("validated"))).unwrap().len();
+        assert_eq!(stats.total_size, 6 + sidecar);
+
+        let mut bodies: Vec<Vec<u8>> = cache.bodies().collect();
+        bodies.sort();
+        assert_eq!(bodies, [b"12".to_vec(), b"1234".to_vec()]);
+    }
+}
@@ -0,0 +1,49 @@
+/// Why the cache couldn't be read or written
+///
+/// Most cache failures only cost a refetch and are logged where they happen;
+/// [`DiskCache`](super::DiskCache) returns a [`CacheError`] so the callers
+/// that do stop on one can report the file and what was being done to it.
+use super::layout::UnsupportedLayoutError;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+pub enum CacheError {
+    /// Creating, reading, writing or removing a cache file failed
+    Io {
+        path: PathBuf,
+        operation: &'static str,
+        source: std::io::Error,
+    },
+    /// The cache was written by a newer pip-rs
+    UnsupportedLayout(UnsupportedLayoutError),
+}
+
+impl CacheError {
+    pub fn io(operation: &'static str, path: &Path, source: std::io::Error) -> Self {
+        CacheError::Io {
+            path: path.to_path_buf(),
+            operation,
+            source,
+        }
+    }
+}
+
+impl fmt::Display for CacheError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CacheError::Io { path, operation, source } => {
+                write!(f, "cannot {} {}: {}", operation, path.display(), source)
+            }
+            CacheError::UnsupportedLayout(layout) => write!(f, "{}", layout),
+        }
+    }
+}
+
+impl std::error::Error for CacheError {}
+
+impl From<UnsupportedLayoutError> for CacheError {
+    fn from(layout: UnsupportedLayoutError) -> Self {
+        CacheError::UnsupportedLayout(layout)
+    }
+}
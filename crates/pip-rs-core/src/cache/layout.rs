@@ -0,0 +1,320 @@
+/// On-disk cache layout versioning and migration
+///
+/// The cache root carries a `layout-version` file. When an older layout is
+/// found, the registered migration steps are run in order; a newer (unknown)
+/// layout is refused rather than misinterpreted. Layouts:
+///
+/// - v1: HTTP responses hashed directly under the root (`<root>/ab/<hash>`),
+///   package metadata under `<root>/packages/`, no version file
+/// - v2: HTTP responses moved under `<root>/http/`, leaving room for other
+///   namespaces (wheels, indexes) next to it
+use anyhow::{Result, anyhow};
+use once_cell::sync::OnceCell;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Layout version written by this build
+pub const CURRENT_LAYOUT_VERSION: u32 = 2;
+
+/// Name of the version file at the cache root
+pub const LAYOUT_VERSION_FILE: &str = "layout-version";
+
+/// Subdirectory holding cached HTTP responses
+pub const HTTP_DIR: &str = "http";
+
+/// Subdirectory holding cached package metadata
+pub const PACKAGES_DIR: &str = "packages";
+
+/// Subdirectory holding in-progress (`.part`) downloads
+pub const DOWNLOADS_DIR: &str = "downloads";
+
+/// Subdirectory holding per-environment snapshots (installed sizes)
+pub const SNAPSHOTS_DIR: &str = "snapshots";
+
+/// Subdirectory holding wheels kept for reuse (`{name}-{version}-*.whl`)
+pub const WHEELS_DIR: &str = "wheels";
+
+/// Subdirectory holding index project lists (`pip search`)
+pub const INDEXES_DIR: &str = "indexes";
+
+/// Recent warnings and errors, one JSON object per line
+pub const JOURNAL_FILE: &str = "journal.jsonl";
+
+/// Caches with more entries than this get a progress message while migrating
+const LARGE_CACHE_ENTRIES: usize = 1000;
+
+/// The cache was written by a newer pip-rs with a layout we don't understand
+#[derive(Debug)]
+pub struct UnsupportedLayoutError {
+    pub root: PathBuf,
+    pub found: u32,
+}
+
+impl fmt::Display for UnsupportedLayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Cache at {} uses layout version {}, newer than this pip-rs supports ({}). \
+             Upgrade pip-rs or point PIP_CACHE_DIR at a separate directory",
+            self.root.display(),
+            self.found,
+            CURRENT_LAYOUT_VERSION
+        )
+    }
+}
+
+impl std::error::Error for UnsupportedLayoutError {}
+
+/// A registered migration from `from` to `from + 1`
+struct MigrationStep {
+    from: u32,
+    description: &'static str,
+    run: fn(&Path) -> Result<usize>,
+}
+
+fn migration_steps() -> Vec<MigrationStep> {
+    vec![MigrationStep {
+        from: 1,
+        description: "move HTTP responses into http/",
+        run: migrate_v1_to_v2,
+    }]
+}
+
+/// v1 -> v2: move the hashed `ab/` shard directories under `http/`
+fn migrate_v1_to_v2(root: &Path) -> Result<usize> {
+    let http = root.join(HTTP_DIR);
+    fs::create_dir_all(&http)?;
+    let mut moved = 0;
+
+    for entry in fs::read_dir(root)?.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !entry.path().is_dir() || !is_shard_dir(&name) {
+            continue;
+        }
+
+        let dest = http.join(&name);
+        if !dest.exists() {
+            fs::rename(entry.path(), &dest)?;
+            moved += count_files(&dest);
+            continue;
+        }
+
+        // Shard already exists in the new location: merge file by file
+        for file in fs::read_dir(entry.path())?.flatten() {
+            let target = dest.join(file.file_name());
+            if target.exists() {
+                fs::remove_file(file.path())?;
+            } else {
+                fs::rename(file.path(), target)?;
+                moved += 1;
+            }
+        }
+        fs::remove_dir(entry.path())?;
+    }
+
+    Ok(moved)
+}
+
+/// Two lowercase hex characters, as produced by `DiskCache` sharding
+fn is_shard_dir(name: &str) -> bool {
+    name.len() == 2 && name.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn count_files(dir: &Path) -> usize {
+    walkdir::WalkDir::new(dir)
+        .into_iter()
+        .flatten()
+        .filter(|e| e.file_type().is_file())
+        .count()
+}
+
+/// Read the layout version of a cache root.
+///
+/// A missing version file means either a fresh (empty) cache, which is
+/// treated as current, or a cache written before versioning existed (v1).
+pub fn read_layout_version(root: &Path) -> Result<u32> {
+    let version_file = root.join(LAYOUT_VERSION_FILE);
+    if version_file.exists() {
+        let content = fs::read_to_string(&version_file)?;
+        return content
+            .trim()
+            .parse::<u32>()
+            .map_err(|_| anyhow!("Invalid cache layout version file {}", version_file.display()));
+    }
+
+    let has_entries = fs::read_dir(root)
+        .map(|mut entries| entries.next().is_some())
+        .unwrap_or(false);
+    Ok(if has_entries { 1 } else { CURRENT_LAYOUT_VERSION })
+}
+
+fn write_layout_version(root: &Path, version: u32) -> Result<()> {
+    fs::write(root.join(LAYOUT_VERSION_FILE), format!("{}\n", version))?;
+    Ok(())
+}
+
+/// Directory used instead of an old cache when migration is disabled
+pub fn fresh_root_for(root: &Path) -> PathBuf {
+    let name = root
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "pip-rs".to_string());
+    root.with_file_name(format!("{}-v{}", name, CURRENT_LAYOUT_VERSION))
+}
+
+/// Make sure `root` uses the current layout, migrating it if allowed.
+///
+/// Returns the cache root to use: `root` itself, or a fresh sibling
+/// directory when the cache is outdated and migration is disabled.
+pub fn ensure_layout(root: &Path, allow_migration: bool) -> Result<PathBuf> {
+    fs::create_dir_all(root)?;
+    let mut version = read_layout_version(root)?;
+
+    if version > CURRENT_LAYOUT_VERSION {
+        return Err(UnsupportedLayoutError {
+            root: root.to_path_buf(),
+            found: version,
+        }
+        .into());
+    }
+
+    if version < CURRENT_LAYOUT_VERSION && !allow_migration {
+        let fresh = fresh_root_for(root);
+        tracing::debug!(
+            "Cache migration disabled; using {} instead of {}",
+            fresh.display(),
+            root.display()
+        );
+        return ensure_layout(&fresh, true);
+    }
+
+    if version < CURRENT_LAYOUT_VERSION {
+        let entries = count_files(root);
+        if entries > LARGE_CACHE_ENTRIES {
+            eprintln!(
+                "Migrating cache at {} ({} entries) to layout version {}...",
+                root.display(),
+                entries,
+                CURRENT_LAYOUT_VERSION
+            );
+        }
+    }
+
+    for step in migration_steps() {
+        if step.from != version {
+            continue;
+        }
+        let moved = (step.run)(root)
+            .map_err(|e| anyhow!("Cache migration v{} ({}) failed: {}", step.from, step.description, e))?;
+        tracing::debug!("Cache migration v{}: {} ({} entries)", step.from, step.description, moved);
+        version = step.from + 1;
+        // Record progress after every step so an interrupted run resumes
+        write_layout_version(root, version)?;
+    }
+
+    if !root.join(LAYOUT_VERSION_FILE).exists() {
+        write_layout_version(root, CURRENT_LAYOUT_VERSION)?;
+    }
+
+    Ok(root.to_path_buf())
+}
+
+/// Configured cache root: PIP_CACHE_DIR, or the user cache directory
+pub fn configured_root() -> Option<PathBuf> {
+    if let Ok(cache_dir) = std::env::var("PIP_CACHE_DIR") {
+        return Some(PathBuf::from(cache_dir));
+    }
+    dirs::cache_dir().map(|d| d.join("pip-rs"))
+}
+
+static PREPARED_ROOT: OnceCell<PathBuf> = OnceCell::new();
+
+/// Check (and migrate) the configured cache root once per process.
+///
+/// Without `allow_migration` (`--no-cache-migration`) an old layout is left
+/// alone and a fresh directory used instead. The first call decides.
+pub fn prepare_cache_root(allow_migration: bool) -> Result<PathBuf> {
+    PREPARED_ROOT
+        .get_or_try_init(|| {
+            let root = configured_root().ok_or_else(|| anyhow!("No cache directory available"))?;
+            ensure_layout(&root, allow_migration)
+        })
+        .cloned()
+}
+
+/// Prepared cache root, or None when caching is unavailable
+pub fn cache_root() -> Option<PathBuf> {
+    match prepare_cache_root(true) {
+        Ok(root) => Some(root),
+        Err(e) => {
+            tracing::warn!("Disk cache disabled: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::DiskCache;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    const TTL: Duration = Duration::from_secs(3600);
+
+    #[test]
+    fn test_migrates_v1_layout() -> Result<()> {
+        let temp = TempDir::new()?;
+        let root = temp.path().join("pip-rs");
+
+        // v1: HTTP cache directly at the root, package metadata under packages/
+        DiskCache::new(&root, TTL)?.set("https://pypi.org/pypi/requests/json", b"{}")?;
+        DiskCache::new(&root.join(PACKAGES_DIR), TTL)?.set("requests-latest", b"pkg")?;
+        assert_eq!(read_layout_version(&root)?, 1);
+
+        let migrated = ensure_layout(&root, true)?;
+        assert_eq!(migrated, root);
+        assert_eq!(read_layout_version(&root)?, CURRENT_LAYOUT_VERSION);
+        assert_eq!(
+            fs::read_to_string(root.join(LAYOUT_VERSION_FILE))?.trim(),
+            CURRENT_LAYOUT_VERSION.to_string()
+        );
+
+        let http = DiskCache::new(&root.join(HTTP_DIR), TTL)?;
+        assert_eq!(http.get("https://pypi.org/pypi/requests/json")?, Some(b"{}".to_vec()));
+        let packages = DiskCache::new(&root.join(PACKAGES_DIR), TTL)?;
+        assert_eq!(packages.get("requests-latest")?, Some(b"pkg".to_vec()));
+
+        // Running again is a no-op
+        assert_eq!(ensure_layout(&root, true)?, root);
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_migration_uses_fresh_directory() -> Result<()> {
+        let temp = TempDir::new()?;
+        let root = temp.path().join("pip-rs");
+        DiskCache::new(&root, TTL)?.set("key", b"value")?;
+
+        let fresh = ensure_layout(&root, false)?;
+        assert_eq!(fresh, temp.path().join(format!("pip-rs-v{}", CURRENT_LAYOUT_VERSION)));
+        assert_eq!(read_layout_version(&fresh)?, CURRENT_LAYOUT_VERSION);
+        // The old cache is left untouched
+        assert_eq!(read_layout_version(&root)?, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_refuses_future_layout() -> Result<()> {
+        let temp = TempDir::new()?;
+        write_layout_version(temp.path(), CURRENT_LAYOUT_VERSION + 1)?;
+
+        let err = ensure_layout(temp.path(), true).unwrap_err();
+        assert!(err.downcast_ref::<UnsupportedLayoutError>().is_some());
+        let err = err.to_string();
+        assert!(err.contains("newer than this pip-rs supports"));
+        assert!(err.contains("PIP_CACHE_DIR"));
+        Ok(())
+    }
+}
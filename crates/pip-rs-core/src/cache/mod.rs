@@ -1,4 +1,9 @@
 pub mod disk_cache;
+pub mod error;
+pub mod layout;
 pub mod package_cache;
+pub mod wheel_cache;
 
 pub use disk_cache::DiskCache;
+pub use error::CacheError;
+pub use wheel_cache::WheelCache;
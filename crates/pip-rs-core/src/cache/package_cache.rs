@@ -1,6 +1,8 @@
 use crate::models::Package;
 use crate::cache::disk_cache::DiskCache;
-use anyhow::Result;
+use crate::cache::layout;
+use anyhow::{Result, anyhow};
+use std::collections::BTreeSet;
 use std::time::Duration;
 
 pub struct PackageCache {
@@ -9,7 +11,9 @@ pub struct PackageCache {
 
 impl PackageCache {
     pub fn new() -> Result<Self> {
-        let cache_dir = dirs::cache_dir().unwrap().join("pip-rs").join("packages");
+        let cache_dir = layout::cache_root()
+            .ok_or_else(|| anyhow!("No cache directory available"))?
+            .join(layout::PACKAGES_DIR);
         Self::new_custom(cache_dir)
     }
 
@@ -18,9 +22,16 @@ impl PackageCache {
         Ok(Self { disk_cache })
     }
 
+    /// The cached metadata, if younger than a day; in offline mode however
+    /// old it is
     pub fn get(&self, package_name: &str, version: &str) -> Result<Option<Package>> {
         let key = format!("{}-{}", package_name, version);
-        if let Some(data) = self.disk_cache.get(&key)? {
+        let cached = if crate::network::is_offline() {
+            self.disk_cache.get_within(&key, Duration::MAX)
+        } else {
+            self.disk_cache.get(&key)?
+        };
+        if let Some(data) = cached {
             let package: Package = serde_json::from_slice(&data)?;
             return Ok(Some(package));
         }
@@ -33,6 +44,21 @@ impl PackageCache {
         self.disk_cache.set(&key, &data)?;
         Ok(())
     }
+
+    /// Names of every project with cached metadata, however old
+    pub fn names(&self) -> BTreeSet<String> {
+        self.disk_cache
+            .bodies()
+            .filter_map(|data| serde_json::from_slice::<Package>(&data).ok())
+            .map(|package| package.name)
+            .collect()
+    }
+
+    /// Store `package` as the answer to `get(name, "latest")`
+    pub fn set_latest(&self, package: &Package) -> Result<()> {
+        let key = format!("{}-latest", package.name);
+        Ok(self.disk_cache.set(&key, &serde_json::to_vec(package)?)?)
+    }
 }
 
 impl Default for PackageCache {
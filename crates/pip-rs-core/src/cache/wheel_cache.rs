@@ -0,0 +1,260 @@
+/// Downloaded wheels kept for reuse across installs
+///
+/// Each entry is a directory `wheels/<key>/` holding the wheel under its
+/// published filename plus a `sha256` file with the digest of what was
+/// stored. The key hashes the artifact URL (fragment dropped) together with
+/// the sha256 the index published, so a re-uploaded file under the same URL
+/// gets a new entry. A lookup re-hashes the wheel; an entry that no longer
+/// matches is evicted and the caller downloads again.
+///
+/// Entries are assembled in a temporary directory and renamed into place,
+/// so a concurrent pip-rs sees either a complete entry or none.
+use super::layout;
+use crate::network::artifact;
+use crate::utils::hash;
+use anyhow::{Result, anyhow};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// File in each entry holding the stored wheel's sha256
+const DIGEST_FILE: &str = "sha256";
+
+/// Prefix of entries still being written
+const TEMP_PREFIX: &str = ".tmp-";
+
+/// One cached wheel, as listed by `pip cache list`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedWheel {
+    pub filename: String,
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+pub struct WheelCache {
+    root: PathBuf,
+}
+
+impl WheelCache {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// The wheel cache under the configured cache root
+    pub fn open() -> Option<Self> {
+        layout::cache_root().map(|root| Self::new(root.join(layout::WHEELS_DIR)))
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Entry key for an artifact URL and its published sha256
+    pub fn key(url: &str, sha256: Option<&str>) -> String {
+        let url = url.split('#').next().unwrap_or(url);
+        let mut hasher = Sha256::new();
+        hasher.update(url.as_bytes());
+        hasher.update(b"\n");
+        hasher.update(sha256.unwrap_or_default().to_lowercase().as_bytes());
+        format!("{:x}", hasher.finalize())
+    }
+
+    /// The cached wheel for `url`, if present and intact. An entry whose
+    /// contents don't hash to what was stored (or to `sha256`) is removed.
+    pub fn get(&self, url: &str, sha256: Option<&str>) -> Option<Vec<u8>> {
+        fs::read(self.get_path(url, sha256)?).ok()
+    }
+
+    /// Like [`get`](Self::get), returning where the wheel is instead of
+    /// reading it
+    pub fn get_path(&self, url: &str, sha256: Option<&str>) -> Option<PathBuf> {
+        let entry = self.root.join(Self::key(url, sha256));
+        let wheel = wheel_in(&entry)?;
+        let actual = hash::digest_file(&wheel, "sha256").ok()?;
+        let recorded = fs::read_to_string(entry.join(DIGEST_FILE)).unwrap_or_default();
+        let expected = sha256.map(str::to_lowercase);
+
+        if recorded.trim() != actual || expected.is_some_and(|expected| expected != actual) {
+            tracing::warn!("Cached wheel {} is corrupt; downloading it again", wheel.display());
+            let _ = fs::remove_dir_all(&entry);
+            return None;
+        }
+        tracing::debug!("Using cached wheel {}", wheel.display());
+        Some(wheel)
+    }
+
+    /// Store `data` as the wheel downloaded from `url`
+    pub fn put(&self, url: &str, sha256: Option<&str>, data: &[u8]) -> Result<PathBuf> {
+        let digest = format!("{:x}", Sha256::digest(data));
+        self.store(url, sha256, &digest, |path| fs::write(path, data))
+    }
+
+    /// Store a copy of the wheel downloaded from `url` to `path`
+    pub fn put_file(&self, url: &str, sha256: Option<&str>, path: &Path) -> Result<PathBuf> {
+        let digest = hash::digest_file(path, "sha256")?;
+        self.store(url, sha256, &digest, |dest| fs::copy(path, dest).map(drop))
+    }
+
+    /// Add an entry whose wheel `write` creates and hashes to `digest`
+    fn store(
+        &self,
+        url: &str,
+        sha256: Option<&str>,
+        digest: &str,
+        write: impl FnOnce(&Path) -> std::io::Result<()>,
+    ) -> Result<PathBuf> {
+        let filename = artifact::filename(url)
+            .filter(|name| name.ends_with(".whl"))
+            .ok_or_else(|| anyhow!("Not a wheel URL: {}", url))?;
+        let key = Self::key(url, sha256);
+        let entry = self.root.join(&key);
+        if entry.exists() {
+            return Ok(entry.join(filename));
+        }
+
+        fs::create_dir_all(&self.root)?;
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or_default();
+        let temp = self.root.join(format!("{}{}-{}-{}", TEMP_PREFIX, &key[..16], std::process::id(), nanos));
+        fs::create_dir_all(&temp)?;
+        let written =
+            write(&temp.join(filename)).and_then(|()| fs::write(temp.join(DIGEST_FILE), format!("{}\n", digest)));
+        if let Err(e) = written {
+            let _ = fs::remove_dir_all(&temp);
+            return Err(e.into());
+        }
+
+        if let Err(e) = fs::rename(&temp, &entry) {
+            let _ = fs::remove_dir_all(&temp);
+            // Another process stored the same wheel first
+            if !entry.exists() {
+                return Err(e.into());
+            }
+        }
+        Ok(entry.join(filename))
+    }
+
+    /// Every complete entry, sorted by filename
+    pub fn list(&self) -> Vec<CachedWheel> {
+        let Ok(entries) = fs::read_dir(&self.root) else {
+            return Vec::new();
+        };
+        let mut wheels: Vec<CachedWheel> = entries
+            .flatten()
+            .filter(|e| !e.file_name().to_string_lossy().starts_with(TEMP_PREFIX))
+            .filter_map(|e| wheel_in(&e.path()))
+            .map(|path| CachedWheel {
+                filename: path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
+                size: fs::metadata(&path).map(|m| m.len()).unwrap_or(0),
+                path,
+            })
+            .collect();
+        wheels.sort_by(|a, b| a.filename.cmp(&b.filename).then_with(|| a.path.cmp(&b.path)));
+        wheels
+    }
+
+    /// Entries whose filename matches `pattern`, the way `pip cache list
+    /// <pattern>` matches: a bare name matches `name-*.whl`
+    pub fn matching(&self, pattern: &str) -> Vec<CachedWheel> {
+        let pattern = wheel_pattern(pattern);
+        self.list()
+            .into_iter()
+            .filter(|wheel| pattern.matches_with(&wheel.filename, glob_options()))
+            .collect()
+    }
+
+    /// Remove the entries matching `pattern`, returning how many were removed
+    pub fn remove(&self, pattern: &str) -> Result<usize> {
+        let matching = self.matching(pattern);
+        for wheel in &matching {
+            if let Some(entry) = wheel.path.parent() {
+                fs::remove_dir_all(entry)?;
+            }
+        }
+        Ok(matching.len())
+    }
+}
+
+/// The `.whl` file in an entry directory
+fn wheel_in(entry: &Path) -> Option<PathBuf> {
+    fs::read_dir(entry)
+        .ok()?
+        .flatten()
+        .map(|e| e.path())
+        .find(|p| p.extension().is_some_and(|ext| ext == "whl"))
+}
+
+/// Glob for a `pip cache` pattern: names are normalized to their wheel
+/// form (`_` for `-`), and a bare name is extended to `name-*.whl`
+fn wheel_pattern(pattern: &str) -> glob::Pattern {
+    let pattern = pattern.to_lowercase();
+    let glob = match pattern.split_once('-') {
+        Some(_) => format!("{}*.whl", pattern.trim_end_matches(".whl").trim_end_matches('*')),
+        None => format!("{}-*.whl", pattern.replace('.', "_")),
+    };
+    glob::Pattern::new(&glob).unwrap_or_else(|_| glob::Pattern::new(&glob::Pattern::escape(&glob)).unwrap())
+}
+
+fn glob_options() -> glob::MatchOptions {
+    glob::MatchOptions {
+        case_sensitive: false,
+        require_literal_separator: true,
+        require_literal_leading_dot: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const URL: &str = "https://files.example/packages/requests-2.31.0-py3-none-any.whl";
+
+    #[test]
+    fn test_round_trip_and_corrupt_entry_is_evicted() -> Result<()> {
+        let temp = TempDir::new()?;
+        let cache = WheelCache::new(temp.path());
+        let data = b"wheel bytes".to_vec();
+        let sha256 = format!("{:x}", Sha256::digest(&data));
+
+        let path = cache.put(&format!("{}#sha256={}", URL, sha256), Some(&sha256), &data)?;
+        assert_eq!(path.file_name().unwrap(), "requests-2.31.0-py3-none-any.whl");
+        assert_eq!(cache.get(URL, Some(&sha256)), Some(data.clone()));
+        // Keyed by the published hash too
+        assert_eq!(cache.get(URL, None), None);
+        // No temporary directories are left behind
+        assert_eq!(fs::read_dir(temp.path())?.count(), 1);
+
+        fs::write(&path, b"truncated")?;
+        assert_eq!(cache.get(URL, Some(&sha256)), None);
+        assert!(cache.list().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_and_remove_by_pattern() -> Result<()> {
+        let temp = TempDir::new()?;
+        let cache = WheelCache::new(temp.path());
+        cache.put(URL, None, b"a")?;
+        cache.put("https://files.example/requests_toolbelt-1.0.0-py2.py3-none-any.whl", None, b"b")?;
+        cache.put("https://files.example/idna-3.6-py3-none-any.whl", None, b"c")?;
+        assert!(cache.put("https://files.example/idna-3.6.tar.gz", None, b"d").is_err());
+
+        let names: Vec<String> = cache.list().into_iter().map(|w| w.filename).collect();
+        assert_eq!(
+            names,
+            [
+                "idna-3.6-py3-none-any.whl",
+                "requests-2.31.0-py3-none-any.whl",
+                "requests_toolbelt-1.0.0-py2.py3-none-any.whl"
+            ]
+        );
+        assert_eq!(cache.matching("Requests").len(), 1);
+        assert_eq!(cache.matching("requests-2.31*").len(), 1);
+
+        assert_eq!(cache.remove("requests")?, 1);
+        assert_eq!(cache.remove("nothing")?, 0);
+        assert_eq!(cache.list().len(), 2);
+        Ok(())
+    }
+}
@@ -1,38 +1,165 @@
 /// Configuration file parsing and management
+///
+/// Settings are layered, later layers winning: built-in defaults, pip.conf
+/// files, directory-local `.pip-rs.toml` files ([`super::local`]), then
+/// environment variables.
+use super::local::{DEFAULTS_SECTION, LocalConfig};
+use crate::utils::text_encoding;
 use anyhow::Result;
+use once_cell::sync::OnceCell;
+use serde::Serialize;
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 use std::fs;
 
+/// A config file, or the environment, and the settings it supplied
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConfigSource {
+    /// The file, or `environment` for `PIP_*` variables
+    pub origin: String,
+    pub settings: Vec<ConfigSetting>,
+}
+
+/// One setting as written; keys outside `[global]` as `section.key`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConfigSetting {
+    pub key: String,
+    pub value: String,
+}
+
+impl ConfigSetting {
+    fn new(key: impl Into<String>, value: impl Into<String>) -> Self {
+        Self { key: key.into(), value: value.into() }
+    }
+}
+
+/// An `[index "name"]` section: credentials (and a name) for one of the
+/// configured indexes, matched by `url`, or by name when `url` is absent
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NamedIndex {
+    pub name: String,
+    pub url: Option<String>,
+    pub token: Option<String>,
+}
+
+/// The configuration this process runs with; see [`Config::global`]
+static GLOBAL: OnceCell<Config> = OnceCell::new();
+
 #[derive(Debug, Clone)]
 pub struct Config {
     index_url: String,
     extra_index_urls: Vec<String>,
     timeout: u64,
     retries: u32,
+    retry_delay_ms: Option<u64>,
+    proxy: Option<String>,
+    offline: Option<bool>,
     cache_dir: PathBuf,
     user_agent: String,
     trusted_hosts: Vec<String>,
+    find_links: Vec<String>,
+    no_index: Option<bool>,
+    override_file: Option<PathBuf>,
+    index_policy: Option<PathBuf>,
+    require_virtualenv: Option<bool>,
+    break_system_packages: Option<bool>,
+    part_max_age_hours: Option<u64>,
+    cache_ttl_secs: Option<u64>,
+    lock_timeout_secs: Option<u64>,
+    max_rps: Option<f64>,
+    import_map: Option<PathBuf>,
+    jobs: Option<usize>,
+    pager: Option<String>,
+    data_dir: Option<PathBuf>,
+    datasets_url: Option<String>,
+    warn_deprecated: Option<bool>,
+    simple_api_only: Option<bool>,
+    trusted_local_configs: Vec<PathBuf>,
+    named_indexes: Vec<NamedIndex>,
+    sources: Vec<ConfigSource>,
 }
 
 impl Config {
     pub fn new() -> Self {
-        let mut config = Self {
+        Self::layered(true)
+    }
+
+    /// Like [`Config::new`] but without `.pip-rs.toml` files
+    /// (`--ignore-local-config`)
+    pub fn without_local() -> Self {
+        Self::layered(false)
+    }
+
+    fn layered(with_local: bool) -> Self {
+        let mut config = Self::defaults();
+
+        // Load from pip.conf files
+        config.load_from_standard_locations();
+
+        // Directory-local defaults; a broken file is reported by the CLI
+        // before any command runs, so it is only skipped here
+        if with_local
+            && let Ok(cwd) = std::env::current_dir()
+            && let Ok(local) = LocalConfig::load(&cwd, &config.trusted_local_configs)
+        {
+            config.apply_local(&local);
+        }
+
+        // Environment variables override every file
+        config.load_from_env();
+
+        config
+    }
+
+    /// The configuration this process runs with: the one the CLI installed
+    /// with [`init_global`](Self::init_global), command-line flags
+    /// included, else [`Config::new`] as of first use
+    pub fn global() -> &'static Config {
+        GLOBAL.get_or_init(Config::new)
+    }
+
+    /// Make this the configuration [`global`](Self::global) returns. Call
+    /// once before any command runs; it has no effect once read.
+    pub fn init_global(self) {
+        if GLOBAL.set(self).is_err() {
+            tracing::debug!("Configuration already in use; command-line settings not applied");
+        }
+    }
+
+    /// Built-in defaults, before any environment or file configuration
+    fn defaults() -> Self {
+        Self {
             index_url: "https://pypi.org/simple/".to_string(),
             extra_index_urls: Vec::new(),
-            timeout: 15,
-            retries: 3,
+            timeout: crate::network::client::DEFAULT_TIMEOUT.as_secs(),
+            retries: crate::network::client::DEFAULT_RETRIES,
+            retry_delay_ms: None,
+            proxy: None,
+            offline: None,
             cache_dir: PathBuf::from(".pip-cache"),
             user_agent: "pip-rs/1.0.0".to_string(),
             trusted_hosts: Vec::new(),
-        };
-        
-        // Load from environment variables
-        config.load_from_env();
-        
-        // Load from pip.conf files
-        config.load_from_standard_locations();
-        
-        config
+            find_links: Vec::new(),
+            no_index: None,
+            override_file: None,
+            index_policy: None,
+            require_virtualenv: None,
+            break_system_packages: None,
+            part_max_age_hours: None,
+            cache_ttl_secs: None,
+            lock_timeout_secs: None,
+            max_rps: None,
+            import_map: None,
+            jobs: None,
+            pager: None,
+            data_dir: None,
+            datasets_url: None,
+            warn_deprecated: None,
+            simple_api_only: None,
+            trusted_local_configs: Vec::new(),
+            named_indexes: Vec::new(),
+            sources: Vec::new(),
+        }
     }
 
     /// Load configuration from environment variables
@@ -62,45 +189,164 @@ impl Config {
             }
         }
         
+        // PIP_FIND_LINKS (whitespace-separated, like pip)
+        if let Ok(links) = std::env::var("PIP_FIND_LINKS") {
+            self.find_links.extend(links.split_whitespace().map(String::from));
+        }
+
+        // PIP_NO_INDEX
+        if let Ok(value) = std::env::var("PIP_NO_INDEX") {
+            self.no_index = parse_bool(&value);
+        }
+
         // PIP_CACHE_DIR
         if let Ok(cache_dir) = std::env::var("PIP_CACHE_DIR") {
             self.cache_dir = PathBuf::from(cache_dir);
         }
+
+        // PIP_OVERRIDE_FILE
+        if let Ok(path) = std::env::var("PIP_OVERRIDE_FILE") {
+            self.override_file = Some(PathBuf::from(path));
+        }
+
+        // PIP_INDEX_POLICY
+        if let Ok(path) = std::env::var("PIP_INDEX_POLICY") {
+            self.index_policy = Some(PathBuf::from(path));
+        }
+
+        // PIP_REQUIRE_VIRTUALENV (takes precedence over config files)
+        if let Ok(value) = std::env::var("PIP_REQUIRE_VIRTUALENV") {
+            self.require_virtualenv = parse_bool(&value);
+        }
+
+        // PIP_BREAK_SYSTEM_PACKAGES (install into externally managed environments)
+        if let Ok(value) = std::env::var("PIP_BREAK_SYSTEM_PACKAGES") {
+            self.break_system_packages = parse_bool(&value);
+        }
+
+        // PIP_IMPORT_MAP (extra import name -> distribution mappings)
+        if let Ok(path) = std::env::var("PIP_IMPORT_MAP") {
+            self.import_map = Some(PathBuf::from(path));
+        }
+
+        // PIP_PART_MAX_AGE (hours before partial downloads count as orphaned)
+        if let Ok(value) = std::env::var("PIP_PART_MAX_AGE") {
+            self.part_max_age_hours = value.trim().parse().ok();
+        }
+
+        // PIP_CACHE_TTL (seconds cached responses without validators stay fresh)
+        if let Ok(value) = std::env::var("PIP_CACHE_TTL") {
+            self.cache_ttl_secs = value.trim().parse().ok();
+        }
+
+        // PIP_TIMEOUT (seconds a request may take)
+        if let Ok(value) = std::env::var("PIP_TIMEOUT")
+            && let Ok(timeout) = value.trim().parse()
+        {
+            self.timeout = timeout;
+        }
+
+        // PIP_RETRIES (further attempts after a failed request)
+        if let Ok(value) = std::env::var("PIP_RETRIES")
+            && let Ok(retries) = value.trim().parse()
+        {
+            self.retries = retries;
+        }
+
+        // PIP_PROXY (one proxy for every request, over HTTPS_PROXY and friends)
+        if let Ok(value) = std::env::var("PIP_PROXY") {
+            self.proxy = Some(value).filter(|proxy| !proxy.trim().is_empty());
+        }
+
+        // PIP_RS_OFFLINE (never open a network connection; caches only)
+        if let Ok(value) = std::env::var("PIP_RS_OFFLINE") {
+            self.offline = parse_bool(&value);
+        }
+
+        // PIP_RS_RETRY_DELAY (milliseconds before the first retry)
+        if let Ok(value) = std::env::var("PIP_RS_RETRY_DELAY") {
+            self.retry_delay_ms = value.trim().parse().ok();
+        }
+
+        // PIP_RS_LOCK_TIMEOUT (seconds to wait for another process changing the environment)
+        if let Ok(value) = std::env::var("PIP_RS_LOCK_TIMEOUT") {
+            self.lock_timeout_secs = value.trim().parse().ok();
+        }
+
+        // PIP_RS_MAX_RPS (requests per second toward the indexes)
+        if let Ok(value) = std::env::var("PIP_RS_MAX_RPS") {
+            self.max_rps = parse_rps(&value);
+        }
+
+        // PIP_RS_JOBS (ceiling for all worker pools)
+        if let Ok(value) = std::env::var("PIP_RS_JOBS") {
+            self.jobs = parse_jobs(&value);
+        }
+
+        // PIP_PAGER (pager command for long output, or `off`)
+        if let Ok(value) = std::env::var("PIP_PAGER") {
+            self.pager = Some(value);
+        }
+
+        // PIP_DATA_DIR (updated datasets)
+        if let Ok(path) = std::env::var("PIP_DATA_DIR") {
+            self.data_dir = Some(PathBuf::from(path));
+        }
+
+        // PIP_DATASETS_URL (where `pip datasets update` fetches from)
+        if let Ok(url) = std::env::var("PIP_DATASETS_URL") {
+            self.datasets_url = Some(url);
+        }
+
+        // PIP_WARN_DEPRECATED (check selected releases for deprecation notices)
+        if let Ok(value) = std::env::var("PIP_WARN_DEPRECATED") {
+            self.warn_deprecated = parse_bool(&value);
+        }
+
+        // PIP_SIMPLE_API_ONLY (indexes without a JSON API)
+        if let Ok(value) = std::env::var("PIP_SIMPLE_API_ONLY") {
+            self.simple_api_only = parse_bool(&value);
+        }
+
+        let mut settings: Vec<ConfigSetting> = std::env::vars()
+            .filter(|(name, _)| name.starts_with("PIP_"))
+            .map(|(name, value)| ConfigSetting::new(name, value))
+            .collect();
+        if !settings.is_empty() {
+            settings.sort_by(|a, b| a.key.cmp(&b.key));
+            self.sources.push(ConfigSource { origin: "environment".to_string(), settings });
+        }
     }
 
     /// Load configuration from standard pip.conf locations
     fn load_from_standard_locations(&mut self) {
-        use std::path::PathBuf;
-        
-        // Try user config first: ~/.pip/pip.conf (Unix) or %APPDATA%\pip\pip.ini (Windows)
-        let user_config = if cfg!(windows) {
-            if let Some(appdata) = std::env::var_os("APPDATA") {
-                PathBuf::from(appdata).join("pip").join("pip.ini")
-            } else {
-                return;
+        for path in Self::standard_locations() {
+            if let Ok(config) = Self::load_from_file(&path) {
+                self.merge(&config);
             }
-        } else {
-            if let Some(home) = dirs::home_dir() {
-                home.join(".pip").join("pip.conf")
-            } else {
-                return;
+        }
+    }
+
+    /// pip.conf locations in load order: the user file, then the site file
+    pub fn standard_locations() -> Vec<PathBuf> {
+        let mut locations = Vec::new();
+
+        // User config: ~/.pip/pip.conf (Unix) or %APPDATA%\pip\pip.ini (Windows)
+        if cfg!(windows) {
+            if let Some(appdata) = std::env::var_os("APPDATA") {
+                locations.push(PathBuf::from(appdata).join("pip").join("pip.ini"));
             }
-        };
-        
-        if let Ok(config) = Self::load_from_file(&user_config) {
-            self.merge(&config);
+        } else if let Some(home) = dirs::home_dir() {
+            locations.push(home.join(".pip").join("pip.conf"));
         }
-        
-        // Try site config: /etc/pip.conf (Unix) or C:\ProgramData\pip\pip.ini (Windows)
-        let site_config = if cfg!(windows) {
+
+        // Site config: /etc/pip.conf (Unix) or C:\ProgramData\pip\pip.ini (Windows)
+        locations.push(if cfg!(windows) {
             PathBuf::from("C:\\ProgramData\\pip\\pip.ini")
         } else {
             PathBuf::from("/etc/pip.conf")
-        };
-        
-        if let Ok(config) = Self::load_from_file(&site_config) {
-            self.merge(&config);
-        }
+        });
+        locations
     }
 
     /// Merge another config into this one
@@ -110,16 +356,97 @@ impl Config {
             self.index_url = other.index_url.clone();
         }
         self.extra_index_urls.extend_from_slice(&other.extra_index_urls);
-        if other.timeout != 15 {
+        if other.timeout != crate::network::client::DEFAULT_TIMEOUT.as_secs() {
             self.timeout = other.timeout;
         }
-        if other.retries != 3 {
+        if other.retries != crate::network::client::DEFAULT_RETRIES {
             self.retries = other.retries;
         }
         if other.cache_dir != PathBuf::from(".pip-cache") {
             self.cache_dir = other.cache_dir.clone();
         }
         self.trusted_hosts.extend_from_slice(&other.trusted_hosts);
+        self.find_links.extend_from_slice(&other.find_links);
+        if self.no_index.is_none() {
+            self.no_index = other.no_index;
+        }
+        if other.override_file.is_some() {
+            self.override_file = other.override_file.clone();
+        }
+        if other.index_policy.is_some() {
+            self.index_policy = other.index_policy.clone();
+        }
+        if self.require_virtualenv.is_none() {
+            self.require_virtualenv = other.require_virtualenv;
+        }
+        if self.break_system_packages.is_none() {
+            self.break_system_packages = other.break_system_packages;
+        }
+        if self.part_max_age_hours.is_none() {
+            self.part_max_age_hours = other.part_max_age_hours;
+        }
+        if self.cache_ttl_secs.is_none() {
+            self.cache_ttl_secs = other.cache_ttl_secs;
+        }
+        if self.retry_delay_ms.is_none() {
+            self.retry_delay_ms = other.retry_delay_ms;
+        }
+        if self.proxy.is_none() {
+            self.proxy = other.proxy.clone();
+        }
+        if self.offline.is_none() {
+            self.offline = other.offline;
+        }
+        if self.lock_timeout_secs.is_none() {
+            self.lock_timeout_secs = other.lock_timeout_secs;
+        }
+        if self.max_rps.is_none() {
+            self.max_rps = other.max_rps;
+        }
+        if self.import_map.is_none() {
+            self.import_map = other.import_map.clone();
+        }
+        if self.jobs.is_none() {
+            self.jobs = other.jobs;
+        }
+        if self.pager.is_none() {
+            self.pager = other.pager.clone();
+        }
+        if self.data_dir.is_none() {
+            self.data_dir = other.data_dir.clone();
+        }
+        if self.datasets_url.is_none() {
+            self.datasets_url = other.datasets_url.clone();
+        }
+        if self.warn_deprecated.is_none() {
+            self.warn_deprecated = other.warn_deprecated;
+        }
+        if self.simple_api_only.is_none() {
+            self.simple_api_only = other.simple_api_only;
+        }
+        self.trusted_local_configs.extend_from_slice(&other.trusted_local_configs);
+        self.named_indexes.extend_from_slice(&other.named_indexes);
+        self.sources.extend_from_slice(&other.sources);
+    }
+
+    /// Apply the `[defaults]` table of directory-local config files
+    pub fn apply_local(&mut self, local: &LocalConfig) {
+        let mut by_file: BTreeMap<&Path, Vec<ConfigSetting>> = BTreeMap::new();
+        for (key, value) in local.section(DEFAULTS_SECTION) {
+            for item in &value.values {
+                self.apply_key(key, item);
+                by_file.entry(&value.source).or_default().push(ConfigSetting::new(key, item.as_str()));
+            }
+        }
+        for (file, settings) in by_file {
+            self.sources.push(ConfigSource { origin: file.display().to_string(), settings });
+        }
+    }
+
+    /// Every config file that supplied settings, then the environment, in
+    /// load order
+    pub fn sources(&self) -> &[ConfigSource] {
+        &self.sources
     }
 
     pub fn index_url(&self) -> &str {
@@ -154,6 +481,34 @@ impl Config {
         self.retries = count;
     }
 
+    /// The proxy every request goes through (`[user:pass@]host:port`), set
+    /// by `--proxy`; `None` leaves it to HTTPS_PROXY, HTTP_PROXY and NO_PROXY
+    pub fn proxy(&self) -> Option<&str> {
+        self.proxy.as_deref()
+    }
+
+    pub fn set_proxy(&mut self, proxy: String) {
+        self.proxy = Some(proxy).filter(|proxy| !proxy.trim().is_empty());
+    }
+
+    /// Whether requests are answered from the caches only (`--offline`)
+    pub fn offline(&self) -> bool {
+        self.offline.unwrap_or(false)
+    }
+
+    pub fn set_offline(&mut self, offline: bool) {
+        self.offline = Some(offline);
+    }
+
+    /// Delay before the first retry of a failed request; later ones back
+    /// off from it
+    pub fn retry_delay(&self) -> std::time::Duration {
+        match self.retry_delay_ms {
+            Some(ms) => std::time::Duration::from_millis(ms),
+            None => crate::network::client::DEFAULT_RETRY_DELAY,
+        }
+    }
+
     pub fn cache_dir(&self) -> &Path {
         &self.cache_dir
     }
@@ -174,31 +529,212 @@ impl Config {
         self.trusted_hosts.push(host);
     }
 
+    /// `--find-links` directories and pages
+    pub fn find_links(&self) -> &[String] {
+        &self.find_links
+    }
+
+    pub fn add_find_links(&mut self, link: String) {
+        self.find_links.push(link);
+    }
+
+    /// Whether package lookups skip the indexes and use only `find_links`
+    pub fn no_index(&self) -> bool {
+        self.no_index.unwrap_or(false)
+    }
+
+    pub fn set_no_index(&mut self, no_index: bool) {
+        self.no_index = Some(no_index);
+    }
+
+    pub fn override_file(&self) -> Option<&Path> {
+        self.override_file.as_deref()
+    }
+
+    pub fn set_override_file(&mut self, path: PathBuf) {
+        self.override_file = Some(path);
+    }
+
+    pub fn index_policy(&self) -> Option<&Path> {
+        self.index_policy.as_deref()
+    }
+
+    pub fn set_index_policy(&mut self, path: PathBuf) {
+        self.index_policy = Some(path);
+    }
+
+    pub fn require_virtualenv(&self) -> bool {
+        self.require_virtualenv.unwrap_or(false)
+    }
+
+    pub fn set_require_virtualenv(&mut self, required: bool) {
+        self.require_virtualenv = Some(required);
+    }
+
+    /// Whether PEP 668 externally managed environments may be changed anyway
+    pub fn break_system_packages(&self) -> bool {
+        self.break_system_packages.unwrap_or(false)
+    }
+
+    pub fn set_break_system_packages(&mut self, allowed: bool) {
+        self.break_system_packages = Some(allowed);
+    }
+
+    /// Age after which `pip cache purge` removes partial downloads
+    pub fn part_max_age(&self) -> std::time::Duration {
+        match self.part_max_age_hours {
+            Some(hours) => std::time::Duration::from_secs(hours * 60 * 60),
+            None => crate::network::resume::DEFAULT_PART_MAX_AGE,
+        }
+    }
+
+    pub fn set_part_max_age_hours(&mut self, hours: u64) {
+        self.part_max_age_hours = Some(hours);
+    }
+
+    /// How long cached index responses the server sent no ETag,
+    /// Last-Modified or max-age for are used before fetching again
+    pub fn cache_ttl(&self) -> std::time::Duration {
+        match self.cache_ttl_secs {
+            Some(secs) => std::time::Duration::from_secs(secs),
+            None => crate::network::client::DEFAULT_CACHE_TTL,
+        }
+    }
+
+    /// How long an install or uninstall waits for another process changing
+    /// the same site-packages
+    pub fn lock_timeout(&self) -> std::time::Duration {
+        match self.lock_timeout_secs {
+            Some(secs) => std::time::Duration::from_secs(secs),
+            None => crate::installer::env_lock::DEFAULT_TIMEOUT,
+        }
+    }
+
+    pub fn set_lock_timeout_secs(&mut self, secs: u64) {
+        self.lock_timeout_secs = Some(secs);
+    }
+
+    /// Ceiling on requests per second toward the indexes, shared by all
+    /// concurrent lookups
+    pub fn max_rps(&self) -> f64 {
+        self.max_rps.unwrap_or(crate::network::throttle::DEFAULT_MAX_RPS)
+    }
+
+    /// User file extending the bundled import-name table
+    pub fn import_map(&self) -> Option<&Path> {
+        self.import_map.as_deref()
+    }
+
+    pub fn set_import_map(&mut self, path: PathBuf) {
+        self.import_map = Some(path);
+    }
+
+    /// Ceiling for concurrent work (`--jobs`); `None` means detect
+    pub fn jobs(&self) -> Option<usize> {
+        self.jobs
+    }
+
+    pub fn set_jobs(&mut self, jobs: usize) {
+        self.jobs = Some(jobs.max(1));
+    }
+
+    /// Pager for long human-readable output: `Some("off")` disables paging,
+    /// any other value is the command to run
+    pub fn pager(&self) -> Option<&str> {
+        self.pager.as_deref()
+    }
+
+    pub fn set_pager(&mut self, pager: String) {
+        self.pager = Some(pager);
+    }
+
+    /// Directory for updated datasets (default: the platform data dir)
+    pub fn data_dir(&self) -> Option<PathBuf> {
+        self.data_dir
+            .clone()
+            .or_else(|| dirs::data_local_dir().map(|dir| dir.join("pip-rs")))
+    }
+
+    pub fn set_data_dir(&mut self, path: PathBuf) {
+        self.data_dir = Some(path);
+    }
+
+    /// Base URL for `pip datasets update`, when configured
+    pub fn datasets_url(&self) -> Option<&str> {
+        self.datasets_url.as_deref()
+    }
+
+    /// Whether installs check selected releases for deprecation notices;
+    /// unset, only interactive sessions do
+    pub fn warn_deprecated(&self, interactive: bool) -> bool {
+        self.warn_deprecated.unwrap_or(interactive)
+    }
+
+    /// Whether package lookups read the indexes' simple API directly
+    /// instead of trying their JSON API first
+    pub fn simple_api_only(&self) -> bool {
+        self.simple_api_only.unwrap_or(false)
+    }
+
+    /// Directories whose `.pip-rs.toml` files may set any key
+    pub fn trusted_local_configs(&self) -> &[PathBuf] {
+        &self.trusted_local_configs
+    }
+
+    /// `[index "name"]` sections, in load order
+    pub fn named_indexes(&self) -> &[NamedIndex] {
+        &self.named_indexes
+    }
+
+    /// Apply one key of an `[index "name"]` section
+    fn apply_index_key(&mut self, name: &str, key: &str, value: &str) {
+        let position = match self.named_indexes.iter().position(|index| index.name == name) {
+            Some(position) => position,
+            None => {
+                self.named_indexes.push(NamedIndex {
+                    name: name.to_string(),
+                    ..Default::default()
+                });
+                self.named_indexes.len() - 1
+            }
+        };
+        let index = &mut self.named_indexes[position];
+        match key {
+            "url" | "index-url" | "index_url" => index.url = Some(value.to_string()),
+            "token" => index.token = Some(value.to_string()),
+            _ => {}
+        }
+    }
+
     /// Load configuration from pip.ini or .pip/pip.conf
     pub fn load_from_file(path: &Path) -> Result<Self> {
+        let mut config = Self::defaults();
+        let mut settings = Vec::new();
+        for (section, key, value) in Self::read_entries(path)? {
+            // Only process [global] section or if no section specified
+            if section.is_empty() || section == "global" {
+                config.apply_key(&key, &value);
+                settings.push(ConfigSetting::new(key, value));
+            } else if let Some(name) = index_section_name(&section) {
+                config.apply_index_key(name, &key, &value);
+                settings.push(ConfigSetting::new(format!("{}.{}", section, key), value));
+            }
+        }
+        if !settings.is_empty() {
+            config.sources.push(ConfigSource { origin: path.display().to_string(), settings });
+        }
+        Ok(config)
+    }
+
+    /// Every `key = value` line of a pip.conf file as (section, key, value),
+    /// in file order; a missing file has none
+    pub fn read_entries(path: &Path) -> Result<Vec<(String, String, String)>> {
         if !path.exists() {
-            return Ok(Self {
-                index_url: "https://pypi.org/simple/".to_string(),
-                extra_index_urls: Vec::new(),
-                timeout: 15,
-                retries: 3,
-                cache_dir: PathBuf::from(".pip-cache"),
-                user_agent: "pip-rs/1.0.0".to_string(),
-                trusted_hosts: Vec::new(),
-            });
-        }
-
-        let content = fs::read_to_string(path)?;
-        let mut config = Self {
-            index_url: "https://pypi.org/simple/".to_string(),
-            extra_index_urls: Vec::new(),
-            timeout: 15,
-            retries: 3,
-            cache_dir: PathBuf::from(".pip-cache"),
-            user_agent: "pip-rs/1.0.0".to_string(),
-            trusted_hosts: Vec::new(),
-        };
+            return Ok(Vec::new());
+        }
 
+        let content = text_encoding::read_text(path)?;
+        let mut entries = Vec::new();
         let mut current_section = String::new();
 
         for line in content.lines() {
@@ -218,40 +754,89 @@ impl Config {
             // Parse key = value pairs
             if let Some((key, value)) = line.split_once('=') {
                 let key = key.trim().to_lowercase();
-                let value = value.trim();
-
-                // Only process [global] section or if no section specified
-                if current_section.is_empty() || current_section == "global" {
-                    match key.as_str() {
-                        "index-url" | "index_url" => config.index_url = value.to_string(),
-                        "extra-index-url" | "extra_index_url" => {
-                            config.extra_index_urls.push(value.to_string());
-                        }
-                    "timeout" => {
-                        if let Ok(timeout) = value.parse::<u64>() {
-                            config.timeout = timeout;
-                        }
-                    }
-                    "retries" => {
-                        if let Ok(retries) = value.parse::<u32>() {
-                            config.retries = retries;
-                        }
-                    }
-                        "cache-dir" | "cache_dir" => config.cache_dir = PathBuf::from(value),
-                        "trusted-host" | "trusted_host" => {
-                            config.trusted_hosts.push(value.to_string());
-                        }
-                        "find-links" | "find_links" => {
-                            // Store as extra index URL for now
-                            config.extra_index_urls.push(value.to_string());
-                        }
-                    _ => {}
-                    }
-                }
+                entries.push((current_section.clone(), key, value.trim().to_string()));
             }
         }
 
-        Ok(config)
+        Ok(entries)
+    }
+
+    /// Apply one setting from a config file; unknown keys are ignored
+    fn apply_key(&mut self, key: &str, value: &str) {
+        match key {
+            "index-url" | "index_url" => self.index_url = value.to_string(),
+            "extra-index-url" | "extra_index_url" => {
+                self.extra_index_urls.push(value.to_string());
+            }
+            "timeout" => {
+                if let Ok(timeout) = value.parse::<u64>() {
+                    self.timeout = timeout;
+                }
+            }
+            "retries" => {
+                if let Ok(retries) = value.parse::<u32>() {
+                    self.retries = retries;
+                }
+            }
+            "offline" => self.offline = parse_bool(value),
+            "proxy" => self.proxy = Some(value.to_string()).filter(|proxy| !proxy.is_empty()),
+            "retry-delay" | "retry_delay" => {
+                self.retry_delay_ms = value.parse().ok();
+            }
+            "cache-dir" | "cache_dir" => self.cache_dir = PathBuf::from(value),
+            "trusted-host" | "trusted_host" => {
+                self.trusted_hosts.push(value.to_string());
+            }
+            "require-virtualenv" | "require_virtualenv" => {
+                self.require_virtualenv = parse_bool(value);
+            }
+            "break-system-packages" | "break_system_packages" => {
+                self.break_system_packages = parse_bool(value);
+            }
+            "import-map" | "import_map" => {
+                self.import_map = Some(PathBuf::from(value));
+            }
+            "part-max-age" | "part_max_age" => {
+                self.part_max_age_hours = value.parse().ok();
+            }
+            "cache-ttl" | "cache_ttl" => {
+                self.cache_ttl_secs = value.parse().ok();
+            }
+            "lock-timeout" | "lock_timeout" => {
+                self.lock_timeout_secs = value.parse().ok();
+            }
+            "max-rps" | "max_rps" => self.max_rps = parse_rps(value),
+            "jobs" => self.jobs = parse_jobs(value),
+            "pager" => self.pager = Some(value.to_string()),
+            "data-dir" | "data_dir" => {
+                self.data_dir = Some(PathBuf::from(value));
+            }
+            "datasets-url" | "datasets_url" => {
+                self.datasets_url = Some(value.to_string());
+            }
+            "warn-deprecated" | "warn_deprecated" => {
+                self.warn_deprecated = parse_bool(value);
+            }
+            "simple-api-only" | "simple_api_only" => {
+                self.simple_api_only = parse_bool(value);
+            }
+            "override-file" | "override_file" => {
+                self.override_file = Some(PathBuf::from(value));
+            }
+            "index-policy" | "index_policy" => {
+                self.index_policy = Some(PathBuf::from(value));
+            }
+            "find-links" | "find_links" => {
+                self.find_links.extend(value.split_whitespace().map(String::from));
+            }
+            "no-index" | "no_index" => {
+                self.no_index = parse_bool(value);
+            }
+            "trusted-local-config" | "trusted_local_config" => {
+                self.trusted_local_configs.push(PathBuf::from(value));
+            }
+            _ => {}
+        }
     }
 
     /// Save configuration to file
@@ -266,12 +851,85 @@ impl Config {
         
         content.push_str(&format!("timeout = {}\n", self.timeout));
         content.push_str(&format!("retries = {}\n", self.retries));
+        if let Some(ms) = self.retry_delay_ms {
+            content.push_str(&format!("retry-delay = {}\n", ms));
+        }
+        if let Some(proxy) = &self.proxy {
+            content.push_str(&format!("proxy = {}\n", proxy));
+        }
+        if let Some(offline) = self.offline {
+            content.push_str(&format!("offline = {}\n", offline));
+        }
         content.push_str(&format!("cache-dir = {}\n", self.cache_dir.display()));
         
         for host in &self.trusted_hosts {
             content.push_str(&format!("trusted-host = {}\n", host));
         }
 
+        for link in &self.find_links {
+            content.push_str(&format!("find-links = {}\n", link));
+        }
+        if let Some(no_index) = self.no_index {
+            content.push_str(&format!("no-index = {}\n", no_index));
+        }
+
+        if let Some(path) = &self.override_file {
+            content.push_str(&format!("override-file = {}\n", path.display()));
+        }
+
+        if let Some(path) = &self.index_policy {
+            content.push_str(&format!("index-policy = {}\n", path.display()));
+        }
+
+        if let Some(required) = self.require_virtualenv {
+            content.push_str(&format!("require-virtualenv = {}\n", required));
+        }
+
+        if let Some(allowed) = self.break_system_packages {
+            content.push_str(&format!("break-system-packages = {}\n", allowed));
+        }
+
+        if let Some(path) = &self.import_map {
+            content.push_str(&format!("import-map = {}\n", path.display()));
+        }
+
+        if let Some(hours) = self.part_max_age_hours {
+            content.push_str(&format!("part-max-age = {}\n", hours));
+        }
+        if let Some(secs) = self.lock_timeout_secs {
+            content.push_str(&format!("lock-timeout = {}\n", secs));
+        }
+        if let Some(jobs) = self.jobs {
+            content.push_str(&format!("jobs = {}\n", jobs));
+        }
+        if let Some(pager) = &self.pager {
+            content.push_str(&format!("pager = {}\n", pager));
+        }
+        if let Some(path) = &self.data_dir {
+            content.push_str(&format!("data-dir = {}\n", path.display()));
+        }
+        if let Some(url) = &self.datasets_url {
+            content.push_str(&format!("datasets-url = {}\n", url));
+        }
+        if let Some(warn) = self.warn_deprecated {
+            content.push_str(&format!("warn-deprecated = {}\n", warn));
+        }
+        if let Some(simple_only) = self.simple_api_only {
+            content.push_str(&format!("simple-api-only = {}\n", simple_only));
+        }
+        for dir in &self.trusted_local_configs {
+            content.push_str(&format!("trusted-local-config = {}\n", dir.display()));
+        }
+        for index in &self.named_indexes {
+            content.push_str(&format!("\n[index \"{}\"]\n", index.name));
+            if let Some(url) = &index.url {
+                content.push_str(&format!("url = {}\n", url));
+            }
+            if let Some(token) = &index.token {
+                content.push_str(&format!("token = {}\n", token));
+            }
+        }
+
         if let Some(parent) = path.parent() {
             fs::create_dir_all(parent)?;
         }
@@ -281,6 +939,30 @@ impl Config {
     }
 }
 
+/// Parse pip-style boolean values (true/false, yes/no, on/off, 1/0)
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+/// Parse a `jobs` value; zero and garbage are ignored
+fn parse_jobs(value: &str) -> Option<usize> {
+    value.trim().parse().ok().filter(|&jobs| jobs > 0)
+}
+
+/// `name` of an `[index "name"]` section header
+fn index_section_name(section: &str) -> Option<&str> {
+    let name = section.strip_prefix("index")?.trim().strip_prefix('"')?.strip_suffix('"')?.trim();
+    (!name.is_empty()).then_some(name)
+}
+
+fn parse_rps(value: &str) -> Option<f64> {
+    value.trim().parse().ok().filter(|&rps: &f64| rps.is_finite() && rps > 0.0)
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self::new()
@@ -296,8 +978,8 @@ mod tests {
     fn test_config_creation() {
         let config = Config::new();
         assert_eq!(config.index_url(), "https://pypi.org/simple/");
-        assert_eq!(config.timeout(), 15);
-        assert_eq!(config.retries(), 3);
+        assert_eq!(config.timeout(), 30);
+        assert_eq!(config.retries(), 2);
     }
 
     #[test]
@@ -314,6 +996,57 @@ mod tests {
         assert_eq!(loaded.timeout(), 30);
         assert_eq!(loaded.extra_index_urls().len(), 1);
 
+        let mut config = Config::load_from_file(&config_path)?;
+        config.set_require_virtualenv(true);
+        config.set_break_system_packages(true);
+        config.set_part_max_age_hours(12);
+        config.set_lock_timeout_secs(30);
+        config.set_jobs(2);
+        config.set_pager("off".to_string());
+        config.add_find_links("/srv/wheels".to_string());
+        config.set_no_index(true);
+        config.set_offline(true);
+        config.save_to_file(&config_path)?;
+        let loaded = Config::load_from_file(&config_path)?;
+        assert!(loaded.require_virtualenv());
+        assert!(loaded.break_system_packages());
+        assert_eq!(loaded.part_max_age(), std::time::Duration::from_secs(12 * 60 * 60));
+        assert_eq!(loaded.lock_timeout(), std::time::Duration::from_secs(30));
+        assert_eq!(loaded.jobs(), Some(2));
+        assert_eq!(loaded.pager(), Some("off"));
+        assert_eq!(loaded.find_links(), ["/srv/wheels"]);
+        assert!(loaded.offline());
+        assert!(loaded.no_index());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_named_index_sections() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_path = temp_dir.path().join("pip.conf");
+        fs::write(
+            &config_path,
+            "[global]\nindex-url = https://pypi.corp.example/simple/\n\n\
+             [index \"corp\"]\nurl = https://pypi.corp.example/simple\ntoken = s3cret\n\n\
+             [index-servers]\nurl = https://ignored.example/\n",
+        )?;
+
+        let loaded = Config::load_from_file(&config_path)?;
+        assert_eq!(
+            loaded.named_indexes(),
+            [NamedIndex {
+                name: "corp".to_string(),
+                url: Some("https://pypi.corp.example/simple".to_string()),
+                token: Some("s3cret".to_string()),
+            }]
+        );
+        loaded.save_to_file(&config_path)?;
+        assert_eq!(Config::load_from_file(&config_path)?.named_indexes(), loaded.named_indexes());
+
+        let manager = crate::network::index::IndexManager::from_config(&loaded);
+        assert_eq!(manager.get_primary().name, "corp");
+        assert_eq!(manager.get_primary().token.as_deref(), Some("s3cret"));
         Ok(())
     }
 }
@@ -0,0 +1,270 @@
+/// Per-directory defaults from `.pip-rs.toml` files
+///
+/// Files are found by walking up from the working directory, stopping at the
+/// repository root (the directory holding `.git`) or the filesystem root.
+/// `[defaults]` applies to every command; a table named after a command
+/// (`[install]`, `[lock]`, ...) holds default options for that command. When
+/// several files set the same key, the one nearest the working directory wins.
+///
+/// A checkout can carry such a file, so untrusted files may only set the keys
+/// in [`ALLOWED_KEYS`]. Anything else needs the file's directory (or one of
+/// its parents) listed as `trusted-local-config` in the user's pip.conf.
+use anyhow::{Result, anyhow, bail};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Name of a directory-local config file
+pub const FILE_NAME: &str = ".pip-rs.toml";
+
+/// Table applying to every command
+pub const DEFAULTS_SECTION: &str = "defaults";
+
+/// Keys an untrusted directory file may set: index selection, tuning and
+/// options that only narrow or preview what a command does
+pub const ALLOWED_KEYS: &[&str] = &[
+    "index-url",
+    "extra-index-url",
+    "simple-api-only",
+    "timeout",
+    "retries",
+    "jobs",
+    "pager",
+    "require-virtualenv",
+    "no-deps",
+    "only-deps",
+    "upgrade",
+    "keep-going",
+    "only-binary",
+    "require-hashes",
+    "strict-repro",
+    "refuse-if-in-use",
+    "dry-run",
+    "show-sizes",
+    "constraints",
+    "override-file",
+    "flat",
+    "json",
+    "licenses",
+    "env",
+    "hashes",
+    "outdated",
+    "sizes",
+    "check-only",
+];
+
+/// Keys holding paths, resolved against the directory of their file
+const PATH_KEYS: &[&str] = &[
+    "constraints",
+    "override-file",
+    "index-policy",
+    "requirements",
+    "target",
+    "report",
+    "output",
+    "locked",
+    "destination",
+    "cache-dir",
+    "data-dir",
+    "import-map",
+];
+
+/// A value and the file it came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalValue {
+    /// One entry per TOML array element; a single entry otherwise
+    pub values: Vec<String>,
+    pub source: PathBuf,
+}
+
+/// Merged settings of every `.pip-rs.toml` that applies
+#[derive(Debug, Clone, Default)]
+pub struct LocalConfig {
+    entries: BTreeMap<(String, String), LocalValue>,
+}
+
+impl LocalConfig {
+    /// Load the files that apply to `start`. `trusted` lists directories
+    /// whose files may set any key.
+    pub fn load(start: &Path, trusted: &[PathBuf]) -> Result<Self> {
+        Self::from_files(&discover(start), trusted)
+    }
+
+    /// Merge `files`, nearest first, so that nearer files win per key
+    pub fn from_files(files: &[PathBuf], trusted: &[PathBuf]) -> Result<Self> {
+        let mut config = Self::default();
+        for file in files.iter().rev() {
+            for (key, value) in parse_file(file, trusted)? {
+                config.entries.insert(key, value);
+            }
+        }
+        Ok(config)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Value of `key` in the `section` table
+    pub fn get(&self, section: &str, key: &str) -> Option<&LocalValue> {
+        self.entries.get(&(section.to_string(), key.to_string()))
+    }
+
+    /// Keys set in one table
+    pub fn section<'a>(&'a self, section: &'a str) -> impl Iterator<Item = (&'a str, &'a LocalValue)> + 'a {
+        self.entries
+            .iter()
+            .filter(move |((name, _), _)| name == section)
+            .map(|((_, key), value)| (key.as_str(), value))
+    }
+
+    /// Every setting as (table, key, value)
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &str, &LocalValue)> {
+        self.entries
+            .iter()
+            .map(|((section, key), value)| (section.as_str(), key.as_str(), value))
+    }
+}
+
+/// Every `.pip-rs.toml` from `start` up to the repository root, nearest first
+pub fn discover(start: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut dir = Some(start);
+    while let Some(current) = dir {
+        let file = current.join(FILE_NAME);
+        if file.is_file() {
+            files.push(file);
+        }
+        if current.join(".git").exists() {
+            break;
+        }
+        dir = current.parent();
+    }
+    files
+}
+
+fn is_trusted(dir: &Path, trusted: &[PathBuf]) -> bool {
+    let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+    trusted.iter().any(|root| {
+        let root = root.canonicalize().unwrap_or_else(|_| root.clone());
+        dir.starts_with(root)
+    })
+}
+
+fn parse_file(path: &Path, trusted: &[PathBuf]) -> Result<Vec<((String, String), LocalValue)>> {
+    let content = std::fs::read_to_string(path).map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+    let document: toml::Table = toml::from_str(&content).map_err(|e| anyhow!("{} is not valid TOML: {}", path.display(), e))?;
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let trusted = is_trusted(dir, trusted);
+
+    let mut entries = Vec::new();
+    for (section, table) in document {
+        let toml::Value::Table(table) = table else {
+            bail!("{}: '{}' must be inside a table such as [defaults] or [install]", path.display(), section);
+        };
+        for (key, value) in table {
+            if key == "trusted-local-config" {
+                bail!("{}: '{}' can only be set in pip.conf", path.display(), key);
+            }
+            if !trusted && !ALLOWED_KEYS.contains(&key.as_str()) {
+                bail!(
+                    "{}: '{}' isn't allowed in a directory config from an untrusted location; \
+                     to allow it, add `trusted-local-config = {}` to your pip.conf",
+                    path.display(),
+                    key,
+                    dir.display()
+                );
+            }
+            let mut values = to_strings(&value).ok_or_else(|| {
+                anyhow!("{}: [{}] {} must be a string, number, boolean or array of those", path.display(), section, key)
+            })?;
+            if PATH_KEYS.contains(&key.as_str()) {
+                values = values.iter().map(|v| dir.join(v).to_string_lossy().into_owned()).collect();
+            }
+            let source = path.to_path_buf();
+            entries.push(((section.clone(), key), LocalValue { values, source }));
+        }
+    }
+    Ok(entries)
+}
+
+fn to_strings(value: &toml::Value) -> Option<Vec<String>> {
+    match value {
+        toml::Value::String(s) => Some(vec![s.clone()]),
+        toml::Value::Integer(i) => Some(vec![i.to_string()]),
+        toml::Value::Float(f) => Some(vec![f.to_string()]),
+        toml::Value::Boolean(b) => Some(vec![b.to_string()]),
+        toml::Value::Array(items) => items
+            .iter()
+            .map(|item| match item {
+                toml::Value::Array(_) | toml::Value::Table(_) => None,
+                item => to_strings(item).map(|mut v| v.remove(0)),
+            })
+            .collect(),
+        toml::Value::Datetime(_) | toml::Value::Table(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// repo/.git, repo/.pip-rs.toml, repo/sub/.pip-rs.toml, plus a file
+    /// above the repository that must not be picked up
+    fn monorepo() -> (TempDir, PathBuf) {
+        let temp = TempDir::new().unwrap();
+        let repo = temp.path().join("repo");
+        let sub = repo.join("services").join("api");
+        std::fs::create_dir_all(repo.join(".git")).unwrap();
+        std::fs::create_dir_all(&sub).unwrap();
+        std::fs::write(temp.path().join(FILE_NAME), "[defaults]\njobs = 99\n").unwrap();
+        std::fs::write(
+            repo.join(FILE_NAME),
+            "[defaults]\nindex-url = \"https://corp.example/simple\"\njobs = 4\n\n[install]\nkeep-going = true\n",
+        )
+        .unwrap();
+        std::fs::write(
+            sub.join(FILE_NAME),
+            "[defaults]\nindex-url = \"https://api.example/simple\"\n\n[install]\nconstraints = \"constraints.txt\"\n",
+        )
+        .unwrap();
+        (temp, sub)
+    }
+
+    #[test]
+    fn test_discovery_and_nearest_wins() {
+        let (temp, sub) = monorepo();
+        let repo = temp.path().join("repo");
+        assert_eq!(discover(&sub), vec![sub.join(FILE_NAME), repo.join(FILE_NAME)]);
+
+        let config = LocalConfig::from_files(&discover(&sub), &[]).unwrap();
+        let index = config.get(DEFAULTS_SECTION, "index-url").unwrap();
+        assert_eq!(index.values, ["https://api.example/simple"]);
+        assert_eq!(index.source, sub.join(FILE_NAME));
+        // Keys only the outer file sets still apply; the file above .git doesn't
+        assert_eq!(config.get(DEFAULTS_SECTION, "jobs").unwrap().values, ["4"]);
+        assert_eq!(config.get("install", "keep-going").unwrap().source, repo.join(FILE_NAME));
+        // Paths are relative to the file that names them
+        let constraints = PathBuf::from(&config.get("install", "constraints").unwrap().values[0]);
+        assert_eq!(constraints, sub.join("constraints.txt"));
+    }
+
+    #[test]
+    fn test_disallowed_keys_need_trust() {
+        let (temp, sub) = monorepo();
+        let repo = temp.path().join("repo");
+        std::fs::write(repo.join(FILE_NAME), "[install]\ntrusted-host = [\"evil.example\"]\n").unwrap();
+
+        let error = LocalConfig::from_files(&discover(&sub), &[]).unwrap_err().to_string();
+        assert!(error.contains("'trusted-host' isn't allowed"), "{}", error);
+        assert!(error.contains(&format!("trusted-local-config = {}", repo.display())), "{}", error);
+
+        // Trusting the repository (or a parent of it) lifts the restriction
+        let config = LocalConfig::from_files(&discover(&sub), &[temp.path().to_path_buf()]).unwrap();
+        assert_eq!(config.get("install", "trusted-host").unwrap().values, ["evil.example"]);
+
+        // A directory file can never mark itself trusted
+        std::fs::write(sub.join(FILE_NAME), format!("[defaults]\ntrusted-local-config = \"{}\"\n", sub.display())).unwrap();
+        assert!(LocalConfig::from_files(&discover(&sub), &[temp.path().to_path_buf()]).is_err());
+    }
+}
@@ -1,4 +1,5 @@
 /// Configuration management
 pub mod config;
+pub mod local;
 pub mod pyproject;
 
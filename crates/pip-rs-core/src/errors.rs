@@ -1,6 +1,80 @@
 /// Error handling and diagnostics
+use crate::cache::CacheError;
+use crate::installer::{InstallError, InstallErrorKind};
+use crate::network::{NetworkError, NetworkErrorKind};
+use crate::resolver::ResolutionError;
+use serde::Serialize;
 use std::fmt;
 
+/// Exit status when a request to an index or file host failed
+pub const EXIT_NETWORK: i32 = 2;
+/// Exit status when no set of packages meets the requirements
+pub const EXIT_RESOLUTION: i32 = 3;
+/// Exit status when writing packages into the environment failed
+pub const EXIT_INSTALL: i32 = 4;
+/// Exit status when the cache can't be used
+pub const EXIT_CACHE: i32 = 5;
+
+const RESOLUTION_ADVICE: &str = "Relax the conflicting requirements or pin compatible versions with a constraints file \
+     (-c constraints.txt); if only pre-releases match, request one explicitly (e.g. 'pkg>=2.0rc1')";
+
+/// Pipeline stage an error happened in; each needs a different fix
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Phase {
+    Resolution,
+    Download,
+    BuildEnvSetup,
+    Build,
+    Install,
+    PostInstall,
+}
+
+impl Phase {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Phase::Resolution => "resolution",
+            Phase::Download => "download",
+            Phase::BuildEnvSetup => "build-env-setup",
+            Phase::Build => "build",
+            Phase::Install => "install",
+            Phase::PostInstall => "post-install",
+        }
+    }
+
+    /// What was being done, as in "error while <activity> lxml"
+    fn activity(&self) -> &'static str {
+        match self {
+            Phase::Resolution => "resolving dependencies of",
+            Phase::Download => "downloading",
+            Phase::BuildEnvSetup => "setting up the build environment for",
+            Phase::Build => "building wheel for",
+            Phase::Install => "installing",
+            Phase::PostInstall => "finishing the installation of",
+        }
+    }
+}
+
+/// A required package that no index has
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingPackage {
+    pub name: String,
+    /// Known projects with a close name, closest first
+    pub suggestions: Vec<String>,
+}
+
+impl fmt::Display for MissingPackage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        let quoted: Vec<String> = self.suggestions.iter().map(|name| format!("'{}'", name)).collect();
+        match quoted.split_last() {
+            None => Ok(()),
+            Some((only, [])) => write!(f, " (did you mean {}?)", only),
+            Some((last, rest)) => write!(f, " (did you mean {} or {}?)", rest.join(", "), last),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum PipError {
     /// Network error with retry information
@@ -14,6 +88,10 @@ pub enum PipError {
         name: String,
         version: Option<String>,
     },
+    /// Required packages no index has, with similarly named projects
+    PackagesNotFound {
+        packages: Vec<MissingPackage>,
+    },
     /// Dependency conflict
     DependencyConflict {
         package: String,
@@ -55,6 +133,81 @@ pub enum PipError {
         name: String,
         reason: String,
     },
+    /// Refused to modify an environment outside a virtualenv
+    VirtualEnvRequired {
+        site_packages: String,
+    },
+    /// Refused to modify an environment the OS manages (PEP 668)
+    ExternallyManaged {
+        marker: String,
+        message: String,
+    },
+    /// Another process kept the environment locked past `lock-timeout`
+    EnvironmentLocked {
+        site_packages: String,
+        /// `pid N, started T`, when the lock file could be read
+        holder: Option<String>,
+        waited_secs: u64,
+    },
+    /// Site-packages is damaged where the operation would touch it
+    DegradedEnvironment {
+        anomalies: Vec<String>,
+    },
+    /// A package would come from an index the index policy doesn't allow
+    IndexPolicyViolation {
+        package: String,
+        index: String,
+        url: String,
+        allowed: Vec<String>,
+    },
+    /// A downloaded artifact doesn't match any hash pinned for it
+    HashMismatch {
+        package: String,
+        version: String,
+        expected: Vec<String>,
+        actual: String,
+    },
+    /// An artifact can't be installed because of its own contents (a
+    /// corrupt archive, RECORD mismatches, METADATA for another project)
+    BrokenArtifact {
+        package: String,
+        artifact: String,
+        reason: String,
+    },
+    /// No file of a release fits the target: its wheels are for other
+    /// platforms or interpreters, and an sdist isn't there or allowed
+    NoCompatibleWheel {
+        package: String,
+        version: String,
+        /// Wheels the release does have
+        available: Vec<String>,
+        /// The tags that were tried, summarized
+        considered: String,
+    },
+    /// A VCS requirement couldn't be cloned, checked out or read
+    VcsError {
+        url: String,
+        reason: String,
+    },
+    /// `--python` doesn't name a Python interpreter that can be asked
+    InvalidInterpreter {
+        path: String,
+        reason: String,
+    },
+    /// A request to an index or file host failed
+    Network(NetworkError),
+    /// Resolution stopped without a set of packages
+    Resolution(ResolutionError),
+    /// A wheel couldn't be written into the environment
+    Install(InstallError),
+    /// The cache couldn't be read or written
+    Cache(CacheError),
+    /// An error tagged with the pipeline stage it happened in
+    InPhase {
+        phase: Phase,
+        package: Option<String>,
+        source: Box<PipError>,
+    },
 }
 
 impl fmt::Display for PipError {
@@ -78,6 +231,13 @@ impl fmt::Display for PipError {
                     write!(f, "Package not found: {}", name)
                 }
             }
+            PipError::PackagesNotFound { packages } => match packages.as_slice() {
+                [package] => write!(f, "Package not found: {}", package),
+                packages => {
+                    write!(f, "Packages not found:")?;
+                    packages.iter().try_for_each(|package| write!(f, "\n  {}", package))
+                }
+            },
             PipError::DependencyConflict {
                 package,
                 required,
@@ -118,12 +278,373 @@ impl fmt::Display for PipError {
             PipError::InvalidPackage { name, reason } => {
                 write!(f, "Invalid package {}: {}", name, reason)
             }
+            PipError::VirtualEnvRequired { site_packages } => {
+                write!(
+                    f,
+                    "Could not find an activated virtualenv (required); refusing to modify {}",
+                    site_packages
+                )
+            }
+            PipError::ExternallyManaged { marker, message } => {
+                write!(f, "This environment is externally managed ({}):\n{}", marker, message)
+            }
+            PipError::EnvironmentLocked { site_packages, holder, waited_secs } => {
+                write!(f, "Another pip-rs process is installing into this environment")?;
+                if let Some(holder) = holder {
+                    write!(f, " ({})", holder)?;
+                }
+                write!(f, "; gave up after {}s waiting for {}", waited_secs, site_packages)
+            }
+            PipError::DegradedEnvironment { anomalies } => {
+                write!(f, "The environment has damaged metadata for the packages being changed:")?;
+                for anomaly in anomalies {
+                    write!(f, "\n  - {}", anomaly)?;
+                }
+                write!(f, "\nRemove or repair these dist-info directories, or pass --allow-degraded-environment to proceed anyway")
+            }
+            PipError::IndexPolicyViolation { package, index, url, allowed } => write!(
+                f,
+                "Index policy violation: {} would be served by index '{}' ({}), but the index policy only allows: {}",
+                package,
+                index,
+                url,
+                allowed.join(", ")
+            ),
+            PipError::HashMismatch { package, version, expected, actual } => write!(
+                f,
+                "Hash mismatch for {} {}: expected {}, got {}",
+                package,
+                version,
+                expected.join(" or "),
+                actual
+            ),
+            PipError::BrokenArtifact { package, artifact, reason } => {
+                write!(f, "{} for {} is broken: {}", artifact, package, reason)
+            }
+            PipError::NoCompatibleWheel { package, version, available, considered } => {
+                write!(f, "No compatible wheel for {} {}", package, version)?;
+                if !available.is_empty() {
+                    write!(f, " (available: {})", available.join(", "))?;
+                }
+                write!(f, "; tags considered: {}", considered)
+            }
+            PipError::VcsError { url, reason } => write!(f, "Failed to install from {}: {}", url, reason),
+            PipError::InvalidInterpreter { path, reason } => {
+                write!(f, "Cannot use {} as the Python interpreter: {}", path, reason)
+            }
+            PipError::Network(error) => write!(f, "Network error for {}: {}", error.url, error),
+            PipError::Resolution(error) => match error.package() {
+                Some(package) => write!(f, "Failed to resolve dependencies for {}: {}", package, error),
+                None => write!(f, "Failed to resolve dependencies: {}", error),
+            },
+            PipError::Install(error) => {
+                write!(f, "Failed to install {} into {}: {}", error.package, error.path.display(), error)
+            }
+            PipError::Cache(error) => write!(f, "Cache error: {}", error),
+            PipError::InPhase { phase, package, source } => match package {
+                Some(package) => write!(f, "error while {} {} ({} phase): {}", phase.activity(), package, phase.as_str(), source),
+                None => write!(f, "error during the {} phase: {}", phase.as_str(), source),
+            },
         }
     }
 }
 
 impl std::error::Error for PipError {}
 
+impl PipError {
+    /// Stable, machine-readable error code (used in JSON reports)
+    pub fn code(&self) -> &'static str {
+        match self {
+            PipError::NetworkError { last_error, .. } if last_error.contains("Hash mismatch") => "hash-mismatch",
+            PipError::NetworkError { .. } => "download-failed",
+            PipError::PackageNotFound { .. } | PipError::PackagesNotFound { .. } => "package-not-found",
+            PipError::DependencyConflict { .. } => "dependency-conflict",
+            PipError::InvalidRequirement { .. } => "invalid-requirement",
+            PipError::InstallationFailed { .. } => "install-failed",
+            PipError::UninstallationFailed { .. } => "uninstall-failed",
+            PipError::FileSystemError { .. } => "filesystem-error",
+            PipError::ConfigError { .. } => "config-error",
+            PipError::DependencyResolutionError { .. } => "resolution-failed",
+            PipError::InvalidPackage { .. } => "invalid-package",
+            PipError::VirtualEnvRequired { .. } => "virtualenv-required",
+            PipError::ExternallyManaged { .. } => "externally-managed-environment",
+            PipError::EnvironmentLocked { .. } => "environment-locked",
+            PipError::DegradedEnvironment { .. } => "degraded-environment",
+            PipError::IndexPolicyViolation { .. } => "index-policy-violation",
+            PipError::HashMismatch { .. } => "hash-mismatch",
+            PipError::BrokenArtifact { .. } => "broken-artifact",
+            PipError::NoCompatibleWheel { .. } => "no-compatible-wheel",
+            PipError::VcsError { .. } => "vcs-failed",
+            PipError::InvalidInterpreter { .. } => "invalid-interpreter",
+            PipError::Network(error) => match error.kind {
+                NetworkErrorKind::AuthRequired | NetworkErrorKind::Status(401 | 403) => "auth-required",
+                NetworkErrorKind::InvalidArtifact => "broken-artifact",
+                NetworkErrorKind::Offline => "offline-cache-miss",
+                _ => "download-failed",
+            },
+            PipError::Resolution(ResolutionError::TooComplex(_)) => "resolution-too-complex",
+            PipError::Resolution(ResolutionError::Cancelled) => "cancelled",
+            PipError::Resolution(_) => "resolution-failed",
+            PipError::Install(error) => match error.kind {
+                InstallErrorKind::PermissionDenied => "permission-denied",
+                InstallErrorKind::Locked => "environment-locked",
+                InstallErrorKind::InvalidWheel => "broken-artifact",
+                InstallErrorKind::Conflict => "file-conflict",
+                InstallErrorKind::Cancelled => "cancelled",
+                InstallErrorKind::Io => "install-failed",
+            },
+            PipError::Cache(_) => "cache-error",
+            PipError::InPhase { source, .. } => source.code(),
+        }
+    }
+
+    /// Process exit status: [`EXIT_NETWORK`], [`EXIT_RESOLUTION`],
+    /// [`EXIT_INSTALL`] or [`EXIT_CACHE`] by what failed, 1 for the rest
+    /// (bad input, a missing package, a refused environment)
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            PipError::Network(_) | PipError::NetworkError { .. } => EXIT_NETWORK,
+            PipError::Resolution(_)
+            | PipError::DependencyResolutionError { .. }
+            | PipError::DependencyConflict { .. } => EXIT_RESOLUTION,
+            PipError::Install(_) | PipError::InstallationFailed { .. } | PipError::UninstallationFailed { .. } => {
+                EXIT_INSTALL
+            }
+            PipError::Cache(_) => EXIT_CACHE,
+            PipError::InPhase { source, .. } => source.exit_code(),
+            _ => 1,
+        }
+    }
+
+    /// The typed error `error` carries, or `error` itself when it is an
+    /// untyped one the caller has to describe
+    pub fn from_anyhow(error: anyhow::Error) -> Result<Self, anyhow::Error> {
+        let error = match error.downcast::<PipError>() {
+            Ok(error) => return Ok(error),
+            Err(error) => error,
+        };
+        let error = match error.downcast::<NetworkError>() {
+            Ok(error) => return Ok(error.into()),
+            Err(error) => error,
+        };
+        let error = match error.downcast::<ResolutionError>() {
+            Ok(error) => return Ok(error.into()),
+            Err(error) => error,
+        };
+        let error = match error.downcast::<InstallError>() {
+            Ok(error) => return Ok(error.into()),
+            Err(error) => error,
+        };
+        error.downcast::<CacheError>().map(PipError::from)
+    }
+
+    /// Tag the error with the stage it happened in. An error that already
+    /// carries a phase keeps it (the innermost stage is the precise one).
+    pub fn in_phase(self, phase: Phase, package: Option<&str>) -> Self {
+        match self {
+            PipError::InPhase { .. } => self,
+            source => PipError::InPhase {
+                phase,
+                package: package.map(String::from),
+                source: Box::new(source),
+            },
+        }
+    }
+
+    /// Stage the error happened in, when it was tagged
+    pub fn phase(&self) -> Option<Phase> {
+        match self {
+            PipError::InPhase { phase, .. } => Some(*phase),
+            _ => None,
+        }
+    }
+
+    /// How to fix the error: phase-specific advice first, then advice
+    /// matched from the message
+    pub fn suggestion(&self) -> Option<String> {
+        if let PipError::IndexPolicyViolation { package, .. } = self {
+            return Some(format!(
+                "Make sure {} is published on an allowed index, or update the rule for it in the index policy file",
+                package
+            ));
+        }
+        let untagged = match self {
+            PipError::InPhase { source, .. } => source.as_ref(),
+            error => error,
+        };
+        match untagged {
+            PipError::Network(error) => return network_advice(error.kind).map(String::from),
+            PipError::Resolution(ResolutionError::Impossible(_)) => return Some(RESOLUTION_ADVICE.to_string()),
+            // The budget message names what to pin and which limit to raise
+            PipError::Resolution(_) => return None,
+            PipError::Install(error) => return install_advice(error.kind).map(String::from),
+            PipError::Cache(CacheError::Io { .. }) => {
+                return Some(
+                    "Check that the cache directory is writable, point PIP_CACHE_DIR at another one, or clear it \
+                     with `pip cache purge`"
+                        .to_string(),
+                );
+            }
+            PipError::Cache(CacheError::UnsupportedLayout(_)) => return None,
+            _ => {}
+        }
+        if let PipError::ExternallyManaged { .. } = untagged {
+            return Some(
+                "Install into a virtual environment (pip-rs venv create .venv) or a directory with --target <dir>; \
+                 to override at the risk of breaking the system Python, pass --break-system-packages or set \
+                 break-system-packages = true in pip.conf"
+                    .to_string(),
+            );
+        }
+        if let PipError::EnvironmentLocked { .. } = untagged {
+            return Some(
+                "Wait for the other install to finish, or raise lock-timeout in pip.conf (PIP_RS_LOCK_TIMEOUT) to \
+                 wait longer"
+                    .to_string(),
+            );
+        }
+        if let PipError::PackagesNotFound { .. } = untagged {
+            return Some(
+                "Check the spelling of the name; a package published on a private index needs that index given \
+                 with --index-url or --extra-index-url"
+                    .to_string(),
+            );
+        }
+        if let PipError::InvalidInterpreter { .. } = untagged {
+            return Some(
+                "Pass the path of a Python 3 executable, e.g. --python /usr/bin/python3.12 or --python .venv/bin/python"
+                    .to_string(),
+            );
+        }
+        if let PipError::NoCompatibleWheel { .. } = untagged {
+            return Some(
+                "Pick a version that publishes a wheel for this platform and Python, or allow building from source \
+                 (drop --only-binary for this package)"
+                    .to_string(),
+            );
+        }
+        // Checkout problems; build problems get the build advice below
+        if let PipError::VcsError { .. } = untagged
+            && self.phase() != Some(Phase::Build)
+        {
+            return Some(
+                "Check that git is installed, the repository URL is reachable with your credentials, and the ref \
+                 after '@' names an existing branch, tag or commit"
+                    .to_string(),
+            );
+        }
+        let advice = match self.phase() {
+            Some(Phase::Resolution) => Some(RESOLUTION_ADVICE),
+            Some(Phase::Download) if !self.to_string().contains("Hash mismatch") => Some(
+                "Check network access to the index: use --trusted-host <host> for a self-signed index, --index-url in a \
+                 requirements file for a mirror, and HTTPS_PROXY for a proxy",
+            ),
+            Some(Phase::BuildEnvSetup) => Some(
+                "The isolated build environment could not be prepared; check that the build-system requirements in \
+                 pyproject.toml are installable",
+            ),
+            Some(Phase::Build) => Some(
+                "Install a C compiler and the Python development headers (e.g. build-essential and python3-dev), or \
+                 pick a version that publishes a wheel for this platform so nothing has to be built",
+            ),
+            Some(Phase::PostInstall) => Some(
+                "The package files are in place but a post-install step failed; run `pip check` and reinstall with \
+                 --force-reinstall if it reports problems",
+            ),
+            _ => None,
+        };
+        advice.map(String::from).or_else(|| suggest_fix(&self.to_string()))
+    }
+
+    /// Machine-readable form: code, phase, message and suggestion
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "code": self.code(),
+            "phase": self.phase(),
+            "message": self.to_string(),
+            "suggestion": self.suggestion(),
+        })
+    }
+}
+
+impl From<NetworkError> for PipError {
+    fn from(error: NetworkError) -> Self {
+        PipError::Network(error)
+    }
+}
+
+impl From<ResolutionError> for PipError {
+    fn from(error: ResolutionError) -> Self {
+        PipError::Resolution(error)
+    }
+}
+
+impl From<InstallError> for PipError {
+    fn from(error: InstallError) -> Self {
+        PipError::Install(error)
+    }
+}
+
+impl From<CacheError> for PipError {
+    fn from(error: CacheError) -> Self {
+        PipError::Cache(error)
+    }
+}
+
+/// What to do about a failed request, by how it failed
+fn network_advice(kind: NetworkErrorKind) -> Option<&'static str> {
+    match kind {
+        NetworkErrorKind::Unreachable => Some(
+            "Check network access to the index: use --proxy (or HTTPS_PROXY) behind a proxy, and --trusted-host \
+             <host> for a self-signed index",
+        ),
+        NetworkErrorKind::Timeout => {
+            Some("The index answered too slowly; raise --timeout, or --retries on a flaky connection")
+        }
+        NetworkErrorKind::Status(404) => {
+            Some("The requested resource was not found. Check the package name and version")
+        }
+        NetworkErrorKind::Status(401 | 403) => Some(
+            "Access denied. Check the credentials for the index: in its URL, in ~/.netrc, or as a token in pip.conf",
+        ),
+        NetworkErrorKind::Status(500..) => Some("The index had a server error. Try again later, or raise --retries"),
+        // The message says where credentials go
+        NetworkErrorKind::Status(_) | NetworkErrorKind::AuthRequired => None,
+        NetworkErrorKind::InvalidArtifact => Some(
+            "Try again; if it keeps failing, check for a proxy rewriting responses, and drop a bad cached copy \
+             with `pip cache remove <package>`",
+        ),
+        NetworkErrorKind::InvalidResponse => Some(
+            "The index sent something pip-rs can't read; check that --index-url points at a simple index, or try \
+             again later",
+        ),
+        NetworkErrorKind::Offline => {
+            Some("Run once without --offline to fill the cache, or fetch the files first with `pip download`")
+        }
+        NetworkErrorKind::Io => Some("Check free disk space and that the download directory is writable"),
+    }
+}
+
+/// What to do about a failed install, by how it failed
+fn install_advice(kind: InstallErrorKind) -> Option<&'static str> {
+    match kind {
+        InstallErrorKind::PermissionDenied => Some(
+            "Install into a virtual environment (pip-rs venv create .venv) or with --user instead of a \
+             site-packages you can't write to",
+        ),
+        InstallErrorKind::Locked => Some(
+            "Wait for the other install to finish, or raise lock-timeout in pip.conf (PIP_RS_LOCK_TIMEOUT) to \
+             wait longer",
+        ),
+        InstallErrorKind::InvalidWheel => {
+            Some("The wheel is damaged; drop the cached copy with `pip cache remove <package>` and install again")
+        }
+        InstallErrorKind::Io => Some("Check free disk space where the environment lives"),
+        // The message names the flag that overrides it
+        InstallErrorKind::Conflict | InstallErrorKind::Cancelled => None,
+    }
+}
+
 /// Helper functions for better error messages
 pub fn suggest_fix(error: &str) -> Option<String> {
     if error.contains("No such file or directory") {
@@ -148,6 +669,19 @@ pub fn suggest_fix(error: &str) -> Option<String> {
         Some("Check the requirement format. Use 'package==version' or 'package>=version'. See PEP 508 for details".to_string())
     } else if error.contains("Dependency conflict") {
         Some("Try upgrading conflicting packages or use --no-deps to skip dependency checks".to_string())
+    } else if error.contains("virtualenv (required)") {
+        Some("Activate a virtual environment, create one with `python -m venv .venv`, or install into an explicit directory with --target <dir>".to_string())
+    } else if let Some(module) = missing_module(error) {
+        let module = module.split('.').next().unwrap_or(module);
+        match crate::installer::import_names::CuratedImportMap::bundled().lookup(module) {
+            Some(dists) => Some(format!(
+                "The '{}' module is provided by {}. Try: pip install {}",
+                module,
+                dists.join(" or "),
+                dists[0]
+            )),
+            None => Some(format!("Find the package that provides it with: pip find-import {}", module)),
+        }
     } else if error.contains("Failed to parse JSON") {
         Some("The response from PyPI was invalid. This may be a temporary issue. Try again later".to_string())
     } else {
@@ -155,10 +689,32 @@ pub fn suggest_fix(error: &str) -> Option<String> {
     }
 }
 
-/// Format error with suggestions
-pub fn format_error_with_suggestion(error: &str) -> String {
+/// Module name from a Python "No module named 'x'" message
+fn missing_module(error: &str) -> Option<&str> {
+    let rest = &error[error.find("No module named ")? + "No module named ".len()..];
+    let module = rest.trim_start().trim_start_matches(['\'', '"']);
+    let end = module.find(['\'', '"', ' ', '\n']).unwrap_or(module.len());
+    (end > 0).then(|| &module[..end])
+}
+
+/// Format an error with its (phase-aware) suggestion
+pub fn format_pip_error(error: &PipError) -> String {
+    let mut msg = format!("ERROR: {}", error);
+    if let Some(suggestion) = error.suggestion() {
+        msg.push_str(&format!("\nSuggestion: {}", suggestion));
+    }
+    msg
+}
+
+/// Format an error from outside the commands, advised by its type when it
+/// carries one and by its message otherwise
+pub fn format_error_with_suggestion(error: anyhow::Error) -> String {
+    let error = match PipError::from_anyhow(error) {
+        Ok(error) => return format_pip_error(&error),
+        Err(error) => error,
+    };
     let mut msg = format!("ERROR: {}", error);
-    if let Some(suggestion) = suggest_fix(error) {
+    if let Some(suggestion) = suggest_fix(&error.to_string()) {
         msg.push_str(&format!("\nSuggestion: {}", suggestion));
     }
     msg
@@ -197,4 +753,159 @@ mod tests {
         assert!(suggest_fix("Timeout").is_some());
         assert!(suggest_fix("404 Not Found").is_some());
     }
+
+    #[test]
+    fn test_error_codes() {
+        let hash = PipError::NetworkError {
+            message: "Failed to download lxml".to_string(),
+            retries: 0,
+            last_error: "Hash mismatch for https://example.org/lxml.whl".to_string(),
+        };
+        assert_eq!(hash.code(), "hash-mismatch");
+        let pinned = PipError::HashMismatch {
+            package: "lxml".to_string(),
+            version: "5.2.1".to_string(),
+            expected: vec!["sha256:aaa".to_string()],
+            actual: "sha256:bbb".to_string(),
+        }
+        .in_phase(Phase::Download, Some("lxml"));
+        assert_eq!(pinned.code(), "hash-mismatch");
+        assert!(pinned.to_string().contains("Hash mismatch for lxml 5.2.1: expected sha256:aaa, got sha256:bbb"));
+        assert!(pinned.suggestion().is_none());
+        let not_found = PipError::PackageNotFound {
+            name: "nonexistent".to_string(),
+            version: None,
+        };
+        assert_eq!(not_found.code(), "package-not-found");
+    }
+
+    #[test]
+    fn test_packages_not_found_lists_suggestions() {
+        let missing = |name: &str, suggestions: &[&str]| MissingPackage {
+            name: name.to_string(),
+            suggestions: suggestions.iter().map(|s| s.to_string()).collect(),
+        };
+        let one = PipError::PackagesNotFound { packages: vec![missing("reqests", &["requests"])] };
+        assert_eq!(one.to_string(), "Package not found: reqests (did you mean 'requests'?)");
+        assert_eq!(one.code(), "package-not-found");
+        assert!(one.suggestion().unwrap().contains("--extra-index-url"));
+
+        let several = PipError::PackagesNotFound {
+            packages: vec![missing("flaks", &["flask", "flake8", "flask-cors"]), missing("acme-internal", &[])],
+        };
+        assert_eq!(
+            several.to_string(),
+            "Packages not found:\n  flaks (did you mean 'flask', 'flake8' or 'flask-cors'?)\n  acme-internal"
+        );
+    }
+
+    #[test]
+    fn test_missing_module_suggestion() {
+        let hint = suggest_fix("ModuleNotFoundError: No module named 'cv2'").unwrap();
+        assert!(hint.contains("pip install opencv-python"));
+        let hint = suggest_fix("No module named 'acme_internal.client'").unwrap();
+        assert_eq!(hint, "Find the package that provides it with: pip find-import acme_internal");
+    }
+
+    #[test]
+    fn test_phase_tagging_and_suggestions() {
+        let build = PipError::InstallationFailed {
+            package: "lxml".to_string(),
+            reason: "command 'gcc' failed".to_string(),
+        }
+        .in_phase(Phase::Build, Some("lxml"));
+        assert!(build.to_string().starts_with("error while building wheel for lxml (build phase): "));
+        assert_eq!(build.code(), "install-failed");
+        assert!(build.suggestion().unwrap().contains("C compiler"));
+
+        // The innermost stage wins when an error is tagged twice
+        let resolution = PipError::DependencyResolutionError {
+            package: "app".to_string(),
+            reason: "no version of idna satisfies idna<2,>=3".to_string(),
+        }
+        .in_phase(Phase::Resolution, None)
+        .in_phase(Phase::Install, Some("app"));
+        assert_eq!(resolution.phase(), Some(Phase::Resolution));
+        assert!(resolution.suggestion().unwrap().contains("constraints file"));
+
+        let download = PipError::NetworkError {
+            message: "Failed to download idna".to_string(),
+            retries: 0,
+            last_error: "Connection refused".to_string(),
+        }
+        .in_phase(Phase::Download, Some("idna"));
+        assert!(download.suggestion().unwrap().contains("--trusted-host"));
+        let json = download.to_json();
+        assert_eq!(json["phase"], "download");
+        assert_eq!(json["code"], "download-failed");
+
+        let checkout = PipError::VcsError {
+            url: "git+https://github.com/org/repo.git@nope".to_string(),
+            reason: "'nope' is not a branch, tag or commit of the repository".to_string(),
+        }
+        .in_phase(Phase::Download, None);
+        assert_eq!(checkout.code(), "vcs-failed");
+        assert!(checkout.suggestion().unwrap().contains("git is installed"));
+    }
+
+    #[test]
+    fn test_externally_managed_message_and_suggestion() {
+        let err = PipError::ExternallyManaged {
+            marker: "/usr/lib/python3.11/EXTERNALLY-MANAGED".to_string(),
+            message: "To install Python packages system-wide, try apt install python3-xyz".to_string(),
+        };
+        assert_eq!(err.code(), "externally-managed-environment");
+        assert!(err.to_string().ends_with("\nTo install Python packages system-wide, try apt install python3-xyz"));
+        assert!(err.suggestion().unwrap().contains("--break-system-packages"));
+    }
+
+    #[test]
+    fn test_virtualenv_required_suggestion() {
+        let err = PipError::VirtualEnvRequired {
+            site_packages: "/usr/lib/python3/dist-packages".to_string(),
+        };
+        let formatted = format_error_with_suggestion(err.into());
+        assert!(formatted.contains("/usr/lib/python3/dist-packages"));
+        assert!(formatted.contains("--target"));
+    }
+
+    #[test]
+    fn test_typed_errors_exit_codes_and_advice() {
+        let url = "https://pypi.org/simple/requests/";
+        let refused = PipError::from(NetworkError::new(NetworkErrorKind::Unreachable, url, "Connection refused"))
+            .in_phase(Phase::Download, Some("requests"));
+        assert_eq!(refused.exit_code(), EXIT_NETWORK);
+        assert_eq!(refused.code(), "download-failed");
+        assert!(refused.suggestion().unwrap().contains("--proxy"));
+        let offline = PipError::from(NetworkError::offline(url));
+        assert!(offline.to_string().starts_with("Network error for https://pypi.org/simple/requests/: offline mode"));
+        assert!(offline.suggestion().unwrap().contains("without --offline"));
+
+        let site = std::path::Path::new("/usr/lib/python3/site-packages");
+        let install = InstallError::with_cause(InstallErrorKind::Io, "six", site, denied_error().into());
+        let install = PipError::from(install);
+        assert_eq!(install.exit_code(), EXIT_INSTALL);
+        assert_eq!(install.code(), "permission-denied");
+        assert_eq!(install.to_string(), "Failed to install six into /usr/lib/python3/site-packages: Permission denied");
+        assert!(install.suggestion().unwrap().contains("--user"));
+
+        let cancelled = PipError::from(ResolutionError::Cancelled);
+        assert_eq!((cancelled.exit_code(), cancelled.code()), (EXIT_RESOLUTION, "cancelled"));
+        assert!(cancelled.suggestion().is_none());
+        let cache = CacheError::io("write", std::path::Path::new("/cache/http/ab"), denied_error());
+        assert_eq!(PipError::from(cache).exit_code(), EXIT_CACHE);
+        let config = PipError::ConfigError { message: "bad value".to_string() };
+        assert_eq!(config.exit_code(), 1);
+
+        // Typed errors are recovered from anyhow, the rest handed back
+        let typed = PipError::from_anyhow(NetworkError::offline(url).into()).unwrap();
+        assert_eq!(typed.code(), "offline-cache-miss");
+        assert!(PipError::from_anyhow(anyhow::anyhow!("something else")).is_err());
+        let formatted = format_error_with_suggestion(ResolutionError::Cancelled.into());
+        assert_eq!(formatted, "ERROR: Failed to resolve dependencies: resolution cancelled");
+    }
+
+    fn denied_error() -> std::io::Error {
+        std::io::Error::new(std::io::ErrorKind::PermissionDenied, "Permission denied")
+    }
 }
@@ -0,0 +1,453 @@
+/// Per-package failure isolation for batch installs (`--keep-going`)
+///
+/// Packages are installed dependencies-first. When one fails, everything
+/// that (transitively) depends on it is skipped instead of installed against
+/// a missing dependency, while unrelated packages carry on.
+use crate::errors::{Phase, PipError};
+use crate::installer::events::{self, InstallEvent};
+use crate::installer::fallback::ArtifactFallback;
+use crate::installer::provenance::Provenance;
+use crate::resolver::deprecation::DeprecationNotice;
+use crate::resolver::index_policy::IndexSource;
+use crate::resolver::markers::MarkerSkip;
+use crate::resolver::DependencyMode;
+use crate::models::Package;
+use crate::utils::names::canonicalize_name;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+/// Exit code when some, but not all, packages were installed
+pub const EXIT_PARTIAL_FAILURE: i32 = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InstalledPackage {
+    pub name: String,
+    pub version: String,
+    /// Bytes written to the environment
+    pub size: u64,
+    /// Index that served the metadata
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata_index: Option<IndexSource>,
+    /// Index that served the artifact
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artifact_index: Option<IndexSource>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FailedPackage {
+    pub name: String,
+    pub version: String,
+    /// Stable error code (see `PipError::code`)
+    pub code: String,
+    /// Pipeline stage the failure happened in
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phase: Option<Phase>,
+    pub error: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SkippedPackage {
+    pub name: String,
+    pub version: String,
+    /// The failed package this one (transitively) depends on
+    pub due_to: String,
+}
+
+/// Outcome of a batch install
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct BatchReport {
+    pub installed: Vec<InstalledPackage>,
+    pub failed: Vec<FailedPackage>,
+    pub skipped: Vec<SkippedPackage>,
+    /// Packages installed and then removed again because another one failed
+    /// (`--rollback-on-failure`)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rolled_back: Vec<InstalledPackage>,
+    /// Dependencies the resolver left out because of environment markers
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub skipped_by_markers: Vec<MarkerSkip>,
+    /// Packages installed from another file after their first one was broken
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fallbacks: Vec<ArtifactFallback>,
+    /// Selected releases that declare themselves deprecated or unmaintained
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub deprecated: Vec<DeprecationNotice>,
+    /// `no-deps` or `only-deps` when part of the graph was left out on purpose
+    #[serde(skip_serializing_if = "DependencyMode::is_all")]
+    pub dependency_mode: DependencyMode,
+    /// Download volume and phase timings
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<InstallSummary>,
+}
+
+/// Totals behind the closing "Successfully installed" line
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct InstallSummary {
+    /// Artifacts fetched, copies from the wheel cache included
+    pub downloads: usize,
+    pub downloaded_bytes: u64,
+    /// Time spent fetching artifacts
+    pub download_ms: u64,
+    pub resolve_ms: u64,
+    /// Downloading and installing, from the first package to the last
+    pub install_ms: u64,
+}
+
+impl InstallSummary {
+    /// `Successfully installed foo-1.0 bar-2.3 (downloaded 48.2 MiB in 6.1s)`,
+    /// or nothing when no package was installed
+    pub fn line(&self, installed: &[InstalledPackage]) -> Option<String> {
+        if installed.is_empty() {
+            return None;
+        }
+        let packages: Vec<String> = installed.iter().map(|p| format!("{}-{}", p.name, p.version)).collect();
+        let mut line = format!("Successfully installed {}", packages.join(" "));
+        if self.downloads > 0 {
+            line.push_str(&format!(
+                " (downloaded {} in {:.1}s)",
+                crate::installer::sizes::format_size(self.downloaded_bytes),
+                self.download_ms as f64 / 1000.0
+            ));
+        }
+        Some(line)
+    }
+}
+
+impl BatchReport {
+    /// 0 on success, 1 when nothing could be installed, and
+    /// `EXIT_PARTIAL_FAILURE` when only some packages were installed
+    pub fn exit_code(&self) -> i32 {
+        if self.failed.is_empty() && self.skipped.is_empty() {
+            0
+        } else if self.installed.is_empty() {
+            1
+        } else {
+            EXIT_PARTIAL_FAILURE
+        }
+    }
+
+    /// Move `name` from the installed packages to the rolled back ones
+    pub fn record_rolled_back(&mut self, name: &str) {
+        if let Some(index) = self.installed.iter().position(|pkg| pkg.name == name) {
+            let pkg = self.installed.remove(index);
+            self.rolled_back.push(pkg);
+        }
+    }
+
+    /// Installed packages as sizes, largest first, with the total
+    pub fn sizes(&self) -> (Vec<crate::installer::sizes::PackageSize>, u64) {
+        let mut sizes: Vec<_> = self
+            .installed
+            .iter()
+            .map(|p| crate::installer::sizes::PackageSize {
+                name: p.name.clone(),
+                version: p.version.clone(),
+                bytes: p.size,
+            })
+            .collect();
+        let total = crate::installer::sizes::sort_by_size(&mut sizes);
+        (sizes, total)
+    }
+
+    /// Human-readable failure section of the final summary
+    pub fn failure_summary(&self) -> Option<String> {
+        if self.failed.is_empty() && self.skipped.is_empty() {
+            return None;
+        }
+
+        let mut summary = format!("Failed ({}):", self.failed.len());
+        for pkg in &self.failed {
+            let tag = match pkg.phase {
+                Some(phase) => format!("{}, {} phase", pkg.code, phase.as_str()),
+                None => pkg.code.clone(),
+            };
+            summary.push_str(&format!("\n  - {} {} [{}]: {}", pkg.name, pkg.version, tag, pkg.error));
+        }
+        if !self.skipped.is_empty() {
+            summary.push_str(&format!("\nSkipped ({}):", self.skipped.len()));
+            for pkg in &self.skipped {
+                summary.push_str(&format!("\n  - {} {} (depends on {})", pkg.name, pkg.version, pkg.due_to));
+            }
+        }
+        Some(summary)
+    }
+}
+
+/// Tracks outcomes while installing a resolved set
+pub struct BatchInstall {
+    packages: Vec<Package>,
+    /// normalized name -> normalized names of its dependencies
+    edges: HashMap<String, Vec<String>>,
+    /// normalized name of every failed or skipped package -> failed root
+    blocked: HashMap<String, String>,
+    report: BatchReport,
+}
+
+impl BatchInstall {
+    /// `edges` maps each package to the names it requires (resolution graph)
+    pub fn new(packages: &[Package], edges: &HashMap<String, Vec<String>>) -> Self {
+        let edges = edges
+            .iter()
+            .map(|(parent, deps)| (canonicalize_name(parent), deps.iter().map(|d| canonicalize_name(d)).collect()))
+            .collect();
+        Self {
+            packages: packages.to_vec(),
+            edges,
+            blocked: HashMap::new(),
+            report: BatchReport::default(),
+        }
+    }
+
+    /// Resolved packages ordered so dependencies come before their dependents
+    pub fn install_order(&self) -> Vec<Package> {
+        let by_name: HashMap<String, &Package> =
+            self.packages.iter().map(|p| (canonicalize_name(&p.name), p)).collect();
+        let mut visited = HashSet::new();
+        let mut order = Vec::with_capacity(self.packages.len());
+
+        fn visit<'a>(
+            name: &str,
+            by_name: &HashMap<String, &'a Package>,
+            edges: &HashMap<String, Vec<String>>,
+            visited: &mut HashSet<String>,
+            order: &mut Vec<&'a Package>,
+        ) {
+            if !visited.insert(name.to_string()) {
+                return;
+            }
+            for dep in edges.get(name).into_iter().flatten() {
+                visit(dep, by_name, edges, visited, order);
+            }
+            if let Some(pkg) = by_name.get(name) {
+                order.push(pkg);
+            }
+        }
+
+        for pkg in &self.packages {
+            visit(&canonicalize_name(&pkg.name), &by_name, &self.edges, &mut visited, &mut order);
+        }
+        order.into_iter().cloned().collect()
+    }
+
+    /// The failed package that prevents installing `name`, if any.
+    /// Relies on `install_order`, so dependencies are already decided.
+    pub fn blocked_by(&self, name: &str) -> Option<String> {
+        self.edges
+            .get(&canonicalize_name(name))
+            .into_iter()
+            .flatten()
+            .find_map(|dep| self.blocked.get(dep).cloned())
+    }
+
+    pub fn record_installed(&mut self, pkg: &Package, size: u64) {
+        self.record_installed_from(pkg, size, None);
+    }
+
+    /// Record an install along with the indexes recorded in its provenance
+    pub fn record_installed_from(&mut self, pkg: &Package, size: u64, provenance: Option<&Provenance>) {
+        let installed = InstalledPackage {
+            name: pkg.name.clone(),
+            version: pkg.version.clone(),
+            size,
+            metadata_index: provenance.and_then(|p| p.metadata_index.clone()),
+            artifact_index: provenance.and_then(|p| p.artifact_index.clone()),
+        };
+        events::emit(InstallEvent::Installed(installed.clone()));
+        self.report.installed.push(installed);
+    }
+
+    /// Record that a package's first artifact was replaced by another
+    pub fn record_fallback(&mut self, fallback: ArtifactFallback) {
+        self.report.fallbacks.push(fallback);
+    }
+
+    pub fn record_failed(&mut self, pkg: &Package, error: &PipError) {
+        self.blocked.insert(canonicalize_name(&pkg.name), pkg.name.clone());
+        let failed = FailedPackage {
+            name: pkg.name.clone(),
+            version: pkg.version.clone(),
+            code: error.code().to_string(),
+            phase: error.phase(),
+            error: error.to_string(),
+            suggestion: error.suggestion(),
+        };
+        events::emit(InstallEvent::Failed(failed.clone()));
+        self.report.failed.push(failed);
+    }
+
+    pub fn record_skipped(&mut self, pkg: &Package, due_to: String) {
+        self.blocked.insert(canonicalize_name(&pkg.name), due_to.clone());
+        let skipped = SkippedPackage {
+            name: pkg.name.clone(),
+            version: pkg.version.clone(),
+            due_to,
+        };
+        events::emit(InstallEvent::Skipped(skipped.clone()));
+        self.report.skipped.push(skipped);
+    }
+
+    pub fn into_report(self) -> BatchReport {
+        self.report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// app -> (web, cli); web -> (lxml, idna); lxml -> libxml-shim; cli -> click
+    fn graph() -> (Vec<Package>, HashMap<String, Vec<String>>) {
+        let packages = ["app", "web", "cli", "lxml", "idna", "click", "libxml-shim"]
+            .iter()
+            .map(|name| Package::new(name.to_string(), "1.0".to_string()))
+            .collect();
+        let edges = [
+            ("app", vec!["web", "cli"]),
+            ("web", vec!["lxml", "idna"]),
+            ("lxml", vec!["libxml-shim"]),
+            ("cli", vec!["click"]),
+        ]
+        .into_iter()
+        .map(|(parent, deps)| (parent.to_string(), deps.into_iter().map(String::from).collect()))
+        .collect();
+        (packages, edges)
+    }
+
+    /// Drive a batch the way `pip install` does, failing `failing`
+    fn run(keep_going: bool, failing: &str) -> BatchReport {
+        let (packages, edges) = graph();
+        let mut batch = BatchInstall::new(&packages, &edges);
+        for pkg in batch.install_order() {
+            if let Some(due_to) = batch.blocked_by(&pkg.name) {
+                batch.record_skipped(&pkg, due_to);
+                continue;
+            }
+            if pkg.name == failing {
+                let error = PipError::NetworkError {
+                    message: "Failed to download lxml".to_string(),
+                    retries: 0,
+                    last_error: "Hash mismatch for lxml".to_string(),
+                }
+                .in_phase(Phase::Download, Some("lxml"));
+                batch.record_failed(&pkg, &error);
+                if !keep_going {
+                    break;
+                }
+            } else {
+                batch.record_installed(&pkg, 1024);
+            }
+        }
+        batch.into_report()
+    }
+
+    #[test]
+    fn test_install_order_puts_dependencies_first() {
+        let (packages, edges) = graph();
+        let order: Vec<String> = BatchInstall::new(&packages, &edges)
+            .install_order()
+            .into_iter()
+            .map(|p| p.name)
+            .collect();
+        let position = |name: &str| order.iter().position(|n| n == name).unwrap();
+        assert_eq!(order.len(), 7);
+        assert!(position("libxml-shim") < position("lxml"));
+        assert!(position("lxml") < position("web"));
+        assert!(position("web") < position("app"));
+        assert!(position("click") < position("cli"));
+    }
+
+    #[test]
+    fn test_keep_going_skips_dependents_of_failed_package() {
+        let report = run(true, "lxml");
+
+        let mut installed: Vec<&str> = report.installed.iter().map(|p| p.name.as_str()).collect();
+        installed.sort();
+        assert_eq!(installed, vec!["cli", "click", "idna", "libxml-shim"]);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].code, "hash-mismatch");
+        let skipped: Vec<(&str, &str)> = report
+            .skipped
+            .iter()
+            .map(|p| (p.name.as_str(), p.due_to.as_str()))
+            .collect();
+        assert_eq!(skipped, vec![("web", "lxml"), ("app", "lxml")]);
+        assert_eq!(report.exit_code(), EXIT_PARTIAL_FAILURE);
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["skipped"][0]["due_to"], "lxml");
+        assert_eq!(json["failed"][0]["phase"], "download");
+        let summary = report.failure_summary().unwrap();
+        assert!(summary.contains("lxml 1.0 [hash-mismatch, download phase]: error while downloading lxml"));
+        assert!(summary.contains("web 1.0 (depends on lxml)"));
+
+        // The dependency mode is only reported when part of the graph was left out
+        assert!(json.get("dependency_mode").is_none());
+        let no_deps = BatchReport {
+            dependency_mode: DependencyMode::NoDeps,
+            ..report
+        };
+        assert_eq!(serde_json::to_value(&no_deps).unwrap()["dependency_mode"], "no-deps");
+    }
+
+    #[test]
+    fn test_dependents_match_however_spelled() {
+        let packages: Vec<Package> = ["zope.interface", "zope.component"]
+            .iter()
+            .map(|name| Package::new(name.to_string(), "1.0".to_string()))
+            .collect();
+        let edges = HashMap::from([("Zope_Component".to_string(), vec!["zope-interface".to_string()])]);
+        let mut batch = BatchInstall::new(&packages, &edges);
+        let order = batch.install_order();
+        assert_eq!(order[0].name, "zope.interface");
+        let error = PipError::InstallationFailed {
+            package: "zope.interface".to_string(),
+            reason: "boom".to_string(),
+        };
+        batch.record_failed(&order[0], &error);
+        assert_eq!(batch.blocked_by("zope.component").as_deref(), Some("zope.interface"));
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        assert_eq!(run(true, "none").exit_code(), 0);
+        // Without --keep-going the batch stops at the first failure
+        let aborted = run(false, "lxml");
+        assert!(aborted.skipped.is_empty());
+        assert_eq!(aborted.installed.len(), 1);
+        // Nothing installed at all is a total failure
+        let mut batch = BatchInstall::new(&graph().0[..1], &HashMap::new());
+        let app = batch.install_order().remove(0);
+        let error = PipError::InstallationFailed {
+            package: "app".to_string(),
+            reason: "boom".to_string(),
+        };
+        batch.record_failed(&app, &error);
+        assert_eq!(batch.into_report().exit_code(), 1);
+    }
+
+    #[test]
+    fn test_summary_line() {
+        let installed = |name: &str, version: &str| InstalledPackage {
+            name: name.to_string(),
+            version: version.to_string(),
+            size: 0,
+            metadata_index: None,
+            artifact_index: None,
+        };
+        let summary = InstallSummary {
+            downloads: 2,
+            downloaded_bytes: 48 * 1024 * 1024 + 200 * 1024,
+            download_ms: 6120,
+            ..Default::default()
+        };
+        let packages = [installed("foo", "1.0"), installed("bar", "2.3")];
+        assert_eq!(
+            summary.line(&packages).unwrap(),
+            "Successfully installed foo-1.0 bar-2.3 (downloaded 48.2 MiB in 6.1s)"
+        );
+        assert_eq!(InstallSummary::default().line(&packages[..1]).unwrap(), "Successfully installed foo-1.0");
+        assert_eq!(summary.line(&[]), None);
+    }
+}
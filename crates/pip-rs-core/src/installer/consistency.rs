@@ -0,0 +1,140 @@
+/// Whether installed distributions' requirements are met (`pip check`)
+///
+/// Every installed distribution's `Requires-Dist` entries that apply in the
+/// environment are checked against the other installed distributions: a
+/// dependency may be missing, or installed at a version outside the
+/// specifiers. Requirements only an extra pulls in aren't checked, since
+/// nothing records which extras were requested.
+use super::site_packages::PackageDetails;
+use crate::models::{Environment, Requirement};
+use crate::utils::names::canonicalize_name;
+use crate::utils::version;
+use std::collections::HashMap;
+use std::fmt;
+
+/// One unmet requirement of an installed distribution
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BrokenRequirement {
+    Missing {
+        package: String,
+        version: String,
+        /// The requirement, without its marker
+        requirement: String,
+    },
+    Incompatible {
+        package: String,
+        version: String,
+        requirement: String,
+        dependency: String,
+        installed: String,
+    },
+}
+
+impl BrokenRequirement {
+    /// The installed distribution whose requirement is unmet
+    pub fn package(&self) -> &str {
+        match self {
+            BrokenRequirement::Missing { package, .. } | BrokenRequirement::Incompatible { package, .. } => package,
+        }
+    }
+}
+
+impl fmt::Display for BrokenRequirement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BrokenRequirement::Missing { package, version, requirement } => {
+                write!(f, "{} {} requires {}, which is not installed.", package, version, requirement)
+            }
+            BrokenRequirement::Incompatible { package, version, requirement, dependency, installed } => write!(
+                f,
+                "{} {} requires {}, but you have {} {}.",
+                package, version, requirement, dependency, installed
+            ),
+        }
+    }
+}
+
+/// Unmet requirements of `installed`, by package and then in METADATA order
+pub fn broken_requirements(installed: &[PackageDetails], env: &Environment) -> Vec<BrokenRequirement> {
+    let by_name: HashMap<String, &PackageDetails> =
+        installed.iter().map(|details| (canonicalize_name(&details.name), details)).collect();
+    let mut packages: Vec<&PackageDetails> = installed.iter().collect();
+    packages.sort_by_key(|details| canonicalize_name(&details.name));
+
+    let mut broken = Vec::new();
+    for details in packages {
+        for requirement in details.requirements(env) {
+            let shown = without_marker(&requirement);
+            match by_name.get(&canonicalize_name(&requirement.name)) {
+                None => broken.push(BrokenRequirement::Missing {
+                    package: details.name.clone(),
+                    version: details.version.clone(),
+                    requirement: shown,
+                }),
+                Some(dependency) if !requirement.specs.iter().all(|spec| version::satisfies(&dependency.version, spec)) => {
+                    broken.push(BrokenRequirement::Incompatible {
+                        package: details.name.clone(),
+                        version: details.version.clone(),
+                        requirement: shown,
+                        dependency: dependency.name.clone(),
+                        installed: dependency.version.clone(),
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+    }
+    broken
+}
+
+fn without_marker(requirement: &Requirement) -> String {
+    Requirement {
+        marker: None,
+        ..requirement.clone()
+    }
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::installer::SitePackages;
+    use std::fs;
+
+    #[test]
+    fn test_missing_and_incompatible_requirements() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |dist_info: &str, metadata: &str| {
+            fs::create_dir_all(dir.path().join(dist_info)).unwrap();
+            fs::write(dir.path().join(dist_info).join("METADATA"), metadata).unwrap();
+        };
+        write(
+            "requests-2.31.0.dist-info",
+            "Name: requests\nVersion: 2.31.0\nRequires-Dist: charset-normalizer (<4,>=2)\n\
+             Requires-Dist: idna<4,>=2.5\nRequires-Dist: urllib3<3,>=1.21.1\n\
+             Requires-Dist: PySocks!=1.5.7,>=1.5.6 ; extra == \"socks\"\n\
+             Requires-Dist: pywin32 ; sys_platform == \"never\"\n",
+        );
+        write("charset_normalizer-4.0.1.dist-info", "Name: charset-normalizer\nVersion: 4.0.1\n");
+        write("urllib3-2.2.1.dist-info", "Name: urllib3\nVersion: 2.2.1\n");
+
+        let site_packages = SitePackages::new(dir.path().to_path_buf()).unwrap();
+        let installed = site_packages.all_package_details().unwrap();
+        let lines: Vec<String> = broken_requirements(&installed, &Environment::current())
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "requests 2.31.0 requires charset-normalizer<4,>=2, but you have charset-normalizer 4.0.1.",
+                "requests 2.31.0 requires idna<4,>=2.5, which is not installed.",
+            ]
+        );
+
+        write("idna-3.7.dist-info", "Name: idna\nVersion: 3.7\n");
+        write("charset_normalizer-4.0.1.dist-info", "Name: charset-normalizer\nVersion: 3.3.2\n");
+        let installed = site_packages.all_package_details().unwrap();
+        assert!(broken_requirements(&installed, &Environment::current()).is_empty());
+    }
+}
@@ -1,4 +1,5 @@
 /// Editable package installation support
+use crate::resolver::direct_url::{DIRECT_URL_FILE, DirInfo, DirectUrlJson};
 use anyhow::{Result, anyhow};
 use std::path::PathBuf;
 use std::fs;
@@ -83,13 +84,26 @@ impl EditableInstall {
                      Tag: py3-none-any\n";
         fs::write(dist_info.join("WHEEL"), wheel)?;
 
+        // Record the project directory (PEP 610) so freeze emits `-e <path>`
+        let direct_url = DirectUrlJson {
+            url: format!("file://{}", self.project_path.display()),
+            subdirectory: None,
+            vcs_info: None,
+            archive_info: None,
+            dir_info: Some(DirInfo { editable: true }),
+        };
+        direct_url.write(&dist_info)?;
+
         // Create RECORD file
         let record = format!(
             "{}/METADATA,,\n\
              {}/WHEEL,,\n\
+             {}/{},,\n\
              {}/RECORD,,\n",
             dist_info.display(),
             dist_info.display(),
+            dist_info.display(),
+            DIRECT_URL_FILE,
             dist_info.display()
         );
         fs::write(dist_info.join("RECORD"), record)?;
@@ -147,6 +161,10 @@ mod tests {
             .collect();
 
         assert!(!pth_files.is_empty());
+
+        let direct_url = DirectUrlJson::read(&site_packages.join("project.dist-info")).unwrap();
+        assert!(direct_url.is_editable());
+        assert!(direct_url.url.starts_with("file://") && direct_url.url.ends_with("project"));
         Ok(())
     }
 }
@@ -1,14 +1,113 @@
 /// Entry point generation for console scripts
+///
+/// Scripts are rendered from the versioned template in
+/// `data/console-script.py`. The import of the entry point is guarded: if
+/// the owning package has since been uninstalled, the script explains what
+/// is missing and how to reinstall it instead of dying with a bare
+/// `ModuleNotFoundError`. A header line records the entry point and its
+/// package so orphaned scripts can be found later.
+///
+/// When the interpreter is known, posix scripts name it directly; paths
+/// the kernel can't take in a `#!` line (spaces, non-ASCII, too long) get
+/// pip's `/bin/sh` re-exec wrapper instead. Windows scripts always go
+/// through the launcher, so none of this applies there: a script is
+/// installed as `<name>-script.py` next to a `<name>.cmd` that runs it.
+///
+/// Scripts go to the `bin` (`Scripts`) directory of the environment that
+/// owns site-packages, see [`scripts_target`].
 use anyhow::Result;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::fs;
 
+/// Version of the console script template, recorded in every script
+pub const SCRIPT_TEMPLATE_VERSION: u32 = 1;
+
+const SCRIPT_TEMPLATE: &str = include_str!("../../data/console-script.py");
+
+/// Header line identifying a script generated by pip-rs
+const HEADER_PREFIX: &str = "# pip-rs-entry-point: ";
+
+/// Longest `#!` line (newline included) that every kernel we target reads
+/// whole; older Linux kernels cut at 128 bytes, macOS allows 512
+pub const MAX_SHEBANG_LENGTH: usize = if cfg!(target_os = "macos") { 512 } else { 127 };
+
+/// Start of the line the sh wrapper re-execs the interpreter from
+const SH_EXEC_PREFIX: &str = "'''exec' ";
+
+/// First line(s) of a posix script, starting its interpreter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Launcher {
+    /// `#!/path/to/python`
+    Shebang(String),
+    /// `#!/bin/sh` whose next line re-execs the interpreter; the shell
+    /// sees an `exec`, Python sees a string literal
+    ShExec(String),
+}
+
+impl Launcher {
+    /// Pick how to start `interpreter`, given the platform's `#!` limit
+    pub fn for_interpreter(interpreter: &str, max_length: usize) -> Self {
+        let shebang_length = "#!".len() + interpreter.len() + "\n".len();
+        let unsafe_char = |c: char| !c.is_ascii() || c.is_ascii_whitespace() || c.is_ascii_control();
+        if interpreter.chars().any(unsafe_char) || shebang_length > max_length {
+            Launcher::ShExec(interpreter.to_string())
+        } else {
+            Launcher::Shebang(interpreter.to_string())
+        }
+    }
+
+    /// The script lines this launcher contributes, without a final newline
+    pub fn header(&self) -> String {
+        match self {
+            Launcher::Shebang(interpreter) => format!("#!{}", interpreter),
+            Launcher::ShExec(interpreter) => format!(
+                "#!/bin/sh\n{}{} \"$0\" \"$@\"\n' '''",
+                SH_EXEC_PREFIX,
+                sh_quote(interpreter)
+            ),
+        }
+    }
+}
+
+/// Single-quoted for `sh`
+fn sh_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// A `.data/scripts` file with its `#!python` (or `#!pythonw`) line pointed
+/// at `interpreter`, `python` on PATH when unknown; `None` if it has no
+/// such line and is copied as is
+pub fn rewrite_shebang(content: &[u8], interpreter: Option<&Path>) -> Option<Vec<u8>> {
+    let line_end = content.iter().position(|&b| b == b'\n').unwrap_or(content.len());
+    let first = content[..line_end].strip_suffix(b"\r").unwrap_or(&content[..line_end]);
+    let args = first.strip_prefix(b"#!pythonw").or_else(|| first.strip_prefix(b"#!python"))?;
+    if args.first().is_some_and(|b| !b.is_ascii_whitespace()) {
+        return None;
+    }
+    let header = match interpreter {
+        Some(interpreter) => Launcher::for_interpreter(&interpreter.to_string_lossy(), MAX_SHEBANG_LENGTH).header(),
+        None => "#!/usr/bin/env python".to_string(),
+    };
+    let mut rewritten = header.into_bytes();
+    // Arguments survive a plain shebang; the sh wrapper has no room for them
+    if !rewritten.starts_with(b"#!/bin/sh") {
+        rewritten.extend_from_slice(args);
+    }
+    rewritten.extend_from_slice(&content[first.len()..]);
+    Some(rewritten)
+}
+
 #[derive(Debug, Clone)]
-#[allow(dead_code)]
 pub struct EntryPoint {
     pub name: String,
     pub module: String,
     pub function: String,
+    /// Distribution providing the entry point (defaults to the top-level module)
+    pub package: Option<String>,
+    /// site-packages the script imports from, named in the missing-package message
+    pub site_packages: Option<PathBuf>,
+    /// Interpreter posix scripts start; `/usr/bin/env python` when unknown
+    pub interpreter: Option<PathBuf>,
 }
 
 impl EntryPoint {
@@ -17,63 +116,249 @@ impl EntryPoint {
             name,
             module,
             function,
+            package: None,
+            site_packages: None,
+            interpreter: None,
         }
     }
 
+    /// Record the distribution that owns this entry point and where it lives
+    pub fn with_owner(mut self, package: &str, site_packages: &Path) -> Self {
+        self.package = Some(package.to_string());
+        self.site_packages = Some(site_packages.to_path_buf());
+        self
+    }
+
+    /// Start posix scripts with this interpreter rather than `python` on PATH
+    pub fn with_interpreter(mut self, interpreter: &Path) -> Self {
+        self.interpreter = Some(interpreter.to_path_buf());
+        self
+    }
+
+    fn package_name(&self) -> &str {
+        self.package
+            .as_deref()
+            .unwrap_or_else(|| self.module.split('.').next().unwrap_or(&self.module))
+    }
+
     /// Generate a console script wrapper
     pub fn generate_script(&self) -> String {
-        if cfg!(target_os = "windows") {
-            self.generate_windows_script()
-        } else {
-            self.generate_unix_script()
-        }
+        self.render(cfg!(target_os = "windows"))
     }
 
-    fn generate_unix_script(&self) -> String {
-        format!(
-            "#!/usr/bin/env python\n\
-             import sys\n\
-             from {} import {}\n\
-             if __name__ == '__main__':\n\
-             \tsys.exit({}())\n",
-            self.module, self.function, self.function
-        )
+    /// Render the script template for a posix or windows launcher
+    pub fn render(&self, windows: bool) -> String {
+        let package = self.package_name();
+        let location = self
+            .site_packages
+            .as_ref()
+            .map(|p| p.display().to_string())
+            .unwrap_or_else(|| "site-packages".to_string());
+        let message = format!(
+            "The '{}' entry point is installed but its package '{}' is missing from this environment \
+             (expected at {}); reinstall with: pip install {}",
+            self.name, package, location, package
+        );
+        let shebang = match (&self.interpreter, windows) {
+            (_, true) => "#!python".to_string(),
+            (None, false) => "#!/usr/bin/env python".to_string(),
+            (Some(interpreter), false) => {
+                Launcher::for_interpreter(&interpreter.to_string_lossy(), MAX_SHEBANG_LENGTH).header()
+            }
+        };
+
+        SCRIPT_TEMPLATE
+            .replace("{{shebang}}", &shebang)
+            .replace("{{template_version}}", &SCRIPT_TEMPLATE_VERSION.to_string())
+            .replace("{{name}}", &self.name)
+            .replace("{{module_literal}}", &python_literal(&self.module))
+            .replace("{{message_literal}}", &python_literal(&message))
+            .replace("{{module}}", &self.module)
+            .replace("{{function}}", &self.function)
+            .replace("{{package}}", package)
     }
 
-    fn generate_windows_script(&self) -> String {
-        format!(
-            "#!python\n\
-             # -*- coding: utf-8 -*-\n\
-             import re\n\
-             import sys\n\
-             from {} import {}\n\
-             if __name__ == '__main__':\n\
-             \tsys.exit({}())\n",
-            self.module, self.function, self.function
-        )
+    /// `.cmd` launcher running the `-script.py` beside it
+    fn render_cmd(&self) -> String {
+        let python = self
+            .interpreter
+            .as_ref()
+            .map(|p| p.display().to_string())
+            .unwrap_or_else(|| "python".to_string());
+        format!("@echo off\r\n\"{}\" \"%~dp0{}-script.py\" %*\r\n", python, self.name)
     }
 
-    pub fn install(&self, scripts_dir: &PathBuf) -> Result<PathBuf> {
+    /// The files this entry point is installed as, with their contents, in
+    /// the order of [`script_files`]
+    pub fn files(&self, windows: bool) -> Vec<(String, String)> {
+        let names = script_files(&self.name, windows);
+        let mut contents = vec![self.render(windows)];
+        if windows {
+            contents.push(self.render_cmd());
+        }
+        names.into_iter().zip(contents).collect()
+    }
+
+    /// Write the script(s) into `scripts_dir`, returning the paths written
+    pub fn install(&self, scripts_dir: &Path) -> Result<Vec<PathBuf>> {
         fs::create_dir_all(scripts_dir)?;
-        
-        let script_path = if cfg!(target_os = "windows") {
-            scripts_dir.join(format!("{}.exe", self.name))
-        } else {
-            scripts_dir.join(&self.name)
+
+        let mut written = Vec::new();
+        for (file_name, content) in self.files(cfg!(target_os = "windows")) {
+            let script_path = scripts_dir.join(file_name);
+            super::replace::write_file(&script_path, content.as_bytes())?;
+
+            // Make executable on Unix
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                let perms = fs::Permissions::from_mode(0o755);
+                fs::set_permissions(&script_path, perms)?;
+            }
+            written.push(script_path);
+        }
+
+        Ok(written)
+    }
+}
+
+/// File names a console script called `name` is installed as; the first
+/// holds the script itself
+pub fn script_files(name: &str, windows: bool) -> Vec<String> {
+    if windows {
+        vec![format!("{}-script.py", name), format!("{}.cmd", name)]
+    } else {
+        vec![name.to_string()]
+    }
+}
+
+/// Double-quoted Python string literal
+fn python_literal(value: &str) -> String {
+    let escaped = value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+    format!("\"{}\"", escaped)
+}
+
+/// `[console_scripts]` entries of an `entry_points.txt`
+pub fn console_scripts(entry_points_txt: &str) -> Vec<EntryPoint> {
+    let mut in_section = false;
+    let mut entry_points = Vec::new();
+    for line in entry_points_txt.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_section = line == "[console_scripts]";
+            continue;
+        }
+        if !in_section || line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((name, target)) = line.split_once('=') else {
+            continue;
         };
+        // `module:function [extra]` -- extras don't change the script
+        let target = target.split('[').next().unwrap_or(target).trim();
+        if let Some((module, function)) = target.split_once(':') {
+            entry_points.push(EntryPoint::new(
+                name.trim().to_string(),
+                module.trim().to_string(),
+                function.trim().to_string(),
+            ));
+        }
+    }
+    entry_points
+}
 
-        let script_content = self.generate_script();
-        fs::write(&script_path, script_content)?;
+/// What a script says about the code it runs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScriptOwner {
+    /// Generated by pip-rs: the recorded owning distribution
+    PipRs { package: String, module: String },
+    /// Another installer's wrapper: only the imported module is known
+    Foreign { module: String },
+}
 
-        // Make executable on Unix
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            let perms = fs::Permissions::from_mode(0o755);
-            fs::set_permissions(&script_path, perms)?;
+/// Inspect a Python console script; `None` if it isn't one
+pub fn script_owner(content: &str) -> Option<ScriptOwner> {
+    let mut lines = content.lines();
+    let mut first = lines.next()?;
+    if first == "#!/bin/sh" {
+        // The sh wrapper names the interpreter on the next line
+        first = lines.next().filter(|line| line.starts_with(SH_EXEC_PREFIX))?;
+    } else if !first.starts_with("#!") {
+        return None;
+    }
+    if !first.contains("python") {
+        return None;
+    }
+    for line in content.lines() {
+        if let Some(header) = line.strip_prefix(HEADER_PREFIX) {
+            // `name = module:function [package]`
+            let (_, target) = header.split_once(" = ")?;
+            let (module, _) = target.split_once(':')?;
+            let package = header.rsplit_once('[')?.1.trim_end_matches(']');
+            return Some(ScriptOwner::PipRs {
+                package: package.to_string(),
+                module: module.to_string(),
+            });
         }
+    }
+    content
+        .lines()
+        .filter_map(|line| line.strip_prefix("from ")?.split_once(" import "))
+        .map(|(module, _)| ScriptOwner::Foreign {
+            module: module.trim().to_string(),
+        })
+        .next()
+}
+
+/// Where the console scripts of an environment go, and what they run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptsTarget {
+    pub dir: PathBuf,
+    /// Interpreter the scripts start; `None` leaves it to `python` on PATH
+    pub interpreter: Option<PathBuf>,
+}
 
-        Ok(script_path)
+/// Scripts location for packages installed into `site_packages`: `bin`
+/// (`Scripts` on Windows) of its environment prefix (see
+/// [`scheme::environment_prefix`](super::scheme::environment_prefix)), else
+/// -- a `--target` directory -- `bin` inside site-packages itself. Scripts
+/// start the interpreter given with `--python`, else the one found there;
+/// for a target directory, that of the active virtualenv.
+pub fn scripts_target(site_packages: &Path, virtual_env: Option<&Path>) -> ScriptsTarget {
+    let selected = crate::utils::python_env::selected().map(|python| python.executable.clone());
+    let bin = |prefix: &Path| prefix.join(if cfg!(target_os = "windows") { "Scripts" } else { "bin" });
+    let python_in = |bin: &Path| {
+        let names: &[&str] = if cfg!(target_os = "windows") { &["python.exe"] } else { &["python", "python3"] };
+        names.iter().map(|name| bin.join(name)).find(|python| python.exists())
+    };
+
+    match super::scheme::environment_prefix(site_packages, virtual_env) {
+        Some(prefix) => {
+            let dir = bin(&prefix);
+            ScriptsTarget {
+                interpreter: selected.or_else(|| python_in(&dir)),
+                dir,
+            }
+        }
+        None => ScriptsTarget {
+            dir: site_packages.join("bin"),
+            interpreter: selected.or_else(|| virtual_env.and_then(|venv| python_in(&bin(venv)))),
+        },
+    }
+}
+
+/// Directory console scripts were installed to before they went to the
+/// environment's own `bin`; still searched when uninstalling
+pub fn scripts_dir() -> PathBuf {
+    if cfg!(target_os = "windows") {
+        // On Windows, use Scripts directory
+        std::env::var("USERPROFILE")
+            .map(|home| PathBuf::from(home).join("Scripts"))
+            .unwrap_or_else(|_| PathBuf::from("Scripts"))
+    } else {
+        // On Unix-like systems, use ~/.local/bin
+        std::env::var("HOME")
+            .map(|home| PathBuf::from(home).join(".local").join("bin"))
+            .unwrap_or_else(|_| PathBuf::from("/usr/local/bin"))
     }
 }
 
@@ -103,4 +388,149 @@ mod tests {
         assert!(script.contains("pip._internal.cli.main"));
         assert!(script.contains("main()"));
     }
+
+    #[test]
+    fn test_render_posix_and_windows_templates() {
+        let ep = EntryPoint::new("black".to_string(), "black".to_string(), "patched_main".to_string())
+            .with_owner("black", Path::new("C:\\venv\\Lib\\site-packages"));
+
+        let posix = ep.render(false);
+        assert!(posix.starts_with("#!/usr/bin/env python\n"));
+        assert!(posix.contains(&format!("template v{}", SCRIPT_TEMPLATE_VERSION)));
+        assert!(posix.contains("    from black import patched_main\n"));
+        assert!(posix.contains("except ModuleNotFoundError as exc:"));
+        assert!(posix.contains(
+            "\"The 'black' entry point is installed but its package 'black' is missing from this environment \
+             (expected at C:\\\\venv\\\\Lib\\\\site-packages); reinstall with: pip install black\""
+        ));
+        assert!(posix.ends_with("    sys.exit(patched_main())\n"));
+        assert!(!posix.contains("{{"));
+
+        let windows = ep.render(true);
+        assert!(windows.starts_with("#!python\n"));
+        assert_eq!(windows.lines().skip(1).collect::<Vec<_>>(), posix.lines().skip(1).collect::<Vec<_>>());
+
+        assert_eq!(
+            script_owner(&posix),
+            Some(ScriptOwner::PipRs {
+                package: "black".to_string(),
+                module: "black".to_string()
+            })
+        );
+        let pip_style = "#!/usr/bin/python3\nimport re\nimport sys\nfrom httpie.core import main\n";
+        assert_eq!(
+            script_owner(pip_style),
+            Some(ScriptOwner::Foreign {
+                module: "httpie.core".to_string()
+            })
+        );
+        assert_eq!(script_owner("#!/bin/sh\nexec foo\n"), None);
+    }
+
+    #[test]
+    fn test_launcher_choice_over_interpreter_paths() {
+        let long = format!("/opt/{}/bin/python3", "x".repeat(120));
+        let cases = [
+            ("/home/user/venv/bin/python", false),
+            ("/home/user/my venv/bin/python", true),
+            ("/home/user/venv\tx/bin/python", true),
+            ("/home/josé/venv/bin/python", true),
+            ("/srv/你好/bin/python", true),
+            (long.as_str(), true),
+        ];
+        for (interpreter, wrapped) in cases {
+            let launcher = Launcher::for_interpreter(interpreter, 127);
+            assert_eq!(matches!(launcher, Launcher::ShExec(_)), wrapped, "{}", interpreter);
+        }
+
+        // The limit counts "#!" and the newline
+        let fits = format!("/{}", "p".repeat(123));
+        assert_eq!(Launcher::for_interpreter(&fits, 127), Launcher::Shebang(fits.clone()));
+        let over = format!("{}p", fits);
+        assert_eq!(Launcher::for_interpreter(&over, 127), Launcher::ShExec(over.clone()));
+        assert_eq!(Launcher::for_interpreter(&over, 512), Launcher::Shebang(over));
+
+        assert_eq!(
+            Launcher::ShExec("/it's here/python".to_string()).header(),
+            "#!/bin/sh\n'''exec' '/it'\\''s here/python' \"$0\" \"$@\"\n' '''"
+        );
+    }
+
+    #[test]
+    fn test_wrapped_script_keeps_owner_and_windows_launcher() {
+        let ep = EntryPoint::new("black".to_string(), "black".to_string(), "main".to_string())
+            .with_owner("black", Path::new("/home/my venv/lib/site-packages"))
+            .with_interpreter(Path::new("/home/my venv/bin/python"));
+
+        let posix = ep.render(false);
+        assert!(posix.starts_with("#!/bin/sh\n'''exec' '/home/my venv/bin/python' \"$0\" \"$@\"\n' '''\n"));
+        assert_eq!(
+            script_owner(&posix),
+            Some(ScriptOwner::PipRs {
+                package: "black".to_string(),
+                module: "black".to_string()
+            })
+        );
+        assert!(ep.render(true).starts_with("#!python\n"));
+
+        let plain = ep.with_interpreter(Path::new("/venv/bin/python"));
+        assert!(plain.render(false).starts_with("#!/venv/bin/python\n"));
+    }
+
+    #[test]
+    fn test_windows_files_and_scripts_target() {
+        let ep = EntryPoint::new("black".to_string(), "black".to_string(), "main".to_string())
+            .with_interpreter(Path::new("C:\\venv\\Scripts\\python.exe"));
+        let files = ep.files(true);
+        assert_eq!(files.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>(), ["black-script.py", "black.cmd"]);
+        assert!(files[0].1.starts_with("#!python\n"));
+        assert_eq!(files[1].1, "@echo off\r\n\"C:\\venv\\Scripts\\python.exe\" \"%~dp0black-script.py\" %*\r\n");
+        assert_eq!(ep.files(false).len(), 1);
+
+        let temp = tempfile::tempdir().unwrap();
+        let bin = if cfg!(target_os = "windows") { "Scripts" } else { "bin" };
+        let venv = temp.path().join("venv");
+        let site = venv.join("lib/python3.12/site-packages");
+        fs::create_dir_all(&site).unwrap();
+        fs::write(venv.join("pyvenv.cfg"), "home = /usr/bin\n").unwrap();
+        let target = scripts_target(&site, None);
+        assert_eq!(target.dir, venv.canonicalize().unwrap().join(bin));
+        assert_eq!(target.interpreter, None);
+
+        let prefix = temp.path().join("usr/local");
+        assert_eq!(scripts_target(&prefix.join("lib64/python3.11/site-packages"), None).dir, prefix.join(bin));
+
+        // A --target directory keeps its scripts inside and runs the active venv's python
+        let python = venv.join(bin).join(if cfg!(target_os = "windows") { "python.exe" } else { "python" });
+        fs::create_dir_all(python.parent().unwrap()).unwrap();
+        fs::write(&python, "").unwrap();
+        let vendored = temp.path().join("vendored");
+        let target = scripts_target(&vendored, Some(&venv));
+        assert_eq!(target.dir, vendored.join("bin"));
+        assert_eq!(target.interpreter, Some(python));
+    }
+
+    #[test]
+    fn test_rewrite_shebang_of_data_scripts() {
+        let script = b"#!python -E\nprint('hi')\n";
+        assert_eq!(
+            rewrite_shebang(script, Some(Path::new("/venv/bin/python"))).unwrap(),
+            b"#!/venv/bin/python -E\nprint('hi')\n"
+        );
+        assert_eq!(rewrite_shebang(b"#!pythonw\r\nx", None).unwrap(), b"#!/usr/bin/env python\r\nx");
+        let wrapped = rewrite_shebang(script, Some(Path::new("/my venv/bin/python"))).unwrap();
+        assert!(wrapped.starts_with(b"#!/bin/sh\n'''exec' '/my venv/bin/python'"));
+        assert_eq!(rewrite_shebang(b"#!/bin/sh\necho\n", None), None);
+        assert_eq!(rewrite_shebang(b"#!python3.12\n", None), None);
+    }
+
+    #[test]
+    fn test_console_scripts_section() {
+        let eps = console_scripts(
+            "[console_scripts]\nblack = black:patched_main\nblackd = blackd:patched_main [d]\n\n[gui_scripts]\nui = x:y\n",
+        );
+        let names: Vec<(&str, &str, &str)> =
+            eps.iter().map(|e| (e.name.as_str(), e.module.as_str(), e.function.as_str())).collect();
+        assert_eq!(names, vec![("black", "black", "patched_main"), ("blackd", "blackd", "patched_main")]);
+    }
 }
@@ -0,0 +1,263 @@
+/// Exclusive access to a site-packages directory while it is changed
+///
+/// Two processes installing into one environment at once (parallel CI steps
+/// sharing a venv) interleave their writes and can leave half a dist-info
+/// behind. Every install, uninstall and upgrade therefore holds an OS lock
+/// (flock, LockFileEx) on `.pip-rs.lock` in site-packages while it changes
+/// files, and waits up to `lock-timeout` seconds for another holder to let
+/// go. The OS drops the lock when its process exits, however it exits; the
+/// file itself stays and records the pid and start time of the last holder
+/// for the message a waiting process shows. Commands that only read
+/// metadata (list, show, freeze) never take it.
+///
+/// The lock is reentrant within a process: a command holding it for its
+/// whole mutation phase can call installer code that takes it again.
+use crate::config::config::Config;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Lock file kept in each site-packages pip-rs changes
+pub const LOCK_FILE: &str = ".pip-rs.lock";
+
+/// How long to wait for another process when `lock-timeout` isn't set
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// A held lock whose recorded holder is gone and that hasn't been touched
+/// for this long is taken to be left over (e.g. on a network filesystem
+/// that lost the holder) and is removed
+pub const STALE_AFTER: Duration = Duration::from_secs(60 * 60);
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+static TIMEOUT: Lazy<Duration> = Lazy::new(|| Config::global().lock_timeout());
+
+/// Locks this process holds, by site-packages path
+static HELD: Lazy<Mutex<HashMap<PathBuf, Weak<Held>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// The process that holds (or last held) a lock
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Holder {
+    pub pid: u32,
+    /// RFC 3339
+    pub started: String,
+}
+
+impl std::fmt::Display for Holder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "pid {}, started {}", self.pid, self.started)
+    }
+}
+
+/// Another process kept the lock past the timeout
+#[derive(Debug)]
+pub struct Contended {
+    pub site_packages: PathBuf,
+    /// Unknown when the lock file can't be read while held (Windows)
+    pub holder: Option<Holder>,
+    pub waited: Duration,
+}
+
+impl std::fmt::Display for Contended {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "another pip-rs process is installing into this environment")?;
+        if let Some(holder) = &self.holder {
+            write!(f, " ({})", holder)?;
+        }
+        write!(f, "; gave up after {}s waiting for {}", self.waited.as_secs(), self.site_packages.display())
+    }
+}
+
+impl std::error::Error for Contended {}
+
+struct Held {
+    file: File,
+    path: PathBuf,
+}
+
+impl Drop for Held {
+    fn drop(&mut self) {
+        // Closing the file releases the lock too; unlocking first keeps a
+        // waiter from seeing the next holder's half-written record
+        let _ = self.file.set_len(0);
+        let _ = self.file.unlock();
+        tracing::debug!("Released {}", self.path.display());
+    }
+}
+
+/// Exclusive access to one site-packages; released when the last clone is dropped
+#[derive(Clone)]
+pub struct EnvironmentLock {
+    _held: Arc<Held>,
+}
+
+impl EnvironmentLock {
+    /// Lock `site_packages`, waiting up to the configured `lock-timeout`
+    pub fn acquire(site_packages: &Path) -> anyhow::Result<Self> {
+        Self::acquire_within(site_packages, *TIMEOUT)
+    }
+
+    /// Lock `site_packages`, waiting up to `timeout` for another process
+    pub fn acquire_within(site_packages: &Path, timeout: Duration) -> anyhow::Result<Self> {
+        let key = site_packages.canonicalize().unwrap_or_else(|_| site_packages.to_path_buf());
+        let mut held = HELD.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(existing) = held.get(&key).and_then(Weak::upgrade) {
+            return Ok(Self { _held: existing });
+        }
+        let lock = Arc::new(lock_file(&key.join(LOCK_FILE), &key, timeout)?);
+        held.insert(key, Arc::downgrade(&lock));
+        Ok(Self { _held: lock })
+    }
+}
+
+fn lock_file(path: &Path, site_packages: &Path, timeout: Duration) -> anyhow::Result<Held> {
+    let started = Instant::now();
+    let mut announced = false;
+    loop {
+        let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path)?;
+        match file.try_lock() {
+            Ok(()) => {
+                // Removed as stale by another process while we opened it: lock the new file
+                if !same_file(&file, path) {
+                    continue;
+                }
+                let holder = Holder {
+                    pid: std::process::id(),
+                    started: chrono::Local::now().to_rfc3339(),
+                };
+                file.set_len(0)?;
+                file.write_all(serde_json::to_string(&holder)?.as_bytes())?;
+                file.flush()?;
+                tracing::debug!("Locked {}", path.display());
+                return Ok(Held {
+                    file,
+                    path: path.to_path_buf(),
+                });
+            }
+            Err(TryLockError::WouldBlock) => {}
+            Err(TryLockError::Error(e)) => return Err(e.into()),
+        }
+
+        let holder = read_holder(path);
+        if is_stale(path, holder.as_ref()) {
+            tracing::warn!("Removing stale lock {} ({:?})", path.display(), holder);
+            let _ = fs::remove_file(path);
+            continue;
+        }
+        let waited = started.elapsed();
+        if waited >= timeout {
+            return Err(Contended {
+                site_packages: site_packages.to_path_buf(),
+                holder,
+                waited,
+            }
+            .into());
+        }
+        if !announced {
+            announced = true;
+            let by = holder.map(|holder| format!(" ({})", holder)).unwrap_or_default();
+            eprintln!(
+                "Waiting for another pip-rs process{} to finish with {}...",
+                by,
+                site_packages.display()
+            );
+        }
+        std::thread::sleep(POLL_INTERVAL.min(timeout - waited));
+    }
+}
+
+fn read_holder(path: &Path) -> Option<Holder> {
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+/// Held by a process that is gone, and untouched for [`STALE_AFTER`]
+fn is_stale(path: &Path, holder: Option<&Holder>) -> bool {
+    let Some(holder) = holder else {
+        return false;
+    };
+    let age = fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok());
+    age.is_some_and(|age| age >= STALE_AFTER) && !process_alive(holder.pid)
+}
+
+/// Whether `pid` runs on this machine; assumed so where that can't be told
+fn process_alive(pid: u32) -> bool {
+    if cfg!(target_os = "linux") {
+        Path::new("/proc").join(pid.to_string()).exists()
+    } else {
+        true
+    }
+}
+
+/// Whether `file` is still the one at `path`
+#[cfg(unix)]
+fn same_file(file: &File, path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (file.metadata(), fs::metadata(path)) {
+        (Ok(open), Ok(current)) => open.dev() == current.dev() && open.ino() == current.ino(),
+        _ => false,
+    }
+}
+
+/// Whether `file` is still the one at `path` (Windows can't remove an open file)
+#[cfg(not(unix))]
+fn same_file(_file: &File, _path: &Path) -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_is_exclusive_across_handles_and_reentrant_in_process() {
+        let temp = tempfile::tempdir().unwrap();
+        let site = temp.path();
+        let lock = EnvironmentLock::acquire_within(site, Duration::ZERO).unwrap();
+        let again = EnvironmentLock::acquire_within(site, Duration::ZERO).unwrap();
+        let holder = read_holder(&site.join(LOCK_FILE)).unwrap();
+        assert_eq!(holder.pid, std::process::id());
+
+        // Another process sees it held, and is told by whom
+        let err = lock_file(&site.join(LOCK_FILE), site, Duration::ZERO).err().unwrap();
+        let contended = err.downcast_ref::<Contended>().unwrap();
+        assert_eq!(contended.holder.as_ref(), Some(&holder));
+        assert!(err.to_string().starts_with(&format!(
+            "another pip-rs process is installing into this environment (pid {}, started",
+            holder.pid
+        )));
+
+        drop(lock);
+        assert!(lock_file(&site.join(LOCK_FILE), site, Duration::ZERO).is_err());
+        drop(again);
+        assert!(lock_file(&site.join(LOCK_FILE), site, Duration::ZERO).is_ok());
+    }
+
+    #[test]
+    fn test_stale_needs_a_dead_holder_and_an_old_file() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join(LOCK_FILE);
+        fs::write(&path, "").unwrap();
+        let alive = Holder {
+            pid: std::process::id(),
+            started: String::new(),
+        };
+        assert!(!is_stale(&path, Some(&alive)));
+        assert!(!is_stale(&path, None));
+        if cfg!(target_os = "linux") {
+            let gone = Holder { pid: u32::MAX, ..alive };
+            // Recent: the holder may just be in another pid namespace
+            assert!(!is_stale(&path, Some(&gone)));
+            let old = SystemTime::now() - STALE_AFTER - Duration::from_secs(1);
+            File::options().write(true).open(&path).unwrap().set_modified(old).unwrap();
+            assert!(is_stale(&path, Some(&gone)));
+        }
+    }
+}
@@ -0,0 +1,65 @@
+/// Why a wheel couldn't be installed
+///
+/// [`PackageInstaller::install_wheel`](super::PackageInstaller::install_wheel)
+/// returns an [`InstallError`] naming the package and the environment it
+/// was going into, so a refused write into site-packages is told apart from
+/// a broken wheel or a file another package owns.
+use serde::Serialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum InstallErrorKind {
+    /// The OS refused a write into the environment
+    PermissionDenied,
+    /// Another process kept the environment locked
+    Locked,
+    /// The wheel itself is unusable: not a zip, or no readable METADATA
+    InvalidWheel,
+    /// A file belongs to another package or is mapped by a running process
+    Conflict,
+    /// Ctrl-C or SIGTERM before the install was committed
+    Cancelled,
+    /// Any other filesystem failure
+    Io,
+}
+
+#[derive(Debug)]
+pub struct InstallError {
+    pub kind: InstallErrorKind,
+    pub package: String,
+    /// The site-packages (or `--target` directory) written to
+    pub path: PathBuf,
+    message: String,
+}
+
+impl InstallError {
+    pub fn new(kind: InstallErrorKind, package: &str, path: &Path, message: impl fmt::Display) -> Self {
+        Self {
+            kind,
+            package: package.to_string(),
+            path: path.to_path_buf(),
+            message: message.to_string(),
+        }
+    }
+
+    /// A failure of `kind`, unless the OS refused access somewhere in
+    /// `error`'s chain: that is [`InstallErrorKind::PermissionDenied`]
+    pub fn with_cause(kind: InstallErrorKind, package: &str, path: &Path, error: anyhow::Error) -> Self {
+        let denied = error
+            .chain()
+            .filter_map(|cause| cause.downcast_ref::<std::io::Error>())
+            .any(|cause| cause.kind() == std::io::ErrorKind::PermissionDenied);
+        let kind = if denied { InstallErrorKind::PermissionDenied } else { kind };
+        Self::new(kind, package, path, error)
+    }
+}
+
+impl fmt::Display for InstallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for InstallError {}
@@ -0,0 +1,119 @@
+/// Install progress as a stream of structured events
+///
+/// The install pipeline reports what it is doing as [`InstallEvent`]s:
+/// resolution rounds, phase changes, per-package download sizes, installed
+/// and failed packages, warnings, and the final report. Library users call
+/// [`subscribe`] for a channel of them; the CLI forwards the same channel to
+/// `--progress-socket` as newline-delimited JSON. With no subscriber,
+/// [`emit`] costs a lock and nothing else.
+use super::batch::{FailedPackage, InstalledPackage, SkippedPackage};
+use crate::errors::Phase;
+use crate::utils::warnings::WarningCode;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::sync::Mutex;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel};
+
+/// Bumped whenever a field is removed or changes meaning
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum InstallEvent {
+    /// A resolution round finished
+    ResolutionProgress { round: usize, resolved: usize, pending: usize },
+    /// The pipeline moved on to `phase`, for one package or as a whole
+    Phase {
+        phase: Phase,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        package: Option<String>,
+    },
+    /// An artifact finished downloading, in `seconds` (a cached copy takes
+    /// next to none)
+    Downloaded { package: String, version: String, bytes: u64, seconds: f64 },
+    Installed(InstalledPackage),
+    Failed(FailedPackage),
+    Skipped(SkippedPackage),
+    Warning { code: WarningCode, message: String },
+    /// A cancel request was honoured; no further packages are started
+    Cancelled,
+    /// The final install report, as written by `--report`
+    Report { report: serde_json::Value },
+}
+
+/// An event with the fields every consumer needs to order and version it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EventEnvelope {
+    pub schema_version: u32,
+    /// 1 for the first event of the process, then consecutive
+    pub seq: u64,
+    #[serde(flatten)]
+    pub event: InstallEvent,
+}
+
+#[derive(Default)]
+struct Subscribers {
+    senders: Vec<UnboundedSender<EventEnvelope>>,
+    seq: u64,
+}
+
+static SUBSCRIBERS: Lazy<Mutex<Subscribers>> = Lazy::new(Default::default);
+
+/// Receive every event emitted from now on. Dropping the receiver
+/// unsubscribes.
+pub fn subscribe() -> UnboundedReceiver<EventEnvelope> {
+    let (sender, receiver) = unbounded_channel();
+    if let Ok(mut subscribers) = SUBSCRIBERS.lock() {
+        subscribers.senders.push(sender);
+    }
+    receiver
+}
+
+/// Deliver an event to all subscribers
+pub fn emit(event: InstallEvent) {
+    let Ok(mut subscribers) = SUBSCRIBERS.lock() else {
+        return;
+    };
+    if subscribers.senders.is_empty() {
+        return;
+    }
+    subscribers.seq += 1;
+    let envelope = EventEnvelope {
+        schema_version: SCHEMA_VERSION,
+        seq: subscribers.seq,
+        event,
+    };
+    subscribers.senders.retain(|sender| sender.send(envelope.clone()).is_ok());
+}
+
+/// Announce a phase change
+pub fn phase(phase: Phase, package: Option<&str>) {
+    emit(InstallEvent::Phase {
+        phase,
+        package: package.map(String::from),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_envelope_schema() {
+        let mut events = subscribe();
+        phase(Phase::Download, Some("events-test-pkg"));
+        let envelope = std::iter::from_fn(|| events.try_recv().ok())
+            .find(|envelope| matches!(&envelope.event, InstallEvent::Phase { package: Some(p), .. } if p == "events-test-pkg"))
+            .unwrap();
+        let json = serde_json::to_value(&envelope).unwrap();
+        assert_eq!(json["schema_version"], SCHEMA_VERSION);
+        assert_eq!(json["event"], "phase");
+        assert_eq!(json["phase"], "download");
+        assert!(json["seq"].as_u64().unwrap() >= 1);
+
+        // Dropped receivers are pruned on the next emit
+        drop(events);
+        emit(InstallEvent::Cancelled);
+        assert!(SUBSCRIBERS.lock().unwrap().senders.iter().all(|sender| !sender.is_closed()));
+    }
+}
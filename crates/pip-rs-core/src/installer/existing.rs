@@ -0,0 +1,211 @@
+/// Installed distributions an install is about to replace
+///
+/// Finds the dist-info of an already-installed project and removes it the
+/// way pip does: every file listed in its `RECORD`, then directories left
+/// empty (stray `__pycache__` included), then the dist-info itself. Run
+/// after the new version is in place, files it wrote at the same paths are
+/// kept, so only what it dropped goes away along with the old dist-info.
+use super::egg_link_handler::EggLinkHandler;
+use crate::utils::names::{canonicalize_name, split_dist_info};
+use anyhow::Result;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A distribution found in site-packages
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstalledDist {
+    pub name: String,
+    pub version: String,
+    pub dist_info: PathBuf,
+    /// Installed in development mode; its files belong to a source tree
+    pub editable: bool,
+}
+
+impl InstalledDist {
+    /// The installed distribution of `name`, if any
+    pub fn find(site_packages: &Path, name: &str) -> Option<Self> {
+        Self::find_all(site_packages, name).into_iter().next()
+    }
+
+    /// The installed distribution of `name` at exactly `version`
+    pub fn find_version(site_packages: &Path, name: &str, version: &str) -> Option<Self> {
+        Self::find_all(site_packages, name).into_iter().find(|dist| dist.version == version)
+    }
+
+    fn find_all(site_packages: &Path, name: &str) -> Vec<Self> {
+        let wanted = canonicalize_name(name);
+        let Ok(entries) = fs::read_dir(site_packages) else {
+            return Vec::new();
+        };
+        let mut found: Vec<Self> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let file_name = entry.file_name().to_string_lossy().to_string();
+                let (dist, version) = split_dist_info(&file_name)?;
+                if version.is_empty() || canonicalize_name(dist) != wanted {
+                    return None;
+                }
+                let dist_info = entry.path();
+                let editable = is_editable(&dist_info, site_packages, dist);
+                Some(Self {
+                    name: dist.to_string(),
+                    version: version.to_string(),
+                    dist_info,
+                    editable,
+                })
+            })
+            .collect();
+        found.sort_by(|a, b| a.dist_info.cmp(&b.dist_info));
+        found
+    }
+
+    /// Files the `RECORD` lists outside the dist-info itself
+    pub fn recorded_files(&self, site_packages: &Path) -> HashSet<PathBuf> {
+        let record = fs::read_to_string(self.dist_info.join("RECORD")).unwrap_or_default();
+        record
+            .lines()
+            .filter_map(record_path)
+            .map(|relative| site_packages.join(relative))
+            .filter(|path| !path.starts_with(&self.dist_info))
+            .collect()
+    }
+
+    /// Remove the distribution's files, except those in `keep` (what a
+    /// replacement version installed at the same paths), and its
+    /// dist-info. Returns how many recorded files were deleted.
+    pub fn remove(&self, site_packages: &Path, keep: &HashSet<PathBuf>) -> Result<usize> {
+        let mut removed = 0;
+        let mut parents = Vec::new();
+        for path in self.recorded_files(site_packages) {
+            if keep.contains(&path) || !path.is_file() {
+                continue;
+            }
+            fs::remove_file(&path)?;
+            removed += 1;
+            if let Some(parent) = path.parent() {
+                parents.push(parent.to_path_buf());
+            }
+        }
+        fs::remove_dir_all(&self.dist_info)?;
+
+        // Deepest first, so emptied package directories go before their parents
+        parents.sort_by_key(|p| std::cmp::Reverse(p.components().count()));
+        parents.dedup();
+        for dir in parents {
+            prune_empty(&dir, site_packages);
+        }
+        Ok(removed)
+    }
+}
+
+/// Path column of a `RECORD` line; quoted when it contains a comma
+fn record_path(line: &str) -> Option<&str> {
+    let path = match line.strip_prefix('"') {
+        Some(quoted) => quoted.split_once('"')?.0,
+        None => line.split(',').next()?,
+    };
+    (!path.is_empty()).then_some(path)
+}
+
+/// Remove `dir` and its ancestors below `root` while they hold nothing but
+/// bytecode caches
+pub(super) fn prune_empty(dir: &Path, root: &Path) {
+    let mut current = dir.to_path_buf();
+    while current.starts_with(root) && current != root {
+        let _ = fs::remove_dir_all(current.join("__pycache__"));
+        if fs::remove_dir(&current).is_err() {
+            break;
+        }
+        match current.parent() {
+            Some(parent) => current = parent.to_path_buf(),
+            None => break,
+        }
+    }
+}
+
+/// `direct_url.json` says so, or a setuptools `.egg-link` points at it
+fn is_editable(dist_info: &Path, site_packages: &Path, name: &str) -> bool {
+    let direct_url_editable = fs::read_to_string(dist_info.join("direct_url.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .is_some_and(|direct_url| direct_url["dir_info"]["editable"].as_bool() == Some(true));
+    direct_url_editable || EggLinkHandler::find_egg_link(name, site_packages).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_remove_follows_record_and_prunes_empty_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        let site = dir.path();
+        write(&site.join("requests/__init__.py"), "");
+        write(&site.join("requests/adapters.py"), "");
+        write(&site.join("requests/__pycache__/adapters.cpython-311.pyc"), "");
+        write(&site.join("shared/other.py"), "not ours");
+        write(&site.join("shared/ours.py"), "");
+        write(&site.join("requests-2.31.0.dist-info/METADATA"), "Name: requests\nVersion: 2.31.0\n");
+        write(
+            &site.join("requests-2.31.0.dist-info/RECORD"),
+            "requests/__init__.py,sha256=x,0\nrequests/adapters.py,,\nshared/ours.py,,\n\
+             requests-2.31.0.dist-info/METADATA,,\nrequests-2.31.0.dist-info/RECORD,,\n",
+        );
+
+        let installed = InstalledDist::find(site, "Requests").unwrap();
+        assert_eq!(installed.version, "2.31.0");
+        assert!(!installed.editable);
+        assert_eq!(installed.remove(site, &HashSet::new()).unwrap(), 3);
+
+        assert!(!site.join("requests").exists());
+        assert!(!site.join("requests-2.31.0.dist-info").exists());
+        assert!(site.join("shared/other.py").exists());
+        assert!(InstalledDist::find(site, "requests").is_none());
+
+        // Files the replacement wrote at the same paths survive
+        write(&site.join("idna/core.py"), "");
+        write(&site.join("idna/old.py"), "");
+        write(&site.join("idna-3.6.dist-info/RECORD"), "idna/core.py,,\nidna/old.py,,\n");
+        write(&site.join("idna-3.7.dist-info/RECORD"), "idna/core.py,,\n");
+        let old = InstalledDist::find_version(site, "idna", "3.6").unwrap();
+        let new = InstalledDist::find_version(site, "idna", "3.7").unwrap();
+        assert_eq!(old.remove(site, &new.recorded_files(site)).unwrap(), 1);
+        assert!(site.join("idna/core.py").exists() && !site.join("idna/old.py").exists());
+        assert_eq!(InstalledDist::find(site, "idna"), Some(new));
+    }
+
+    #[test]
+    fn test_editable_detection() {
+        let dir = tempfile::tempdir().unwrap();
+        let site = dir.path();
+        write(
+            &site.join("my_tool-0.1.dist-info/direct_url.json"),
+            r#"{"url": "file:///src/my-tool", "dir_info": {"editable": true}}"#,
+        );
+        write(&site.join("legacy_pkg-1.0.dist-info/METADATA"), "");
+        write(&site.join("legacy_pkg.egg-link"), "/src/legacy\n.\n");
+        write(&site.join("plain-1.0.dist-info/METADATA"), "");
+
+        assert!(InstalledDist::find(site, "my-tool").unwrap().editable);
+        assert!(InstalledDist::find(site, "legacy-pkg").unwrap().editable);
+        assert!(!InstalledDist::find(site, "plain").unwrap().editable);
+        assert_eq!(record_path("\"a,b/c.py\",sha256=x,1"), Some("a,b/c.py"));
+    }
+
+    #[test]
+    fn test_find_matches_any_spelling_of_the_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let site = dir.path();
+        write(&site.join("Flask-SQLAlchemy-3.1.1.dist-info/METADATA"), "");
+
+        let dist = InstalledDist::find(site, "flask_sqlalchemy").unwrap();
+        assert_eq!((dist.name.as_str(), dist.version.as_str()), ("Flask-SQLAlchemy", "3.1.1"));
+        assert!(InstalledDist::find(site, "Flask.SQLAlchemy").is_some());
+    }
+}
@@ -0,0 +1,183 @@
+/// Externally managed environments (PEP 668)
+///
+/// Distributions that manage Python packages themselves (Debian, Ubuntu,
+/// Fedora, Homebrew) put an `EXTERNALLY-MANAGED` file in the interpreter's
+/// stdlib directory. Installing into such an environment can break the
+/// system tools that depend on it, so mutating commands refuse unless
+/// `--break-system-packages` is given. The file is INI: its
+/// `[externally-managed]` section carries an `Error` message, often with
+/// distro-specific advice, which is shown as is. Virtual environments never
+/// count as externally managed, even when their base interpreter is.
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const MARKER: &str = "EXTERNALLY-MANAGED";
+
+const SECTION: &str = "externally-managed";
+
+/// What pip prints when the marker has no `Error` key
+const DEFAULT_MESSAGE: &str = "The Python environment is managed externally, and may not be manipulated by the \
+    user. Please use specific tooling from the distributor of the Python installation to interact with this \
+    Python environment instead.";
+
+/// A marker file found for the environment about to be changed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExternallyManaged {
+    pub marker: PathBuf,
+    /// The marker's own error text
+    pub message: String,
+}
+
+/// The marker governing `site_packages`, unless a virtual environment owns it
+pub fn check(site_packages: &Path, virtual_env: Option<&Path>) -> Option<ExternallyManaged> {
+    if let Some(venv) = super::venv_guard::owning_virtualenv(site_packages, virtual_env) {
+        tracing::debug!("{} belongs to virtualenv {}; not externally managed", site_packages.display(), venv.display());
+        return None;
+    }
+    let marker = marker_dirs(site_packages).into_iter().map(|dir| dir.join(MARKER)).find(|path| path.is_file())?;
+    let message = fs::read_to_string(&marker).ok().and_then(|content| error_message(&content, &locale_names()));
+    Some(ExternallyManaged {
+        marker,
+        message: message.unwrap_or_else(|| DEFAULT_MESSAGE.to_string()),
+    })
+}
+
+/// Check the current process environment (reads `VIRTUAL_ENV`, or
+/// `--python`)
+pub fn check_current(site_packages: &Path) -> Option<ExternallyManaged> {
+    check(site_packages, crate::utils::python_env::virtual_env().as_deref())
+}
+
+/// Where the stdlib of the Python owning `site_packages` may be:
+/// `<prefix>/lib/python3.X` above `<prefix>/lib/python3.X/site-packages`,
+/// or `/usr/lib/python3.X` beside Debian's `/usr/lib/python3/dist-packages`
+fn marker_dirs(site_packages: &Path) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    let Some(parent) = site_packages.parent() else {
+        return dirs;
+    };
+    let is_python_dir = |dir: &Path| dir.file_name().is_some_and(|name| name.to_string_lossy().starts_with("python"));
+    if is_python_dir(parent) {
+        dirs.push(parent.to_path_buf());
+    }
+    if let Some(lib) = parent.parent() {
+        let versioned = lib.join(format!("python{}", crate::utils::python_env::detected().version()));
+        if !dirs.contains(&versioned) {
+            dirs.push(versioned);
+        }
+    }
+    // Windows and framework layouts keep site-packages directly under Lib
+    if !is_python_dir(parent) {
+        dirs.push(parent.to_path_buf());
+    }
+    dirs
+}
+
+/// The `Error` of the `[externally-managed]` section, preferring a
+/// translation (`Error-de_DE`, then `Error-de`) for one of `locales`.
+/// Indented lines continue the value before them, blank lines included,
+/// as in configparser.
+fn error_message(content: &str, locales: &[String]) -> Option<String> {
+    let mut in_section = false;
+    let mut values: Vec<(String, String)> = Vec::new();
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('#') || trimmed.starts_with(';') {
+            continue;
+        }
+        if let Some(header) = trimmed.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+            in_section = header.trim() == SECTION;
+            continue;
+        }
+        if !in_section {
+            continue;
+        }
+        if trimmed.is_empty() {
+            if let Some((_, value)) = values.last_mut() {
+                value.push('\n');
+            }
+        } else if line.starts_with(char::is_whitespace) {
+            if let Some((_, value)) = values.last_mut() {
+                value.push('\n');
+                value.push_str(trimmed);
+            }
+        } else if let Some((key, value)) = trimmed.split_once('=').or_else(|| trimmed.split_once(':')) {
+            values.push((key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    let lookup = |key: &str| {
+        values.iter().find(|(k, _)| k.eq_ignore_ascii_case(key)).map(|(_, v)| v.trim().to_string())
+    };
+    locales
+        .iter()
+        .find_map(|locale| lookup(&format!("Error-{}", locale)))
+        .or_else(|| lookup("Error"))
+        .filter(|message| !message.is_empty())
+}
+
+/// `de_DE` and `de` for `LC_ALL`, `LC_MESSAGES` or `LANG` set to `de_DE.UTF-8`
+fn locale_names() -> Vec<String> {
+    let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .unwrap_or_default();
+    let locale = locale.split(['.', '@']).next().unwrap_or_default();
+    if locale.is_empty() || locale == "C" || locale == "POSIX" {
+        return Vec::new();
+    }
+    let mut names = vec![locale.to_string()];
+    if let Some((language, _)) = locale.split_once('_') {
+        names.push(language.to_string());
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const DEBIAN: &str = "[externally-managed]\nError=To install Python packages system-wide, try apt install\n \
+        python3-xyz, where xyz is the package you are trying to\n install.\n\n See README.venv.\n\n\
+        Error-de=Bitte apt verwenden.\n";
+
+    #[test]
+    fn test_marker_beside_stdlib_is_found_outside_virtualenvs() {
+        let temp = TempDir::new().unwrap();
+        let stdlib = temp.path().join("usr/lib/python3.11");
+        let site = stdlib.join("site-packages");
+        fs::create_dir_all(&site).unwrap();
+        assert_eq!(check(&site, None), None);
+
+        fs::write(stdlib.join(MARKER), DEBIAN).unwrap();
+        let managed = check(&site, None).unwrap();
+        assert_eq!(managed.marker, stdlib.join(MARKER));
+        assert!(managed.message.starts_with("To install Python packages system-wide"));
+
+        // A virtualenv is never externally managed
+        let venv = temp.path().join("venv");
+        let venv_site = venv.join("lib/python3.11/site-packages");
+        fs::create_dir_all(&venv_site).unwrap();
+        fs::write(venv.join("pyvenv.cfg"), "home = /usr/bin\n").unwrap();
+        fs::write(venv.join("lib/python3.11").join(MARKER), DEBIAN).unwrap();
+        assert_eq!(check(&venv_site, None), None);
+        assert_eq!(check(&site, Some(&venv)).map(|m| m.marker), Some(stdlib.join(MARKER)));
+
+        // No Error key: pip's generic text
+        fs::write(stdlib.join(MARKER), "[externally-managed]\n").unwrap();
+        assert_eq!(check(&site, None).unwrap().message, DEFAULT_MESSAGE);
+    }
+
+    #[test]
+    fn test_error_message_continuations_and_translations() {
+        let english = error_message(DEBIAN, &[]).unwrap();
+        assert_eq!(
+            english,
+            "To install Python packages system-wide, try apt install\npython3-xyz, where xyz is the package you are \
+             trying to\ninstall.\n\nSee README.venv."
+        );
+        assert_eq!(error_message(DEBIAN, &["de_DE".to_string(), "de".to_string()]).unwrap(), "Bitte apt verwenden.");
+        assert_eq!(error_message("[other]\nError=nope\n", &[]), None);
+    }
+}
@@ -0,0 +1,386 @@
+/// Installing a release from the files its [`SelectionTrace`] ranks, with
+/// one retry when the chosen wheel is broken
+///
+/// Some uploads are broken in ways only installing reveals: a truncated
+/// zip, RECORD hashes that don't match the files, METADATA naming another
+/// project. When the best-ranked wheel fails like that, the next candidate
+/// the [`FallbackPolicy`] allows (another wheel, or an sdist built as a
+/// pure-Python wheel) is installed instead, with a warning naming both
+/// files. Failures of the environment — network errors, permissions, files
+/// in use — are not retried: another artifact would fare no better.
+use crate::errors::{Phase, PipError};
+use crate::installer::events::{self, InstallEvent};
+use crate::installer::provenance::Provenance;
+use crate::installer::source_tree::{self, SourceTree};
+use crate::installer::wheel::WheelFile;
+use crate::installer::scheme::Scheme;
+use crate::installer::{Overwrite, PackageInstaller, SitePackages};
+use crate::models::Package;
+use crate::network::resume::Progress;
+use crate::network::{FallbackPolicy, NetworkError, ReleaseFile, SelectionTrace};
+use crate::utils::progress;
+use crate::utils::warnings::{self, WarningCode};
+use async_trait::async_trait;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// A fallback that happened, as recorded in the install report
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ArtifactFallback {
+    pub name: String,
+    pub version: String,
+    /// The file tried first
+    pub failed: String,
+    /// The file installed instead
+    pub installed: String,
+    pub reason: String,
+}
+
+/// Where artifacts come from
+#[async_trait]
+pub trait ArtifactFetcher: Send + Sync {
+    /// Write `file` to `dest`
+    async fn fetch(&self, file: &ReleaseFile, dest: &Path) -> Result<(), NetworkError>;
+}
+
+/// Downloads each file from its index URL, resuming interrupted transfers
+/// and checking the sha256 the index published for it
+pub struct NetworkFetcher;
+
+#[async_trait]
+impl ArtifactFetcher for NetworkFetcher {
+    async fn fetch(&self, file: &ReleaseFile, dest: &Path) -> Result<(), NetworkError> {
+        let client = crate::network::PackageClient::new();
+        let bar = progress::artifact_bar(&file.filename);
+        let report = |written, total| {
+            if let Some(bar) = &bar {
+                progress::track_download(bar, written, total);
+            }
+        };
+        let report = bar.as_ref().map(|_| &report as &Progress<'_>);
+        let fetched = client.download_to_file_with(&file.url, dest, file.sha256.as_deref(), report).await;
+        if let Some(bar) = bar {
+            bar.finish_and_clear();
+        }
+        fetched.map(|_| ())
+    }
+}
+
+/// A release installed from one of its files
+#[derive(Debug)]
+pub struct InstalledArtifact {
+    /// Bytes written to the environment
+    pub size: u64,
+    pub provenance: Provenance,
+    pub fallback: Option<ArtifactFallback>,
+}
+
+/// Installs releases into one site-packages directory
+pub struct ArtifactInstaller<'a> {
+    pub site_packages: &'a Path,
+    /// Where `.data` trees and scripts go; derived from `site_packages`
+    /// when unset
+    pub scheme: Option<&'a Scheme>,
+    /// Downloads and sdist builds go here
+    pub work_dir: &'a Path,
+    /// Whether files in use or scripts of other packages may be replaced
+    pub overwrite: Overwrite,
+}
+
+impl ArtifactInstaller<'_> {
+    /// Install the best file of `trace`; when it is broken, warn and
+    /// install the next candidate `policy` allows instead
+    pub async fn install(
+        &self,
+        pkg: &Package,
+        trace: &SelectionTrace,
+        fetcher: &dyn ArtifactFetcher,
+        policy: &FallbackPolicy<'_>,
+    ) -> Result<InstalledArtifact, PipError> {
+        let best = trace.best(policy).map_err(|e| e.in_phase(Phase::Download, Some(&pkg.name)))?;
+        let error = match self.install_file(pkg, best, fetcher, policy).await {
+            Ok((size, provenance)) => {
+                return Ok(InstalledArtifact {
+                    size,
+                    provenance,
+                    fallback: None,
+                });
+            }
+            Err(e) if e.code() == "broken-artifact" => e,
+            Err(e) => return Err(e),
+        };
+
+        let reason = broken_reason(&error);
+        let alternate = match trace.alternate(best, policy) {
+            Ok(alternate) => alternate,
+            Err(why) => {
+                warnings::warn_user(
+                    WarningCode::ArtifactFallback,
+                    format!("{} is broken ({}); not retrying: {}", best.filename, reason, why),
+                );
+                return Err(error);
+            }
+        };
+        warnings::warn_user(WarningCode::ArtifactFallback, fallback_warning(best, alternate, &reason));
+        let (size, provenance) = self.install_file(pkg, alternate, fetcher, policy).await?;
+        Ok(InstalledArtifact {
+            size,
+            provenance,
+            fallback: Some(ArtifactFallback {
+                name: pkg.name.clone(),
+                version: pkg.version.clone(),
+                failed: best.filename.clone(),
+                installed: alternate.filename.clone(),
+                reason,
+            }),
+        })
+    }
+
+    async fn install_file(
+        &self,
+        pkg: &Package,
+        file: &ReleaseFile,
+        fetcher: &dyn ArtifactFetcher,
+        policy: &FallbackPolicy<'_>,
+    ) -> Result<(u64, Provenance), PipError> {
+        events::phase(Phase::Download, Some(&pkg.name));
+        let path = self.work_dir.join(&file.filename);
+        let started = Instant::now();
+        fetcher
+            .fetch(file, &path)
+            .await
+            .map_err(|e| PipError::from(e).in_phase(Phase::Download, Some(&pkg.name)))?;
+        let read_error = |e: anyhow::Error| PipError::FileSystemError {
+            path: path.display().to_string(),
+            operation: "read".to_string(),
+            reason: e.to_string(),
+        };
+        let bytes = std::fs::metadata(&path).map_err(|e| read_error(e.into()))?.len();
+        events::emit(InstallEvent::Downloaded {
+            package: pkg.name.clone(),
+            version: pkg.version.clone(),
+            bytes,
+            seconds: started.elapsed().as_secs_f64(),
+        });
+        if let Some(expected) = policy.pinned_hashes {
+            crate::utils::hash::check_file(&path, expected).map_err(read_error)?.map_err(|actual| {
+                PipError::HashMismatch {
+                    package: pkg.name.clone(),
+                    version: pkg.version.clone(),
+                    expected: expected.to_vec(),
+                    actual,
+                }
+                .in_phase(Phase::Download, Some(&pkg.name))
+            })?;
+        }
+        let provenance = Provenance::from_file(&file.url, &path).map_err(read_error)?;
+
+        let wheel_path = if file.is_wheel() {
+            path
+        } else {
+            events::phase(Phase::Build, Some(&pkg.name));
+            self.build_sdist(pkg, file, &path).map_err(|e| e.in_phase(Phase::Build, Some(&pkg.name)))?
+        };
+        events::phase(Phase::Install, Some(&pkg.name));
+        let size = install_wheel(self.site_packages, self.scheme, pkg, wheel_path, &file.filename, self.overwrite)
+            .await
+            .map_err(|e| e.in_phase(Phase::Install, Some(&pkg.name)))?;
+        Ok((size, provenance))
+    }
+
+    /// Unpack a downloaded sdist and build it as a pure-Python wheel
+    fn build_sdist(&self, pkg: &Package, file: &ReleaseFile, archive: &Path) -> Result<PathBuf, PipError> {
+        let unpacked = self.work_dir.join(format!("{}.d", file.filename));
+        let root = source_tree::unpack_sdist(archive, &unpacked).map_err(|e| PipError::BrokenArtifact {
+            package: pkg.name.clone(),
+            artifact: file.filename.clone(),
+            reason: e.to_string(),
+        })?;
+        let build_error = |e: anyhow::Error| PipError::InvalidPackage {
+            name: pkg.name.clone(),
+            reason: e.to_string(),
+        };
+        let mut tree = SourceTree::load(&root).map_err(build_error)?;
+        // A dynamic version is only known to the build backend; the index knows it too
+        if tree.version == "0" {
+            tree.version = pkg.version.clone();
+        }
+        tree.build_wheel(&unpacked, &[]).map_err(build_error)
+    }
+}
+
+/// Verify a wheel and install it into `site_packages` (and `scheme`, when
+/// given), returning the bytes written. `artifact` names the file the wheel
+/// came from in errors.
+pub async fn install_wheel(
+    site_packages: &Path,
+    scheme: Option<&Scheme>,
+    pkg: &Package,
+    wheel_path: PathBuf,
+    artifact: &str,
+    overwrite: Overwrite,
+) -> Result<u64, PipError> {
+    let install_error = |e: anyhow::Error| PipError::InstallationFailed {
+        package: pkg.name.clone(),
+        reason: e.to_string(),
+    };
+    let wheel = WheelFile::new(wheel_path).map_err(install_error)?;
+    wheel.verify(&pkg.name).map_err(|e| PipError::BrokenArtifact {
+        package: pkg.name.clone(),
+        artifact: artifact.to_string(),
+        reason: e.reason,
+    })?;
+    let site_packages = SitePackages::new(site_packages.to_path_buf()).map_err(install_error)?;
+    let mut installer = PackageInstaller::new(site_packages).with_overwrite(overwrite);
+    if let Some(scheme) = scheme {
+        installer = installer.with_scheme(scheme.clone());
+    }
+    Ok(installer.install_wheel(&wheel).await?)
+}
+
+/// The warning printed when `failed` is replaced by `alternate`
+pub fn fallback_warning(failed: &ReleaseFile, alternate: &ReleaseFile, reason: &str) -> String {
+    format!(
+        "{} could not be installed ({}); falling back to {}",
+        failed.filename, reason, alternate.filename
+    )
+}
+
+fn broken_reason(error: &PipError) -> String {
+    match error {
+        PipError::InPhase { source, .. } => broken_reason(source),
+        PipError::BrokenArtifact { reason, .. } => reason.clone(),
+        error => error.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::NetworkErrorKind;
+    use std::collections::HashMap;
+    use std::io::Write;
+
+    /// Serves fixture bytes by filename
+    struct Fixtures(HashMap<String, Vec<u8>>);
+
+    #[async_trait]
+    impl ArtifactFetcher for Fixtures {
+        async fn fetch(&self, file: &ReleaseFile, dest: &Path) -> Result<(), NetworkError> {
+            let missing = || NetworkError::status(&file.url, reqwest::StatusCode::NOT_FOUND);
+            let data = self.0.get(&file.filename).ok_or_else(missing)?;
+            std::fs::write(dest, data).map_err(|e| NetworkError::new(NetworkErrorKind::Io, &file.url, e))
+        }
+    }
+
+    fn release_file(filename: &str, sha256: Option<String>) -> ReleaseFile {
+        ReleaseFile {
+            filename: filename.to_string(),
+            url: format!("https://files.example/{}", filename),
+            size: None,
+            uploaded: None,
+            sha256,
+        }
+    }
+
+    /// A `.zip` sdist of a pure-Python `demo` 1.0
+    fn sdist() -> Vec<u8> {
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let options = zip::write::FileOptions::default();
+        for (name, content) in [
+            ("demo-1.0/PKG-INFO", "Metadata-Version: 2.1\nName: demo\nVersion: 1.0\n"),
+            ("demo-1.0/pyproject.toml", "[project]\nname = \"demo\"\nversion = \"1.0\"\n"),
+            ("demo-1.0/demo/__init__.py", "VALUE = 1\n"),
+        ] {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    /// A wheel cut off before its central directory
+    const CORRUPT_WHEEL: &[u8] = b"PK\x03\x04\x14\x00\x00\x00\x08\x00 truncated upload";
+
+    fn setup(sdist: &[u8], sdist_hash: Option<String>) -> (SelectionTrace, Fixtures) {
+        let trace = SelectionTrace::new(
+            "demo",
+            "1.0",
+            vec![
+                release_file("demo-1.0.zip", sdist_hash),
+                release_file("demo-1.0-py3-none-any.whl", Some(sha256(CORRUPT_WHEEL))),
+            ],
+        );
+        let fixtures = Fixtures(HashMap::from([
+            ("demo-1.0-py3-none-any.whl".to_string(), CORRUPT_WHEEL.to_vec()),
+            ("demo-1.0.zip".to_string(), sdist.to_vec()),
+        ]));
+        (trace, fixtures)
+    }
+
+    fn sha256(data: &[u8]) -> String {
+        crate::utils::hash::digest(data, "sha256").unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_broken_wheel_falls_back_to_sdist() {
+        let dir = tempfile::tempdir().unwrap();
+        let site = dir.path().join("site-packages");
+        let installer = ArtifactInstaller {
+            site_packages: &site,
+            scheme: None,
+            work_dir: dir.path(),
+            overwrite: Overwrite::default(),
+        };
+        let (trace, fixtures) = setup(&sdist(), None);
+        let pkg = Package::new("demo".to_string(), "1.0".to_string());
+
+        let installed = installer.install(&pkg, &trace, &fixtures, &FallbackPolicy::default()).await.unwrap();
+        let fallback = installed.fallback.unwrap();
+        assert_eq!((fallback.failed.as_str(), fallback.installed.as_str()), ("demo-1.0-py3-none-any.whl", "demo-1.0.zip"));
+        assert!(fallback.reason.starts_with("not a valid zip archive"), "{}", fallback.reason);
+        assert_eq!(installed.provenance.url, "https://files.example/demo-1.0.zip");
+        assert_eq!(std::fs::read_to_string(site.join("demo/__init__.py")).unwrap(), "VALUE = 1\n");
+        assert!(site.join("demo-1.0.dist-info/METADATA").is_file());
+
+        let warning = fallback_warning(&trace.candidates[0], &trace.candidates[1], &fallback.reason);
+        assert_eq!(
+            warning,
+            format!(
+                "demo-1.0-py3-none-any.whl could not be installed ({}); falling back to demo-1.0.zip",
+                fallback.reason
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_hash_checking_blocks_unhashed_alternate() {
+        let dir = tempfile::tempdir().unwrap();
+        let site = dir.path().join("site-packages");
+        let installer = ArtifactInstaller {
+            site_packages: &site,
+            scheme: None,
+            work_dir: dir.path(),
+            overwrite: Overwrite::default(),
+        };
+        let pkg = Package::new("demo".to_string(), "1.0".to_string());
+        let sdist = sdist();
+        let pins = vec![format!("sha256:{}", sha256(CORRUPT_WHEEL)), format!("sha256:{}", sha256(&sdist))];
+        let policy = FallbackPolicy {
+            only_binary: false,
+            pinned_hashes: Some(&pins),
+        };
+
+        // The index published no hash for the sdist, so it can't be vouched for
+        let (trace, fixtures) = setup(&sdist, None);
+        let error = installer.install(&pkg, &trace, &fixtures, &policy).await.unwrap_err();
+        assert_eq!(error.code(), "broken-artifact");
+        assert!(error.to_string().contains("demo-1.0-py3-none-any.whl for demo is broken"), "{}", error);
+        assert!(!site.join("demo").exists());
+
+        // With its hash published and pinned, the sdist is eligible
+        let (trace, fixtures) = setup(&sdist, Some(sha256(&sdist)));
+        let installed = installer.install(&pkg, &trace, &fixtures, &policy).await.unwrap();
+        assert_eq!(installed.fallback.unwrap().installed, "demo-1.0.zip");
+    }
+}
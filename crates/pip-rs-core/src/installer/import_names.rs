@@ -0,0 +1,355 @@
+/// Import-name to distribution-name mapping
+///
+/// Installed distributions are indexed from their `top_level.txt` (or, when
+/// that is missing, the top-level entries of `RECORD`). Modules that aren't
+/// installed are looked up in a curated table of well-known mismatches
+/// (`cv2` -> `opencv-python`), which a user file can extend or override.
+/// The table is the `import-names` dataset, so `pip datasets update` can
+/// refresh it between releases.
+use crate::config::config::Config;
+use crate::utils::datasets::Datasets;
+use crate::utils::names::canonicalize_name;
+use anyhow::{Result, anyhow};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+/// Curated import name -> candidate distributions (most common first)
+#[derive(Debug, Clone, Default)]
+pub struct CuratedImportMap {
+    entries: BTreeMap<String, Vec<String>>,
+}
+
+impl CuratedImportMap {
+    /// The table shipped with pip-rs, or its verified update
+    pub fn bundled() -> Self {
+        Self::from_datasets(&Datasets::from_config(Config::global()))
+    }
+
+    /// The `import-names` dataset as `datasets` resolves it
+    pub fn from_datasets(datasets: &Datasets) -> Self {
+        datasets
+            .read("import-names")
+            .and_then(|asset| Self::parse(&asset.content))
+            .unwrap_or_default()
+    }
+
+    /// The bundled table, extended/overridden by a user file if given
+    pub fn with_override_file(path: Option<&Path>) -> Result<Self> {
+        let mut map = Self::bundled();
+        if let Some(path) = path {
+            let content = fs::read_to_string(path)
+                .map_err(|e| anyhow!("Failed to read import map {}: {}", path.display(), e))?;
+            map.entries.extend(Self::parse(&content)?.entries);
+        }
+        Ok(map)
+    }
+
+    /// Parse `import_name = dist[, alternative...]` lines (`#` comments)
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut entries = BTreeMap::new();
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (module, dists) = line
+                .split_once('=')
+                .ok_or_else(|| anyhow!("line {}: expected '<import name> = <distribution>'", index + 1))?;
+            let dists: Vec<String> = dists
+                .split(',')
+                .map(str::trim)
+                .filter(|d| !d.is_empty())
+                .map(String::from)
+                .collect();
+            if dists.is_empty() {
+                return Err(anyhow!("line {}: no distribution given for '{}'", index + 1, module.trim()));
+            }
+            entries.insert(module.trim().to_string(), dists);
+        }
+        Ok(Self { entries })
+    }
+
+    /// Candidate distributions for a module (`cv2.aruco` looks up `cv2`)
+    pub fn lookup(&self, module: &str) -> Option<&[String]> {
+        self.entries.get(top_level(module)).map(|d| d.as_slice())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// One installed distribution in the index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstalledDistribution {
+    pub name: String,
+    pub version: String,
+    /// Top-level import names this distribution provides
+    pub import_names: BTreeSet<String>,
+    /// Normalized names of the distributions it requires
+    pub requires: BTreeSet<String>,
+}
+
+/// Import names provided by the distributions in a site-packages directory
+#[derive(Debug, Clone, Default)]
+pub struct InstalledImportIndex {
+    distributions: Vec<InstalledDistribution>,
+}
+
+impl InstalledImportIndex {
+    /// Scan the `.dist-info` directories of a site-packages directory
+    pub fn build(site_packages: &Path) -> Result<Self> {
+        let mut distributions = Vec::new();
+        for entry in fs::read_dir(site_packages)?.flatten() {
+            let path = entry.path();
+            let dir_name = entry.file_name().to_string_lossy().to_string();
+            if !path.is_dir() || !dir_name.ends_with(".dist-info") {
+                continue;
+            }
+            if let Some(dist) = read_distribution(&path, &dir_name) {
+                distributions.push(dist);
+            }
+        }
+        distributions.sort_by_key(|d| d.name.to_lowercase());
+        Ok(Self { distributions })
+    }
+
+    pub fn distributions(&self) -> &[InstalledDistribution] {
+        &self.distributions
+    }
+
+    /// Installed distribution by (normalized) name
+    pub fn get(&self, name: &str) -> Option<&InstalledDistribution> {
+        let name = canonicalize_name(name);
+        self.distributions.iter().find(|d| canonicalize_name(&d.name) == name)
+    }
+
+    /// Installed distributions providing a module
+    pub fn providers(&self, module: &str) -> Vec<&InstalledDistribution> {
+        let module = top_level(module);
+        self.distributions
+            .iter()
+            .filter(|d| d.import_names.contains(module))
+            .collect()
+    }
+
+    /// Warnings for removing `names`: installed distributions that require
+    /// one of them (and aren't removed too) lose the import names it provides
+    pub fn uninstall_warnings(&self, names: &[String]) -> Vec<String> {
+        let removing: BTreeSet<String> = names.iter().map(|n| canonicalize_name(n)).collect();
+        names
+            .iter()
+            .filter_map(|name| self.get(name))
+            .flat_map(|removed| self.dependents_warnings(removed, &removing))
+            .collect()
+    }
+
+    fn dependents_warnings(&self, removed: &InstalledDistribution, removing: &BTreeSet<String>) -> Vec<String> {
+        let removed_name = canonicalize_name(&removed.name);
+        let imports: Vec<&str> = removed.import_names.iter().map(|s| s.as_str()).collect();
+
+        self.distributions
+            .iter()
+            .filter(|d| !removing.contains(&canonicalize_name(&d.name)) && d.requires.contains(&removed_name))
+            .map(|d| {
+                if imports.is_empty() {
+                    format!("{} {} requires {}, which is being removed", d.name, d.version, removed.name)
+                } else {
+                    format!(
+                        "{} {} requires {}; removing it takes away import {}",
+                        d.name,
+                        d.version,
+                        removed.name,
+                        imports.iter().map(|i| format!("'{}'", i)).collect::<Vec<_>>().join(", ")
+                    )
+                }
+            })
+            .collect()
+    }
+}
+
+fn read_distribution(dist_info: &Path, dir_name: &str) -> Option<InstalledDistribution> {
+    let metadata = fs::read_to_string(dist_info.join("METADATA")).unwrap_or_default();
+    let mut name = None;
+    let mut version = String::new();
+    let mut requires = BTreeSet::new();
+    for line in metadata.lines() {
+        if let Some(value) = line.strip_prefix("Name: ") {
+            name = Some(value.trim().to_string());
+        } else if let Some(value) = line.strip_prefix("Version: ") {
+            version = value.trim().to_string();
+        } else if let Some(value) = line.strip_prefix("Requires-Dist: ") {
+            // Only unconditional requirements count as references
+            if !value.contains("extra ==") && !value.contains("extra==") {
+                requires.insert(canonicalize_name(requirement_name(value)));
+            }
+        } else if line.is_empty() {
+            break; // end of headers
+        }
+    }
+
+    let name = name.or_else(|| {
+        let stem = dir_name.trim_end_matches(".dist-info");
+        Some(stem.split('-').next()?.to_string())
+    })?;
+
+    let import_names = match fs::read_to_string(dist_info.join("top_level.txt")) {
+        Ok(content) => content
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .map(|l| l.replace('/', "."))
+            .map(|l| top_level(&l).to_string())
+            .collect(),
+        Err(_) => fs::read_to_string(dist_info.join("RECORD"))
+            .map(|record| imports_from_record(&record))
+            .unwrap_or_default(),
+    };
+
+    Some(InstalledDistribution {
+        name,
+        version,
+        import_names,
+        requires,
+    })
+}
+
+/// Top-level importable names from RECORD paths
+fn imports_from_record(record: &str) -> BTreeSet<String> {
+    let mut names = BTreeSet::new();
+    for line in record.lines() {
+        let path = line.split(',').next().unwrap_or("").trim();
+        if path.is_empty() || path.starts_with("..") || path.starts_with('/') {
+            continue;
+        }
+        let (first, is_dir) = match path.split_once('/') {
+            Some((first, _)) => (first, true),
+            None => (path, false),
+        };
+        if first.ends_with(".dist-info") || first.ends_with(".data") || first == "__pycache__" {
+            continue;
+        }
+        let module = if is_dir {
+            first
+        } else if first.ends_with(".py") || first.ends_with(".so") || first.ends_with(".pyd") {
+            // `six.py`, `_cffi_backend.cpython-311-x86_64-linux-gnu.so`
+            first.split('.').next().unwrap_or(first)
+        } else {
+            continue;
+        };
+        if !module.is_empty() && module.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            names.insert(module.to_string());
+        }
+    }
+    names
+}
+
+/// Distribution name at the start of a Requires-Dist value
+fn requirement_name(requirement: &str) -> &str {
+    let end = requirement
+        .find(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_' || c == '.'))
+        .unwrap_or(requirement.len());
+    &requirement[..end]
+}
+
+fn top_level(module: &str) -> &str {
+    module.split('.').next().unwrap_or(module)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn dist_info(site: &Path, dir: &str, metadata: &str, files: &[(&str, &str)]) {
+        let path = site.join(dir);
+        fs::create_dir_all(&path).unwrap();
+        fs::write(path.join("METADATA"), metadata).unwrap();
+        for (name, content) in files {
+            fs::write(path.join(name), content).unwrap();
+        }
+    }
+
+    /// opencv (top_level.txt), PyYAML (RECORD only) and an app requiring opencv
+    fn fixture_environment() -> TempDir {
+        let site = TempDir::new().unwrap();
+        dist_info(
+            site.path(),
+            "opencv_python-4.9.0.80.dist-info",
+            "Metadata-Version: 2.1\nName: opencv-python\nVersion: 4.9.0.80\nRequires-Dist: numpy>=1.21\n",
+            &[("top_level.txt", "cv2\n")],
+        );
+        dist_info(
+            site.path(),
+            "PyYAML-6.0.1.dist-info",
+            "Metadata-Version: 2.1\nName: PyYAML\nVersion: 6.0.1\n",
+            &[(
+                "RECORD",
+                "yaml/__init__.py,sha256=abc,100\n_yaml/__init__.py,sha256=def,10\n\
+                 PyYAML-6.0.1.dist-info/METADATA,,\n../../../bin/yamlscript,,\n",
+            )],
+        );
+        dist_info(
+            site.path(),
+            "vision_app-1.0.dist-info",
+            "Metadata-Version: 2.1\nName: vision-app\nVersion: 1.0\n\
+             Requires-Dist: opencv-python (>=4.5)\nRequires-Dist: pytest ; extra == \"test\"\n",
+            &[("top_level.txt", "vision_app\n")],
+        );
+        site
+    }
+
+    #[test]
+    fn test_installed_index_maps_imports() {
+        let site = fixture_environment();
+        let index = InstalledImportIndex::build(site.path()).unwrap();
+
+        let providers: Vec<&str> = index.providers("cv2.aruco").iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(providers, vec!["opencv-python"]);
+        let yaml = index.get("pyyaml").unwrap();
+        assert_eq!(yaml.import_names.iter().collect::<Vec<_>>(), vec!["_yaml", "yaml"]);
+        assert!(index.providers("numpy").is_empty());
+    }
+
+    #[test]
+    fn test_curated_lookup_and_override() {
+        let bundled = CuratedImportMap::bundled();
+        assert!(bundled.len() > 100);
+        assert_eq!(bundled.lookup("cv2").unwrap()[0], "opencv-python");
+        assert_eq!(bundled.lookup("PIL.Image").unwrap(), ["Pillow"]);
+        assert_eq!(bundled.lookup("yaml").unwrap(), ["PyYAML"]);
+        assert_eq!(bundled.lookup("sklearn").unwrap(), ["scikit-learn"]);
+        assert!(bundled.lookup("requests").is_none());
+
+        let temp = TempDir::new().unwrap();
+        let override_file = temp.path().join("imports.txt");
+        fs::write(&override_file, "cv2 = opencv-python-headless\ninternal_sdk = acme-sdk\n").unwrap();
+        let map = CuratedImportMap::with_override_file(Some(&override_file)).unwrap();
+        assert_eq!(map.lookup("cv2").unwrap(), ["opencv-python-headless"]);
+        assert_eq!(map.lookup("internal_sdk").unwrap(), ["acme-sdk"]);
+        assert!(CuratedImportMap::parse("no separator here").is_err());
+    }
+
+    #[test]
+    fn test_uninstall_warns_about_dependents() {
+        let site = fixture_environment();
+        let index = InstalledImportIndex::build(site.path()).unwrap();
+
+        let removing = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            index.uninstall_warnings(&removing(&["opencv_python"])),
+            vec!["vision-app 1.0 requires opencv-python; removing it takes away import 'cv2'"]
+        );
+        // Removing the dependent along with it is fine
+        assert!(index.uninstall_warnings(&removing(&["opencv-python", "vision-app"])).is_empty());
+        // Nothing requires PyYAML; optional (extra) requirements don't count
+        assert!(index.uninstall_warnings(&removing(&["PyYAML"])).is_empty());
+        assert!(index.uninstall_warnings(&removing(&["not-installed"])).is_empty());
+    }
+}
@@ -0,0 +1,221 @@
+/// `pip inspect`: the installed distributions and the environment as JSON
+///
+/// The layout is pip's inspect report (version "1"), so tools written
+/// against pip read it unchanged: `installed[]` holds each distribution's
+/// METADATA converted to JSON as PEP 566 describes, where it lives, its
+/// INSTALLER, whether it was REQUESTED and its `direct_url.json`; and
+/// `environment` holds the marker variables. Only those few files of each
+/// dist-info are read, and nothing is fetched, so even environments with
+/// thousands of distributions are inspected in a fraction of a second.
+use super::egg_link_handler::{EggLinkHandler, EggLinkInfo};
+use super::site_packages::SitePackages;
+use crate::models::Environment;
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::Path;
+
+/// Version of the report layout, as pip spells it
+pub const INSPECT_VERSION: &str = "1";
+
+/// METADATA fields that may appear more than once; PEP 566 makes them lists
+const MULTIPLE_USE: &[&str] = &[
+    "classifier",
+    "dynamic",
+    "license_file",
+    "obsoletes_dist",
+    "platform",
+    "project_url",
+    "provides_dist",
+    "provides_extra",
+    "requires_dist",
+    "requires_external",
+    "supported_platform",
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InspectReport {
+    /// [`INSPECT_VERSION`]
+    pub version: &'static str,
+    /// pip-rs's own version, under pip's key
+    pub pip_version: String,
+    pub installed: Vec<InspectedDist>,
+    /// Marker variables of the inspected environment
+    pub environment: Environment,
+}
+
+/// One installed distribution
+#[derive(Debug, Clone, Serialize)]
+pub struct InspectedDist {
+    /// METADATA as JSON (PEP 566)
+    pub metadata: Map<String, Value>,
+    /// The dist-info directory
+    pub metadata_location: String,
+    /// `direct_url.json` as recorded (PEP 610)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub direct_url: Option<Value>,
+    /// Asked for by name rather than pulled in as a dependency (`REQUESTED`)
+    pub requested: bool,
+    /// The tool that installed it (`INSTALLER`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub installer: Option<String>,
+    pub editable: bool,
+    /// The source tree an editable install points at
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub editable_project_location: Option<String>,
+}
+
+/// Describe every distribution in `site_packages`, sorted by normalized name
+pub fn inspect(site_packages: &SitePackages, environment: Environment) -> Result<InspectReport> {
+    let site = site_packages.path();
+    let installed = site_packages
+        .installed_distributions()?
+        .into_values()
+        .filter_map(|dist| {
+            // Reported absolute, as pip does
+            let location = match dist.dist_info.file_name() {
+                Some(name) => site_packages.canonical_path().join(name),
+                None => dist.dist_info.clone(),
+            };
+            inspect_dist(&dist.dist_info, &location, site, &dist.name)
+        })
+        .collect();
+    Ok(InspectReport {
+        version: INSPECT_VERSION,
+        pip_version: env!("CARGO_PKG_VERSION").to_string(),
+        installed,
+        environment,
+    })
+}
+
+/// `None` when the dist-info has no readable METADATA
+fn inspect_dist(dist_info: &Path, location: &Path, site_packages: &Path, name: &str) -> Option<InspectedDist> {
+    let metadata = metadata_json(&fs::read_to_string(dist_info.join("METADATA")).ok()?);
+    let installer = fs::read_to_string(dist_info.join("INSTALLER"))
+        .ok()
+        .map(|content| content.trim().to_string())
+        .filter(|installer| !installer.is_empty());
+    let direct_url: Option<Value> = fs::read_to_string(dist_info.join(crate::resolver::direct_url::DIRECT_URL_FILE))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok());
+
+    let editable_url = direct_url
+        .as_ref()
+        .filter(|direct_url| direct_url["dir_info"]["editable"].as_bool() == Some(true))
+        .and_then(|direct_url| direct_url["url"].as_str());
+    let editable_project_location = match editable_url {
+        Some(url) => Some(url.strip_prefix("file://").unwrap_or(url).to_string()),
+        None => EggLinkHandler::find_egg_link(name, site_packages)
+            .and_then(|egg_link| EggLinkInfo::parse(&egg_link).ok())
+            .map(|info| info.get_absolute_location().display().to_string()),
+    };
+
+    Some(InspectedDist {
+        metadata,
+        metadata_location: location.display().to_string(),
+        direct_url,
+        requested: dist_info.join("REQUESTED").exists(),
+        installer,
+        editable: editable_project_location.is_some(),
+        editable_project_location,
+    })
+}
+
+/// Core metadata as JSON per PEP 566: keys lower-cased with `-` as `_`,
+/// multiple-use fields as lists, `keywords` split into a list, and the
+/// message body, if any, as `description`. Of a repeated single-use field
+/// the first value counts.
+pub fn metadata_json(content: &str) -> Map<String, Value> {
+    let content = content.replace("\r\n", "\n");
+    let (headers, body) = content.split_once("\n\n").unwrap_or((&content, ""));
+
+    let mut fields: Vec<(String, String)> = Vec::new();
+    for line in headers.lines() {
+        if line.starts_with([' ', '\t']) {
+            // Folded continuation, e.g. of a Description header
+            if let Some((_, value)) = fields.last_mut() {
+                value.push('\n');
+                value.push_str(line.trim_start());
+            }
+        } else if let Some((key, value)) = line.split_once(':') {
+            fields.push((key.trim().to_lowercase().replace('-', "_"), value.trim().to_string()));
+        }
+    }
+
+    let mut json = Map::new();
+    for (key, value) in fields {
+        if key == "keywords" {
+            let separator = if value.contains(',') { ',' } else { ' ' };
+            let keywords = value.split(separator).map(str::trim).filter(|k| !k.is_empty());
+            json.insert(key, keywords.map(|k| Value::String(k.to_string())).collect());
+        } else if MULTIPLE_USE.contains(&key.as_str()) {
+            if let Value::Array(values) = json.entry(key).or_insert_with(|| Value::Array(Vec::new())) {
+                values.push(Value::String(value));
+            }
+        } else {
+            json.entry(key).or_insert(Value::String(value));
+        }
+    }
+    if !body.trim().is_empty() {
+        json.insert("description".to_string(), Value::String(body.to_string()));
+    }
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metadata_json_follows_pep_566() {
+        let json = metadata_json(
+            "Metadata-Version: 2.1\nName: demo\nVersion: 1.0\nKeywords: web,http\nClassifier: A\nClassifier: B\n\
+             Requires-Dist: idna>=2\nProject-URL: Home, https://example.com\nLicense: MIT\n  second line\n\n\
+             # demo\n\nLong description.\n",
+        );
+        assert_eq!(json["metadata_version"], "2.1");
+        assert_eq!(json["name"], "demo");
+        assert_eq!(json["keywords"], serde_json::json!(["web", "http"]));
+        assert_eq!(json["classifier"], serde_json::json!(["A", "B"]));
+        assert_eq!(json["requires_dist"], serde_json::json!(["idna>=2"]));
+        assert_eq!(json["project_url"], serde_json::json!(["Home, https://example.com"]));
+        assert_eq!(json["license"], "MIT\nsecond line");
+        assert_eq!(json["description"], "# demo\n\nLong description.\n");
+    }
+
+    #[test]
+    fn test_inspect_reads_installer_requested_and_direct_url() {
+        let temp = tempfile::tempdir().unwrap();
+        let site = temp.path();
+        let demo = site.join("demo-1.0.dist-info");
+        let tool = site.join("my_tool-0.1.dist-info");
+        for (dir, name, version) in [(&demo, "demo", "1.0"), (&tool, "my-tool", "0.1")] {
+            fs::create_dir_all(dir).unwrap();
+            fs::write(dir.join("METADATA"), format!("Metadata-Version: 2.1\nName: {}\nVersion: {}\n", name, version))
+                .unwrap();
+        }
+        fs::write(demo.join("INSTALLER"), "pip-rs\n").unwrap();
+        fs::write(demo.join("REQUESTED"), "").unwrap();
+        fs::write(tool.join("direct_url.json"), r#"{"url": "file:///src/my-tool", "dir_info": {"editable": true}}"#)
+            .unwrap();
+        // No METADATA: left out
+        fs::create_dir_all(site.join("broken-2.0.dist-info")).unwrap();
+
+        let report = inspect(&SitePackages::new(site.to_path_buf()).unwrap(), Environment::current()).unwrap();
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["version"], "1");
+        assert!(json["environment"]["sys_platform"].is_string());
+        let installed = json["installed"].as_array().unwrap();
+        assert_eq!(installed.len(), 2);
+        assert_eq!(installed[0]["metadata"]["name"], "demo");
+        assert_eq!(installed[0]["installer"], "pip-rs");
+        assert_eq!(installed[0]["requested"], true);
+        assert_eq!(installed[0]["editable"], false);
+        assert!(installed[0].get("direct_url").is_none());
+        assert_eq!(installed[1]["requested"], false);
+        assert_eq!(installed[1]["editable"], true);
+        assert_eq!(installed[1]["editable_project_location"], "/src/my-tool");
+        assert_eq!(installed[1]["direct_url"]["dir_info"]["editable"], true);
+    }
+}
@@ -1,15 +1,37 @@
 /// Package installer implementation
 use crate::models::Package;
 use crate::network::PackageClient;
-use super::{SitePackages, wheel::WheelFile};
+use crate::utils::names::canonicalize_name;
+use crate::utils::{cancel, progress};
+use super::entry_point::{self, ScriptOwner};
+use super::env_lock::{Contended, EnvironmentLock};
+use super::error::{InstallError, InstallErrorKind};
+use super::existing::InstalledDist;
+use super::scheme::Scheme;
+use super::transaction::Transaction;
+use super::{SitePackages, replace, wheel::WheelFile};
 use anyhow::{Result, anyhow};
-use std::path::Path;
+use std::path::{Component, Path, PathBuf};
 use tempfile::TempDir;
+use walkdir::WalkDir;
+
+/// How an install treats files already in the environment
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Overwrite {
+    /// Abort instead of replacing files running processes have mapped
+    pub refuse_if_in_use: bool,
+    /// Replace console scripts another package or tool installed under
+    /// the same name instead of failing
+    pub force_reinstall: bool,
+}
 
 #[allow(dead_code)]
 pub struct PackageInstaller {
     client: PackageClient,
     site_packages: SitePackages,
+    overwrite: Overwrite,
+    /// Scheme directories; derived from site-packages when unset
+    scheme: Option<Scheme>,
 }
 
 impl PackageInstaller {
@@ -17,11 +39,31 @@ impl PackageInstaller {
         Self {
             client: PackageClient::new(),
             site_packages,
+            overwrite: Overwrite::default(),
+            scheme: None,
         }
     }
 
+    pub fn with_overwrite(mut self, overwrite: Overwrite) -> Self {
+        self.overwrite = overwrite;
+        self
+    }
+
+    /// Install `.data` trees and scripts to these directories, e.g. those
+    /// of a `--target` directory
+    pub fn with_scheme(mut self, scheme: Scheme) -> Self {
+        self.scheme = Some(scheme);
+        self
+    }
+
+    /// Fail the install rather than replace files that are in use
+    pub fn with_refuse_if_in_use(mut self, refuse: bool) -> Self {
+        self.overwrite.refuse_if_in_use = refuse;
+        self
+    }
+
     pub async fn install(&self, package: &Package) -> Result<()> {
-        println!("Installing {} {}", package.name, package.version);
+        progress::status(format!("Installing {} {}", package.name, package.version));
 
         // Create temporary directory for downloads
         let _ = TempDir::new()?;
@@ -36,24 +78,176 @@ impl PackageInstaller {
         // let wheel = WheelFile::new(wheel_path)?;
         // self.install_wheel(&wheel).await?;
 
-        println!("Successfully installed {} {}", package.name, package.version);
+        progress::status(format!("Successfully installed {} {}", package.name, package.version));
         Ok(())
     }
 
-    pub async fn install_wheel(&self, wheel: &WheelFile) -> Result<()> {
-        println!("Extracting wheel: {}", wheel.name);
+    /// Install a wheel, returning the number of bytes written. Nothing in
+    /// the environment changes until the whole wheel is staged and checked,
+    /// and a failure or cancel request leaves it as it was (see
+    /// [`Transaction`]); the previous version's leftovers are the caller's.
+    pub async fn install_wheel(&self, wheel: &WheelFile) -> Result<u64, InstallError> {
+        let site = self.site_packages.path();
+        let failed = |kind, package: &str, e| InstallError::with_cause(kind, package, site, e);
+        let _lock = EnvironmentLock::acquire(site).map_err(|e| {
+            let kind = if e.is::<Contended>() { InstallErrorKind::Locked } else { InstallErrorKind::Io };
+            failed(kind, &wheel.name, e)
+        })?;
+        progress::status(format!("Extracting wheel: {}", wheel.name));
 
-        // Create temporary extraction directory
-        let temp_dir = TempDir::new()?;
-        
-        // Extract wheel contents
-        wheel.extract(temp_dir.path())?;
+        let mut transaction = Transaction::new(site).map_err(|e| failed(InstallErrorKind::Io, &wheel.name, e))?;
+        let extracted = transaction.stage_dir().to_path_buf();
+        wheel.extract(&extracted).map_err(|e| failed(InstallErrorKind::InvalidWheel, &wheel.name, e))?;
 
         // Get wheel metadata
-        let metadata = wheel.get_metadata()?;
+        let metadata = wheel.get_metadata().map_err(|e| failed(InstallErrorKind::InvalidWheel, &wheel.name, e))?;
+        // Everything extracted gets written (pip-rs doesn't byte-compile)
+        let size = super::sizes::tree_size(&extracted);
+
+        let in_use = self.check_in_use(&extracted, &metadata.name)?;
+        for line in replace::in_use_notice(&metadata.name, &in_use) {
+            progress::status(line);
+        }
+        self.check_script_clashes(&extracted, &metadata.name)?;
+
+        self.stage_extracted(&extracted, &mut transaction).map_err(|e| {
+            let kind = if cancel::is_requested() { InstallErrorKind::Cancelled } else { InstallErrorKind::Io };
+            failed(kind, &metadata.name, e)
+        })?;
+        if cancel::is_requested() {
+            return Err(InstallError::new(InstallErrorKind::Cancelled, &metadata.name, site, "installation cancelled"));
+        }
+        transaction.commit().map_err(|e| failed(InstallErrorKind::Io, &metadata.name, e))?;
+
+        progress::status(format!("Successfully installed {} {}", metadata.name, metadata.version));
+        Ok(size)
+    }
+
+    /// Processes mapping files this install would replace; an error when
+    /// refusing to replace files in use
+    pub fn check_in_use(&self, extracted: &Path, package: &str) -> Result<Vec<replace::InUse>, InstallError> {
+        let site = self.site_packages.path();
+        let targets = self
+            .replacement_targets(extracted)
+            .map_err(|e| InstallError::with_cause(InstallErrorKind::Io, package, site, e))?;
+        let in_use = replace::mapped_by(&targets);
+        if self.overwrite.refuse_if_in_use && !in_use.is_empty() {
+            let processes: Vec<String> = in_use.iter().map(|process| process.to_string()).collect();
+            let message = format!(
+                "{} is in use by running processes and --refuse-if-in-use is set: {}",
+                package,
+                processes.join("; ")
+            );
+            return Err(InstallError::new(InstallErrorKind::Conflict, package, site, message));
+        }
+        Ok(in_use)
+    }
+
+    /// Where each part of a wheel goes: as configured, else the scheme of
+    /// the environment owning site-packages
+    fn scheme(&self) -> Scheme {
+        self.scheme.clone().unwrap_or_else(|| {
+            let virtual_env = crate::utils::python_env::virtual_env();
+            Scheme::for_site_packages(self.site_packages.path(), virtual_env.as_deref())
+        })
+    }
+
+    /// Fail if a console script of an extracted wheel would overwrite a
+    /// file that neither pip-rs wrote for this project nor the installed
+    /// version's RECORD lists, unless `--force-reinstall` is set
+    pub fn check_script_clashes(&self, extracted: &Path, package: &str) -> Result<(), InstallError> {
+        let site = self.site_packages.path();
+        let entry_points = std::fs::read_dir(extracted)
+            .map_err(|e| InstallError::with_cause(InstallErrorKind::Io, package, site, e.into()))?
+            .flatten()
+            .filter(|entry| entry.file_name().to_string_lossy().ends_with(".dist-info"))
+            .find_map(|entry| std::fs::read_to_string(entry.path().join("entry_points.txt")).ok());
+        let Some(entry_points) = entry_points else {
+            return Ok(());
+        };
+        let owner = canonicalize_name(package);
+        let recorded: Vec<PathBuf> = InstalledDist::find(site, package)
+            .map(|dist| dist.recorded_files(site).iter().filter_map(|path| path.canonicalize().ok()).collect())
+            .unwrap_or_default();
+        let dir = self.scheme().scripts;
+
+        let mut clashes = Vec::new();
+        for script in entry_point::console_scripts(&entry_points) {
+            let files: Vec<PathBuf> = entry_point::script_files(&script.name, cfg!(target_os = "windows"))
+                .iter()
+                .map(|name| dir.join(name))
+                .collect();
+            let ours = std::fs::read_to_string(&files[0])
+                .ok()
+                .and_then(|content| entry_point::script_owner(&content))
+                .is_some_and(|found| matches!(found, ScriptOwner::PipRs { package, .. } if canonicalize_name(&package) == owner));
+            if ours {
+                continue;
+            }
+            clashes.extend(files.into_iter().filter(|path| {
+                path.exists() && !path.canonicalize().is_ok_and(|path| recorded.contains(&path))
+            }));
+        }
+
+        if clashes.is_empty() {
+            return Ok(());
+        }
+        let paths: Vec<String> = clashes.iter().map(|path| path.display().to_string()).collect();
+        if self.overwrite.force_reinstall {
+            tracing::debug!("--force-reinstall: replacing {}", paths.join(", "));
+            return Ok(());
+        }
+        let message = format!(
+            "{}'s console scripts would overwrite files another package or tool installed: {}; \
+             pass --force-reinstall to replace them",
+            package,
+            paths.join(", ")
+        );
+        Err(InstallError::new(InstallErrorKind::Conflict, package, site, message))
+    }
 
-        // Install package files
-        for entry in std::fs::read_dir(temp_dir.path())? {
+    /// Installed files an extracted wheel would replace, as resolved paths
+    fn replacement_targets(&self, extracted: &Path) -> Result<Vec<PathBuf>> {
+        // (directory to walk, directory its paths are relative to)
+        let mut roots = Vec::new();
+        for entry in std::fs::read_dir(extracted)? {
+            let path = entry?.path();
+            if !path.is_dir() {
+                continue;
+            }
+            if path.to_string_lossy().ends_with(".data") {
+                for lib in ["purelib", "platlib"].map(|lib| path.join(lib)) {
+                    roots.push((lib.clone(), lib));
+                }
+            } else {
+                roots.push((path, extracted.to_path_buf()));
+            }
+        }
+
+        let mut targets = Vec::new();
+        for (root, base) in roots {
+            for file in WalkDir::new(&root).into_iter().flatten().filter(|e| e.file_type().is_file()) {
+                if let Ok(relative) = file.path().strip_prefix(&base)
+                    && let Ok(target) = self.site_packages.path().join(relative).canonicalize()
+                {
+                    targets.push(target);
+                }
+            }
+        }
+        Ok(targets)
+    }
+
+    /// Stage an extracted wheel's files where the scheme puts them, with a
+    /// RECORD listing every one of them. A cancel request stops it between
+    /// top-level entries.
+    fn stage_extracted(&self, extracted: &Path, transaction: &mut Transaction) -> Result<()> {
+        let scheme = self.scheme();
+        let site = self.site_packages.path();
+        let mut dist_info = None;
+        for entry in std::fs::read_dir(extracted)? {
+            if cancel::is_requested() {
+                return Err(anyhow!("installation cancelled"));
+            }
             let entry = entry?;
             let path = entry.path();
             
@@ -63,130 +257,154 @@ impl PackageInstaller {
                     .ok_or_else(|| anyhow!("Invalid directory name"))?;
 
                 if dir_name.ends_with(".dist-info") {
-                    // Install metadata
-                    self.install_metadata(&path, dir_name)?;
-                } else if dir_name.ends_with(".data") {
+                    transaction.check_record(&path.join("RECORD"))?;
+                    self.stage_metadata(&path, dir_name, &scheme, transaction)?;
+                    dist_info = Some(site.join(dir_name));
+                } else if let Some(dist) = dir_name.strip_suffix(".data") {
                     // Install data files
-                    self.install_data_files(&path)?;
+                    let dist = dist.split_once('-').map_or(dist, |(name, _)| name);
+                    self.stage_data_files(&path, dist, &scheme, transaction)?;
                 } else {
                     // Install package files
-                    self.site_packages.install_directory(&path, Path::new(dir_name))?;
+                    transaction.stage_tree(&path, &site.join(dir_name))?;
                 }
+            } else if path.is_file() {
+                // Top-level modules, e.g. `six.py`
+                transaction.stage_file(&path, &site.join(entry.file_name()))?;
             }
         }
 
-        println!("Successfully installed {} {}", metadata.name, metadata.version);
+        if let Some(dist_info) = dist_info {
+            let record = dist_info.join("RECORD");
+            let content = self.record(transaction, &record)?;
+            transaction.stage_bytes(&record, content.as_bytes(), false)?;
+        }
         Ok(())
     }
 
-    fn install_metadata(&self, source: &Path, dist_info_name: &str) -> Result<()> {
-        let target = self.site_packages.path().join(dist_info_name);
-        std::fs::create_dir_all(&target)?;
+    /// RECORD listing the staged files as installed: paths relative to
+    /// site-packages, sha256 and size, and a hashless row for RECORD itself
+    fn record(&self, transaction: &Transaction, record: &Path) -> Result<String> {
+        let site = self.site_packages.canonical_path();
+        let parent = record.parent().ok_or_else(|| anyhow!("{} has no parent directory", record.display()))?;
+        let record = parent.canonicalize()?.join("RECORD");
+        let mut rows: Vec<String> = transaction
+            .files()
+            .iter()
+            .filter(|file| file.resolved != record)
+            .map(|file| {
+                let path = record_field(&relative_path(&file.resolved, site));
+                format!("{},sha256={},{}", path, file.sha256, file.size)
+            })
+            .collect();
+        rows.sort();
+        rows.dedup();
+        rows.push(format!("{},,", record_field(&relative_path(&record, site))));
+
+        let mut content = rows.join("\n");
+        content.push('\n');
+        Ok(content)
+    }
 
+    /// Stage the dist-info, all but the RECORD written afresh, and the
+    /// console scripts its entry_points.txt declares
+    fn stage_metadata(
+        &self,
+        source: &Path,
+        dist_info_name: &str,
+        scheme: &Scheme,
+        transaction: &mut Transaction,
+    ) -> Result<()> {
+        let target = self.site_packages.path().join(dist_info_name);
         for entry in std::fs::read_dir(source)? {
             let entry = entry?;
             let path = entry.path();
-            let file_name = entry.file_name();
-            let target_file = target.join(&file_name);
+            if path.is_dir() {
+                // e.g. `licenses/`
+                transaction.stage_tree(&path, &target.join(entry.file_name()))?;
+            } else if path.is_file() && entry.file_name() != "RECORD" {
+                transaction.stage_file(&path, &target.join(entry.file_name()))?;
+            }
+        }
 
-            if path.is_file() {
-                std::fs::copy(&path, &target_file)?;
+        // Console scripts declared in entry_points.txt
+        if let Ok(entry_points) = std::fs::read_to_string(source.join("entry_points.txt")) {
+            let package = dist_info_name
+                .trim_end_matches(".dist-info")
+                .split_once('-')
+                .map_or(dist_info_name, |(name, _)| name);
+            for script in entry_point::console_scripts(&entry_points) {
+                let mut script = script.with_owner(package, self.site_packages.path());
+                // Scripts start the environment's own interpreter
+                if let Some(python) = &scheme.interpreter {
+                    script = script.with_interpreter(python);
+                }
+                for (file_name, content) in script.files(cfg!(target_os = "windows")) {
+                    transaction.stage_bytes(&scheme.scripts.join(file_name), content.as_bytes(), true)?;
+                }
             }
         }
 
         Ok(())
     }
 
-    fn install_data_files(&self, data_dir: &Path) -> Result<()> {
-        // Handle purelib, platlib, headers, scripts, data
+    /// Stage the trees of a wheel's `.data` directory for their scheme
+    /// directories. `dist` names the headers directory.
+    fn stage_data_files(
+        &self,
+        data_dir: &Path,
+        dist: &str,
+        scheme: &Scheme,
+        transaction: &mut Transaction,
+    ) -> Result<()> {
         for entry in std::fs::read_dir(data_dir)? {
             let entry = entry?;
             let path = entry.path();
-            let dir_type = entry.file_name();
-
-            if let Some(dir_type_str) = dir_type.to_str() {
-                match dir_type_str {
-                    "purelib" | "platlib" => {
-                        // Install to site-packages
-                        for file_entry in std::fs::read_dir(&path)? {
-                            let file_entry = file_entry?;
-                            let file_path = file_entry.path();
-                            let file_name = file_entry.file_name();
-                            
-                            if file_path.is_dir() {
-                                self.site_packages.install_directory(&file_path, Path::new(&file_name))?;
-                            }
-                        }
-                    }
-                    "scripts" => {
-                        // Install to bin directory
-                        self.install_scripts(&path)?;
-                    }
-                    _ => {}
-                }
+            let key = entry.file_name().to_string_lossy().to_string();
+            let Some(root) = scheme.root(&key, dist) else {
+                tracing::warn!("Ignoring unknown wheel data directory {}", path.display());
+                continue;
+            };
+            if key == "scripts" {
+                self.stage_scripts(&path, &root, scheme.interpreter.as_deref(), transaction)?;
+            } else {
+                transaction.stage_tree(&path, &root)?;
             }
         }
 
         Ok(())
     }
 
-    fn install_scripts(&self, scripts_dir: &Path) -> Result<()> {
-        // Get the bin directory (usually ~/.local/bin or /usr/local/bin)
-        let bin_dir = if cfg!(target_os = "windows") {
-            // On Windows, use Scripts directory
-            if let Ok(home) = std::env::var("USERPROFILE") {
-                std::path::PathBuf::from(home).join("Scripts")
-            } else {
-                std::path::PathBuf::from("Scripts")
-            }
-        } else {
-            // On Unix-like systems, use ~/.local/bin
-            if let Ok(home) = std::env::var("HOME") {
-                std::path::PathBuf::from(home).join(".local").join("bin")
-            } else {
-                std::path::PathBuf::from("/usr/local/bin")
-            }
-        };
-
-        // Create bin directory if it doesn't exist
-        std::fs::create_dir_all(&bin_dir)?;
-
-        // Copy scripts to bin directory
-        for entry in std::fs::read_dir(scripts_dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            
-            if path.is_file() {
-                let file_name = entry.file_name();
-                let target = bin_dir.join(&file_name);
-                
-                // Copy the script
-                std::fs::copy(&path, &target)?;
-                
-                // Make executable on Unix
-                #[cfg(unix)]
-                {
-                    use std::os::unix::fs::PermissionsExt;
-                    let perms = std::fs::Permissions::from_mode(0o755);
-                    std::fs::set_permissions(&target, perms)?;
-                }
-            }
+    /// Stage `.data/scripts` for `bin_dir` as executables, pointing
+    /// `#!python` lines at `interpreter`
+    fn stage_scripts(
+        &self,
+        scripts_dir: &Path,
+        bin_dir: &Path,
+        interpreter: Option<&Path>,
+        transaction: &mut Transaction,
+    ) -> Result<()> {
+        for file in WalkDir::new(scripts_dir).into_iter().flatten().filter(|e| e.file_type().is_file()) {
+            let Ok(relative) = file.path().strip_prefix(scripts_dir) else {
+                continue;
+            };
+            let content = std::fs::read(file.path())?;
+            let content = entry_point::rewrite_shebang(&content, interpreter).unwrap_or(content);
+            transaction.stage_bytes(&bin_dir.join(relative), &content, true)?;
         }
 
         Ok(())
     }
 
     pub async fn uninstall(&self, package_name: &str) -> Result<()> {
-        println!("Uninstalling {}", package_name);
+        progress::status(format!("Uninstalling {}", package_name));
         
-        let dist_info = self.site_packages.path().join(format!("{}.dist-info", package_name));
-        if dist_info.exists() {
-            std::fs::remove_dir_all(&dist_info)?;
-            println!("Successfully uninstalled {}", package_name);
-        } else {
-            return Err(anyhow!("Package {} not found", package_name));
-        }
-
+        let site = self.site_packages.path();
+        let _lock = EnvironmentLock::acquire(site)?;
+        let plan = super::uninstall::UninstallPlan::new(site, &super::uninstall::scripts_dirs(site), package_name)
+            .ok_or_else(|| anyhow!("Package {} not found", package_name))?;
+        plan.execute(site, false)?;
+        progress::status(format!("Successfully uninstalled {}", plan.label()));
         Ok(())
     }
 
@@ -195,9 +413,304 @@ impl PackageInstaller {
     }
 }
 
+/// Path column of a RECORD row: `/`-separated, quoted CSV-style when it
+/// holds a comma or quote
+fn record_field(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    if path.contains([',', '"', '\n']) {
+        format!("\"{}\"", path.replace('"', "\"\""))
+    } else {
+        path
+    }
+}
+
+/// `path` relative to `base`, climbing out with `..` where needed; both
+/// are taken as already resolved
+fn relative_path(path: &Path, base: &Path) -> PathBuf {
+    let path_parts: Vec<Component> = path.components().collect();
+    let base_parts: Vec<Component> = base.components().collect();
+    let common = path_parts.iter().zip(&base_parts).take_while(|(a, b)| a == b).count();
+    if common == 0 {
+        // Another drive: only an absolute path reaches it
+        return path.to_path_buf();
+    }
+    let mut relative = PathBuf::new();
+    for _ in common..base_parts.len() {
+        relative.push("..");
+    }
+    relative.extend(&path_parts[common..]);
+    relative
+}
+
 impl PackageInstaller {
     pub fn default_installer() -> Result<Self> {
         let site_packages = SitePackages::default()?;
         Ok(Self::new(site_packages))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine;
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use sha2::{Digest, Sha256};
+    use std::io::Write;
+    
+    fn build_wheel(dir: &Path, version: &str, native: &[u8]) -> WheelFile {
+        let path = dir.join(format!("demo-{}-py3-none-any.whl", version));
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+        let options = zip::write::FileOptions::default();
+        zip.start_file("demo/_native.so", options).unwrap();
+        zip.write_all(native).unwrap();
+        zip.start_file(format!("demo-{}.dist-info/METADATA", version), options).unwrap();
+        zip.write_all(format!("Metadata-Version: 2.1\nName: demo\nVersion: {}\n", version).as_bytes())
+            .unwrap();
+        zip.start_file(format!("demo-{}.dist-info/RECORD", version), options).unwrap();
+        zip.write_all(format!("demo/_native.so,,\ndemo-{0}.dist-info/METADATA,,\ndemo-{0}.dist-info/RECORD,,\n", version).as_bytes())
+            .unwrap();
+        zip.finish().unwrap();
+        WheelFile::new(path).unwrap()
+    }
+
+    fn build_tool_wheel(dir: &Path, version: &str) -> WheelFile {
+        let path = dir.join(format!("tool-{}-py3-none-any.whl", version));
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+        let options = zip::write::FileOptions::default();
+        let dist_info = format!("tool-{}.dist-info", version);
+        for (name, content) in [
+            ("tool/__init__.py".to_string(), "def main(): pass\n".to_string()),
+            (format!("{}/METADATA", dist_info), format!("Metadata-Version: 2.1\nName: tool\nVersion: {}\n", version)),
+            (format!("{}/entry_points.txt", dist_info), "[console_scripts]\ntool = tool:main\n".to_string()),
+            (format!("{}/RECORD", dist_info), format!("tool/__init__.py,,\n{0}/METADATA,,\n{0}/RECORD,,\n", dist_info)),
+        ] {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+        WheelFile::new(path).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_failed_install_removes_what_it_wrote() {
+        let dir = tempfile::tempdir().unwrap();
+        let site = dir.path().join("site-packages");
+        // A directory where the wheel's top-level module goes makes the copy fail
+        std::fs::create_dir_all(site.join("clash.py/inner")).unwrap();
+        let path = dir.path().join("demo-1.0-py3-none-any.whl");
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+        let options = zip::write::FileOptions::default();
+        for (name, content) in [
+            ("demo/__init__.py", ""),
+            ("clash.py", "x = 1\n"),
+            ("demo-1.0.dist-info/METADATA", "Metadata-Version: 2.1\nName: demo\nVersion: 1.0\n"),
+            ("demo-1.0.dist-info/RECORD", "demo/__init__.py,,\nclash.py,,\ndemo-1.0.dist-info/METADATA,,\n"),
+        ] {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+
+        let installer = PackageInstaller::new(SitePackages::new(site.clone()).unwrap());
+        assert!(installer.install_wheel(&WheelFile::new(path).unwrap()).await.is_err());
+        assert!(!site.join("demo").exists());
+        assert!(!site.join("demo-1.0.dist-info").exists());
+        assert!(site.join("clash.py/inner").is_dir());
+        let staged = std::fs::read_dir(&site).unwrap().flatten().map(|entry| entry.file_name());
+        assert!(staged.filter(|name| name.to_string_lossy().starts_with(".pip-rs-stage-")).count() == 0);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_console_scripts_in_venv_bin_recorded_and_guarded() {
+        let dir = tempfile::tempdir().unwrap();
+        let venv = dir.path().join("venv");
+        let site = venv.join("lib/python3.12/site-packages");
+        let bin = venv.join("bin");
+        std::fs::create_dir_all(&bin).unwrap();
+        std::fs::write(venv.join("pyvenv.cfg"), "home = /usr/bin\n").unwrap();
+        std::fs::write(bin.join("python"), "").unwrap();
+        std::fs::write(bin.join("tool"), "#!/bin/sh\necho another tool\n").unwrap();
+        let installer = PackageInstaller::new(SitePackages::new(site.clone()).unwrap());
+
+        // Someone else's `tool` is in the way
+        let err = installer.install_wheel(&build_tool_wheel(dir.path(), "1.0")).await.unwrap_err();
+        assert_eq!((err.kind, err.package.as_str()), (InstallErrorKind::Conflict, "tool"));
+        assert_eq!(err.path, site);
+        let err = err.to_string();
+        assert!(err.contains("--force-reinstall") && err.contains("bin/tool"), "{}", err);
+        assert!(!site.join("tool-1.0.dist-info").exists());
+
+        let forced = PackageInstaller::new(SitePackages::new(site.clone()).unwrap()).with_overwrite(Overwrite {
+            force_reinstall: true,
+            ..Overwrite::default()
+        });
+        forced.install_wheel(&build_tool_wheel(dir.path(), "1.0")).await.unwrap();
+        let script = std::fs::read_to_string(bin.join("tool")).unwrap();
+        let python = venv.canonicalize().unwrap().join("bin/python");
+        assert!(script.starts_with(&format!("#!{}\n", python.display())), "{}", script);
+        let record = std::fs::read_to_string(site.join("tool-1.0.dist-info/RECORD")).unwrap();
+        assert!(record.lines().any(|line| line.starts_with("../../../bin/tool,sha256=")), "{}", record);
+
+        // A later version may replace the script pip-rs wrote for it
+        let upgrade = build_tool_wheel(dir.path(), "2.0");
+        let extracted = tempfile::tempdir().unwrap();
+        upgrade.extract(extracted.path()).unwrap();
+        installer.check_script_clashes(extracted.path(), "tool").unwrap();
+
+        installer.uninstall("tool").await.unwrap();
+        assert!(!bin.join("tool").exists() && !site.join("tool").exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_data_trees_relocated_and_record_rewritten() {
+        let dir = tempfile::tempdir().unwrap();
+        let venv = dir.path().join("venv");
+        let site = venv.join("lib/python3.12/site-packages");
+        std::fs::create_dir_all(venv.join("bin")).unwrap();
+        std::fs::write(venv.join("pyvenv.cfg"), "home = /usr/bin\n").unwrap();
+        std::fs::write(venv.join("bin/python"), "").unwrap();
+
+        let path = dir.path().join("kit-1.0-py3-none-any.whl");
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+        let options = zip::write::FileOptions::default();
+        for (name, content) in [
+            ("kit/__init__.py", "VALUE = 1\n"),
+            ("kit-1.0.data/purelib/kit_extra.py", "EXTRA = 1\n"),
+            ("kit-1.0.data/scripts/kit-run", "#!python\nimport kit\n"),
+            ("kit-1.0.data/headers/kit.h", "int kit(void);\n"),
+            ("kit-1.0.data/data/share/kit/config,v1.json", "{}\n"),
+            ("kit-1.0.dist-info/METADATA", "Metadata-Version: 2.1\nName: kit\nVersion: 1.0\n"),
+            (
+                "kit-1.0.dist-info/RECORD",
+                "kit/__init__.py,,\nkit-1.0.data/purelib/kit_extra.py,,\nkit-1.0.data/scripts/kit-run,,\n\
+                 kit-1.0.data/headers/kit.h,,\n\"kit-1.0.data/data/share/kit/config,v1.json\",,\n\
+                 kit-1.0.dist-info/METADATA,,\nkit-1.0.dist-info/RECORD,,\n",
+            ),
+        ] {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+        let installer = PackageInstaller::new(SitePackages::new(site.clone()).unwrap());
+        installer.install_wheel(&WheelFile::new(path).unwrap()).await.unwrap();
+
+        let venv = venv.canonicalize().unwrap();
+        assert!(site.join("kit_extra.py").is_file());
+        assert!(venv.join("include/site/python3.12/kit/kit.h").is_file());
+        assert!(venv.join("share/kit/config,v1.json").is_file());
+        assert!(!site.join("kit-1.0.data").exists());
+        let script = std::fs::read_to_string(venv.join("bin/kit-run")).unwrap();
+        assert_eq!(script, format!("#!{}\nimport kit\n", venv.join("bin/python").display()));
+
+        let record = std::fs::read_to_string(site.join("kit-1.0.dist-info/RECORD")).unwrap();
+        let paths: Vec<&str> = record.lines().map(|line| line.rsplitn(3, ',').nth(2).unwrap()).collect();
+        assert_eq!(
+            paths,
+            [
+                "\"../../../share/kit/config,v1.json\"",
+                "../../../bin/kit-run",
+                "../../../include/site/python3.12/kit/kit.h",
+                "kit-1.0.dist-info/METADATA",
+                "kit/__init__.py",
+                "kit_extra.py",
+                "kit-1.0.dist-info/RECORD",
+            ]
+        );
+        let expected = format!("sha256={},", URL_SAFE_NO_PAD.encode(Sha256::digest(script.as_bytes())));
+        assert!(record.contains(&format!("../../../bin/kit-run,{}{}\n", expected, script.len())), "{}", record);
+        assert!(record.ends_with("kit-1.0.dist-info/RECORD,,\n"));
+
+        let plan = super::super::uninstall::UninstallPlan::new(&site, &[venv.join("bin")], "kit").unwrap();
+        plan.execute(&site, true).unwrap();
+        assert!(!venv.join("bin/kit-run").exists() && !venv.join("share/kit/config,v1.json").exists());
+        assert!(!venv.join("include/site/python3.12/kit/kit.h").exists() && !site.join("kit_extra.py").exists());
+    }
+
+    /// Map `path` read-only, the way a running interpreter maps an extension
+    #[cfg(target_os = "linux")]
+    fn map(path: &Path) -> &'static [u8] {
+        use std::os::fd::AsRawFd;
+        let file = std::fs::File::open(path).unwrap();
+        let len = file.metadata().unwrap().len() as usize;
+        let ptr = unsafe {
+            libc::mmap(std::ptr::null_mut(), len, libc::PROT_READ, libc::MAP_SHARED, file.as_raw_fd(), 0)
+        };
+        assert_ne!(ptr, libc::MAP_FAILED);
+        unsafe { std::slice::from_raw_parts(ptr as *const u8, len) }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_upgrade_while_mapped_keeps_old_mapping_and_names_pid() {
+        let dir = tempfile::tempdir().unwrap();
+        let site = dir.path().join("site-packages");
+        let installer = PackageInstaller::new(SitePackages::new(site.clone()).unwrap());
+        installer.install_wheel(&build_wheel(dir.path(), "1.0", b"old native code")).await.unwrap();
+        let native = site.join("demo").join("_native.so");
+        let held = map(&native);
+
+        let upgrade = build_wheel(dir.path(), "2.0", b"new native code, longer");
+        let extracted = tempfile::tempdir().unwrap();
+        upgrade.extract(extracted.path()).unwrap();
+        let in_use = installer.check_in_use(extracted.path(), "demo").unwrap();
+        let me = in_use.iter().find(|p| p.pid == std::process::id()).expect("test process not detected");
+        assert_eq!(me.paths, [native.canonicalize().unwrap()]);
+        let notice = replace::in_use_notice("demo", &in_use).join("\n");
+        assert!(notice.contains(&format!("PID {}", std::process::id())), "{}", notice);
+
+        installer.install_wheel(&upgrade).await.unwrap();
+        assert_eq!(held, b"old native code");
+        assert_eq!(std::fs::read(&native).unwrap(), b"new native code, longer");
+        unsafe { libc::munmap(held.as_ptr() as *mut libc::c_void, held.len()) };
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_refuse_if_in_use_aborts_before_writing() {
+        let dir = tempfile::tempdir().unwrap();
+        let site = dir.path().join("site-packages");
+        let installer = PackageInstaller::new(SitePackages::new(site.clone()).unwrap()).with_refuse_if_in_use(true);
+        installer.install_wheel(&build_wheel(dir.path(), "1.0", b"old native code")).await.unwrap();
+        let native = site.join("demo").join("_native.so");
+        let held = map(&native);
+
+        let err = installer
+            .install_wheel(&build_wheel(dir.path(), "2.0", b"new native code"))
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("--refuse-if-in-use"), "{}", err);
+        assert!(err.contains(&format!("PID {}", std::process::id())), "{}", err);
+        assert_eq!(std::fs::read(&native).unwrap(), b"old native code");
+        assert!(!site.join("demo-2.0.dist-info").exists());
+        unsafe { libc::munmap(held.as_ptr() as *mut libc::c_void, held.len()) };
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_symlinked_site_packages_install_list_uninstall_agree() {
+        let dir = tempfile::tempdir().unwrap();
+        let share = dir.path().join("nfs/site-packages");
+        std::fs::create_dir_all(&share).unwrap();
+        let link = dir.path().join("venv-site-packages");
+        std::os::unix::fs::symlink(&share, &link).unwrap();
+
+        // Reached through the symlink, with a trailing slash
+        let site = SitePackages::new(PathBuf::from(format!("{}/", link.display()))).unwrap();
+        assert_eq!(site.canonical_path(), share.canonicalize().unwrap());
+        assert!(site.is_same_dir(&share) && site.contains(&link.join("demo/_native.so")));
+        let installer = PackageInstaller::new(site);
+        installer.install_wheel(&build_wheel(dir.path(), "1.0", b"native")).await.unwrap();
+
+        assert!(share.join("demo/_native.so").is_file());
+        assert_eq!(installer.list_installed().unwrap(), ["demo"]);
+        assert_eq!(PackageInstaller::new(SitePackages::new(share.clone()).unwrap()).list_installed().unwrap(), ["demo"]);
+
+        installer.uninstall("Demo").await.unwrap();
+        assert!(!share.join("demo").exists() && !share.join("demo-1.0.dist-info").exists());
+        assert!(installer.list_installed().unwrap().is_empty());
+        assert!(installer.uninstall("demo").await.unwrap_err().to_string().contains("not found"));
+    }
+}
@@ -8,8 +8,29 @@ pub mod entry_point;
 pub mod editable;
 pub mod editable_cache;
 pub mod egg_link_handler;
+pub mod env_lock;
+pub mod error;
+pub mod existing;
+pub mod externally_managed;
+pub mod fallback;
+pub mod venv_guard;
+pub mod batch;
+pub mod consistency;
+pub mod events;
+pub mod import_names;
+pub mod inspect;
+pub mod preflight;
+pub mod provenance;
+pub mod replace;
+pub mod scheme;
+pub mod sizes;
+pub mod source_tree;
+pub mod sync_plan;
+pub mod transaction;
+pub mod uninstall;
 
-pub use installer::PackageInstaller;
-pub use site_packages::SitePackages;
+pub use installer::{Overwrite, PackageInstaller};
+pub use error::{InstallError, InstallErrorKind};
+pub use site_packages::{DistInfoEntry, SitePackages};
 pub use editable_cache::EditableCache;
 pub use egg_link_handler::{EggLinkInfo, EggLinkHandler};
@@ -0,0 +1,458 @@
+/// Pre-flight environment scan
+///
+/// Interrupted runs of other installers can leave site-packages half-written:
+/// empty METADATA, RECORD entries escaping the environment, two dist-infos
+/// for one project, or dist-info directories whose names don't parse. The
+/// scan runs before install/uninstall/upgrade mutate anything, and also
+/// backs `pip check`, which additionally looks for console scripts left
+/// behind by packages that are gone.
+use super::entry_point::{self, ScriptOwner};
+use super::site_packages::canonical_path;
+pub use crate::utils::names::canonicalize_name;
+use crate::utils::names::split_dist_info;
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// Kind of structural problem found in site-packages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AnomalyKind {
+    /// METADATA is missing or zero bytes
+    EmptyMetadata,
+    /// A RECORD entry points outside the environment
+    RecordOutsideEnvironment,
+    /// Several dist-infos for the same canonical name
+    DuplicateDistribution,
+    /// The dist-info directory name isn't `name-version.dist-info`
+    UnparseableName,
+    /// A console script whose package is no longer installed
+    OrphanedScript,
+}
+
+impl AnomalyKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AnomalyKind::EmptyMetadata => "empty-metadata",
+            AnomalyKind::RecordOutsideEnvironment => "record-outside-environment",
+            AnomalyKind::DuplicateDistribution => "duplicate-distribution",
+            AnomalyKind::UnparseableName => "unparseable-name",
+            AnomalyKind::OrphanedScript => "orphaned-script",
+        }
+    }
+}
+
+/// One problem, tied to the distribution it belongs to (when known)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Anomaly {
+    pub kind: AnomalyKind,
+    /// Canonical project name, if it could be determined
+    pub project: Option<String>,
+    pub dist_info: PathBuf,
+    pub detail: String,
+}
+
+impl std::fmt::Display for Anomaly {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let dir = self
+            .dist_info
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        write!(f, "{} [{}]: {}", dir, self.kind.as_str(), self.detail)
+    }
+}
+
+/// Result of scanning one site-packages directory
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct EnvironmentScan {
+    pub anomalies: Vec<Anomaly>,
+}
+
+impl EnvironmentScan {
+    /// Scan the `.dist-info` directories of `site_packages`
+    pub fn scan(site_packages: &Path) -> Result<Self> {
+        let boundary = Boundary::of(site_packages);
+        let mut anomalies = Vec::new();
+        let mut by_project: BTreeMap<String, Vec<(PathBuf, String)>> = BTreeMap::new();
+
+        let mut entries: Vec<_> = fs::read_dir(site_packages)?
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p.is_dir() && p.extension().is_some_and(|ext| ext == "dist-info"))
+            .collect();
+        entries.sort();
+
+        for dist_info in entries {
+            let stem = dist_info
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let parsed = parse_dist_info_name(&stem);
+            let project = parsed.as_ref().map(|(name, _)| canonicalize_name(name));
+
+            if parsed.is_none() {
+                anomalies.push(Anomaly {
+                    kind: AnomalyKind::UnparseableName,
+                    project: None,
+                    dist_info: dist_info.clone(),
+                    detail: "directory name does not parse as <name>-<version>.dist-info".to_string(),
+                });
+            }
+
+            match fs::metadata(dist_info.join("METADATA")) {
+                Ok(meta) if meta.len() > 0 => {}
+                Ok(_) => anomalies.push(Anomaly {
+                    kind: AnomalyKind::EmptyMetadata,
+                    project: project.clone(),
+                    dist_info: dist_info.clone(),
+                    detail: "METADATA is empty (zero bytes)".to_string(),
+                }),
+                Err(_) => anomalies.push(Anomaly {
+                    kind: AnomalyKind::EmptyMetadata,
+                    project: project.clone(),
+                    dist_info: dist_info.clone(),
+                    detail: "METADATA is missing".to_string(),
+                }),
+            }
+
+            if let Ok(record) = fs::read_to_string(dist_info.join("RECORD")) {
+                for path in record.lines().filter_map(|l| l.split(',').next()) {
+                    if !path.is_empty() && !boundary.contains(site_packages, path) {
+                        anomalies.push(Anomaly {
+                            kind: AnomalyKind::RecordOutsideEnvironment,
+                            project: project.clone(),
+                            dist_info: dist_info.clone(),
+                            detail: format!("RECORD references '{}' outside the environment", path),
+                        });
+                    }
+                }
+            }
+
+            if let (Some(project), Some((_, version))) = (project, parsed) {
+                by_project.entry(project).or_default().push((dist_info, version));
+            }
+        }
+
+        for (project, dists) in by_project.into_iter().filter(|(_, d)| d.len() > 1) {
+            let versions: BTreeSet<&str> = dists.iter().map(|(_, v)| v.as_str()).collect();
+            let versions: Vec<&str> = versions.into_iter().collect();
+            for (dist_info, _) in &dists {
+                anomalies.push(Anomaly {
+                    kind: AnomalyKind::DuplicateDistribution,
+                    project: Some(project.clone()),
+                    dist_info: dist_info.clone(),
+                    detail: format!("{} is installed more than once ({})", project, versions.join(", ")),
+                });
+            }
+        }
+
+        Ok(Self { anomalies })
+    }
+
+    /// Add console scripts in `scripts_dir` whose package is missing from
+    /// `site_packages`. Wrappers written by other installers are only
+    /// judged when their interpreter belongs to this environment.
+    pub fn scan_scripts(&mut self, scripts_dir: &Path, site_packages: &Path) -> Result<()> {
+        let Ok(entries) = fs::read_dir(scripts_dir) else {
+            return Ok(());
+        };
+        let root = environment_root(site_packages);
+        let installed: BTreeSet<String> = fs::read_dir(site_packages)?
+            .flatten()
+            .filter_map(|e| {
+                let name = e.file_name().to_string_lossy().to_string();
+                let (name, _) = parse_dist_info_name(name.strip_suffix(".dist-info")?)?;
+                Some(canonicalize_name(&name))
+            })
+            .collect();
+        let mut scripts: Vec<PathBuf> = entries
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p.is_file() && fs::metadata(p).is_ok_and(|m| m.len() <= MAX_SCRIPT_SIZE))
+            .collect();
+        scripts.sort();
+
+        for script in scripts {
+            let Ok(content) = fs::read_to_string(&script) else {
+                continue;
+            };
+            let anomaly = match entry_point::script_owner(&content) {
+                Some(ScriptOwner::PipRs { package, module }) if !installed.contains(&canonicalize_name(&package)) => {
+                    Some((
+                        Some(canonicalize_name(&package)),
+                        format!("runs {} from package '{}', which is not installed", module, package),
+                    ))
+                }
+                Some(ScriptOwner::Foreign { module }) if uses_interpreter_in(&content, &root) => {
+                    let top = module.split('.').next().unwrap_or(&module);
+                    (!module_present(site_packages, top))
+                        .then(|| (None, format!("imports '{}', which is not installed in this environment", module)))
+                }
+                _ => None,
+            };
+            if let Some((project, detail)) = anomaly {
+                self.anomalies.push(Anomaly {
+                    kind: AnomalyKind::OrphanedScript,
+                    project,
+                    dist_info: script,
+                    detail,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.anomalies.is_empty()
+    }
+
+    /// Split anomalies into those touching `projects` (or whose project is
+    /// unknown, so they might) and unrelated ones
+    pub fn partition(&self, projects: &[String]) -> (Vec<&Anomaly>, Vec<&Anomaly>) {
+        let projects: BTreeSet<String> = projects.iter().map(|p| canonicalize_name(p)).collect();
+        self.anomalies.iter().partition(|a| match &a.project {
+            Some(project) => projects.contains(project),
+            None => true,
+        })
+    }
+}
+
+/// Console scripts are small; anything larger is not a wrapper
+const MAX_SCRIPT_SIZE: u64 = 64 * 1024;
+
+/// Whether the script's shebang interpreter lives under `root`
+fn uses_interpreter_in(content: &str, root: &Path) -> bool {
+    content
+        .lines()
+        .next()
+        .and_then(|line| line.strip_prefix("#!"))
+        .and_then(|line| line.split_whitespace().next())
+        .is_some_and(|interpreter| Path::new(interpreter).starts_with(root))
+}
+
+/// Whether top-level module `top` is importable from `site_packages`
+fn module_present(site_packages: &Path, top: &str) -> bool {
+    site_packages.join(top).is_dir()
+        || fs::read_dir(site_packages).is_ok_and(|entries| {
+            entries
+                .flatten()
+                .any(|e| e.file_name().to_string_lossy().starts_with(&format!("{}.", top)) && e.path().is_file())
+        })
+}
+
+/// `name-version` from a dist-info stem (the name part uses `_` for `-`,
+/// though legacy installers kept the dashes)
+fn parse_dist_info_name(stem: &str) -> Option<(String, String)> {
+    let file_name = format!("{}.dist-info", stem);
+    let (name, version) = split_dist_info(&file_name)?;
+    let valid_name = name.chars().all(|c| c.is_alphanumeric() || "-_.".contains(c));
+    let valid_version = !version.is_empty() && version.chars().all(|c| c.is_alphanumeric() || "._+!".contains(c));
+    (valid_name && valid_version).then(|| (name.to_string(), version.to_string()))
+}
+
+/// The environment prefix: the ancestor holding `pyvenv.cfg`, or the
+/// conventional `<prefix>/lib/pythonX.Y/site-packages` layout. Scripts and
+/// data files under the prefix (`../../../bin/tool`) are legitimate.
+fn environment_root(site_packages: &Path) -> PathBuf {
+    site_packages
+        .ancestors()
+        .find(|dir| dir.join("pyvenv.cfg").is_file())
+        .or_else(|| site_packages.ancestors().nth(3))
+        .unwrap_or(site_packages)
+        .to_path_buf()
+}
+
+/// Where RECORD paths may point, with symlinks resolved: the environment
+/// prefix, or site-packages itself when it is a symlink out of the prefix
+struct Boundary {
+    root: PathBuf,
+    site_packages: PathBuf,
+}
+
+impl Boundary {
+    fn of(site_packages: &Path) -> Self {
+        Self {
+            root: canonical_path(&environment_root(site_packages)),
+            site_packages: canonical_path(site_packages),
+        }
+    }
+
+    /// Whether a RECORD path, relative to site-packages, stays inside.
+    /// `..` is resolved the way the filesystem will when the file is
+    /// removed: from where site-packages really is, not from the symlink.
+    fn contains(&self, site_packages: &Path, record_path: &str) -> bool {
+        let record_path = Path::new(record_path);
+        let resolved = if record_path.is_absolute() {
+            canonical_path(record_path)
+        } else if record_path.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir)) {
+            self.site_packages.join(record_path)
+        } else if record_path.components().any(|c| matches!(c, Component::RootDir | Component::Prefix(_))) {
+            return false;
+        } else {
+            canonical_path(&site_packages.join(record_path))
+        };
+        resolved.starts_with(&self.root) || resolved.starts_with(&self.site_packages)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// `<tmp>/venv/lib/python3.11/site-packages` with a `pyvenv.cfg`
+    fn environment() -> (TempDir, PathBuf) {
+        let temp = TempDir::new().unwrap();
+        let site = temp.path().join("venv/lib/python3.11/site-packages");
+        fs::create_dir_all(&site).unwrap();
+        fs::write(temp.path().join("venv/pyvenv.cfg"), "home = /usr/bin\n").unwrap();
+        (temp, site)
+    }
+
+    fn dist_info(site: &Path, dir: &str, metadata: &str, record: &str) {
+        let path = site.join(dir);
+        fs::create_dir_all(&path).unwrap();
+        fs::write(path.join("METADATA"), metadata).unwrap();
+        fs::write(path.join("RECORD"), record).unwrap();
+    }
+
+    #[test]
+    fn test_detects_each_anomaly_class() {
+        let (_temp, site) = environment();
+        dist_info(&site, "requests-2.31.0.dist-info", "Name: requests\n", "requests/__init__.py,,\n../../../bin/req,,\n");
+        dist_info(&site, "truncated-1.0.dist-info", "", "truncated/__init__.py,,\n");
+        dist_info(&site, "escaper-0.1.dist-info", "Name: escaper\n", "../../../../etc/profile.d/x.sh,,\n/etc/passwd,,\n");
+        dist_info(&site, "Six-1.16.0.dist-info", "Name: six\n", "six.py,,\n");
+        dist_info(&site, "six-1.15.0.dist-info", "Name: six\n", "six.py,,\n");
+        dist_info(&site, "leftover.dist-info", "Name: leftover\n", "");
+        dist_info(&site, "Flask-SQLAlchemy-3.1.1.dist-info", "Name: Flask-SQLAlchemy\n", "flask_sqlalchemy.py,,\n");
+
+        let scan = EnvironmentScan::scan(&site).unwrap();
+        let kinds = |kind: AnomalyKind| -> Vec<Option<&str>> {
+            scan.anomalies.iter().filter(|a| a.kind == kind).map(|a| a.project.as_deref()).collect()
+        };
+        assert_eq!(kinds(AnomalyKind::EmptyMetadata), vec![Some("truncated")]);
+        assert_eq!(kinds(AnomalyKind::RecordOutsideEnvironment), vec![Some("escaper"), Some("escaper")]);
+        assert_eq!(kinds(AnomalyKind::DuplicateDistribution), vec![Some("six"), Some("six")]);
+        assert_eq!(kinds(AnomalyKind::UnparseableName), vec![None]);
+        assert!(scan.anomalies.iter().all(|a| a.project.as_deref() != Some("requests")));
+        assert!(scan.anomalies.iter().all(|a| !a.dist_info.ends_with("Flask-SQLAlchemy-3.1.1.dist-info")));
+    }
+
+    #[test]
+    fn test_partition_by_operated_packages() {
+        let (_temp, site) = environment();
+        dist_info(&site, "truncated-1.0.dist-info", "", "");
+        dist_info(&site, "six-1.16.0.dist-info", "Name: six\n", "");
+        dist_info(&site, "six-1.15.0.dist-info", "Name: six\n", "");
+
+        let scan = EnvironmentScan::scan(&site).unwrap();
+        let (affecting, unrelated) = scan.partition(&["Six".to_string()]);
+        assert_eq!(affecting.len(), 2);
+        assert_eq!(unrelated.len(), 1);
+        assert_eq!(unrelated[0].project.as_deref(), Some("truncated"));
+
+        let (affecting, _) = scan.partition(&["requests".to_string()]);
+        assert!(affecting.is_empty());
+    }
+
+    #[test]
+    fn test_orphaned_scripts() {
+        let (temp, site) = environment();
+        let bin = temp.path().join("venv/bin");
+        fs::create_dir_all(&bin).unwrap();
+        dist_info(&site, "black-24.1.0.dist-info", "Name: black\n", "");
+        fs::create_dir_all(site.join("httpie")).unwrap();
+        let script = |name: &str, module: &str, package: &str| {
+            entry_point::EntryPoint::new(name.to_string(), module.to_string(), "main".to_string())
+                .with_owner(package, &site)
+                .render(false)
+        };
+        fs::write(bin.join("black"), script("black", "black", "black")).unwrap();
+        fs::write(bin.join("ruff-lsp"), script("ruff-lsp", "ruff_lsp.__main__", "ruff-lsp")).unwrap();
+        let python = temp.path().join("venv/bin/python");
+        let foreign = |module: &str| format!("#!{}\nimport sys\nfrom {} import main\n", python.display(), module);
+        fs::write(bin.join("http"), foreign("httpie.core")).unwrap();
+        fs::write(bin.join("flake8"), foreign("flake8.main.cli")).unwrap();
+        // Other interpreters' wrappers and non-Python files are not judged
+        fs::write(bin.join("system-tool"), "#!/usr/bin/python3\nfrom gone import main\n").unwrap();
+        fs::write(bin.join("activate"), "# source this\n").unwrap();
+
+        let mut scan = EnvironmentScan::scan(&site).unwrap();
+        scan.scan_scripts(&bin, &site).unwrap();
+        let orphans: Vec<(String, Option<&str>)> = scan
+            .anomalies
+            .iter()
+            .filter(|a| a.kind == AnomalyKind::OrphanedScript)
+            .map(|a| (a.to_string(), a.project.as_deref()))
+            .collect();
+        assert_eq!(
+            orphans,
+            vec![
+                (
+                    "flake8 [orphaned-script]: imports 'flake8.main.cli', which is not installed in this environment"
+                        .to_string(),
+                    None
+                ),
+                (
+                    "ruff-lsp [orphaned-script]: runs ruff_lsp.__main__ from package 'ruff-lsp', which is not installed"
+                        .to_string(),
+                    Some("ruff-lsp")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_clean_environment_scan_is_cheap() {
+        let (_temp, site) = environment();
+        for i in 0..300 {
+            dist_info(
+                &site,
+                &format!("package_{}-1.0.{}.dist-info", i, i),
+                "Metadata-Version: 2.1\n",
+                &format!("package_{}/__init__.py,,\n../../../bin/package-{},,\n", i, i),
+            );
+        }
+
+        let start = std::time::Instant::now();
+        let scan = EnvironmentScan::scan(&site).unwrap();
+        assert!(scan.is_clean());
+        // Generous bound: a few hundred distributions must not be noticeable
+        assert!(start.elapsed() < std::time::Duration::from_millis(500), "scan took {:?}", start.elapsed());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinked_site_packages_boundary() {
+        // venv/lib/python3.11/site-packages -> share/site-packages, as on an NFS mount
+        let (temp, site) = environment();
+        let share = temp.path().join("share/site-packages");
+        fs::create_dir_all(&share).unwrap();
+        fs::remove_dir(&site).unwrap();
+        std::os::unix::fs::symlink(&share, &site).unwrap();
+
+        let absolute = format!("{},,\n", share.join("requests/adapters.py").display());
+        dist_info(
+            &site,
+            "requests-2.31.0.dist-info",
+            "Name: requests\n",
+            &format!("requests/__init__.py,,\n{}", absolute),
+        );
+        // `..` from the symlink lands next to the share, outside the venv
+        dist_info(&site, "escaper-0.1.dist-info", "Name: escaper\n", "../outside.py,,\n");
+
+        let slashed = PathBuf::from(format!("{}/", site.display()));
+        let scan = EnvironmentScan::scan(&slashed).unwrap();
+        let outside: Vec<&str> = scan
+            .anomalies
+            .iter()
+            .filter(|a| a.kind == AnomalyKind::RecordOutsideEnvironment)
+            .filter_map(|a| a.project.as_deref())
+            .collect();
+        assert_eq!(outside, ["escaper"]);
+    }
+}
@@ -0,0 +1,192 @@
+/// Replacing installed files without disturbing running processes
+///
+/// Files are never truncated or overwritten in place: the new content is
+/// written to a temporary sibling and renamed over the target. A process
+/// that has the old file open or mapped keeps its inode and goes on
+/// reading the old version until it restarts, instead of crashing on a
+/// shared object that changed under it. On Linux, `/proc/*/maps` tells us
+/// which processes those are, so the install can say so (or refuse).
+use anyhow::{Result, anyhow};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Copy `source` over `target` by rename, keeping `source`'s permissions
+pub fn replace_file(source: &Path, target: &Path) -> Result<()> {
+    let mut input = fs::File::open(source)?;
+    let permissions = input.metadata()?.permissions();
+    let staged = stage(target, |file| {
+        std::io::copy(&mut input, file)?;
+        Ok(())
+    })?;
+    staged.as_file().set_permissions(permissions)?;
+    commit(staged, target)
+}
+
+/// Write `contents` to `target` by rename
+pub fn write_file(target: &Path, contents: &[u8]) -> Result<()> {
+    let staged = stage(target, |file| Ok(file.write_all(contents)?))?;
+    commit(staged, target)
+}
+
+/// A temporary file next to `target`, so the rename stays on one filesystem
+fn stage(
+    target: &Path,
+    fill: impl FnOnce(&mut fs::File) -> Result<()>,
+) -> Result<tempfile::NamedTempFile> {
+    let parent = target
+        .parent()
+        .ok_or_else(|| anyhow!("{} has no parent directory", target.display()))?;
+    let mut staged = tempfile::Builder::new().prefix(".pip-rs-").tempfile_in(parent)?;
+    fill(staged.as_file_mut())?;
+    staged.as_file().sync_all()?;
+    Ok(staged)
+}
+
+fn commit(staged: tempfile::NamedTempFile, target: &Path) -> Result<()> {
+    staged
+        .persist(target)
+        .map_err(|e| anyhow!("Failed to replace {}: {}", target.display(), e.error))?;
+    Ok(())
+}
+
+/// A process that has files about to be replaced mapped into memory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InUse {
+    pub pid: u32,
+    /// Short command name, when readable
+    pub command: Option<String>,
+    pub paths: Vec<PathBuf>,
+}
+
+impl fmt::Display for InUse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.command {
+            Some(command) => write!(f, "PID {} ({})", self.pid, command)?,
+            None => write!(f, "PID {}", self.pid)?,
+        }
+        let names: Vec<String> = self
+            .paths
+            .iter()
+            .map(|p| p.file_name().unwrap_or(p.as_os_str()).to_string_lossy().to_string())
+            .collect();
+        write!(f, ": {}", names.join(", "))
+    }
+}
+
+/// Processes mapping any of `paths`, by PID. Best-effort: processes whose
+/// maps can't be read are left out, and only Linux is scanned.
+pub fn mapped_by(paths: &[PathBuf]) -> Vec<InUse> {
+    if paths.is_empty() || !cfg!(target_os = "linux") {
+        return Vec::new();
+    }
+    let wanted: HashSet<&Path> = paths.iter().map(PathBuf::as_path).collect();
+    let Ok(proc_dir) = fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+
+    let mut found: BTreeMap<u32, InUse> = BTreeMap::new();
+    for entry in proc_dir.flatten() {
+        let Some(pid) = entry.file_name().to_str().and_then(|s| s.parse::<u32>().ok()) else {
+            continue;
+        };
+        let Ok(maps) = fs::read_to_string(entry.path().join("maps")) else {
+            continue;
+        };
+        let mut mapped: Vec<PathBuf> = maps_paths(&maps)
+            .filter(|path| wanted.contains(path))
+            .map(Path::to_path_buf)
+            .collect();
+        if mapped.is_empty() {
+            continue;
+        }
+        mapped.sort();
+        mapped.dedup();
+        let command = fs::read_to_string(entry.path().join("comm"))
+            .ok()
+            .map(|c| c.trim().to_string());
+        found.insert(pid, InUse { pid, command, paths: mapped });
+    }
+    found.into_values().collect()
+}
+
+/// File paths in a `/proc/<pid>/maps` listing; the path is the last
+/// column and the only one containing `/`
+fn maps_paths(maps: &str) -> impl Iterator<Item = &Path> {
+    maps.lines()
+        .filter_map(|line| line.find('/').map(|start| &line[start..]))
+        .filter(|path| !path.ends_with(" (deleted)"))
+        .map(Path::new)
+}
+
+/// The notice printed when an install replaces files other processes use
+pub fn in_use_notice(package: &str, in_use: &[InUse]) -> Vec<String> {
+    if in_use.is_empty() {
+        return Vec::new();
+    }
+    let mut lines = vec![format!(
+        "Note: {} is in use by {} running process(es); they keep the old version until restarted:",
+        package,
+        in_use.len()
+    )];
+    lines.extend(in_use.iter().map(|process| format!("  - {}", process)));
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replace_keeps_open_handles_on_old_content() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("module.so");
+        fs::write(&target, "old").unwrap();
+        let held = fs::File::open(&target).unwrap();
+
+        let source = dir.path().join("new.so");
+        fs::write(&source, "new content").unwrap();
+        replace_file(&source, &target).unwrap();
+
+        assert_eq!(fs::read_to_string(&target).unwrap(), "new content");
+        let mut old = String::new();
+        std::io::Read::read_to_string(&mut &held, &mut old).unwrap();
+        assert_eq!(old, "old");
+
+        write_file(&target, b"third").unwrap();
+        assert_eq!(fs::read_to_string(&target).unwrap(), "third");
+        // Nothing staged is left behind
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_maps_paths_and_notice() {
+        let maps = "\
+7f1c2000-7f1c3000 r-xp 00000000 08:01 1234   /venv/lib/site-packages/numpy/_core.so
+7f1c4000-7f1c5000 r--p 00000000 08:01 99     /venv/lib/my dir/x.so
+7f1c6000-7f1c7000 r--p 00000000 08:01 77     /venv/gone.so (deleted)
+7ffd0000-7ffd1000 rw-p 00000000 00:00 0      [stack]
+";
+        let paths: Vec<&Path> = maps_paths(maps).collect();
+        assert_eq!(
+            paths,
+            [Path::new("/venv/lib/site-packages/numpy/_core.so"), Path::new("/venv/lib/my dir/x.so")]
+        );
+
+        let in_use = InUse {
+            pid: 4242,
+            command: Some("gunicorn".to_string()),
+            paths: vec![PathBuf::from("/venv/lib/site-packages/numpy/_core.so")],
+        };
+        assert_eq!(
+            in_use_notice("numpy", &[in_use]),
+            [
+                "Note: numpy is in use by 1 running process(es); they keep the old version until restarted:",
+                "  - PID 4242 (gunicorn): _core.so",
+            ]
+        );
+        assert!(in_use_notice("numpy", &[]).is_empty());
+    }
+}
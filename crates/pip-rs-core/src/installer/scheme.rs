@@ -0,0 +1,183 @@
+/// Install scheme: where each part of a wheel goes
+///
+/// Importable code lands in site-packages. A wheel's `<name>.data/`
+/// directory holds trees for the other scheme paths -- `purelib`,
+/// `platlib`, `scripts`, `headers` and `data` -- which are relocated to the
+/// matching directories of the environment rather than copied into
+/// site-packages verbatim. The directories follow sysconfig's layout for
+/// the prefix owning site-packages (a virtualenv, or `<prefix>` of
+/// `<prefix>/lib/pythonX.Y/site-packages`); a `--target` directory, which
+/// has no prefix, keeps everything inside itself. `--user` installs use the
+/// per-user scheme under [`user_base`].
+use super::entry_point;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scheme {
+    pub purelib: PathBuf,
+    pub platlib: PathBuf,
+    /// C headers; each distribution's go in a directory named after it
+    pub headers: PathBuf,
+    pub scripts: PathBuf,
+    pub data: PathBuf,
+    /// Interpreter scripts start; `None` leaves it to `python` on PATH
+    pub interpreter: Option<PathBuf>,
+}
+
+impl Scheme {
+    /// The scheme of the environment `site_packages` belongs to; a
+    /// directory outside any prefix is treated as a `--target` directory
+    pub fn for_site_packages(site_packages: &Path, virtual_env: Option<&Path>) -> Self {
+        let scripts = entry_point::scripts_target(site_packages, virtual_env);
+        let Some(prefix) = environment_prefix(site_packages, virtual_env) else {
+            return Self::for_target(site_packages, scripts.interpreter);
+        };
+        // `include/site/python3.12` in a virtualenv or prefix, as pip does
+        let python = site_packages
+            .parent()
+            .and_then(|dir| dir.file_name())
+            .map(|name| name.to_string_lossy().to_string())
+            .filter(|name| name.starts_with("python"));
+        let headers = match python {
+            Some(python) => prefix.join("include").join("site").join(python),
+            None => prefix.join("Include"),
+        };
+        Self {
+            purelib: site_packages.to_path_buf(),
+            platlib: site_packages.to_path_buf(),
+            headers,
+            scripts: scripts.dir,
+            data: prefix,
+            interpreter: scripts.interpreter,
+        }
+    }
+
+    /// Everything under `target`: code at its top, scripts in `bin/`,
+    /// headers in `include/`, data files relative to `target` itself
+    pub fn for_target(target: &Path, interpreter: Option<PathBuf>) -> Self {
+        Self {
+            purelib: target.to_path_buf(),
+            platlib: target.to_path_buf(),
+            headers: target.join("include"),
+            scripts: target.join("bin"),
+            data: target.to_path_buf(),
+            interpreter,
+        }
+    }
+
+    /// The per-user scheme of Python `version` (`X.Y`) under `base`:
+    /// `<base>/lib/pythonX.Y/site-packages` and `<base>/bin`, or on Windows
+    /// `<base>\PythonXY\site-packages` and `<base>\PythonXY\Scripts`
+    pub fn for_user(base: &Path, version: &str, interpreter: Option<PathBuf>) -> Self {
+        if cfg!(target_os = "windows") {
+            let python = base.join(format!("Python{}", version.replace('.', "")));
+            return Self {
+                purelib: python.join("site-packages"),
+                platlib: python.join("site-packages"),
+                headers: python.join("Include"),
+                scripts: python.join("Scripts"),
+                data: base.to_path_buf(),
+                interpreter,
+            };
+        }
+        let site_packages = base.join("lib").join(format!("python{}", version)).join("site-packages");
+        Self {
+            purelib: site_packages.clone(),
+            platlib: site_packages,
+            headers: base.join("include").join(format!("python{}", version)),
+            scripts: base.join("bin"),
+            data: base.to_path_buf(),
+            interpreter,
+        }
+    }
+
+    /// Where the `.data/<key>` tree of distribution `dist` goes; `None` for
+    /// keys the wheel spec doesn't define
+    pub fn root(&self, key: &str, dist: &str) -> Option<PathBuf> {
+        match key {
+            "purelib" => Some(self.purelib.clone()),
+            "platlib" => Some(self.platlib.clone()),
+            "headers" => Some(self.headers.join(dist)),
+            "scripts" => Some(self.scripts.clone()),
+            "data" => Some(self.data.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// Base of the per-user scheme, as Python's `site.getuserbase()`:
+/// `PYTHONUSERBASE`, else `%APPDATA%\Python` on Windows and `~/.local`
+/// elsewhere
+pub fn user_base() -> Option<PathBuf> {
+    if let Some(base) = std::env::var_os("PYTHONUSERBASE").filter(|base| !base.is_empty()) {
+        return Some(PathBuf::from(base));
+    }
+    if cfg!(target_os = "windows") {
+        return std::env::var_os("APPDATA").map(|appdata| PathBuf::from(appdata).join("Python"));
+    }
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local"))
+}
+
+/// The prefix `site_packages` is installed under: the virtualenv owning
+/// it, else `<prefix>` of a `<prefix>/lib/pythonX.Y/site-packages` layout
+pub fn environment_prefix(site_packages: &Path, virtual_env: Option<&Path>) -> Option<PathBuf> {
+    super::venv_guard::owning_virtualenv(site_packages, virtual_env)
+        .or_else(|| layout_prefix(site_packages).map(Path::to_path_buf))
+}
+
+/// `<prefix>` of `<prefix>/lib/pythonX.Y/site-packages` (also `lib64`,
+/// `dist-packages`, and Windows' `<prefix>/Lib/site-packages`)
+fn layout_prefix(site_packages: &Path) -> Option<&Path> {
+    let name = |dir: &Path| dir.file_name().and_then(|n| n.to_str()).map(str::to_lowercase);
+    if !matches!(name(site_packages)?.as_str(), "site-packages" | "dist-packages") {
+        return None;
+    }
+    let parent = site_packages.parent()?;
+    if name(parent)? == "lib" {
+        return parent.parent();
+    }
+    let lib = parent.parent()?;
+    let versioned = name(parent)?.starts_with("python") && matches!(name(lib)?.as_str(), "lib" | "lib64");
+    versioned.then(|| lib.parent()).flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefix_and_target_schemes() {
+        let temp = tempfile::tempdir().unwrap();
+        let bin = if cfg!(target_os = "windows") { "Scripts" } else { "bin" };
+        let prefix = temp.path().join("usr/local");
+        let site = prefix.join("lib/python3.12/site-packages");
+        let scheme = Scheme::for_site_packages(&site, None);
+        assert_eq!(scheme.scripts, prefix.join(bin));
+        assert_eq!(scheme.data, prefix);
+        assert_eq!(scheme.root("headers", "greenlet"), Some(prefix.join("include/site/python3.12/greenlet")));
+        assert_eq!(scheme.root("platlib", "greenlet"), Some(site.clone()));
+        assert_eq!(scheme.root("unknown", "greenlet"), None);
+
+        let windows = temp.path().join("Python312/Lib/site-packages");
+        assert_eq!(environment_prefix(&windows, None), Some(temp.path().join("Python312")));
+        assert_eq!(Scheme::for_site_packages(&windows, None).headers, temp.path().join("Python312/Include"));
+
+        let target = temp.path().join("vendored");
+        let scheme = Scheme::for_site_packages(&target, None);
+        assert_eq!(scheme, Scheme::for_target(&target, None));
+        assert_eq!((scheme.scripts, scheme.data), (target.join("bin"), target.clone()));
+
+        let base = temp.path().join("home/.local");
+        let user = Scheme::for_user(&base, "3.12", None);
+        if cfg!(target_os = "windows") {
+            assert_eq!(user.purelib, base.join("Python312/site-packages"));
+            assert_eq!(user.scripts, base.join("Python312/Scripts"));
+        } else {
+            assert_eq!(user.purelib, base.join("lib/python3.12/site-packages"));
+            assert_eq!(user.scripts, base.join("bin"));
+            // Derived from the site-packages alone, the scripts land in the same place
+            assert_eq!(Scheme::for_site_packages(&user.purelib, None).scripts, user.scripts);
+        }
+        assert_eq!(user.data, base);
+    }
+}
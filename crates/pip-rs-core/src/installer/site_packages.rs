@@ -1,6 +1,15 @@
 /// Site-packages management
+///
+/// Site-packages is often reached through a symlink (a venv whose
+/// site-packages lives on an NFS share, a trailing slash from the
+/// environment), so the directory is kept in two forms: the path as given,
+/// for messages, and its canonical form, for every equality or containment
+/// check.
+use crate::models::{Environment, Marker, Requirement};
+use crate::utils::names::{canonicalize_name, split_dist_info};
 use anyhow::Result;
-use std::path::{Path, PathBuf};
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
 use std::fs;
 
 #[derive(Debug, Clone)]
@@ -9,30 +18,49 @@ pub struct PackageDetails {
     pub version: String,
     pub location: PathBuf,
     pub requires: Vec<String>,
+    pub dist_info: PathBuf,
+}
+
+/// A dist-info directory, named `<name>-<version>.dist-info`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DistInfoEntry {
+    /// The name as the directory spells it
+    pub name: String,
+    /// Empty when the directory name carries none
+    pub version: String,
+    pub dist_info: PathBuf,
 }
 
 pub struct SitePackages {
     path: PathBuf,
+    /// `path` with symlinks resolved
+    canonical: PathBuf,
 }
 
 impl SitePackages {
     pub fn new(path: PathBuf) -> Result<Self> {
         fs::create_dir_all(&path)?;
-        Ok(Self { path })
+        let canonical = canonical_path(&path);
+        Ok(Self { path, canonical })
     }
 
     /// Get the default site-packages directory
-    /// Auto-detects virtual environment if VIRTUAL_ENV is set
+    /// That of the `--python` interpreter when one was given; otherwise
+    /// auto-detects virtual environment if VIRTUAL_ENV is set
     /// Falls back to detecting actual Python site-packages location
     pub fn default() -> Result<Self> {
+        if let Some(python) = crate::utils::python_env::selected() {
+            tracing::debug!("Using site-packages of {}: {}", python.executable.display(), python.purelib.display());
+            return Self::new(python.purelib.clone());
+        }
         // Smart defaults: Auto-detect venv
-        if let Ok(venv_path) = std::env::var("VIRTUAL_ENV") {
-            let venv = PathBuf::from(venv_path);
+        if let Some(venv) = crate::utils::python_env::virtual_env() {
             let site_packages = if cfg!(target_os = "windows") {
                 venv.join("Lib").join("site-packages")
             } else {
                 // Try to detect Python version from venv
-                let python_version = Self::detect_python_version(&venv).unwrap_or_else(|| "3.11".to_string());
+                let python_version = Self::detect_python_version(&venv)
+                    .unwrap_or_else(|| crate::utils::python_env::detected().version());
                 venv.join("lib").join(format!("python{}", python_version)).join("site-packages")
             };
             
@@ -94,7 +122,7 @@ impl SitePackages {
         let path = if cfg!(target_os = "windows") {
             PathBuf::from("Lib/site-packages")
         } else {
-            PathBuf::from("lib/python3.11/site-packages")
+            PathBuf::from(format!("lib/python{}/site-packages", crate::utils::python_env::detected().version()))
         };
         Self::new(path)
     }
@@ -104,52 +132,32 @@ impl SitePackages {
         vec![self.path.clone()]
     }
 
-    /// Detect Python version from venv
-    fn detect_python_version(venv_path: &Path) -> Option<String> {
-        // Check pyvenv.cfg for Python version
-        let pyvenv_cfg = venv_path.join("pyvenv.cfg");
-        if let Ok(content) = fs::read_to_string(&pyvenv_cfg) {
-            for line in content.lines() {
-                if line.starts_with("version") {
-                    if let Some(version) = line.split('=').nth(1) {
-                        let version = version.trim();
-                        // Extract major.minor from version string
-                        if let Some(dot_pos) = version.find('.') {
-                            if let Some(minor_end) = version[dot_pos+1..].find(|c: char| !c.is_ascii_digit()) {
-                                let minor_end = dot_pos + 1 + minor_end;
-                                return Some(version[..minor_end].to_string());
-                            }
-                            // Try to get major.minor (first 3 chars after dot_pos)
-                            if version.len() >= dot_pos + 3 {
-                                return Some(version[..dot_pos+3].to_string());
-                            }
-                            return Some(version.to_string());
-                        }
-                    }
-                }
-            }
-        }
-        
-        // Try to detect from Python executable
-        let python_exe = if cfg!(target_os = "windows") {
-            venv_path.join("Scripts").join("python.exe")
-        } else {
-            venv_path.join("bin").join("python3")
-        };
-        
-        if python_exe.exists() {
-            // In a real implementation, we'd run python --version
-            // For now, default to 3.11
-            return Some("3.11".to_string());
-        }
-        
-        None
+    /// `X.Y` of the virtualenv at `venv_path`, from its `pyvenv.cfg` or
+    /// else its interpreter
+    pub(crate) fn detect_python_version(venv_path: &Path) -> Option<String> {
+        crate::utils::python_env::venv_version(venv_path)
     }
 
+    /// The directory as given, for display
     pub fn path(&self) -> &Path {
         &self.path
     }
 
+    /// The directory with symlinks resolved, for comparisons
+    pub fn canonical_path(&self) -> &Path {
+        &self.canonical
+    }
+
+    /// Whether `path` is this directory, however it is spelled
+    pub fn is_same_dir(&self, path: &Path) -> bool {
+        canonical_path(path) == self.canonical
+    }
+
+    /// Whether `path` lies inside this directory once symlinks are resolved
+    pub fn contains(&self, path: &Path) -> bool {
+        canonical_path(path).starts_with(&self.canonical)
+    }
+
     pub fn install_file(&self, source: &Path, relative_path: &Path) -> Result<PathBuf> {
         let target = self.path.join(relative_path);
         
@@ -157,14 +165,16 @@ impl SitePackages {
             fs::create_dir_all(parent)?;
         }
         
-        fs::copy(source, &target)?;
+        super::replace::replace_file(source, &target)?;
         Ok(target)
     }
 
-    pub fn install_directory(&self, source: &Path, relative_path: &Path) -> Result<()> {
+    /// Copy `source` to `relative_path`, returning the files written
+    pub fn install_directory(&self, source: &Path, relative_path: &Path) -> Result<Vec<PathBuf>> {
         let target = self.path.join(relative_path);
         fs::create_dir_all(&target)?;
         
+        let mut written = Vec::new();
         for entry in fs::read_dir(source)? {
             let entry = entry?;
             let path = entry.path();
@@ -172,93 +182,200 @@ impl SitePackages {
             let target_path = target.join(&file_name);
             
             if path.is_dir() {
-                self.install_directory(&path, &relative_path.join(&file_name))?;
+                written.extend(self.install_directory(&path, &relative_path.join(&file_name))?);
             } else {
-                fs::copy(&path, &target_path)?;
+                super::replace::replace_file(&path, &target_path)?;
+                written.push(target_path);
             }
         }
         
-        Ok(())
+        Ok(written)
     }
 
+    /// Whether a distribution of `package_name` is installed, however its
+    /// dist-info directory spells the name
     pub fn is_installed(&self, package_name: &str) -> bool {
-        let dist_info = self.path.join(format!("{}.dist-info", package_name));
-        dist_info.exists()
+        self.installed_distributions()
+            .is_ok_and(|installed| installed.contains_key(&canonicalize_name(package_name)))
     }
 
+    /// Names of the installed distributions, as their dist-info directories
+    /// spell them, sorted by normalized name
     pub fn get_installed_packages(&self) -> Result<Vec<String>> {
-        let mut packages = Vec::new();
-        
-        for entry in fs::read_dir(&self.path)? {
-            let entry = entry?;
-            let path = entry.path();
-            
-            if path.is_dir() {
-                if let Some(name) = path.file_name() {
-                    if let Some(name_str) = name.to_str() {
-                        if name_str.ends_with(".dist-info") {
-                            let pkg_name = name_str.trim_end_matches(".dist-info").to_string();
-                            // Parse version from directory name if possible, or just use name
-                            // Actually, directory is usually name-version.dist-info
-                            if let Some(dash_pos) = pkg_name.find('-') {
-                                packages.push(pkg_name[..dash_pos].to_string());
-                            } else {
-                                packages.push(pkg_name);
-                            }
-                        }
-                    }
-                }
-            }
+        Ok(self.installed_distributions()?.into_values().map(|dist| dist.name).collect())
+    }
+
+    /// Every `.dist-info` directory, keyed by normalized name. Name and
+    /// version come from the directory name alone, so this stays cheap for
+    /// environments with hundreds of packages; should two directories claim
+    /// one project, the first by directory name is kept.
+    pub fn installed_distributions(&self) -> Result<BTreeMap<String, DistInfoEntry>> {
+        let mut dirs: Vec<PathBuf> = fs::read_dir(&self.path)?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir())
+            .collect();
+        dirs.sort();
+
+        let mut installed = BTreeMap::new();
+        for dist_info in dirs {
+            let Some(file_name) = dist_info.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            let Some((name, version)) = split_dist_info(file_name) else {
+                continue;
+            };
+            installed.entry(canonicalize_name(name)).or_insert_with(|| DistInfoEntry {
+                name: name.to_string(),
+                version: version.to_string(),
+                dist_info: dist_info.clone(),
+            });
         }
-        
-        // Remove duplicates and sort
-        packages.sort();
-        packages.dedup();
-        
-        Ok(packages)
+        Ok(installed)
     }
 
+    /// Details of an installed distribution, matched by normalized name
     pub fn get_package_details(&self, package_name: &str) -> Result<Option<PackageDetails>> {
+        let wanted = canonicalize_name(package_name);
+        Ok(self
+            .all_package_details()?
+            .into_iter()
+            .find(|details| canonicalize_name(&details.name) == wanted))
+    }
+
+    /// Details of every distribution with a readable METADATA file
+    pub fn all_package_details(&self) -> Result<Vec<PackageDetails>> {
+        let mut installed = Vec::new();
         for entry in fs::read_dir(&self.path)? {
-            let entry = entry?;
-            let path = entry.path();
-            if path.is_dir() {
-                if let Some(name) = path.file_name() {
-                    let name_str = name.to_string_lossy();
-                    if name_str.ends_with(".dist-info") {
-                         let metadata_path = path.join("METADATA");
-                         if metadata_path.exists() {
-                             if let Ok(content) = fs::read_to_string(&metadata_path) {
-                                 let mut found_name = String::new();
-                                 let mut version = String::new();
-                                 let mut requires = Vec::new();
-                                 
-                                 for line in content.lines() {
-                                     if line.starts_with("Name: ") {
-                                         found_name = line["Name: ".len()..].trim().to_string();
-                                     } else if line.starts_with("Version: ") {
-                                         version = line["Version: ".len()..].trim().to_string();
-                                     } else if line.starts_with("Requires-Dist: ") {
-                                         requires.push(line["Requires-Dist: ".len()..].trim().to_string());
-                                     }
-                                 }
-                                 
-                                 if found_name.eq_ignore_ascii_case(package_name) {
-                                     return Ok(Some(PackageDetails {
-                                         name: found_name,
-                                         version,
-                                         location: self.path.clone(),
-                                         requires,
-                                     }));
-                                 }
-                             }
-                         }
-                    }
+            let path = entry?.path();
+            if path.is_dir() && path.to_string_lossy().ends_with(".dist-info") {
+                installed.extend(self.read_details(&path));
+            }
+        }
+        installed.sort_by_key(|details| canonicalize_name(&details.name));
+        Ok(installed)
+    }
+
+    fn read_details(&self, dist_info: &Path) -> Option<PackageDetails> {
+        let content = fs::read_to_string(dist_info.join("METADATA")).ok()?;
+        let mut name = String::new();
+        let mut version = String::new();
+        let mut requires = Vec::new();
+        for line in content.lines() {
+            if let Some(value) = line.strip_prefix("Name: ") {
+                name = value.trim().to_string();
+            } else if let Some(value) = line.strip_prefix("Version: ") {
+                version = value.trim().to_string();
+            } else if let Some(value) = line.strip_prefix("Requires-Dist: ") {
+                requires.push(value.trim().to_string());
+            } else if line.is_empty() {
+                // The description body follows the headers
+                break;
+            }
+        }
+        (!name.is_empty()).then(|| PackageDetails {
+            name,
+            version,
+            location: self.path.clone(),
+            requires,
+            dist_info: dist_info.to_path_buf(),
+        })
+    }
+}
+
+impl PackageDetails {
+    /// The `Requires-Dist` entries that apply in `env` when no extras are
+    /// requested, in METADATA order
+    pub fn requirements(&self, env: &Environment) -> Vec<Requirement> {
+        self.applicable(env).into_iter().map(|(_, requirement)| requirement).collect()
+    }
+
+    /// [`requirements`](Self::requirements), each with the name as spelled
+    /// in METADATA (parsing normalizes it)
+    fn applicable(&self, env: &Environment) -> Vec<(String, Requirement)> {
+        let mut requirements = Vec::new();
+        for spec in &self.requires {
+            let spelled = spec
+                .trim()
+                .split(|c: char| !(c.is_alphanumeric() || matches!(c, '-' | '_' | '.')))
+                .next()
+                .unwrap_or_default()
+                .to_string();
+            // `name (>=1.0)` is the older spelling of `name>=1.0`
+            let spec = match spec.split_once(';') {
+                Some((head, marker)) => format!("{};{}", head.replace(['(', ')'], ""), marker),
+                None => spec.replace(['(', ')'], ""),
+            };
+            let Ok(requirement) = spec.parse::<Requirement>() else {
+                continue;
+            };
+            let applies = match requirement.marker.as_deref() {
+                Some(marker) => Marker::parse(marker)
+                    .map(|m| m.evaluate(env))
+                    .unwrap_or(!marker.contains("extra")),
+                None => true,
+            };
+            if applies {
+                requirements.push((spelled, requirement));
+            }
+        }
+        requirements
+    }
+
+    /// Names of the dependencies that apply in `env` when no extras are
+    /// requested, sorted case-insensitively as `pip show` prints them
+    pub fn dependencies(&self, env: &Environment) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        for (spelled, requirement) in self.applicable(env) {
+            let name = canonicalize_name(&requirement.name);
+            if !names.iter().any(|n| canonicalize_name(n) == name) {
+                names.push(spelled);
+            }
+        }
+        names.sort_by_key(|n| n.to_lowercase());
+        names
+    }
+
+    /// Names of the `installed` distributions that depend on this one
+    pub fn required_by(&self, installed: &[PackageDetails], env: &Environment) -> Vec<String> {
+        let name = canonicalize_name(&self.name);
+        let mut dependents: Vec<String> = installed
+            .iter()
+            .filter(|other| canonicalize_name(&other.name) != name)
+            .filter(|other| other.dependencies(env).iter().any(|dep| canonicalize_name(dep) == name))
+            .map(|other| other.name.clone())
+            .collect();
+        dependents.sort_by_key(|n| n.to_lowercase());
+        dependents
+    }
+}
+
+/// `path` made absolute with symlinks, `.` and `..` resolved. A leaf that
+/// doesn't exist yet is resolved against its deepest existing ancestor.
+pub fn canonical_path(path: &Path) -> PathBuf {
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir().map(|dir| dir.join(path)).unwrap_or_else(|_| path.to_path_buf())
+    };
+    let components: Vec<Component> = absolute.components().collect();
+    for split in (1..=components.len()).rev() {
+        let existing: PathBuf = components[..split].iter().collect();
+        let Ok(mut resolved) = existing.canonicalize() else {
+            continue;
+        };
+        for component in &components[split..] {
+            match component {
+                Component::ParentDir => {
+                    resolved.pop();
                 }
+                Component::Normal(part) => resolved.push(part),
+                _ => {}
             }
         }
-        Ok(None)
+        return resolved;
     }
+    absolute
 }
 
 #[cfg(test)]
@@ -286,4 +403,85 @@ mod tests {
         assert!(!site_packages.is_installed("numpy"));
         Ok(())
     }
+
+    #[test]
+    fn test_installed_names_are_normalized() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let site_packages = SitePackages::new(temp_dir.path().to_path_buf())?;
+        fs::create_dir_all(site_packages.path().join("Flask_SQLAlchemy-3.1.1.dist-info"))?;
+        fs::create_dir_all(site_packages.path().join("zope.interface-6.0.dist-info"))?;
+        fs::create_dir_all(site_packages.path().join("requests-2.31.0.egg-info"))?;
+
+        for name in ["flask-sqlalchemy", "Flask_SQLAlchemy", "FLASK.SQLALCHEMY"] {
+            assert!(site_packages.is_installed(name), "{}", name);
+        }
+        assert!(site_packages.is_installed("zope-interface"));
+        assert!(!site_packages.is_installed("requests"));
+
+        let installed = site_packages.installed_distributions()?;
+        assert_eq!(installed.keys().collect::<Vec<_>>(), ["flask-sqlalchemy", "zope-interface"]);
+        assert_eq!(installed["flask-sqlalchemy"].version, "3.1.1");
+        assert_eq!(site_packages.get_installed_packages()?, ["Flask_SQLAlchemy", "zope.interface"]);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_canonical_path_resolves_symlinks_and_missing_leaves() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let real = temp_dir.path().canonicalize()?.join("share");
+        fs::create_dir_all(&real)?;
+        let link = temp_dir.path().join("link");
+        std::os::unix::fs::symlink(&real, &link)?;
+
+        assert_eq!(canonical_path(&link), real);
+        assert_eq!(canonical_path(&PathBuf::from(format!("{}/", link.display()))), real);
+        assert_eq!(canonical_path(&link.join("pkg/./mod.py")), real.join("pkg/mod.py"));
+        // `..` after the symlink leaves from where the link points
+        assert_eq!(canonical_path(&link.join("..").join("x")), real.parent().unwrap().join("x"));
+        assert_eq!(canonical_path(&link.join("new/../y")), real.join("y"));
+        Ok(())
+    }
+
+    fn write_metadata(site: &Path, dir: &str, metadata: &str) -> Result<()> {
+        let dist_info = site.join(dir);
+        fs::create_dir_all(&dist_info)?;
+        fs::write(dist_info.join("METADATA"), metadata)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_requires_and_required_by_normalize_names() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let site_packages = SitePackages::new(temp_dir.path().to_path_buf())?;
+        write_metadata(
+            site_packages.path(),
+            "zope_interface-6.0.dist-info",
+            "Name: zope.interface\nVersion: 6.0\nRequires-Dist: setuptools\n",
+        )?;
+        write_metadata(
+            site_packages.path(),
+            "Twisted-23.10.0.dist-info",
+            "Name: Twisted\nVersion: 23.10.0\nRequires-Dist: Zope_Interface (>=5)\n\
+             Requires-Dist: attrs>=21.3.0\nRequires-Dist: pywin32 ; sys_platform == \"never\"\n\
+             Requires-Dist: pytest ; extra == \"test\"\n\nRequires-Dist: not-a-header\n",
+        )?;
+        write_metadata(
+            site_packages.path(),
+            "grpcio_tools-1.60.0.dist-info",
+            "Name: grpcio-tools\nVersion: 1.60.0\nRequires-Dist: zope-interface\n",
+        )?;
+
+        let env = Environment::current();
+        let installed = site_packages.all_package_details()?;
+        let twisted = site_packages.get_package_details("twisted")?.unwrap();
+        assert_eq!(twisted.dependencies(&env), vec!["attrs", "Zope_Interface"]);
+
+        let zope = site_packages.get_package_details("ZOPE-interface")?.unwrap();
+        assert_eq!(zope.name, "zope.interface");
+        assert_eq!(zope.required_by(&installed, &env), vec!["grpcio-tools", "Twisted"]);
+        assert!(twisted.required_by(&installed, &env).is_empty());
+        assert!(site_packages.get_package_details("numpy")?.is_none());
+        Ok(())
+    }
 }
@@ -0,0 +1,260 @@
+/// Installed-size accounting
+///
+/// Sizes of installed distributions come from their RECORD size column,
+/// falling back to stat for rows without one, plus byte-compiled files in
+/// `__pycache__` that RECORD doesn't list. Results are kept in a snapshot
+/// file keyed by each RECORD's length and mtime so repeated `pip list
+/// --sizes` runs only re-read distributions that changed.
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use walkdir::WalkDir;
+
+/// Installed size of one distribution
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PackageSize {
+    pub name: String,
+    pub version: String,
+    pub bytes: u64,
+}
+
+/// Sort largest first (ties by name) and total the sizes
+pub fn sort_by_size(sizes: &mut [PackageSize]) -> u64 {
+    sizes.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase())));
+    sizes.iter().map(|s| s.bytes).sum()
+}
+
+/// Total size of the regular files under `dir`
+pub fn tree_size(dir: &Path) -> u64 {
+    WalkDir::new(dir)
+        .into_iter()
+        .flatten()
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| e.metadata().ok())
+        .map(|m| m.len())
+        .sum()
+}
+
+/// Installed size of the distribution owning `dist_info`, from its RECORD
+pub fn dist_info_size(site_packages: &Path, dist_info: &Path) -> Result<u64> {
+    let record = fs::read_to_string(dist_info.join("RECORD"))?;
+    let mut listed = BTreeSet::new();
+    let mut source_dirs = BTreeSet::new();
+    let mut total = 0;
+
+    for line in record.lines() {
+        let mut fields = line.rsplitn(3, ',');
+        let (size, _hash, path) = (fields.next(), fields.next(), fields.next());
+        let Some(path) = path.filter(|p| !p.is_empty()) else {
+            continue;
+        };
+        let full = site_packages.join(path);
+        total += match size.and_then(|s| s.trim().parse::<u64>().ok()) {
+            Some(bytes) => bytes,
+            None => fs::metadata(&full).map(|m| m.len()).unwrap_or(0),
+        };
+        if path.ends_with(".py")
+            && let Some(parent) = full.parent()
+        {
+            source_dirs.insert(parent.to_path_buf());
+        }
+        listed.insert(full);
+    }
+
+    // Byte-compiled artifacts written after install aren't in RECORD
+    for dir in source_dirs {
+        let Ok(entries) = fs::read_dir(dir.join("__pycache__")) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == "pyc") && !listed.contains(&path) {
+                total += entry.metadata().map(|m| m.len()).unwrap_or(0);
+            }
+        }
+    }
+
+    Ok(total)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct SnapshotEntry {
+    /// RECORD length and mtime when the size was computed
+    stamp: String,
+    bytes: u64,
+}
+
+/// Cached per-distribution sizes for one site-packages directory
+#[derive(Debug, Default)]
+pub struct SizeSnapshot {
+    path: Option<PathBuf>,
+    entries: BTreeMap<String, SnapshotEntry>,
+    hits: usize,
+    dirty: bool,
+}
+
+impl SizeSnapshot {
+    /// Snapshot file for `site_packages` inside `dir`; every spelling of
+    /// the directory (symlink, trailing slash) shares one snapshot
+    pub fn path_for(dir: &Path, site_packages: &Path) -> PathBuf {
+        let canonical = super::site_packages::canonical_path(site_packages);
+        let digest = Sha256::digest(canonical.to_string_lossy().as_bytes());
+        let name: String = digest.iter().take(8).map(|b| format!("{:02x}", b)).collect();
+        dir.join(format!("sizes-{}.json", name))
+    }
+
+    /// Load a snapshot; a missing or unreadable file starts empty
+    pub fn load(path: &Path) -> Self {
+        let entries = fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Self {
+            path: Some(path.to_path_buf()),
+            entries,
+            ..Default::default()
+        }
+    }
+
+    /// Size of a distribution, reusing the cached value while its RECORD
+    /// is unchanged
+    pub fn size_of(&mut self, site_packages: &Path, dist_info: &Path) -> Result<u64> {
+        let key = dist_info
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let meta = fs::metadata(dist_info.join("RECORD"))?;
+        let mtime = meta
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        let stamp = format!("{}:{}", meta.len(), mtime);
+
+        if let Some(entry) = self.entries.get(&key)
+            && entry.stamp == stamp
+        {
+            self.hits += 1;
+            return Ok(entry.bytes);
+        }
+
+        let bytes = dist_info_size(site_packages, dist_info)?;
+        self.entries.insert(key, SnapshotEntry { stamp, bytes });
+        self.dirty = true;
+        Ok(bytes)
+    }
+
+    /// Number of sizes served from the snapshot
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /// Write the snapshot back if anything was recomputed
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if !self.dirty {
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string(&self.entries)?)?;
+        Ok(())
+    }
+}
+
+/// Human-readable size with binary units ("1.5 MiB")
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Install a fake distribution with files of known sizes. The last file
+    /// is listed without a size (RECORD rows for RECORD itself look like that).
+    fn install(site: &Path, name: &str, files: &[(&str, usize)]) -> PathBuf {
+        let dist_info = site.join(format!("{}-1.0.dist-info", name));
+        fs::create_dir_all(&dist_info).unwrap();
+        let mut record = String::new();
+        for (index, (path, size)) in files.iter().enumerate() {
+            let full = site.join(path);
+            fs::create_dir_all(full.parent().unwrap()).unwrap();
+            fs::write(&full, vec![b'x'; *size]).unwrap();
+            if index + 1 == files.len() {
+                record.push_str(&format!("{},,\n", path));
+            } else {
+                record.push_str(&format!("{},sha256=abc,{}\n", path, size));
+            }
+        }
+        fs::write(dist_info.join("RECORD"), record).unwrap();
+        dist_info
+    }
+
+    #[test]
+    fn test_sizes_from_record_with_stat_fallback_and_pycache() {
+        let site = TempDir::new().unwrap();
+        let big = install(site.path(), "big", &[("big/__init__.py", 4000), ("big/core.so", 60000), ("big/data.bin", 1000)]);
+        let small = install(site.path(), "small", &[("small.py", 300), ("small-extra.txt", 20)]);
+        // Compiled after install, not in RECORD
+        fs::create_dir_all(site.path().join("big/__pycache__")).unwrap();
+        fs::write(site.path().join("big/__pycache__/__init__.cpython-311.pyc"), vec![0; 500]).unwrap();
+
+        let mut sizes = vec![
+            PackageSize { name: "small".into(), version: "1.0".into(), bytes: dist_info_size(site.path(), &small).unwrap() },
+            PackageSize { name: "big".into(), version: "1.0".into(), bytes: dist_info_size(site.path(), &big).unwrap() },
+        ];
+        let total = sort_by_size(&mut sizes);
+
+        assert_eq!(sizes[0].name, "big");
+        assert_eq!(sizes[0].bytes, 4000 + 60000 + 1000 + 500);
+        assert_eq!(sizes[1].bytes, 320);
+        assert_eq!(total, 65820);
+        assert_eq!(format_size(total), "64.3 KiB");
+        assert_eq!(format_size(320), "320 B");
+    }
+
+    #[test]
+    fn test_snapshot_serves_unchanged_distributions() {
+        let site = TempDir::new().unwrap();
+        let cache = TempDir::new().unwrap();
+        let dist_info = install(site.path(), "pkg", &[("pkg/__init__.py", 100), ("pkg/RECORD.txt", 10)]);
+        let path = SizeSnapshot::path_for(cache.path(), site.path());
+
+        let mut first = SizeSnapshot::load(&path);
+        assert_eq!(first.size_of(site.path(), &dist_info).unwrap(), 110);
+        assert_eq!(first.hits(), 0);
+        first.save().unwrap();
+
+        // Second run: served from the snapshot, even if files changed size
+        fs::write(site.path().join("pkg/__init__.py"), vec![b'x'; 5000]).unwrap();
+        let mut second = SizeSnapshot::load(&path);
+        assert_eq!(second.size_of(site.path(), &dist_info).unwrap(), 110);
+        assert_eq!(second.hits(), 1);
+
+        // A rewritten RECORD invalidates the entry
+        fs::write(dist_info.join("RECORD"), "pkg/__init__.py,sha256=abc,5000\n").unwrap();
+        let mut third = SizeSnapshot::load(&path);
+        assert_eq!(third.size_of(site.path(), &dist_info).unwrap(), 5000);
+        assert_eq!(third.hits(), 0);
+    }
+}
@@ -0,0 +1,390 @@
+/// Pure-Python wheels built straight from a source tree
+///
+/// pip-rs doesn't run PEP 517 build backends, but most projects installed
+/// from a git checkout are plain Python packages: their metadata is in
+/// `pyproject.toml` or `setup.cfg` and their code is a `src/` directory or
+/// a package next to those files. For such a tree this builds a
+/// `py3-none-any` wheel, so it installs through the normal wheel path.
+/// Projects that compile extensions are refused rather than half-installed.
+use anyhow::{Result, anyhow, bail};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Build backends whose projects always compile something
+const COMPILING_BACKENDS: &[&str] = &["maturin", "scikit_build_core.build", "mesonpy", "setuptools_rust"];
+
+/// Top-level directories that are never the package in a flat layout
+const NOT_PACKAGES: &[&str] = &["tests", "test", "docs", "doc", "examples", "benchmarks", "build", "dist", "scripts"];
+
+/// A project checked out on disk
+#[derive(Debug, Clone)]
+pub struct SourceTree {
+    pub root: PathBuf,
+    pub name: String,
+    /// `0` when the version is dynamic and only a build backend knows it
+    pub version: String,
+    pub requires_dist: Vec<String>,
+    pub requires_python: Option<String>,
+    /// Console scripts, `name -> module:function`
+    pub scripts: BTreeMap<String, String>,
+}
+
+impl SourceTree {
+    /// Read metadata from `pyproject.toml` `[project]`, falling back to `setup.cfg`
+    pub fn load(root: &Path) -> Result<Self> {
+        let mut tree = Self {
+            root: root.to_path_buf(),
+            name: String::new(),
+            version: "0".to_string(),
+            requires_dist: Vec::new(),
+            requires_python: None,
+            scripts: BTreeMap::new(),
+        };
+
+        let pyproject: toml::Table = match std::fs::read_to_string(root.join("pyproject.toml")) {
+            Ok(content) => content.parse().map_err(|e| anyhow!("invalid pyproject.toml: {}", e))?,
+            Err(_) => toml::Table::new(),
+        };
+        let backend = pyproject
+            .get("build-system")
+            .and_then(|build| build.get("build-backend"))
+            .and_then(|backend| backend.as_str())
+            .unwrap_or_default();
+        let setup_py = std::fs::read_to_string(root.join("setup.py")).unwrap_or_default();
+        if COMPILING_BACKENDS.iter().any(|compiling| backend.starts_with(compiling)) || setup_py.contains("ext_modules") {
+            bail!("the project compiles extension modules, and building them is not supported");
+        }
+
+        if let Some(project) = pyproject.get("project").and_then(|project| project.as_table()) {
+            let string = |key: &str| project.get(key).and_then(|value| value.as_str()).map(String::from);
+            tree.name = string("name").unwrap_or_default();
+            tree.version = string("version").unwrap_or(tree.version);
+            tree.requires_python = string("requires-python");
+            tree.requires_dist = strings(project.get("dependencies"));
+            if let Some(scripts) = project.get("scripts").and_then(|scripts| scripts.as_table()) {
+                for (name, target) in scripts {
+                    if let Some(target) = target.as_str() {
+                        tree.scripts.insert(name.clone(), target.to_string());
+                    }
+                }
+            }
+        }
+        if tree.name.is_empty() {
+            tree.apply_setup_cfg()?;
+        }
+        if tree.name.is_empty() {
+            bail!("no project name in pyproject.toml [project] or setup.cfg [metadata]");
+        }
+        Ok(tree)
+    }
+
+    fn apply_setup_cfg(&mut self) -> Result<()> {
+        let Ok(content) = std::fs::read_to_string(self.root.join("setup.cfg")) else {
+            return Ok(());
+        };
+        let sections = parse_ini(&content);
+        let value = |section: &str, key: &str| sections.get(&(section.to_string(), key.to_string()));
+        if let Some(name) = value("metadata", "name") {
+            self.name = name.trim().to_string();
+        }
+        // `attr:` and `file:` versions need setuptools to evaluate
+        if let Some(version) = value("metadata", "version").filter(|v| !v.contains(':')) {
+            self.version = version.trim().to_string();
+        }
+        if let Some(requires_python) = value("options", "python_requires") {
+            self.requires_python = Some(requires_python.trim().to_string());
+        }
+        if let Some(requires) = value("options", "install_requires") {
+            self.requires_dist = non_empty_lines(requires);
+        }
+        if let Some(scripts) = value("options.entry_points", "console_scripts") {
+            for line in non_empty_lines(scripts) {
+                if let Some((name, target)) = line.split_once('=') {
+                    self.scripts.insert(name.trim().to_string(), target.trim().to_string());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Files and directories that make up the importable code, with the
+    /// directory they're relative to
+    fn sources(&self) -> Result<(PathBuf, Vec<PathBuf>)> {
+        let src = self.root.join("src");
+        let (base, candidates): (PathBuf, Vec<PathBuf>) = if src.is_dir() {
+            let entries = read_dir_sorted(&src)?
+                .into_iter()
+                .filter(|path| !path.to_string_lossy().ends_with(".egg-info"))
+                .filter(|path| path.is_dir() || is_module(path))
+                .collect();
+            (src, entries)
+        } else {
+            let import_name = self.name.to_lowercase().replace(['-', '.'], "_");
+            let package = self.root.join(&import_name);
+            let module = self.root.join(format!("{}.py", import_name));
+            let entries = if package.is_dir() {
+                vec![package]
+            } else if module.is_file() {
+                vec![module]
+            } else {
+                read_dir_sorted(&self.root)?
+                    .into_iter()
+                    .filter(|path| path.join("__init__.py").is_file())
+                    .filter(|path| {
+                        let name = path.file_name().unwrap_or_default().to_string_lossy();
+                        !NOT_PACKAGES.contains(&name.as_ref())
+                    })
+                    .collect()
+            };
+            (self.root.clone(), entries)
+        };
+        if candidates.is_empty() {
+            bail!("no Python package or module found in {}", self.root.display());
+        }
+        Ok((base, candidates))
+    }
+
+    /// Build `{name}-{version}-py3-none-any.whl` in `out_dir`. `extra` files
+    /// (e.g. `direct_url.json`) go into the dist-info directory.
+    pub fn build_wheel(&self, out_dir: &Path, extra: &[(&str, String)]) -> Result<PathBuf> {
+        let wheel_name = self.name.to_lowercase().replace(['-', '.'], "_");
+        let dist_info = format!("{}-{}.dist-info", wheel_name, self.version);
+        let path = out_dir.join(format!("{}-{}-py3-none-any.whl", wheel_name, self.version));
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&path)?);
+        let options = zip::write::FileOptions::default();
+        let mut record = String::new();
+        let mut add = |zip: &mut zip::ZipWriter<std::fs::File>, name: &str, data: &[u8]| -> Result<()> {
+            zip.start_file(name, options)?;
+            zip.write_all(data)?;
+            record.push_str(&format!(
+                "{},sha256={},{}\n",
+                name,
+                URL_SAFE_NO_PAD.encode(Sha256::digest(data)),
+                data.len()
+            ));
+            Ok(())
+        };
+
+        let (base, sources) = self.sources()?;
+        for source in sources {
+            for entry in WalkDir::new(&source).sort_by_file_name().into_iter().flatten() {
+                let file = entry.path();
+                let compiled = file.components().any(|c| c.as_os_str() == "__pycache__")
+                    || file.extension().is_some_and(|ext| ext == "pyc");
+                if !entry.file_type().is_file() || compiled {
+                    continue;
+                }
+                let relative = file.strip_prefix(&base)?;
+                let name = relative.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/");
+                add(&mut zip, &name, &std::fs::read(file)?)?;
+            }
+        }
+
+        add(&mut zip, &format!("{}/METADATA", dist_info), self.metadata().as_bytes())?;
+        let wheel = "Wheel-Version: 1.0\nGenerator: pip-rs\nRoot-Is-Purelib: true\nTag: py3-none-any\n";
+        add(&mut zip, &format!("{}/WHEEL", dist_info), wheel.as_bytes())?;
+        if !self.scripts.is_empty() {
+            let mut entry_points = String::from("[console_scripts]\n");
+            for (name, target) in &self.scripts {
+                entry_points.push_str(&format!("{} = {}\n", name, target));
+            }
+            add(&mut zip, &format!("{}/entry_points.txt", dist_info), entry_points.as_bytes())?;
+        }
+        for (name, content) in extra {
+            add(&mut zip, &format!("{}/{}", dist_info, name), content.as_bytes())?;
+        }
+        let record_name = format!("{}/RECORD", dist_info);
+        record.push_str(&format!("{},,\n", record_name));
+        zip.start_file(&record_name, options)?;
+        zip.write_all(record.as_bytes())?;
+        zip.finish()?;
+        Ok(path)
+    }
+
+    fn metadata(&self) -> String {
+        let mut metadata = format!("Metadata-Version: 2.1\nName: {}\nVersion: {}\n", self.name, self.version);
+        if let Some(requires_python) = &self.requires_python {
+            metadata.push_str(&format!("Requires-Python: {}\n", requires_python));
+        }
+        for requirement in &self.requires_dist {
+            metadata.push_str(&format!("Requires-Dist: {}\n", requirement));
+        }
+        metadata
+    }
+}
+
+/// Unpack a `.tar.gz` or `.zip` sdist into `dest` and return the project
+/// directory inside it
+pub fn unpack_sdist(archive: &Path, dest: &Path) -> Result<PathBuf> {
+    std::fs::create_dir_all(dest)?;
+    let name = archive.file_name().unwrap_or_default().to_string_lossy();
+    if name.ends_with(".zip") {
+        zip::ZipArchive::new(std::fs::File::open(archive)?)?.extract(dest)?;
+    } else if name.ends_with(".tar.gz") {
+        // Like git for VCS requirements, the system tar does the unpacking
+        let status = std::process::Command::new("tar")
+            .arg("-xzf")
+            .arg(archive)
+            .arg("-C")
+            .arg(dest)
+            .status()
+            .map_err(|e| anyhow!("could not run tar: {}", e))?;
+        if !status.success() {
+            bail!("tar could not unpack {}", name);
+        }
+    } else {
+        bail!("{} is not a .tar.gz or .zip sdist", name);
+    }
+    // An sdist holds a single `{name}-{version}/` directory
+    match read_dir_sorted(dest)?.as_slice() {
+        [root] if root.is_dir() => Ok(root.clone()),
+        _ => bail!("{} does not contain a single project directory", name),
+    }
+}
+
+fn strings(value: Option<&toml::Value>) -> Vec<String> {
+    value
+        .and_then(|value| value.as_array())
+        .map(|items| items.iter().filter_map(|item| item.as_str()).map(String::from).collect())
+        .unwrap_or_default()
+}
+
+fn non_empty_lines(value: &str) -> Vec<String> {
+    value
+        .lines()
+        .map(|line| line.split(" #").next().unwrap_or_default().trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect()
+}
+
+fn is_module(path: &Path) -> bool {
+    path.is_file() && path.extension().is_some_and(|ext| ext == "py")
+}
+
+fn read_dir_sorted(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut entries: Vec<PathBuf> = std::fs::read_dir(dir)?.flatten().map(|entry| entry.path()).collect();
+    entries.sort();
+    Ok(entries)
+}
+
+/// `(section, key) -> value` of an INI file, with indented continuation
+/// lines appended to the value the way configparser reads them
+fn parse_ini(content: &str) -> BTreeMap<(String, String), String> {
+    let mut values: BTreeMap<(String, String), String> = BTreeMap::new();
+    let mut section = String::new();
+    let mut current: Option<(String, String)> = None;
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with(';') {
+            continue;
+        }
+        if line.starts_with([' ', '\t']) {
+            if let Some(key) = &current
+                && let Some(value) = values.get_mut(key)
+            {
+                value.push('\n');
+                value.push_str(trimmed);
+            }
+            continue;
+        }
+        if let Some(name) = trimmed.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+            section = name.trim().to_string();
+            current = None;
+        } else if let Some((key, value)) = trimmed.split_once(['=', ':']) {
+            let key = (section.clone(), key.trim().to_string());
+            values.insert(key.clone(), value.trim().to_string());
+            current = Some(key);
+        }
+    }
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::installer::wheel::WheelFile;
+
+    fn write(root: &Path, files: &[(&str, &str)]) {
+        for (path, content) in files {
+            let path = root.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_pyproject_src_layout_wheel() {
+        let dir = tempfile::tempdir().unwrap();
+        write(
+            dir.path(),
+            &[
+                (
+                    "pyproject.toml",
+                    "[project]\nname = \"My-Tool\"\nversion = \"1.2.0\"\ndependencies = [\"requests>=2\"]\n\n\
+                     [project.scripts]\nmytool = \"my_tool.cli:main\"\n",
+                ),
+                ("src/my_tool/__init__.py", ""),
+                ("src/my_tool/cli.py", "def main(): pass\n"),
+                ("src/my_tool/__pycache__/cli.cpython-312.pyc", ""),
+                ("tests/test_cli.py", ""),
+            ],
+        );
+        let tree = SourceTree::load(dir.path()).unwrap();
+        assert_eq!((tree.name.as_str(), tree.version.as_str()), ("My-Tool", "1.2.0"));
+
+        let out = tempfile::tempdir().unwrap();
+        let wheel = tree.build_wheel(out.path(), &[("direct_url.json", "{}".to_string())]).unwrap();
+        let wheel = WheelFile::new(wheel).unwrap();
+        let metadata = wheel.get_metadata().unwrap();
+        assert_eq!(metadata.requires_dist, ["requests>=2"]);
+
+        let extracted = tempfile::tempdir().unwrap();
+        wheel.extract(extracted.path()).unwrap();
+        let dist_info = extracted.path().join("my_tool-1.2.0.dist-info");
+        let record = std::fs::read_to_string(dist_info.join("RECORD")).unwrap();
+        let recorded: Vec<&str> = record.lines().map(|line| line.split(',').next().unwrap()).collect();
+        assert_eq!(
+            recorded,
+            [
+                "my_tool/__init__.py",
+                "my_tool/cli.py",
+                "my_tool-1.2.0.dist-info/METADATA",
+                "my_tool-1.2.0.dist-info/WHEEL",
+                "my_tool-1.2.0.dist-info/entry_points.txt",
+                "my_tool-1.2.0.dist-info/direct_url.json",
+                "my_tool-1.2.0.dist-info/RECORD",
+            ]
+        );
+        assert!(std::fs::read_to_string(dist_info.join("entry_points.txt")).unwrap().contains("mytool = my_tool.cli:main"));
+    }
+
+    #[test]
+    fn test_setup_cfg_flat_layout() {
+        let dir = tempfile::tempdir().unwrap();
+        write(
+            dir.path(),
+            &[
+                (
+                    "setup.cfg",
+                    "[metadata]\nname = legacy\nversion = attr: legacy.__version__\n\n\
+                     [options]\ninstall_requires =\n    six\n    attrs>=20  # pinned low\n",
+                ),
+                ("setup.py", "from setuptools import setup\nsetup()\n"),
+                ("legacy.py", "__version__ = '3.0'\n"),
+            ],
+        );
+        let tree = SourceTree::load(dir.path()).unwrap();
+        assert_eq!((tree.name.as_str(), tree.version.as_str()), ("legacy", "0"));
+        assert_eq!(tree.requires_dist, ["six", "attrs>=20"]);
+        assert_eq!(tree.sources().unwrap().1, [dir.path().join("legacy.py")]);
+
+        write(dir.path(), &[("setup.py", "setup(ext_modules=[Extension('legacy._speedups', [])])\n")]);
+        assert!(SourceTree::load(dir.path()).unwrap_err().to_string().contains("extension modules"));
+    }
+}
@@ -0,0 +1,159 @@
+/// Plan to bring an environment in line with pinned requirements
+///
+/// Compares desired pins against installed distributions and lists what
+/// has to be installed or changed. Only `==` pins are compared by version;
+/// a looser requirement is satisfied by any installed version. Installed
+/// packages that aren't mentioned are left alone, since the dependencies
+/// of a requirements file aren't known without resolving it; a lock file
+/// names the whole closure, so [`SyncPlan::converge`] removes the rest.
+use crate::utils::names::canonicalize_name;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// Exit status of `pip sync` when it changed the environment (0 means it
+/// already matched), so CI can tell drift from a no-op
+pub const EXIT_CHANGED: i32 = 2;
+
+/// Packaging tools a sync never removes, though locks don't list them
+pub const SYNC_KEEPS: &[&str] = &["pip", "setuptools", "wheel"];
+
+/// A desired package, optionally pinned to an exact version
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pin {
+    pub name: String,
+    pub version: Option<String>,
+}
+
+/// One step of a plan
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    Install { name: String, version: Option<String> },
+    Replace { name: String, from: String, to: String },
+    Remove { name: String, version: String },
+}
+
+impl Change {
+    /// Requirement string that performs this change
+    pub fn requirement(&self) -> String {
+        match self {
+            Change::Install { name, version: Some(version) } | Change::Replace { name, to: version, .. } => {
+                format!("{}=={}", name, version)
+            }
+            Change::Install { name, version: None } | Change::Remove { name, .. } => name.clone(),
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Install { .. } => write!(f, "+ {}", self.requirement()),
+            Change::Replace { name, from, to } => write!(f, "~ {} {} -> {}", name, from, to),
+            Change::Remove { name, version } => write!(f, "- {} {}", name, version),
+        }
+    }
+}
+
+/// Changes needed for an environment to match its pins
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncPlan {
+    pub changes: Vec<Change>,
+}
+
+impl SyncPlan {
+    /// Diff `pins` against `installed` (project name -> version)
+    pub fn compute(pins: &[Pin], installed: &HashMap<String, String>) -> Self {
+        let installed: HashMap<String, &String> = installed.iter().map(|(n, v)| (canonicalize_name(n), v)).collect();
+        let changes = pins
+            .iter()
+            .filter_map(|pin| match (installed.get(&canonicalize_name(&pin.name)), &pin.version) {
+                (None, version) => Some(Change::Install {
+                    name: pin.name.clone(),
+                    version: version.clone(),
+                }),
+                (Some(current), Some(version)) if *current != version => Some(Change::Replace {
+                    name: pin.name.clone(),
+                    from: current.to_string(),
+                    to: version.clone(),
+                }),
+                _ => None,
+            })
+            .collect();
+        Self { changes }
+    }
+
+    /// [`SyncPlan::compute`], plus removing every installed package the
+    /// pins don't name, except those in `keep`
+    pub fn converge(pins: &[Pin], installed: &HashMap<String, String>, keep: &[&str]) -> Self {
+        let mut plan = Self::compute(pins, installed);
+        let wanted: HashSet<String> = pins
+            .iter()
+            .map(|pin| pin.name.as_str())
+            .chain(keep.iter().copied())
+            .map(canonicalize_name)
+            .collect();
+        let mut extra: Vec<Change> = installed
+            .iter()
+            .filter(|(name, _)| !wanted.contains(&canonicalize_name(name)))
+            .map(|(name, version)| Change::Remove {
+                name: name.clone(),
+                version: version.clone(),
+            })
+            .collect();
+        extra.sort_by_key(Change::requirement);
+        plan.changes.extend(extra);
+        plan
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Requirement strings that install what the plan adds or changes
+    pub fn requirements(&self) -> Vec<String> {
+        self.changes
+            .iter()
+            .filter(|change| !matches!(change, Change::Remove { .. }))
+            .map(Change::requirement)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_installs_missing_and_replaces_mismatched_pins() {
+        let installed: HashMap<String, String> = [("Requests", "2.30.0"), ("six", "1.16.0"), ("typing_extensions", "4.9.0")]
+            .into_iter()
+            .map(|(n, v)| (n.to_string(), v.to_string()))
+            .collect();
+        let pin = |name: &str, version: Option<&str>| Pin {
+            name: name.to_string(),
+            version: version.map(String::from),
+        };
+        let plan = SyncPlan::compute(
+            &[
+                pin("requests", Some("2.31.0")),
+                pin("six", Some("1.16.0")),
+                pin("typing-extensions", None),
+                pin("idna", None),
+                pin("urllib3", Some("2.2.1")),
+            ],
+            &installed,
+        );
+
+        let lines: Vec<String> = plan.changes.iter().map(ToString::to_string).collect();
+        assert_eq!(lines, vec!["~ requests 2.30.0 -> 2.31.0", "+ idna", "+ urllib3==2.2.1"]);
+        assert_eq!(plan.requirements(), vec!["requests==2.31.0", "idna", "urllib3==2.2.1"]);
+        assert!(SyncPlan::compute(&[pin("six", Some("1.16.0"))], &installed).is_empty());
+
+        let mut installed = installed;
+        installed.insert("pip".to_string(), "24.0".to_string());
+        let plan = SyncPlan::converge(&[pin("requests", Some("2.31.0"))], &installed, SYNC_KEEPS);
+        let lines: Vec<String> = plan.changes.iter().map(ToString::to_string).collect();
+        assert_eq!(lines, vec!["~ requests 2.30.0 -> 2.31.0", "- six 1.16.0", "- typing_extensions 4.9.0"]);
+        assert_eq!(plan.requirements(), vec!["requests==2.31.0"]);
+    }
+}
@@ -0,0 +1,382 @@
+/// Installing one wheel as a unit
+///
+/// A wheel is extracted into a staging directory inside site-packages, so
+/// its files later move into place by rename on the same filesystem, and
+/// every file it installs is staged before anything in the environment
+/// changes: files for site-packages wait in the staging directory, those for
+/// other scheme directories (scripts, data, headers) in hidden siblings of
+/// their targets. Only when the whole wheel is staged, its RECORD checked
+/// and every file hashed does [`Transaction::commit`] rename them into
+/// place, hard-linking each file it replaces first. If a rename fails, the
+/// files already moved are put back, so the previous installation is as it
+/// was; on any earlier error, dropping the transaction removes the staging
+/// directory, the siblings and the directories made for them.
+///
+/// A [`Snapshot`] keeps an installed distribution's files the same way, so
+/// a batch install can put back the versions it replaced
+/// (`--rollback-on-failure`).
+use super::existing::InstalledDist;
+use anyhow::{Result, anyhow};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tempfile::{TempDir, TempPath};
+use walkdir::WalkDir;
+
+/// Prefix of the staging directories in site-packages
+pub const STAGE_PREFIX: &str = ".pip-rs-stage-";
+
+/// Prefix of the snapshot directories in site-packages
+pub const SNAPSHOT_PREFIX: &str = ".pip-rs-snapshot-";
+
+/// A file waiting to be moved into place
+pub struct StagedFile {
+    pub target: PathBuf,
+    /// `target` with its directory's symlinks resolved, for RECORD
+    pub resolved: PathBuf,
+    /// URL-safe base64 of the sha256, as RECORD spells it
+    pub sha256: String,
+    pub size: u64,
+    /// In the staging directory, or a sibling of `target`
+    source: PathBuf,
+    /// Deletes a sibling that never made it into place
+    _sibling: Option<TempPath>,
+}
+
+/// The staged files of one wheel; see the module docs
+pub struct Transaction {
+    stage: TempDir,
+    site_packages: PathBuf,
+    files: Vec<StagedFile>,
+    /// Directories created for staged files, parents first
+    created_dirs: Vec<PathBuf>,
+}
+
+impl Transaction {
+    /// Start with an empty staging directory in `site_packages`
+    pub fn new(site_packages: &Path) -> Result<Self> {
+        let stage = tempfile::Builder::new().prefix(STAGE_PREFIX).tempdir_in(site_packages)?;
+        Ok(Self {
+            stage,
+            site_packages: site_packages.to_path_buf(),
+            files: Vec::new(),
+            created_dirs: Vec::new(),
+        })
+    }
+
+    /// Where the wheel is extracted
+    pub fn stage_dir(&self) -> &Path {
+        self.stage.path()
+    }
+
+    /// Files staged so far, in the order they will be moved
+    pub fn files(&self) -> &[StagedFile] {
+        &self.files
+    }
+
+    /// Stage `source` for `target`. An extracted file bound for
+    /// site-packages is moved as it is; anything else is copied next to
+    /// `target` now, keeping `source`'s permissions.
+    pub fn stage_file(&mut self, source: &Path, target: &Path) -> Result<()> {
+        let data = fs::read(source)?;
+        if source.starts_with(self.stage.path()) && target.starts_with(&self.site_packages) {
+            let resolved = self.create_parents(target)?;
+            self.push(target, resolved, &data, source.to_path_buf(), None);
+            return Ok(());
+        }
+        let sibling = self.write_sibling(target, &data)?;
+        fs::set_permissions(&sibling, fs::metadata(source)?.permissions())?;
+        let resolved = self.create_parents(target)?;
+        self.push(target, resolved, &data, sibling.to_path_buf(), Some(sibling));
+        Ok(())
+    }
+
+    /// Stage `contents` for `target`; `executable` for scripts
+    pub fn stage_bytes(&mut self, target: &Path, contents: &[u8], executable: bool) -> Result<()> {
+        let sibling = self.write_sibling(target, contents)?;
+        #[cfg(unix)]
+        if executable {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&sibling, fs::Permissions::from_mode(0o755))?;
+        }
+        #[cfg(not(unix))]
+        let _ = executable;
+        let resolved = self.create_parents(target)?;
+        self.push(target, resolved, contents, sibling.to_path_buf(), Some(sibling));
+        Ok(())
+    }
+
+    /// Stage every file under `source` for the same place below `target`
+    pub fn stage_tree(&mut self, source: &Path, target: &Path) -> Result<()> {
+        for file in WalkDir::new(source).into_iter().flatten().filter(|e| e.file_type().is_file()) {
+            let Ok(relative) = file.path().strip_prefix(source) else {
+                continue;
+            };
+            self.stage_file(file.path(), &target.join(relative))?;
+        }
+        Ok(())
+    }
+
+    /// Check the extracted wheel against its `RECORD`: every file listed
+    /// must be there and match its sha256, where one is given
+    pub fn check_record(&self, record: &Path) -> Result<()> {
+        let content = fs::read_to_string(record).map_err(|e| anyhow!("Cannot read {}: {}", record.display(), e))?;
+        for line in content.lines().filter(|line| !line.trim().is_empty()) {
+            // path,hash,size; only the path may contain (quoted) commas
+            let mut fields = line.rsplitn(3, ',');
+            let (_size, hash, path) = (fields.next(), fields.next(), fields.next());
+            let (Some(hash), Some(path)) = (hash, path) else {
+                return Err(anyhow!("Malformed RECORD line '{}'", line));
+            };
+            let path = path.trim_matches('"');
+            if Path::new(path).is_absolute() || path.split(['/', '\\']).any(|part| part == "..") {
+                return Err(anyhow!("RECORD lists {}, outside the wheel", path));
+            }
+            let file = self.stage.path().join(path);
+            if !file.is_file() {
+                return Err(anyhow!("RECORD lists {}, which the wheel doesn't contain", path));
+            }
+            if let Some(expected) = hash.strip_prefix("sha256=")
+                && URL_SAFE_NO_PAD.encode(Sha256::digest(fs::read(&file)?)) != expected.trim_end_matches('=')
+            {
+                return Err(anyhow!("{} doesn't match its RECORD hash", path));
+            }
+        }
+        Ok(())
+    }
+
+    /// Move every staged file into place. Should one fail, those already
+    /// moved are put back before the error is returned.
+    pub fn commit(mut self) -> Result<Vec<PathBuf>> {
+        let mut placed: Vec<(&Path, Option<TempPath>)> = Vec::new();
+        for file in &self.files {
+            match place(file) {
+                Ok(backup) => placed.push((&file.target, backup)),
+                Err(e) => {
+                    for (target, backup) in placed.into_iter().rev() {
+                        let restored = match backup {
+                            Some(backup) => backup.persist(target).map_err(|e| e.error),
+                            None => fs::remove_file(target),
+                        };
+                        if let Err(e) = restored {
+                            tracing::warn!("Could not roll back {}: {}", target.display(), e);
+                        }
+                    }
+                    return Err(e);
+                }
+            }
+        }
+        // They hold installed files now
+        self.created_dirs.clear();
+        Ok(self.files.iter().map(|file| file.target.clone()).collect())
+    }
+
+    fn push(&mut self, target: &Path, resolved: PathBuf, data: &[u8], source: PathBuf, sibling: Option<TempPath>) {
+        self.files.push(StagedFile {
+            target: target.to_path_buf(),
+            resolved,
+            sha256: URL_SAFE_NO_PAD.encode(Sha256::digest(data)),
+            size: data.len() as u64,
+            source,
+            _sibling: sibling,
+        });
+    }
+
+    /// `contents` in a hidden file beside `target`, so the rename stays on
+    /// one filesystem
+    fn write_sibling(&mut self, target: &Path, contents: &[u8]) -> Result<TempPath> {
+        self.create_parents(target)?;
+        let parent = target.parent().ok_or_else(|| anyhow!("{} has no parent directory", target.display()))?;
+        let mut sibling = tempfile::Builder::new().prefix(".pip-rs-").tempfile_in(parent)?;
+        sibling.write_all(contents)?;
+        sibling.as_file().sync_all()?;
+        Ok(sibling.into_temp_path())
+    }
+
+    /// Create the directories above `target`, remembering those that were
+    /// missing; returns `target` with its directory resolved
+    fn create_parents(&mut self, target: &Path) -> Result<PathBuf> {
+        let parent = target.parent().ok_or_else(|| anyhow!("{} has no parent directory", target.display()))?;
+        let mut missing = Vec::new();
+        let mut dir = Some(parent);
+        while let Some(current) = dir.filter(|dir| !dir.exists()) {
+            missing.push(current.to_path_buf());
+            dir = current.parent();
+        }
+        fs::create_dir_all(parent)?;
+        self.created_dirs.extend(missing.into_iter().rev());
+        Ok(parent.canonicalize()?.join(target.file_name().unwrap_or_default()))
+    }
+}
+
+impl Drop for Transaction {
+    fn drop(&mut self) {
+        // Siblings go with their paths and the stage with its TempDir;
+        // the directories made for them only while still empty
+        self.files.clear();
+        for dir in self.created_dirs.iter().rev() {
+            let _ = fs::remove_dir(dir);
+        }
+    }
+}
+
+/// Rename `file` over its target, returning a link to what it replaced
+fn place(file: &StagedFile) -> Result<Option<TempPath>> {
+    let backup = if file.target.is_file() { Some(link_aside(&file.target, None)?) } else { None };
+    fs::rename(&file.source, &file.target).map_err(|e| anyhow!("Failed to replace {}: {}", file.target.display(), e))?;
+    Ok(backup)
+}
+
+/// A hidden hard link to `path` in `dir` (beside it by default), or a copy
+/// where links aren't supported
+fn link_aside(path: &Path, dir: Option<&Path>) -> Result<TempPath> {
+    let dir = match dir.or_else(|| path.parent()) {
+        Some(dir) => dir,
+        None => return Err(anyhow!("{} has no parent directory", path.display())),
+    };
+    let kept = tempfile::Builder::new()
+        .prefix(".pip-rs-backup-")
+        .make_in(dir, |kept| fs::hard_link(path, kept).or_else(|_| fs::copy(path, kept).map(drop)))?;
+    Ok(kept.into_temp_path())
+}
+
+/// Links to every file of an installed distribution, taken before a batch
+/// replaces it so it can be put back
+pub struct Snapshot {
+    /// Removed along with the links once the snapshot is dropped
+    _dir: TempDir,
+    /// Where each file was, and its link
+    files: Vec<(PathBuf, TempPath)>,
+}
+
+impl Snapshot {
+    /// Keep the files `dist`'s RECORD lists and everything in its dist-info
+    pub fn take(dist: &InstalledDist, site_packages: &Path) -> Result<Self> {
+        let dir = tempfile::Builder::new().prefix(SNAPSHOT_PREFIX).tempdir_in(site_packages)?;
+        let mut paths: Vec<PathBuf> = dist.recorded_files(site_packages).into_iter().collect();
+        paths.extend(WalkDir::new(&dist.dist_info).into_iter().flatten().map(|entry| entry.into_path()));
+        let mut seen = HashSet::new();
+        let mut files = Vec::new();
+        for path in paths {
+            if path.is_file() && seen.insert(path.canonicalize()?) {
+                files.push((path.clone(), link_aside(&path, Some(dir.path()))?));
+            }
+        }
+        Ok(Self { _dir: dir, files })
+    }
+
+    /// Put every file back where it was
+    pub fn restore(self) -> Result<()> {
+        for (path, kept) in self.files {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            // Scripts may live on another filesystem than site-packages
+            if let Err(e) = kept.persist(&path) {
+                fs::copy(&e.path, &path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Remove staging and snapshot directories a killed process left in
+/// `site_packages`; only call while holding its environment lock
+pub fn remove_leftovers(site_packages: &Path) {
+    let Ok(entries) = fs::read_dir(site_packages) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with(STAGE_PREFIX) || name.starts_with(SNAPSHOT_PREFIX) {
+            tracing::debug!("Removing leftover {}", entry.path().display());
+            let _ = fs::remove_dir_all(entry.path());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hidden(dir: &Path) -> Vec<String> {
+        WalkDir::new(dir)
+            .into_iter()
+            .flatten()
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .filter(|name| name.starts_with(".pip-rs-"))
+            .collect()
+    }
+
+    #[test]
+    fn test_failed_commit_puts_replaced_files_back() {
+        let temp = tempfile::tempdir().unwrap();
+        let site = temp.path().join("site-packages");
+        let bin = temp.path().join("bin");
+        fs::create_dir_all(site.join("pkg")).unwrap();
+        fs::write(site.join("pkg/a.py"), "old").unwrap();
+        // A directory where a file goes: its rename fails last
+        fs::create_dir_all(bin.join("tool")).unwrap();
+
+        let mut transaction = Transaction::new(&site).unwrap();
+        let stage = transaction.stage_dir().to_path_buf();
+        fs::create_dir_all(stage.join("pkg/sub")).unwrap();
+        fs::write(stage.join("pkg/a.py"), "new").unwrap();
+        fs::write(stage.join("pkg/sub/b.py"), "b").unwrap();
+        transaction.stage_tree(&stage.join("pkg"), &site.join("pkg")).unwrap();
+        transaction.stage_bytes(&bin.join("tool"), b"#!/bin/sh\n", true).unwrap();
+        assert_eq!(fs::read_to_string(site.join("pkg/a.py")).unwrap(), "old");
+        assert_eq!(transaction.files().len(), 3);
+
+        assert!(transaction.commit().is_err());
+        assert_eq!(fs::read_to_string(site.join("pkg/a.py")).unwrap(), "old");
+        assert!(!site.join("pkg/sub").exists());
+        assert!(bin.join("tool").is_dir());
+        assert!(hidden(temp.path()).is_empty(), "{:?}", hidden(temp.path()));
+    }
+
+    #[test]
+    fn test_check_record_wants_every_listed_file() {
+        let temp = tempfile::tempdir().unwrap();
+        let transaction = Transaction::new(temp.path()).unwrap();
+        let stage = transaction.stage_dir();
+        fs::create_dir_all(stage.join("demo-1.0.dist-info")).unwrap();
+        fs::write(stage.join("demo.py"), "x = 1\n").unwrap();
+        let hash = URL_SAFE_NO_PAD.encode(Sha256::digest(b"x = 1\n"));
+        let record = stage.join("demo-1.0.dist-info/RECORD");
+
+        fs::write(&record, format!("demo.py,sha256={},6\ndemo-1.0.dist-info/RECORD,,\n", hash)).unwrap();
+        transaction.check_record(&record).unwrap();
+        fs::write(&record, "demo.py,sha256=AAAA,6\n").unwrap();
+        assert!(transaction.check_record(&record).unwrap_err().to_string().contains("doesn't match"));
+        fs::write(&record, "missing.py,,\n").unwrap();
+        assert!(transaction.check_record(&record).unwrap_err().to_string().contains("doesn't contain"));
+        fs::write(&record, "../escape.py,,\n").unwrap();
+        assert!(transaction.check_record(&record).is_err());
+    }
+
+    #[test]
+    fn test_snapshot_restores_a_removed_distribution() {
+        let temp = tempfile::tempdir().unwrap();
+        let site = temp.path();
+        let dist_info = site.join("demo-1.0.dist-info");
+        fs::create_dir_all(site.join("demo")).unwrap();
+        fs::create_dir_all(&dist_info).unwrap();
+        fs::write(site.join("demo/__init__.py"), "v1").unwrap();
+        fs::write(dist_info.join("METADATA"), "Name: demo\nVersion: 1.0\n").unwrap();
+        fs::write(dist_info.join("RECORD"), "demo/__init__.py,,\ndemo-1.0.dist-info/METADATA,,\n").unwrap();
+        let dist = InstalledDist::find(site, "demo").unwrap();
+
+        let snapshot = Snapshot::take(&dist, site).unwrap();
+        dist.remove(site, &HashSet::new()).unwrap();
+        assert!(!site.join("demo").exists());
+        snapshot.restore().unwrap();
+        assert_eq!(fs::read_to_string(site.join("demo/__init__.py")).unwrap(), "v1");
+        assert!(dist_info.join("RECORD").is_file());
+        assert!(hidden(site).is_empty());
+    }
+}
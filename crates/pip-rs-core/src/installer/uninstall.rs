@@ -0,0 +1,310 @@
+/// What uninstalling one distribution removes
+///
+/// The plan is worked out before anything is deleted so the command can show
+/// it and ask first. It covers every file the `RECORD` lists (modules,
+/// scripts under bin/Scripts, data files), the console scripts pip-rs
+/// generated from `entry_points.txt`, the dist-info, and for setuptools
+/// development installs the `.egg-link` and its `easy-install.pth` line.
+/// Recorded paths that escape site-packages and the scripts directories are
+/// only removed when forced.
+use super::egg_link_handler::EggLinkHandler;
+use super::entry_point::{self, ScriptOwner};
+use super::existing::{InstalledDist, prune_empty};
+use crate::utils::names::canonicalize_name;
+use anyhow::{Result, bail};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// A setuptools development install
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EggLinkInstall {
+    pub egg_link: PathBuf,
+    /// The project directory, as the first line of the `.egg-link` names it
+    pub project: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UninstallPlan {
+    pub name: String,
+    pub dist: Option<InstalledDist>,
+    /// Files to delete, sorted; includes those in `outside`
+    pub files: Vec<PathBuf>,
+    /// Files outside site-packages and the scripts directories
+    pub outside: Vec<PathBuf>,
+    pub egg_link: Option<EggLinkInstall>,
+}
+
+impl UninstallPlan {
+    /// Plan the removal of `name`, or `None` when it isn't installed.
+    /// `scripts_dirs` are where its scripts may live besides site-packages.
+    pub fn new(site_packages: &Path, scripts_dirs: &[PathBuf], name: &str) -> Option<Self> {
+        let dist = InstalledDist::find(site_packages, name);
+        let egg_link = EggLinkHandler::find_egg_link(name, site_packages).and_then(|egg_link| {
+            let content = fs::read_to_string(&egg_link).ok()?;
+            let project = content.lines().next()?.trim().to_string();
+            Some(EggLinkInstall { egg_link, project })
+        });
+        if dist.is_none() && egg_link.is_none() {
+            return None;
+        }
+
+        let mut files: Vec<PathBuf> = match &dist {
+            Some(dist) => dist
+                .recorded_files(site_packages)
+                .into_iter()
+                .map(|path| normalize(&path))
+                .filter(|path| path.symlink_metadata().is_ok_and(|meta| !meta.is_dir()))
+                .collect(),
+            None => Vec::new(),
+        };
+        if let Some(dist) = &dist {
+            for script in generated_scripts(dist, scripts_dirs) {
+                if !files.contains(&script) {
+                    files.push(script);
+                }
+            }
+        }
+        files.sort();
+
+        // Compared both as written and resolved: the virtualenv's bin
+        // directory is found through canonical paths
+        let roots: Vec<PathBuf> = std::iter::once(site_packages)
+            .chain(scripts_dirs.iter().map(PathBuf::as_path))
+            .flat_map(|root| [Some(normalize(root)), root.canonicalize().ok()])
+            .flatten()
+            .collect();
+        let inside = |path: &Path| {
+            let resolved = path.parent().and_then(|parent| parent.canonicalize().ok());
+            roots
+                .iter()
+                .any(|root| path.starts_with(root) || resolved.as_ref().is_some_and(|dir| dir.starts_with(root)))
+        };
+        let outside = files.iter().filter(|path| !inside(path)).cloned().collect();
+
+        Some(Self {
+            name: dist.as_ref().map_or_else(|| name.to_string(), |dist| dist.name.clone()),
+            dist,
+            files,
+            outside,
+            egg_link,
+        })
+    }
+
+    /// `name version`, or just the name for a bare `.egg-link`
+    pub fn label(&self) -> String {
+        match &self.dist {
+            Some(dist) => format!("{} {}", dist.name, dist.version),
+            None => self.name.clone(),
+        }
+    }
+
+    /// One line per thing that goes away, for showing before the prompt
+    pub fn preview(&self, site_packages: &Path) -> Vec<String> {
+        let mut lines: Vec<String> = self.files.iter().map(|path| path.display().to_string()).collect();
+        if let Some(dist) = &self.dist {
+            lines.push(format!("{}/", dist.dist_info.display()));
+        }
+        if let Some(egg_link) = &self.egg_link {
+            lines.push(egg_link.egg_link.display().to_string());
+            lines.push(format!("{} (entry {})", site_packages.join("easy-install.pth").display(), egg_link.project));
+        }
+        lines
+    }
+
+    /// Why the plan can't run without `--force`, if it can't
+    pub fn refusal(&self) -> Option<String> {
+        if self.outside.is_empty() {
+            return None;
+        }
+        let paths: Vec<String> = self.outside.iter().map(|path| path.display().to_string()).collect();
+        Some(format!(
+            "{} lists files outside site-packages and the scripts directory: {}; pass --force to remove them anyway",
+            self.label(),
+            paths.join(", ")
+        ))
+    }
+
+    /// Delete everything in the plan, holding the environment lock. Returns
+    /// how many files were removed.
+    pub fn execute(&self, site_packages: &Path, force: bool) -> Result<usize> {
+        if let Some(refusal) = self.refusal().filter(|_| !force) {
+            bail!(refusal);
+        }
+        let _lock = super::env_lock::EnvironmentLock::acquire(site_packages)?;
+
+        let mut removed = 0;
+        let mut parents = Vec::new();
+        for path in &self.files {
+            match fs::remove_file(path) {
+                Ok(()) => removed += 1,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            }
+            if let Some(parent) = path.parent() {
+                parents.push(parent.to_path_buf());
+            }
+        }
+        if let Some(dist) = &self.dist {
+            fs::remove_dir_all(&dist.dist_info)?;
+        }
+        if let Some(egg_link) = &self.egg_link {
+            fs::remove_file(&egg_link.egg_link)?;
+            remove_pth_entry(&site_packages.join("easy-install.pth"), &egg_link.project)?;
+        }
+
+        // Deepest first, so emptied package directories go before their
+        // parents; the scripts directories are shared and stay
+        parents.sort_by_key(|p| std::cmp::Reverse(p.components().count()));
+        parents.dedup();
+        let site_packages = normalize(site_packages);
+        for dir in parents {
+            if dir.starts_with(&site_packages) {
+                prune_empty(&dir, &site_packages);
+            }
+        }
+        Ok(removed)
+    }
+}
+
+/// Where scripts of packages in `site_packages` are installed: the `bin`
+/// (`Scripts`) directory of its environment, and the per-user directory
+/// older pip-rs versions wrote console scripts to
+pub fn scripts_dirs(site_packages: &Path) -> Vec<PathBuf> {
+    let virtual_env = crate::utils::python_env::virtual_env();
+    let mut dirs = vec![entry_point::scripts_target(site_packages, virtual_env.as_deref()).dir];
+    let legacy = entry_point::scripts_dir();
+    if !dirs.contains(&legacy) {
+        dirs.push(legacy);
+    }
+    dirs
+}
+
+/// Console scripts pip-rs wrote for `dist`; scripts another distribution
+/// owns under the same name are left alone
+fn generated_scripts(dist: &InstalledDist, scripts_dirs: &[PathBuf]) -> Vec<PathBuf> {
+    let Ok(entry_points) = fs::read_to_string(dist.dist_info.join("entry_points.txt")) else {
+        return Vec::new();
+    };
+    let owner = canonicalize_name(&dist.name);
+    let mut scripts = Vec::new();
+    for script in entry_point::console_scripts(&entry_points) {
+        let file_names = entry_point::script_files(&script.name, cfg!(target_os = "windows"));
+        for dir in scripts_dirs {
+            let paths: Vec<PathBuf> = file_names.iter().map(|name| normalize(&dir.join(name))).collect();
+            // The first file holds the script; a Windows `.cmd` goes with it
+            let ours = fs::read_to_string(&paths[0]).ok().and_then(|content| entry_point::script_owner(&content));
+            if matches!(ours, Some(ScriptOwner::PipRs { package, .. }) if canonicalize_name(&package) == owner) {
+                scripts.extend(paths.into_iter().filter(|path| path.exists()));
+            }
+        }
+    }
+    scripts
+}
+
+/// Drop the line naming `project` from `easy-install.pth`
+fn remove_pth_entry(pth: &Path, project: &str) -> Result<()> {
+    let Ok(content) = fs::read_to_string(pth) else {
+        return Ok(());
+    };
+    let kept: Vec<&str> = content.lines().filter(|line| line.trim() != project).collect();
+    if kept.len() == content.lines().count() {
+        return Ok(());
+    }
+    let mut rewritten = kept.join("\n");
+    if !rewritten.is_empty() {
+        rewritten.push('\n');
+    }
+    super::replace::write_file(pth, rewritten.as_bytes())?;
+    Ok(())
+}
+
+/// Resolve `.` and `..` without touching the filesystem, so a RECORD entry
+/// like `../../../bin/tool` compares by where it really points
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_plan_covers_record_scripts_and_data() {
+        let dir = tempfile::tempdir().unwrap();
+        let venv = dir.path().join("venv");
+        let site = venv.join("lib/python3.12/site-packages");
+        let bin = venv.join("bin");
+        write(&site.join("tool/__init__.py"), "");
+        write(&site.join("tool/__pycache__/__init__.cpython-312.pyc"), "");
+        write(&bin.join("tool-legacy"), "#!/bin/sh\n");
+        write(&venv.join("share/tool/data.json"), "{}");
+        write(&site.join("tool-1.0.dist-info/METADATA"), "Name: tool\nVersion: 1.0\n");
+        write(&site.join("tool-1.0.dist-info/entry_points.txt"), "[console_scripts]\ntool = tool:main\nother = tool:main\n");
+        write(
+            &site.join("tool-1.0.dist-info/RECORD"),
+            "tool/__init__.py,,\n../../../bin/tool-legacy,,\n../../../share/tool/data.json,,\n\
+             tool-1.0.dist-info/RECORD,,\n",
+        );
+        let script = |package: &str| {
+            entry_point::EntryPoint::new("tool".into(), "tool".into(), "main".into())
+                .with_owner(package, &site)
+                .render(false)
+        };
+        write(&bin.join("tool"), &script("tool"));
+        // Same name, generated for another distribution
+        write(&bin.join("other"), &script("other-dist"));
+
+        let scripts_dirs = vec![bin.clone()];
+        let plan = UninstallPlan::new(&site, &scripts_dirs, "Tool").unwrap();
+        assert_eq!(plan.label(), "tool 1.0");
+        let mut expected = vec![
+            site.join("tool/__init__.py"),
+            bin.join("tool-legacy"),
+            bin.join("tool"),
+            venv.join("share/tool/data.json"),
+        ];
+        expected.sort();
+        assert_eq!(plan.files, expected);
+        assert_eq!(plan.outside, [venv.join("share/tool/data.json")]);
+        assert!(plan.refusal().unwrap().contains("--force"));
+
+        // Nothing is touched when refused
+        assert!(plan.execute(&site, false).is_err());
+        assert!(site.join("tool/__init__.py").exists());
+
+        assert_eq!(plan.execute(&site, true).unwrap(), 4);
+        assert!(!site.join("tool").exists() && !site.join("tool-1.0.dist-info").exists());
+        assert!(!bin.join("tool").exists() && bin.join("other").exists());
+        assert!(UninstallPlan::new(&site, &scripts_dirs, "tool").is_none());
+    }
+
+    #[test]
+    fn test_egg_link_editable() {
+        let dir = tempfile::tempdir().unwrap();
+        let site = dir.path();
+        write(&site.join("legacy_pkg.egg-link"), "/src/legacy\n.\n");
+        write(&site.join("easy-install.pth"), "/src/other\n/src/legacy\n");
+
+        let plan = UninstallPlan::new(site, &[], "legacy-pkg").unwrap();
+        assert!(plan.dist.is_none() && plan.refusal().is_none());
+        assert_eq!(plan.preview(site).len(), 2);
+        plan.execute(site, false).unwrap();
+        assert!(!site.join("legacy_pkg.egg-link").exists());
+        assert_eq!(fs::read_to_string(site.join("easy-install.pth")).unwrap(), "/src/other\n");
+    }
+}
@@ -0,0 +1,123 @@
+/// Virtual environment enforcement (PIP_REQUIRE_VIRTUALENV)
+///
+/// When a virtual environment is required, mutating commands refuse to touch
+/// a site-packages directory that does not belong to one. Explicit
+/// destinations such as `--target` are exempt.
+use anyhow::{Result, anyhow};
+use std::path::{Path, PathBuf};
+
+/// Find the virtual environment that owns `site_packages`, if any.
+///
+/// `VIRTUAL_ENV` is only trusted when the directory still exists and
+/// actually contains the site-packages directory; otherwise we look for a
+/// `pyvenv.cfg` in the ancestors of site-packages.
+pub fn owning_virtualenv(site_packages: &Path, virtual_env: Option<&Path>) -> Option<PathBuf> {
+    let site_packages = site_packages
+        .canonicalize()
+        .unwrap_or_else(|_| site_packages.to_path_buf());
+
+    if let Some(venv) = virtual_env {
+        match venv.canonicalize() {
+            Ok(venv) if site_packages.starts_with(&venv) => return Some(venv),
+            Ok(venv) => tracing::debug!(
+                "VIRTUAL_ENV {} does not own {}",
+                venv.display(),
+                site_packages.display()
+            ),
+            Err(_) => tracing::debug!("Ignoring stale VIRTUAL_ENV {}", venv.display()),
+        }
+    }
+
+    site_packages
+        .ancestors()
+        .skip(1)
+        .find(|dir| dir.join("pyvenv.cfg").is_file())
+        .map(|dir| dir.to_path_buf())
+}
+
+/// Check that installing into `site_packages` is allowed.
+///
+/// `explicit_target` is true for `--target` style destinations, which the
+/// user chose deliberately and are never refused.
+pub fn check(
+    required: bool,
+    explicit_target: bool,
+    site_packages: &Path,
+    virtual_env: Option<&Path>,
+) -> Result<()> {
+    if !required || explicit_target {
+        return Ok(());
+    }
+
+    match owning_virtualenv(site_packages, virtual_env) {
+        Some(venv) => {
+            tracing::debug!("Target site-packages belongs to virtualenv {}", venv.display());
+            Ok(())
+        }
+        None => Err(anyhow!(
+            "Could not find an activated virtualenv (required); refusing to modify {}",
+            site_packages.display()
+        )),
+    }
+}
+
+/// Check the current process environment (reads `VIRTUAL_ENV`, or
+/// `--python`)
+pub fn check_current(required: bool, explicit_target: bool, site_packages: &Path) -> Result<()> {
+    check(required, explicit_target, site_packages, crate::utils::python_env::virtual_env().as_deref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn fixture_venv(root: &Path) -> PathBuf {
+        let site_packages = root.join("lib").join("python3.11").join("site-packages");
+        fs::create_dir_all(&site_packages).unwrap();
+        fs::write(root.join("pyvenv.cfg"), "home = /usr/bin\nversion = 3.11.4\n").unwrap();
+        site_packages
+    }
+
+    #[test]
+    fn test_refuses_outside_virtualenv() {
+        let temp = TempDir::new().unwrap();
+        let system_site = temp.path().join("usr/lib/python3.11/site-packages");
+        fs::create_dir_all(&system_site).unwrap();
+
+        let err = check(true, false, &system_site, None).unwrap_err();
+        assert!(err.to_string().contains("virtualenv (required)"));
+        // Not required: anything goes
+        assert!(check(false, false, &system_site, None).is_ok());
+        // --target destinations are explicit and exempt
+        assert!(check(true, true, &system_site, None).is_ok());
+    }
+
+    #[test]
+    fn test_accepts_fixture_virtualenv() {
+        let temp = TempDir::new().unwrap();
+        let venv = temp.path().join(".venv");
+        let site_packages = fixture_venv(&venv);
+
+        assert!(check(true, false, &site_packages, Some(&venv)).is_ok());
+        // pyvenv.cfg alone is enough when VIRTUAL_ENV is not set
+        assert!(check(true, false, &site_packages, None).is_ok());
+    }
+
+    #[test]
+    fn test_stale_virtual_env_is_not_trusted() {
+        let temp = TempDir::new().unwrap();
+        let system_site = temp.path().join("usr/lib/python3.11/site-packages");
+        fs::create_dir_all(&system_site).unwrap();
+
+        // Deleted venv directory
+        let deleted = temp.path().join("old-venv");
+        assert!(check(true, false, &system_site, Some(&deleted)).is_err());
+
+        // Existing venv that does not own the chosen site-packages
+        let other = temp.path().join("other-venv");
+        fixture_venv(&other);
+        assert!(check(true, false, &system_site, Some(&other)).is_err());
+    }
+}
@@ -1,10 +1,30 @@
 /// Wheel file handling
+use crate::utils::names::canonicalize_name;
 use anyhow::{Result, anyhow};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use sha2::{Digest, Sha256};
+use std::fmt;
 use std::path::{Path, PathBuf};
 use std::fs;
 use std::io::Read;
 use zip::ZipArchive;
 
+/// A wheel that can't be installed because of its own contents, as
+/// opposed to the environment it is being installed into
+#[derive(Debug)]
+pub struct BrokenWheel {
+    pub reason: String,
+}
+
+impl fmt::Display for BrokenWheel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.reason)
+    }
+}
+
+impl std::error::Error for BrokenWheel {}
+
 #[derive(Debug, Clone)]
 pub struct WheelFile {
     pub path: PathBuf,
@@ -72,6 +92,61 @@ impl WheelFile {
 
         Err(anyhow!("METADATA file not found in wheel"))
     }
+
+    /// Check the archive before anything is written: it must open as a
+    /// zip, METADATA must name `expected_name`, and every file RECORD
+    /// lists with a sha256 must be present and match it
+    pub fn verify(&self, expected_name: &str) -> std::result::Result<(), BrokenWheel> {
+        let broken = |reason: String| BrokenWheel { reason };
+        // Not being able to open the file at all isn't the artifact's fault
+        let Ok(file) = fs::File::open(&self.path) else {
+            return Ok(());
+        };
+        let mut archive = ZipArchive::new(file).map_err(|e| broken(format!("not a valid zip archive: {}", e)))?;
+
+        let metadata_path = archive
+            .file_names()
+            .find(|name| name.ends_with(".dist-info/METADATA") && name.matches('/').count() == 1)
+            .map(String::from)
+            .ok_or_else(|| broken("no .dist-info/METADATA in the archive".to_string()))?;
+        let dist_info = metadata_path.trim_end_matches("METADATA").to_string();
+        let metadata = String::from_utf8_lossy(&read_entry(&mut archive, &metadata_path)?).into_owned();
+        let name = WheelMetadata::parse(&metadata).map(|m| m.name).unwrap_or_default();
+        if canonicalize_name(name.trim()) != canonicalize_name(expected_name) {
+            return Err(broken(format!("METADATA names '{}', not {}", name.trim(), expected_name)));
+        }
+
+        let record_path = format!("{}RECORD", dist_info);
+        let record = read_entry(&mut archive, &record_path)?;
+        for line in String::from_utf8_lossy(&record).lines().filter(|line| !line.trim().is_empty()) {
+            // path,hash,size; only the path may contain (quoted) commas
+            let mut fields = line.rsplitn(3, ',');
+            let (_size, hash, path) = (fields.next(), fields.next(), fields.next());
+            let (Some(hash), Some(path)) = (hash, path) else {
+                return Err(broken(format!("malformed RECORD line '{}'", line)));
+            };
+            let Some(expected) = hash.strip_prefix("sha256=") else {
+                continue;
+            };
+            let path = path.trim_matches('"');
+            let data = read_entry(&mut archive, path)?;
+            if URL_SAFE_NO_PAD.encode(Sha256::digest(&data)) != expected.trim_end_matches('=') {
+                return Err(broken(format!("{} doesn't match its RECORD hash", path)));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn read_entry(archive: &mut ZipArchive<fs::File>, name: &str) -> std::result::Result<Vec<u8>, BrokenWheel> {
+    let mut entry = archive.by_name(name).map_err(|_| BrokenWheel {
+        reason: format!("{} is missing from the archive", name),
+    })?;
+    let mut data = Vec::new();
+    entry.read_to_end(&mut data).map_err(|e| BrokenWheel {
+        reason: format!("{} is corrupt: {}", name, e),
+    })?;
+    Ok(data)
 }
 
 #[derive(Debug, Clone)]
@@ -79,6 +154,7 @@ pub struct WheelMetadata {
     pub name: String,
     pub version: String,
     pub summary: Option<String>,
+    pub requires_python: Option<String>,
     pub requires_dist: Vec<String>,
 }
 
@@ -87,6 +163,7 @@ impl WheelMetadata {
         let mut name = String::new();
         let mut version = String::new();
         let mut summary = None;
+        let mut requires_python = None;
         let mut requires_dist = Vec::new();
 
         for line in content.lines() {
@@ -96,6 +173,8 @@ impl WheelMetadata {
                 version = line[9..].to_string();
             } else if line.starts_with("Summary: ") {
                 summary = Some(line[9..].to_string());
+            } else if let Some(specifier) = line.strip_prefix("Requires-Python: ") {
+                requires_python = Some(specifier.to_string());
             } else if line.starts_with("Requires-Dist: ") {
                 requires_dist.push(line[15..].to_string());
             }
@@ -105,6 +184,7 @@ impl WheelMetadata {
             name,
             version,
             summary,
+            requires_python,
             requires_dist,
         })
     }
@@ -121,4 +201,48 @@ mod tests {
         assert_eq!(wheel.name, "requests");
         assert_eq!(wheel.version, "2.28.0");
     }
+
+    /// A wheel whose RECORD hashes `recorded` while the archive holds `actual`
+    fn wheel_with(dir: &Path, metadata_name: &str, actual: &str, recorded: &str) -> WheelFile {
+        use std::io::Write;
+        fs::create_dir_all(dir).unwrap();
+        let path = dir.join("demo-1.0-py3-none-any.whl");
+        let mut zip = zip::ZipWriter::new(fs::File::create(&path).unwrap());
+        let metadata = format!("Metadata-Version: 2.1\nName: {}\nVersion: 1.0\n", metadata_name);
+        let record = format!(
+            "demo/__init__.py,sha256={},{}\ndemo-1.0.dist-info/METADATA,sha256={},{}\ndemo-1.0.dist-info/RECORD,,\n",
+            URL_SAFE_NO_PAD.encode(Sha256::digest(recorded)),
+            recorded.len(),
+            URL_SAFE_NO_PAD.encode(Sha256::digest(&metadata)),
+            metadata.len()
+        );
+        for (name, data) in [
+            ("demo/__init__.py", actual),
+            ("demo-1.0.dist-info/METADATA", metadata.as_str()),
+            ("demo-1.0.dist-info/RECORD", record.as_str()),
+        ] {
+            zip.start_file(name, zip::write::FileOptions::default()).unwrap();
+            zip.write_all(data.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+        WheelFile::new(path).unwrap()
+    }
+
+    #[test]
+    fn test_verify_catches_broken_wheels() {
+        let dir = tempfile::tempdir().unwrap();
+        let ok = wheel_with(&dir.path().join("ok"), "Demo", "VALUE = 1\n", "VALUE = 1\n");
+        assert!(ok.verify("demo").is_ok());
+
+        let renamed = wheel_with(&dir.path().join("renamed"), "other", "VALUE = 1\n", "VALUE = 1\n");
+        assert_eq!(renamed.verify("demo").unwrap_err().reason, "METADATA names 'other', not demo");
+
+        let tampered = wheel_with(&dir.path().join("tampered"), "demo", "VALUE = 2\n", "VALUE = 1\n");
+        assert_eq!(tampered.verify("demo").unwrap_err().reason, "demo/__init__.py doesn't match its RECORD hash");
+
+        let truncated = dir.path().join("demo-1.0-py3-none-any.whl");
+        fs::write(&truncated, b"PK\x03\x04 not really a zip").unwrap();
+        let reason = WheelFile::new(truncated).unwrap().verify("demo").unwrap_err().reason;
+        assert!(reason.starts_with("not a valid zip archive"), "{}", reason);
+    }
 }
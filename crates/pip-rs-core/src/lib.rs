@@ -7,3 +7,4 @@ pub mod cache;
 pub mod venv;
 pub mod config;
 pub mod errors;
+pub mod vcs;
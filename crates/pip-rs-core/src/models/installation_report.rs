@@ -1,35 +1,89 @@
 /// Installation report with environment override support
-/// 
+///
 /// This module handles installation reports with support for environment
-/// overrides (--python-version, --platform, etc.)
-
-use std::collections::HashMap;
+/// overrides (--python-version, --platform, etc.). `pip install --report`
+/// writes the serialized [`InstallationReport`]; its layout is versioned by
+/// [`REPORT_VERSION`].
+use crate::installer::batch::{BatchReport, FailedPackage, InstallSummary, SkippedPackage};
+use crate::installer::fallback::ArtifactFallback;
+use crate::resolver::deprecation::DeprecationNotice;
+use crate::resolver::markers::MarkerSkip;
+use crate::resolver::DependencyMode;
+use serde::Serialize;
+
+/// Version of the report layout; bumped when a field changes meaning or goes away
+pub const REPORT_VERSION: u32 = 1;
 
 /// Installation report
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct InstallationReport {
+    /// Layout version, [`REPORT_VERSION`]
+    pub version: u32,
+    pub timestamp: String,
+    /// Packages are what an install would do; nothing was written
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub dry_run: bool,
+    /// Packages installed, and resolved packages whose installed version is kept
+    #[serde(rename = "installed")]
     pub installed_packages: Vec<InstalledPackage>,
+    /// Bytes written by the packages installed, where known
+    pub total_size: u64,
+    /// The environment installed for, overrides applied
     pub environment: EnvironmentInfo,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub environment_override: Option<EnvironmentOverride>,
-    pub timestamp: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failed: Vec<FailedPackage>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub skipped: Vec<SkippedPackage>,
+    /// Installed, then removed again because another package failed
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rolled_back: Vec<crate::installer::batch::InstalledPackage>,
+    /// Dependencies the resolver left out because of environment markers
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub skipped_by_markers: Vec<MarkerSkip>,
+    /// Packages installed from another file after their first one was broken
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fallbacks: Vec<ArtifactFallback>,
+    /// Selected releases that declare themselves deprecated or unmaintained
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub deprecated: Vec<DeprecationNotice>,
+    /// `no-deps` or `only-deps` when part of the graph was left out on purpose
+    #[serde(skip_serializing_if = "DependencyMode::is_all")]
+    pub dependency_mode: DependencyMode,
+    /// Download volume and phase timings
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<InstallSummary>,
 }
 
 /// Installed package information
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default, Serialize)]
 pub struct InstalledPackage {
     pub name: String,
     pub version: String,
+    /// site-packages directory the distribution lives in
     pub location: String,
     pub editable: bool,
+    /// Already installed in a suitable version and kept as is
+    pub already_satisfied: bool,
+    /// URL of a direct reference (`name @ url`)
     pub direct_url: Option<String>,
+    /// Where the installed artifact was downloaded from, credentials redacted
+    pub download_url: Option<String>,
+    /// Hex sha256 of that artifact
+    pub sha256: Option<String>,
+    /// Bytes written to the environment, when known
+    pub size: Option<u64>,
 }
 
 /// Environment information
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct EnvironmentInfo {
     pub python_version: String,
     pub platform: String,
+    #[serde(rename = "implementation_name")]
     pub implementation: String,
+    #[serde(rename = "platform_machine")]
     pub architecture: String,
     pub os_name: String,
     pub sys_platform: String,
@@ -38,10 +92,11 @@ pub struct EnvironmentInfo {
 impl EnvironmentInfo {
     /// Create default environment info
     pub fn default() -> Self {
+        let python = crate::utils::python_env::detected();
         Self {
-            python_version: format!("{}.{}", 3, 11),
+            python_version: python.version(),
             platform: Self::get_platform(),
-            implementation: "cpython".to_string(),
+            implementation: python.implementation_name.clone(),
             architecture: Self::get_architecture(),
             os_name: Self::get_os_name(),
             sys_platform: Self::get_platform(),
@@ -79,27 +134,22 @@ impl EnvironmentInfo {
             "unknown".to_string()
         }
     }
-
-    /// Convert to map for serialization
-    pub fn to_map(&self) -> HashMap<String, String> {
-        let mut map = HashMap::new();
-        map.insert("python_version".to_string(), self.python_version.clone());
-        map.insert("platform".to_string(), self.platform.clone());
-        map.insert("implementation_name".to_string(), self.implementation.clone());
-        map.insert("platform_machine".to_string(), self.architecture.clone());
-        map.insert("os_name".to_string(), self.os_name.clone());
-        map.insert("sys_platform".to_string(), self.sys_platform.clone());
-        map
-    }
 }
 
 /// Environment override for cross-platform builds
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct EnvironmentOverride {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub python_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub platform: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub implementation: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub architecture: Option<String>,
+    /// ABI wheels are picked for (`--abi`); doesn't affect markers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub abi: Option<String>,
 }
 
 impl EnvironmentOverride {
@@ -109,6 +159,7 @@ impl EnvironmentOverride {
             platform: None,
             implementation: None,
             architecture: None,
+            abi: None,
         }
     }
 
@@ -135,6 +186,7 @@ impl EnvironmentOverride {
             || self.platform.is_some()
             || self.implementation.is_some()
             || self.architecture.is_some()
+            || self.abi.is_some()
     }
 
     /// Get override description
@@ -153,6 +205,9 @@ impl EnvironmentOverride {
         if let Some(arch) = &self.architecture {
             parts.push(format!("architecture={}", arch));
         }
+        if let Some(abi) = &self.abi {
+            parts.push(format!("abi={}", abi));
+        }
 
         if parts.is_empty() {
             "no overrides".to_string()
@@ -171,15 +226,29 @@ impl Default for EnvironmentOverride {
 impl InstallationReport {
     pub fn new() -> Self {
         Self {
+            version: REPORT_VERSION,
+            timestamp: chrono::Local::now().to_rfc3339(),
+            dry_run: false,
             installed_packages: vec![],
+            total_size: 0,
             environment: EnvironmentInfo::default(),
             environment_override: None,
-            timestamp: chrono::Local::now().to_rfc3339(),
+            failed: vec![],
+            skipped: vec![],
+            rolled_back: vec![],
+            skipped_by_markers: vec![],
+            fallbacks: vec![],
+            deprecated: vec![],
+            dependency_mode: DependencyMode::default(),
+            summary: None,
         }
     }
 
     /// Add installed package
     pub fn add_package(&mut self, package: InstalledPackage) {
+        if !package.already_satisfied {
+            self.total_size += package.size.unwrap_or(0);
+        }
         self.installed_packages.push(package);
     }
 
@@ -191,58 +260,23 @@ impl InstallationReport {
         }
     }
 
+    /// Take the failures, skips, notices and timings of a batch install;
+    /// its installed packages are added separately, with their locations
+    pub fn set_outcome(&mut self, batch: &BatchReport) {
+        self.failed = batch.failed.clone();
+        self.skipped = batch.skipped.clone();
+        self.rolled_back = batch.rolled_back.clone();
+        self.skipped_by_markers = batch.skipped_by_markers.clone();
+        self.fallbacks = batch.fallbacks.clone();
+        self.deprecated = batch.deprecated.clone();
+        self.dependency_mode = batch.dependency_mode;
+        self.summary = batch.summary.clone();
+    }
+
     /// Get effective environment (with overrides applied)
     pub fn effective_environment(&self) -> EnvironmentInfo {
         self.environment.clone()
     }
-
-    /// Convert to JSON-serializable map
-    pub fn to_map(&self) -> HashMap<String, serde_json::Value> {
-        let mut map = HashMap::new();
-
-        // Installed packages
-        let packages: Vec<_> = self
-            .installed_packages
-            .iter()
-            .map(|p| {
-                serde_json::json!({
-                    "name": p.name,
-                    "version": p.version,
-                    "location": p.location,
-                    "editable": p.editable,
-                    "direct_url": p.direct_url,
-                })
-            })
-            .collect();
-        map.insert(
-            "installed".to_string(),
-            serde_json::Value::Array(packages),
-        );
-
-        // Environment
-        let env_map = self.environment.to_map();
-        let env_json: HashMap<String, serde_json::Value> = env_map
-            .into_iter()
-            .map(|(k, v)| (k, serde_json::Value::String(v)))
-            .collect();
-        map.insert("environment".to_string(), serde_json::to_value(env_json).unwrap());
-
-        // Environment override
-        if let Some(override_) = &self.environment_override {
-            map.insert(
-                "environment_override".to_string(),
-                serde_json::Value::String(override_.describe()),
-            );
-        }
-
-        // Timestamp
-        map.insert(
-            "timestamp".to_string(),
-            serde_json::Value::String(self.timestamp.clone()),
-        );
-
-        map
-    }
 }
 
 impl Default for InstallationReport {
@@ -263,15 +297,6 @@ mod tests {
         assert_eq!(env.implementation, "cpython");
     }
 
-    #[test]
-    fn test_environment_info_to_map() {
-        let env = EnvironmentInfo::default();
-        let map = env.to_map();
-        assert!(map.contains_key("python_version"));
-        assert!(map.contains_key("platform"));
-        assert!(map.contains_key("implementation_name"));
-    }
-
     #[test]
     fn test_environment_override_new() {
         let override_ = EnvironmentOverride::new();
@@ -325,8 +350,8 @@ mod tests {
             name: "requests".to_string(),
             version: "2.28.0".to_string(),
             location: "/usr/lib/python3.11/site-packages".to_string(),
-            editable: false,
-            direct_url: None,
+            size: Some(1024),
+            ..Default::default()
         };
 
         report.add_package(pkg);
@@ -345,21 +370,53 @@ mod tests {
     }
 
     #[test]
-    fn test_installation_report_to_map() {
+    fn test_installation_report_serializes_versioned_schema() {
         let mut report = InstallationReport::new();
-        let pkg = InstalledPackage {
+        report.add_package(InstalledPackage {
             name: "requests".to_string(),
             version: "2.28.0".to_string(),
-            location: "/usr/lib/python3.11/site-packages".to_string(),
-            editable: false,
-            direct_url: None,
-        };
-
-        report.add_package(pkg);
-        let map = report.to_map();
+            location: "/venv/lib/python3.11/site-packages".to_string(),
+            download_url: Some("https://files.example/requests-2.28.0-py3-none-any.whl".to_string()),
+            sha256: Some("ab12".to_string()),
+            size: Some(1024),
+            ..Default::default()
+        });
+        report.add_package(InstalledPackage {
+            name: "idna".to_string(),
+            version: "3.7".to_string(),
+            already_satisfied: true,
+            size: Some(4096),
+            ..Default::default()
+        });
+        let mut override_ = EnvironmentOverride::new();
+        override_.platform = Some("win32".to_string());
+        report.set_override(override_);
 
-        assert!(map.contains_key("installed"));
-        assert!(map.contains_key("environment"));
-        assert!(map.contains_key("timestamp"));
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["version"], REPORT_VERSION);
+        assert!(json["timestamp"].is_string());
+        assert_eq!(json["total_size"], 1024);
+        assert_eq!(
+            json["installed"][0],
+            serde_json::json!({
+                "name": "requests",
+                "version": "2.28.0",
+                "location": "/venv/lib/python3.11/site-packages",
+                "editable": false,
+                "already_satisfied": false,
+                "direct_url": null,
+                "download_url": "https://files.example/requests-2.28.0-py3-none-any.whl",
+                "sha256": "ab12",
+                "size": 1024,
+            })
+        );
+        assert_eq!(json["installed"][1]["already_satisfied"], true);
+        assert_eq!(json["environment"]["sys_platform"], "win32");
+        assert!(json["environment"]["implementation_name"].is_string());
+        assert_eq!(json["environment_override"], serde_json::json!({"platform": "win32"}));
+        // Absent unless they apply
+        for key in ["dry_run", "failed", "skipped", "rolled_back", "dependency_mode", "summary"] {
+            assert!(json.get(key).is_none(), "{}", key);
+        }
     }
 }
@@ -1,3 +1,7 @@
+use crate::utils::names::canonicalize_name;
+use crate::utils::version;
+use serde::Serialize;
+
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Marker {
@@ -24,7 +28,7 @@ pub struct MarkerCondition {
 }
 
 /// Environment variables for marker evaluation
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Environment {
     pub python_version: String,
     pub python_full_version: String,
@@ -40,11 +44,11 @@ pub struct Environment {
 }
 
 impl Environment {
-    /// Create environment from current system
+    /// Create environment from current system and the active environment's
+    /// Python
     pub fn current() -> Self {
-        let python_version = format!("{}.{}", 3, 11); // Default to 3.11
-        let python_full_version = format!("{}.0", python_version);
-        
+        let python = crate::utils::python_env::detected();
+
         #[cfg(target_os = "macos")]
         let os_name = "posix";
         #[cfg(target_os = "linux")]
@@ -63,6 +67,13 @@ impl Environment {
         #[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
         let sys_platform = "unknown";
 
+        let platform_system = match sys_platform {
+            "darwin" => "Darwin",
+            "linux" => "Linux",
+            "win32" => "Windows",
+            _ => "unknown",
+        };
+
         #[cfg(target_arch = "x86_64")]
         let platform_machine = "x86_64";
         #[cfg(target_arch = "aarch64")]
@@ -71,19 +82,78 @@ impl Environment {
         let platform_machine = "unknown";
 
         Self {
-            python_version: python_version.clone(),
-            python_full_version,
+            python_version: python.version(),
+            python_full_version: python.full_version.clone(),
             os_name: os_name.to_string(),
             sys_platform: sys_platform.to_string(),
-            platform_release: "unknown".to_string(),
-            platform_system: "Darwin".to_string(),
-            platform_version: "unknown".to_string(),
+            platform_release: python.platform_release.clone(),
+            platform_system: platform_system.to_string(),
+            platform_version: python.platform_version.clone(),
             platform_machine: platform_machine.to_string(),
-            platform_python_implementation: "CPython".to_string(),
-            implementation_name: "cpython".to_string(),
-            implementation_version: python_version,
+            platform_python_implementation: python.platform_python_implementation(),
+            implementation_name: python.implementation_name.clone(),
+            implementation_version: python.implementation_version.clone(),
         }
     }
+
+    /// Value of a marker variable; `None` for names PEP 508 doesn't define
+    pub fn value(&self, variable: &str) -> Option<&str> {
+        Some(match variable {
+            "python_version" => &self.python_version,
+            "python_full_version" => &self.python_full_version,
+            "os_name" => &self.os_name,
+            "sys_platform" => &self.sys_platform,
+            "platform_release" => &self.platform_release,
+            "platform_system" => &self.platform_system,
+            "platform_version" => &self.platform_version,
+            "platform_machine" => &self.platform_machine,
+            "platform_python_implementation" => &self.platform_python_implementation,
+            "implementation_name" => &self.implementation_name,
+            "implementation_version" => &self.implementation_version,
+            _ => return None,
+        })
+    }
+
+    /// Interpreter version Requires-Python is checked against: the full
+    /// version, unless only `python_version` was overridden
+    pub fn target_python(&self) -> &str {
+        let matches = self
+            .python_full_version
+            .strip_prefix(self.python_version.as_str())
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'));
+        if matches { &self.python_full_version } else { &self.python_version }
+    }
+
+    /// Override one marker variable (`sys_platform`, `python_version`, ...)
+    pub fn set(&mut self, variable: &str, value: &str) -> Result<(), String> {
+        let slot = match variable {
+            "python_version" => &mut self.python_version,
+            "python_full_version" => &mut self.python_full_version,
+            "os_name" => &mut self.os_name,
+            "sys_platform" => &mut self.sys_platform,
+            "platform_release" => &mut self.platform_release,
+            "platform_system" => &mut self.platform_system,
+            "platform_version" => &mut self.platform_version,
+            "platform_machine" => &mut self.platform_machine,
+            "platform_python_implementation" => &mut self.platform_python_implementation,
+            "implementation_name" => &mut self.implementation_name,
+            "implementation_version" => &mut self.implementation_version,
+            _ => return Err(format!("unknown marker variable '{}'", variable)),
+        };
+        *slot = value.to_string();
+        Ok(())
+    }
+}
+
+/// Truth value of one comparison inside a marker
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClauseResult {
+    /// The comparison as written, e.g. `sys_platform == "win32"`
+    pub clause: String,
+    pub variable: String,
+    /// What the variable holds in the evaluated environment
+    pub value: String,
+    pub result: bool,
 }
 
 impl Marker {
@@ -100,28 +170,87 @@ impl Marker {
 
     /// Evaluate marker against environment
     pub fn evaluate(&self, env: &Environment) -> bool {
-        self.evaluate_expression(&self.expression, env)
+        self.evaluate_expression(&self.expression, env, None)
+    }
+
+    /// Evaluate marker against environment with `extra` requested, so
+    /// `extra == "socks"` holds for the `socks` extra. Extra names compare
+    /// normalized (PEP 685).
+    pub fn evaluate_with_extra(&self, env: &Environment, extra: &str) -> bool {
+        self.evaluate_expression(&self.expression, env, Some(extra))
+    }
+
+    /// Each comparison of the marker with its truth value in `env`, in
+    /// the order they're written
+    pub fn clauses(&self, env: &Environment) -> Vec<ClauseResult> {
+        let mut leaves = Vec::new();
+        Self::collect_leaves(&self.expression, &mut leaves);
+        leaves
+            .into_iter()
+            .map(|clause| {
+                let variable = Self::clause_variable(clause);
+                ClauseResult {
+                    clause: clause.to_string(),
+                    value: self.get_variable_value(&variable, env, None),
+                    result: self.evaluate_condition(clause, env, None),
+                    variable,
+                }
+            })
+            .collect()
+    }
+
+    /// Marker variables the expression reads, without duplicates
+    pub fn variables(&self) -> Vec<String> {
+        let mut leaves = Vec::new();
+        Self::collect_leaves(&self.expression, &mut leaves);
+        let mut variables: Vec<String> = Vec::new();
+        for variable in leaves.into_iter().map(Self::clause_variable) {
+            if !variables.contains(&variable) {
+                variables.push(variable);
+            }
+        }
+        variables
     }
 
-    fn evaluate_expression(&self, expr: &str, env: &Environment) -> bool {
+    /// Split like `evaluate_expression` does, keeping single comparisons
+    fn collect_leaves<'a>(expr: &'a str, leaves: &mut Vec<&'a str>) {
+        if let Some((left, right)) = expr.split_once(" or ").or_else(|| expr.split_once(" and ")) {
+            Self::collect_leaves(left, leaves);
+            Self::collect_leaves(right, leaves);
+            return;
+        }
+        let leaf = expr.trim().trim_start_matches('(').trim_end_matches(')').trim();
+        if !leaf.is_empty() {
+            leaves.push(leaf);
+        }
+    }
+
+    fn clause_variable(clause: &str) -> String {
+        let end = clause
+            .find(|c: char| c.is_whitespace() || matches!(c, '=' | '!' | '<' | '>' | '~'))
+            .unwrap_or(clause.len());
+        clause[..end].trim_matches('\'').trim_matches('"').to_string()
+    }
+
+    fn evaluate_expression(&self, expr: &str, env: &Environment, extra: Option<&str>) -> bool {
         // Handle 'and' and 'or' operators
         if let Some(idx) = expr.find(" or ") {
             let left = &expr[..idx];
             let right = &expr[idx + 4..];
-            return self.evaluate_expression(left, env) || self.evaluate_expression(right, env);
+            return self.evaluate_expression(left, env, extra) || self.evaluate_expression(right, env, extra);
         }
 
         if let Some(idx) = expr.find(" and ") {
             let left = &expr[..idx];
             let right = &expr[idx + 5..];
-            return self.evaluate_expression(left, env) && self.evaluate_expression(right, env);
+            return self.evaluate_expression(left, env, extra) && self.evaluate_expression(right, env, extra);
         }
 
         // Evaluate single condition
-        self.evaluate_condition(expr.trim(), env)
+        self.evaluate_condition(expr.trim(), env, extra)
     }
 
-    fn evaluate_condition(&self, cond: &str, env: &Environment) -> bool {
+    fn evaluate_condition(&self, cond: &str, env: &Environment, extra: Option<&str>) -> bool {
         let cond = cond.trim();
 
         // Remove parentheses
@@ -160,57 +289,29 @@ impl Marker {
         let variable = parts[0].trim().trim_matches('\'').trim_matches('"');
         let value = parts[1].trim().trim_matches('\'').trim_matches('"');
 
-        let var_value = self.get_variable_value(variable, env);
+        let var_value = self.get_variable_value(variable, env, extra);
+        if variable == "extra" && matches!(op, MarkerOp::Eq | MarkerOp::NotEq) {
+            let equal = extra.is_some() && canonicalize_name(&var_value) == canonicalize_name(value);
+            return equal == (op == MarkerOp::Eq);
+        }
 
         match op {
             MarkerOp::Eq => var_value == value,
             MarkerOp::NotEq => var_value != value,
-            MarkerOp::Lt => self.compare_versions(&var_value, value) < 0,
-            MarkerOp::LtEq => self.compare_versions(&var_value, value) <= 0,
-            MarkerOp::Gt => self.compare_versions(&var_value, value) > 0,
-            MarkerOp::GtEq => self.compare_versions(&var_value, value) >= 0,
+            MarkerOp::Lt => version::compare(&var_value, value).is_lt(),
+            MarkerOp::LtEq => version::compare(&var_value, value).is_le(),
+            MarkerOp::Gt => version::compare(&var_value, value).is_gt(),
+            MarkerOp::GtEq => version::compare(&var_value, value).is_ge(),
             MarkerOp::In => value.contains(&var_value),
             MarkerOp::NotIn => !value.contains(&var_value),
         }
     }
 
-    fn get_variable_value(&self, var: &str, env: &Environment) -> String {
-        match var {
-            "python_version" => env.python_version.clone(),
-            "python_full_version" => env.python_full_version.clone(),
-            "os_name" => env.os_name.clone(),
-            "sys_platform" => env.sys_platform.clone(),
-            "platform_release" => env.platform_release.clone(),
-            "platform_system" => env.platform_system.clone(),
-            "platform_version" => env.platform_version.clone(),
-            "platform_machine" => env.platform_machine.clone(),
-            "platform_python_implementation" => env.platform_python_implementation.clone(),
-            "implementation_name" => env.implementation_name.clone(),
-            "implementation_version" => env.implementation_version.clone(),
-            _ => String::new(),
-        }
-    }
-
-    fn compare_versions(&self, v1: &str, v2: &str) -> i32 {
-        let v1_parts: Vec<u32> = v1
-            .split('.')
-            .filter_map(|s| s.parse().ok())
-            .collect();
-        let v2_parts: Vec<u32> = v2
-            .split('.')
-            .filter_map(|s| s.parse().ok())
-            .collect();
-
-        for i in 0..v1_parts.len().max(v2_parts.len()) {
-            let p1 = v1_parts.get(i).copied().unwrap_or(0);
-            let p2 = v2_parts.get(i).copied().unwrap_or(0);
-            if p1 < p2 {
-                return -1;
-            } else if p1 > p2 {
-                return 1;
-            }
+    fn get_variable_value(&self, var: &str, env: &Environment, extra: Option<&str>) -> String {
+        match (var, extra) {
+            ("extra", Some(extra)) => extra.to_string(),
+            _ => env.value(var).unwrap_or_default().to_string(),
         }
-        0
     }
 }
 
@@ -247,6 +348,39 @@ mod tests {
         assert!(marker.evaluate(&env));
     }
 
+    #[test]
+    fn test_clauses_report_each_comparison() {
+        let marker = Marker::parse("sys_platform == \"win32\" and python_version >= \"3.8\"").unwrap();
+        let mut env = Environment::current();
+        env.set("sys_platform", "linux").unwrap();
+        env.set("python_version", "3.11").unwrap();
+        assert!(!marker.evaluate(&env));
+
+        let clauses = marker.clauses(&env);
+        let summary: Vec<(&str, &str, &str, bool)> = clauses
+            .iter()
+            .map(|c| (c.clause.as_str(), c.variable.as_str(), c.value.as_str(), c.result))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("sys_platform == \"win32\"", "sys_platform", "linux", false),
+                ("python_version >= \"3.8\"", "python_version", "3.11", true),
+            ]
+        );
+        assert_eq!(marker.variables(), vec!["sys_platform", "python_version"]);
+        assert!(env.set("sys.platform", "x").is_err());
+    }
+
+    #[test]
+    fn test_evaluate_extra() {
+        let marker = Marker::parse("python_version >= '3.6' and extra == 'Use_Chardet'").unwrap();
+        let env = Environment::current();
+        assert!(!marker.evaluate(&env));
+        assert!(marker.evaluate_with_extra(&env, "use-chardet"));
+        assert!(!marker.evaluate_with_extra(&env, "socks"));
+    }
+
     #[test]
     fn test_evaluate_or_condition() {
         let marker = Marker::parse("sys_platform == 'win32' or sys_platform == 'darwin'").unwrap();
@@ -1,4 +1,6 @@
 /// Requirement specification and parsing
+use crate::utils::names::canonicalize_name;
+use std::fmt;
 use std::str::FromStr;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -7,6 +9,8 @@ pub struct Requirement {
     pub specs: Vec<VersionSpec>,
     pub extras: Vec<String>,
     pub marker: Option<String>,
+    /// PEP 508 direct reference (`name @ url`)
+    pub url: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -24,6 +28,8 @@ pub enum VersionOp {
     Gt,
     GtEq,
     Compatible,
+    /// `===`: string equality, for versions that aren't PEP 440
+    ArbitraryEq,
 }
 
 impl FromStr for Requirement {
@@ -47,7 +53,7 @@ impl FromStr for Requirement {
 
         for (i, &ch) in req_bytes.iter().enumerate() {
             let c = ch as char;
-            if c.is_alphanumeric() || c == '_' || c == '-' {
+            if c.is_alphanumeric() || c == '_' || c == '-' || c == '.' {
                 name.push(c);
                 pos = i + 1;
             } else {
@@ -70,19 +76,82 @@ impl FromStr for Requirement {
         }
 
         let version_part = remainder[spec_start..].trim();
-        if !version_part.is_empty() {
+        let mut url = None;
+        if let Some(reference) = version_part.strip_prefix('@') {
+            // Direct reference: the URL runs to the end of the requirement
+            let reference = reference.trim();
+            if reference.is_empty() {
+                return Err("Empty URL in direct reference".to_string());
+            }
+            url = Some(reference.to_string());
+        } else if !version_part.is_empty() {
             specs = parse_version_specs(version_part)?;
         }
 
         Ok(Requirement {
-            name: name.to_lowercase().replace('_', "-"),
+            name: canonicalize_name(&name),
             specs,
             extras,
             marker,
+            url,
         })
     }
 }
 
+impl fmt::Display for Requirement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if !self.extras.is_empty() {
+            write!(f, "[{}]", self.extras.join(","))?;
+        }
+        if let Some(url) = &self.url {
+            write!(f, " @ {}", url)?;
+        } else {
+            let specs: Vec<String> = self.specs.iter().map(|s| s.to_string()).collect();
+            write!(f, "{}", specs.join(","))?;
+        }
+        if let Some(marker) = &self.marker {
+            write!(f, "; {}", marker)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for VersionSpec {
+    type Err = String;
+
+    /// One clause such as `>=1.0` or `==2.*`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut specs = parse_version_specs(s)?;
+        match (specs.pop(), specs.is_empty()) {
+            (Some(spec), true) => Ok(spec),
+            _ => Err(format!("Expected a single version specifier: {}", s.trim())),
+        }
+    }
+}
+
+impl fmt::Display for VersionSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.op, self.version)
+    }
+}
+
+impl fmt::Display for VersionOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self {
+            VersionOp::Eq => "==",
+            VersionOp::NotEq => "!=",
+            VersionOp::Lt => "<",
+            VersionOp::LtEq => "<=",
+            VersionOp::Gt => ">",
+            VersionOp::GtEq => ">=",
+            VersionOp::Compatible => "~=",
+            VersionOp::ArbitraryEq => "===",
+        };
+        write!(f, "{}", op)
+    }
+}
+
 fn parse_version_specs(s: &str) -> Result<Vec<VersionSpec>, String> {
     let mut specs = Vec::new();
     let s = s.trim();
@@ -94,7 +163,9 @@ fn parse_version_specs(s: &str) -> Result<Vec<VersionSpec>, String> {
             break;
         }
 
-        let (op, skip) = if remaining.starts_with("==") {
+        let (op, skip) = if remaining.starts_with("===") {
+            (VersionOp::ArbitraryEq, 3)
+        } else if remaining.starts_with("==") {
             (VersionOp::Eq, 2)
         } else if remaining.starts_with("!=") {
             (VersionOp::NotEq, 2)
@@ -117,7 +188,7 @@ fn parse_version_specs(s: &str) -> Result<Vec<VersionSpec>, String> {
         let mut version_end = 0;
 
         for (i, ch) in rest.chars().enumerate() {
-            if ch.is_alphanumeric() || ch == '.' || ch == '*' || ch == '+' {
+            if ch.is_alphanumeric() || matches!(ch, '.' | '*' | '+' | '!') {
                 version.push(ch);
                 version_end = i + 1;
             } else if ch == ',' {
@@ -167,4 +238,26 @@ mod tests {
         assert_eq!(req.name, "requests");
         assert_eq!(req.extras, vec!["security"]);
     }
+
+    #[test]
+    fn test_parse_normalizes_dotted_name() {
+        let req: Requirement = "Zope.Interface[Test]>=5".parse().unwrap();
+        assert_eq!(req.name, "zope-interface");
+        assert_eq!(req.name, "zope_interface".parse::<Requirement>().unwrap().name);
+        assert_eq!(req.to_string(), "zope-interface[Test]>=5");
+    }
+
+    #[test]
+    fn test_parse_direct_reference() {
+        let req: Requirement = "urllib3 @ https://example.com/urllib3-1.26.18-py2.py3-none-any.whl"
+            .parse()
+            .unwrap();
+        assert_eq!(req.name, "urllib3");
+        assert!(req.specs.is_empty());
+        assert_eq!(
+            req.url.as_deref(),
+            Some("https://example.com/urllib3-1.26.18-py2.py3-none-any.whl")
+        );
+        assert_eq!(req.to_string(), "urllib3 @ https://example.com/urllib3-1.26.18-py2.py3-none-any.whl");
+    }
 }
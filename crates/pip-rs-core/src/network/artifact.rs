@@ -0,0 +1,232 @@
+/// Validation of downloaded artifacts before they are kept or extracted
+///
+/// Some proxies answer a wheel URL with `200 OK` and an HTML error page, or
+/// cut the body short. Left alone, that surfaces much later as a zip parsing
+/// error. Every finished download is checked here first: its length against
+/// Content-Length, its leading bytes against the archive format the filename
+/// promises, zip archives for an end-of-central-directory record, and the
+/// sha256 when the index published one. A failure is an [`InvalidArtifact`],
+/// which the client retries with caches bypassed.
+use crate::utils::archive_detector::ArchiveFormat;
+use crate::utils::hash;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// End of central directory record signature (PK\x05\x06)
+const ZIP_EOCD_SIGNATURE: &[u8] = b"PK\x05\x06";
+
+/// The EOCD record is 22 bytes plus a comment of up to 64 KiB
+const ZIP_EOCD_SEARCH: usize = 22 + u16::MAX as usize;
+
+/// Leading bytes of a file read to recognize its format
+const SNIFF_BYTES: u64 = 4096;
+
+/// Characters of an HTML body shown in the error
+const PREVIEW_CHARS: usize = 120;
+
+/// A download whose body can't be the file that was asked for
+#[derive(Debug)]
+pub struct InvalidArtifact {
+    pub url: String,
+    pub reason: String,
+    /// Start of the body, when it looks like an HTML page
+    pub preview: Option<String>,
+}
+
+impl InvalidArtifact {
+    fn new(url: &str, reason: String, data: &[u8]) -> Self {
+        Self {
+            url: url.to_string(),
+            reason,
+            preview: looks_like_html(data).then(|| preview(data)),
+        }
+    }
+
+    /// The body ended before Content-Length said it would
+    pub fn truncated(url: &str, received: u64, expected: u64) -> Self {
+        Self {
+            url: url.to_string(),
+            reason: format!("got {} of {} bytes", received, expected),
+            preview: None,
+        }
+    }
+}
+
+impl fmt::Display for InvalidArtifact {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "received an invalid/truncated file from {} — a proxy may be interfering ({})",
+            self.url, self.reason
+        )?;
+        if let Some(preview) = &self.preview {
+            write!(f, "\nFirst bytes: {}", preview)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for InvalidArtifact {}
+
+/// Last path segment of a download URL, without query or fragment
+pub fn filename(url: &str) -> Option<&str> {
+    url.split(['#', '?'])
+        .next()
+        .and_then(|u| u.rsplit('/').next())
+        .filter(|name| !name.is_empty())
+}
+
+/// Check the body's length against the Content-Length header, if any
+pub fn check_length(url: &str, data: &[u8], content_length: Option<u64>) -> Result<(), InvalidArtifact> {
+    match content_length {
+        Some(expected) if data.len() as u64 != expected => Err(InvalidArtifact {
+            preview: looks_like_html(data).then(|| preview(data)),
+            ..InvalidArtifact::truncated(url, data.len() as u64, expected)
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Check that a finished body is the archive its filename names and,
+/// when `sha256` is known, that it hashes to it
+pub fn check_contents(url: &str, data: &[u8], sha256: Option<&str>) -> Result<(), InvalidArtifact> {
+    let actual = sha256.map(|_| format!("{:x}", Sha256::digest(data)));
+    let tail = &data[data.len().saturating_sub(ZIP_EOCD_SEARCH)..];
+    check_sample(url, data, tail, sha256.zip(actual.as_deref()))
+}
+
+/// Like [`check_contents`], for a body already written to `path`; only its
+/// start and end are read into memory. Invalid contents are an
+/// [`InvalidArtifact`], anything else a failure to read the file.
+pub fn check_file(url: &str, path: &Path, sha256: Option<&str>) -> anyhow::Result<()> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    let mut head = Vec::new();
+    (&mut file).take(SNIFF_BYTES).read_to_end(&mut head)?;
+    let mut tail = Vec::new();
+    file.seek(SeekFrom::Start(len.saturating_sub(ZIP_EOCD_SEARCH as u64)))?;
+    file.read_to_end(&mut tail)?;
+
+    let actual = match sha256 {
+        Some(_) => Some(hash::digest_file(path, "sha256")?),
+        None => None,
+    };
+    check_sample(url, &head, &tail, sha256.zip(actual.as_deref()))?;
+    Ok(())
+}
+
+/// The checks of [`check_contents`] on the first bytes and the last
+/// [`ZIP_EOCD_SEARCH`] bytes of a body, with its (expected, actual) sha256
+fn check_sample(url: &str, head: &[u8], tail: &[u8], sha256: Option<(&str, &str)>) -> Result<(), InvalidArtifact> {
+    if let Some((expected, actual)) = sha256
+        && !actual.eq_ignore_ascii_case(expected)
+    {
+        return Err(InvalidArtifact::new(
+            url,
+            format!("Hash mismatch: expected sha256 {}, got {}", expected, actual),
+            head,
+        ));
+    }
+
+    let expected = filename(url)
+        .map(|name| ArchiveFormat::from_extension(Path::new(name)))
+        .unwrap_or(ArchiveFormat::Unknown);
+    if expected == ArchiveFormat::Unknown {
+        return Ok(());
+    }
+    let actual = ArchiveFormat::from_magic_bytes(head);
+    if actual != expected {
+        let got = if head.is_empty() {
+            "an empty body".to_string()
+        } else if looks_like_html(head) {
+            "an HTML page".to_string()
+        } else if actual == ArchiveFormat::Unknown {
+            "unrecognized data".to_string()
+        } else {
+            format!("{} data", actual.name())
+        };
+        return Err(InvalidArtifact::new(
+            url,
+            format!("expected a {} archive, got {}", expected.name(), got),
+            head,
+        ));
+    }
+
+    if expected == ArchiveFormat::Zip && !tail.windows(ZIP_EOCD_SIGNATURE.len()).any(|w| w == ZIP_EOCD_SIGNATURE) {
+        return Err(InvalidArtifact::new(
+            url,
+            "ZIP archive has no end of central directory; it was cut short".to_string(),
+            head,
+        ));
+    }
+    Ok(())
+}
+
+fn looks_like_html(data: &[u8]) -> bool {
+    let head = String::from_utf8_lossy(&data[..data.len().min(512)]).to_lowercase();
+    let head = head.trim_start_matches('\u{feff}').trim_start();
+    head.starts_with("<!doctype html") || head.starts_with("<html") || head.contains("<head") || head.contains("<body")
+}
+
+fn preview(data: &[u8]) -> String {
+    let text = String::from_utf8_lossy(&data[..data.len().min(PREVIEW_CHARS * 4)]);
+    let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    match collapsed.char_indices().nth(PREVIEW_CHARS) {
+        Some((end, _)) => format!("{}...", &collapsed[..end]),
+        None => collapsed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_contents() {
+        let url = "https://files.example/pkg-1.0-py3-none-any.whl#sha256=abc";
+        let html = b"\n<!DOCTYPE html>\n<html><body>Access   denied by policy</body></html>";
+        let error = check_contents(url, html, None).unwrap_err();
+        assert_eq!(error.reason, "expected a ZIP archive, got an HTML page");
+        assert_eq!(error.preview.as_deref(), Some("<!DOCTYPE html> <html><body>Access denied by policy</body></html>"));
+        assert!(error.to_string().starts_with(&format!(
+            "received an invalid/truncated file from {} — a proxy may be interfering",
+            url
+        )));
+
+        // Right magic, missing end of central directory
+        let error = check_contents(url, b"PK\x03\x04 truncated local header", None).unwrap_err();
+        assert!(error.reason.contains("cut short"));
+        assert!(error.preview.is_none());
+
+        let error = check_contents(url, b"PK\x05\x06", Some("00")).unwrap_err();
+        assert!(error.reason.starts_with("Hash mismatch: expected sha256 00"));
+
+        // Formats the filename doesn't name aren't checked
+        assert!(check_contents("https://example.org/import_names.json", b"{}", None).is_ok());
+        assert!(check_length(url, b"abc", Some(3)).is_ok());
+        assert_eq!(check_length(url, b"abc", Some(10)).unwrap_err().reason, "got 3 of 10 bytes");
+    }
+
+    #[test]
+    fn test_check_file_reads_head_and_tail() {
+        let url = "https://files.example/pkg-1.0-py3-none-any.whl";
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("pkg.whl");
+        let mut body = b"PK\x03\x04".to_vec();
+        body.resize(200_000, 0);
+        std::fs::write(&path, &body).unwrap();
+        let error = check_file(url, &path, None).unwrap_err();
+        assert!(error.downcast_ref::<InvalidArtifact>().unwrap().reason.contains("cut short"));
+
+        body.extend_from_slice(b"PK\x05\x06\0\0");
+        std::fs::write(&path, &body).unwrap();
+        let sha256 = format!("{:x}", Sha256::digest(&body));
+        assert!(check_file(url, &path, Some(&sha256)).is_ok());
+        assert!(check_file(url, &path, Some("00")).is_err());
+        let unreadable = check_file(url, &dir.path().join("missing.whl"), None).unwrap_err();
+        assert!(unreadable.downcast_ref::<InvalidArtifact>().is_none());
+    }
+}
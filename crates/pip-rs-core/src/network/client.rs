@@ -1,244 +1,680 @@
 /// HTTP client for package operations with retry logic and disk caching
-use anyhow::{Result, anyhow};
-use reqwest::Client;
+use reqwest::{Client, StatusCode, header};
 use std::time::Duration;
-use std::path::PathBuf;
-use crate::cache::DiskCache;
+use crate::cache::disk_cache::EntryMeta;
+use crate::cache::{DiskCache, WheelCache, layout};
+use super::artifact::{self, InvalidArtifact};
+use super::resume::{self, Progress};
+use super::auth::{self, AuthRequired, Authenticator, Credentials};
+use super::error::{NetworkError, NetworkErrorKind};
+use super::throttle::{self, Backoff, RateLimiter};
+use super::trusted::{self, TrustedHosts};
+use super::find_links;
+use super::proxy::ProxyConfig;
+use once_cell::sync::{Lazy, OnceCell};
+use crate::utils::redact::redact_url_credentials;
+use std::sync::Arc;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
 
-const MAX_RETRIES: u32 = 2;
-const RETRY_DELAY_MS: u64 = 100; // Reduced delay for faster retries
-const CACHE_TTL_SECS: u64 = 86400; // 24 hour cache TTL for better performance
+/// Limit on a whole request, unless configured (`timeout`)
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+/// Limit on establishing a connection; never more than the request's
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Further attempts after a failed request, unless configured (`retries`)
+pub const DEFAULT_RETRIES: u32 = 2;
+/// First delay between attempts, unless configured (`retry-delay`)
+pub const DEFAULT_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// What clients built without a configuration at hand use: the one given
+/// to [`init_global_client`](super::init_global_client) (`--timeout`,
+/// `--retries`, ...), read on first use
+static SETTINGS: Lazy<NetworkSettings> = Lazy::new(|| NetworkSettings::from_config(super::client_config()));
+
+/// How long a cached response without validators (ETag/Last-Modified) or
+/// `max-age` is used before it is fetched again
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// How long requests may take, how often failed ones are tried again and
+/// which proxies they go through
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetworkSettings {
+    pub timeout: Duration,
+    pub retries: u32,
+    pub retry_delay: Duration,
+    pub proxy: ProxyConfig,
+    /// Never send a request: metadata comes from the HTTP cache however
+    /// old, wheels from the wheel cache or `--find-links` directories
+    pub offline: bool,
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        Self {
+            timeout: DEFAULT_TIMEOUT,
+            retries: DEFAULT_RETRIES,
+            retry_delay: DEFAULT_RETRY_DELAY,
+            proxy: ProxyConfig::default(),
+            offline: false,
+        }
+    }
+}
+
+impl NetworkSettings {
+    pub fn from_config(config: &crate::config::config::Config) -> Self {
+        Self {
+            timeout: Duration::from_secs(config.timeout()),
+            retries: config.retries(),
+            retry_delay: config.retry_delay(),
+            proxy: ProxyConfig::new(config.proxy()),
+            offline: config.offline(),
+        }
+    }
+
+    fn backoff(&self) -> Backoff {
+        Backoff::new(self.retries, self.retry_delay)
+    }
+}
+
+/// Whether the configuration puts this process in offline mode; see
+/// [`NetworkSettings::offline`]
+pub fn is_offline() -> bool {
+    SETTINGS.offline
+}
+
+/// How a metadata lookup may use the disk cache
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CachePolicy {
+    /// Serve fresh entries as-is; revalidate stale ones
+    Default,
+    /// Always ask the server, with a conditional request when the entry
+    /// has validators (e.g. for outdated checks right after a release)
+    Revalidate,
+}
 
 pub struct PackageClient {
     client: Client,
+    /// Skips certificate verification; built on first use for a trusted host
+    insecure_client: OnceCell<Client>,
     base_url: String,
     cache: Option<DiskCache>,
-    trusted_hosts: Vec<String>,
+    cache_ttl: Duration,
+    downloads_dir: Option<PathBuf>,
+    wheel_cache: Option<WheelCache>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    auth: Option<Arc<Authenticator>>,
+    trusted_hosts: Arc<TrustedHosts>,
+    settings: NetworkSettings,
 }
 
 impl PackageClient {
+    /// A client trusting the process-wide trusted hosts (see
+    /// [`configure_indexes`](super::configure_indexes))
     pub fn new() -> Self {
-        Self::with_trusted_hosts(Vec::new())
+        Self::with_trusted(trusted::shared())
     }
 
+    /// A client that skips certificate verification for `trusted_hosts`
+    /// only
     pub fn with_trusted_hosts(trusted_hosts: Vec<String>) -> Self {
-        // For trusted hosts, we need to disable certificate verification
-        // Note: This is a security consideration - trusted hosts bypass SSL verification
-        let client_builder = Client::builder()
-            .timeout(Duration::from_secs(30))  // Reduced from 180s for faster failure
-            .connect_timeout(Duration::from_secs(10))  // Reduced from 30s
-            .pool_max_idle_per_host(20)  // Increased connection pool for better reuse
-            .user_agent(format!("pip-rs/{}", env!("CARGO_PKG_VERSION")));  // Add user agent to help with rate limiting
-        
-        // If we have trusted hosts, we may need to disable cert verification
-        // However, reqwest doesn't support per-host cert verification easily
-        // So we'll store trusted hosts and handle them in request logic
-        // For now, we'll use a client that accepts invalid certs if trusted hosts are specified
-        // In production, you'd want a more sophisticated approach
-        
-        let client = client_builder
-            .build()
-            .unwrap_or_else(|_| Client::new());
+        Self::with_trusted(Arc::new(TrustedHosts::new(trusted_hosts)))
+    }
+
+    fn with_trusted(trusted_hosts: Arc<TrustedHosts>) -> Self {
+        let settings = SETTINGS.clone();
+        let client = Self::builder(&settings).build().unwrap_or_else(|_| Client::new());
         
         // Initialize disk cache
         let cache = Self::init_cache();
         
         Self {
             client,
+            insecure_client: OnceCell::new(),
             base_url: "https://pypi.org/pypi".to_string(),
             cache,
+            cache_ttl: DEFAULT_CACHE_TTL,
+            downloads_dir: layout::cache_root().map(|root| root.join(layout::DOWNLOADS_DIR)),
+            wheel_cache: WheelCache::open(),
+            rate_limiter: Some(throttle::shared()),
+            auth: Some(auth::shared()),
             trusted_hosts,
+            settings,
         }
     }
 
-    /// Initialize disk cache in user's cache directory
-    fn init_cache() -> Option<DiskCache> {
-        if let Ok(cache_dir) = std::env::var("PIP_CACHE_DIR") {
-            let path = PathBuf::from(cache_dir);
-            if let Ok(cache) = DiskCache::new(&path, Duration::from_secs(CACHE_TTL_SECS)) {
-                return Some(cache);
-            }
-        }
-        
-        // Try default cache location
-        if let Some(cache_home) = dirs::cache_dir().map(|d| d.join("pip-rs")) {
-            if let Ok(cache) = DiskCache::new(&cache_home, Duration::from_secs(CACHE_TTL_SECS)) {
-                return Some(cache);
-            }
+    /// Settings shared by the verifying and the trusted-host client
+    fn builder(settings: &NetworkSettings) -> reqwest::ClientBuilder {
+        settings
+            .proxy
+            .apply(Client::builder())
+            .timeout(settings.timeout)
+            .connect_timeout(settings.timeout.min(CONNECT_TIMEOUT))
+            .pool_max_idle_per_host(20)  // Increased connection pool for better reuse
+            .user_agent(format!("pip-rs/{}", env!("CARGO_PKG_VERSION")))  // Add user agent to help with rate limiting
+    }
+
+    /// The client to reach `url` with: one that skips certificate
+    /// verification for trusted hosts, the verifying one otherwise
+    fn client_for(&self, url: &str) -> &Client {
+        if !url.starts_with("https://") || !self.is_trusted_host(url) {
+            return &self.client;
         }
-        
-        None
+        trusted::warn_insecure(url);
+        self.insecure_client
+            .get_or_init(|| trusted::insecure_client(Self::builder(&self.settings), self.trusted_hosts.clone()))
+    }
+
+    /// Initialize disk cache under the (versioned) cache root
+    fn init_cache() -> Option<DiskCache> {
+        let cache_root = layout::cache_root()?;
+        DiskCache::new(&cache_root.join(layout::HTTP_DIR), DEFAULT_CACHE_TTL).ok()
     }
 
-    #[allow(dead_code)]
     pub fn with_base_url(mut self, url: String) -> Self {
         self.base_url = url;
         self
     }
 
+    /// Lifetime of cached responses the server gave no validators for
+    pub fn with_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.cache_ttl = ttl;
+        self
+    }
+
+    /// Timeouts and retries other than the configured ones
+    pub fn with_settings(mut self, settings: NetworkSettings) -> Self {
+        if let Ok(client) = Self::builder(&settings).build() {
+            self.client = client;
+        }
+        self.insecure_client = OnceCell::new();
+        self.settings = settings;
+        self
+    }
+
+    /// A failure to reach `url`, saying whether it went through a proxy
+    fn network_error(&self, url: &str, error: &reqwest::Error) -> NetworkError {
+        let message = format!("Network error: {} ({})", error, self.settings.proxy.describe(url));
+        NetworkError::new(NetworkError::kind_of(error), url, message)
+    }
+
+    /// The error when every attempt failed without saying why
+    fn gave_up(&self, url: &str) -> NetworkError {
+        let url = redact_url_credentials(url);
+        let message = format!("Failed to fetch {} after {} retries", url, self.settings.retries);
+        NetworkError::new(NetworkErrorKind::Unreachable, &url, message)
+    }
+
+    /// Wait for this request's share of the process-wide rate limit
+    async fn throttle(&self) {
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.acquire().await;
+        }
+    }
+
+    /// `url` without embedded credentials, and the credentials to send
+    /// with it: the embedded ones, or those of a host that asked before
+    fn authorize(&self, url: &str) -> (String, Option<Credentials>) {
+        let (url, embedded) = auth::split_credentials(url);
+        let credentials = embedded.or_else(|| self.auth.as_ref()?.preemptive(&url));
+        (url, credentials)
+    }
+
+    /// A GET for `url` with its credentials applied, and whether it has any
+    fn get(&self, url: &str) -> (reqwest::RequestBuilder, bool) {
+        let (url, credentials) = self.authorize(url);
+        let request = self.client_for(&url).get(&url);
+        match credentials {
+            Some(credentials) => (credentials.apply(request), true),
+            None => (request, false),
+        }
+    }
+
+    /// After a 401 from `url`: Ok to send it again with the credentials
+    /// found for its host, or why there are none
+    fn challenge(&self, url: &str, sent: bool) -> Result<(), NetworkError> {
+        let refused = match &self.auth {
+            Some(auth) => auth.challenge(url, sent),
+            None => Err(AuthRequired::new(url, sent)),
+        };
+        refused.map_err(|e| NetworkError::new(NetworkErrorKind::AuthRequired, url, e))
+    }
+
     /// Check if a host is trusted
     pub fn is_trusted_host(&self, url: &str) -> bool {
-        if let Ok(parsed) = url::Url::parse(url) {
-            if let Some(host) = parsed.host_str() {
-                for trusted in &self.trusted_hosts {
-                    if host == trusted || host.ends_with(&format!(".{}", trusted)) {
-                        return true;
-                    }
-                }
-            }
-        }
-        false
+        self.trusted_hosts.contains(url)
+    }
+
+    /// Legacy JSON API URL for a package; also its disk cache key
+    pub fn package_json_url(&self, package_name: &str) -> String {
+        format!("{}/{}/json", self.base_url, package_name)
+    }
+
+    /// Legacy JSON API URL for one release of a package
+    pub fn release_json_url(&self, package_name: &str, version: &str) -> String {
+        format!("{}/{}/{}/json", self.base_url, package_name, version)
+    }
+
+    /// PEP 691 simple API project URL on the same index
+    pub fn simple_project_url(&self, package_name: &str) -> String {
+        let root = self.base_url.trim_end_matches('/').trim_end_matches("/pypi");
+        format!("{}/simple/{}/", root, package_name)
+    }
+
+    /// Disk cache shared with metadata lookups, if the cache is available
+    pub fn http_cache(&self) -> Option<&DiskCache> {
+        self.cache.as_ref()
     }
 
     /// Get package info with retry logic
-    pub async fn get_package_info(&self, package_name: &str) -> Result<serde_json::Value> {
-        let url = format!("{}/{}/json", self.base_url, package_name);
-        self.get_with_retry(&url).await
+    pub async fn get_package_info(&self, package_name: &str) -> Result<serde_json::Value, NetworkError> {
+        self.get_package_info_at(&self.base_url, package_name, CachePolicy::Default).await
     }
-    
-    /// Get package info bypassing cache (for fresh version checks)
-    pub async fn get_package_info_fresh(&self, package_name: &str) -> Result<serde_json::Value> {
-        let url = format!("{}/{}/json", self.base_url, package_name);
-        // Bypass cache by adding timestamp query parameter
-        let url_with_cache_bust = format!("{}?_t={}", url, std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs());
-        self.get_with_retry(&url_with_cache_bust).await
+
+    /// Get package info from the JSON API at `api_base` rather than this
+    /// client's own index
+    pub async fn get_package_info_at(
+        &self,
+        api_base: &str,
+        package_name: &str,
+        policy: CachePolicy,
+    ) -> Result<serde_json::Value, NetworkError> {
+        let url = format!("{}/{}/json", api_base, package_name);
+        self.get_with_retry(&url, policy).await
     }
 
-    /// Download package with retry logic and progress
-    pub async fn download_package(&self, url: &str) -> Result<bytes::Bytes> {
-        self.download_with_retry(url).await
+    /// Get the info of one release (its own requires_dist and files)
+    pub async fn get_release_info(&self, package_name: &str, version: &str) -> Result<serde_json::Value, NetworkError> {
+        self.get_release_info_at(&self.base_url, package_name, version).await
     }
 
-    /// Get with exponential backoff retry and disk caching
-    async fn get_with_retry(&self, url: &str) -> Result<serde_json::Value> {
-        // Check if this is a cache-busting request (has _t= parameter)
-        let bypass_cache = url.contains("_t=");
-        
-        // Try cache first (unless bypassing)
-        if !bypass_cache {
-            if let Some(cache) = &self.cache {
-                if let Ok(Some(cached_data)) = cache.get(url) {
-                    if let Ok(json) = serde_json::from_slice(&cached_data) {
-                        tracing::debug!("Cache hit for {}", url);
-                        return Ok(json);
-                    }
+    /// Get the info of one release from the JSON API at `api_base`
+    pub async fn get_release_info_at(
+        &self,
+        api_base: &str,
+        package_name: &str,
+        version: &str,
+    ) -> Result<serde_json::Value, NetworkError> {
+        let url = format!("{}/{}/{}/json", api_base, package_name, version);
+        self.get_with_retry(&url, CachePolicy::Default).await
+    }
+
+    /// Raw body of `url`, revalidated upstream and never read from the
+    /// disk cache, with retries on server and network errors
+    pub async fn fetch_fresh(&self, url: &str, accept: &str) -> Result<bytes::Bytes, NetworkError> {
+        Ok(self.fetch_negotiated(url, accept).await?.1)
+    }
+
+    /// Like [`fetch_fresh`](Self::fetch_fresh), also returning the
+    /// `Content-Type` the server chose from `accept`
+    pub async fn fetch_negotiated(
+        &self,
+        url: &str,
+        accept: &str,
+    ) -> Result<(Option<String>, bytes::Bytes), NetworkError> {
+        if self.settings.offline {
+            return Err(NetworkError::offline(url));
+        }
+        let mut last_error = None;
+        let mut backoff = self.settings.backoff();
+        loop {
+            self.throttle().await;
+            let (request, sent) = self.get(url);
+            let request = request
+                .header(reqwest::header::ACCEPT, accept)
+                .header(reqwest::header::CACHE_CONTROL, "max-age=0");
+            let (status, retry_after) = match request.send().await {
+                Ok(response) if response.status().is_success() => {
+                    let content_type = response
+                        .headers()
+                        .get(reqwest::header::CONTENT_TYPE)
+                        .and_then(|value| value.to_str().ok())
+                        .map(String::from);
+                    let body = response.bytes().await.map_err(|e| read_error(url, &e))?;
+                    return Ok((content_type, body));
                 }
+                Ok(response) if response.status() == StatusCode::UNAUTHORIZED => {
+                    self.challenge(url, sent)?;
+                    continue;
+                }
+                Ok(response) => match retryable(&response) {
+                    Ok(failure) => failure,
+                    Err(e) => return Err(e),
+                },
+                Err(e) => {
+                    last_error = Some(self.network_error(url, &e));
+                    (None, None)
+                }
+            };
+            if let Some(status) = status {
+                last_error = Some(NetworkError::status(url, status));
+            }
+            match backoff.next_delay(status, retry_after) {
+                Some(delay) => tokio::time::sleep(delay).await,
+                None => break,
             }
         }
-        
+        Err(last_error.unwrap_or_else(|| self.gave_up(url)))
+    }
+
+    /// Download package with retry logic and progress
+    pub async fn download_package(&self, url: &str) -> Result<bytes::Bytes, NetworkError> {
+        self.download_cached(url, resume::sha256_from_url(url)).await
+    }
+
+    /// Download a file whose sha256 the index published; the hash guards
+    /// resuming its `.part` and is checked before the file is accepted
+    pub async fn download_package_with_hash(
+        &self,
+        url: &str,
+        sha256: Option<&str>,
+    ) -> Result<bytes::Bytes, NetworkError> {
+        self.download_cached(url, sha256.or_else(|| resume::sha256_from_url(url))).await
+    }
+
+    /// Download `url` to `dest`, streaming the body to disk rather than
+    /// holding it in memory; see [`download_to_file_with`](Self::download_to_file_with)
+    pub async fn download_to_file(&self, url: &str, dest: &Path) -> Result<PathBuf, NetworkError> {
+        self.download_to_file_with(url, dest, None, None).await
+    }
+
+    /// Download `url` to `dest`, checked against `sha256` (or the URL's
+    /// `#sha256=` fragment) and reported to `progress` as it arrives. The
+    /// body goes to a `.part` file that is synced and renamed into place,
+    /// so `dest` only ever appears complete. Wheels are served from and
+    /// kept in the wheel cache like [`download_package`](Self::download_package).
+    pub async fn download_to_file_with(
+        &self,
+        url: &str,
+        dest: &Path,
+        sha256: Option<&str>,
+        progress: Option<&Progress<'_>>,
+    ) -> Result<PathBuf, NetworkError> {
+        let expected_sha256 = sha256.or_else(|| resume::sha256_from_url(url));
+        let fail = |e: anyhow::Error| NetworkError::from_anyhow(url, e);
+        if let Some(parent) = dest.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| fail(e.into()))?;
+        }
+        // A `--find-links` file on disk is copied, not downloaded
+        if let Some(local) = find_links::local_path(url) {
+            artifact::check_file(url, &local, expected_sha256).map_err(fail)?;
+            resume::copy_into_place(&local, dest).map_err(fail)?;
+            return Ok(dest.to_path_buf());
+        }
+        let is_wheel = artifact::filename(url).is_some_and(|name| name.ends_with(".whl"));
+        let wheel_cache = self.wheel_cache.as_ref().filter(|_| is_wheel);
+        if let Some(cached) = wheel_cache.and_then(|cache| cache.get_path(url, expected_sha256)) {
+            resume::copy_into_place(&cached, dest).map_err(fail)?;
+            return Ok(dest.to_path_buf());
+        }
+
+        // Stage in the downloads directory so an interrupted transfer can
+        // resume on the next run, whatever `dest` is
+        let staged = match &self.downloads_dir {
+            Some(downloads_dir) => staging_path(downloads_dir, url),
+            None => dest.to_path_buf(),
+        };
+        self.download_resumable_with_retry(url, &staged, expected_sha256, progress).await?;
+        if let Some(wheel_cache) = wheel_cache
+            && let Err(e) = wheel_cache.put_file(url, expected_sha256, &staged)
+        {
+            tracing::debug!("Failed to cache wheel from {}: {}", redact_url_credentials(url), e);
+        }
+        if staged != dest {
+            resume::move_into_place(&staged, dest).map_err(fail)?;
+        }
+        Ok(dest.to_path_buf())
+    }
+
+    /// Serve wheels from the wheel cache when an intact copy is there, and
+    /// keep every wheel downloaded for the next install
+    async fn download_cached(&self, url: &str, expected_sha256: Option<&str>) -> Result<bytes::Bytes, NetworkError> {
+        if let Some(local) = find_links::local_path(url) {
+            artifact::check_file(url, &local, expected_sha256).map_err(|e| NetworkError::from_anyhow(url, e))?;
+            let data = std::fs::read(local).map_err(|e| NetworkError::new(NetworkErrorKind::Io, url, e))?;
+            return Ok(bytes::Bytes::from(data));
+        }
+        let is_wheel = artifact::filename(url).is_some_and(|name| name.ends_with(".whl"));
+        let Some(wheel_cache) = self.wheel_cache.as_ref().filter(|_| is_wheel) else {
+            return self.download_with_retry(url, expected_sha256).await;
+        };
+        if let Some(data) = wheel_cache.get(url, expected_sha256) {
+            return Ok(bytes::Bytes::from(data));
+        }
+
+        let data = self.download_with_retry(url, expected_sha256).await?;
+        if let Err(e) = wheel_cache.put(url, expected_sha256, &data) {
+            tracing::debug!("Failed to cache wheel from {}: {}", url, e);
+        }
+        Ok(data)
+    }
+
+    /// Get JSON with exponential backoff retry. Cached responses are used
+    /// while fresh; a stale one is revalidated with `If-None-Match` /
+    /// `If-Modified-Since`, and a 304 refreshes it in place.
+    async fn get_with_retry(&self, url: &str, policy: CachePolicy) -> Result<serde_json::Value, NetworkError> {
+        let cached = self.cache.as_ref().and_then(|cache| cache.lookup(url));
+        if self.settings.offline {
+            return cached
+                .and_then(|entry| serde_json::from_slice(&entry.body).ok())
+                .ok_or_else(|| NetworkError::offline(url));
+        }
+        if let Some(entry) = &cached
+            && policy == CachePolicy::Default
+            && entry.is_fresh(self.cache_ttl)
+            && let Ok(json) = serde_json::from_slice(&entry.body)
+        {
+            tracing::debug!("Cache hit for {}", redact_url_credentials(url));
+            return Ok(json);
+        }
+        let validators = cached.as_ref().map(|entry| &entry.meta).filter(|meta| meta.has_validator());
+
         let mut last_error = None;
-        
-        for attempt in 0..MAX_RETRIES {
-            match self.client.get(url).send().await {
-                Ok(response) => {
-                    if response.status().is_success() {
-                        // For large packages, use streaming JSON parsing
-                        let content_length = response.content_length().unwrap_or(0);
-                        if content_length > 10_000_000 {
-                            // Large response - use streaming
-                            match self.parse_streaming_json(response).await {
-                                Ok(json) => {
-                                    // Cache the result (but strip cache-busting param for cache key)
-                                    if let Some(cache) = &self.cache {
-                                        let cache_key = if bypass_cache {
-                                            url.split('&').next().unwrap_or(url).split('?').next().unwrap_or(url)
-                                        } else {
-                                            url
-                                        };
-                                        if let Ok(json_str) = serde_json::to_string(&json) {
-                                            let _ = cache.set(cache_key, json_str.as_bytes());
-                                        }
-                                    }
-                                    return Ok(json);
-                                }
-                                Err(e) => {
-                                    return Err(anyhow!("Failed to parse streaming JSON: {}", e));
-                                }
-                            }
-                        } else {
-                            // Small response - use standard parsing
-                        match response.json::<serde_json::Value>().await {
-                            Ok(json) => {
-                                // Cache the result (but strip cache-busting param for cache key)
-                                if let Some(cache) = &self.cache {
-                                    let cache_key = if bypass_cache {
-                                        url.split('&').next().unwrap_or(url).split('?').next().unwrap_or(url)
-                                    } else {
-                                        url
-                                    };
-                                    if let Ok(json_str) = serde_json::to_string(&json) {
-                                        let _ = cache.set(cache_key, json_str.as_bytes());
-                                    }
-                                }
-                                return Ok(json);
-                            }
-                            Err(e) => {
-                                return Err(anyhow!("Failed to parse JSON: {}", e));
-                                }
-                            }
-                        }
-                    } else if response.status().is_client_error() {
-                        return Err(anyhow!("Client error: {}", response.status()));
+        let mut backoff = self.settings.backoff();
+
+        loop {
+            self.throttle().await;
+            let (mut request, sent) = self.get(url);
+            if let Some(meta) = validators {
+                if let Some(etag) = &meta.etag {
+                    request = request.header(header::IF_NONE_MATCH, etag);
+                }
+                if let Some(last_modified) = &meta.last_modified {
+                    request = request.header(header::IF_MODIFIED_SINCE, last_modified);
+                }
+            }
+            if policy == CachePolicy::Revalidate {
+                // Don't let an intermediate cache answer for the server
+                request = request.header(header::CACHE_CONTROL, "max-age=0");
+            }
+
+            let (status, retry_after) = match request.send().await {
+                Ok(response) if response.status() == StatusCode::NOT_MODIFIED => {
+                    let (Some(cache), Some(entry)) = (&self.cache, &cached) else {
+                        let message = format!("Unexpected 304 Not Modified for {}", redact_url_credentials(url));
+                        return Err(NetworkError::new(NetworkErrorKind::InvalidResponse, url, message));
+                    };
+                    let json = serde_json::from_slice(&entry.body).map_err(|e| parse_error(url, e))?;
+                    tracing::debug!("Revalidated cached {}", redact_url_credentials(url));
+                    if let Err(e) = cache.refresh(url, &entry_meta(response.headers()).unwrap_or_default()) {
+                        tracing::debug!("Failed to refresh cache entry for {}: {}", url, e);
+                    }
+                    return Ok(json);
+                }
+                Ok(response) if response.status().is_success() => {
+                    let meta = entry_meta(response.headers());
+                    let body = response.bytes().await.map_err(|e| read_error(url, &e))?;
+                    let json = serde_json::from_slice(&body).map_err(|e| parse_error(url, e))?;
+                    if let (Some(cache), Some(meta)) = (&self.cache, meta) {
+                        let _ = cache.set_with_meta(url, &body, &meta);
                     }
-                    // Server error, retry
-                    last_error = Some(anyhow!("Server error: {}", response.status()));
+                    return Ok(json);
+                }
+                Ok(response) if response.status() == StatusCode::UNAUTHORIZED => {
+                    self.challenge(url, sent)?;
+                    continue;
                 }
+                Ok(response) => match retryable(&response) {
+                    Ok(failure) => failure,
+                    Err(e) => return Err(e),
+                },
                 Err(e) => {
-                    last_error = Some(anyhow!("Network error: {}", e));
+                    last_error = Some(self.network_error(url, &e));
+                    (None, None)
                 }
+            };
+            if let Some(status) = status {
+                last_error = Some(NetworkError::status(url, status));
             }
-            
-            if attempt < MAX_RETRIES - 1 {
-                let delay = Duration::from_millis(RETRY_DELAY_MS * 2_u64.pow(attempt));
-                // Only log retries in debug mode to reduce noise
-                tracing::debug!("Retry attempt {} for {} after {:?}...", attempt + 1, url, delay);
-                tokio::time::sleep(delay).await;
+
+            match backoff.next_delay(status, retry_after) {
+                Some(delay) => {
+                    // Only log retries in debug mode to reduce noise
+                    tracing::debug!("Retrying {} after {:?}...", redact_url_credentials(url), delay);
+                    tokio::time::sleep(delay).await;
+                }
+                None => break,
             }
         }
-        
-        Err(last_error.unwrap_or_else(|| anyhow!("Failed to fetch after {} retries", MAX_RETRIES)))
+
+        Err(last_error.unwrap_or_else(|| self.gave_up(url)))
     }
 
     /// Download with exponential backoff retry
-    async fn download_with_retry(&self, url: &str) -> Result<bytes::Bytes> {
-        let mut last_error = None;
+    async fn download_with_retry(
+        &self,
+        url: &str,
+        expected_sha256: Option<&str>,
+    ) -> Result<bytes::Bytes, NetworkError> {
+        if self.settings.offline {
+            return Err(NetworkError::offline(url));
+        }
+        if let Some(downloads_dir) = &self.downloads_dir {
+            let path = self
+                .download_resumable_with_retry(url, &staging_path(downloads_dir, url), expected_sha256, None)
+                .await?;
+            let data = std::fs::read(&path);
+            let _ = std::fs::remove_file(&path);
+            return data
+                .map(bytes::Bytes::from)
+                .map_err(|e| NetworkError::new(NetworkErrorKind::Io, url, format!("Failed to read download: {}", e)));
+        }
+
+        let mut bypass_cache = false;
+        let mut backoff = self.settings.backoff();
         
-        for attempt in 0..MAX_RETRIES {
-            match self.client.get(url).send().await {
+        let error = loop {
+            self.throttle().await;
+            let (mut request, sent) = self.get(url);
+            if bypass_cache {
+                request = request.header(reqwest::header::CACHE_CONTROL, "no-cache");
+            }
+            let (mut status, mut retry_after) = (None, None);
+            let error = match request.send().await {
+                Ok(response) if response.status() == StatusCode::UNAUTHORIZED => {
+                    self.challenge(url, sent)?;
+                    continue;
+                }
                 Ok(response) => {
                     if response.status().is_success() {
-                        return response.bytes().await.map_err(|e| anyhow!("Failed to read response: {}", e));
-                    } else if response.status().is_client_error() {
-                        return Err(anyhow!("Client error: {}", response.status()));
+                        let content_length = response.content_length();
+                        match response.bytes().await {
+                            Ok(data) => match artifact::check_length(url, &data, content_length)
+                                .and_then(|()| artifact::check_contents(url, &data, expected_sha256))
+                            {
+                                Ok(()) => return Ok(data),
+                                Err(invalid) => {
+                                    tracing::debug!("{}; retrying without caches", invalid);
+                                    bypass_cache = true;
+                                    NetworkError::new(NetworkErrorKind::InvalidArtifact, url, invalid)
+                                }
+                            },
+                            // A body cut off mid-transfer is worth another try
+                            Err(e) => read_error(url, &e),
+                        }
+                    } else {
+                        (status, retry_after) = retryable(&response)?;
+                        NetworkError::status(url, response.status())
                     }
-                    // Server error, retry
-                    last_error = Some(anyhow!("Server error: {}", response.status()));
-                }
-                Err(e) => {
-                    last_error = Some(anyhow!("Network error: {}", e));
                 }
-            }
+                Err(e) => self.network_error(url, &e),
+            };
             
-            if attempt < MAX_RETRIES - 1 {
-                let delay = Duration::from_millis(RETRY_DELAY_MS * 2_u64.pow(attempt));
-                // Only log retries in debug mode to reduce noise
-                tracing::debug!("Retry attempt {} for {} after {:?}...", attempt + 1, url, delay);
-                tokio::time::sleep(delay).await;
+            match backoff.next_delay(status, retry_after) {
+                Some(delay) => {
+                    // Only log retries in debug mode to reduce noise
+                    tracing::debug!("Retrying {} after {:?}...", redact_url_credentials(url), delay);
+                    tokio::time::sleep(delay).await;
+                }
+                None => break error,
             }
-        }
+        };
         
-        Err(last_error.unwrap_or_else(|| anyhow!("Failed to download after {} retries", MAX_RETRIES)))
+        Err(error)
     }
 
-    /// Parse JSON from a streaming response for large packages
-    async fn parse_streaming_json(&self, response: reqwest::Response) -> Result<serde_json::Value> {
-        // For large responses, read all bytes first then parse
-        // This avoids timeout issues with very large JSON responses
-        let bytes = response.bytes().await.map_err(|e| anyhow!("Failed to read response: {}", e))?;
-        
-        // Parse the complete JSON
-        serde_json::from_slice(&bytes)
-            .map_err(|e| anyhow!("Failed to parse JSON from stream: {}", e))
+    /// Download to `dest` through a `.part` file beside it, so an
+    /// interrupted transfer continues on the next attempt (or the next run)
+    async fn download_resumable_with_retry(
+        &self,
+        url: &str,
+        dest: &Path,
+        expected_sha256: Option<&str>,
+        progress: Option<&Progress<'_>>,
+    ) -> Result<PathBuf, NetworkError> {
+        if self.settings.offline {
+            return Err(NetworkError::offline(url));
+        }
+        let mut bypass_cache = false;
+        let mut backoff = self.settings.backoff();
+
+        let error = loop {
+            self.throttle().await;
+            let (plain_url, credentials) = self.authorize(url);
+            let (mut status, mut retry_after) = (None, None);
+            let download = resume::download_resumable(
+                self.client_for(&plain_url),
+                &plain_url,
+                credentials.as_ref(),
+                dest,
+                expected_sha256,
+                bypass_cache,
+                progress,
+            );
+            let error = match download.await {
+                Ok(path) => return Ok(path),
+                Err(e) => {
+                    if let Some(error) = e.downcast_ref::<resume::HttpStatusError>() {
+                        if error.status == StatusCode::UNAUTHORIZED {
+                            self.challenge(url, credentials.is_some())?;
+                            continue;
+                        }
+                        if !throttle::is_retryable(error.status) && error.status.is_client_error() {
+                            return Err(NetworkError::from_anyhow(url, e));
+                        }
+                        (status, retry_after) = (Some(error.status), error.retry_after);
+                    }
+                    if let Some(invalid) = e.downcast_ref::<InvalidArtifact>() {
+                        // Don't resume from (or let a proxy serve again) what was just rejected
+                        tracing::debug!("{}; retrying without caches", invalid);
+                        bypass_cache = true;
+                    }
+                    match e.downcast_ref::<reqwest::Error>() {
+                        Some(error) => self.network_error(&plain_url, error),
+                        None => NetworkError::from_anyhow(url, e),
+                    }
+                }
+            };
+
+            match backoff.next_delay(status, retry_after) {
+                Some(delay) => {
+                    tracing::debug!("Retrying {} after {:?}...", redact_url_credentials(url), delay);
+                    tokio::time::sleep(delay).await;
+                }
+                None => break error,
+            }
+        };
+
+        Err(error)
     }
 }
 
@@ -248,7 +684,450 @@ impl Default for PackageClient {
     }
 }
 
-/// Helper function to create a client with trusted hosts from config
+/// A client for the configured primary index. Its trusted hosts are the
+/// process-wide ones, so `--trusted-host` applied later still reaches it.
 pub fn create_client_with_config(config: &crate::config::config::Config) -> PackageClient {
-    PackageClient::with_trusted_hosts(config.trusted_hosts().to_vec())
+    let primary = super::index::IndexManager::from_config(config).get_primary().json_api_url();
+    PackageClient::new()
+        .with_base_url(primary)
+        .with_cache_ttl(config.cache_ttl())
+        .with_settings(NetworkSettings::from_config(config))
+}
+
+/// Where a download of `url` is staged under `downloads_dir`
+fn staging_path(downloads_dir: &Path, url: &str) -> PathBuf {
+    let filename = artifact::filename(url).unwrap_or("download");
+    let url_hash = format!("{:x}", Sha256::digest(url.as_bytes()));
+    downloads_dir.join(format!("{}-{}", &url_hash[..16], filename))
+}
+
+/// For a failed response: its status and `Retry-After` when worth another
+/// try, or the error to give up with
+fn retryable(response: &reqwest::Response) -> Result<(Option<StatusCode>, Option<Duration>), NetworkError> {
+    let status = response.status();
+    if status.is_client_error() && !throttle::is_retryable(status) {
+        return Err(NetworkError::status(response.url().as_str(), status));
+    }
+    Ok((Some(status), throttle::retry_after(response.headers())))
+}
+
+/// A body that broke off mid-transfer
+fn read_error(url: &str, error: &reqwest::Error) -> NetworkError {
+    NetworkError::new(NetworkError::kind_of(error), url, format!("Failed to read response: {}", error))
+}
+
+fn parse_error(url: &str, error: serde_json::Error) -> NetworkError {
+    NetworkError::new(NetworkErrorKind::InvalidResponse, url, format!("Failed to parse JSON: {}", error))
+}
+
+/// Caching headers of a response; `None` when it must not be stored
+fn entry_meta(headers: &header::HeaderMap) -> Option<EntryMeta> {
+    let value = |name: header::HeaderName| headers.get(name).and_then(|v| v.to_str().ok()).map(String::from);
+    let mut max_age_secs = None;
+    for directive in value(header::CACHE_CONTROL).unwrap_or_default().to_lowercase().split(',').map(str::trim) {
+        if directive == "no-store" {
+            return None;
+        }
+        if directive == "no-cache" {
+            max_age_secs = Some(0);
+        } else if let Some(secs) = directive.strip_prefix("max-age=") {
+            max_age_secs = max_age_secs.or(secs.trim_matches('"').parse().ok());
+        }
+    }
+    Some(EntryMeta {
+        etag: value(header::ETAG),
+        last_modified: value(header::LAST_MODIFIED),
+        max_age_secs,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::Matcher;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    const HTML: &str = "<!DOCTYPE html><html><body>Blocked by corporate proxy</body></html>";
+
+    fn test_client(downloads_dir: Option<PathBuf>) -> PackageClient {
+        PackageClient {
+            client: Client::new(),
+            base_url: "http://localhost".to_string(),
+            cache: None,
+            cache_ttl: DEFAULT_CACHE_TTL,
+            downloads_dir,
+            wheel_cache: None,
+            rate_limiter: None,
+            auth: None,
+            insecure_client: OnceCell::new(),
+            trusted_hosts: Arc::new(TrustedHosts::default()),
+            settings: NetworkSettings::default(),
+        }
+    }
+
+    fn wheel_bytes() -> Vec<u8> {
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        zip.start_file("pkg/__init__.py", zip::write::FileOptions::default()).unwrap();
+        zip.write_all(b"VERSION = '1.0'\n").unwrap();
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[tokio::test]
+    async fn test_html_body_is_retried_then_reported() {
+        let mut server = mockito::Server::new_async().await;
+        let first = server
+            .mock("GET", "/pkg-1.0-py3-none-any.whl")
+            .match_header("cache-control", Matcher::Missing)
+            .with_body(HTML)
+            .create_async()
+            .await;
+        let retry = server
+            .mock("GET", "/pkg-1.0-py3-none-any.whl")
+            .match_header("cache-control", "no-cache")
+            .with_body(HTML)
+            .create_async()
+            .await;
+
+        let downloads = TempDir::new().unwrap();
+        let url = format!("{}/pkg-1.0-py3-none-any.whl", server.url());
+        let error = test_client(Some(downloads.path().to_path_buf()))
+            .download_package(&url)
+            .await
+            .unwrap_err()
+            .to_string();
+
+        first.assert_async().await;
+        retry.assert_async().await;
+        assert!(error.contains(&format!("received an invalid/truncated file from {} — a proxy may be interfering", url)), "{}", error);
+        assert!(error.contains("First bytes: <!DOCTYPE html>"), "{}", error);
+        // Nothing of the bad body is kept to resume from
+        assert_eq!(std::fs::read_dir(downloads.path()).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_truncated_zip_is_rejected() {
+        let mut wheel = wheel_bytes();
+        wheel.truncate(wheel.len() / 2);
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/pkg-1.0-py3-none-any.whl")
+            .with_body(&wheel)
+            .expect(2)
+            .create_async()
+            .await;
+
+        let url = format!("{}/pkg-1.0-py3-none-any.whl", server.url());
+        let error = test_client(None).download_package(&url).await.unwrap_err().to_string();
+
+        mock.assert_async().await;
+        assert!(error.contains("a proxy may be interfering (ZIP archive has no end of central directory"), "{}", error);
+        assert!(!error.contains("First bytes"));
+    }
+
+    #[tokio::test]
+    async fn test_retry_bypassing_caches_succeeds() {
+        let wheel = wheel_bytes();
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/pkg-1.0-py3-none-any.whl")
+            .match_header("cache-control", Matcher::Missing)
+            .with_body(HTML)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/pkg-1.0-py3-none-any.whl")
+            .match_header("cache-control", "no-cache")
+            .with_body(&wheel)
+            .create_async()
+            .await;
+
+        let downloads = TempDir::new().unwrap();
+        let url = format!("{}/pkg-1.0-py3-none-any.whl", server.url());
+        let data = test_client(Some(downloads.path().to_path_buf()))
+            .download_package(&url)
+            .await
+            .unwrap();
+
+        assert_eq!(data.as_ref(), wheel.as_slice());
+        assert_eq!(std::fs::read_dir(downloads.path()).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_published_hash_is_verified() {
+        let wheel = wheel_bytes();
+        let hash = format!("{:x}", Sha256::digest(&wheel));
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/pkg-1.0-py3-none-any.whl")
+            .with_body(&wheel)
+            .create_async()
+            .await;
+
+        let downloads = TempDir::new().unwrap();
+        let client = test_client(Some(downloads.path().to_path_buf()));
+        let url = format!("{}/pkg-1.0-py3-none-any.whl", server.url());
+        let data = client.download_package_with_hash(&url, Some(&hash)).await.unwrap();
+        assert_eq!(data.as_ref(), wheel.as_slice());
+
+        let error = client.download_package_with_hash(&url, Some(&"0".repeat(64))).await.unwrap_err();
+        assert!(error.to_string().contains("Hash mismatch"), "{}", error);
+        // A rejected body is never kept to resume from
+        assert_eq!(std::fs::read_dir(downloads.path()).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_wheels_are_served_from_the_wheel_cache() {
+        let wheel = wheel_bytes();
+        let hash = format!("{:x}", Sha256::digest(&wheel));
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/pkg-1.0-py3-none-any.whl")
+            .with_body(&wheel)
+            .expect(2)
+            .create_async()
+            .await;
+
+        let wheels = TempDir::new().unwrap();
+        let client = PackageClient {
+            wheel_cache: Some(WheelCache::new(wheels.path())),
+            ..test_client(None)
+        };
+        let url = format!("{}/pkg-1.0-py3-none-any.whl", server.url());
+        for _ in 0..2 {
+            let data = client.download_package_with_hash(&url, Some(&hash)).await.unwrap();
+            assert_eq!(data.as_ref(), wheel.as_slice());
+        }
+
+        // A corrupted entry is evicted and fetched again
+        let cached = WheelCache::new(wheels.path()).list();
+        assert_eq!(cached.len(), 1);
+        std::fs::write(&cached[0].path, b"garbage").unwrap();
+        let data = client.download_package_with_hash(&url, Some(&hash)).await.unwrap();
+        assert_eq!(data.as_ref(), wheel.as_slice());
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_stale_metadata_is_revalidated_with_its_etag() {
+        let mut server = mockito::Server::new_async().await;
+        let full = server
+            .mock("GET", "/pkg/json")
+            .match_header("if-none-match", Matcher::Missing)
+            .with_header("etag", "\"v1\"")
+            .with_body(r#"{"info": {"version": "1.0"}}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let not_modified = server
+            .mock("GET", "/pkg/json")
+            .match_header("if-none-match", "\"v1\"")
+            .with_status(304)
+            .expect(2)
+            .create_async()
+            .await;
+
+        let cache_dir = TempDir::new().unwrap();
+        let client = PackageClient {
+            cache: Some(DiskCache::new(cache_dir.path(), DEFAULT_CACHE_TTL).unwrap()),
+            ..test_client(None)
+        };
+        // An entry with an ETag and no max-age is checked on every use
+        for policy in [CachePolicy::Default, CachePolicy::Default, CachePolicy::Revalidate] {
+            let json = client.get_package_info_at(&server.url(), "pkg", policy).await.unwrap();
+            assert_eq!(json["info"]["version"], "1.0");
+        }
+        full.assert_async().await;
+        not_modified.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_too_many_requests_is_retried_after_retry_after() {
+        let mut server = mockito::Server::new_async().await;
+        let throttled = server
+            .mock("GET", "/pkg/json")
+            .with_status(429)
+            .with_header("retry-after", "0")
+            .expect(2)
+            .create_async()
+            .await;
+        let json = r#"{"info": {"version": "1.0"}}"#;
+        let ok = server.mock("GET", "/pkg/json").with_body(json).create_async().await;
+
+        // The 429 mock answers until its hits are used up; two of them are
+        // more than the ordinary retry budget allows
+        let client = test_client(None);
+        let info = client.get_package_info_at(&server.url(), "pkg", CachePolicy::Default).await.unwrap();
+        assert_eq!(info["info"]["version"], "1.0");
+        throttled.assert_async().await;
+        ok.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_index_token_is_sent_after_a_401() {
+        let mut server = mockito::Server::new_async().await;
+        let challenge = server
+            .mock("GET", "/pkg/json")
+            .match_header("authorization", Matcher::Missing)
+            .with_status(401)
+            .expect(1)
+            .create_async()
+            .await;
+        let json = r#"{"info": {"version": "1.0"}}"#;
+        let ok = server
+            .mock("GET", "/pkg/json")
+            .match_header("authorization", "Bearer tok")
+            .with_body(json)
+            .create_async()
+            .await;
+
+        let index = super::super::index::IndexConfig {
+            name: "corp".to_string(),
+            url: format!("{}/simple/", server.url()),
+            priority: 0,
+            default: true,
+            token: Some("tok".to_string()),
+            simple_only: false,
+        };
+        let client = PackageClient {
+            auth: Some(Arc::new(Authenticator::new(vec![index], None))),
+            ..test_client(None)
+        };
+        let info = client.get_package_info_at(&server.url(), "pkg", CachePolicy::Default).await.unwrap();
+        assert_eq!(info["info"]["version"], "1.0");
+        challenge.assert_async().await;
+        ok.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_large_download_is_streamed_to_disk() {
+        let body: Vec<u8> = (0..8 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+        let mut server = mockito::Server::new_async().await;
+        let _big = server.mock("GET", "/big.bin").with_body(&body).create_async().await;
+        let temp = TempDir::new().unwrap();
+        let dest = temp.path().join("out").join("big.bin");
+
+        let reports = std::sync::Mutex::new(Vec::new());
+        let progress = |written: u64, total: Option<u64>| reports.lock().unwrap().push((written, total));
+        let sha256 = format!("{:x}", Sha256::digest(&body));
+        let url = format!("{}/big.bin", server.url());
+        let path = test_client(None)
+            .download_to_file_with(&url, &dest, Some(&sha256), Some(&progress as &Progress<'_>))
+            .await
+            .unwrap();
+
+        assert_eq!(path, dest);
+        assert_eq!(std::fs::read(&dest).unwrap(), body);
+        // Reported while chunks arrived, not once after buffering the body
+        let reports = reports.into_inner().unwrap();
+        assert!(reports.len() > 2, "{:?}", reports);
+        assert!(reports.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert_eq!(reports.last(), Some(&(body.len() as u64, Some(body.len() as u64))));
+        // Only the finished file is left
+        assert_eq!(std::fs::read_dir(temp.path().join("out")).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_only_trusted_https_hosts_skip_verification() {
+        let client = PackageClient {
+            trusted_hosts: Arc::new(TrustedHosts::new(vec!["pypi.internal:8443".to_string()])),
+            ..test_client(None)
+        };
+        let insecure = client.client_for("https://cdn.pypi.internal:8443/simple/");
+        assert!(!std::ptr::eq(insecure, &client.client));
+        assert!(std::ptr::eq(client.client_for("https://cdn.pypi.internal:8443/a.whl"), insecure));
+        assert!(std::ptr::eq(client.client_for("https://pypi.internal/simple/"), &client.client));
+        assert!(std::ptr::eq(client.client_for("http://pypi.internal:8443/simple/"), &client.client));
+        assert!(std::ptr::eq(client.client_for("https://pypi.org/simple/"), &client.client));
+    }
+
+    #[tokio::test]
+    async fn test_missing_credentials_are_reported_per_host() {
+        let mut server = mockito::Server::new_async().await;
+        let _denied = server.mock("GET", "/pkg/json").with_status(401).create_async().await;
+
+        let client = PackageClient {
+            auth: Some(Arc::new(Authenticator::new(Vec::new(), None))),
+            ..test_client(None)
+        };
+        let error = client.get_package_info_at(&server.url(), "pkg", CachePolicy::Default).await.unwrap_err();
+        assert!(error.to_string().contains("authentication required for host 127.0.0.1"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_configured_timeout_fails_a_slow_request_fast() {
+        // Accepts connections and never answers
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let mut held = Vec::new();
+            for stream in listener.incoming() {
+                held.push(stream);
+            }
+        });
+
+        let mut config = crate::config::config::Config::new();
+        config.set_timeout(1);
+        config.set_retries(0);
+        let client = create_client_with_config(&config);
+        let started = std::time::Instant::now();
+        let result = client
+            .get_package_info_at(&format!("http://{}/pypi", address), "demo", CachePolicy::Revalidate)
+            .await;
+        assert!(result.is_err());
+        assert!(started.elapsed() < Duration::from_secs(5), "took {:?}", started.elapsed());
+    }
+
+    #[tokio::test]
+    async fn test_connection_errors_name_the_proxy() {
+        // Nothing listens there any more
+        let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let settings = NetworkSettings {
+            retries: 0,
+            proxy: ProxyConfig::from_vars(Some(&format!("user:pw@{}", closed)), |_| None),
+            ..NetworkSettings::default()
+        };
+        let error = test_client(None)
+            .with_settings(settings)
+            .get_package_info_at("http://pypi.example/pypi", "demo", CachePolicy::Revalidate)
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains(&format!("(via proxy http://****@{})", closed)), "{}", error);
+
+        let error = test_client(None)
+            .with_settings(NetworkSettings { retries: 0, ..NetworkSettings::default() })
+            .get_package_info_at(&format!("http://{}/pypi", closed), "demo", CachePolicy::Revalidate)
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(error.ends_with("(direct, no proxy configured)"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_offline_mode_answers_from_the_caches_only() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/pkg/json")
+            .with_body(r#"{"info": {"version": "1.0"}}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let cache_dir = TempDir::new().unwrap();
+        let cache = || DiskCache::new(cache_dir.path(), DEFAULT_CACHE_TTL).unwrap();
+        let online = PackageClient { cache: Some(cache()), ..test_client(None) };
+        online.get_package_info_at(&server.url(), "pkg", CachePolicy::Default).await.unwrap();
+
+        // Served however stale, even where a revalidation was asked for
+        let offline = PackageClient { cache: Some(cache()), cache_ttl: Duration::ZERO, ..test_client(None) }
+            .with_settings(NetworkSettings { offline: true, ..NetworkSettings::default() });
+        let json = offline.get_package_info_at(&server.url(), "pkg", CachePolicy::Revalidate).await.unwrap();
+        assert_eq!(json["info"]["version"], "1.0");
+        mock.assert_async().await;
+
+        let error = offline.get_package_info_at(&server.url(), "other", CachePolicy::Default).await.unwrap_err();
+        assert_eq!(error.to_string(), format!("offline mode: {}/other/json not available in cache", server.url()));
+        let error = offline.download_package(&format!("{}/pkg-1.0-py3-none-any.whl", server.url())).await.unwrap_err();
+        assert_eq!(error.kind, NetworkErrorKind::Offline);
+    }
 }
@@ -0,0 +1,200 @@
+/// `--find-links` locations and `--no-index`
+///
+/// A find-links location is a directory of distributions, or an HTML page
+/// of links to them. Its files for a project are picked out by filename and
+/// laid out as a JSON API document the same way simple API pages are, so
+/// resolution and downloads treat them like any other index; a file on
+/// disk is then "downloaded" by copying it. Dependencies come from the
+/// METADATA of a local wheel. With `--no-index` these locations are the
+/// only place packages are looked up, so an install needs no network.
+use super::client::PackageClient;
+use super::simple_api::{self, SimpleFile};
+use crate::installer::wheel::WheelFile;
+use crate::utils::names::canonicalize_name;
+use crate::utils::pep691_handler::Pep691Handler;
+use crate::utils::warnings::{self, WarningCode};
+use anyhow::{Result, anyhow};
+use serde_json::{Value, json};
+use std::path::PathBuf;
+
+/// A package was looked for only in find-links locations and isn't there
+#[derive(Debug)]
+pub struct NotFound {
+    pub package: String,
+    pub locations: Vec<String>,
+}
+
+impl std::fmt::Display for NotFound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.locations.is_empty() {
+            write!(f, "package {} not found: --no-index is set and no --find-links location was given", self.package)
+        } else {
+            write!(f, "package {} not found in any of: {}", self.package, self.locations.join(", "))
+        }
+    }
+}
+
+impl std::error::Error for NotFound {}
+
+/// The directory a find-links entry names, or `None` for a remote page
+pub fn local_dir(link: &str) -> Option<PathBuf> {
+    if link.starts_with("file:") {
+        return url::Url::parse(link).ok()?.to_file_path().ok();
+    }
+    if link.contains("://") {
+        return None;
+    }
+    Some(PathBuf::from(link))
+}
+
+/// The file a `file://` download URL points at
+pub fn local_path(url: &str) -> Option<PathBuf> {
+    let url = url::Url::parse(url).ok().filter(|url| url.scheme() == "file")?;
+    url.to_file_path().ok()
+}
+
+/// Whether `filename` is a distribution of `project`
+fn belongs_to(project: &str, filename: &str) -> bool {
+    let Some(version) = simple_api::filename_version(project, filename) else {
+        return false;
+    };
+    let Some(name) = filename.find(&format!("-{}", version)).map(|end| &filename[..end]) else {
+        return false;
+    };
+    canonicalize_name(name) == canonicalize_name(project)
+}
+
+/// Files of `project` found in `links`, in order. Directories that don't
+/// exist and pages that can't be fetched are skipped.
+pub async fn project_files(client: &PackageClient, links: &[String], project: &str) -> Vec<SimpleFile> {
+    let mut files = Vec::new();
+    for link in links {
+        let found = match local_dir(link) {
+            Some(dir) => match Pep691Handler::scan_directory(&dir) {
+                Ok(index) => index
+                    .files
+                    .into_iter()
+                    .filter_map(|file| {
+                        let url = url::Url::from_file_path(std::path::absolute(&file.path).ok()?).ok()?;
+                        let requires_python = file
+                            .filename
+                            .ends_with(".whl")
+                            .then(|| WheelFile::new(file.path).and_then(|wheel| wheel.get_metadata()).ok())
+                            .flatten()
+                            .and_then(|metadata| metadata.requires_python);
+                        Some(SimpleFile {
+                            filename: file.filename,
+                            url: url.to_string(),
+                            requires_python,
+                            sha256: None,
+                            yanked: file.yanked.then(String::new),
+                            has_metadata: false,
+                        })
+                    })
+                    .collect(),
+                Err(e) => {
+                    tracing::debug!("Skipping find-links {}: {}", link, e);
+                    Vec::new()
+                }
+            },
+            None => match client.fetch_fresh(link, "text/html").await {
+                Ok(body) => simple_api::parse_html(link, &String::from_utf8_lossy(&body)),
+                Err(e) => {
+                    tracing::debug!("Skipping find-links {}: {}", link, e);
+                    Vec::new()
+                }
+            },
+        };
+        files.extend(found.into_iter().filter(|file| belongs_to(project, &file.filename)));
+    }
+    files
+}
+
+/// A JSON API document for `project` built from the files in `links`: for
+/// `version`, or the latest release when `None`
+pub async fn document(client: &PackageClient, links: &[String], project: &str, version: Option<&str>) -> Result<Value> {
+    let files = project_files(client, links, project).await;
+    if files.is_empty() {
+        return Err(NotFound {
+            package: project.to_string(),
+            locations: links.to_vec(),
+        }
+        .into());
+    }
+    let mut document = simple_api::to_json_api(project, &files, version)?;
+    let selected = document["info"]["version"].as_str().unwrap_or_default().to_string();
+
+    let wheel = files
+        .iter()
+        .filter(|file| file.filename.ends_with(".whl"))
+        .filter(|file| simple_api::filename_version(project, &file.filename).as_deref() == Some(selected.as_str()))
+        .find_map(|file| local_path(&file.url));
+    let metadata = wheel
+        .ok_or_else(|| anyhow!("no local wheel"))
+        .and_then(WheelFile::new)
+        .and_then(|wheel| wheel.get_metadata());
+    match metadata {
+        Ok(metadata) => {
+            document["info"]["requires_dist"] = json!(metadata.requires_dist);
+            document["info"]["summary"] = json!(metadata.summary);
+            document["info"]["requires_python"] = json!(metadata.requires_python);
+        }
+        Err(_) => warnings::warn_user(
+            WarningCode::MetadataUnavailable,
+            format!(
+                "find-links has no local wheel of {} {} to read metadata from; its dependencies are not known",
+                project, selected
+            ),
+        ),
+    }
+    Ok(document)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    fn write_wheel(dir: &std::path::Path, filename: &str, metadata: &str) {
+        let file = fs::File::create(dir.join(filename)).unwrap();
+        let mut zip = zip::ZipWriter::new(file);
+        let dist_info = filename.splitn(3, '-').take(2).collect::<Vec<_>>().join("-");
+        zip.start_file(format!("{}.dist-info/METADATA", dist_info), zip::write::FileOptions::default())
+            .unwrap();
+        zip.write_all(metadata.as_bytes()).unwrap();
+        zip.finish().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_local_directory_document() {
+        let temp = TempDir::new().unwrap();
+        write_wheel(
+            temp.path(),
+            "demo_pkg-1.0-py3-none-any.whl",
+            "Metadata-Version: 2.1\nName: demo-pkg\nVersion: 1.0\n",
+        );
+        write_wheel(
+            temp.path(),
+            "demo_pkg-1.2-py3-none-any.whl",
+            "Metadata-Version: 2.1\nName: demo-pkg\nVersion: 1.2\nRequires-Python: >=3.9\nRequires-Dist: idna>=3\n",
+        );
+        fs::write(temp.path().join("demo_pkg_extras-9.0.tar.gz"), b"").unwrap();
+        let links = vec![temp.path().display().to_string()];
+        let client = PackageClient::new();
+
+        let document = document(&client, &links, "Demo.Pkg", None).await.unwrap();
+        assert_eq!(document["info"]["version"], "1.2");
+        assert_eq!(document["info"]["requires_dist"], json!(["idna>=3"]));
+        assert_eq!(document["info"]["requires_python"], ">=3.9");
+        assert_eq!(document["releases"].as_object().unwrap().len(), 2);
+        assert_eq!(document["releases"]["1.2"][0]["requires_python"], ">=3.9");
+        assert!(document["releases"]["1.0"][0]["requires_python"].is_null());
+        let url = document["urls"][0]["url"].as_str().unwrap();
+        assert_eq!(local_path(url), Some(temp.path().join("demo_pkg-1.2-py3-none-any.whl")));
+
+        let error = super::document(&client, &links, "missing", None).await.unwrap_err();
+        assert_eq!(error.to_string(), format!("package missing not found in any of: {}", links[0]));
+    }
+}
@@ -1,5 +1,6 @@
 /// PyPI index management and support for multiple indexes
 use anyhow::{Result, anyhow};
+use futures::future::BoxFuture;
 use serde::{Deserialize, Serialize};
 
 /// PyPI index configuration
@@ -15,14 +16,72 @@ pub struct IndexConfig {
     pub default: bool,
     /// Optional authentication token
     pub token: Option<String>,
+    /// Read only the simple API, never the JSON API
+    #[serde(default)]
+    pub simple_only: bool,
+}
+
+/// Every index asked answered 404 for a project: it doesn't exist there
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectNotFound {
+    pub package: String,
+    /// Names of the indexes asked
+    pub indexes: Vec<String>,
+}
+
+impl std::fmt::Display for ProjectNotFound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.indexes.as_slice() {
+            [index] => write!(f, "package {} not found on index {}", self.package, index),
+            indexes => write!(f, "package {} not found on any index ({})", self.package, indexes.join(", ")),
+        }
+    }
+}
+
+impl std::error::Error for ProjectNotFound {}
+
+/// Whether `error` is an HTTP 404
+fn is_not_found(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<super::NetworkError>().is_some_and(super::NetworkError::is_not_found)
+}
+
+/// Whether two index URLs name the same index, ignoring credentials and a
+/// trailing slash
+fn same_index_url(a: &str, b: &str) -> bool {
+    let (a, _) = super::auth::split_credentials(a);
+    let (b, _) = super::auth::split_credentials(b);
+    a.trim_end_matches('/') == b.trim_end_matches('/')
+}
+
+impl IndexConfig {
+    /// Whether this is the public PyPI index
+    pub fn is_pypi(&self) -> bool {
+        url::Url::parse(&self.url).is_ok_and(|url| url.host_str() == Some("pypi.org"))
+    }
+
+    /// Base of the JSON API (`<base>/<name>/json`) next to this simple
+    /// index: `.../simple` becomes `.../pypi`, the layout PyPI, Nexus and
+    /// Artifactory share
+    pub fn json_api_url(&self) -> String {
+        let url = self.url.trim_end_matches('/');
+        match url.strip_suffix("/simple").or_else(|| url.strip_suffix("/+simple")) {
+            Some(root) => format!("{}/pypi", root),
+            None => url.to_string(),
+        }
+    }
 }
 
 /// Index manager for handling multiple PyPI indexes
+#[derive(Debug, Clone)]
 pub struct IndexManager {
     /// Primary index (default PyPI)
     primary: IndexConfig,
     /// Additional indexes
     secondary: Vec<IndexConfig>,
+    /// `--find-links` directories and pages, in order
+    find_links: Vec<String>,
+    /// `--no-index`: look packages up in `find_links` only
+    no_index: bool,
 }
 
 impl IndexManager {
@@ -35,11 +94,71 @@ impl IndexManager {
                 priority: 0,
                 default: true,
                 token: None,
+                simple_only: false,
             },
             secondary: Vec::new(),
+            find_links: Vec::new(),
+            no_index: false,
         }
     }
 
+    /// `index-url` first, then each `extra-index-url` in order
+    pub fn from_urls(index_url: &str, extra_index_urls: &[String]) -> Self {
+        let mut manager = Self::new();
+        let primary = IndexConfig {
+            name: "pypi".to_string(),
+            url: index_url.to_string(),
+            priority: 0,
+            default: true,
+            token: None,
+            simple_only: false,
+        };
+        if !primary.is_pypi() {
+            manager.primary = IndexConfig {
+                name: "primary".to_string(),
+                ..primary
+            };
+        }
+        for (i, url) in extra_index_urls.iter().enumerate() {
+            let priority = i as u32 + 1;
+            let _ = manager.add_index(IndexConfig {
+                name: format!("extra-{}", priority),
+                url: url.clone(),
+                priority,
+                default: false,
+                token: None,
+                simple_only: false,
+            });
+        }
+        manager
+    }
+
+    /// The indexes a configuration names
+    pub fn from_config(config: &crate::config::config::Config) -> Self {
+        let mut manager = Self::from_urls(config.index_url(), config.extra_index_urls());
+        manager.find_links = config.find_links().to_vec();
+        manager.no_index = config.no_index();
+        if config.simple_api_only() {
+            manager.primary.simple_only = true;
+            manager.secondary.iter_mut().for_each(|index| index.simple_only = true);
+        }
+        for named in config.named_indexes() {
+            let index = std::iter::once(&mut manager.primary)
+                .chain(manager.secondary.iter_mut())
+                .find(|index| match &named.url {
+                    Some(url) => same_index_url(&index.url, url),
+                    None => index.name == named.name,
+                });
+            if let Some(index) = index {
+                index.name = named.name.clone();
+                if named.token.is_some() {
+                    index.token = named.token.clone();
+                }
+            }
+        }
+        manager
+    }
+
     /// Add a secondary index
     pub fn add_index(&mut self, mut index: IndexConfig) -> Result<()> {
         if index.url.is_empty() {
@@ -65,6 +184,17 @@ impl IndexManager {
         indexes
     }
 
+    /// `--find-links` locations, looked in after the indexes (or instead
+    /// of them with `--no-index`)
+    pub fn find_links(&self) -> &[String] {
+        &self.find_links
+    }
+
+    /// Whether the indexes are skipped entirely
+    pub fn no_index(&self) -> bool {
+        self.no_index
+    }
+
     /// Get primary index
     pub fn get_primary(&self) -> &IndexConfig {
         &self.primary
@@ -92,54 +222,42 @@ impl IndexManager {
         format!("{}{}/", index.url.trim_end_matches('/'), package_name)
     }
 
-    /// Try to fetch from multiple indexes with fallback
-    pub async fn fetch_with_fallback<F, T>(
-        &self,
-        package_name: &str,
-        fetch_fn: F,
-    ) -> Result<T>
+    /// Try each index in priority order; the first success wins and is
+    /// returned with the index that served it. When every index fails, the
+    /// error lists what each one said, or is a [`ProjectNotFound`] if they
+    /// all answered 404.
+    pub async fn fetch_with_fallback<F, T>(&self, package_name: &str, fetch_fn: F) -> Result<(T, IndexConfig)>
     where
-        F: Fn(&str) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<T>>>>,
+        F: Fn(&IndexConfig) -> BoxFuture<'static, Result<T>>,
     {
         let indexes = self.get_all_indexes();
+        let mut failures = Vec::new();
+        let mut all_missing = true;
 
-        for (i, index) in indexes.iter().enumerate() {
-            let url = self.get_package_url(index, package_name);
-            
-            match fetch_fn(&url).await {
+        for index in &indexes {
+            match fetch_fn(index).await {
                 Ok(result) => {
-                    tracing::info!(
-                        "Successfully fetched {} from index: {}",
-                        package_name,
-                        index.name
-                    );
-                    return Ok(result);
+                    tracing::debug!("Fetched {} from index: {}", package_name, index.name);
+                    return Ok((result, index.clone()));
                 }
                 Err(e) => {
-                    if i < indexes.len() - 1 {
-                        tracing::warn!(
-                            "Failed to fetch {} from {}: {}. Trying next index...",
-                            package_name,
-                            index.name,
-                            e
-                        );
-                    } else {
-                        tracing::error!(
-                            "Failed to fetch {} from all indexes. Last error: {}",
-                            package_name,
-                            e
-                        );
-                        return Err(anyhow!(
-                            "Failed to fetch {} from any index: {}",
-                            package_name,
-                            e
-                        ));
-                    }
+                    tracing::debug!("Failed to fetch {} from {}: {}", package_name, index.name, e);
+                    all_missing &= is_not_found(&e);
+                    failures.push(if indexes.len() > 1 { format!("{}: {}", index.name, e) } else { e.to_string() });
                 }
             }
         }
 
-        Err(anyhow!("No indexes available"))
+        match failures.len() {
+            0 => Err(anyhow!("No indexes available")),
+            _ if all_missing => Err(ProjectNotFound {
+                package: package_name.to_string(),
+                indexes: indexes.iter().map(|index| index.name.clone()).collect(),
+            }
+            .into()),
+            1 => Err(anyhow!(failures.remove(0))),
+            _ => Err(anyhow!("Failed to fetch {} from any index ({})", package_name, failures.join("; "))),
+        }
     }
 }
 
@@ -180,6 +298,7 @@ pub fn parse_index_config(content: &str) -> Result<Vec<IndexConfig>> {
                     priority: 0,
                     default: true,
                     token: None,
+                    simple_only: false,
                 });
             } else if current_section == "index-servers" && key == "extra-index-url" {
                 let priority = (indexes.len() as u32) + 1;
@@ -189,6 +308,7 @@ pub fn parse_index_config(content: &str) -> Result<Vec<IndexConfig>> {
                     priority,
                     default: false,
                     token: None,
+                    simple_only: false,
                 });
             }
         }
@@ -217,6 +337,7 @@ mod tests {
             priority: 1,
             default: false,
             token: None,
+            simple_only: false,
         };
         manager.add_index(index).unwrap();
         assert_eq!(manager.secondary.len(), 1);
@@ -231,6 +352,7 @@ mod tests {
             priority: 1,
             default: false,
             token: None,
+            simple_only: false,
         };
         manager.add_index(index).unwrap();
         let all = manager.get_all_indexes();
@@ -246,6 +368,7 @@ mod tests {
             priority: 1,
             default: false,
             token: None,
+            simple_only: false,
         };
         manager.add_index(index).unwrap();
         assert!(manager.find_index("test").is_some());
@@ -272,6 +395,59 @@ extra-index-url = https://test.example.com/simple
         assert_eq!(indexes.len(), 2);
     }
 
+    #[test]
+    fn test_from_urls_and_json_api() {
+        let manager = IndexManager::from_urls(
+            "https://nexus.acme.internal/repository/pypi-group/simple/",
+            &["https://pypi.org/simple".to_string()],
+        );
+        let all = manager.get_all_indexes();
+        let names: Vec<&str> = all.iter().map(|index| index.name.as_str()).collect();
+        assert_eq!(names, ["primary", "extra-1"]);
+        assert_eq!(all[0].json_api_url(), "https://nexus.acme.internal/repository/pypi-group/pypi");
+        assert_eq!(all[1].json_api_url(), "https://pypi.org/pypi");
+        assert!(all[1].is_pypi() && !all[0].is_pypi());
+        assert_eq!(IndexManager::from_urls("https://pypi.org/simple/", &[]).get_primary().name, "pypi");
+    }
+
+    #[tokio::test]
+    async fn test_fetch_with_fallback() {
+        let manager = IndexManager::from_urls("https://private.example/simple", &["https://pypi.org/simple".to_string()]);
+        let (found, index) = manager
+            .fetch_with_fallback("requests", |index| {
+                let pypi = index.is_pypi();
+                Box::pin(async move { if pypi { Ok("2.31.0") } else { Err(anyhow!("Client error: 404 Not Found")) } })
+            })
+            .await
+            .unwrap();
+        assert_eq!((found, index.name.as_str()), ("2.31.0", "extra-1"));
+
+        let error = manager
+            .fetch_with_fallback("nope", |_| Box::pin(async { Err::<(), _>(anyhow!("Client error: 404 Not Found")) }))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Failed to fetch nope from any index (primary: Client error: 404 Not Found; extra-1: Client error: 404 Not Found)"
+        );
+
+        // A typed 404 from every index: the project doesn't exist
+        let error = manager
+            .fetch_with_fallback("reqests", |_| {
+                Box::pin(async {
+                    let not_found = crate::network::NetworkError::status("https://x/", reqwest::StatusCode::NOT_FOUND);
+                    Err::<(), _>(not_found.into())
+                })
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<ProjectNotFound>(),
+            Some(&ProjectNotFound { package: "reqests".to_string(), indexes: vec!["primary".into(), "extra-1".into()] })
+        );
+        assert_eq!(error.to_string(), "package reqests not found on any index (primary, extra-1)");
+    }
+
     #[test]
     fn test_url_normalization() {
         let mut manager = IndexManager::new();
@@ -281,6 +457,7 @@ extra-index-url = https://test.example.com/simple
             priority: 1,
             default: false,
             token: None,
+            simple_only: false,
         };
         manager.add_index(index).unwrap();
         let added = manager.find_index("test").unwrap();
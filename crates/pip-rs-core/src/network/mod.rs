@@ -1,23 +1,60 @@
 /// Network operations and PyPI communication
 pub mod pypi;
 pub mod client;
+pub mod error;
 pub mod index;
 pub mod lazy_client;
+pub mod resume;
+pub mod search;
+pub mod artifact;
+pub mod auth;
+pub mod find_links;
+pub mod proxy;
+pub mod selection;
+pub mod simple_api;
+pub mod throttle;
+pub mod trusted;
 
 pub use pypi::*;
-pub use client::PackageClient;
+pub use client::{CachePolicy, NetworkSettings, PackageClient, is_offline};
+pub use error::{NetworkError, NetworkErrorKind};
 pub use lazy_client::get_client;
+pub use selection::{FallbackPolicy, SelectionTrace};
 
-use once_cell::sync::Lazy;
+use crate::config::config::Config;
+use once_cell::sync::{Lazy, OnceCell};
+use std::sync::RwLock;
+
+/// Configuration the global client is built from, set by [`init_global_client`]
+static CLIENT_CONFIG: OnceCell<Config> = OnceCell::new();
 
 /// Global HTTP client for all PyPI requests - lazily initialized
 /// This avoids startup overhead when commands don't need network access
-static GLOBAL_CLIENT: Lazy<client::PackageClient> = Lazy::new(client::PackageClient::new);
+static GLOBAL_CLIENT: Lazy<client::PackageClient> = Lazy::new(|| client::create_client_with_config(client_config()));
+
+/// Indexes metadata lookups try, in order; from the configuration until a
+/// command applies its own `--index-url`/`--extra-index-url`
+static INDEXES: Lazy<RwLock<index::IndexManager>> =
+    Lazy::new(|| RwLock::new(index::IndexManager::from_config(crate::config::config::Config::global())));
 
 /// Global package cache - lazily initialized
 static PACKAGE_CACHE: Lazy<std::sync::Mutex<crate::cache::package_cache::PackageCache>> = 
     Lazy::new(|| std::sync::Mutex::new(crate::cache::package_cache::PackageCache::new().unwrap_or_default()));
 
+/// Build the global client, and clients made without a configuration at
+/// hand, from `config` (timeouts, retries, proxy, offline mode). Call once
+/// at startup, before any request; it has no effect once one was made.
+pub fn init_global_client(config: Config) {
+    if CLIENT_CONFIG.set(config).is_err() {
+        tracing::debug!("Network client already configured; settings not applied");
+    }
+}
+
+/// The configuration given to [`init_global_client`], else the global one
+pub(crate) fn client_config() -> &'static Config {
+    CLIENT_CONFIG.get_or_init(|| Config::global().clone())
+}
+
 /// Get the global package client (lazy initialization)
 pub fn global_client() -> &'static client::PackageClient {
     &GLOBAL_CLIENT
@@ -27,3 +64,18 @@ pub fn global_client() -> &'static client::PackageClient {
 pub fn global_cache() -> &'static std::sync::Mutex<crate::cache::package_cache::PackageCache> {
     &PACKAGE_CACHE
 }
+
+/// Look packages up on the indexes `config` names from now on
+pub fn configure_indexes(config: &crate::config::config::Config) {
+    let manager = index::IndexManager::from_config(config);
+    auth::shared().configure(manager.get_all_indexes());
+    trusted::shared().configure(config.trusted_hosts().to_vec());
+    if let Ok(mut indexes) = INDEXES.write() {
+        *indexes = manager;
+    }
+}
+
+/// The indexes metadata lookups currently try
+pub fn configured_indexes() -> index::IndexManager {
+    INDEXES.read().map(|indexes| indexes.clone()).unwrap_or_default()
+}
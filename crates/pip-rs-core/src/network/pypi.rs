@@ -1,96 +1,242 @@
 /// PyPI API interactions
+use super::client::CachePolicy;
+use super::index::IndexConfig;
 use crate::models::Package;
+use crate::resolver::requires_python::Release;
+use crate::utils::names::canonicalize_name;
+use crate::utils::tags;
 use anyhow::{Result, anyhow};
+use futures::future::BoxFuture;
+use once_cell::sync::Lazy;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
 
-pub async fn search_package(query: &str) -> Result<Vec<Package>> {
-    // Use PyPI JSON API to search for packages
-    // Note: PyPI deprecated the simple search API, so we use the JSON API
-    match super::GLOBAL_CLIENT.get_package_info(query).await {
-        Ok(response) => {
-            // Try to parse as a single package
-            if let Some(info) = response.get("info") {
-                let package = Package {
-                    name: info["name"].as_str().unwrap_or(query).to_string(),
-                    version: info["version"].as_str().unwrap_or("unknown").to_string(),
-                    summary: info["summary"].as_str().map(|s| s.to_string()),
-                    home_page: info["home_page"].as_str().map(|s| s.to_string()),
-                    author: info["author"].as_str().map(|s| s.to_string()),
-                    license: info["license"].as_str().map(|s| s.to_string()),
-                    requires_python: info["requires_python"].as_str().map(|s| s.to_string()),
-                    requires_dist: info["requires_dist"]
-                        .as_array()
-                        .map(|arr| {
-                            arr.iter()
-                                .filter_map(|v| v.as_str().map(|s| s.to_string()))
-                                .collect()
-                        })
-                        .unwrap_or_default(),
-                    classifiers: info["classifiers"]
-                        .as_array()
-                        .map(|arr| {
-                            arr.iter()
-                                .filter_map(|v| v.as_str().map(|s| s.to_string()))
-                                .collect()
-                        })
-                        .unwrap_or_default(),
-                };
-                return Ok(vec![package]);
-            }
-            Ok(Vec::new())
+/// Which index served each package's metadata, by canonical name
+static SERVED_BY: Lazy<Mutex<HashMap<String, IndexConfig>>> = Lazy::new(Default::default);
+
+/// JSON API document of a package from the first configured index that has it
+pub async fn package_info(package_name: &str) -> Result<serde_json::Value> {
+    package_info_with(package_name, CachePolicy::Default).await
+}
+
+/// JSON API document of one release, from the first index that has it
+pub async fn release_info(package_name: &str, version: &str) -> Result<serde_json::Value> {
+    let (name, release) = (package_name.to_string(), version.to_string());
+    from_indexes(package_name, Some(version), move |api| {
+        let (name, release) = (name.clone(), release.clone());
+        Box::pin(async move { Ok(super::GLOBAL_CLIENT.get_release_info_at(&api, &name, &release).await?) })
+    })
+    .await
+    // The project may well exist; only this release is missing
+    .map_err(|e| {
+        if !is_missing_project(&e) {
+            return e;
         }
-        Err(_) => {
-            // Package not found or network error
-            Ok(Vec::new())
+        anyhow!("{} {} not found on the configured indexes", package_name, version)
+    })
+}
+
+/// Whether a metadata lookup failed because no index has the project: all
+/// of them answered 404, or `--no-index` is set and no `--find-links`
+/// location has it
+pub fn is_missing_project(error: &anyhow::Error) -> bool {
+    error.is::<super::index::ProjectNotFound>() || error.is::<super::find_links::NotFound>()
+}
+
+/// Like [`package_info`], with the given use of the HTTP cache
+async fn package_info_with(package_name: &str, policy: CachePolicy) -> Result<serde_json::Value> {
+    let name = package_name.to_string();
+    from_indexes(package_name, None, move |api| {
+        let name = name.clone();
+        Box::pin(async move { Ok(super::GLOBAL_CLIENT.get_package_info_at(&api, &name, policy).await?) })
+    })
+    .await
+}
+
+/// The index that served `package_name`'s metadata in this run, if it was fetched
+pub fn served_by(package_name: &str) -> Option<IndexConfig> {
+    let served = SERVED_BY.lock().ok()?;
+    served.get(&canonicalize_name(package_name)).cloned()
+}
+
+/// Indexes found this run to have no JSON API, by URL; later lookups go
+/// straight to their simple API
+static SIMPLE_ONLY: Lazy<Mutex<HashSet<String>>> = Lazy::new(Default::default);
+
+/// Run `fetch` against the JSON API of each configured index in turn. An
+/// index without one (a 404 there, or configured simple-only) is read
+/// through its simple API instead, for `version` or the latest release.
+/// The `--find-links` locations are tried when no index has the package,
+/// and are the only place looked with `--no-index`.
+async fn from_indexes<F>(package_name: &str, version: Option<&str>, fetch: F) -> Result<serde_json::Value>
+where
+    F: Fn(String) -> BoxFuture<'static, Result<serde_json::Value>>,
+{
+    let indexes = super::configured_indexes();
+    let find_links = indexes.find_links();
+    if indexes.no_index() {
+        return super::find_links::document(&super::GLOBAL_CLIENT, find_links, package_name, version).await;
+    }
+    let found = from_network_indexes(&indexes, package_name, version, fetch).await;
+    match found {
+        Err(index_error) if !find_links.is_empty() => {
+            super::find_links::document(&super::GLOBAL_CLIENT, find_links, package_name, version)
+                .await
+                .map_err(|links_error| match links_error.downcast_ref::<super::find_links::NotFound>() {
+                    Some(_) => index_error,
+                    None => links_error,
+                })
         }
+        found => found,
     }
 }
 
-/// Find the best wheel URL for a package version
-pub async fn find_wheel_url(package_name: &str, version: &str) -> Result<String> {
-    let info = super::GLOBAL_CLIENT.get_package_info(package_name).await?;
-    
-    let urls = info["urls"]
-        .as_array()
-        .ok_or_else(|| anyhow!("No URLs found for package"))?;
-    
-    // Prefer pure Python wheels (py3-none-any), then platform-specific
-    for url_info in urls {
-        let filename = url_info["filename"]
-            .as_str()
-            .ok_or_else(|| anyhow!("No filename in URL info"))?;
-        
-        // Look for .whl files
-        if filename.ends_with(".whl") {
-            // Prefer pure Python wheels
-            if filename.contains("py3-none-any") {
-                let url = url_info["url"]
-                    .as_str()
-                    .ok_or_else(|| anyhow!("No URL in URL info"))?;
-                return Ok(url.to_string());
-            }
-        }
+async fn from_network_indexes<F>(
+    indexes: &super::index::IndexManager,
+    package_name: &str,
+    version: Option<&str>,
+    fetch: F,
+) -> Result<serde_json::Value>
+where
+    F: Fn(String) -> BoxFuture<'static, Result<serde_json::Value>>,
+{
+    let (json, index) = indexes
+        .fetch_with_fallback(package_name, |index| {
+            let simple_only = index.simple_only
+                || SIMPLE_ONLY.lock().is_ok_and(|known| known.contains(&index.url));
+            let lookup = (!simple_only).then(|| fetch(index.json_api_url()));
+            let (index, name, version) = (index.clone(), package_name.to_string(), version.map(String::from));
+            Box::pin(async move {
+                let json_error = match lookup {
+                    None => return super::simple_api::document(&super::GLOBAL_CLIENT, &index, &name, version.as_deref()).await,
+                    Some(lookup) => match lookup.await {
+                        Ok(json) => return Ok(json),
+                        Err(e) => e,
+                    },
+                };
+                // PyPI's JSON API is authoritative; elsewhere a 404 may only
+                // mean the index has no JSON API
+                if index.is_pypi() || !json_error.to_string().contains("404") {
+                    return Err(json_error);
+                }
+                match super::simple_api::document(&super::GLOBAL_CLIENT, &index, &name, version.as_deref()).await {
+                    Ok(json) => {
+                        tracing::debug!("index {} has no JSON API; reading its simple API", index.name);
+                        if let Ok(mut known) = SIMPLE_ONLY.lock() {
+                            known.insert(index.url.clone());
+                        }
+                        Ok(json)
+                    }
+                    Err(simple_error) if simple_error.to_string().contains("404") => Err(json_error),
+                    Err(simple_error) => Err(anyhow!("{} (simple API: {})", json_error, simple_error)),
+                }
+            })
+        })
+        .await?;
+    if let Ok(mut served) = SERVED_BY.lock() {
+        served.insert(canonicalize_name(package_name), index);
     }
-    
-    // If no pure Python wheel found, get any wheel
-    for url_info in urls {
-        let filename = url_info["filename"]
-            .as_str()
-            .ok_or_else(|| anyhow!("No filename in URL info"))?;
-        
-        if filename.ends_with(".whl") {
-            let url = url_info["url"]
-                .as_str()
-                .ok_or_else(|| anyhow!("No URL in URL info"))?;
-            return Ok(url.to_string());
-        }
+    Ok(json)
+}
+
+/// A file of a release, as listed in the JSON API's `urls`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReleaseFile {
+    pub filename: String,
+    pub url: String,
+    /// Bytes to download
+    pub size: Option<u64>,
+    /// `upload_time_iso_8601`
+    pub uploaded: Option<String>,
+    /// Hex sha256 from `digests`, when the index published one
+    pub sha256: Option<String>,
+}
+
+impl ReleaseFile {
+    /// Parse the `urls` array of a JSON API response
+    pub fn from_urls(urls: &serde_json::Value) -> Vec<Self> {
+        urls.as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|file| {
+                Some(Self {
+                    filename: file["filename"].as_str()?.to_string(),
+                    url: file["url"].as_str()?.to_string(),
+                    size: file["size"].as_u64(),
+                    uploaded: file["upload_time_iso_8601"]
+                        .as_str()
+                        .or_else(|| file["upload_time"].as_str())
+                        .map(String::from),
+                    sha256: file["digests"]["sha256"].as_str().map(String::from),
+                })
+            })
+            .collect()
     }
-    
-    Err(anyhow!("No wheel found for {} {}", package_name, version))
+
+    /// The wheel an install would pick: the one whose tags best fit the
+    /// running system
+    pub fn preferred_wheel(files: &[Self]) -> Option<&Self> {
+        let tags = tags::current();
+        files
+            .iter()
+            .filter_map(|f| Some((tags.rank(&f.filename)?, f)))
+            .min_by_key(|(rank, _)| *rank)
+            .map(|(_, f)| f)
+    }
+
+    pub fn is_wheel(&self) -> bool {
+        self.filename.ends_with(".whl")
+    }
+
+    /// A source distribution pip-rs can unpack
+    pub fn is_sdist(&self) -> bool {
+        self.filename.ends_with(".tar.gz") || self.filename.ends_with(".zip")
+    }
+}
+
+/// Files of the release the JSON API reports as current
+pub async fn get_release_files(package_name: &str) -> Result<Vec<ReleaseFile>> {
+    let info = package_info(package_name).await?;
+    Ok(ReleaseFile::from_urls(&info["urls"]))
+}
+
+/// Files of one release of a package
+async fn files_of_release(package_name: &str, version: &str) -> Result<Vec<ReleaseFile>> {
+    let info = package_info(package_name).await?;
+    // The current release's files are in `urls`; older ones only in `releases`
+    Ok(if info["info"]["version"].as_str() == Some(version) {
+        ReleaseFile::from_urls(&info["urls"])
+    } else {
+        ReleaseFile::from_urls(&info["releases"][version])
+    })
+}
+
+/// Find the best wheel URL for a package version
+pub async fn find_wheel_url(package_name: &str, version: &str) -> Result<String> {
+    let files = files_of_release(package_name, version).await?;
+    let trace = super::selection::SelectionTrace::new(package_name, version, files);
+    Ok(trace.best_wheel()?.url.clone())
+}
+
+/// The ranked installable files of a package version
+pub async fn release_selection(package_name: &str, version: &str) -> Result<super::selection::SelectionTrace> {
+    release_selection_for(package_name, version, tags::current()).await
+}
+
+/// The installable files of a package version, ranked for another
+/// environment's tags
+pub async fn release_selection_for(
+    package_name: &str,
+    version: &str,
+    tags: &tags::TagPriority,
+) -> Result<super::selection::SelectionTrace> {
+    let files = files_of_release(package_name, version).await?;
+    Ok(super::selection::SelectionTrace::for_tags(package_name, version, files, tags))
 }
 
 #[allow(dead_code)]
 pub async fn get_package_releases(package_name: &str) -> Result<Vec<String>> {
-    let info = super::GLOBAL_CLIENT.get_package_info(package_name).await?;
+    let info = package_info(package_name).await?;
     
     let releases = info["releases"]
         .as_object()
@@ -100,11 +246,11 @@ pub async fn get_package_releases(package_name: &str) -> Result<Vec<String>> {
     Ok(releases)
 }
 
-/// Get the latest version from PyPI, bypassing cache for fresh data
+/// Get the latest version from PyPI, revalidating any cached metadata
 /// This is used for outdated checking to ensure we get current versions
 pub async fn get_latest_version(package_name: &str) -> Result<String> {
-    // Use fresh request to bypass cache - critical for accurate outdated detection
-    let info = super::GLOBAL_CLIENT.get_package_info_fresh(package_name).await?;
+    // Always ask the index - critical for accurate outdated detection
+    let info = package_info_with(package_name, CachePolicy::Revalidate).await?;
     
     let version = info["info"]["version"]
         .as_str()
@@ -114,6 +260,55 @@ pub async fn get_latest_version(package_name: &str) -> Result<String> {
     Ok(version)
 }
 
+/// Every release of a package with its Requires-Python, from the JSON API
+pub async fn get_releases(package_name: &str) -> Result<Vec<Release>> {
+    let info = package_info(package_name).await?;
+    Ok(releases_from_json(&info))
+}
+
+/// The releases of a JSON API project document (`/pypi/<name>/json`)
+pub fn releases_from_json(info: &serde_json::Value) -> Vec<Release> {
+    info["releases"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(version, files)| {
+            // Releases without files can't be installed
+            let files = files.as_array().filter(|files| !files.is_empty())?;
+            Some(Release {
+                version: version.clone(),
+                requires_python: files
+                    .iter()
+                    .find_map(|file| file["requires_python"].as_str())
+                    .filter(|spec| !spec.trim().is_empty())
+                    .map(String::from),
+                yanked: files.iter().all(|file| file["yanked"].as_bool().unwrap_or(false)),
+                yanked_reason: files
+                    .iter()
+                    .find_map(|file| file["yanked_reason"].as_str())
+                    .filter(|reason| !reason.trim().is_empty())
+                    .map(String::from),
+            })
+        })
+        .collect()
+}
+
+/// Metadata of one specific release (not necessarily the latest)
+pub async fn get_release_metadata(package_name: &str, version: &str) -> Result<Package> {
+    if let Ok(cache) = super::PACKAGE_CACHE.lock()
+        && let Ok(Some(package)) = cache.get(package_name, version)
+    {
+        return Ok(package);
+    }
+
+    let info = release_info(package_name, version).await?;
+    let package = package_from_info(&info["info"], package_name, version);
+    if let Ok(cache) = super::PACKAGE_CACHE.lock() {
+        let _ = cache.set(&package);
+    }
+    Ok(package)
+}
+
 pub async fn get_package_metadata(package_name: &str, version: &str) -> Result<Package> {
     // Try to get from cache first
     if let Ok(cache) = super::PACKAGE_CACHE.lock() {
@@ -122,11 +317,20 @@ pub async fn get_package_metadata(package_name: &str, version: &str) -> Result<P
         }
     }
 
-    let info = super::GLOBAL_CLIENT.get_package_info(package_name).await?;
+    let info = package_info(package_name).await?;
+    let package = package_from_info(&info["info"], package_name, version);
     
-    let pkg_info = &info["info"];
+    // Save to cache
+    if let Ok(cache) = super::PACKAGE_CACHE.lock() {
+        let _ = cache.set(&package);
+    }
     
-    let package = Package {
+    Ok(package)
+}
+
+/// Build a `Package` from the `info` object of a JSON API response
+fn package_from_info(pkg_info: &serde_json::Value, package_name: &str, version: &str) -> Package {
+    Package {
         name: pkg_info["name"].as_str().unwrap_or(package_name).to_string(),
         version: pkg_info["version"].as_str().unwrap_or(version).to_string(),
         summary: pkg_info["summary"].as_str().map(|s| s.to_string()),
@@ -150,35 +354,26 @@ pub async fn get_package_metadata(package_name: &str, version: &str) -> Result<P
                     .collect()
             })
             .unwrap_or_default(),
-    };
-    
-    // Save to cache
-    if let Ok(cache) = super::PACKAGE_CACHE.lock() {
-        let _ = cache.set(&package);
     }
-    
-    Ok(package)
 }
 
 /// Batch fetch package metadata for multiple packages in parallel
-/// This improves performance by making concurrent requests
+/// This improves performance by making concurrent requests (bounded by
+/// the `--jobs` budget), returned in input order
 pub async fn batch_get_package_metadata(
     package_names: Vec<String>,
 ) -> Result<Vec<(String, Result<Package>)>> {
-    use futures::future::join_all;
-    
-    let futures: Vec<_> = package_names
-        .iter()
-        .map(|name| {
-            let name = name.clone();
-            async move {
-                let result = get_package_metadata(&name, "latest").await;
-                (name, result)
-            }
+    use futures::stream::{self, StreamExt};
+
+    let concurrency = crate::utils::resources::ResourceBudget::global().network;
+    let results = stream::iter(package_names)
+        .map(|name| async move {
+            let result = get_package_metadata(&name, "latest").await;
+            (name, result)
         })
-        .collect();
-    
-    let results = join_all(futures).await;
+        .buffered(concurrency)
+        .collect()
+        .await;
     Ok(results)
 }
 
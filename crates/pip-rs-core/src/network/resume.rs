@@ -0,0 +1,522 @@
+/// Resumable downloads backed by `.part` files kept across runs
+///
+/// Each `<file>.part` has a `<file>.part.json` sidecar recording the URL, the
+/// ETag/Last-Modified and total length of the response it came from, and the
+/// expected sha256 if known. A resume sends `Range` with `If-Range` set to the
+/// stored validator, so the server either continues (206) or sends the whole,
+/// changed file (200) and the partial data is dropped. When there is no
+/// validator to send, a 206 is only trusted if its length and headers still
+/// match the sidecar; otherwise the download restarts from scratch.
+///
+/// A finished file is only renamed into place once it passes the checks in
+/// [`super::artifact`]; a bad body is deleted rather than resumed from.
+use super::artifact::{self, InvalidArtifact};
+use super::auth::Credentials;
+use anyhow::{Result, anyhow};
+use reqwest::{Client, Response, StatusCode, header};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Extension of in-progress downloads
+pub const PART_EXTENSION: &str = "part";
+
+/// Bytes received between two progress reports
+const PROGRESS_INTERVAL: u64 = 1024 * 1024;
+
+/// Called as a download is written with the bytes on disk so far and the
+/// total, when the server said
+pub type Progress<'a> = dyn Fn(u64, Option<u64>) + Send + Sync + 'a;
+
+/// Partial downloads untouched for longer than this are considered orphaned
+pub const DEFAULT_PART_MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// The server answered with a non-success status
+#[derive(Debug)]
+pub struct HttpStatusError {
+    pub status: StatusCode,
+    /// Delay the server asked for before trying again
+    pub retry_after: Option<Duration>,
+}
+
+impl fmt::Display for HttpStatusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.status.is_client_error() {
+            write!(f, "Client error: {}", self.status)
+        } else {
+            write!(f, "Server error: {}", self.status)
+        }
+    }
+}
+
+impl std::error::Error for HttpStatusError {}
+
+/// Sidecar metadata describing the remote file a `.part` belongs to
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartMeta {
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_length: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+impl PartMeta {
+    fn from_response(url: &str, response: &Response, sha256: Option<&str>) -> Self {
+        let header_value = |name: header::HeaderName| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string())
+        };
+        let total_length = if response.status() == StatusCode::PARTIAL_CONTENT {
+            content_range(response).and_then(|(_, total)| total)
+        } else {
+            response.content_length()
+        };
+
+        Self {
+            url: url.to_string(),
+            etag: header_value(header::ETAG),
+            last_modified: header_value(header::LAST_MODIFIED),
+            total_length,
+            sha256: sha256.map(|s| s.to_lowercase()),
+        }
+    }
+
+    /// Validator for If-Range: a strong ETag, else Last-Modified
+    pub fn validator(&self) -> Option<&str> {
+        match &self.etag {
+            Some(etag) if !etag.starts_with("W/") => Some(etag),
+            _ => self.last_modified.as_deref(),
+        }
+    }
+
+    /// Whether a new response describes the same remote file. Nothing to
+    /// compare counts as a mismatch: we can't prove the file is unchanged.
+    pub fn same_file(&self, fresh: &PartMeta) -> bool {
+        let mut compared = false;
+        for (stored, new) in [(&self.etag, &fresh.etag), (&self.last_modified, &fresh.last_modified)] {
+            if let (Some(stored), Some(new)) = (stored, new) {
+                if stored != new {
+                    return false;
+                }
+                compared = true;
+            }
+        }
+        if let (Some(stored), Some(new)) = (self.total_length, fresh.total_length) {
+            if stored != new {
+                return false;
+            }
+            compared = true;
+        }
+        compared
+    }
+}
+
+/// Path of the in-progress file for `dest`
+pub fn part_path(dest: &Path) -> PathBuf {
+    let mut name = dest.as_os_str().to_os_string();
+    name.push(".");
+    name.push(PART_EXTENSION);
+    PathBuf::from(name)
+}
+
+/// Path of the sidecar for `dest`
+pub fn sidecar_path(dest: &Path) -> PathBuf {
+    let mut name = part_path(dest).into_os_string();
+    name.push(".json");
+    PathBuf::from(name)
+}
+
+/// Expected sha256 from a `#sha256=...` URL fragment (PEP 503 style)
+pub fn sha256_from_url(url: &str) -> Option<&str> {
+    let (_, fragment) = url.split_once('#')?;
+    fragment
+        .split('&')
+        .find_map(|part| part.strip_prefix("sha256="))
+        .filter(|hash| !hash.is_empty())
+}
+
+fn load_meta(dest: &Path) -> Option<PartMeta> {
+    let content = fs::read(sidecar_path(dest)).ok()?;
+    serde_json::from_slice(&content).ok()
+}
+
+fn save_meta(dest: &Path, meta: &PartMeta) -> Result<()> {
+    fs::write(sidecar_path(dest), serde_json::to_vec_pretty(meta)?)?;
+    Ok(())
+}
+
+fn discard(dest: &Path) {
+    let _ = fs::remove_file(part_path(dest));
+    let _ = fs::remove_file(sidecar_path(dest));
+}
+
+/// `Content-Range: bytes <start>-<end>/<total>` as (start, total)
+fn content_range(response: &Response) -> Option<(u64, Option<u64>)> {
+    let value = response.headers().get(header::CONTENT_RANGE)?.to_str().ok()?;
+    let range = value.strip_prefix("bytes ")?;
+    let (span, total) = range.split_once('/')?;
+    let start = span.split_once('-')?.0.trim().parse().ok()?;
+    Some((start, total.trim().parse().ok()))
+}
+
+/// A `.part` that can be continued: its sidecar and current length
+fn resumable_state(url: &str, dest: &Path, sha256: Option<&str>) -> Option<(PartMeta, u64)> {
+    let meta = load_meta(dest)?;
+    let offset = fs::metadata(part_path(dest)).ok()?.len();
+    let hash_matches = match (sha256, &meta.sha256) {
+        (Some(expected), Some(stored)) => expected.eq_ignore_ascii_case(stored),
+        _ => true,
+    };
+    (meta.url == url && hash_matches && offset > 0).then_some((meta, offset))
+}
+
+/// Download `url` to `dest`, continuing a `.part` left by an earlier run
+/// when the remote file is provably unchanged.
+///
+/// The body is written to the `.part` chunk by chunk, never held in memory
+/// whole. The partial file is kept if the transfer is interrupted, and
+/// discarded if the finished file fails validation. With `bypass_cache`, any
+/// partial file is dropped and intermediaries are asked for a fresh copy.
+pub async fn download_resumable(
+    client: &Client,
+    url: &str,
+    credentials: Option<&Credentials>,
+    dest: &Path,
+    sha256: Option<&str>,
+    bypass_cache: bool,
+    progress: Option<&Progress<'_>>,
+) -> Result<PathBuf> {
+    let part = part_path(dest);
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut allow_resume = !bypass_cache;
+    let mut response = loop {
+        let resume = if allow_resume { resumable_state(url, dest, sha256) } else { None };
+        if resume.is_none() {
+            discard(dest);
+        }
+
+        let mut request = client.get(url);
+        if let Some(credentials) = credentials {
+            request = credentials.apply(request);
+        }
+        if bypass_cache {
+            request = request.header(header::CACHE_CONTROL, "no-cache");
+        }
+        if let Some((meta, offset)) = &resume {
+            request = request.header(header::RANGE, format!("bytes={}-", offset));
+            if let Some(validator) = meta.validator() {
+                request = request.header(header::IF_RANGE, validator);
+            }
+        }
+        let response = request.send().await?;
+        let status = response.status();
+
+        match (status, resume) {
+            (StatusCode::PARTIAL_CONTENT, Some((meta, offset))) => {
+                let fresh = PartMeta::from_response(url, &response, sha256);
+                let start = content_range(&response).map(|(start, _)| start);
+                if start == Some(offset) && meta.same_file(&fresh) {
+                    tracing::debug!("Resuming {} at byte {}", url, offset);
+                    break response;
+                }
+                tracing::debug!("Remote file changed since {} was started; restarting", part.display());
+            }
+            (StatusCode::RANGE_NOT_SATISFIABLE, Some(_)) => {
+                tracing::debug!("Server rejected resume range for {}; restarting", url);
+            }
+            (StatusCode::OK, resume) => {
+                if resume.is_some() {
+                    tracing::debug!("Server sent the full file for {}; discarding partial data", url);
+                    discard(dest);
+                }
+                save_meta(dest, &PartMeta::from_response(url, &response, sha256))?;
+                File::create(&part)?;
+                break response;
+            }
+            (status, _) if !status.is_success() => {
+                let retry_after = super::throttle::retry_after(response.headers());
+                return Err(HttpStatusError { status, retry_after }.into());
+            }
+            (status, _) => return Err(anyhow!("Unexpected response {} for {}", status, url)),
+        }
+
+        // Only retry once, without a range
+        if !allow_resume {
+            return Err(anyhow!("Could not restart download of {}", url));
+        }
+        allow_resume = false;
+    };
+
+    let expected_length = load_meta(dest).and_then(|meta| meta.total_length);
+    let mut file = OpenOptions::new().append(true).open(&part)?;
+    let mut written = file.metadata()?.len();
+    let start = written;
+    let mut reported = None;
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| anyhow!("Failed to read response: {}", e))?
+    {
+        file.write_all(&chunk)?;
+        written += chunk.len() as u64;
+        if let Some(progress) = progress
+            && written - reported.unwrap_or(start) >= PROGRESS_INTERVAL
+        {
+            progress(written, expected_length);
+            reported = Some(written);
+        }
+    }
+    file.flush()?;
+    file.sync_all()?;
+    drop(file);
+    if let Some(progress) = progress
+        && reported != Some(written)
+    {
+        progress(written, expected_length);
+    }
+
+    let written = fs::metadata(&part)?.len();
+    if let Some(expected) = expected_length
+        && written != expected
+    {
+        // Keep the .part; unless the retry bypasses it, it continues from here
+        return Err(InvalidArtifact::truncated(url, written, expected).into());
+    }
+
+    if let Err(e) = artifact::check_file(url, &part, sha256) {
+        if e.is::<InvalidArtifact>() {
+            discard(dest);
+        }
+        return Err(e);
+    }
+
+    fs::rename(&part, dest)?;
+    let _ = fs::remove_file(sidecar_path(dest));
+    Ok(dest.to_path_buf())
+}
+
+/// Move a finished download to `dest`, copying it when a rename can't
+/// cross filesystems
+pub fn move_into_place(from: &Path, dest: &Path) -> Result<()> {
+    if fs::rename(from, dest).is_ok() {
+        return Ok(());
+    }
+    copy_into_place(from, dest)?;
+    let _ = fs::remove_file(from);
+    Ok(())
+}
+
+/// Copy `from` to `dest` through a temporary file beside `dest`, so `dest`
+/// only ever appears complete
+pub fn copy_into_place(from: &Path, dest: &Path) -> Result<()> {
+    let mut temp = dest.as_os_str().to_os_string();
+    temp.push(format!(".tmp-{}", std::process::id()));
+    let temp = PathBuf::from(temp);
+    let copied = fs::copy(from, &temp)
+        .and_then(|_| File::open(&temp)?.sync_all())
+        .and_then(|()| fs::rename(&temp, dest));
+    if let Err(e) = copied {
+        let _ = fs::remove_file(&temp);
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+/// Remove `.part` files (and their sidecars) not modified within `max_age`,
+/// plus sidecars whose `.part` is gone. Returns the number of files removed.
+pub fn purge_stale_parts(dir: &Path, max_age: Duration) -> Result<usize> {
+    if !dir.exists() {
+        return Ok(0);
+    }
+
+    let now = SystemTime::now();
+    let mut removed = 0;
+    for entry in fs::read_dir(dir)?.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+
+        if let Some(dest) = name.strip_suffix(".part.json") {
+            if !dir.join(format!("{}.{}", dest, PART_EXTENSION)).exists() {
+                fs::remove_file(&path)?;
+                removed += 1;
+            }
+            continue;
+        }
+
+        if path.extension().is_some_and(|ext| ext == PART_EXTENSION) {
+            let age = entry
+                .metadata()
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|modified| now.duration_since(modified).ok())
+                .unwrap_or_default();
+            if age >= max_age {
+                fs::remove_file(&path)?;
+                let _ = fs::remove_file(sidecar_path(&path.with_extension("")));
+                removed += 1;
+            }
+        }
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::{Digest, Sha256};
+    use tempfile::TempDir;
+
+    /// Just enough of a zip (local header magic, end of central directory) to pass validation
+    const BODY: &[u8] = b"PK\x03\x04payloadPK\x05\x06\0\0\0\0\0";
+
+    fn sha256_hex(data: &[u8]) -> String {
+        format!("{:x}", Sha256::digest(data))
+    }
+
+    /// A `.part` holding the first 8 bytes of BODY, as left by an interrupted run
+    fn seed_partial(dest: &Path, meta: PartMeta) {
+        fs::write(part_path(dest), &BODY[..8]).unwrap();
+        save_meta(dest, &meta).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_resume_with_matching_validator() {
+        let mut server = mockito::Server::new_async().await;
+        let url = format!("{}/pkg-1.0-py3-none-any.whl", server.url());
+        let ranged = server
+            .mock("GET", "/pkg-1.0-py3-none-any.whl")
+            .match_header("range", "bytes=8-")
+            .match_header("if-range", "\"v1\"")
+            .with_status(206)
+            .with_header("etag", "\"v1\"")
+            .with_header("content-range", "bytes 8-19/20")
+            .with_body(&BODY[8..])
+            .create_async()
+            .await;
+
+        let temp = TempDir::new().unwrap();
+        let dest = temp.path().join("pkg.whl");
+        seed_partial(
+            &dest,
+            PartMeta {
+                url: url.clone(),
+                etag: Some("\"v1\"".to_string()),
+                total_length: Some(20),
+                ..Default::default()
+            },
+        );
+
+        let hash = sha256_hex(BODY);
+        download_resumable(&Client::new(), &url, None, &dest, Some(&hash), false, None).await.unwrap();
+
+        ranged.assert_async().await;
+        assert_eq!(fs::read(&dest).unwrap(), BODY);
+        assert!(!part_path(&dest).exists());
+        assert!(!sidecar_path(&dest).exists());
+    }
+
+    #[tokio::test]
+    async fn test_changed_etag_restarts() {
+        let new_body = b"PK\x03\x04completely different filePK\x05\x06";
+        let mut server = mockito::Server::new_async().await;
+        let url = format!("{}/pkg.whl", server.url());
+        // If-Range didn't match: the server sends the whole new file
+        let full = server
+            .mock("GET", "/pkg.whl")
+            .match_header("if-range", "\"v1\"")
+            .with_status(200)
+            .with_header("etag", "\"v2\"")
+            .with_body(new_body)
+            .create_async()
+            .await;
+
+        let temp = TempDir::new().unwrap();
+        let dest = temp.path().join("pkg.whl");
+        seed_partial(
+            &dest,
+            PartMeta {
+                url: url.clone(),
+                etag: Some("\"v1\"".to_string()),
+                total_length: Some(20),
+                ..Default::default()
+            },
+        );
+
+        download_resumable(&Client::new(), &url, None, &dest, None, false, None).await.unwrap();
+
+        full.assert_async().await;
+        assert_eq!(fs::read(&dest).unwrap(), new_body);
+    }
+
+    #[tokio::test]
+    async fn test_no_validator_falls_back_to_length_check() {
+        let mut server = mockito::Server::new_async().await;
+        let url = format!("{}/pkg.whl", server.url());
+        // No validator stored: the server happily continues a range of a
+        // file whose length changed, so we must notice and restart.
+        let ranged = server
+            .mock("GET", "/pkg.whl")
+            .match_header("range", "bytes=8-")
+            .with_status(206)
+            .with_header("content-range", "bytes 8-29/30")
+            .with_body("x".repeat(22))
+            .create_async()
+            .await;
+        let full = server
+            .mock("GET", "/pkg.whl")
+            .match_header("range", mockito::Matcher::Missing)
+            .with_status(200)
+            .with_body(BODY)
+            .create_async()
+            .await;
+
+        let temp = TempDir::new().unwrap();
+        let dest = temp.path().join("pkg.whl");
+        seed_partial(
+            &dest,
+            PartMeta {
+                url: url.clone(),
+                total_length: Some(20),
+                ..Default::default()
+            },
+        );
+
+        download_resumable(&Client::new(), &url, None, &dest, None, false, None).await.unwrap();
+
+        ranged.assert_async().await;
+        full.assert_async().await;
+        assert_eq!(fs::read(&dest).unwrap(), BODY);
+    }
+
+    #[test]
+    fn test_purge_stale_parts() {
+        let temp = TempDir::new().unwrap();
+        let dest = temp.path().join("old.whl");
+        seed_partial(&dest, PartMeta::default());
+        fs::write(temp.path().join("orphan.whl.part.json"), "{}").unwrap();
+        fs::write(temp.path().join("done.whl"), BODY).unwrap();
+
+        // Nothing is old enough yet; only the orphaned sidecar goes
+        assert_eq!(purge_stale_parts(temp.path(), DEFAULT_PART_MAX_AGE).unwrap(), 1);
+        assert!(part_path(&dest).exists());
+
+        assert_eq!(purge_stale_parts(temp.path(), Duration::ZERO).unwrap(), 1);
+        assert!(!part_path(&dest).exists());
+        assert!(!sidecar_path(&dest).exists());
+        assert!(temp.path().join("done.whl").exists());
+    }
+}
@@ -0,0 +1,290 @@
+/// Package search over the indexes' project lists
+///
+/// PyPI's XML-RPC search endpoint is gone and the simple API has no query
+/// parameter, but every PEP 503 index serves its root page: the names of
+/// all its projects. That list is fetched at most once a day per index and
+/// kept under `<cache>/indexes/`, names are matched locally, and the hits
+/// shown get their latest version and summary from the JSON API. Exact
+/// name matches rank first, then prefix and then substring matches, shorter
+/// names first within each; names compare in their normalized form, so
+/// `zope_interface` finds `zope.interface`.
+use super::client::PackageClient;
+use super::index::IndexConfig;
+use super::simple_api;
+use crate::cache::layout;
+use crate::utils::names::canonicalize_name;
+use crate::utils::redact::redact_url_credentials;
+use anyhow::Result;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// How long a cached project list is used before it is fetched again
+pub const PROJECT_LIST_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// JSON preferred: PyPI's HTML list is several times larger
+const ACCEPT: &str = "application/vnd.pypi.simple.v1+json, text/html;q=0.1";
+
+/// How a name matched the query; variants are in rank order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchKind {
+    Exact,
+    Prefix,
+    Substring,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SearchHit {
+    pub name: String,
+    #[serde(rename = "match")]
+    pub kind: MatchKind,
+    /// Latest version, when details were fetched and the index has them
+    pub version: Option<String>,
+    pub summary: Option<String>,
+}
+
+/// No configured index would list its projects
+#[derive(Debug)]
+pub struct NotEnumerable {
+    /// (index URL, why its list couldn't be read)
+    pub failures: Vec<(String, String)>,
+}
+
+impl std::fmt::Display for NotEnumerable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.failures.is_empty() {
+            return write!(f, "search needs a package index, but --no-index is set");
+        }
+        let failures: Vec<String> =
+            self.failures.iter().map(|(index, reason)| format!("{}: {}", index, reason)).collect();
+        write!(
+            f,
+            "could not read the project list of any index ({}); search needs an index that serves \
+             the list of all its projects (the PEP 503 root page)",
+            failures.join("; ")
+        )
+    }
+}
+
+impl std::error::Error for NotEnumerable {}
+
+/// Up to `limit` of `names` matching `query`, best first
+pub fn rank(names: &[String], query: &str, limit: usize) -> Vec<SearchHit> {
+    let query = canonicalize_name(query);
+    let mut seen = HashSet::new();
+    let mut hits: Vec<(MatchKind, usize, String, &String)> = names
+        .iter()
+        .filter_map(|name| {
+            let canonical = canonicalize_name(name);
+            let kind = if canonical == query {
+                MatchKind::Exact
+            } else if canonical.starts_with(&query) {
+                MatchKind::Prefix
+            } else if canonical.contains(&query) {
+                MatchKind::Substring
+            } else {
+                return None;
+            };
+            Some((kind, canonical.len(), canonical, name))
+        })
+        .filter(|(_, _, canonical, _)| seen.insert(canonical.clone()))
+        .collect();
+    hits.sort();
+    hits.into_iter()
+        .take(limit)
+        .map(|(kind, _, _, name)| SearchHit {
+            name: name.clone(),
+            kind,
+            version: None,
+            summary: None,
+        })
+        .collect()
+}
+
+/// Search the configured indexes for `query`; with `details`, each hit's
+/// latest version and summary are looked up (bounded by `--jobs`)
+pub async fn search(query: &str, limit: usize, details: bool) -> Result<Vec<SearchHit>> {
+    let indexes = super::configured_indexes();
+    let client = super::global_client();
+    let cache_dir = layout::cache_root().map(|root| root.join(layout::INDEXES_DIR));
+
+    let mut names = Vec::new();
+    let mut failures = Vec::new();
+    if !indexes.no_index() {
+        for index in indexes.get_all_indexes() {
+            match project_names(client, &index, cache_dir.as_deref(), PROJECT_LIST_TTL).await {
+                Ok(found) => names.extend(found),
+                Err(e) => failures.push((redact_url_credentials(&index.url), e.to_string())),
+            }
+        }
+    }
+    if names.is_empty() && (indexes.no_index() || !failures.is_empty()) {
+        return Err(NotEnumerable { failures }.into());
+    }
+
+    let mut hits = rank(&names, query, limit);
+    if details {
+        fill_details(&mut hits).await;
+    }
+    Ok(hits)
+}
+
+/// Latest version and summary of each hit from the JSON API; a hit whose
+/// lookup fails keeps neither
+async fn fill_details(hits: &mut [SearchHit]) {
+    use futures::stream::{self, StreamExt};
+
+    let concurrency = crate::utils::resources::ResourceBudget::global().network;
+    let documents: Vec<_> = stream::iter(hits.iter().map(|hit| hit.name.clone()))
+        .map(|name| async move { super::package_info(&name).await })
+        .buffered(concurrency)
+        .collect()
+        .await;
+    for (hit, document) in hits.iter_mut().zip(documents) {
+        let Ok(document) = document else {
+            continue;
+        };
+        let info = &document["info"];
+        hit.version = info["version"].as_str().map(String::from);
+        hit.summary = info["summary"].as_str().filter(|s| !s.is_empty()).map(String::from);
+    }
+}
+
+/// Every project name `index` lists, from `cache_dir` while younger than
+/// `ttl`. A stale list is still used when the index can't be reached.
+pub async fn project_names(
+    client: &PackageClient,
+    index: &IndexConfig,
+    cache_dir: Option<&Path>,
+    ttl: Duration,
+) -> Result<Vec<String>> {
+    let cached = cache_dir.map(|dir| cache_file(dir, &index.url));
+    let age = cached.as_deref().and_then(|path| {
+        let modified = fs::metadata(path).and_then(|m| m.modified()).ok()?;
+        Some(SystemTime::now().duration_since(modified).unwrap_or_default())
+    });
+    if let (Some(path), Some(age)) = (&cached, age)
+        && age < ttl
+        && let Some(names) = read_list(path)
+    {
+        tracing::debug!("Using cached project list of {}", index.url);
+        return Ok(names);
+    }
+
+    let fetched = client.fetch_negotiated(&index.url, ACCEPT).await.map_err(anyhow::Error::from).and_then(
+        |(content_type, body)| simple_api::parse_project_list(content_type.as_deref(), &body),
+    );
+    let names = match fetched {
+        Ok(names) if !names.is_empty() => names,
+        Ok(_) => return Err(anyhow::anyhow!("its root page lists no projects")),
+        Err(e) => {
+            if let Some(names) = cached.as_deref().and_then(read_list) {
+                tracing::debug!("Using stale project list of {}: {}", index.url, e);
+                return Ok(names);
+            }
+            return Err(e);
+        }
+    };
+    if let Some(path) = &cached
+        && let Err(e) = write_list(path, &names)
+    {
+        tracing::debug!("Could not cache project list of {}: {}", index.url, e);
+    }
+    Ok(names)
+}
+
+/// `<cache_dir>/<hash of the index URL>.txt`
+fn cache_file(cache_dir: &Path, index_url: &str) -> PathBuf {
+    let digest = format!("{:x}", Sha256::digest(index_url.trim_end_matches('/').as_bytes()));
+    cache_dir.join(format!("{}.txt", &digest[..32]))
+}
+
+fn read_list(path: &Path) -> Option<Vec<String>> {
+    let content = fs::read_to_string(path).ok()?;
+    let names: Vec<String> = content.lines().filter(|line| !line.is_empty()).map(String::from).collect();
+    (!names.is_empty()).then_some(names)
+}
+
+/// Written beside the target and renamed, so a concurrent search never
+/// reads half a list
+fn write_list(path: &Path, names: &[String]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let temp = path.with_extension(format!("tmp-{}", std::process::id()));
+    fs::write(&temp, names.join("\n") + "\n")?;
+    if let Err(e) = fs::rename(&temp, path) {
+        let _ = fs::remove_file(&temp);
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(list: &[&str]) -> Vec<String> {
+        list.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_rank_exact_then_prefix_then_substring() {
+        let all = names(&["flask-login", "Flask", "pytest-flask", "flask_cors", "django", "FLASK"]);
+        let hits = rank(&all, "flask", 10);
+        let ranked: Vec<(&str, MatchKind)> = hits.iter().map(|hit| (hit.name.as_str(), hit.kind)).collect();
+        assert_eq!(
+            ranked,
+            [
+                ("Flask", MatchKind::Exact),
+                ("flask_cors", MatchKind::Prefix),
+                ("flask-login", MatchKind::Prefix),
+                ("pytest-flask", MatchKind::Substring),
+            ]
+        );
+        assert_eq!(rank(&all, "flask", 2).len(), 2);
+        assert_eq!(rank(&names(&["zope.interface"]), "Zope_Interface", 5)[0].kind, MatchKind::Exact);
+    }
+
+    #[tokio::test]
+    async fn test_project_list_is_cached_and_stale_list_used_offline() {
+        let mut server = mockito::Server::new_async().await;
+        let page = server
+            .mock("GET", "/simple/")
+            .with_header("content-type", simple_api::SIMPLE_JSON)
+            .with_body(r#"{"meta": {"api-version": "1.0"}, "projects": [{"name": "demo-pkg"}, {"name": "other"}]}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let temp = tempfile::tempdir().unwrap();
+        let index = IndexConfig {
+            name: "private".to_string(),
+            url: format!("{}/simple/", server.url()),
+            priority: 0,
+            default: true,
+            token: None,
+            simple_only: true,
+        };
+        let client = PackageClient::new();
+
+        let first = project_names(&client, &index, Some(temp.path()), PROJECT_LIST_TTL).await.unwrap();
+        assert_eq!(first, ["demo-pkg", "other"]);
+        let second = project_names(&client, &index, Some(temp.path()), PROJECT_LIST_TTL).await.unwrap();
+        assert_eq!(second, first);
+        page.assert_async().await;
+
+        // Expired, and the index is gone: the old list still answers
+        let offline = IndexConfig {
+            url: "http://127.0.0.1:9/simple/".to_string(),
+            ..index.clone()
+        };
+        fs::copy(cache_file(temp.path(), &index.url), cache_file(temp.path(), &offline.url)).unwrap();
+        let stale = project_names(&client, &offline, Some(temp.path()), Duration::ZERO).await.unwrap();
+        assert_eq!(stale, first);
+        assert!(project_names(&client, &offline, None, PROJECT_LIST_TTL).await.is_err());
+    }
+}
@@ -0,0 +1,210 @@
+/// Which file of a release gets installed, and which one is next in line
+///
+/// A release's files are ranked the way an install picks them: wheels by
+/// how well their tags fit the target environment, then sdists; wheels for
+/// other platforms or interpreters are set aside. The ranking is kept as a
+/// [`SelectionTrace`] so that when the chosen wheel turns out to be broken
+/// the installer can ask for the next candidate the install options allow.
+use super::pypi::ReleaseFile;
+use crate::errors::PipError;
+use crate::utils::tags::{self, TagPriority};
+
+/// What an alternate artifact must satisfy to be installed instead
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FallbackPolicy<'a> {
+    /// `--only-binary` covers the package: sdists are not eligible
+    pub only_binary: bool,
+    /// Hash-checking mode: only files whose published sha256 is one of
+    /// these are eligible
+    pub pinned_hashes: Option<&'a [String]>,
+}
+
+/// The installable files of one release, best first
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectionTrace {
+    pub package: String,
+    pub version: String,
+    pub candidates: Vec<ReleaseFile>,
+    /// Wheels none of whose tags the target supports
+    pub incompatible: Vec<String>,
+    /// The tags that were tried, summarized for errors
+    considered: String,
+}
+
+impl SelectionTrace {
+    /// Rank `files` for the running system
+    pub fn new(package: &str, version: &str, files: Vec<ReleaseFile>) -> Self {
+        Self::for_tags(package, version, files, tags::current())
+    }
+
+    /// Rank `files` for the environment `tags` describes
+    pub fn for_tags(package: &str, version: &str, files: Vec<ReleaseFile>, tags: &TagPriority) -> Self {
+        let mut ranked = Vec::new();
+        let mut incompatible = Vec::new();
+        for file in files {
+            if file.is_wheel() {
+                match tags.rank(&file.filename) {
+                    Some(rank) => ranked.push((rank, file)),
+                    None => incompatible.push(file.filename),
+                }
+            } else if file.is_sdist() {
+                ranked.push((usize::MAX, file));
+            }
+        }
+        // Stable, so the index's order decides between equals
+        ranked.sort_by_key(|(rank, _)| *rank);
+        Self {
+            package: package.to_string(),
+            version: version.to_string(),
+            candidates: ranked.into_iter().map(|(_, file)| file).collect(),
+            incompatible,
+            considered: tags.summary(),
+        }
+    }
+
+    /// The file an install starts with: the best-fitting wheel, else an
+    /// sdist when `policy` allows building one
+    pub fn best(&self, policy: &FallbackPolicy) -> Result<&ReleaseFile, PipError> {
+        match self.candidates.first() {
+            Some(file) if file.is_wheel() || !policy.only_binary => Ok(file),
+            _ => Err(self.no_compatible_wheel()),
+        }
+    }
+
+    /// The best-fitting wheel, for callers that can't build sdists
+    pub fn best_wheel(&self) -> Result<&ReleaseFile, PipError> {
+        self.candidates
+            .first()
+            .filter(|file| file.is_wheel())
+            .ok_or_else(|| self.no_compatible_wheel())
+    }
+
+    fn no_compatible_wheel(&self) -> PipError {
+        PipError::NoCompatibleWheel {
+            package: self.package.clone(),
+            version: self.version.clone(),
+            available: self.incompatible.clone(),
+            considered: self.considered.clone(),
+        }
+    }
+
+    /// The highest-ranked candidate other than `failed` that `policy`
+    /// allows, or why there is none
+    pub fn alternate(&self, failed: &ReleaseFile, policy: &FallbackPolicy) -> Result<&ReleaseFile, String> {
+        let mut refused = Vec::new();
+        for file in self.candidates.iter().filter(|file| file.filename != failed.filename) {
+            match ineligible(file, policy) {
+                None => return Ok(file),
+                Some(reason) => refused.push(format!("{} ({})", file.filename, reason)),
+            }
+        }
+        if refused.is_empty() {
+            Err(format!("{} {} has no other compatible artifact", self.package, self.version))
+        } else {
+            Err(format!("no eligible alternate: {}", refused.join(", ")))
+        }
+    }
+}
+
+fn ineligible(file: &ReleaseFile, policy: &FallbackPolicy) -> Option<&'static str> {
+    if policy.only_binary && !file.is_wheel() {
+        return Some("--only-binary excludes sdists");
+    }
+    let pinned = policy.pinned_hashes?;
+    let recorded = file.sha256.as_deref().is_some_and(|sha256| {
+        pinned
+            .iter()
+            .any(|hash| hash.strip_prefix("sha256:").is_some_and(|pin| pin.eq_ignore_ascii_case(sha256)))
+    });
+    (!recorded).then_some("hash-checking mode requires a recorded hash")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::tags::{Tag, TargetEnvironment};
+
+    fn file(filename: &str, sha256: Option<&str>) -> ReleaseFile {
+        ReleaseFile {
+            filename: filename.to_string(),
+            url: format!("https://files.example/{}", filename),
+            size: None,
+            uploaded: None,
+            sha256: sha256.map(String::from),
+        }
+    }
+
+    fn linux_cp312() -> TagPriority {
+        TagPriority::for_target(&TargetEnvironment {
+            implementation: "cp".to_string(),
+            python: (3, 12),
+            platforms: vec!["manylinux_2_17_x86_64".to_string(), "manylinux2014_x86_64".to_string()],
+            abi: None,
+        })
+    }
+
+    #[test]
+    fn test_ranking_and_alternates() {
+        let trace = SelectionTrace::for_tags(
+            "demo",
+            "1.0",
+            vec![
+                file("demo-1.0.tar.gz", Some("cc")),
+                file("demo-1.0-py3-none-any.whl", Some("aa")),
+                file("demo-1.0-cp312-cp312-macosx_11_0_arm64.whl", None),
+                file("demo-1.0-cp312-cp312-manylinux_2_17_x86_64.whl", None),
+                file("demo-1.0.exe", None),
+            ],
+            &linux_cp312(),
+        );
+        let names: Vec<&str> = trace.candidates.iter().map(|f| f.filename.as_str()).collect();
+        assert_eq!(
+            names,
+            ["demo-1.0-cp312-cp312-manylinux_2_17_x86_64.whl", "demo-1.0-py3-none-any.whl", "demo-1.0.tar.gz"]
+        );
+        assert_eq!(trace.incompatible, ["demo-1.0-cp312-cp312-macosx_11_0_arm64.whl"]);
+        let any = FallbackPolicy::default();
+        let best = trace.best(&any).unwrap();
+        assert_eq!(best.filename, "demo-1.0-cp312-cp312-manylinux_2_17_x86_64.whl");
+        assert_eq!(trace.alternate(best, &any).unwrap().filename, "demo-1.0-py3-none-any.whl");
+
+        // The pure-Python wheel's hash isn't pinned, so only the sdist is left
+        let pins = vec!["sha256:BB".to_string(), "sha256:cc".to_string()];
+        let hashed = FallbackPolicy { pinned_hashes: Some(&pins), ..Default::default() };
+        assert_eq!(trace.alternate(best, &hashed).unwrap().filename, "demo-1.0.tar.gz");
+
+        let strict = FallbackPolicy { only_binary: true, pinned_hashes: Some(&pins) };
+        assert_eq!(
+            trace.alternate(best, &strict).unwrap_err(),
+            "no eligible alternate: demo-1.0-py3-none-any.whl (hash-checking mode requires a recorded hash), \
+             demo-1.0.tar.gz (--only-binary excludes sdists)"
+        );
+    }
+
+    #[test]
+    fn test_no_compatible_wheel() {
+        let files = vec![
+            file("demo-1.0-cp312-cp312-win_amd64.whl", None),
+            file("demo-1.0-cp311-cp311-manylinux_2_17_x86_64.whl", None),
+            file("demo-1.0.tar.gz", None),
+        ];
+        let trace = SelectionTrace::for_tags("demo", "1.0", files, &linux_cp312());
+        assert_eq!(trace.best(&FallbackPolicy::default()).unwrap().filename, "demo-1.0.tar.gz");
+
+        let only_binary = FallbackPolicy { only_binary: true, ..Default::default() };
+        let error = trace.best(&only_binary).unwrap_err();
+        assert_eq!(error.code(), "no-compatible-wheel");
+        let message = error.to_string();
+        assert!(
+            message.starts_with(
+                "No compatible wheel for demo 1.0 (available: demo-1.0-cp312-cp312-win_amd64.whl, \
+                 demo-1.0-cp311-cp311-manylinux_2_17_x86_64.whl); tags considered: \
+                 cp312-cp312-manylinux_2_17_x86_64, cp312-cp312-manylinux2014_x86_64, cp312-abi3-manylinux_2_17_x86_64"
+            ),
+            "{}",
+            message
+        );
+        assert!(message.ends_with(&format!("..., {} ({} tags)", Tag::new("py30", "none", "any"), linux_cp312().len())));
+        assert!(trace.best_wheel().is_err());
+    }
+}
@@ -0,0 +1,563 @@
+/// PEP 503 simple repository API, for indexes without a JSON API
+///
+/// Artifactory, devpi and Nexus often serve only `/simple/<project>/`. The
+/// project page is requested with PEP 691 content negotiation: a server
+/// that answers `application/vnd.pypi.simple.v1+json` is read as JSON,
+/// anything else as the PEP 503 anchor list. The files are then laid out
+/// as a JSON API document (`info`, `releases`, `urls`) so that candidate
+/// enumeration, artifact selection and hash-checked downloads read them
+/// like any other index. Dependencies come from the core metadata the
+/// index serves next to a file (PEP 658), when it does.
+use super::client::PackageClient;
+use super::index::IndexConfig;
+use crate::installer::wheel::WheelMetadata;
+use crate::utils::names::canonicalize_name;
+use crate::utils::pep691_handler::Pep691Handler;
+use crate::utils::version;
+use crate::utils::warnings::{self, WarningCode};
+use anyhow::{Result, anyhow};
+use serde_json::{Value, json};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// PEP 691 JSON serialization of the simple API
+pub const SIMPLE_JSON: &str = "application/vnd.pypi.simple.v1+json";
+
+/// JSON preferred, HTML from servers that don't negotiate
+const ACCEPT: &str = "application/vnd.pypi.simple.v1+json, application/vnd.pypi.simple.v1+html;q=0.2, text/html;q=0.1";
+
+/// One file of a project page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimpleFile {
+    pub filename: String,
+    /// Absolute download URL, without the hash fragment
+    pub url: String,
+    /// `data-requires-python`
+    pub requires_python: Option<String>,
+    /// Hex sha256, from the URL fragment or `hashes`
+    pub sha256: Option<String>,
+    /// The yank reason (possibly empty) when the file is yanked
+    pub yanked: Option<String>,
+    /// The index serves the file's core metadata at `<url>.metadata`
+    pub has_metadata: bool,
+}
+
+/// `<index>/<normalized project>/`
+pub fn project_url(index: &IndexConfig, project: &str) -> String {
+    format!("{}/{}/", index.url.trim_end_matches('/'), canonicalize_name(project))
+}
+
+/// Fetch and parse the project page on `index`
+pub async fn project_files(client: &PackageClient, index: &IndexConfig, project: &str) -> Result<Vec<SimpleFile>> {
+    let url = project_url(index, project);
+    let (content_type, body) = client.fetch_negotiated(&url, ACCEPT).await?;
+    if content_type.as_deref().is_some_and(|content_type| content_type.starts_with(SIMPLE_JSON)) {
+        parse_json(&url, &body)
+    } else {
+        Ok(parse_html(&url, &String::from_utf8_lossy(&body)))
+    }
+}
+
+/// A JSON API document for `project` built from its simple API page:
+/// for `version`, or the latest release when `None`
+pub async fn document(
+    client: &PackageClient,
+    index: &IndexConfig,
+    project: &str,
+    version: Option<&str>,
+) -> Result<Value> {
+    let files = project_files(client, index, project).await?;
+    let mut document = to_json_api(project, &files, version)?;
+    let selected = document["info"]["version"].as_str().unwrap_or_default().to_string();
+
+    let with_metadata = files
+        .iter()
+        .filter(|file| file.has_metadata && filename_version(project, &file.filename).as_deref() == Some(selected.as_str()))
+        .min_by_key(|file| !file.filename.ends_with(".whl"));
+    let Some(file) = with_metadata else {
+        warnings::warn_user(
+            WarningCode::MetadataUnavailable,
+            format!(
+                "index '{}' serves no core metadata for {} {}; its dependencies are not known",
+                index.name, project, selected
+            ),
+        );
+        return Ok(document);
+    };
+    let body = client.fetch_fresh(&format!("{}.metadata", file.url), "*/*").await?;
+    let metadata = WheelMetadata::parse(&String::from_utf8_lossy(&body))?;
+    document["info"]["requires_dist"] = json!(metadata.requires_dist);
+    document["info"]["summary"] = json!(metadata.summary);
+    Ok(document)
+}
+
+/// Parse a PEP 503 HTML project page fetched from `page_url`
+pub fn parse_html(page_url: &str, html: &str) -> Vec<SimpleFile> {
+    // ASCII lowercasing keeps byte offsets, so positions carry over
+    let lower = html.to_ascii_lowercase();
+    let mut files = Vec::new();
+    let mut rest = 0;
+    while let Some(found) = lower[rest..].find("<a") {
+        let after = rest + found + 2;
+        if !lower[after..].starts_with(|c: char| c.is_ascii_whitespace() || c == '>') {
+            rest = after;
+            continue;
+        }
+        let Some(tag_end) = tag_end(&html[after..]).map(|end| after + end) else {
+            break;
+        };
+        let attributes = parse_attributes(&html[after..tag_end]);
+        let text_start = tag_end + 1;
+        let text_end = lower[text_start..].find("</a").map_or(html.len(), |end| text_start + end);
+        rest = text_end;
+        if let Some(file) = anchor_file(page_url, &attributes, &unescape(html[text_start..text_end].trim())) {
+            files.push(file);
+        }
+    }
+    files
+}
+
+/// Project names listed on an index's root page: PEP 691 JSON when the
+/// server answered with it, otherwise the text of each PEP 503 anchor
+pub fn parse_project_list(content_type: Option<&str>, body: &[u8]) -> Result<Vec<String>> {
+    if content_type.is_some_and(|content_type| content_type.starts_with(SIMPLE_JSON)) {
+        let page: Value = serde_json::from_slice(body)?;
+        let projects = page["projects"].as_array().ok_or_else(|| anyhow!("no project list in index page"))?;
+        return Ok(projects.iter().filter_map(|p| p["name"].as_str().map(String::from)).collect());
+    }
+    let html = String::from_utf8_lossy(body);
+    let lower = html.to_ascii_lowercase();
+    let mut names = Vec::new();
+    let mut rest = 0;
+    while let Some(found) = lower[rest..].find("<a") {
+        let after = rest + found + 2;
+        if !lower[after..].starts_with(|c: char| c.is_ascii_whitespace() || c == '>') {
+            rest = after;
+            continue;
+        }
+        let Some(tag_end) = tag_end(&html[after..]).map(|end| after + end) else {
+            break;
+        };
+        let text_end = lower[tag_end..].find("</a").map_or(html.len(), |end| tag_end + end);
+        rest = text_end;
+        let name = unescape(html[tag_end + 1..text_end].trim());
+        if !name.is_empty() {
+            names.push(name);
+        }
+    }
+    Ok(names)
+}
+
+/// Parse a PEP 691 JSON project page fetched from `page_url`
+pub fn parse_json(page_url: &str, body: &[u8]) -> Result<Vec<SimpleFile>> {
+    let page: Value = serde_json::from_slice(body).map_err(|e| anyhow!("Invalid simple API JSON from {}: {}", page_url, e))?;
+    let base = url::Url::parse(page_url)?;
+    let files = page["files"]
+        .as_array()
+        .ok_or_else(|| anyhow!("Simple API JSON from {} has no files", page_url))?;
+    Ok(files
+        .iter()
+        .filter_map(|file| {
+            let filename = file["filename"].as_str().filter(|name| Pep691Handler::is_package_file(name))?;
+            let url = base.join(file["url"].as_str()?).ok()?;
+            Some(SimpleFile {
+                filename: filename.to_string(),
+                url: url.to_string(),
+                requires_python: file["requires-python"]
+                    .as_str()
+                    .filter(|spec| !spec.trim().is_empty())
+                    .map(String::from),
+                sha256: file["hashes"]["sha256"].as_str().map(str::to_ascii_lowercase),
+                yanked: match &file["yanked"] {
+                    Value::Bool(true) => Some(String::new()),
+                    Value::String(reason) => Some(reason.clone()),
+                    _ => None,
+                },
+                has_metadata: ["core-metadata", "dist-info-metadata"]
+                    .iter()
+                    .any(|key| matches!(&file[*key], Value::Bool(true) | Value::Object(_))),
+            })
+        })
+        .collect())
+}
+
+/// The version in a distribution's filename; sdist names are split after
+/// the project's own name, which may itself contain dashes
+pub fn filename_version(project: &str, filename: &str) -> Option<String> {
+    if let Some(stem) = filename.strip_suffix(".whl").or_else(|| filename.strip_suffix(".egg")) {
+        return stem.split('-').nth(1).map(String::from);
+    }
+    let stem = [".tar.gz", ".tar.bz2", ".tar.xz", ".tgz", ".zip"]
+        .iter()
+        .find_map(|extension| filename.strip_suffix(extension))?;
+    let project = canonicalize_name(project);
+    stem.match_indices('-')
+        .map(|(i, _)| i)
+        .find(|&i| canonicalize_name(&stem[..i]) == project)
+        .or_else(|| stem.rfind('-'))
+        .map(|i| stem[i + 1..].to_string())
+        .filter(|version| !version.is_empty())
+}
+
+/// Lay `files` out the way the JSON API does, with `info` describing
+/// `version` (or the latest non-yanked final release)
+pub fn to_json_api(project: &str, files: &[SimpleFile], version: Option<&str>) -> Result<Value> {
+    let mut releases: Vec<Release> = Vec::new();
+    for file in files {
+        let Some(file_version) = filename_version(project, &file.filename) else {
+            continue;
+        };
+        let entry = json!({
+            "filename": file.filename,
+            "url": file.url,
+            "digests": { "sha256": file.sha256 },
+            "requires_python": file.requires_python,
+            "yanked": file.yanked.is_some(),
+            "yanked_reason": file.yanked.as_deref().filter(|reason| !reason.is_empty()),
+        });
+        match releases.iter_mut().find(|(known, _)| *known == file_version) {
+            Some((_, entries)) => entries.push(entry),
+            None => releases.push((file_version, vec![entry])),
+        }
+    }
+
+    let selected = match version {
+        Some(version) => releases
+            .iter()
+            .find(|(known, _)| version::compare(known, version) == Ordering::Equal)
+            .ok_or_else(|| anyhow!("{} {} is not listed on the simple API", project, version))?,
+        None => latest(&releases).ok_or_else(|| anyhow!("{} has no distributions on the simple API", project))?,
+    };
+    let (selected_version, urls) = selected.clone();
+    let requires_python = urls.iter().find_map(|file| file["requires_python"].as_str()).map(String::from);
+    let releases: serde_json::Map<String, Value> = releases
+        .into_iter()
+        .map(|(version, entries)| (version, Value::Array(entries)))
+        .collect();
+    Ok(json!({
+        "info": {
+            "name": project,
+            "version": selected_version,
+            "requires_python": requires_python,
+            "requires_dist": null,
+            "summary": null,
+        },
+        "releases": releases,
+        "urls": urls,
+    }))
+}
+
+/// A version and the JSON API entries of its files
+type Release = (String, Vec<Value>);
+
+/// What the JSON API would call current: the highest final release with
+/// a non-yanked file, falling back to the highest release
+fn latest(releases: &[Release]) -> Option<&Release> {
+    let highest = |eligible: &dyn Fn(&Release) -> bool| {
+        releases
+            .iter()
+            .filter(|release| eligible(release))
+            .max_by(|a, b| version::compare(&a.0, &b.0))
+    };
+    highest(&|(version, files)| {
+        !version::is_prerelease(version) && files.iter().any(|file| file["yanked"] == Value::Bool(false))
+    })
+    .or_else(|| highest(&|_| true))
+}
+
+fn anchor_file(page_url: &str, attributes: &HashMap<String, String>, text: &str) -> Option<SimpleFile> {
+    let mut url = url::Url::parse(page_url).ok()?.join(attributes.get("href")?).ok()?;
+    let sha256 = url
+        .fragment()
+        .and_then(|fragment| fragment.strip_prefix("sha256="))
+        .map(str::to_ascii_lowercase);
+    url.set_fragment(None);
+    let filename = if text.is_empty() {
+        url.path_segments()?.next_back()?.to_string()
+    } else {
+        text.to_string()
+    };
+    if !Pep691Handler::is_package_file(&filename) {
+        return None;
+    }
+    Some(SimpleFile {
+        filename,
+        url: url.to_string(),
+        requires_python: attributes
+            .get("data-requires-python")
+            .filter(|spec| !spec.trim().is_empty())
+            .cloned(),
+        sha256,
+        yanked: attributes.get("data-yanked").cloned(),
+        has_metadata: ["data-core-metadata", "data-dist-info-metadata"]
+            .iter()
+            .any(|key| attributes.get(*key).is_some_and(|value| value != "false")),
+    })
+}
+
+/// Offset of the `>` closing a tag; some indexes leave `>` unescaped in
+/// quoted `data-requires-python` values
+fn tag_end(tag: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in tag.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(open), _) if c == open => quote = None,
+            (None, '>') => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Attributes of a start tag, names lowercased and values unescaped
+fn parse_attributes(tag: &str) -> HashMap<String, String> {
+    let bytes = tag.as_bytes();
+    let skip_space = |mut i: usize| {
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        i
+    };
+    let mut attributes = HashMap::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i].is_ascii_whitespace() || bytes[i] == b'/' {
+            i += 1;
+            continue;
+        }
+        let name_start = i;
+        while i < bytes.len() && !bytes[i].is_ascii_whitespace() && bytes[i] != b'=' {
+            i += 1;
+        }
+        let name = tag[name_start..i].to_ascii_lowercase();
+        i = skip_space(i);
+        let value = if bytes.get(i) == Some(&b'=') {
+            i = skip_space(i + 1);
+            match bytes.get(i) {
+                Some(&quote @ (b'"' | b'\'')) => {
+                    let start = i + 1;
+                    let end = tag[start..].find(quote as char).map_or(tag.len(), |end| start + end);
+                    i = (end + 1).min(tag.len());
+                    unescape(&tag[start..end])
+                }
+                _ => {
+                    let start = i;
+                    while i < bytes.len() && !bytes[i].is_ascii_whitespace() {
+                        i += 1;
+                    }
+                    unescape(&tag[start..i])
+                }
+            }
+        } else {
+            String::new()
+        };
+        attributes.insert(name, value);
+    }
+    attributes
+}
+
+/// Decode the character references that show up in project pages
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let decoded = rest
+            .find(';')
+            .filter(|&end| end <= 10)
+            .and_then(|end| Some((entity(&rest[1..end])?, end)));
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn entity(name: &str) -> Option<char> {
+    match name {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        _ => {
+            let code = match name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => name.strip_prefix('#')?.parse().ok()?,
+            };
+            char::from_u32(code)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::pypi::ReleaseFile;
+    use crate::utils::pep691_handler::{FileIndex, FileUrlInfo};
+    use std::path::PathBuf;
+
+    const PAGE: &str = "https://nexus.example/repository/pypi/simple/demo-pkg/";
+
+    const HTML: &str = r#"<!DOCTYPE html>
+<html><head><title>Links for demo-pkg</title></head>
+<body>
+<h1>Links for demo-pkg</h1>
+<A HREF="../../packages/demo_pkg-1.0-py3-none-any.whl#sha256=AABB" data-requires-python="&gt;=3.8">demo_pkg-1.0-py3-none-any.whl</A><br/>
+<a href="https://files.example/demo-pkg-1.0.tar.gz#md5=00" data-requires-python=">=3.8">demo-pkg-1.0.tar.gz</a><br/>
+<a href='/packages/demo_pkg-2.0-py3-none-any.whl#sha256=ccdd' data-dist-info-metadata="sha256=ee" data-yanked="broken &amp; replaced">demo_pkg-2.0-py3-none-any.whl</a>
+<a href="/packages/demo_pkg-1.5rc1-py3-none-any.whl" data-core-metadata="true">demo_pkg-1.5rc1-py3-none-any.whl</a>
+<abbr title="x">not a link</abbr>
+<a href="/docs/">documentation</a>
+</body></html>"#;
+
+    #[test]
+    fn test_parse_html() {
+        let files = parse_html(PAGE, HTML);
+        let names: Vec<&str> = files.iter().map(|file| file.filename.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "demo_pkg-1.0-py3-none-any.whl",
+                "demo-pkg-1.0.tar.gz",
+                "demo_pkg-2.0-py3-none-any.whl",
+                "demo_pkg-1.5rc1-py3-none-any.whl"
+            ]
+        );
+        assert_eq!(
+            files[0],
+            SimpleFile {
+                filename: "demo_pkg-1.0-py3-none-any.whl".to_string(),
+                url: "https://nexus.example/repository/pypi/packages/demo_pkg-1.0-py3-none-any.whl".to_string(),
+                requires_python: Some(">=3.8".to_string()),
+                sha256: Some("aabb".to_string()),
+                yanked: None,
+                has_metadata: false,
+            }
+        );
+        // Unescaped `>` inside quotes; only sha256 fragments are kept
+        assert_eq!(files[1].requires_python.as_deref(), Some(">=3.8"));
+        assert_eq!((files[1].sha256.as_deref(), files[1].url.as_str()), (None, "https://files.example/demo-pkg-1.0.tar.gz"));
+        assert_eq!(files[2].yanked.as_deref(), Some("broken & replaced"));
+        assert!(files[2].has_metadata && files[3].has_metadata && !files[1].has_metadata);
+    }
+
+    #[test]
+    fn test_parse_json() {
+        let body = br#"{
+            "meta": {"api-version": "1.1"},
+            "name": "demo-pkg",
+            "files": [
+                {"filename": "demo_pkg-1.0-py3-none-any.whl", "url": "../../packages/demo_pkg-1.0-py3-none-any.whl",
+                 "hashes": {"sha256": "AABB"}, "requires-python": ">=3.8", "core-metadata": {"sha256": "ee"}},
+                {"filename": "demo-pkg-1.0.tar.gz", "url": "https://files.example/demo-pkg-1.0.tar.gz",
+                 "hashes": {}, "yanked": "bad build"},
+                {"filename": "README.txt", "url": "README.txt", "hashes": {}}
+            ]
+        }"#;
+        let files = parse_json(PAGE, body).unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].url, "https://nexus.example/repository/pypi/packages/demo_pkg-1.0-py3-none-any.whl");
+        assert_eq!(files[0].sha256.as_deref(), Some("aabb"));
+        assert!(files[0].has_metadata && files[0].yanked.is_none());
+        assert_eq!(files[1].yanked.as_deref(), Some("bad build"));
+
+        // What the local PEP 691 index generator writes reads back
+        let mut index = FileIndex::new(PathBuf::from("/srv/wheels"));
+        let mut wheel = FileUrlInfo::new(PathBuf::from("/srv/wheels/demo_pkg-1.0-py3-none-any.whl"), "demo_pkg-1.0-py3-none-any.whl".into());
+        wheel.add_hash("SHA256", "ff");
+        wheel.set_requires_python(">=3.9");
+        wheel.set_yanked(true);
+        index.add_file(wheel);
+        let generated = Pep691Handler::generate_json(&index);
+        let files = parse_json("file:///srv/wheels/", generated.as_bytes()).unwrap();
+        assert_eq!(files[0].url, "file:///srv/wheels/demo_pkg-1.0-py3-none-any.whl");
+        assert_eq!((files[0].sha256.as_deref(), files[0].requires_python.as_deref()), (Some("ff"), Some(">=3.9")));
+        assert_eq!(files[0].yanked.as_deref(), Some(""));
+    }
+
+    #[test]
+    fn test_parse_project_list() {
+        let json = br#"{"meta": {"api-version": "1.1"}, "projects": [{"name": "Flask"}, {"name": "requests"}]}"#;
+        assert_eq!(parse_project_list(Some(SIMPLE_JSON), json).unwrap(), ["Flask", "requests"]);
+        let html = b"<html><body>\n<a href=\"/simple/flask/\">Flask</a>\n<A HREF='/simple/zope-interface/'>zope.interface</A>\n</body></html>";
+        assert_eq!(parse_project_list(Some("text/html"), html).unwrap(), ["Flask", "zope.interface"]);
+        assert!(parse_project_list(Some(SIMPLE_JSON), b"{}").is_err());
+    }
+
+    #[test]
+    fn test_filename_version() {
+        assert_eq!(filename_version("demo-pkg", "demo_pkg-1.0-py3-none-any.whl").as_deref(), Some("1.0"));
+        assert_eq!(filename_version("demo-pkg", "demo-pkg-1.0.post1.tar.gz").as_deref(), Some("1.0.post1"));
+        assert_eq!(filename_version("zope.interface", "zope.interface-6.0.zip").as_deref(), Some("6.0"));
+        assert_eq!(filename_version("demo", "demo-1.0.exe"), None);
+    }
+
+    #[test]
+    fn test_to_json_api() {
+        let files = parse_html(PAGE, HTML);
+        let document = to_json_api("demo-pkg", &files, None).unwrap();
+        // 2.0 is yanked and 1.5rc1 a pre-release
+        assert_eq!(document["info"]["version"], "1.0");
+        assert_eq!(document["info"]["requires_python"], ">=3.8");
+        let mut versions: Vec<&String> = document["releases"].as_object().unwrap().keys().collect();
+        versions.sort();
+        assert_eq!(versions, ["1.0", "1.5rc1", "2.0"]);
+        assert_eq!(document["releases"]["2.0"][0]["yanked"], true);
+
+        let current = ReleaseFile::from_urls(&document["urls"]);
+        assert_eq!(current.len(), 2);
+        assert_eq!(current[0].sha256.as_deref(), Some("aabb"));
+
+        let release = to_json_api("demo-pkg", &files, Some("2.0")).unwrap();
+        assert_eq!(release["info"]["version"], "2.0");
+        assert!(to_json_api("demo-pkg", &files, Some("3.0")).is_err());
+        assert!(to_json_api("demo-pkg", &[], None).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_document_negotiates_json_and_reads_core_metadata() {
+        let mut server = mockito::Server::new_async().await;
+        let page = server
+            .mock("GET", "/simple/demo-pkg/")
+            .match_header("accept", mockito::Matcher::Regex(SIMPLE_JSON.replace('+', "\\+")))
+            .with_header("content-type", SIMPLE_JSON)
+            .with_body(
+                r#"{"meta": {"api-version": "1.0"}, "name": "demo-pkg", "files": [
+                    {"filename": "demo_pkg-1.0-py3-none-any.whl", "url": "/files/demo_pkg-1.0-py3-none-any.whl",
+                     "hashes": {"sha256": "aa"}, "core-metadata": true}]}"#,
+            )
+            .create_async()
+            .await;
+        let metadata = server
+            .mock("GET", "/files/demo_pkg-1.0-py3-none-any.whl.metadata")
+            .with_body("Metadata-Version: 2.1\nName: demo-pkg\nVersion: 1.0\nSummary: Demo\nRequires-Dist: attrs>=21\n")
+            .create_async()
+            .await;
+
+        let index = IndexConfig {
+            name: "private".to_string(),
+            url: format!("{}/simple", server.url()),
+            priority: 0,
+            default: true,
+            token: None,
+            simple_only: true,
+        };
+        let document = document(&PackageClient::new(), &index, "Demo_Pkg", None).await.unwrap();
+        page.assert_async().await;
+        metadata.assert_async().await;
+        assert_eq!(document["info"]["requires_dist"], json!(["attrs>=21"]));
+        assert_eq!(document["info"]["summary"], "Demo");
+        assert_eq!(document["urls"][0]["url"], format!("{}/files/demo_pkg-1.0-py3-none-any.whl", server.url()));
+    }
+}
@@ -0,0 +1,200 @@
+/// Request rate limiting and retry pacing
+///
+/// Every request the client sends first takes a token from a process-wide
+/// [`RateLimiter`], so concurrent scans stay under a requests-per-second
+/// ceiling together (`PIP_RS_MAX_RPS`, or `max-rps` in pip.conf). A server
+/// that still answers 429 Too Many Requests, or 503 with `Retry-After`, is
+/// retried after the delay it asked for plus some jitter, on a separate
+/// budget from ordinary failures.
+use crate::utils::warnings::{self, WarningCode};
+use once_cell::sync::Lazy;
+use reqwest::{StatusCode, header};
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Requests per second when nothing is configured
+pub const DEFAULT_MAX_RPS: f64 = 25.0;
+
+/// Retries allowed for 429 responses, on top of the ordinary retries
+const MAX_THROTTLED_RETRIES: u32 = 4;
+
+/// Longest `Retry-After` honored; a longer one is cut short
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// Token bucket allowing `rate` requests per second, in bursts of up to
+/// one second's worth
+pub struct RateLimiter {
+    rate: f64,
+    state: Mutex<Bucket>,
+    warned: AtomicBool,
+}
+
+struct Bucket {
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(rate: f64) -> Self {
+        let rate = rate.max(0.1);
+        Self {
+            rate,
+            state: Mutex::new(Bucket {
+                tokens: rate.max(1.0),
+                refilled: Instant::now(),
+            }),
+            warned: AtomicBool::new(false),
+        }
+    }
+
+    /// Take a token, waiting until one is available
+    pub async fn acquire(&self) {
+        let wait = self.reserve(Instant::now());
+        if wait.is_zero() {
+            return;
+        }
+        if !self.warned.swap(true, Ordering::Relaxed) {
+            warnings::warn_user(
+                WarningCode::RateLimited,
+                format!(
+                    "Requests are being paced to {} per second; raise PIP_RS_MAX_RPS to go faster",
+                    self.rate
+                ),
+            );
+        }
+        tokio::time::sleep(wait).await;
+    }
+
+    /// Take a token at `now`, returning how long the caller must wait for it
+    fn reserve(&self, now: Instant) -> Duration {
+        let Ok(mut bucket) = self.state.lock() else {
+            return Duration::ZERO;
+        };
+        let elapsed = now.saturating_duration_since(bucket.refilled).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.rate.max(1.0));
+        bucket.refilled = now;
+        bucket.tokens -= 1.0;
+        if bucket.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.tokens / self.rate)
+        }
+    }
+}
+
+static SHARED: Lazy<Arc<RateLimiter>> =
+    Lazy::new(|| Arc::new(RateLimiter::new(crate::config::config::Config::global().max_rps())));
+
+/// The limiter every client in this process shares
+pub fn shared() -> Arc<RateLimiter> {
+    SHARED.clone()
+}
+
+/// Delay a response asks for in `Retry-After`: seconds or an HTTP date
+pub fn retry_after(headers: &header::HeaderMap) -> Option<Duration> {
+    let value = headers.get(header::RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let now = chrono::Utc::now();
+    Some((at.with_timezone(&chrono::Utc) - now).to_std().unwrap_or_default())
+}
+
+/// Whether a response with `status` is worth sending again
+pub fn is_retryable(status: StatusCode) -> bool {
+    status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+/// Pacing of retries for one request
+pub struct Backoff {
+    max_retries: u32,
+    base_delay: Duration,
+    attempts: u32,
+    throttled: u32,
+}
+
+impl Backoff {
+    pub fn new(max_retries: u32, base_delay: Duration) -> Self {
+        Self {
+            max_retries,
+            base_delay,
+            attempts: 1,
+            throttled: 0,
+        }
+    }
+
+    /// Delay before trying again after a failure, or `None` once the
+    /// budget is spent. `status` is that of a retryable response, if any.
+    pub fn next_delay(&mut self, status: Option<StatusCode>, retry_after: Option<Duration>) -> Option<Duration> {
+        if status == Some(StatusCode::TOO_MANY_REQUESTS) {
+            self.throttled += 1;
+            if self.throttled > MAX_THROTTLED_RETRIES {
+                return None;
+            }
+            warnings::warn_user(
+                WarningCode::RateLimited,
+                "The index is rate limiting requests (HTTP 429); slowing down",
+            );
+            let delay = retry_after.unwrap_or(self.base_delay * 2_u32.pow(self.throttled));
+            return Some(delay.min(MAX_RETRY_AFTER) + jitter(delay));
+        }
+
+        if self.attempts >= self.max_retries {
+            return None;
+        }
+        self.attempts += 1;
+        Some(match retry_after {
+            Some(delay) => delay.min(MAX_RETRY_AFTER) + jitter(delay),
+            None => self.base_delay * 2_u32.pow(self.attempts - 2),
+        })
+    }
+}
+
+/// Up to a quarter of `delay` (at least 100ms worth of range), so clients
+/// told to wait the same time don't all come back at once
+fn jitter(delay: Duration) -> Duration {
+    let range = (delay / 4).max(Duration::from_millis(100)).as_nanos() as u64;
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.subsec_nanos() as u64).unwrap_or(0);
+    Duration::from_nanos(nanos % range.max(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_paces_bursts() {
+        let limiter = RateLimiter::new(2.0);
+        let start = Instant::now();
+        assert_eq!(limiter.reserve(start), Duration::ZERO);
+        assert_eq!(limiter.reserve(start), Duration::ZERO);
+        assert_eq!(limiter.reserve(start), Duration::from_millis(500));
+        // Tokens come back with time
+        assert_eq!(limiter.reserve(start + Duration::from_secs(2)), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_retry_after_and_backoff_budgets() {
+        let mut headers = header::HeaderMap::new();
+        headers.insert(header::RETRY_AFTER, "3".parse().unwrap());
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(3)));
+        headers.insert(header::RETRY_AFTER, "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap());
+        assert_eq!(retry_after(&headers), Some(Duration::ZERO));
+        assert!(is_retryable(StatusCode::TOO_MANY_REQUESTS));
+        assert!(!is_retryable(StatusCode::NOT_FOUND));
+
+        let mut backoff = Backoff::new(2, Duration::from_millis(100));
+        let delay = backoff.next_delay(Some(StatusCode::TOO_MANY_REQUESTS), Some(Duration::from_secs(2))).unwrap();
+        assert!(delay >= Duration::from_secs(2) && delay < Duration::from_millis(2500), "{:?}", delay);
+        // 429s don't use up the ordinary retries
+        assert_eq!(backoff.next_delay(None, None), Some(Duration::from_millis(100)));
+        assert_eq!(backoff.next_delay(None, None), None);
+        for _ in 1..MAX_THROTTLED_RETRIES {
+            assert!(backoff.next_delay(Some(StatusCode::TOO_MANY_REQUESTS), None).is_some());
+        }
+        assert_eq!(backoff.next_delay(Some(StatusCode::TOO_MANY_REQUESTS), None), None);
+    }
+}
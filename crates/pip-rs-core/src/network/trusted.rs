@@ -0,0 +1,150 @@
+/// Hosts whose TLS certificates are not verified (`--trusted-host`)
+///
+/// An entry is a host (`pypi.internal`), which also covers its subdomains,
+/// or `host:port`, which covers that port only; a scheme or path pasted
+/// along with it is ignored. Requests to a matching host go through a
+/// second client that accepts invalid certificates, and everything else
+/// keeps strict verification. That client only follows redirects that stay
+/// on trusted hosts.
+use crate::utils::warnings::{self, WarningCode};
+use once_cell::sync::Lazy;
+use reqwest::{Client, ClientBuilder, redirect};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+
+/// Redirects the insecure client follows, like reqwest's default policy
+const MAX_REDIRECTS: usize = 10;
+
+/// Set once the first insecure connection has been warned about
+static WARNED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Default)]
+pub struct TrustedHosts {
+    hosts: RwLock<Vec<String>>,
+}
+
+impl TrustedHosts {
+    pub fn new(hosts: Vec<String>) -> Self {
+        Self {
+            hosts: RwLock::new(hosts),
+        }
+    }
+
+    /// Trust these hosts from now on
+    pub fn configure(&self, hosts: Vec<String>) {
+        if let Ok(mut current) = self.hosts.write() {
+            *current = hosts;
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hosts.read().map(|hosts| hosts.is_empty()).unwrap_or(true)
+    }
+
+    /// Whether `url` is on a trusted host
+    pub fn contains(&self, url: &str) -> bool {
+        let Ok(url) = url::Url::parse(url) else {
+            return false;
+        };
+        self.hosts
+            .read()
+            .map(|hosts| hosts.iter().any(|trusted| host_matches(trusted, &url)))
+            .unwrap_or(false)
+    }
+}
+
+/// Whether `url` is covered by the `--trusted-host` entry `trusted`
+pub fn host_matches(trusted: &str, url: &url::Url) -> bool {
+    let Some(host) = url.host_str().map(str::to_lowercase) else {
+        return false;
+    };
+    let trusted = trusted.trim().to_lowercase();
+    let trusted = trusted.split_once("://").map_or(trusted.as_str(), |(_, rest)| rest);
+    let trusted = trusted.split('/').next().unwrap_or_default();
+    let (trusted_host, port) = match trusted.rsplit_once(':') {
+        Some((host, port)) => match port.parse::<u16>() {
+            Ok(port) => (host, Some(port)),
+            Err(_) => (trusted, None),
+        },
+        _ => (trusted, None),
+    };
+    let trusted_host = trusted_host.trim_start_matches('[').trim_end_matches(']');
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if trusted_host.is_empty() {
+        return false;
+    }
+
+    let host_matches = host == trusted_host || host.ends_with(&format!(".{}", trusted_host));
+    host_matches && port.is_none_or(|port| url.port_or_known_default() == Some(port))
+}
+
+/// A client like `builder` builds that skips certificate verification,
+/// following redirects only while they stay on `trusted` hosts
+pub fn insecure_client(builder: ClientBuilder, trusted: Arc<TrustedHosts>) -> Client {
+    let policy = redirect::Policy::custom(move |attempt| {
+        if attempt.previous().len() >= MAX_REDIRECTS {
+            attempt.error("too many redirects")
+        } else if trusted.contains(attempt.url().as_str()) {
+            attempt.follow()
+        } else {
+            let error = format!("refusing to follow a redirect from a trusted host to {}", attempt.url());
+            attempt.error(error)
+        }
+    });
+    builder
+        .danger_accept_invalid_certs(true)
+        .redirect(policy)
+        .build()
+        .unwrap_or_else(|_| Client::new())
+}
+
+/// Warn, once per process, that certificates of `url`'s host are not checked
+pub fn warn_insecure(url: &str) {
+    if WARNED.swap(true, Ordering::Relaxed) {
+        return;
+    }
+    let host = url::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(String::from))
+        .unwrap_or_default();
+    warnings::warn_user(
+        WarningCode::InsecureConnection,
+        format!(
+            "Not verifying the TLS certificate of {} because it is a trusted host; \
+             its responses could be intercepted",
+            host
+        ),
+    );
+}
+
+static SHARED: Lazy<Arc<TrustedHosts>> =
+    Lazy::new(|| Arc::new(TrustedHosts::new(crate::config::config::Config::global().trusted_hosts().to_vec())));
+
+/// The trusted hosts every client in this process shares
+pub fn shared() -> Arc<TrustedHosts> {
+    SHARED.clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_host_subdomain_and_port_forms() {
+        let trusted = TrustedHosts::new(vec![
+            "pypi.internal".to_string(),
+            "mirror.example:8443".to_string(),
+            "https://Files.Example/simple".to_string(),
+        ]);
+        assert!(trusted.contains("https://pypi.internal/simple/"));
+        assert!(trusted.contains("https://cdn.pypi.internal:4443/pkg.whl"));
+        assert!(!trusted.contains("https://notpypi.internal/"));
+        assert!(trusted.contains("https://mirror.example:8443/simple/"));
+        assert!(!trusted.contains("https://mirror.example/simple/"));
+        assert!(trusted.contains("https://files.example/a.whl"));
+        assert!(!trusted.contains("https://pypi.org/simple/"));
+
+        assert!(host_matches("[::1]:8080", &url::Url::parse("https://[::1]:8080/").unwrap()));
+        assert!(host_matches("localhost:443", &url::Url::parse("https://localhost/").unwrap()));
+    }
+}
@@ -0,0 +1,192 @@
+/// Resolution budget and exploration statistics
+///
+/// Resolution is bounded by a wall-clock limit and a backtrack limit, where
+/// a backtrack is a candidate version the resolver fetched and then had to
+/// give up (it missed a specifier, a constraint or Requires-Python). When
+/// either limit is hit, resolution stops with [`ResolutionTooComplex`],
+/// which names the packages and conflicts the time went into instead of
+/// leaving the command looking hung. The same [`ResolutionStats`] are
+/// available after a successful run (`--resolution-stats`).
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::Duration;
+
+/// Wall-clock limit when none is given (`--resolution-timeout`)
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Rejected candidates tolerated before giving up
+pub const DEFAULT_MAX_BACKTRACKS: usize = 10_000;
+
+/// Entries listed per section of the statistics
+const TOP_ENTRIES: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResolutionBudget {
+    pub timeout: Duration,
+    pub max_backtracks: usize,
+}
+
+impl Default for ResolutionBudget {
+    fn default() -> Self {
+        Self {
+            timeout: DEFAULT_TIMEOUT,
+            max_backtracks: DEFAULT_MAX_BACKTRACKS,
+        }
+    }
+}
+
+/// A requirement edge that rejected candidates
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Conflict {
+    pub package: String,
+    /// The specifier that failed, e.g. `urllib3<1.27` or `Requires-Python >=3.12`
+    pub requirement: String,
+    /// `None` for a top-level requirement or constraint
+    pub required_by: Option<String>,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.requirement)?;
+        if let Some(parent) = &self.required_by {
+            write!(f, " (required by {})", parent)?;
+        }
+        Ok(())
+    }
+}
+
+/// What a resolution explored
+#[derive(Debug, Clone, Default)]
+pub struct ResolutionStats {
+    pub elapsed: Duration,
+    pub rounds: usize,
+    pub backtracks: usize,
+    /// Candidate versions fetched per package
+    pub candidates: BTreeMap<String, usize>,
+    /// Rejections per requirement edge
+    pub conflicts: BTreeMap<Conflict, usize>,
+}
+
+impl ResolutionStats {
+    pub fn record_candidate(&mut self, package: &str) {
+        *self.candidates.entry(package.to_string()).or_default() += 1;
+    }
+
+    pub fn record_backtrack(&mut self, conflict: Conflict) {
+        self.backtracks += 1;
+        *self.conflicts.entry(conflict).or_default() += 1;
+    }
+
+    pub fn candidates_tried(&self) -> usize {
+        self.candidates.values().sum()
+    }
+
+    /// Packages with the most candidate versions tried, most first
+    pub fn hottest_packages(&self, n: usize) -> Vec<(&str, usize)> {
+        let mut packages: Vec<_> = self.candidates.iter().map(|(name, &count)| (name.as_str(), count)).collect();
+        packages.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        packages.truncate(n);
+        packages
+    }
+
+    /// Conflicts that caused the most backtracking, most first
+    pub fn top_conflicts(&self, n: usize) -> Vec<(&Conflict, usize)> {
+        let mut conflicts: Vec<_> = self.conflicts.iter().map(|(conflict, &count)| (conflict, count)).collect();
+        conflicts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        conflicts.truncate(n);
+        conflicts
+    }
+
+    /// One line of totals
+    pub fn headline(&self) -> String {
+        format!(
+            "{} candidate versions of {} packages in {} rounds, {} backtracks, {}",
+            self.candidates_tried(),
+            self.candidates.len(),
+            self.rounds,
+            self.backtracks,
+            format_duration(self.elapsed)
+        )
+    }
+
+    /// The hottest packages and conflicts, one per line
+    pub fn details(&self) -> String {
+        let mut out = String::from("Most candidate versions tried:\n");
+        for (name, count) in self.hottest_packages(TOP_ENTRIES) {
+            out.push_str(&format!("  {:<30} {}\n", name, count));
+        }
+        let conflicts = self.top_conflicts(TOP_ENTRIES);
+        if !conflicts.is_empty() {
+            out.push_str("Conflicts that caused the most backtracking:\n");
+            for (conflict, count) in conflicts {
+                out.push_str(&format!("  {:<30} {}\n", conflict.to_string(), count));
+            }
+        }
+        out
+    }
+}
+
+/// Which limit stopped resolution
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BudgetLimit {
+    Timeout(Duration),
+    Backtracks(usize),
+}
+
+/// Resolution ran out of budget
+#[derive(Debug)]
+pub struct ResolutionTooComplex {
+    pub limit: BudgetLimit,
+    pub stats: ResolutionStats,
+}
+
+impl fmt::Display for ResolutionTooComplex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.limit {
+            BudgetLimit::Timeout(limit) => write!(f, "resolution took longer than {}", format_duration(limit))?,
+            BudgetLimit::Backtracks(limit) => write!(f, "resolution gave up after {} backtracks", limit)?,
+        }
+        writeln!(f, " ({})", self.stats.headline())?;
+        write!(f, "{}", self.stats.details())?;
+
+        writeln!(f, "Suggestions:")?;
+        // Packages in the worst conflicts, else the ones with most versions tried
+        let mut hot: Vec<&str> = Vec::new();
+        for (conflict, _) in self.stats.top_conflicts(TOP_ENTRIES) {
+            if hot.len() < 3 && !hot.contains(&conflict.package.as_str()) {
+                hot.push(&conflict.package);
+            }
+        }
+        if hot.is_empty() {
+            hot = self.stats.hottest_packages(3).into_iter().map(|(name, _)| name).collect();
+        }
+        if !hot.is_empty() {
+            writeln!(
+                f,
+                "  - add constraints (-c constraints.txt) pinning {} to narrow the search",
+                hot.join(", ")
+            )?;
+        }
+        if let Some((conflict, _)) = self.stats.top_conflicts(1).into_iter().next() {
+            writeln!(f, "  - loosen the pin {}", conflict)?;
+        }
+        if let BudgetLimit::Timeout(_) = self.limit {
+            write!(f, "  - allow more time with --resolution-timeout")?;
+        } else {
+            write!(f, "  - split the requirements and resolve the conflicting groups separately")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ResolutionTooComplex {}
+
+/// `1m 05s`, `4.2s`
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 60 {
+        format!("{}m {:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{:.1}s", duration.as_secs_f64())
+    }
+}
@@ -5,6 +5,7 @@
 
 use std::collections::HashMap;
 use crate::models::Package;
+use crate::utils::version;
 
 /// Candidate information
 #[derive(Clone, Debug)]
@@ -100,8 +101,7 @@ impl CandidateSelector {
                 candidates
                     .iter()
                     .max_by(|a, b| {
-                        // Simple version comparison (would use proper PEP 440 in production)
-                        a.package.version.cmp(&b.package.version)
+                        version::compare(&a.package.version, &b.package.version)
                     })
                     .cloned()
             }
@@ -115,7 +115,7 @@ impl CandidateSelector {
                         candidates
                             .iter()
                             .max_by(|a, b| {
-                                a.package.version.cmp(&b.package.version)
+                                version::compare(&a.package.version, &b.package.version)
                             })
                             .cloned()
                     })
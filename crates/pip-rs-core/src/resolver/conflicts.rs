@@ -0,0 +1,81 @@
+/// Requirements that can't all hold at once
+///
+/// Every requirement edge naming a package is kept as a [`Demand`], so the
+/// version a resolution pass picked can be checked against all of them and
+/// not just the first one seen. When no release meets them all,
+/// [`ResolutionImpossible`] lists each demand with the chain of packages
+/// that introduced it, the way pip's ResolutionImpossible report does.
+use crate::models::Requirement;
+use std::fmt;
+
+/// One requirement on a package
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Demand {
+    /// The requirement as declared, without its marker
+    pub requirement: Requirement,
+    /// Canonical name of the package that declared it; `None` when requested directly
+    pub required_by: Option<String>,
+}
+
+impl Demand {
+    pub fn new(requirement: &Requirement, required_by: Option<String>) -> Self {
+        Self {
+            requirement: Requirement {
+                marker: None,
+                ..requirement.clone()
+            },
+            required_by,
+        }
+    }
+}
+
+/// No release of a package satisfies every requirement on it
+#[derive(Debug)]
+pub struct ResolutionImpossible {
+    pub package: String,
+    /// Per demand: `name version` of each package from the requested one
+    /// down, then the requirement itself
+    pub chains: Vec<Vec<String>>,
+}
+
+impl fmt::Display for ResolutionImpossible {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "cannot resolve {}: no release satisfies all of", self.package)?;
+        for chain in &self.chains {
+            writeln!(f, "  {}", chain.join(" -> "))?;
+        }
+        write!(
+            f,
+            "Loosen one of these requirements, or constrain the packages that declare them (-c constraints.txt) \
+             to versions that agree"
+        )
+    }
+}
+
+impl std::error::Error for ResolutionImpossible {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_lists_each_chain() {
+        let error = ResolutionImpossible {
+            package: "urllib3".to_string(),
+            chains: vec![
+                vec!["app 1.0".to_string(), "requests 2.31.0".to_string(), "urllib3<2".to_string()],
+                vec!["app 1.0".to_string(), "urllib3>=2".to_string()],
+            ],
+        };
+        assert_eq!(
+            error.to_string().lines().take(3).collect::<Vec<_>>(),
+            [
+                "cannot resolve urllib3: no release satisfies all of",
+                "  app 1.0 -> requests 2.31.0 -> urllib3<2",
+                "  app 1.0 -> urllib3>=2",
+            ]
+        );
+        let demand = Demand::new(&"idna>=3; python_version >= '3.8'".parse().unwrap(), None);
+        assert_eq!(demand.requirement.to_string(), "idna>=3");
+    }
+}
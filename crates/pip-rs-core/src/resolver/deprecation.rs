@@ -0,0 +1,404 @@
+/// Packages that declare themselves deprecated or unmaintained
+///
+/// An advisory check on the releases resolution selected. Projects signal
+/// abandonment in a few ways: the `Development Status :: 7 - Inactive`
+/// classifier (or a classifier saying "deprecated"), a `project_urls` entry
+/// pointing at a deprecation or archival notice, or a banner at the top of
+/// the summary/description ("The sklearn PyPI package is deprecated, use
+/// scikit-learn instead"). All heuristics live here; a banner has to talk
+/// about the project itself, so libraries *about* deprecation (`Deprecated`,
+/// `deprecation`) aren't flagged.
+use crate::utils::names::canonicalize_name;
+use serde::Serialize;
+
+/// Characters of summary + description searched for a banner
+const BANNER_WINDOW: usize = 800;
+
+/// What gave the package away
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DeprecationSignal {
+    /// `Development Status :: 7 - Inactive`
+    InactiveClassifier,
+    /// A classifier that says "deprecated"
+    DeprecatedClassifier,
+    /// A `project_urls` entry labelled or pointing at a deprecation notice
+    ProjectUrl,
+    /// The description opens with a deprecation banner
+    Banner,
+}
+
+/// A selected release that declares itself deprecated
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DeprecationNotice {
+    pub package: String,
+    pub version: String,
+    pub signal: DeprecationSignal,
+    /// The classifier, URL or banner sentence that matched
+    pub evidence: String,
+    /// Replacement the description names, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub successor: Option<String>,
+}
+
+impl DeprecationNotice {
+    /// `sklearn 0.0.post12 (consider scikit-learn)`
+    pub fn describe(&self) -> String {
+        match &self.successor {
+            Some(successor) => format!("{} {} (consider {})", self.package, self.version, successor),
+            None => format!("{} {} ({})", self.package, self.version, self.evidence),
+        }
+    }
+}
+
+/// Inspect the `info` object of a release's JSON API response
+pub fn detect(package: &str, version: &str, info: &serde_json::Value) -> Option<DeprecationNotice> {
+    let text = format!(
+        "{}\n\n{}",
+        info["summary"].as_str().unwrap_or_default(),
+        info["description"].as_str().unwrap_or_default()
+    );
+    let banner = banner(package, &text);
+    let notice = |signal, evidence: String| DeprecationNotice {
+        package: package.to_string(),
+        version: version.to_string(),
+        signal,
+        evidence,
+        successor: banner.as_ref().and_then(|(_, successor)| successor.clone()),
+    };
+
+    let classifiers = info["classifiers"].as_array().into_iter().flatten().filter_map(|c| c.as_str());
+    for classifier in classifiers {
+        if classifier.starts_with("Development Status :: 7") {
+            return Some(notice(DeprecationSignal::InactiveClassifier, classifier.to_string()));
+        }
+        if classifier.to_lowercase().contains("deprecated") {
+            return Some(notice(DeprecationSignal::DeprecatedClassifier, classifier.to_string()));
+        }
+    }
+    if let Some((sentence, _)) = &banner {
+        return Some(notice(DeprecationSignal::Banner, sentence.clone()));
+    }
+    let urls = info["project_urls"].as_object().into_iter().flatten();
+    for (label, url) in urls {
+        let url = url.as_str().unwrap_or_default();
+        if notice_url(label, url) {
+            return Some(notice(DeprecationSignal::ProjectUrl, format!("{}: {}", label, url)));
+        }
+    }
+    None
+}
+
+/// The consolidated warning for everything `notices` flagged
+pub fn warning(notices: &[DeprecationNotice]) -> Option<String> {
+    match notices {
+        [] => None,
+        [notice] => Some(match &notice.successor {
+            Some(successor) => format!(
+                "{} {} is deprecated; consider {}",
+                notice.package, notice.version, successor
+            ),
+            None => format!("{} {} is deprecated or unmaintained ({})", notice.package, notice.version, notice.evidence),
+        }),
+        _ => {
+            let listed: Vec<String> = notices.iter().map(DeprecationNotice::describe).collect();
+            Some(format!("{} selected packages are deprecated or unmaintained: {}", notices.len(), listed.join(", ")))
+        }
+    }
+}
+
+/// Fetch each release's metadata and check it; releases whose metadata
+/// can't be fetched are passed over, this is advisory only
+pub async fn scan(releases: &[(String, String)]) -> Vec<DeprecationNotice> {
+    use futures::stream::{self, StreamExt};
+
+    let concurrency = crate::utils::resources::ResourceBudget::global().network;
+    let found: Vec<Option<DeprecationNotice>> = stream::iter(releases.iter().cloned())
+        .map(|(name, version)| async move {
+            match crate::network::release_info(&name, &version).await {
+                Ok(release) => detect(&name, &version, &release["info"]),
+                Err(e) => {
+                    tracing::debug!("deprecation check skipped {} {}: {}", name, version, e);
+                    None
+                }
+            }
+        })
+        .buffered(concurrency)
+        .collect()
+        .await;
+    found.into_iter().flatten().collect()
+}
+
+/// A project URL labelled as, or pointing at, a deprecation/archival notice
+fn notice_url(label: &str, url: &str) -> bool {
+    let label = label.to_lowercase();
+    let url = url.to_lowercase();
+    ["deprecat", "archived", "archival", "unmaintained", "end of life"]
+        .iter()
+        .any(|word| label.contains(word))
+        || ["deprecation-notice", "deprecation_notice", "deprecated.html", "archival-notice"]
+            .iter()
+            .any(|word| url.contains(word))
+}
+
+/// What a project says about itself when it's done
+const STATES: &[&str] = &[
+    "is deprecated",
+    "is now deprecated",
+    "has been deprecated",
+    "was deprecated",
+    "is unmaintained",
+    "is no longer maintained",
+    "is no longer being maintained",
+    "is not maintained anymore",
+    "is not maintained",
+    "is abandoned",
+    "has been archived",
+    "is archived",
+    "has been discontinued",
+    "has been renamed to",
+    "was renamed to",
+];
+
+/// Words allowed between the project's name and its state
+const FILLERS: &[&str] = &["pypi", "package", "project", "library", "module", "distribution", "repository"];
+
+/// Phrases that introduce the replacement
+const SUCCESSOR_CUES: &[&str] = &[
+    "renamed to ",
+    "replaced by ",
+    "superseded by ",
+    "in favor of ",
+    "in favour of ",
+    "migrate to ",
+    "switch to ",
+    "successor is ",
+    "pip install ",
+    "consider ",
+    "use ",
+];
+
+/// The banner sentence and the successor it (or the next sentences) names
+fn banner(package: &str, text: &str) -> Option<(String, Option<String>)> {
+    let window: String = text.chars().take(BANNER_WINDOW).collect();
+    let cleaned: String = window
+        .to_lowercase()
+        .chars()
+        .filter(|c| !matches!(c, '*' | '`' | '#' | '>' | '"' | '\''))
+        .collect();
+    let sentences = sentences(&cleaned);
+    let subjects = subjects(package);
+
+    let index = sentences
+        .iter()
+        .position(|sentence| is_heading(sentence) || declares_state(sentence, &subjects))?;
+    let successor = sentences[index..]
+        .iter()
+        .take(3)
+        .find_map(|sentence| successor(sentence, package));
+    Some((sentences[index].clone(), successor))
+}
+
+/// Split on sentence ends and blank lines; lines within a paragraph join
+fn sentences(text: &str) -> Vec<String> {
+    text.split("\n\n")
+        .flat_map(|paragraph| {
+            let paragraph = paragraph.split_whitespace().collect::<Vec<_>>().join(" ");
+            paragraph
+                .split(". ")
+                .flat_map(|s| s.split("! "))
+                .map(|s| s.trim().trim_end_matches(['.', '!']).to_string())
+                .filter(|s| !s.is_empty())
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// How a description refers to its own project
+fn subjects(package: &str) -> Vec<String> {
+    let mut subjects: Vec<String> = FILLERS.iter().map(|filler| format!("this {}", filler)).collect();
+    let lower = package.to_lowercase();
+    let canonical = canonicalize_name(package);
+    subjects.push(canonical.replace('-', "_"));
+    subjects.push(canonical);
+    subjects.push(lower);
+    subjects.dedup();
+    subjects
+}
+
+/// `DEPRECATED: ...`, `Deprecated`, `Deprecation notice`, `Unmaintained!`
+fn is_heading(sentence: &str) -> bool {
+    let sentence = sentence.trim_start_matches(['[', '(', ' ', '!', ':', '-']);
+    ["deprecated", "deprecation notice", "unmaintained", "warning: deprecated", "note: deprecated"]
+        .iter()
+        .any(|word| {
+            sentence.strip_prefix(word).is_some_and(|rest| {
+                rest.is_empty() || rest.starts_with([':', '-', '!', ']', ')']) || rest.starts_with(" -")
+            })
+        })
+}
+
+/// `<subject> [fillers] <state>`, e.g. "the sklearn pypi package is deprecated"
+fn declares_state(sentence: &str, subjects: &[String]) -> bool {
+    subjects.iter().any(|subject| {
+        sentence.match_indices(subject.as_str()).any(|(at, _)| {
+            // Whole words only: "flake8" must not match inside "pyflake8s"
+            let before = sentence[..at].chars().next_back();
+            if before.is_some_and(|c| c.is_alphanumeric() || c == '-' || c == '_') {
+                return false;
+            }
+            let mut rest = sentence[at + subject.len()..].trim_start();
+            while let Some(filler) = FILLERS.iter().find(|filler| {
+                rest.strip_prefix(**filler).is_some_and(|after| after.starts_with(' '))
+            }) {
+                rest = rest[filler.len()..].trim_start();
+            }
+            let rest = rest.trim_start_matches([',', ':', ' ']);
+            STATES.iter().any(|state| rest.starts_with(state))
+        })
+    })
+}
+
+/// The package name after a successor cue, if it looks like one
+fn successor(sentence: &str, package: &str) -> Option<String> {
+    const NOT_NAMES: &[&str] = &["of", "the", "a", "an", "it", "this", "that", "instead", "at", "with", "your", "in"];
+    let own = canonicalize_name(package);
+    SUCCESSOR_CUES.iter().find_map(|cue| {
+        sentence.match_indices(cue).find_map(|(at, _)| {
+            if at > 0 && sentence[..at].ends_with(|c: char| c.is_alphanumeric()) {
+                return None;
+            }
+            let after = sentence[at + cue.len()..].trim_start_matches(['[', '(', ' ']);
+            let name: String = after
+                .chars()
+                .take_while(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+                .collect();
+            let name = name.trim_end_matches(['.', '-', '_']);
+            let plausible = name.len() > 1
+                && name.chars().any(|c| c.is_ascii_alphabetic())
+                && !NOT_NAMES.contains(&name)
+                && canonicalize_name(name) != own;
+            plausible.then(|| name.to_string())
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// The signal and suggested replacement a snippet should produce
+    type Expected = Option<(DeprecationSignal, Option<&'static str>)>;
+
+    /// Metadata snippets of well-known deprecated projects, and of projects
+    /// that merely talk about deprecation
+    fn fixtures() -> Vec<(&'static str, serde_json::Value, Expected)> {
+        vec![
+            (
+                "sklearn",
+                json!({
+                    "summary": "deprecated sklearn package, use scikit-learn instead",
+                    "description": "# sklearn: deprecated package\n\nThe `sklearn` PyPI package is deprecated, \
+                                    use `scikit-learn` instead.\n\nThe reason for the deprecation is ...",
+                    "classifiers": [],
+                }),
+                Some((DeprecationSignal::Banner, Some("scikit-learn"))),
+            ),
+            (
+                "oauth2client",
+                json!({
+                    "summary": "OAuth 2.0 client library",
+                    "description": "oauth2client\n============\n\nNote: oauth2client is now deprecated. No more \
+                                    features will be added to the libraries and the core team is turning down \
+                                    support. We recommend you use [google-auth](https://google-auth.readthedocs.io) \
+                                    and oauthlib.",
+                    "classifiers": [
+                        "Development Status :: 7 - Inactive",
+                        "Programming Language :: Python :: 3",
+                    ],
+                }),
+                Some((DeprecationSignal::InactiveClassifier, Some("google-auth"))),
+            ),
+            (
+                "pep8",
+                json!({
+                    "summary": "Python style guide checker",
+                    "description": "pep8 - Python style guide checker\n=================================\n\n\
+                                    .. note::\n\n    This package has been renamed to ``pycodestyle``. Use of \
+                                    the pep8 tool will be removed in a future release.",
+                    "classifiers": ["Development Status :: 5 - Production/Stable"],
+                }),
+                Some((DeprecationSignal::Banner, Some("pycodestyle"))),
+            ),
+            (
+                "old-helpers",
+                json!({
+                    "summary": "Assorted helpers",
+                    "description": "Assorted helpers.",
+                    "classifiers": ["Development Status :: 5 - Production/Stable"],
+                    "project_urls": {"Deprecation notice": "https://example.org/old-helpers"},
+                }),
+                Some((DeprecationSignal::ProjectUrl, None)),
+            ),
+            (
+                "Deprecated",
+                json!({
+                    "summary": "Python @deprecated decorator to deprecate old python classes, functions or methods.",
+                    "description": "Deprecated Library\n==================\n\nDeprecated is Easy to Use\n\n\
+                                    If you need to mark a function or a method as deprecated, you can use the \
+                                    `@deprecated` decorator.",
+                    "classifiers": ["Development Status :: 5 - Production/Stable"],
+                    "project_urls": {"Documentation": "https://deprecated.readthedocs.io/en/latest/"},
+                }),
+                None,
+            ),
+            (
+                "deprecation",
+                json!({
+                    "summary": "A library to handle automated deprecations",
+                    "description": "The deprecation library provides a `deprecated` decorator and a \
+                                    `fail_if_not_removed` decorator for your tests. When an API is deprecated, \
+                                    callers get a warning.",
+                    "classifiers": ["Development Status :: 5 - Production/Stable"],
+                }),
+                None,
+            ),
+            (
+                "requests",
+                json!({
+                    "summary": "Python HTTP for Humans.",
+                    "description": "# Requests\n\nRequests is a simple, yet elegant, HTTP library.",
+                    "classifiers": ["Development Status :: 5 - Production/Stable"],
+                    "project_urls": {"Documentation": "https://requests.readthedocs.io"},
+                }),
+                None,
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_fixtures() {
+        for (package, info, expected) in fixtures() {
+            let notice = detect(package, "1.0", &info);
+            let found = notice.as_ref().map(|n| (n.signal, n.successor.as_deref()));
+            assert_eq!(found, expected, "{}: {:?}", package, notice);
+        }
+    }
+
+    #[test]
+    fn test_consolidated_warning() {
+        let sklearn = detect("sklearn", "0.0.post12", &fixtures()[0].1).unwrap();
+        assert_eq!(warning(std::slice::from_ref(&sklearn)).unwrap(), "sklearn 0.0.post12 is deprecated; consider scikit-learn");
+
+        let mut old = detect("old-helpers", "2.0", &fixtures()[3].1).unwrap();
+        old.successor = None;
+        assert_eq!(
+            warning(&[sklearn, old]).unwrap(),
+            "2 selected packages are deprecated or unmaintained: sklearn 0.0.post12 (consider scikit-learn), \
+             old-helpers 2.0 (Deprecation notice: https://example.org/old-helpers)"
+        );
+        assert!(warning(&[]).is_none());
+        assert!(is_heading("deprecated: use foo instead") && !is_heading("deprecated library"));
+    }
+}
@@ -1,9 +1,20 @@
 /// Direct URL support for package dependencies
 /// 
 /// This module handles direct URLs in package specifications,
-/// enabling support for git+, file://, and other direct URL schemes.
+/// enabling support for git+, file://, and other direct URL schemes,
+/// and the PEP 610 `direct_url.json` that records them in a dist-info.
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
 
-use std::collections::HashMap;
+/// PEP 610 file recording where a distribution was installed from
+pub const DIRECT_URL_FILE: &str = "direct_url.json";
+
+/// Archive extensions that mark a `file://` URL as an artifact rather
+/// than a project directory
+const ARCHIVE_EXTENSIONS: &[&str] = &[".whl", ".tar.gz", ".tar.bz2", ".tar.xz", ".tgz", ".zip"];
 
 /// Direct URL information
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -131,6 +142,143 @@ impl DirectUrl {
     pub fn is_local(&self) -> bool {
         matches!(self.url_type, DirectUrlType::File)
     }
+
+    /// The PEP 610 record for an install from this URL; `sha256` is the
+    /// hash of the downloaded archive, when there was one
+    pub fn to_json(&self, sha256: Option<&str>) -> DirectUrlJson {
+        let url = self.url.split('#').next().unwrap_or(&self.url);
+        let mut json = DirectUrlJson {
+            url: url.to_string(),
+            subdirectory: self.subdirectory.clone(),
+            vcs_info: None,
+            archive_info: None,
+            dir_info: None,
+        };
+
+        if self.is_vcs() {
+            // `git+https://host/repo.git@v1.0` -> `https://host/repo.git` at `v1.0`
+            let (vcs, location) = url.split_once('+').unwrap_or(("git", url));
+            let path_start = location.rfind('/').unwrap_or(0);
+            let (location, requested_revision) = match location[path_start..].rfind('@') {
+                Some(at) => (&location[..path_start + at], Some(location[path_start + at + 1..].to_string())),
+                None => (location, None),
+            };
+            json.url = location.to_string();
+            json.vcs_info = Some(VcsInfo {
+                vcs: vcs.to_string(),
+                requested_revision,
+                commit_id: None,
+            });
+        } else if self.editable || (self.is_local() && !ARCHIVE_EXTENSIONS.iter().any(|ext| url.ends_with(ext))) {
+            json.dir_info = Some(DirInfo { editable: self.editable });
+        } else {
+            json.archive_info = Some(ArchiveInfo {
+                hashes: sha256.map(|hash| BTreeMap::from([("sha256".to_string(), hash.to_string())])),
+                hash: sha256.map(|hash| format!("sha256={}", hash)),
+            });
+        }
+        json
+    }
+}
+
+/// The contents of a PEP 610 `direct_url.json`
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DirectUrlJson {
+    /// Where the distribution came from, without any `vcs+` prefix
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subdirectory: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vcs_info: Option<VcsInfo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archive_info: Option<ArchiveInfo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dir_info: Option<DirInfo>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VcsInfo {
+    pub vcs: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requested_revision: Option<String>,
+    /// The exact revision installed; unknown until the checkout is made
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit_id: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveInfo {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hashes: Option<BTreeMap<String, String>>,
+    /// Deprecated single `<algorithm>=<hex>` form, still read by older tools
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DirInfo {
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub editable: bool,
+}
+
+impl DirectUrlJson {
+    /// Read `direct_url.json` from a dist-info directory
+    pub fn read(dist_info: &Path) -> Option<Self> {
+        let content = fs::read_to_string(dist_info.join(DIRECT_URL_FILE)).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    pub fn write(&self, dist_info: &Path) -> Result<()> {
+        fs::write(dist_info.join(DIRECT_URL_FILE), self.to_json_string())?;
+        Ok(())
+    }
+
+    /// The file's contents
+    pub fn to_json_string(&self) -> String {
+        serde_json::to_string(self).expect("direct_url.json fields always serialize")
+    }
+
+    pub fn is_editable(&self) -> bool {
+        self.dir_info.as_ref().is_some_and(|dir| dir.editable)
+    }
+
+    /// The requirement that reinstalls the same thing, as `pip freeze`
+    /// prints it: `name @ git+https://...@<commit>`, `name @ <url>` or,
+    /// for editables, `-e <url>`
+    pub fn requirement(&self, name: &str) -> String {
+        if self.is_editable() {
+            return format!("-e {}", self.url);
+        }
+        format!("{} @ {}", name, self.pep508_url())
+    }
+
+    /// The URL in PEP 508 form: `vcs+` prefix and pinned revision for
+    /// VCS installs, hash and subdirectory as fragments
+    pub fn pep508_url(&self) -> String {
+        let mut url = match &self.vcs_info {
+            Some(vcs) => {
+                let mut url = format!("{}+{}", vcs.vcs, self.url);
+                if let Some(revision) = vcs.commit_id.as_ref().or(vcs.requested_revision.as_ref()) {
+                    url.push('@');
+                    url.push_str(revision);
+                }
+                url
+            }
+            None => self.url.clone(),
+        };
+        let mut fragments = Vec::new();
+        if let Some(hash) = self.archive_info.as_ref().and_then(|archive| archive.hash.as_ref()) {
+            fragments.push(hash.clone());
+        }
+        if let Some(subdirectory) = &self.subdirectory {
+            fragments.push(format!("subdirectory={}", subdirectory));
+        }
+        if !fragments.is_empty() {
+            url.push('#');
+            url.push_str(&fragments.join("&"));
+        }
+        url
+    }
 }
 
 /// Direct URL conflict detector
@@ -274,6 +422,56 @@ mod tests {
         assert!(conflict.is_none());
     }
 
+    #[test]
+    fn test_direct_url_json_round_trip() {
+        let vcs = DirectUrl::parse("git+https://github.com/user/repo.git@v1.2#subdirectory=pkg").unwrap();
+        let mut json = vcs.to_json(None);
+        json.vcs_info.as_mut().unwrap().commit_id = Some("abc123".to_string());
+        let text = json.to_json_string();
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&text).unwrap(),
+            serde_json::json!({
+                "url": "https://github.com/user/repo.git",
+                "subdirectory": "pkg",
+                "vcs_info": {"vcs": "git", "requested_revision": "v1.2", "commit_id": "abc123"},
+            })
+        );
+        assert_eq!(serde_json::from_str::<DirectUrlJson>(&text).unwrap(), json);
+        assert_eq!(json.requirement("repo"), "repo @ git+https://github.com/user/repo.git@abc123#subdirectory=pkg");
+
+        let editable = DirectUrl::parse("-e file:///src/mytool").unwrap().to_json(None);
+        let text = editable.to_json_string();
+        assert_eq!(text, r#"{"url":"file:///src/mytool","dir_info":{"editable":true}}"#);
+        assert_eq!(serde_json::from_str::<DirectUrlJson>(&text).unwrap(), editable);
+        assert_eq!(editable.requirement("mytool"), "-e file:///src/mytool");
+    }
+
+    #[test]
+    fn test_direct_url_json_archives_and_directories() {
+        let archive = DirectUrl::parse("https://files.example/demo-1.0-py3-none-any.whl#sha256=old")
+            .unwrap()
+            .to_json(Some("aaa"));
+        assert_eq!(archive.url, "https://files.example/demo-1.0-py3-none-any.whl");
+        let value = serde_json::to_value(&archive).unwrap();
+        assert_eq!(value["archive_info"], serde_json::json!({"hashes": {"sha256": "aaa"}, "hash": "sha256=aaa"}));
+        assert_eq!(archive.requirement("demo"), "demo @ https://files.example/demo-1.0-py3-none-any.whl#sha256=aaa");
+
+        let directory = DirectUrl::parse("file:///src/demo").unwrap().to_json(None);
+        assert_eq!(directory.dir_info, Some(DirInfo { editable: false }));
+        assert_eq!(serde_json::to_value(&directory).unwrap()["dir_info"], serde_json::json!({}));
+        assert_eq!(directory.requirement("demo"), "demo @ file:///src/demo");
+
+        // Files written by other tools: user@host isn't a revision, unknown keys are ignored
+        let foreign: DirectUrlJson = serde_json::from_str(
+            r#"{"url": "ssh://git@github.com/user/repo.git", "vcs_info": {"vcs": "git", "commit_id": "def"}, "extra": 1}"#,
+        )
+        .unwrap();
+        assert_eq!(foreign.requirement("repo"), "repo @ git+ssh://git@github.com/user/repo.git@def");
+        let ssh = DirectUrl::parse("git+ssh://git@github.com/user/repo.git").unwrap().to_json(None);
+        assert_eq!(ssh.url, "ssh://git@github.com/user/repo.git");
+        assert_eq!(ssh.vcs_info.unwrap().requested_revision, None);
+    }
+
     #[test]
     fn test_url_type_from_scheme() {
         assert_eq!(DirectUrlType::from_scheme("git"), DirectUrlType::Git);
@@ -0,0 +1,53 @@
+/// Why resolution stopped without a set of packages
+///
+/// [`Resolver::resolve`](super::Resolver::resolve) returns a
+/// [`ResolutionError`]; packages it merely couldn't fetch are skipped and
+/// reported through [`Resolver::unresolved`](super::Resolver::unresolved)
+/// and [`Resolver::not_found`](super::Resolver::not_found) instead.
+use super::budget::ResolutionTooComplex;
+use super::conflicts::ResolutionImpossible;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum ResolutionError {
+    /// No release of a package meets every requirement on it
+    Impossible(ResolutionImpossible),
+    /// The backtrack or time budget ran out first
+    TooComplex(ResolutionTooComplex),
+    /// Ctrl-C or SIGTERM between rounds
+    Cancelled,
+}
+
+impl ResolutionError {
+    /// The package resolution failed on, when there is one
+    pub fn package(&self) -> Option<&str> {
+        match self {
+            ResolutionError::Impossible(impossible) => Some(&impossible.package),
+            ResolutionError::TooComplex(_) | ResolutionError::Cancelled => None,
+        }
+    }
+}
+
+impl fmt::Display for ResolutionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResolutionError::Impossible(impossible) => write!(f, "{}", impossible),
+            ResolutionError::TooComplex(too_complex) => write!(f, "{}", too_complex),
+            ResolutionError::Cancelled => write!(f, "resolution cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for ResolutionError {}
+
+impl From<ResolutionImpossible> for ResolutionError {
+    fn from(impossible: ResolutionImpossible) -> Self {
+        ResolutionError::Impossible(impossible)
+    }
+}
+
+impl From<ResolutionTooComplex> for ResolutionError {
+    fn from(too_complex: ResolutionTooComplex) -> Self {
+        ResolutionError::TooComplex(too_complex)
+    }
+}
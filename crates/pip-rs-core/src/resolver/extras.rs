@@ -1,53 +1,50 @@
 /// Extras resolution - handles optional dependencies
-use crate::models::{Package, Requirement};
-use anyhow::Result;
+use crate::models::{Environment, Marker, Package, Requirement};
+use crate::utils::names::canonicalize_name;
 
-/// Resolve extras for a package
-pub fn resolve_extras(package: &Package, requested_extras: &[String]) -> Result<Vec<Requirement>> {
+/// Dependencies `requested_extras` add to `package` in `env`: those whose
+/// marker only holds with one of the extras requested
+pub fn resolve_extras(package: &Package, requested_extras: &[String], env: &Environment) -> Vec<Requirement> {
     let mut extra_deps = Vec::new();
 
     if requested_extras.is_empty() {
-        return Ok(extra_deps);
+        return extra_deps;
     }
 
-    // Parse requires_dist to find extras
     for dep_str in &package.requires_dist {
-        if let Ok(req) = dep_str.parse::<Requirement>() {
-            // Check if this dependency is conditional on extras
-            if let Some(marker) = &req.marker {
-                // Look for "extra ==" pattern in marker
-                for extra in requested_extras {
-                    if marker.contains(&format!("extra == '{}'", extra))
-                        || marker.contains(&format!("extra == \"{}\"", extra))
-                    {
-                        extra_deps.push(req.clone());
-                        break;
-                    }
-                }
-            }
+        let Ok(req) = dep_str.parse::<Requirement>() else {
+            continue;
+        };
+        let Some(marker) = req.marker.as_deref().and_then(|m| Marker::parse(m).ok()) else {
+            continue;
+        };
+        if !marker.evaluate(env) && requested_extras.iter().any(|extra| marker.evaluate_with_extra(env, extra)) {
+            extra_deps.push(req);
         }
     }
 
-    Ok(extra_deps)
+    extra_deps
 }
 
-/// Get available extras for a package
+/// Get available extras for a package, normalized and sorted
 pub fn get_available_extras(package: &Package) -> Vec<String> {
-    let mut extras = std::collections::HashSet::new();
+    let mut extras = std::collections::BTreeSet::new();
 
     for dep_str in &package.requires_dist {
         if let Ok(req) = dep_str.parse::<Requirement>() {
             if let Some(marker) = &req.marker {
                 // Extract extra names from markers like "extra == 'security'"
-                if let Some(start) = marker.find("extra == '") {
-                    let rest = &marker[start + 10..];
-                    if let Some(end) = rest.find('\'') {
-                        extras.insert(rest[..end].to_string());
-                    }
-                } else if let Some(start) = marker.find("extra == \"") {
-                    let rest = &marker[start + 10..];
-                    if let Some(end) = rest.find('"') {
-                        extras.insert(rest[..end].to_string());
+                for (at, _) in marker.match_indices("extra") {
+                    let rest = marker[at + 5..].trim_start();
+                    let Some(rest) = rest.strip_prefix("==") else {
+                        continue;
+                    };
+                    let rest = rest.trim_start();
+                    let Some(quote) = rest.chars().next().filter(|c| matches!(c, '\'' | '"')) else {
+                        continue;
+                    };
+                    if let Some(end) = rest[1..].find(quote) {
+                        extras.insert(canonicalize_name(&rest[1..1 + end]));
                     }
                 }
             }
@@ -57,6 +54,16 @@ pub fn get_available_extras(package: &Package) -> Vec<String> {
     extras.into_iter().collect()
 }
 
+/// Requested extras `package` doesn't declare, as pip warns about them
+pub fn unknown_extras(package: &Package, requested_extras: &[String]) -> Vec<String> {
+    let available = get_available_extras(package);
+    requested_extras
+        .iter()
+        .filter(|extra| !available.contains(&canonicalize_name(extra)))
+        .cloned()
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -83,4 +90,29 @@ mod tests {
         let extras = get_available_extras(&package);
         assert!(extras.contains(&"security".to_string()));
     }
+
+    #[test]
+    fn test_requests_socks_extra() {
+        // requests 2.31.0's Requires-Dist, as the JSON API serves it
+        let package = Package::new("requests".to_string(), "2.31.0".to_string()).with_requires(vec![
+            "charset-normalizer<4,>=2".to_string(),
+            "idna<4,>=2.5".to_string(),
+            "urllib3<3,>=1.21.1".to_string(),
+            "certifi>=2017.4.17".to_string(),
+            "PySocks!=1.5.7,>=1.5.6; extra == \"socks\"".to_string(),
+            "chardet<6,>=3.0.2; extra == \"use_chardet_on_py3\"".to_string(),
+        ]);
+        let env = Environment::current();
+
+        let socks: Vec<String> = resolve_extras(&package, &["socks".to_string()], &env)
+            .iter()
+            .map(|req| req.to_string())
+            .collect();
+        assert_eq!(socks, ["pysocks!=1.5.7,>=1.5.6; extra == \"socks\""]);
+        assert_eq!(resolve_extras(&package, &["use-chardet-on-py3".to_string()], &env)[0].name, "chardet");
+        assert!(resolve_extras(&package, &[], &env).is_empty());
+
+        assert_eq!(get_available_extras(&package), ["socks", "use-chardet-on-py3"]);
+        assert_eq!(unknown_extras(&package, &["socks".to_string(), "http2".to_string()]), ["http2"]);
+    }
 }
@@ -0,0 +1,595 @@
+/// Import of foreign lock formats (`pip lock --import`)
+///
+/// Converts `poetry.lock` and `uv.lock` into a pip-rs [`LockFile`]. Names,
+/// versions, markers, extras, hashes and sources (index, git, local path)
+/// carry over; anything the pip-rs lock can't represent is listed in the
+/// [`ConversionSummary`] instead of being dropped silently. Parsing keys off
+/// the declared format version and ignores fields it doesn't know, so newer
+/// minor revisions of either format still import.
+use super::lockfile::{LockFile, LockedPackage};
+use super::DependencyMode;
+use crate::utils::names::canonicalize_name;
+use crate::utils::text_encoding;
+use anyhow::{anyhow, bail, Context, Result};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use toml::Value;
+
+/// Newest `lock-version` of each poetry.lock major this importer knows
+const POETRY_KNOWN: &[(u64, u64)] = &[(1, 1), (2, 1)];
+
+/// uv.lock `version` this importer knows
+const UV_KNOWN: i64 = 1;
+
+/// A lock format pip-rs can import
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForeignFormat {
+    Poetry,
+    Uv,
+}
+
+impl ForeignFormat {
+    /// Detect the format from the file name, falling back to the content
+    pub fn detect(path: &Path, content: &str) -> Option<Self> {
+        match path.file_name().and_then(|n| n.to_str()) {
+            Some("poetry.lock") => return Some(ForeignFormat::Poetry),
+            Some("uv.lock") => return Some(ForeignFormat::Uv),
+            _ => {}
+        }
+        let document: toml::Table = toml::from_str(content).ok()?;
+        if document.get("metadata").and_then(|m| m.get("lock-version")).is_some() {
+            Some(ForeignFormat::Poetry)
+        } else if document.get("version").is_some_and(Value::is_integer) && document.contains_key("package") {
+            Some(ForeignFormat::Uv)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ForeignFormat::Poetry => "poetry.lock",
+            ForeignFormat::Uv => "uv.lock",
+        }
+    }
+}
+
+/// What an import kept and what it couldn't carry over
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConversionSummary {
+    pub format: ForeignFormat,
+    /// The format version declared by the file
+    pub format_version: String,
+    pub imported: usize,
+    /// Information that has no place in a pip-rs lock
+    pub dropped: Vec<String>,
+}
+
+/// A converted lock and its summary
+#[derive(Debug, Clone)]
+pub struct ImportedLock {
+    pub lock: LockFile,
+    pub summary: ConversionSummary,
+}
+
+/// Read and convert a foreign lock file
+pub fn import_lock(path: &Path) -> Result<ImportedLock> {
+    let content = text_encoding::read_text(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let format = ForeignFormat::detect(path, &content)
+        .ok_or_else(|| anyhow!("{} is neither a poetry.lock nor a uv.lock file", path.display()))?;
+    let base_dir = path.parent().unwrap_or(Path::new("."));
+    convert(&content, format, base_dir)
+}
+
+/// Convert lock `content`; relative paths resolve against `base_dir`
+pub fn convert(content: &str, format: ForeignFormat, base_dir: &Path) -> Result<ImportedLock> {
+    let document: toml::Table = toml::from_str(content).with_context(|| format!("{} is not valid TOML", format.as_str()))?;
+    let mut converter = Converter {
+        base_dir,
+        dropped: Vec::new(),
+        extra_artifacts: Vec::new(),
+    };
+    let (format_version, python, packages) = match format {
+        ForeignFormat::Poetry => converter.poetry(&document)?,
+        ForeignFormat::Uv => converter.uv(&document)?,
+    };
+
+    if !converter.extra_artifacts.is_empty() {
+        converter.dropped.push(format!(
+            "hashes of alternative artifacts for {} package(s) ({}); only the hash of the artifact pip-rs installs is kept",
+            converter.extra_artifacts.len(),
+            converter.extra_artifacts.join(", ")
+        ));
+    }
+
+    let mut lock = LockFile::from_packages(Vec::new(), python).with_dependency_mode(DependencyMode::All);
+    lock.packages = packages
+        .into_iter()
+        .map(|p| (format!("{}-{}", p.name, p.version), p))
+        .collect();
+    lock.validate()?;
+
+    let summary = ConversionSummary {
+        format,
+        format_version,
+        imported: lock.packages.len(),
+        dropped: converter.dropped,
+    };
+    Ok(ImportedLock { lock, summary })
+}
+
+struct Converter<'a> {
+    base_dir: &'a Path,
+    dropped: Vec<String>,
+    /// Packages that listed more artifacts than the one whose hash was kept
+    extra_artifacts: Vec<String>,
+}
+
+/// One downloadable file listed for a package
+struct Artifact {
+    name: String,
+    url: Option<String>,
+    hash: Option<String>,
+}
+
+impl Converter<'_> {
+    fn poetry(&mut self, document: &toml::Table) -> Result<(String, String, Vec<LockedPackage>)> {
+        let metadata = document.get("metadata").ok_or_else(|| anyhow!("poetry.lock has no [metadata] table"))?;
+        let version = metadata
+            .get("lock-version")
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow!("poetry.lock has no metadata.lock-version"))?
+            .to_string();
+        let (major, minor) = parse_version(&version).ok_or_else(|| anyhow!("unrecognized poetry lock-version '{}'", version))?;
+        let newest = POETRY_KNOWN
+            .iter()
+            .find(|(known, _)| *known == major)
+            .map(|(_, newest)| *newest)
+            .ok_or_else(|| anyhow!("poetry lock-version {} is not supported (known: 1.x, 2.x)", version))?;
+        if minor > newest {
+            self.dropped.push(format!(
+                "fields introduced after poetry lock-version {}.{} (file declares {}) were ignored",
+                major, newest, version
+            ));
+        }
+        let python = metadata.get("python-versions").and_then(Value::as_str).unwrap_or_default().to_string();
+        // lock-version 1.x lists files under [metadata.files], keyed by name
+        let legacy_files = metadata.get("files").and_then(Value::as_table);
+
+        let mut dev_only = Vec::new();
+        let mut packages = Vec::new();
+        for package in document.get("package").and_then(Value::as_array).into_iter().flatten() {
+            let name = str_field(package, "name")?;
+            let version = str_field(package, "version")?;
+
+            let groups: Vec<&str> = match package.get("groups").and_then(Value::as_array) {
+                Some(groups) => groups.iter().filter_map(Value::as_str).collect(),
+                None => vec![package.get("category").and_then(Value::as_str).unwrap_or("main")],
+            };
+            if !groups.contains(&"main") {
+                dev_only.push(format!("{} ({})", name, groups.join(", ")));
+            }
+
+            let files = package
+                .get("files")
+                .or_else(|| legacy_files.and_then(|f| f.get(name)))
+                .and_then(Value::as_array);
+            let artifacts: Vec<Artifact> = files
+                .into_iter()
+                .flatten()
+                .map(|file| Artifact {
+                    name: file.get("file").and_then(Value::as_str).unwrap_or_default().to_string(),
+                    url: None,
+                    hash: file.get("hash").and_then(Value::as_str).map(String::from),
+                })
+                .collect();
+            let (mut url, hash) = self.preferred(name, artifacts);
+
+            if let Some(source) = package.get("source") {
+                let kind = source.get("type").and_then(Value::as_str).unwrap_or_default();
+                let source_url = source.get("url").and_then(Value::as_str).unwrap_or_default();
+                match kind {
+                    "git" => {
+                        let reference = source
+                            .get("resolved_reference")
+                            .or_else(|| source.get("reference"))
+                            .and_then(Value::as_str);
+                        url = Some(vcs_url(source_url, reference));
+                    }
+                    "directory" | "file" => url = Some(self.file_url(source_url)),
+                    "url" => url = Some(source_url.to_string()),
+                    _ => self.dropped.push(format!(
+                        "{}: source index '{}' ({}); the lock installs from the configured index",
+                        name,
+                        source.get("reference").and_then(Value::as_str).unwrap_or(source_url),
+                        source_url
+                    )),
+                }
+            }
+            if package.get("develop").and_then(Value::as_bool) == Some(true) {
+                self.dropped.push(format!("{}: editable (develop) install; imported as a regular install", name));
+            }
+
+            let markers = match package.get("markers") {
+                Some(Value::String(markers)) => Some(markers.clone()),
+                Some(Value::Table(per_group)) => {
+                    let distinct: std::collections::BTreeSet<&str> = per_group.values().filter_map(Value::as_str).collect();
+                    if distinct.len() == 1 {
+                        distinct.into_iter().next().map(String::from)
+                    } else {
+                        self.dropped.push(format!("{}: per-group markers ({})", name, per_group_markers(per_group)));
+                        None
+                    }
+                }
+                _ => None,
+            };
+
+            packages.push(LockedPackage {
+                name: name.to_string(),
+                version: version.to_string(),
+                summary: package.get("description").and_then(Value::as_str).map(String::from),
+                dependencies: poetry_dependencies(package),
+                hash,
+                url,
+                index_url: None,
+                extras: Vec::new(),
+                build: None,
+                markers,
+            });
+        }
+
+        if !dev_only.is_empty() {
+            self.dropped.push(format!(
+                "dependency groups: {} belong only to non-main groups and will be installed unconditionally",
+                dev_only.join(", ")
+            ));
+        }
+        Ok((version, python, packages))
+    }
+
+    fn uv(&mut self, document: &toml::Table) -> Result<(String, String, Vec<LockedPackage>)> {
+        let version = document
+            .get("version")
+            .and_then(Value::as_integer)
+            .ok_or_else(|| anyhow!("uv.lock has no top-level version"))?;
+        if version != UV_KNOWN {
+            bail!("uv.lock version {} is not supported (known: {})", version, UV_KNOWN);
+        }
+        let python = document.get("requires-python").and_then(Value::as_str).unwrap_or_default().to_string();
+
+        let mut packages = Vec::new();
+        for package in document.get("package").and_then(Value::as_array).into_iter().flatten() {
+            let name = str_field(package, "name")?;
+            let version = package.get("version").and_then(Value::as_str).unwrap_or_default();
+            let source = package.get("source").and_then(Value::as_table);
+
+            if let Some(source) = source
+                && let Some((kind, path)) = ["editable", "virtual", "workspace"]
+                    .iter()
+                    .find_map(|kind| source.get(*kind).map(|v| (*kind, v)))
+            {
+                let location = path.as_str().map(|p| format!(" at '{}'", p)).unwrap_or_default();
+                self.dropped.push(format!(
+                    "workspace member {}{} ({} source); install it separately, e.g. `pip install -e`",
+                    name, location, kind
+                ));
+                for group in package.get("dev-dependencies").and_then(Value::as_table).into_iter().flatten() {
+                    self.dropped.push(format!("{}: dev-dependency group '{}'", name, group.0));
+                }
+                continue;
+            }
+
+            let mut artifacts = Vec::new();
+            let wheels = package.get("wheels").and_then(Value::as_array).into_iter().flatten();
+            for file in wheels.chain(package.get("sdist")) {
+                let url = file.get("url").and_then(Value::as_str).map(String::from);
+                artifacts.push(Artifact {
+                    name: url.as_deref().and_then(|u| u.rsplit('/').next()).unwrap_or_default().to_string(),
+                    url,
+                    hash: file.get("hash").and_then(Value::as_str).map(String::from),
+                });
+            }
+            let (mut url, hash) = self.preferred(name, artifacts);
+
+            if let Some(source) = source {
+                if let Some(git) = source.get("git").and_then(Value::as_str) {
+                    // `https://host/repo?rev=v1#<commit>`: the fragment is the resolved commit
+                    let (location, commit) = git.split_once('#').unwrap_or((git, ""));
+                    let location = location.split('?').next().unwrap_or(location);
+                    url = Some(vcs_url(location, Some(commit).filter(|c| !c.is_empty())));
+                } else if let Some(path) = source.get("path").or_else(|| source.get("directory")).and_then(Value::as_str) {
+                    url = Some(self.file_url(path));
+                } else if let Some(direct) = source.get("url").and_then(Value::as_str)
+                    && url.is_none()
+                {
+                    url = Some(direct.to_string());
+                }
+            }
+
+            let mut dependencies: Vec<String> = package
+                .get("dependencies")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .map(|dep| uv_dependency(dep, None))
+                .collect();
+            for (extra, deps) in package.get("optional-dependencies").and_then(Value::as_table).into_iter().flatten() {
+                dependencies.extend(deps.as_array().into_iter().flatten().map(|dep| uv_dependency(dep, Some(extra))));
+            }
+            for group in package.get("dev-dependencies").and_then(Value::as_table).into_iter().flatten() {
+                self.dropped.push(format!("{}: dev-dependency group '{}'", name, group.0));
+            }
+
+            packages.push(LockedPackage {
+                name: name.to_string(),
+                version: version.to_string(),
+                summary: None,
+                dependencies,
+                hash,
+                url,
+                index_url: None,
+                extras: Vec::new(),
+                build: None,
+                markers: None,
+            });
+        }
+        Ok((version.to_string(), python, packages))
+    }
+
+    /// The artifact pip-rs would install: a platform-independent wheel,
+    /// else the sdist, else the first listed file
+    fn preferred(&mut self, name: &str, artifacts: Vec<Artifact>) -> (Option<String>, Option<String>) {
+        if artifacts.len() > 1 {
+            self.extra_artifacts.push(name.to_string());
+        }
+        let rank = |a: &Artifact| {
+            if a.name.ends_with("-none-any.whl") {
+                0
+            } else if !a.name.ends_with(".whl") {
+                1
+            } else {
+                2
+            }
+        };
+        match artifacts.into_iter().min_by_key(rank) {
+            Some(artifact) => (artifact.url, artifact.hash),
+            None => (None, None),
+        }
+    }
+
+    fn file_url(&self, path: &str) -> String {
+        let path = Path::new(path);
+        let absolute = if path.is_absolute() { path.to_path_buf() } else { self.base_dir.join(path) };
+        format!("file://{}", absolute.display())
+    }
+}
+
+fn str_field<'v>(package: &'v Value, key: &str) -> Result<&'v str> {
+    package
+        .get(key)
+        .and_then(Value::as_str)
+        .ok_or_else(|| anyhow!("lock entry without '{}': {}", key, package))
+}
+
+/// `major.minor` of a declared format version
+fn parse_version(version: &str) -> Option<(u64, u64)> {
+    let mut parts = version.split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next().unwrap_or("0").parse().ok()?;
+    Some((major, minor))
+}
+
+/// `git+<url>@<reference>` as used in pip-rs lock entries
+fn vcs_url(url: &str, reference: Option<&str>) -> String {
+    let url = if url.starts_with("git+") { url.to_string() } else { format!("git+{}", url) };
+    match reference {
+        Some(reference) => format!("{}@{}", url, reference),
+        None => url,
+    }
+}
+
+fn per_group_markers(per_group: &toml::Table) -> String {
+    per_group
+        .iter()
+        .map(|(group, marker)| format!("{}: {}", group, marker.as_str().unwrap_or_default()))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// PEP 508 strings from poetry's `[package.dependencies]`. Optional
+/// dependencies get the `extra == "..."` marker of the extra naming them.
+fn poetry_dependencies(package: &Value) -> Vec<String> {
+    let mut extra_of: HashMap<String, String> = HashMap::new();
+    for (extra, members) in package.get("extras").and_then(Value::as_table).into_iter().flatten() {
+        for member in members.as_array().into_iter().flatten().filter_map(Value::as_str) {
+            let dep = member.split([' ', '(', '[', ';']).next().unwrap_or(member);
+            extra_of.entry(canonicalize_name(dep)).or_insert_with(|| extra.clone());
+        }
+    }
+
+    let mut dependencies = BTreeMap::new();
+    for (name, spec) in package.get("dependencies").and_then(Value::as_table).into_iter().flatten() {
+        // A list holds alternatives for different markers
+        let alternatives: Vec<&Value> = match spec {
+            Value::Array(items) => items.iter().collect(),
+            other => vec![other],
+        };
+        for (index, spec) in alternatives.into_iter().enumerate() {
+            let (version, extras, mut markers, optional) = match spec {
+                Value::String(version) => (version.as_str(), Vec::new(), None, false),
+                table => (
+                    table.get("version").and_then(Value::as_str).unwrap_or("*"),
+                    table
+                        .get("extras")
+                        .and_then(Value::as_array)
+                        .map(|e| e.iter().filter_map(Value::as_str).collect())
+                        .unwrap_or_default(),
+                    table.get("markers").and_then(Value::as_str).map(String::from),
+                    table.get("optional").and_then(Value::as_bool) == Some(true),
+                ),
+            };
+            if optional && let Some(extra) = extra_of.get(&canonicalize_name(name)) {
+                let extra_marker = format!("extra == \"{}\"", extra);
+                markers = Some(match markers {
+                    Some(m) => format!("({}) and {}", m, extra_marker),
+                    None => extra_marker,
+                });
+            }
+            let requirement = requirement(name, &extras, version, markers.as_deref());
+            dependencies.insert((canonicalize_name(name), index), requirement);
+        }
+    }
+    dependencies.into_values().collect()
+}
+
+/// A PEP 508 string from a uv dependency table
+fn uv_dependency(dep: &Value, extra: Option<&str>) -> String {
+    let name = dep.get("name").and_then(Value::as_str).unwrap_or_default();
+    let extras: Vec<&str> = dep
+        .get("extra")
+        .and_then(Value::as_array)
+        .map(|e| e.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    let version = dep.get("version").and_then(Value::as_str).map(|v| format!("=={}", v));
+    let marker = dep.get("marker").and_then(Value::as_str);
+    let markers = match (marker, extra) {
+        (Some(marker), Some(extra)) => Some(format!("({}) and extra == \"{}\"", marker, extra)),
+        (Some(marker), None) => Some(marker.to_string()),
+        (None, Some(extra)) => Some(format!("extra == \"{}\"", extra)),
+        (None, None) => None,
+    };
+    requirement(name, &extras, version.as_deref().unwrap_or("*"), markers.as_deref())
+}
+
+fn requirement(name: &str, extras: &[&str], version: &str, markers: Option<&str>) -> String {
+    let mut requirement = name.to_string();
+    if !extras.is_empty() {
+        requirement.push_str(&format!("[{}]", extras.join(",")));
+    }
+    if version != "*" && !version.is_empty() {
+        requirement.push_str(version);
+    }
+    if let Some(markers) = markers {
+        requirement.push_str(&format!("; {}", markers));
+    }
+    requirement
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POETRY_LOCK: &str = include_str!("../../tests/fixtures/locks/poetry.lock");
+    const UV_LOCK: &str = include_str!("../../tests/fixtures/locks/uv.lock");
+
+    fn package<'a>(lock: &'a LockFile, name: &str) -> &'a LockedPackage {
+        lock.packages.values().find(|p| p.name == name).unwrap()
+    }
+
+    #[test]
+    fn test_poetry_lock_round_trip() {
+        assert_eq!(ForeignFormat::detect(Path::new("deps.lock"), POETRY_LOCK), Some(ForeignFormat::Poetry));
+        let imported = convert(POETRY_LOCK, ForeignFormat::Poetry, Path::new("/work/app")).unwrap();
+        let lock = &imported.lock;
+        assert!(lock.validate().is_ok());
+        assert_eq!(lock.packages.len(), 6);
+
+        let requests = package(lock, "requests");
+        assert_eq!(requests.version, "2.31.0");
+        assert_eq!(
+            requests.hash.as_deref(),
+            Some("sha256:58cd2187c01e70e6e26505bca751777aa9f2ee0b7f4300988b709f44e013003f")
+        );
+        assert_eq!(
+            requests.dependencies,
+            vec!["certifi>=2017.4.17", "PySocks>=1.5.6,<1.5.7 || >1.5.7; extra == \"socks\""]
+        );
+        assert_eq!(package(lock, "pytest").dependencies, vec!["colorama; sys_platform == \"win32\""]);
+        assert_eq!(
+            package(lock, "mytool").url.as_deref(),
+            Some("git+https://github.com/acme/mytool.git@0123456789abcdef0123456789abcdef01234567")
+        );
+        assert_eq!(package(lock, "shared-utils").url.as_deref(), Some("file:///work/app/../shared-utils"));
+
+        let summary = &imported.summary;
+        assert_eq!(summary.format_version, "2.0");
+        assert_eq!(summary.imported, 6);
+        assert_eq!(
+            summary.dropped,
+            vec![
+                "shared-utils: editable (develop) install; imported as a regular install".to_string(),
+                "hashes of alternative artifacts for 3 package(s) (certifi, colorama, requests); only the hash of the artifact pip-rs installs is kept"
+                    .to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_uv_lock_round_trip() {
+        assert_eq!(ForeignFormat::detect(Path::new("locked.toml"), UV_LOCK), Some(ForeignFormat::Uv));
+        let imported = convert(UV_LOCK, ForeignFormat::Uv, Path::new("/work/service")).unwrap();
+        let lock = &imported.lock;
+        assert!(lock.validate().is_ok());
+        assert_eq!(lock.python_version, ">=3.9");
+        assert!(!lock.has_package("service"));
+
+        let anyio = package(lock, "anyio");
+        assert_eq!(
+            anyio.url.as_deref(),
+            Some("https://files.pythonhosted.org/packages/14/fd/anyio-4.3.0-py3-none-any.whl")
+        );
+        assert_eq!(
+            anyio.hash.as_deref(),
+            Some("sha256:048e05d0f6caeed70d731f3db756d35dcc1f35747c8c403364a8332c630441b8")
+        );
+        assert_eq!(
+            anyio.dependencies,
+            vec!["exceptiongroup; python_full_version < '3.11'", "idna", "trio; extra == \"trio\""]
+        );
+        // Only an sdist: its hash is kept
+        assert!(package(lock, "idna").url.as_deref().unwrap().ends_with("idna-3.6.tar.gz"));
+        assert_eq!(
+            package(lock, "parser").url.as_deref(),
+            Some("git+https://github.com/acme/parser@89abcdef0123456789abcdef0123456789abcdef")
+        );
+
+        let summary = &imported.summary;
+        assert_eq!(summary.imported, 4);
+        assert_eq!(summary.dropped.len(), 3);
+        assert!(summary.dropped[0].starts_with("workspace member service at '.' (editable source)"));
+        assert_eq!(summary.dropped[1], "service: dev-dependency group 'dev'");
+        assert!(summary.dropped[2].contains("for 2 package(s) (anyio, exceptiongroup)"));
+    }
+
+    #[test]
+    fn test_format_versions() {
+        // lock-version 1.1: hashes live under [metadata.files], groups are categories
+        let legacy = r#"
+[[package]]
+name = "six"
+version = "1.16.0"
+category = "dev"
+
+[metadata]
+lock-version = "1.1"
+python-versions = "*"
+
+[metadata.files]
+six = [{file = "six-1.16.0-py2.py3-none-any.whl", hash = "sha256:aaa"}]
+"#;
+        let imported = convert(legacy, ForeignFormat::Poetry, Path::new(".")).unwrap();
+        assert_eq!(package(&imported.lock, "six").hash.as_deref(), Some("sha256:aaa"));
+        assert_eq!(
+            imported.summary.dropped,
+            vec!["dependency groups: six (dev) belong only to non-main groups and will be installed unconditionally"]
+        );
+
+        // A newer minor revision imports with a note; a new major is refused
+        let newer = legacy.replace("lock-version = \"1.1\"", "lock-version = \"1.4\"");
+        let imported = convert(&newer, ForeignFormat::Poetry, Path::new(".")).unwrap();
+        assert!(imported.summary.dropped[0].contains("after poetry lock-version 1.1 (file declares 1.4)"));
+        let future = legacy.replace("lock-version = \"1.1\"", "lock-version = \"3.0\"");
+        assert!(convert(&future, ForeignFormat::Poetry, Path::new(".")).is_err());
+        assert!(convert("version = 2\n[[package]]\nname = \"x\"\n", ForeignFormat::Uv, Path::new(".")).is_err());
+    }
+}
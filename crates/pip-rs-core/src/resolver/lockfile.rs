@@ -1,24 +1,51 @@
 /// Lock file support for reproducible installs
 /// Generates and reads lock files in JSON format
-
-use crate::models::Package;
+///
+/// Packages are keyed `name-version` in a sorted map and every other
+/// collection keeps a fixed order, so re-locking the same resolution
+/// writes the same file and changes diff cleanly.
+use crate::models::{Package, Requirement};
+use crate::utils::names::canonicalize_name;
+use crate::utils::text_encoding;
+use super::markers::MarkerSkip;
+use super::overrides::AppliedOverride;
+use super::resolver::DependencyMode;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
 use std::path::Path;
 
+/// Marker variables that decide which files and dependencies a lock
+/// holds; a lock resolved under other values doesn't fit
+const PINNED_ENVIRONMENT: &[&str] = &["python_version", "sys_platform", "platform_machine"];
+
 /// Lock file format
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LockFile {
     /// Lock file version
     pub version: String,
+    /// pip-rs release that wrote the lock
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pip_rs_version: Option<String>,
     /// Generated timestamp
     pub generated_at: String,
     /// Python version used
     pub python_version: String,
-    /// Locked packages
-    pub packages: HashMap<String, LockedPackage>,
+    /// Locked packages, keyed `name-version`
+    pub packages: BTreeMap<String, LockedPackage>,
+    /// Dependency overrides applied during resolution
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub overrides: Vec<AppliedOverride>,
+    /// Dependency mode used for resolution (--no-deps / --only-deps)
+    #[serde(default, skip_serializing_if = "DependencyMode::is_all")]
+    pub dependency_mode: DependencyMode,
+    /// Marker environment the lock was resolved for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<BTreeMap<String, String>>,
+    /// Dependencies left out because their markers were false there
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped_by_markers: Vec<MarkerSkip>,
 }
 
 /// A locked package entry
@@ -32,16 +59,49 @@ pub struct LockedPackage {
     pub summary: Option<String>,
     /// Direct dependencies
     pub dependencies: Vec<String>,
-    /// Installation hash (for verification)
+    /// Installation hash (for verification), `sha256:<hex>`
     pub hash: Option<String>,
     /// Installation URL
     pub url: Option<String>,
+    /// Index the artifact was found on, credentials redacted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index_url: Option<String>,
+    /// Extras the package was requested with
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extras: Vec<String>,
+    /// Build environment for entries that are built from source
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build: Option<BuildPin>,
+    /// Environment markers limiting where the package is installed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub markers: Option<String>,
+}
+
+/// Build backend and build requirements used for a source distribution
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildPin {
+    /// PEP 517 backend (e.g. `setuptools.build_meta`)
+    pub backend: String,
+    /// Build requirements
+    pub requires: Vec<String>,
+}
+
+impl BuildPin {
+    /// Every build requirement is pinned to an exact version
+    pub fn is_pinned(&self) -> bool {
+        !self.backend.is_empty()
+            && !self.requires.is_empty()
+            && self.requires.iter().all(|r| {
+                r.split_once("==")
+                    .is_some_and(|(_, version)| !version.is_empty() && !version.contains('*'))
+            })
+    }
 }
 
 impl LockFile {
     /// Create a new lock file from resolved packages
     pub fn from_packages(packages: Vec<Package>, python_version: String) -> Self {
-        let mut locked_packages = HashMap::new();
+        let mut locked_packages = BTreeMap::new();
 
         for pkg in packages {
             let key = format!("{}-{}", pkg.name, pkg.version);
@@ -54,28 +114,103 @@ impl LockFile {
                     dependencies: pkg.requires_dist,
                     hash: None,
                     url: None,
+                    index_url: None,
+                    extras: Vec::new(),
+                    build: None,
+                    markers: None,
                 },
             );
         }
 
         Self {
             version: "1.0".to_string(),
+            pip_rs_version: None,
             generated_at: chrono::Local::now().to_rfc3339(),
             python_version,
             packages: locked_packages,
+            overrides: Vec::new(),
+            dependency_mode: DependencyMode::All,
+            environment: None,
+            skipped_by_markers: Vec::new(),
+        }
+    }
+
+    /// Record the pip-rs release writing the lock
+    pub fn with_pip_rs_version(mut self, version: &str) -> Self {
+        self.pip_rs_version = Some(version.to_string());
+        self
+    }
+
+    /// Record the dependency mode used for resolution
+    pub fn with_dependency_mode(mut self, mode: DependencyMode) -> Self {
+        self.dependency_mode = mode;
+        self
+    }
+
+    /// Record the marker environment this resolution is for
+    pub fn with_environment(mut self, environment: BTreeMap<String, String>) -> Self {
+        self.environment = Some(environment);
+        self
+    }
+
+    /// Record the dependencies environment markers excluded
+    pub fn with_marker_skips(mut self, skips: Vec<MarkerSkip>) -> Self {
+        self.skipped_by_markers = skips;
+        self
+    }
+
+    /// Record the overrides that shaped this resolution
+    pub fn with_overrides(mut self, overrides: Vec<AppliedOverride>) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    /// Fill in each entry's markers and extras from the requirements that
+    /// pulled it in: the direct `requirements` and the locked packages' own
+    /// dependency lines. A package required anywhere without a marker gets
+    /// none; `extra == ...` markers only say which dependencies were
+    /// followed, so they count as unconditional.
+    pub fn record_requirements(&mut self, requirements: &[Requirement]) {
+        let mut wanted: BTreeMap<String, (Vec<Option<String>>, BTreeSet<String>)> = BTreeMap::new();
+        let dependencies = self
+            .packages
+            .values()
+            .flat_map(|package| package.dependencies.iter())
+            .filter_map(|dependency| dependency.parse::<Requirement>().ok());
+        for requirement in requirements.iter().cloned().chain(dependencies) {
+            let (markers, extras) = wanted.entry(canonicalize_name(&requirement.name)).or_default();
+            markers.push(requirement.marker.filter(|marker| !marker.contains("extra")));
+            extras.extend(requirement.extras);
+        }
+
+        for package in self.packages.values_mut() {
+            let Some((markers, extras)) = wanted.get(&canonicalize_name(&package.name)) else {
+                continue;
+            };
+            package.markers = if markers.iter().any(Option::is_none) {
+                None
+            } else {
+                let distinct: BTreeSet<&str> = markers.iter().flatten().map(String::as_str).collect();
+                match distinct.len() {
+                    1 => distinct.into_iter().next().map(String::from),
+                    _ => Some(distinct.iter().map(|marker| format!("({})", marker)).collect::<Vec<_>>().join(" or ")),
+                }
+            };
+            package.extras = extras.iter().cloned().collect();
         }
     }
 
     /// Save lock file to disk
     pub fn save(&self, path: &Path) -> Result<()> {
-        let json = serde_json::to_string_pretty(self)?;
+        let mut json = serde_json::to_string_pretty(self)?;
+        json.push('\n');
         fs::write(path, json)?;
         Ok(())
     }
 
     /// Load lock file from disk
     pub fn load(path: &Path) -> Result<Self> {
-        let contents = fs::read_to_string(path)?;
+        let contents = text_encoding::read_text(path)?;
         let lockfile = serde_json::from_str(&contents)?;
         Ok(lockfile)
     }
@@ -104,6 +239,11 @@ impl LockFile {
         self.packages.get(&key).cloned()
     }
 
+    /// A locked package, for recording what it resolved to
+    pub fn package_mut(&mut self, name: &str, version: &str) -> Option<&mut LockedPackage> {
+        self.packages.get_mut(&format!("{}-{}", name, version))
+    }
+
     /// Check if package is locked
     pub fn has_package(&self, name: &str) -> bool {
         self.packages.values().any(|pkg| pkg.name == name)
@@ -117,6 +257,23 @@ impl LockFile {
             .collect()
     }
 
+    /// How the Python version or platform the lock was resolved for
+    /// differs from `running`; empty when it fits
+    pub fn environment_mismatch(&self, running: &BTreeMap<String, String>) -> Vec<String> {
+        let differs = |key: &str, locked: &str| {
+            let current = running.get(key)?;
+            (current != locked).then(|| format!("{} locked as '{}' but running '{}'", key, locked, current))
+        };
+        match &self.environment {
+            Some(locked) => PINNED_ENVIRONMENT
+                .iter()
+                .filter_map(|&key| differs(key, locked.get(key)?.as_str()))
+                .collect(),
+            // Older locks only record the Python version
+            None => differs("python_version", self.python_version.as_str()).into_iter().collect(),
+        }
+    }
+
     /// Validate lock file integrity
     pub fn validate(&self) -> Result<()> {
         if self.version != "1.0" {
@@ -212,4 +369,96 @@ mod tests {
         let lockfile = LockFile::from_packages(packages, "3.11".to_string());
         assert!(lockfile.validate().is_ok());
     }
+
+    #[test]
+    fn test_lockfile_records_requirements_deterministically() -> Result<()> {
+        let packages = vec![
+            Package::new("requests".to_string(), "2.31.0".to_string()).with_requires(vec![
+                "urllib3<3,>=1.21.1".to_string(),
+                "PySocks!=1.5.7,>=1.5.6; extra == \"socks\"".to_string(),
+            ]),
+            Package::new("urllib3".to_string(), "2.2.1".to_string()),
+            Package::new("colorama".to_string(), "0.4.6".to_string()),
+            Package::new("PySocks".to_string(), "1.7.1".to_string()),
+        ];
+        let requirements: Vec<Requirement> = ["requests[socks]>=2", "colorama; sys_platform == \"win32\""]
+            .iter()
+            .map(|r| r.parse().unwrap())
+            .collect();
+        let mut lockfile = LockFile::from_packages(packages.clone(), "3.11".to_string()).with_pip_rs_version("0.1.0");
+        lockfile.record_requirements(&requirements);
+
+        let requests = lockfile.get_package("requests", "2.31.0").unwrap();
+        assert_eq!((requests.extras.as_slice(), requests.markers.as_deref()), (["socks".to_string()].as_slice(), None));
+        let colorama = lockfile.get_package("colorama", "0.4.6").unwrap();
+        assert_eq!(colorama.markers.as_deref(), Some("sys_platform == \"win32\""));
+        assert!(lockfile.get_package("PySocks", "1.7.1").unwrap().markers.is_none());
+
+        lockfile.package_mut("urllib3", "2.2.1").unwrap().hash = Some("sha256:abc".to_string());
+        let temp_dir = tempfile::TempDir::new()?;
+        let path = temp_dir.path().join("pip-rs.lock");
+        lockfile.save(&path)?;
+        let first = fs::read_to_string(&path)?;
+        let keys: Vec<&str> = lockfile.packages.keys().map(String::as_str).collect();
+        assert_eq!(keys, ["PySocks-1.7.1", "colorama-0.4.6", "requests-2.31.0", "urllib3-2.2.1"]);
+
+        // The same resolution in another order writes the same file
+        let mut reordered = LockFile::from_packages(packages.into_iter().rev().collect(), "3.11".to_string())
+            .with_pip_rs_version("0.1.0");
+        reordered.generated_at = lockfile.generated_at.clone();
+        reordered.record_requirements(&requirements);
+        reordered.package_mut("urllib3", "2.2.1").unwrap().hash = Some("sha256:abc".to_string());
+        reordered.save(&path)?;
+        assert_eq!(fs::read_to_string(&path)?, first);
+        assert_eq!(LockFile::load(&path)?.pip_rs_version.as_deref(), Some("0.1.0"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_environment_mismatch() {
+        let environment = |python: &str, platform: &str| -> BTreeMap<String, String> {
+            [("python_version", python), ("sys_platform", platform), ("platform_machine", "x86_64"), ("os_name", "posix")]
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect()
+        };
+        let lockfile = LockFile::from_packages(Vec::new(), "3.12".to_string());
+        assert_eq!(
+            lockfile.environment_mismatch(&environment("3.11", "linux")),
+            ["python_version locked as '3.12' but running '3.11'"]
+        );
+
+        let lockfile = lockfile.with_environment(environment("3.12", "linux"));
+        assert!(lockfile.environment_mismatch(&environment("3.12", "linux")).is_empty());
+        assert_eq!(
+            lockfile.environment_mismatch(&environment("3.11", "darwin")),
+            [
+                "python_version locked as '3.12' but running '3.11'",
+                "sys_platform locked as 'linux' but running 'darwin'"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lockfile_overrides_round_trip() -> Result<()> {
+        let packages = vec![Package::new("urllib3".to_string(), "1.26.18".to_string())];
+        let overrides = vec![AppliedOverride {
+            package: "urllib3".to_string(),
+            required_by: Some("requests".to_string()),
+            original: "urllib3>=1.21".to_string(),
+            replacement: "urllib3<2".to_string(),
+        }];
+        let lockfile = LockFile::from_packages(packages, "3.11".to_string())
+            .with_overrides(overrides.clone())
+            .with_dependency_mode(DependencyMode::OnlyDeps);
+
+        let temp_dir = tempfile::TempDir::new()?;
+        let path = temp_dir.path().join("pip-rs.lock");
+        lockfile.save(&path)?;
+
+        let loaded = LockFile::load(&path)?;
+        assert_eq!(loaded.overrides, overrides);
+        assert_eq!(loaded.dependency_mode, DependencyMode::OnlyDeps);
+        Ok(())
+    }
 }
@@ -0,0 +1,73 @@
+/// Dependencies left out by environment markers
+///
+/// The resolver records every dependency edge whose marker is false for
+/// the target environment, with the variable values that decided it, so
+/// "why is this missing on Linux?" has an answer in the install output,
+/// the installation report and the lock file.
+use crate::models::{Environment, Marker};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// A dependency edge skipped because its marker didn't match
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MarkerSkip {
+    /// Skipped dependency
+    pub package: String,
+    /// Package that declared the dependency
+    pub required_by: String,
+    /// Marker as written in the requirement
+    pub marker: String,
+    /// Value of every variable the marker reads, in the evaluated environment
+    pub values: BTreeMap<String, String>,
+}
+
+impl MarkerSkip {
+    pub fn new(package: &str, required_by: &str, marker: &Marker, env: &Environment) -> Self {
+        let values = marker
+            .variables()
+            .into_iter()
+            .filter_map(|variable| {
+                let value = env.value(&variable)?.to_string();
+                Some((variable, value))
+            })
+            .collect();
+        Self {
+            package: package.to_string(),
+            required_by: required_by.to_string(),
+            marker: marker.expression.clone(),
+            values,
+        }
+    }
+
+    /// Markers about extras select optional features rather than
+    /// platforms, so they aren't environment exclusions
+    pub fn is_extra_marker(marker: &Marker) -> bool {
+        marker.variables().iter().any(|variable| variable == "extra")
+    }
+}
+
+impl fmt::Display for MarkerSkip {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let values: Vec<String> = self.values.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+        write!(
+            f,
+            "{} (required by {}): {} [{}]",
+            self.package,
+            self.required_by,
+            self.marker,
+            values.join(", ")
+        )
+    }
+}
+
+/// The "Skipped due to environment markers" section of `install -v`;
+/// empty when nothing was skipped
+pub fn skipped_section(skips: &[MarkerSkip]) -> Vec<String> {
+    if skips.is_empty() {
+        return Vec::new();
+    }
+    let mut lines = vec![format!("Skipped due to environment markers ({}):", skips.len())];
+    lines.extend(skips.iter().map(|skip| format!("  - {}", skip)));
+    lines
+}
@@ -5,9 +5,29 @@ pub mod lockfile;
 pub mod dependency_cache;
 pub mod direct_url;
 pub mod candidate_selector;
+pub mod deprecation;
+pub mod overrides;
+pub mod provider;
+pub mod strict;
+pub mod foreign;
+pub mod index_policy;
+pub mod markers;
+pub mod budget;
+pub mod conflicts;
+pub mod error;
+pub mod requires_python;
 
 pub use resolver::*;
 pub use lockfile::LockFile;
 pub use dependency_cache::DependencyCache;
-pub use direct_url::{DirectUrl, DirectUrlType, DirectUrlConflictDetector};
+pub use direct_url::{DirectUrl, DirectUrlJson, DirectUrlType, DirectUrlConflictDetector};
 pub use candidate_selector::{CandidateSelector, SelectionStrategy, Candidate};
+pub use overrides::{AppliedOverride, Override, OverrideSet};
+pub use provider::{CachedProvider, MetadataProvider, MultiIndexProvider, PyPIProvider};
+pub use index_policy::{IndexPolicy, IndexSource, PolicyViolation};
+pub use markers::MarkerSkip;
+pub use deprecation::DeprecationNotice;
+pub use budget::{ResolutionBudget, ResolutionStats, ResolutionTooComplex};
+pub use conflicts::ResolutionImpossible;
+pub use error::ResolutionError;
+pub use requires_python::Release;
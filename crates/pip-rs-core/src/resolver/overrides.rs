@@ -0,0 +1,312 @@
+/// Local dependency overrides (`--override-file`)
+///
+/// An overrides file forces a different specifier or source for a package
+/// wherever it appears in the dependency graph, optionally only when it is
+/// required by a particular parent. Format:
+///
+/// ```toml
+/// [overrides]
+/// urllib3 = "<2"
+/// certifi = "https://mirror.example.com/certifi-2024.2.2-py3-none-any.whl"
+/// idna = { version = "==3.4", when-required-by = "requests" }
+///
+/// [[override]]
+/// name = "idna"
+/// url = "https://mirror.example.com/idna-3.7-py3-none-any.whl"
+/// when-required-by = "httpx"
+/// ```
+use crate::models::Requirement;
+use crate::utils::names::canonicalize_name;
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// A single declared override
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Override {
+    /// Normalized name of the overridden package
+    pub name: String,
+    /// Replacement specifiers (empty when a URL is given)
+    pub specifier: String,
+    /// Replacement direct URL
+    pub url: Option<String>,
+    /// Only apply when the dependency edge comes from this parent
+    pub when_required_by: Option<String>,
+}
+
+/// Record of an override that replaced a dependency edge
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppliedOverride {
+    /// Overridden package
+    pub package: String,
+    /// Package whose dependency edge was replaced (None for top-level requirements)
+    pub required_by: Option<String>,
+    /// Original requirement
+    pub original: String,
+    /// Requirement that replaced it
+    pub replacement: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct OverrideFileFormat {
+    #[serde(default)]
+    overrides: BTreeMap<String, OverrideValue>,
+    #[serde(default, rename = "override")]
+    entries: Vec<OverrideEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum OverrideValue {
+    Simple(String),
+    Detailed(OverrideEntry),
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct OverrideEntry {
+    name: Option<String>,
+    version: Option<String>,
+    url: Option<String>,
+    when_required_by: Option<String>,
+}
+
+/// The set of overrides loaded from an overrides file
+#[derive(Debug, Clone, Default)]
+pub struct OverrideSet {
+    overrides: Vec<Override>,
+    used: Vec<bool>,
+}
+
+impl OverrideSet {
+    /// Load overrides from a TOML file
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read override file {}: {}", path.display(), e))?;
+        Self::parse(&content)
+            .map_err(|e| anyhow!("Invalid override file {}: {}", path.display(), e))
+    }
+
+    /// Parse overrides from TOML content
+    pub fn parse(content: &str) -> Result<Self> {
+        let file: OverrideFileFormat = toml::from_str(content)?;
+        let mut overrides = Vec::new();
+
+        for (name, value) in file.overrides {
+            let entry = match value {
+                OverrideValue::Simple(s) if s.contains("://") => OverrideEntry {
+                    url: Some(s),
+                    ..Default::default()
+                },
+                OverrideValue::Simple(s) => OverrideEntry {
+                    version: Some(s),
+                    ..Default::default()
+                },
+                OverrideValue::Detailed(entry) => entry,
+            };
+            overrides.push(Self::build(&name, entry)?);
+        }
+
+        for entry in file.entries {
+            let name = entry
+                .name
+                .clone()
+                .ok_or_else(|| anyhow!("[[override]] entry is missing a name"))?;
+            overrides.push(Self::build(&name, entry)?);
+        }
+
+        let used = vec![false; overrides.len()];
+        Ok(Self { overrides, used })
+    }
+
+    fn build(name: &str, entry: OverrideEntry) -> Result<Override> {
+        let name = canonicalize_name(name);
+        let specifier = entry.version.unwrap_or_default().trim().to_string();
+
+        if entry.url.is_some() && !specifier.is_empty() {
+            return Err(anyhow!("Override for {} sets both a version and a url", name));
+        }
+        if entry.url.is_none() && specifier.is_empty() {
+            return Err(anyhow!("Override for {} needs a version or a url", name));
+        }
+        if !specifier.is_empty() {
+            // Validate the specifier up front so typos fail fast
+            format!("{}{}", name, specifier)
+                .parse::<Requirement>()
+                .map_err(|e| anyhow!("Invalid specifier for {}: {}", name, e))?;
+        }
+
+        Ok(Override {
+            name,
+            specifier,
+            url: entry.url,
+            when_required_by: entry.when_required_by.map(|p| canonicalize_name(&p)),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.overrides.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.overrides.len()
+    }
+
+    /// Apply any matching override to a dependency edge.
+    ///
+    /// Scoped overrides (`when-required-by`) take precedence over global ones.
+    /// Returns the (possibly replaced) requirement and a record when an
+    /// override was applied.
+    pub fn apply(
+        &mut self,
+        parent: Option<&str>,
+        req: Requirement,
+    ) -> (Requirement, Option<AppliedOverride>) {
+        let name = canonicalize_name(&req.name);
+        let parent = parent.map(canonicalize_name);
+
+        let scoped = self.overrides.iter().position(|o| {
+            o.name == name && o.when_required_by.is_some() && o.when_required_by == parent
+        });
+        let index = scoped.or_else(|| {
+            self.overrides
+                .iter()
+                .position(|o| o.name == name && o.when_required_by.is_none())
+        });
+
+        let Some(index) = index else {
+            return (req, None);
+        };
+        self.used[index] = true;
+        let over = &self.overrides[index];
+
+        let mut replacement = req.clone();
+        replacement.url = over.url.clone();
+        replacement.specs = if over.specifier.is_empty() {
+            Vec::new()
+        } else {
+            format!("{}{}", name, over.specifier)
+                .parse::<Requirement>()
+                .map(|r| r.specs)
+                .unwrap_or_default()
+        };
+
+        let applied = AppliedOverride {
+            package: name,
+            required_by: parent,
+            original: req.to_string(),
+            replacement: replacement.to_string(),
+        };
+        (replacement, Some(applied))
+    }
+
+    /// Overrides that never matched a dependency edge
+    pub fn unused(&self) -> Vec<&Override> {
+        self.overrides
+            .iter()
+            .zip(&self.used)
+            .filter(|(_, used)| !**used)
+            .map(|(o, _)| o)
+            .collect()
+    }
+}
+
+impl fmt::Display for Override {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.url {
+            Some(url) => write!(f, "{} @ {}", self.name, url)?,
+            None => write!(f, "{}{}", self.name, self.specifier)?,
+        }
+        if let Some(parent) = &self.when_required_by {
+            write!(f, " (when required by {})", parent)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for AppliedOverride {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} -> {}", self.original, self.replacement)?;
+        if let Some(parent) = &self.required_by {
+            write!(f, " (required by {})", parent)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_override_file() {
+        let set = OverrideSet::parse(
+            r#"
+[overrides]
+urllib3 = "<2"
+certifi = "https://example.com/certifi-2024.2.2-py3-none-any.whl"
+idna = { version = "==3.4", when-required-by = "requests" }
+
+[[override]]
+name = "Idna"
+url = "https://example.com/idna-3.7-py3-none-any.whl"
+when-required-by = "httpx"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(set.len(), 4);
+        assert!(set.overrides.iter().any(|o| o.name == "urllib3" && o.specifier == "<2"));
+        assert!(set.overrides.iter().any(|o| o.name == "certifi" && o.url.is_some()));
+        assert!(set.overrides.iter().any(|o| o.name == "idna"
+            && o.when_required_by.as_deref() == Some("httpx")));
+    }
+
+    #[test]
+    fn test_scoped_override_only_matches_parent() {
+        let mut set = OverrideSet::parse(
+            "[overrides]\nidna = { version = \"==3.4\", when-required-by = \"requests\" }\n",
+        )
+        .unwrap();
+
+        let req: Requirement = "idna>=2.5".parse().unwrap();
+        let (unchanged, applied) = set.apply(Some("httpx"), req.clone());
+        assert_eq!(unchanged, req);
+        assert!(applied.is_none());
+        assert_eq!(set.unused().len(), 1);
+
+        let (replaced, applied) = set.apply(Some("Requests"), req);
+        assert_eq!(replaced.to_string(), "idna==3.4");
+        assert_eq!(applied.unwrap().required_by.as_deref(), Some("requests"));
+        assert!(set.unused().is_empty());
+    }
+
+    #[test]
+    fn test_names_match_however_spelled() {
+        let mut set = OverrideSet::parse(
+            r#"
+[overrides]
+zope-interface = "<6"
+zc_lockfile = { version = "==3.0", when-required-by = "zope.component" }
+"#,
+        )
+        .unwrap();
+
+        let (replaced, applied) = set.apply(Some("plone"), "zope.interface>=5".parse().unwrap());
+        assert_eq!(replaced.to_string(), "zope-interface<6");
+        assert!(applied.is_some());
+        let (replaced, applied) = set.apply(Some("Zope_Component"), "zc.lockfile".parse().unwrap());
+        assert_eq!(replaced.to_string(), "zc-lockfile==3.0");
+        assert_eq!(applied.unwrap().required_by.as_deref(), Some("zope-component"));
+        assert!(set.unused().is_empty());
+    }
+
+    #[test]
+    fn test_invalid_override_rejected() {
+        assert!(OverrideSet::parse("[overrides]\nfoo = { when-required-by = \"bar\" }\n").is_err());
+        assert!(OverrideSet::parse("[overrides]\nfoo = \"=>1\"\n").is_err());
+    }
+}
@@ -0,0 +1,191 @@
+/// Package metadata sources for the resolver
+///
+/// The resolver asks a `MetadataProvider` for package metadata instead of
+/// calling the network layer directly, so alternative sources (and mocked
+/// graphs in tests) can be plugged in.
+use super::index_policy::IndexSource;
+use super::requires_python::Release;
+use crate::cache::package_cache::PackageCache;
+use crate::models::Package;
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+#[async_trait]
+pub trait MetadataProvider: Send + Sync {
+    /// Fetch metadata for the latest release of a package
+    async fn get_package(&self, name: &str) -> Result<Package>;
+
+    /// Fetch metadata for a package referenced by a direct URL
+    ///
+    /// The default implementation derives the version from the artifact
+    /// filename and does not expand dependencies, since the artifact itself
+    /// would have to be downloaded to read them.
+    async fn get_package_from_url(&self, name: &str, url: &str) -> Result<Package> {
+        let version = version_from_url(url)
+            .ok_or_else(|| anyhow!("Cannot determine version of {} from {}", name, url))?;
+        Ok(Package::new(name.to_string(), version))
+    }
+
+    /// Fetch metadata along with the index that served it, when known
+    async fn get_package_with_source(&self, name: &str) -> Result<(Package, Option<IndexSource>)> {
+        Ok((self.get_package(name).await?, None))
+    }
+
+    /// Every release of a package, for falling back from the latest one.
+    /// Sources that can't list releases return none.
+    async fn get_releases(&self, _name: &str) -> Result<Vec<Release>> {
+        Ok(Vec::new())
+    }
+
+    /// Fetch metadata for one specific release
+    async fn get_package_version(&self, name: &str, version: &str) -> Result<Package> {
+        let package = self.get_package(name).await?;
+        if package.version == version {
+            Ok(package)
+        } else {
+            Err(anyhow!("{} {} is not available from this source", name, version))
+        }
+    }
+}
+
+/// Default provider backed by the PyPI JSON API
+pub struct PyPIProvider;
+
+#[async_trait]
+impl MetadataProvider for PyPIProvider {
+    async fn get_package(&self, name: &str) -> Result<Package> {
+        crate::network::get_package_metadata(name, "latest").await
+    }
+
+    async fn get_package_with_source(&self, name: &str) -> Result<(Package, Option<IndexSource>)> {
+        let package = self.get_package(name).await?;
+        // Cached metadata wasn't fetched this run; it came from the primary index
+        let index = crate::network::served_by(name)
+            .unwrap_or_else(|| crate::network::configured_indexes().get_primary().clone());
+        let source = if index.is_pypi() { IndexSource::pypi() } else { IndexSource::new(&index.name, &index.url) };
+        Ok((package, Some(source)))
+    }
+
+    async fn get_releases(&self, name: &str) -> Result<Vec<Release>> {
+        crate::network::get_releases(name).await
+    }
+
+    async fn get_package_version(&self, name: &str, version: &str) -> Result<Package> {
+        crate::network::get_release_metadata(name, version).await
+    }
+}
+
+/// Several indexes tried in order; the first that has a package serves it
+pub struct MultiIndexProvider {
+    indexes: Vec<(IndexSource, Arc<dyn MetadataProvider>)>,
+}
+
+impl MultiIndexProvider {
+    pub fn new(indexes: Vec<(IndexSource, Arc<dyn MetadataProvider>)>) -> Self {
+        Self { indexes }
+    }
+}
+
+#[async_trait]
+impl MetadataProvider for MultiIndexProvider {
+    async fn get_package(&self, name: &str) -> Result<Package> {
+        Ok(self.get_package_with_source(name).await?.0)
+    }
+
+    async fn get_package_with_source(&self, name: &str) -> Result<(Package, Option<IndexSource>)> {
+        let mut last_error = None;
+        for (source, provider) in &self.indexes {
+            match provider.get_package(name).await {
+                Ok(package) => return Ok((package, Some(source.clone()))),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| anyhow!("No indexes configured")))
+    }
+
+    async fn get_releases(&self, name: &str) -> Result<Vec<Release>> {
+        self.serving_index(name).await?.get_releases(name).await
+    }
+
+    async fn get_package_version(&self, name: &str, version: &str) -> Result<Package> {
+        self.serving_index(name).await?.get_package_version(name, version).await
+    }
+}
+
+impl MultiIndexProvider {
+    /// The index that serves `name`: the first one that has it
+    async fn serving_index(&self, name: &str) -> Result<&Arc<dyn MetadataProvider>> {
+        let mut last_error = None;
+        for (_, provider) in &self.indexes {
+            match provider.get_package(name).await {
+                Ok(_) => return Ok(provider),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| anyhow!("No indexes configured")))
+    }
+}
+
+/// Provider serving only metadata already in a package cache, without
+/// network access. Replays resolutions from `pip debug --dump-state`
+/// bundles and recorded test fixtures.
+pub struct CachedProvider {
+    cache: PackageCache,
+}
+
+impl CachedProvider {
+    /// Read from the package cache in `cache_dir` (a `packages/` directory)
+    pub fn new(cache_dir: PathBuf) -> Result<Self> {
+        Ok(Self {
+            cache: PackageCache::new_custom(cache_dir)?,
+        })
+    }
+}
+
+#[async_trait]
+impl MetadataProvider for CachedProvider {
+    async fn get_package(&self, name: &str) -> Result<Package> {
+        self.cache
+            .get(name, "latest")?
+            .ok_or_else(|| anyhow!("{} is not in the recorded cache", name))
+    }
+}
+
+/// Extract the version from a wheel or sdist URL
+/// e.g. `.../urllib3-1.26.18-py2.py3-none-any.whl` -> `1.26.18`
+pub fn version_from_url(url: &str) -> Option<String> {
+    let filename = url
+        .split(['#', '?'])
+        .next()?
+        .rsplit('/')
+        .next()?;
+
+    if let Some(stem) = filename.strip_suffix(".whl") {
+        return stem.split('-').nth(1).map(|v| v.to_string());
+    }
+
+    let stem = filename
+        .strip_suffix(".tar.gz")
+        .or_else(|| filename.strip_suffix(".zip"))?;
+    stem.rsplit_once('-').map(|(_, v)| v.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_from_url() {
+        assert_eq!(
+            version_from_url("https://example.com/urllib3-1.26.18-py2.py3-none-any.whl#sha256=abc"),
+            Some("1.26.18".to_string())
+        );
+        assert_eq!(
+            version_from_url("https://example.com/my-pkg-2.0.tar.gz"),
+            Some("2.0".to_string())
+        );
+        assert_eq!(version_from_url("https://example.com/archive"), None);
+    }
+}
@@ -0,0 +1,111 @@
+/// Requires-Python checks and picking a release the target interpreter supports
+///
+/// The index reports a `Requires-Python` specifier per release. When the
+/// latest release doesn't support the target Python, the resolver walks the
+/// release list from newest to oldest and takes the first one that does.
+/// Pre-releases are only picked when no final release qualifies, or when
+/// the requirement itself names a pre-release (as PEP 440 prescribes).
+/// Yanked releases are never picked here; only an exact `==` pin may still
+/// install one (PEP 592).
+use crate::models::{VersionOp, VersionSpec};
+use crate::utils::version::{self, Version};
+
+/// One release of a package as listed by the index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Release {
+    pub version: String,
+    pub requires_python: Option<String>,
+    /// Every file of the release is yanked (PEP 592)
+    pub yanked: bool,
+    /// Why, when the index says
+    pub yanked_reason: Option<String>,
+}
+
+/// Whether `python` (e.g. `3.8.10`) satisfies a Requires-Python specifier
+/// set such as `>=3.8, !=3.9.*`. Clauses that can't be parsed are ignored,
+/// as pip does, rather than rejecting the release.
+pub fn python_satisfies(requires_python: &str, python: &str) -> bool {
+    let Ok(python) = Version::parse(python) else {
+        return true;
+    };
+    requires_python
+        .split(',')
+        .filter_map(|clause| clause.parse::<VersionSpec>().ok())
+        // `>=3.6.*` is invalid, but common enough on PyPI to tolerate
+        .filter(|spec| !spec.version.ends_with(".*") || matches!(spec.op, VersionOp::Eq | VersionOp::NotEq))
+        .all(|spec| python.satisfies(&spec))
+}
+
+/// The newest non-yanked release in `releases` that supports `python`.
+/// Final releases win over pre-releases unless `allow_prereleases`.
+pub fn select_release<'a>(releases: &'a [Release], python: &str, allow_prereleases: bool) -> Option<&'a Release> {
+    let supported = || {
+        releases.iter().filter(|release| {
+            !release.yanked
+                && release
+                    .requires_python
+                    .as_deref()
+                    .is_none_or(|spec| python_satisfies(spec, python))
+        })
+    };
+    let newest = |candidates: Vec<&'a Release>| {
+        candidates
+            .into_iter()
+            .max_by(|a, b| version::compare(&a.version, &b.version))
+    };
+
+    let finals = supported().filter(|release| !version::is_prerelease(&release.version)).collect();
+    match newest(finals) {
+        Some(release) if !allow_prereleases => Some(release),
+        _ => newest(supported().collect()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn release(version: &str, requires_python: Option<&str>) -> Release {
+        Release {
+            version: version.to_string(),
+            requires_python: requires_python.map(String::from),
+            yanked: false,
+            yanked_reason: None,
+        }
+    }
+
+    #[test]
+    fn test_python_satisfies() {
+        assert!(python_satisfies(">=3.8", "3.8.0"));
+        assert!(!python_satisfies(">=3.10", "3.8.18"));
+        assert!(python_satisfies(">=2.7, !=3.0.*, !=3.1.*", "3.8.0"));
+        assert!(!python_satisfies(">=2.7, !=3.8.*", "3.8.10"));
+        assert!(python_satisfies("~=3.8", "3.11.0"));
+        assert!(!python_satisfies("~=3.8.1", "3.9.0"));
+        assert!(!python_satisfies("<3.11", "3.11.0"));
+        assert!(python_satisfies("==3.*", "3.12.1"));
+        // Unparseable clauses don't exclude a release
+        assert!(python_satisfies(">=3.8, garbage", "3.8.0"));
+        assert!(python_satisfies(">=3.6.*", "3.5.0"));
+        assert!(!python_satisfies(">=3.8", "3.8.0rc1"));
+    }
+
+    #[test]
+    fn test_select_release() {
+        let releases = [
+            release("1.0", Some(">=3.7")),
+            release("2.0", Some(">=3.8")),
+            release("3.0rc1", Some(">=3.8")),
+            release("3.0", Some(">=3.10")),
+            Release { yanked: true, ..release("2.1", Some(">=3.8")) },
+        ];
+        assert_eq!(select_release(&releases, "3.8.0", false).unwrap().version, "2.0");
+        assert_eq!(select_release(&releases, "3.12.0", false).unwrap().version, "3.0");
+        assert_eq!(select_release(&releases, "3.8.0", true).unwrap().version, "3.0rc1");
+        assert!(select_release(&releases, "3.6.0", false).is_none());
+
+        // Only pre-releases published: take the newest compatible one
+        let preview = [release("0.1a1", None), release("0.1b2", Some(">=3.9")), release("0.1b1", None)];
+        assert_eq!(select_release(&preview, "3.8.0", false).unwrap().version, "0.1b1");
+    }
+}
@@ -1,27 +1,92 @@
 /// Dependency resolution algorithm
-use crate::models::{Package, Requirement, VersionOp, Marker, Environment};
-use anyhow::Result;
-use std::collections::{HashMap, VecDeque, HashSet};
+use crate::models::{Package, Requirement, Marker, Environment};
+use super::overrides::{AppliedOverride, Override, OverrideSet};
+use super::extras;
+use super::budget::{BudgetLimit, Conflict, ResolutionBudget, ResolutionStats, ResolutionTooComplex};
+use super::index_policy::IndexSource;
+use super::markers::MarkerSkip;
+use super::provider::{MetadataProvider, PyPIProvider};
+use super::conflicts::{Demand, ResolutionImpossible};
+use super::error::ResolutionError;
+use super::requires_python::{python_satisfies, select_release, Release};
+use crate::utils::names::canonicalize_name;
+use crate::utils::version::{self, Version};
+use crate::installer::events::{self, InstallEvent};
+use crate::utils::cancel;
+use crate::utils::resources::ResourceBudget;
+use crate::utils::warnings::{WarningCode, warn_user};
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque, HashSet};
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::sync::Semaphore;
 
+/// Which parts of the dependency graph to resolve
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DependencyMode {
+    /// The requested packages and everything they require
+    #[default]
+    All,
+    /// Only the requested packages (`--no-deps`)
+    NoDeps,
+    /// Only the dependencies of the requested packages (`--only-deps`)
+    OnlyDeps,
+}
+
+impl DependencyMode {
+    /// Build the mode from the mutually exclusive CLI flags
+    pub fn from_flags(no_deps: bool, only_deps: bool) -> Result<Self> {
+        match (no_deps, only_deps) {
+            (true, true) => Err(anyhow!("--no-deps and --only-deps are mutually exclusive")),
+            (true, false) => Ok(DependencyMode::NoDeps),
+            (false, true) => Ok(DependencyMode::OnlyDeps),
+            (false, false) => Ok(DependencyMode::All),
+        }
+    }
+
+    pub fn is_all(&self) -> bool {
+        *self == DependencyMode::All
+    }
+}
+
 pub struct Resolver {
     cache: HashMap<String, Package>,
     visited: HashSet<String>,
     environment: Environment,
     constraints: HashMap<String, Vec<Requirement>>,
-    version_cache: HashMap<String, Vec<u32>>, // Cache parsed version parts
+    version_cache: HashMap<String, Option<Version>>, // Cache parsed versions
+    provider: Arc<dyn MetadataProvider>,
+    overrides: OverrideSet,
+    applied_overrides: Vec<AppliedOverride>,
+    direct_urls: HashMap<String, String>,
+    dependency_mode: DependencyMode,
+    local_packages: HashMap<String, Package>,
+    dependency_edges: HashMap<String, Vec<String>>,
+    max_concurrent: usize,
+    index_sources: HashMap<String, IndexSource>,
+    marker_skips: Vec<MarkerSkip>,
+    /// Every requirement on each package by canonical name, from any parent
+    demands: HashMap<String, Vec<Demand>>,
+    /// Release lists by canonical name, the candidates a version is picked from
+    releases: HashMap<String, Vec<Release>>,
+    /// Extras requested on each package by canonical name, from any parent
+    extras: HashMap<String, BTreeSet<String>>,
+    /// Extras whose dependencies have been queued already
+    expanded_extras: HashMap<String, BTreeSet<String>>,
+    /// Requested packages given up on, with why
+    unresolved: BTreeMap<String, String>,
+    /// Of those, the ones no index has at all, as spelled when required
+    not_found: BTreeSet<String>,
+    budget: ResolutionBudget,
+    stats: ResolutionStats,
+    started: Instant,
 }
 
 impl Resolver {
     pub fn new() -> Self {
-        Self {
-            cache: HashMap::new(),
-            visited: HashSet::new(),
-            environment: Environment::current(),
-            constraints: HashMap::new(),
-            version_cache: HashMap::new(),
-        }
+        Self::with_environment(Environment::current())
     }
 
     pub fn with_environment(environment: Environment) -> Self {
@@ -31,38 +96,425 @@ impl Resolver {
             environment,
             constraints: HashMap::new(),
             version_cache: HashMap::new(),
+            provider: Arc::new(PyPIProvider),
+            overrides: OverrideSet::default(),
+            applied_overrides: Vec::new(),
+            direct_urls: HashMap::new(),
+            dependency_mode: DependencyMode::All,
+            local_packages: HashMap::new(),
+            dependency_edges: HashMap::new(),
+            max_concurrent: ResourceBudget::global().network,
+            index_sources: HashMap::new(),
+            marker_skips: Vec::new(),
+            demands: HashMap::new(),
+            releases: HashMap::new(),
+            extras: HashMap::new(),
+            expanded_extras: HashMap::new(),
+            unresolved: BTreeMap::new(),
+            not_found: BTreeSet::new(),
+            budget: ResolutionBudget::default(),
+            stats: ResolutionStats::default(),
+            started: Instant::now(),
+        }
+    }
+
+    /// Restrict resolution to the requested packages or their dependencies
+    pub fn set_dependency_mode(&mut self, mode: DependencyMode) {
+        self.dependency_mode = mode;
+    }
+
+    /// Register a local project (e.g. from pyproject.toml) so requirements
+    /// naming it resolve without consulting the index
+    pub fn add_local_package(&mut self, package: Package) {
+        self.local_packages.insert(canonicalize_name(&package.name), package);
+    }
+
+    /// Whether a resolved package is a local project rather than an index release
+    pub fn is_local(&self, name: &str) -> bool {
+        self.local_packages.contains_key(&canonicalize_name(name))
+    }
+
+    /// Use a different metadata source (e.g. a mocked index in tests)
+    pub fn set_provider(&mut self, provider: Arc<dyn MetadataProvider>) {
+        self.provider = provider;
+    }
+
+    /// Index that served the metadata of a resolved package, when known
+    pub fn index_source(&self, name: &str) -> Option<&IndexSource> {
+        self.index_sources.get(&canonicalize_name(name))
+    }
+
+    /// Size metadata fetching from `budget` instead of the process budget
+    pub fn set_budget(&mut self, budget: &ResourceBudget) {
+        self.max_concurrent = budget.network;
+    }
+
+    /// Limit how long resolution may run and how much it may backtrack
+    pub fn set_resolution_budget(&mut self, budget: ResolutionBudget) {
+        self.budget = budget;
+    }
+
+    /// What the last resolution explored
+    pub fn resolution_stats(&self) -> &ResolutionStats {
+        &self.stats
+    }
+
+    /// Set dependency overrides from an overrides file
+    pub fn set_overrides(&mut self, overrides: OverrideSet) {
+        self.overrides = overrides;
+    }
+
+    /// Overrides that replaced a dependency edge during resolution
+    pub fn applied_overrides(&self) -> &[AppliedOverride] {
+        &self.applied_overrides
+    }
+
+    /// Declared overrides that never matched a dependency edge
+    pub fn unused_overrides(&self) -> Vec<&Override> {
+        self.overrides.unused()
+    }
+
+    /// Resolution graph: each resolved package and the names it requires
+    pub fn dependency_edges(&self) -> &HashMap<String, Vec<String>> {
+        &self.dependency_edges
+    }
+
+    /// Dependency edges left out because their environment marker was false
+    pub fn skipped_by_markers(&self) -> &[MarkerSkip] {
+        &self.marker_skips
+    }
+
+    /// Packages that were required but left out of the resolution (no
+    /// release found or none satisfied the requirements), with why
+    pub fn unresolved(&self) -> Vec<(&str, &str)> {
+        self.unresolved
+            .iter()
+            .filter(|(name, _)| !self.cache.keys().any(|cached| canonicalize_name(cached) == **name))
+            .map(|(name, reason)| (name.as_str(), reason.as_str()))
+            .collect()
+    }
+
+    /// Required packages that don't exist on any index (or `--find-links`
+    /// location), for "did you mean" suggestions; also in [`unresolved`](Self::unresolved)
+    pub fn not_found(&self) -> Vec<&str> {
+        self.not_found.iter().map(String::as_str).collect()
+    }
+
+    fn give_up(&mut self, name: &str, reason: String) {
+        self.unresolved.insert(canonicalize_name(name), reason);
+    }
+
+    /// Direct URL a resolved package should be installed from, if any
+    pub fn direct_url(&self, name: &str) -> Option<&str> {
+        self.direct_urls.get(name).map(|s| s.as_str())
+    }
+
+    /// Apply overrides to a dependency edge, recording any replacement
+    fn apply_overrides(&mut self, parent: Option<&str>, req: Requirement) -> Requirement {
+        if self.overrides.is_empty() {
+            return req;
+        }
+        let (req, applied) = self.overrides.apply(parent, req);
+        if let Some(applied) = applied
+            && !self.applied_overrides.contains(&applied)
+        {
+            tracing::debug!("Applied override: {}", applied);
+            self.applied_overrides.push(applied);
+        }
+        req
+    }
+
+    /// Remember a marker-excluded edge (extras aside), once per edge
+    fn record_marker_skip(&mut self, package: &str, required_by: &str, marker: &Marker) {
+        if MarkerSkip::is_extra_marker(marker) {
+            return;
+        }
+        tracing::debug!("Skipping {} (required by {}): marker {} is false", package, required_by, marker.expression);
+        let skip = MarkerSkip::new(package, required_by, marker, &self.environment);
+        if !self.marker_skips.contains(&skip) {
+            self.marker_skips.push(skip);
+        }
+    }
+
+    /// Extras requested on `name` so far, by any requirement naming it
+    pub fn requested_extras(&self, name: &str) -> Vec<String> {
+        self.extras.get(&canonicalize_name(name)).into_iter().flatten().cloned().collect()
+    }
+
+    fn request_extras(&mut self, req: &Requirement) {
+        let extras = req.extras.iter().filter(|e| !e.is_empty()).map(|e| canonicalize_name(e));
+        self.extras.entry(canonicalize_name(&req.name)).or_default().extend(extras);
+    }
+
+    /// Queue the dependencies of extras requested on `package` that
+    /// haven't been expanded yet, warning about extras it doesn't declare
+    fn expand_extras(&mut self, package: &Package, queue: &mut VecDeque<Requirement>) {
+        let key = canonicalize_name(&package.name);
+        let requested = self.extras.get(&key).cloned().unwrap_or_default();
+        let expanded = self.expanded_extras.entry(key).or_default();
+        let new: Vec<String> = requested.difference(expanded).cloned().collect();
+        if new.is_empty() {
+            return;
+        }
+        expanded.extend(new.iter().cloned());
+
+        for extra in extras::unknown_extras(package, &new) {
+            warn_user(
+                WarningCode::UnknownExtra,
+                format!("{} {} does not provide the extra '{}'", package.name, package.version, extra),
+            );
+        }
+        for dep_req in extras::resolve_extras(package, &new, &self.environment) {
+            let dep_req = self.apply_overrides(Some(&package.name), dep_req);
+            self.demand(&dep_req, Some(&package.name));
+            let edges = self.dependency_edges.entry(package.name.clone()).or_default();
+            if !edges.contains(&dep_req.name) {
+                edges.push(dep_req.name.clone());
+            }
+            if !self.visited.contains(&dep_req.name) || !dep_req.extras.is_empty() {
+                queue.push_back(dep_req);
+            }
         }
     }
 
-    /// Set constraints from a constraints file
+    /// Count a fetched candidate that had to be given up; fails once the
+    /// backtrack budget is spent
+    fn backtrack(
+        &mut self,
+        package: &str,
+        requirement: String,
+        required_by: Option<String>,
+    ) -> Result<(), ResolutionError> {
+        tracing::debug!("Rejected a candidate of {}: {}", package, requirement);
+        self.stats.record_backtrack(Conflict {
+            package: package.to_string(),
+            requirement,
+            required_by,
+        });
+        if self.stats.backtracks > self.budget.max_backtracks {
+            return Err(self.out_of_budget(BudgetLimit::Backtracks(self.budget.max_backtracks)));
+        }
+        Ok(())
+    }
+
+    fn out_of_budget(&mut self, limit: BudgetLimit) -> ResolutionError {
+        self.stats.elapsed = self.started.elapsed();
+        ResolutionError::TooComplex(ResolutionTooComplex {
+            limit,
+            stats: self.stats.clone(),
+        })
+    }
+
+    /// The first package (by name) whose dependencies include `name`
+    fn required_by(&self, name: &str) -> Option<String> {
+        self.dependency_edges
+            .iter()
+            .filter(|(_, deps)| deps.iter().any(|dep| dep == name))
+            .map(|(parent, _)| parent.clone())
+            .min()
+    }
+
+    /// Add constraints from constraints files. They only narrow the
+    /// releases of packages something else requires, wherever in the tree
+    /// that is, and never add a package; several files merge. A constraint
+    /// whose marker doesn't match the target environment is dropped.
     pub fn set_constraints(&mut self, constraints: Vec<Requirement>) {
         for req in constraints {
-            self.constraints
-                .entry(req.name.clone())
-                .or_insert_with(Vec::new)
-                .push(req);
+            if let Some(marker_str) = &req.marker
+                && let Ok(marker) = Marker::parse(marker_str)
+                && !marker.evaluate(&self.environment)
+            {
+                continue;
+            }
+            self.constraints.entry(canonicalize_name(&req.name)).or_default().push(req);
+        }
+    }
+
+    /// The constraints on `name`, however it is spelled
+    fn constraints_on(&self, name: &str) -> Option<Vec<Requirement>> {
+        self.constraints.get(&canonicalize_name(name)).cloned()
+    }
+
+    /// Resolve `requirements`, backtracking when the versions picked don't
+    /// meet every requirement on them: each pass picks the newest release
+    /// that fits what is known, and a package whose pick misses a
+    /// requirement found later is pinned to the newest release meeting all
+    /// of them for the next pass. Fails with [`ResolutionError::Impossible`]
+    /// when there is no such release.
+    pub async fn resolve(&mut self, requirements: Vec<Requirement>) -> Result<Vec<Package>, ResolutionError> {
+        self.stats = ResolutionStats::default();
+        self.started = Instant::now();
+        let mut pins: BTreeMap<String, String> = BTreeMap::new();
+        let mut tried = HashSet::new();
+
+        loop {
+            self.reset_pass();
+            // Use concurrent resolution for better performance
+            let resolved = self.resolve_concurrent(requirements.clone(), self.max_concurrent, &pins).await?;
+            let Some(name) = self.find_conflict()? else {
+                return Ok(resolved);
+            };
+            match self.release_meeting_all(&name).await {
+                Some(version) if tried.insert((name.clone(), version.clone())) => {
+                    tracing::debug!("Backtracking: pinning {} to {}", name, version);
+                    pins.insert(name, version);
+                }
+                _ => return Err(self.impossible(&name).into()),
+            }
+        }
+    }
+
+    /// Forget what the previous pass picked
+    fn reset_pass(&mut self) {
+        self.cache.clear();
+        self.visited.clear();
+        self.dependency_edges.clear();
+        self.marker_skips.clear();
+        self.applied_overrides.clear();
+        self.direct_urls.clear();
+        self.index_sources.clear();
+        self.unresolved.clear();
+        self.not_found.clear();
+        self.demands.clear();
+        self.extras.clear();
+        self.expanded_extras.clear();
+    }
+
+    fn demand(&mut self, req: &Requirement, required_by: Option<&str>) {
+        let demand = Demand::new(req, required_by.map(canonicalize_name));
+        let demands = self.demands.entry(canonicalize_name(&req.name)).or_default();
+        if !demands.contains(&demand) {
+            demands.push(demand);
+        }
+    }
+
+    /// The first picked package (by name) whose version misses a
+    /// requirement on it, counted as a backtrack
+    fn find_conflict(&mut self) -> Result<Option<String>, ResolutionError> {
+        let mut picked: Vec<(String, String)> = self
+            .cache
+            .values()
+            .map(|package| (canonicalize_name(&package.name), package.version.clone()))
+            .collect();
+        picked.sort();
+        for (name, version) in picked {
+            let direct = self.direct_urls.keys().any(|url_name| canonicalize_name(url_name) == name);
+            if direct || self.is_local(&name) {
+                continue;
+            }
+            for demand in self.demands.get(&name).cloned().unwrap_or_default() {
+                if !self.satisfies_version(&version, &demand.requirement.specs) {
+                    let required_by = demand.required_by.as_deref().map(|parent| self.label(parent));
+                    self.backtrack(&name, demand.requirement.to_string(), required_by)?;
+                    return Ok(Some(name));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// The newest release of `name` meeting every requirement and
+    /// constraint on it that supports the target Python
+    async fn release_meeting_all(&mut self, name: &str) -> Option<String> {
+        let demands = self.demands.get(name).cloned().unwrap_or_default();
+        let constraints = self.constraints_on(name).unwrap_or_default();
+        let releases = match self.releases(name).await {
+            Ok(releases) => releases,
+            Err(e) => {
+                tracing::debug!("Cannot list releases of {}: {}", name, e);
+                return None;
+            }
+        };
+        let mut acceptable = Vec::new();
+        for release in releases {
+            let specs = demands.iter().map(|d| &d.requirement).chain(&constraints).flat_map(|r| r.specs.clone());
+            let specs: Vec<_> = specs.collect();
+            if self.satisfies_version(&release.version, &specs) {
+                acceptable.push(release);
+            }
         }
+        let allow_prereleases = demands
+            .iter()
+            .flat_map(|d| &d.requirement.specs)
+            .any(|spec| version::is_prerelease(&spec.version));
+        let python = self.environment.target_python().to_string();
+        select_release(&acceptable, &python, allow_prereleases).map(|release| release.version.clone())
     }
 
-    pub async fn resolve(&mut self, requirements: Vec<Requirement>) -> Result<Vec<Package>> {
-        // Use concurrent resolution for better performance
-        self.resolve_concurrent(requirements, 10).await
+    /// `name version` of a picked package, else just the name
+    fn label(&self, name: &str) -> String {
+        self.cache
+            .values()
+            .find(|package| canonicalize_name(&package.name) == name)
+            .map_or_else(|| name.to_string(), |package| format!("{} {}", package.name, package.version))
+    }
+
+    fn impossible(&self, name: &str) -> ResolutionImpossible {
+        let chains = self
+            .demands
+            .get(name)
+            .into_iter()
+            .flatten()
+            .map(|demand| {
+                let mut chain = vec![demand.requirement.to_string()];
+                let mut parent = demand.required_by.clone();
+                while let Some(current) = parent {
+                    if chain.len() > self.demands.len() {
+                        break;
+                    }
+                    chain.push(self.label(&current));
+                    // Follow whoever required the parent first
+                    parent = self.demands.get(&current).and_then(|d| d.first()).and_then(|d| d.required_by.clone());
+                }
+                chain.reverse();
+                chain
+            })
+            .collect();
+        ResolutionImpossible {
+            package: name.to_string(),
+            chains,
+        }
     }
 
     /// Resolve dependencies with bounded concurrency for better performance
-    async fn resolve_concurrent(&mut self, requirements: Vec<Requirement>, max_concurrent: usize) -> Result<Vec<Package>> {
+    async fn resolve_concurrent(
+        &mut self,
+        requirements: Vec<Requirement>,
+        max_concurrent: usize,
+        pins: &BTreeMap<String, String>,
+    ) -> Result<Vec<Package>, ResolutionError> {
         use futures::future;
         
         let mut resolved = Vec::new();
+        let requirements: Vec<Requirement> = requirements
+            .into_iter()
+            .map(|req| self.apply_overrides(None, req))
+            .collect();
+        for req in &requirements {
+            self.demand(req, None);
+        }
+        let targets: HashSet<String> = requirements.iter().map(|req| req.name.clone()).collect();
         let mut queue: VecDeque<Requirement> = requirements.into_iter().collect();
         let semaphore = Arc::new(Semaphore::new(max_concurrent));
 
         while !queue.is_empty() {
+            if cancel::is_requested() {
+                return Err(ResolutionError::Cancelled);
+            }
+
             // Collect batch of requirements to fetch concurrently
             let mut batch = Vec::new();
             while batch.len() < max_concurrent && !queue.is_empty() {
                 if let Some(req) = queue.pop_front() {
+                    self.request_extras(&req);
                     if self.visited.contains(&req.name) {
+                        // Extras asked for by a later parent still need expanding
+                        let cached = self.cache.values().find(|p| canonicalize_name(&p.name) == canonicalize_name(&req.name));
+                        if let Some(package) = cached.cloned()
+                            && self.dependency_mode != DependencyMode::NoDeps
+                        {
+                            self.expand_extras(&package, &mut queue);
+                        }
                         continue;
                     }
                     self.visited.insert(req.name.clone());
@@ -73,39 +525,88 @@ impl Resolver {
             if batch.is_empty() {
                 break;
             }
+            self.stats.rounds += 1;
 
             // Fetch all packages in batch concurrently
             let sem = semaphore.clone();
             let batch_clone: Vec<_> = batch.iter().map(|req| {
-                (req.name.clone(), req.specs.clone(), self.constraints.get(&req.name).cloned())
+                (req.name.clone(), req.specs.clone(), req.url.clone(), self.constraints_on(&req.name))
             }).collect();
             
-            let handles: Vec<_> = batch_clone.into_iter().zip(batch.iter()).map(|((name, specs, constraint_reqs), req)| {
+            let handles: Vec<_> = batch_clone.into_iter().zip(batch.iter()).map(|((name, specs, url, constraint_reqs), req)| {
                 let sem = sem.clone();
+                let provider = self.provider.clone();
+                let local = self.local_packages.get(&req.name).cloned();
+                let pinned = pins.get(&canonicalize_name(&req.name)).cloned();
                 let req_name = req.name.clone();
                 tokio::spawn(async move {
                     let _permit = sem.acquire().await;
-                    let result = crate::network::get_package_metadata(&name, "latest").await;
-                    (req_name, result, specs, constraint_reqs)
+                    let result = match (&url, local, pinned) {
+                        (_, Some(local), _) => Ok((local, None)),
+                        (Some(url), None, _) => provider.get_package_from_url(&name, url).await.map(|p| (p, None)),
+                        // Pinned by backtracking; the latest release's index serves it
+                        (None, None, Some(version)) => match provider.get_package_with_source(&name).await {
+                            Ok((latest, source)) if latest.version == version => Ok((latest, source)),
+                            Ok((_, source)) => provider.get_package_version(&name, &version).await.map(|p| (p, source)),
+                            Err(e) => Err(e),
+                        },
+                        (None, None, None) => provider.get_package_with_source(&name).await,
+                    };
+                    (req_name, result, specs, url, constraint_reqs)
                 })
             }).collect();
 
-            // Wait for all in batch to complete
-            let results = future::join_all(handles).await;
+            // Wait for all in batch to complete, within the time budget
+            let remaining = self.budget.timeout.saturating_sub(self.started.elapsed());
+            let aborts: Vec<_> = handles.iter().map(|handle| handle.abort_handle()).collect();
+            let results = match tokio::time::timeout(remaining, future::join_all(handles)).await {
+                Ok(results) => results,
+                Err(_) => {
+                    aborts.iter().for_each(|handle| handle.abort());
+                    return Err(self.out_of_budget(BudgetLimit::Timeout(self.budget.timeout)));
+                }
+            };
 
             // Process results
             for result in results {
                 match result {
-                    Ok((req_name, package_result, specs, constraint_reqs)) => {
+                    Ok((req_name, package_result, specs, url, constraint_reqs)) => {
                         match package_result {
-                            Ok(package) => {
+                            Ok((package, source)) => {
+                                self.stats.record_candidate(&req_name);
+                                // The latest release may miss the requirement or not support the target interpreter
+                                let package = if url.is_none() && !self.local_packages.contains_key(&req_name) {
+                                    match self.select_candidate(package, &specs, constraint_reqs.as_deref()).await {
+                                        Ok(package) => package,
+                                        Err(e) => match e.downcast::<ResolutionError>() {
+                                            Ok(e) => return Err(e),
+                                            Err(e) => {
+                                                self.give_up(&req_name, e.to_string());
+                                                warn_user(WarningCode::PackageSkipped, e.to_string());
+                                                continue;
+                                            }
+                                        },
+                                    }
+                                } else {
+                                    package
+                                };
+
                                 // Check version constraints from requirement
                                 if !self.satisfies_version(&package.version, &specs) {
-                                    tracing::warn!(
+                                    let requirement = Requirement {
+                                        name: req_name.clone(),
+                                        specs: specs.clone(),
+                                        extras: Vec::new(),
+                                        marker: None,
+                                        url: None,
+                                    };
+                                    self.backtrack(&req_name, requirement.to_string(), self.required_by(&req_name))?;
+                                    let reason = format!(
                                         "Package {} version {} does not satisfy requirements",
-                                        package.name,
-                                        package.version
+                                        package.name, package.version
                                     );
+                                    self.give_up(&req_name, format!("{} ({})", reason, requirement));
+                                    warn_user(WarningCode::PackageSkipped, reason);
                                     continue;
                                 }
                                 
@@ -114,6 +615,12 @@ impl Resolver {
                                     let mut satisfies_constraints = true;
                                     for constraint_req in &constraint_reqs {
                                         if !self.satisfies_version(&package.version, &constraint_req.specs) {
+                                            self.backtrack(&req_name, format!("{} (constraint)", constraint_req), None)?;
+                                            let reason = format!(
+                                                "{} {} conflicts with constraint {}",
+                                                package.name, package.version, constraint_req
+                                            );
+                                            self.give_up(&req_name, reason);
                                             satisfies_constraints = false;
                                             break;
                                         }
@@ -125,29 +632,58 @@ impl Resolver {
                                 
                                 // Cache the package
                                 self.cache.insert(package.name.clone(), package.clone());
+                                if let Some(source) = source {
+                                    self.index_sources.insert(canonicalize_name(&package.name), source);
+                                }
+                                if let Some(url) = url {
+                                    self.direct_urls.insert(req_name.clone(), url);
+                                }
                                 
                                 // Package satisfies all constraints
                                 // Parse dependencies, filtering by environment markers
-                                for dep_str in &package.requires_dist {
-                                    if let Ok(dep_req) = dep_str.parse::<Requirement>() {
-                                        // Check if dependency applies to current environment
-                                        if let Some(marker_str) = &dep_req.marker {
-                                            if let Ok(marker) = Marker::parse(marker_str) {
-                                                if !marker.evaluate(&self.environment) {
-                                                    continue;
-                                                }
+                                // (--no-deps: don't expand the requested packages at all)
+                                if self.dependency_mode != DependencyMode::NoDeps {
+                                    for dep_str in &package.requires_dist {
+                                        if let Ok(dep_req) = dep_str.parse::<Requirement>() {
+                                            // Check if dependency applies to current environment
+                                            if let Some(marker_str) = &dep_req.marker
+                                                && let Ok(marker) = Marker::parse(marker_str)
+                                                && !marker.evaluate(&self.environment)
+                                            {
+                                                self.record_marker_skip(&dep_req.name, &package.name, &marker);
+                                                continue;
                                             }
-                                        }
 
-                                        if !self.visited.contains(&dep_req.name) {
-                                            queue.push_back(dep_req);
+                                            let dep_req = self.apply_overrides(Some(&package.name), dep_req);
+                                            self.demand(&dep_req, Some(&package.name));
+                                            self.dependency_edges
+                                                .entry(package.name.clone())
+                                                .or_default()
+                                                .push(dep_req.name.clone());
+                                            if !self.visited.contains(&dep_req.name) || !dep_req.extras.is_empty() {
+                                                queue.push_back(dep_req);
+                                            }
                                         }
                                     }
+                                    self.expand_extras(&package, &mut queue);
+                                }
+                                // --only-deps: resolve the targets' trees but leave the targets out
+                                if self.dependency_mode == DependencyMode::OnlyDeps
+                                    && targets.contains(&req_name)
+                                {
+                                    continue;
                                 }
                                 resolved.push(package);
                             }
                             Err(e) => {
-                                tracing::warn!("Failed to fetch package {}: {}", req_name, e);
+                                let reason = format!("Failed to fetch package {}: {}", req_name, e);
+                                self.give_up(&req_name, reason.clone());
+                                // Reported by the command, with suggestions
+                                if crate::network::is_missing_project(&e) {
+                                    self.not_found.insert(req_name);
+                                } else {
+                                    warn_user(WarningCode::PackageSkipped, reason);
+                                }
                             }
                         }
                     }
@@ -156,8 +692,14 @@ impl Resolver {
                     }
                 }
             }
+            events::emit(InstallEvent::ResolutionProgress {
+                round: self.stats.rounds,
+                resolved: resolved.len(),
+                pending: queue.len(),
+            });
         }
 
+        self.stats.elapsed = self.started.elapsed();
         Ok(resolved)
     }
 
@@ -187,10 +729,9 @@ impl Resolver {
                     }
                     
                     // Check constraints file constraints
-                    if let Some(constraint_reqs) = self.constraints.get(&req.name) {
+                    if let Some(constraint_reqs) = self.constraints_on(&req.name) {
                         let mut satisfies_constraints = true;
-                        let constraint_reqs_clone = constraint_reqs.clone();
-                        for constraint_req in constraint_reqs_clone {
+                        for constraint_req in constraint_reqs {
                             if !self.satisfies_version(&package.version, &constraint_req.specs) {
                                 tracing::warn!(
                                     "Package {} version {} does not satisfy constraint {}",
@@ -240,12 +781,126 @@ impl Resolver {
         Ok(resolved)
     }
 
+    /// `package` (the latest release) if it meets `specs`, the constraints
+    /// and the target Python; otherwise the newest older release that does
+    async fn select_candidate(
+        &mut self,
+        package: Package,
+        specs: &[crate::models::VersionSpec],
+        constraint_reqs: Option<&[Requirement]>,
+    ) -> Result<Package> {
+        let python = self.environment.target_python().to_string();
+        let name = canonicalize_name(&package.name);
+        let required_by = self.required_by(&name);
+        let requirement = Requirement {
+            name: name.clone(),
+            specs: specs.to_vec(),
+            extras: Vec::new(),
+            marker: None,
+            url: None,
+        };
+        let mut failed_constraint = None;
+        for constraint in constraint_reqs.unwrap_or_default() {
+            if !self.satisfies_version(&package.version, &constraint.specs) {
+                failed_constraint = Some(constraint.clone());
+                break;
+            }
+        }
+
+        // Why the latest release won't do, as the error if nothing else does either
+        let rejection = if let Some(requires_python) =
+            package.requires_python.clone().filter(|spec| !python_satisfies(spec, &python))
+        {
+            self.backtrack(&name, format!("Requires-Python {}", requires_python), required_by)?;
+            format!(
+                "No release of {} supports Python {} (the latest, {}, requires Python {})",
+                package.name, python, package.version, requires_python
+            )
+        } else if !self.satisfies_version(&package.version, specs) {
+            self.backtrack(&name, requirement.to_string(), required_by)?;
+            format!(
+                "Package {} version {} does not satisfy requirements ({})",
+                package.name, package.version, requirement
+            )
+        } else if let Some(constraint) = failed_constraint {
+            self.backtrack(&name, format!("{} (constraint)", constraint), None)?;
+            format!("{} {} conflicts with constraint {}", package.name, package.version, constraint)
+        } else {
+            match self.yanked_release(&package.name, &package.version).await {
+                Some(release) if !pins_exactly(specs) => {
+                    format!("{} {} is yanked{}", package.name, package.version, yank_reason(&release))
+                }
+                Some(release) => {
+                    warn_yanked(&package.name, &release);
+                    return Ok(package);
+                }
+                None => return Ok(package),
+            }
+        };
+
+        let mut acceptable = Vec::new();
+        for release in self.releases(&package.name).await? {
+            let constrained = constraint_reqs
+                .unwrap_or_default()
+                .iter()
+                .all(|constraint| self.satisfies_version(&release.version, &constraint.specs));
+            if constrained && self.satisfies_version(&release.version, specs) {
+                acceptable.push(release);
+            }
+        }
+        let allow_prereleases = specs.iter().any(|spec| version::is_prerelease(&spec.version));
+        match select_release(&acceptable, &python, allow_prereleases) {
+            Some(release) => {
+                tracing::debug!("{} {} rejected ({}); using {}", package.name, package.version, rejection, release.version);
+                self.stats.record_candidate(&name);
+                self.provider.get_package_version(&package.name, &release.version).await
+            }
+            None => {
+                // PEP 592: a yanked release only for a requirement that pins it
+                let pinned = acceptable
+                    .iter()
+                    .filter(|release| release.yanked && pins_exactly(specs))
+                    .filter(|release| {
+                        release.requires_python.as_deref().is_none_or(|spec| python_satisfies(spec, &python))
+                    })
+                    .max_by(|a, b| version::compare(&a.version, &b.version));
+                if let Some(release) = pinned {
+                    warn_yanked(&package.name, release);
+                    self.stats.record_candidate(&name);
+                    return self.provider.get_package_version(&package.name, &release.version).await;
+                }
+                if !acceptable.is_empty() && acceptable.iter().all(|release| release.yanked) {
+                    return Err(anyhow!("Every release of {} matching '{}' is yanked", package.name, requirement));
+                }
+                Err(anyhow!(rejection))
+            }
+        }
+    }
+
+    /// `version` of `name` if the index lists it as yanked. A failure to
+    /// list the releases counts as not yanked; the latest release stands.
+    async fn yanked_release(&mut self, name: &str, version: &str) -> Option<Release> {
+        let releases = self.releases(name).await.ok()?;
+        releases.into_iter().find(|release| release.yanked && version::compare(&release.version, version).is_eq())
+    }
+
+    /// Every release of `name`, fetched once per resolution
+    async fn releases(&mut self, name: &str) -> Result<Vec<Release>> {
+        let key = canonicalize_name(name);
+        if let Some(releases) = self.releases.get(&key) {
+            return Ok(releases.clone());
+        }
+        let releases = self.provider.get_releases(name).await?;
+        self.releases.insert(key, releases.clone());
+        Ok(releases)
+    }
+
     async fn get_package(&mut self, name: &str) -> Result<Package> {
         if let Some(pkg) = self.cache.get(name) {
             return Ok(pkg.clone());
         }
 
-        let package = crate::network::get_package_metadata(name, "latest").await?;
+        let package = self.provider.get_package(name).await?;
         self.cache.insert(name.to_string(), package.clone());
         Ok(package)
     }
@@ -264,57 +919,23 @@ impl Resolver {
     }
 
     fn check_version_spec(&mut self, version: &str, spec: &crate::models::VersionSpec) -> bool {
-        use std::cmp::Ordering;
-
-        // Use cached parsed versions to avoid repeated parsing
-        let v1_parts = self.parse_version_cached(version);
-        let v2_parts = self.parse_version_cached(&spec.version);
-
-        let mut cmp = Ordering::Equal;
-        for i in 0..v1_parts.len().max(v2_parts.len()) {
-            let v1 = v1_parts.get(i).copied().unwrap_or(0);
-            let v2 = v2_parts.get(i).copied().unwrap_or(0);
-            cmp = v1.cmp(&v2);
-            if cmp != Ordering::Equal {
-                break;
-            }
-        }
-
-        match spec.op {
-            VersionOp::Eq => cmp == Ordering::Equal,
-            VersionOp::NotEq => cmp != Ordering::Equal,
-            VersionOp::Lt => cmp == Ordering::Less,
-            VersionOp::LtEq => cmp != Ordering::Greater,
-            VersionOp::Gt => cmp == Ordering::Greater,
-            VersionOp::GtEq => cmp != Ordering::Less,
-            VersionOp::Compatible => {
-                // ~= compatible release: allows patch-level changes
-                // Check major.minor match
-                let v1_major = v1_parts.get(0).copied().unwrap_or(0);
-                let v1_minor = v1_parts.get(1).copied().unwrap_or(0);
-                let v2_major = v2_parts.get(0).copied().unwrap_or(0);
-                let v2_minor = v2_parts.get(1).copied().unwrap_or(0);
-                v1_major == v2_major && v1_minor == v2_minor && cmp != Ordering::Less
-            }
+        if spec.op == crate::models::VersionOp::ArbitraryEq {
+            return version::satisfies(version, spec);
         }
+        self.parse_version_cached(version).is_some_and(|parsed| parsed.satisfies(spec))
     }
 
     /// Parse version and cache the result to avoid repeated parsing
-    fn parse_version_cached(&mut self, version: &str) -> Vec<u32> {
+    fn parse_version_cached(&mut self, version: &str) -> Option<Version> {
         // Check cache first
         if let Some(cached) = self.version_cache.get(version) {
             return cached.clone();
         }
         
         // Parse and cache
-        let parts: Vec<u32> = version
-            .split('.')
-            .filter_map(|p| p.parse::<u32>().ok())
-            .collect();
-        
-        // Cache the parsed version
-        self.version_cache.insert(version.to_string(), parts.clone());
-        parts
+        let parsed = Version::parse(version).ok();
+        self.version_cache.insert(version.to_string(), parsed.clone());
+        parsed
     }
 
     #[allow(dead_code)]
@@ -330,9 +951,318 @@ impl Default for Resolver {
     }
 }
 
+/// Whether `specs` name one version exactly (`==1.0`, `===1.0`), which is
+/// what lets a yanked release be installed
+fn pins_exactly(specs: &[crate::models::VersionSpec]) -> bool {
+    use crate::models::VersionOp;
+    specs.iter().any(|spec| match spec.op {
+        VersionOp::Eq => !spec.version.ends_with(".*"),
+        VersionOp::ArbitraryEq => true,
+        _ => false,
+    })
+}
+
+/// `: <reason>`, or nothing when the index gave none
+fn yank_reason(release: &Release) -> String {
+    release.yanked_reason.as_deref().map(|reason| format!(": {}", reason)).unwrap_or_default()
+}
+
+fn warn_yanked(name: &str, release: &Release) {
+    warn_user(
+        WarningCode::YankedRelease,
+        format!("{} {} is yanked{}; installing it since it is pinned", name, release.version, yank_reason(release)),
+    );
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use super::super::requires_python::Release;
+    use crate::models::VersionOp;
+    use async_trait::async_trait;
+
+    /// In-memory package graph standing in for the index
+    struct MockProvider {
+        packages: HashMap<String, Package>,
+    }
+
+    impl MockProvider {
+        fn new(graph: &[(&str, &str, &[&str])]) -> Arc<Self> {
+            let packages = graph
+                .iter()
+                .map(|(name, version, deps)| {
+                    let pkg = Package::new(name.to_string(), version.to_string())
+                        .with_requires(deps.iter().map(|d| d.to_string()).collect());
+                    (name.to_string(), pkg)
+                })
+                .collect();
+            Arc::new(Self { packages })
+        }
+    }
+
+    #[async_trait]
+    impl MetadataProvider for MockProvider {
+        async fn get_package(&self, name: &str) -> Result<Package> {
+            let indexes = vec!["pypi".to_string()];
+            self.packages.get(name).cloned().ok_or_else(|| match name {
+                "reqests" => crate::network::index::ProjectNotFound { package: name.to_string(), indexes }.into(),
+                _ => anyhow::anyhow!("{} not found", name),
+            })
+        }
+    }
+
+    fn mock_resolver(overrides: &str) -> Resolver {
+        let mut resolver = Resolver::new();
+        resolver.set_provider(MockProvider::new(&[
+            ("app", "1.0.0", &["requests>=2.0", "httpx>=0.20"]),
+            ("requests", "2.31.0", &["urllib3>=1.21", "idna>=2.5"]),
+            ("httpx", "0.27.0", &["idna>=2.0"]),
+            ("urllib3", "2.2.1", &[]),
+            ("idna", "3.7", &[]),
+        ]));
+        resolver.set_overrides(OverrideSet::parse(overrides).unwrap());
+        resolver
+    }
+
+    async fn resolved_names(mode: DependencyMode, requirements: &[&str]) -> Vec<String> {
+        let mut resolver = mock_resolver("");
+        resolver.set_dependency_mode(mode);
+        let reqs = requirements.iter().map(|r| r.parse().unwrap()).collect();
+        let mut names: Vec<String> = resolver
+            .resolve(reqs)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|p| p.name)
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[tokio::test]
+    async fn test_unresolved_packages_are_reported() {
+        let mut resolver = mock_resolver("");
+        let reqs = vec!["requests".parse().unwrap(), "missing-pkg".parse().unwrap()];
+        let resolved = resolver.resolve(reqs).await.unwrap();
+        // The rest of the closure comes back; a non-empty `unresolved()` is
+        // what install, download and lock refuse to go on with
+        assert_eq!(resolved.len(), 3);
+        assert!(resolved.iter().all(|p| p.name != "missing-pkg"));
+        assert_eq!(
+            resolver.unresolved(),
+            [("missing-pkg", "Failed to fetch package missing-pkg: missing-pkg not found")]
+        );
+        assert!(resolver.not_found().is_empty());
+
+        // Not on any index: also set apart for suggestions
+        let resolved = resolver.resolve(vec!["reqests".parse().unwrap()]).await.unwrap();
+        assert!(resolved.is_empty());
+        assert_eq!(resolver.not_found(), ["reqests"]);
+        assert_eq!(resolver.unresolved().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_dependency_modes() {
+        assert_eq!(
+            resolved_names(DependencyMode::All, &["requests"]).await,
+            vec!["idna", "requests", "urllib3"]
+        );
+        assert_eq!(resolved_names(DependencyMode::NoDeps, &["requests"]).await, vec!["requests"]);
+        assert_eq!(
+            resolved_names(DependencyMode::OnlyDeps, &["requests"]).await,
+            vec!["idna", "urllib3"]
+        );
+        assert!(DependencyMode::from_flags(true, true).is_err());
+    }
+
+    /// Counts metadata requests in flight at once
+    struct InstrumentedProvider {
+        inner: Arc<MockProvider>,
+        in_flight: std::sync::atomic::AtomicUsize,
+        peak: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait]
+    impl MetadataProvider for InstrumentedProvider {
+        async fn get_package(&self, name: &str) -> Result<Package> {
+            use std::sync::atomic::Ordering;
+            let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(now, Ordering::SeqCst);
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            self.inner.get_package(name).await
+        }
+    }
+
+    async fn peak_concurrency(jobs: usize) -> usize {
+        let provider = Arc::new(InstrumentedProvider {
+            inner: MockProvider::new(&[
+                ("app", "1.0.0", &["a", "b", "c", "d"]),
+                ("a", "1.0", &[]),
+                ("b", "1.0", &[]),
+                ("c", "1.0", &[]),
+                ("d", "1.0", &[]),
+            ]),
+            in_flight: Default::default(),
+            peak: Default::default(),
+        });
+        let mut resolver = Resolver::new();
+        resolver.set_provider(provider.clone());
+        resolver.set_budget(&ResourceBudget::from_jobs(jobs));
+        assert_eq!(resolver.resolve(vec!["app".parse().unwrap()]).await.unwrap().len(), 5);
+        provider.peak.load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    #[tokio::test]
+    async fn test_jobs_budget_bounds_fetch_concurrency() {
+        assert_eq!(peak_concurrency(1).await, 1);
+        assert_eq!(peak_concurrency(2).await, 4);
+    }
+
+    #[tokio::test]
+    async fn test_marker_skips_recorded_for_other_platforms() {
+        let mut env = Environment::current();
+        env.set("sys_platform", "linux").unwrap();
+        let mut resolver = Resolver::with_environment(env);
+        resolver.set_provider(MockProvider::new(&[
+            ("app", "1.0.0", &["pywin32>=306; sys_platform == \"win32\"", "rich[docs]; extra == \"docs\"", "idna"]),
+            ("pywin32", "306", &[]),
+            ("idna", "3.7", &[]),
+        ]));
+
+        let resolved = resolver.resolve(vec!["app".parse().unwrap()]).await.unwrap();
+        let mut names: Vec<&str> = resolved.iter().map(|p| p.name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, vec!["app", "idna"]);
+
+        assert_eq!(
+            super::super::markers::skipped_section(resolver.skipped_by_markers()),
+            vec![
+                "Skipped due to environment markers (1):".to_string(),
+                "  - pywin32 (required by app): sys_platform == \"win32\" [sys_platform=linux]".to_string(),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_extras_from_every_parent_are_resolved() {
+        let mut resolver = Resolver::new();
+        resolver.set_provider(MockProvider::new(&[
+            ("app", "1.0.0", &["requests[socks]>=2.0", "client"]),
+            ("client", "1.0.0", &["requests[use_chardet_on_py3,http3]"]),
+            (
+                "requests",
+                "2.31.0",
+                &[
+                    "idna<4,>=2.5",
+                    "PySocks!=1.5.7,>=1.5.6; extra == \"socks\"",
+                    "chardet<6,>=3.0.2; extra == \"use_chardet_on_py3\"",
+                ],
+            ),
+            ("idna", "3.7", &[]),
+            ("pysocks", "1.7.1", &[]),
+            ("chardet", "5.2.0", &[]),
+        ]));
+
+        let resolved = resolver.resolve(vec!["app".parse().unwrap()]).await.unwrap();
+        let mut names: Vec<&str> = resolved.iter().map(|p| p.name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, vec!["app", "chardet", "client", "idna", "pysocks", "requests"]);
+        assert_eq!(resolver.requested_extras("requests"), vec!["http3", "socks", "use-chardet-on-py3"]);
+        assert_eq!(resolver.dependency_edges()["requests"], vec!["idna", "pysocks", "chardet"]);
+        assert!(resolver.skipped_by_markers().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_dependency_edges_recorded() {
+        let mut resolver = mock_resolver("");
+        resolver.resolve(vec!["app".parse().unwrap()]).await.unwrap();
+
+        let edges = resolver.dependency_edges();
+        assert_eq!(edges["app"], vec!["requests", "httpx"]);
+        assert_eq!(edges["requests"], vec!["urllib3", "idna"]);
+        assert!(!edges.contains_key("idna"));
+    }
+
+    #[tokio::test]
+    async fn test_only_deps_of_local_project() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let pyproject_path = temp.path().join("pyproject.toml");
+        std::fs::write(
+            &pyproject_path,
+            "[project]\nname = \"my-app\"\nversion = \"0.1.0\"\ndependencies = [\n    \"requests>=2.0\",\n]\n",
+        )
+        .unwrap();
+        let pyproject = crate::config::pyproject::PyProject::load(&pyproject_path).unwrap();
+        let project = Package::new(pyproject.get_name().unwrap(), pyproject.get_version().unwrap())
+            .with_requires(pyproject.get_dependencies());
+
+        let mut resolver = mock_resolver("");
+        resolver.add_local_package(project);
+        resolver.set_dependency_mode(DependencyMode::OnlyDeps);
+        let resolved = resolver.resolve(vec!["my-app".parse().unwrap()]).await.unwrap();
+
+        let mut names: Vec<&str> = resolved.iter().map(|p| p.name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, vec!["idna", "requests", "urllib3"]);
+        assert!(resolver.is_local("my_app"));
+    }
+
+    #[tokio::test]
+    async fn test_only_deps_of_dotted_local_project() {
+        let project = Package::new("my.app".to_string(), "0.1.0".to_string())
+            .with_requires(vec!["idna".to_string()]);
+        let mut resolver = mock_resolver("");
+        resolver.add_local_package(project);
+        resolver.set_dependency_mode(DependencyMode::OnlyDeps);
+        let resolved = resolver.resolve(vec!["My_App".parse().unwrap()]).await.unwrap();
+
+        let names: Vec<&str> = resolved.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["idna"]);
+        assert!(resolver.is_local("my-app"));
+    }
+
+    #[tokio::test]
+    async fn test_override_replaces_edge_with_direct_url() {
+        let mut resolver = mock_resolver(
+            "[overrides]\nurllib3 = \"https://mirror.example.com/urllib3-1.26.18-py2.py3-none-any.whl\"\n",
+        );
+        let resolved = resolver.resolve(vec!["app".parse().unwrap()]).await.unwrap();
+
+        let urllib3 = resolved.iter().find(|p| p.name == "urllib3").unwrap();
+        assert_eq!(urllib3.version, "1.26.18");
+        assert_eq!(
+            resolver.direct_url("urllib3"),
+            Some("https://mirror.example.com/urllib3-1.26.18-py2.py3-none-any.whl")
+        );
+
+        let applied = resolver.applied_overrides();
+        assert_eq!(applied.len(), 1);
+        assert_eq!(applied[0].required_by.as_deref(), Some("requests"));
+        assert_eq!(applied[0].original, "urllib3>=1.21");
+        assert!(resolver.unused_overrides().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_scoped_override_and_unused_warning() {
+        let mut resolver = mock_resolver(
+            "[overrides]\nidna = { version = \"<3\", when-required-by = \"httpx\" }\nsix = \">=1.16\"\n",
+        );
+        // The replaced edge counts against idna 3.7, the only release there is
+        let error = resolver.resolve(vec!["app".parse().unwrap()]).await.unwrap_err();
+        assert_eq!(error.package(), Some("idna"));
+
+        // Only the httpx -> idna edge is replaced, requests -> idna is untouched
+        let applied = resolver.applied_overrides();
+        assert_eq!(applied.len(), 1);
+        assert_eq!(applied[0].required_by.as_deref(), Some("httpx"));
+        assert_eq!(applied[0].replacement, "idna<3");
+
+        // six never appears in the graph
+        let unused = resolver.unused_overrides();
+        assert_eq!(unused.len(), 1);
+        assert_eq!(unused[0].name, "six");
+    }
 
     #[test]
     fn test_version_comparison() {
@@ -345,5 +1275,251 @@ mod tests {
         assert!(resolver.check_version_spec("2.1.0", &spec));
         assert!(!resolver.check_version_spec("1.9.0", &spec));
     }
-}
 
+    /// Answers like [`MockProvider`] but never for `slow`
+    struct HangingProvider(Arc<MockProvider>);
+
+    #[async_trait]
+    impl MetadataProvider for HangingProvider {
+        async fn get_package(&self, name: &str) -> Result<Package> {
+            if name == "slow" {
+                std::future::pending::<()>().await;
+            }
+            self.0.get_package(name).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolution_budget_names_hot_packages() {
+        let graph = MockProvider::new(&[
+            ("app", "1.0", &["botocore>=2", "urllib3<1", "fine"]),
+            ("botocore", "1.0", &[]),
+            ("urllib3", "2.0", &[]),
+            ("fine", "1.0", &["slow"]),
+        ]);
+        let mut resolver = Resolver::new();
+        resolver.set_provider(Arc::new(HangingProvider(graph.clone())));
+        resolver.set_resolution_budget(ResolutionBudget {
+            timeout: std::time::Duration::from_millis(100),
+            ..Default::default()
+        });
+        let error = resolver.resolve(vec!["app".parse().unwrap()]).await.unwrap_err();
+        let ResolutionError::TooComplex(error) = error else { panic!("{}", error) };
+        assert!(matches!(error.limit, BudgetLimit::Timeout(_)));
+        assert_eq!(error.stats.backtracks, 2);
+        let message = error.to_string();
+        assert!(message.contains("botocore>=2 (required by app)"), "{}", message);
+        assert!(message.contains("pinning botocore, urllib3"), "{}", message);
+        assert!(message.contains("--resolution-timeout"), "{}", message);
+
+        // The backtrack limit stops it before the hang
+        let mut resolver = Resolver::new();
+        resolver.set_provider(Arc::new(HangingProvider(graph)));
+        resolver.set_resolution_budget(ResolutionBudget {
+            max_backtracks: 1,
+            ..Default::default()
+        });
+        let error = resolver.resolve(vec!["app".parse().unwrap()]).await.unwrap_err();
+        let ResolutionError::TooComplex(error) = error else { panic!("{}", error) };
+        assert_eq!(error.limit, BudgetLimit::Backtracks(1));
+        assert!(error.to_string().starts_with("resolution gave up after 1 backtracks"));
+    }
+
+    #[tokio::test]
+    async fn test_resolution_stats_on_success() {
+        let mut resolver = mock_resolver("");
+        resolver.set_budget(&ResourceBudget::from_jobs(4));
+        resolver.resolve(vec!["app".parse().unwrap()]).await.unwrap();
+        let stats = resolver.resolution_stats();
+        assert_eq!(stats.candidates_tried(), 5);
+        assert_eq!(stats.backtracks, 0);
+        assert_eq!(stats.rounds, 3);
+        assert!(stats.headline().starts_with("5 candidate versions of 5 packages in 3 rounds, 0 backtracks"));
+    }
+
+    /// Several releases per package, each with its own Requires-Python
+    struct ReleasesProvider {
+        releases: Vec<(&'static str, &'static str, Option<&'static str>, &'static [&'static str])>,
+        /// Name, version and reason of each yanked release
+        yanked: &'static [(&'static str, &'static str, &'static str)],
+    }
+
+    impl ReleasesProvider {
+        fn package(&self, name: &str, version: &str) -> Option<Package> {
+            self.releases
+                .iter()
+                .find(|(n, v, _, _)| *n == name && *v == version)
+                .map(|(name, version, requires_python, deps)| Package {
+                    requires_python: requires_python.map(String::from),
+                    ..Package::new(name.to_string(), version.to_string())
+                        .with_requires(deps.iter().map(|d| d.to_string()).collect())
+                })
+        }
+    }
+
+    #[async_trait]
+    impl MetadataProvider for ReleasesProvider {
+        /// The last listed release is the index's "latest"
+        async fn get_package(&self, name: &str) -> Result<Package> {
+            let (_, version, _, _) = self
+                .releases
+                .iter()
+                .rfind(|(n, _, _, _)| *n == name)
+                .ok_or_else(|| anyhow::anyhow!("{} not found", name))?;
+            Ok(self.package(name, version).unwrap())
+        }
+
+        async fn get_releases(&self, name: &str) -> Result<Vec<Release>> {
+            Ok(self
+                .releases
+                .iter()
+                .filter(|(n, _, _, _)| *n == name)
+                .map(|(name, version, requires_python, _)| {
+                    let yank = self.yanked.iter().find(|(n, v, _)| n == name && v == version);
+                    Release {
+                        version: version.to_string(),
+                        requires_python: requires_python.map(String::from),
+                        yanked: yank.is_some(),
+                        yanked_reason: yank.map(|(_, _, reason)| reason.to_string()),
+                    }
+                })
+                .collect())
+        }
+
+        async fn get_package_version(&self, name: &str, version: &str) -> Result<Package> {
+            self.package(name, version).ok_or_else(|| anyhow::anyhow!("{} {} not found", name, version))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_requires_python_falls_back_to_older_release() {
+        let mut env = Environment::current();
+        env.set("python_version", "3.8").unwrap();
+        env.set("python_full_version", "3.8.18").unwrap();
+        let mut resolver = Resolver::with_environment(env);
+        resolver.set_provider(Arc::new(ReleasesProvider {
+            releases: vec![
+                ("modern", "2.0", Some(">=3.7"), &[]),
+                ("modern", "2.5", Some(">=3.8"), &["helper"]),
+                ("modern", "3.0", Some(">=3.10"), &[]),
+                ("helper", "1.0", None, &[]),
+                // Only pre-releases published
+                ("preview", "0.1b1", Some(">=3.8"), &[]),
+                ("preview", "0.2b1", Some(">=3.10"), &[]),
+                // Nothing supports 3.8
+                ("newonly", "1.0", Some(">=3.10"), &[]),
+                ("newonly", "1.1", Some(">=3.11"), &[]),
+            ],
+            yanked: &[],
+        }));
+
+        let reqs = ["modern", "preview", "newonly"].iter().map(|r| r.parse().unwrap()).collect();
+        let mut resolved: Vec<String> = resolver
+            .resolve(reqs)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|p| format!("{}=={}", p.name, p.version))
+            .collect();
+        resolved.sort();
+        assert_eq!(resolved, vec!["helper==1.0", "modern==2.5", "preview==0.1b1"]);
+
+        // Specifiers still apply to the fallback
+        let mut env = Environment::current();
+        env.set("python_full_version", "3.8.18").unwrap();
+        env.set("python_version", "3.8").unwrap();
+        let mut resolver = Resolver::with_environment(env);
+        resolver.set_provider(Arc::new(ReleasesProvider {
+            releases: vec![("modern", "2.0", Some(">=3.7"), &[]), ("modern", "2.5", Some(">=3.8"), &[]), ("modern", "3.0", Some(">=3.10"), &[])],
+            yanked: &[],
+        }));
+        let resolved = resolver.resolve(vec!["modern<2.5".parse().unwrap()]).await.unwrap();
+        assert_eq!(resolved[0].version, "2.0");
+    }
+
+    fn conflict_provider(a_requires: &'static [&'static str], b_requires: &'static [&'static str]) -> Arc<ReleasesProvider> {
+        Arc::new(ReleasesProvider {
+            releases: vec![
+                ("app", "1.0", None, &["lib-a", "lib-b"]),
+                ("lib-a", "1.0", None, a_requires),
+                ("lib-b", "1.0", None, b_requires),
+                ("urllib3", "1.25.11", None, &[]),
+                ("urllib3", "1.26.18", None, &[]),
+                ("urllib3", "2.2.1", None, &[]),
+            ],
+            yanked: &[],
+        })
+    }
+
+    #[tokio::test]
+    async fn test_conflicting_requirements_backtrack_to_common_release() {
+        let mut resolver = Resolver::new();
+        resolver.set_provider(conflict_provider(&["urllib3>=1.21"], &["urllib3<2"]));
+        let resolved = resolver.resolve(vec!["app".parse().unwrap()]).await.unwrap();
+        let urllib3 = resolved.iter().find(|p| p.name == "urllib3").unwrap();
+        assert_eq!(urllib3.version, "1.26.18");
+        assert_eq!(resolved.len(), 4);
+        assert_eq!(resolver.resolution_stats().backtracks, 1);
+
+        // A requirement the latest release misses picks an older one up front
+        let mut resolver = Resolver::new();
+        resolver.set_provider(conflict_provider(&["urllib3<1.26"], &["urllib3>=1.21"]));
+        let resolved = resolver.resolve(vec!["app".parse().unwrap()]).await.unwrap();
+        assert_eq!(resolved.iter().find(|p| p.name == "urllib3").unwrap().version, "1.25.11");
+    }
+
+    #[tokio::test]
+    async fn test_resolution_impossible_names_requirement_chains() {
+        let mut resolver = Resolver::new();
+        resolver.set_provider(conflict_provider(&["urllib3>=1.21"], &["urllib3>=2.5"]));
+        let error = resolver.resolve(vec!["app".parse().unwrap()]).await.unwrap_err();
+        let ResolutionError::Impossible(error) = error else { panic!("{}", error) };
+        assert_eq!(error.package, "urllib3");
+        assert_eq!(
+            error.chains,
+            vec![
+                vec!["app 1.0", "lib-a 1.0", "urllib3>=1.21"],
+                vec!["app 1.0", "lib-b 1.0", "urllib3>=2.5"],
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_yanked_releases_are_skipped_unless_pinned() {
+        let provider = || {
+            Arc::new(ReleasesProvider {
+                releases: vec![
+                    ("demo", "1.0", None, &[]),
+                    ("demo", "1.1", None, &[]),
+                    ("gone", "0.1", None, &[]),
+                    ("gone", "0.2", None, &[]),
+                ],
+                yanked: &[("demo", "1.1", "broken wheel"), ("gone", "0.1", "withdrawn"), ("gone", "0.2", "withdrawn")],
+            })
+        };
+        // The version picked, or why the requirement was left unresolved
+        let resolve = |requirement: &str| {
+            let mut resolver = Resolver::new();
+            resolver.set_provider(provider());
+            let requirement = requirement.parse().unwrap();
+            async move {
+                let resolved = resolver.resolve(vec![requirement]).await.unwrap();
+                match resolver.unresolved().first() {
+                    Some((_, reason)) => Err(reason.to_string()),
+                    None => Ok(resolved[0].version.clone()),
+                }
+            }
+        };
+
+        // The latest is yanked: the older good release is picked
+        assert_eq!(resolve("demo").await.unwrap(), "1.0");
+        assert_eq!(resolve("demo>=1.0").await.unwrap(), "1.0");
+        // Pinned exactly, it is installed anyway
+        assert_eq!(resolve("demo==1.1").await.unwrap(), "1.1");
+        assert_eq!(resolve("gone==0.1").await.unwrap(), "0.1");
+        // Nothing left that isn't yanked
+        let error = resolve("gone").await.unwrap_err();
+        assert_eq!(error, "Every release of gone matching 'gone' is yanked");
+        assert_eq!(resolve("demo>1.0").await.unwrap_err(), "Every release of demo matching 'demo>1.0' is yanked");
+    }
+}
@@ -0,0 +1,304 @@
+/// Strict reproducibility checks for locked installs (`--strict-repro`)
+///
+/// A validation pass over the lock model that runs before anything is
+/// downloaded. Every entry must be hashed, VCS references must name a
+/// commit, local directories and unpinned source builds are refused, and
+/// the lock's recorded marker environment must equal the running one.
+use super::direct_url::DirectUrl;
+use super::lockfile::{LockFile, LockedPackage};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Archive suffixes of source distributions (they need a build)
+const SDIST_SUFFIXES: &[&str] = &[".tar.gz", ".tgz", ".tar.bz2", ".tar.xz", ".zip"];
+
+/// Pseudo-package name for violations of the lock as a whole
+pub const ENVIRONMENT: &str = "<environment>";
+
+/// Why a lock is not reproducible
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViolationKind {
+    MissingHash,
+    UnpinnedVcsReference,
+    LocalDirectory,
+    UnpinnedBuild,
+    EnvironmentMismatch,
+}
+
+/// One problem, with the offending package and how to fix it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub package: String,
+    pub kind: ViolationKind,
+    pub message: String,
+    pub hint: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} (hint: {})", self.package, self.message, self.hint)
+    }
+}
+
+/// All violations found in a lock
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StrictReproError {
+    pub violations: Vec<Violation>,
+}
+
+impl fmt::Display for StrictReproError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "lock file is not reproducible under --strict-repro ({} violation{}):",
+            self.violations.len(),
+            if self.violations.len() == 1 { "" } else { "s" }
+        )?;
+        for violation in &self.violations {
+            write!(f, "\n  - {}", violation)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for StrictReproError {}
+
+/// Check `lock` against the strict rules and the running marker environment
+pub fn validate(lock: &LockFile, environment: &BTreeMap<String, String>) -> Result<(), StrictReproError> {
+    let mut violations = Vec::new();
+    check_environment(lock, environment, &mut violations);
+
+    let mut packages: Vec<&LockedPackage> = lock.packages.values().collect();
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+    for package in packages {
+        check_package(package, &mut violations);
+    }
+
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(StrictReproError { violations })
+    }
+}
+
+fn check_environment(lock: &LockFile, running: &BTreeMap<String, String>, violations: &mut Vec<Violation>) {
+    let Some(locked) = &lock.environment else {
+        violations.push(Violation {
+            package: ENVIRONMENT.to_string(),
+            kind: ViolationKind::EnvironmentMismatch,
+            message: "the lock does not record the environment it was resolved for".to_string(),
+            hint: "regenerate the lock with `pip lock` on the target environment".to_string(),
+        });
+        return;
+    };
+
+    let keys: std::collections::BTreeSet<&String> = locked.keys().chain(running.keys()).collect();
+    let differences: Vec<String> = keys
+        .into_iter()
+        .filter(|key| locked.get(*key) != running.get(*key))
+        .map(|key| {
+            format!(
+                "{} locked as '{}' but running '{}'",
+                key,
+                locked.get(key).map(String::as_str).unwrap_or("<unset>"),
+                running.get(key).map(String::as_str).unwrap_or("<unset>")
+            )
+        })
+        .collect();
+    if !differences.is_empty() {
+        violations.push(Violation {
+            package: ENVIRONMENT.to_string(),
+            kind: ViolationKind::EnvironmentMismatch,
+            message: differences.join("; "),
+            hint: "install on the environment the lock was made for, or re-lock for this one".to_string(),
+        });
+    }
+}
+
+fn check_package(package: &LockedPackage, violations: &mut Vec<Violation>) {
+    let name = format!("{} {}", package.name, package.version);
+    let mut push = |kind, message: String, hint: String| {
+        violations.push(Violation {
+            package: name.clone(),
+            kind,
+            message,
+            hint,
+        })
+    };
+
+    let direct = package.url.as_deref().and_then(DirectUrl::parse);
+    if let Some(direct) = &direct
+        && direct.is_vcs()
+    {
+        let (base, reference) = vcs_reference(&direct.url);
+        if !reference.is_some_and(is_commit_hash) {
+            push(
+                ViolationKind::UnpinnedVcsReference,
+                match reference {
+                    Some(reference) => format!("VCS reference '{}' is not a commit hash", reference),
+                    None => "VCS URL has no reference (follows the default branch)".to_string(),
+                },
+                format!("pin to a commit: {}@<sha>", base),
+            );
+        }
+        // VCS checkouts are verified by their commit, not an archive hash
+        return;
+    }
+
+    if let Some(direct) = &direct
+        && direct.is_local()
+        && !is_archive(&direct.url)
+    {
+        push(
+            ViolationKind::LocalDirectory,
+            format!("points at a local directory ({}), which cannot be hashed", direct.url),
+            "build a wheel and lock its file URL with a hash".to_string(),
+        );
+        return;
+    }
+
+    if package.hash.is_none() {
+        push(
+            ViolationKind::MissingHash,
+            "lock entry has no hash".to_string(),
+            format!("re-lock with hashes or add one: \"hash\": \"sha256:<digest of {}>\"", package.name),
+        );
+    }
+
+    let url = package.url.as_deref().unwrap_or_default();
+    if SDIST_SUFFIXES.iter().any(|suffix| url.ends_with(suffix)) && !package.build.as_ref().is_some_and(|b| b.is_pinned()) {
+        push(
+            ViolationKind::UnpinnedBuild,
+            "resolves to a source distribution that must be built".to_string(),
+            "lock a wheel instead, or pin the build backend and its requirements with `==` in the lock's build section"
+                .to_string(),
+        );
+    }
+}
+
+/// Split `git+https://host/repo@ref#egg=x` into (`git+https://host/repo`, `ref`)
+fn vcs_reference(url: &str) -> (&str, Option<&str>) {
+    let url = url.split('#').next().unwrap_or(url);
+    // The reference follows the last `@` in the path, not userinfo (`git@host`)
+    let path_start = url.find("://").map(|i| i + 3).unwrap_or(0);
+    let path_start = path_start + url[path_start..].find('/').unwrap_or(0);
+    match url[path_start..].rfind('@') {
+        Some(at) => (&url[..path_start + at], Some(&url[path_start + at + 1..])),
+        None => (url, None),
+    }
+}
+
+fn is_commit_hash(reference: &str) -> bool {
+    matches!(reference.len(), 40 | 64) && reference.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn is_archive(url: &str) -> bool {
+    url.ends_with(".whl") || SDIST_SUFFIXES.iter().any(|suffix| url.ends_with(suffix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resolver::lockfile::BuildPin;
+
+    fn environment() -> BTreeMap<String, String> {
+        [("python_version", "3.11"), ("sys_platform", "linux"), ("platform_machine", "x86_64")]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn locked(name: &str, hash: Option<&str>, url: Option<&str>) -> LockedPackage {
+        LockedPackage {
+            name: name.to_string(),
+            version: "1.0".to_string(),
+            summary: None,
+            dependencies: Vec::new(),
+            hash: hash.map(String::from),
+            url: url.map(String::from),
+            index_url: None,
+            extras: Vec::new(),
+            build: None,
+            markers: None,
+        }
+    }
+
+    fn lock(packages: Vec<LockedPackage>, environment: Option<BTreeMap<String, String>>) -> LockFile {
+        let mut lock = LockFile::from_packages(Vec::new(), "3.11".to_string());
+        lock.packages = packages
+            .into_iter()
+            .map(|p| (format!("{}-{}", p.name, p.version), p))
+            .collect();
+        lock.environment = environment;
+        lock
+    }
+
+    #[test]
+    fn test_compliant_lock_passes() {
+        let mut built = locked("legacy", Some("sha256:bb"), Some("https://files.example/legacy-1.0.tar.gz"));
+        built.build = Some(BuildPin {
+            backend: "setuptools.build_meta".to_string(),
+            requires: vec!["setuptools==69.0.3".to_string(), "wheel==0.42.0".to_string()],
+        });
+        let lock = lock(
+            vec![
+                locked("requests", Some("sha256:aa"), Some("https://files.example/requests-1.0-py3-none-any.whl")),
+                locked(
+                    "tool",
+                    None,
+                    Some("git+https://github.com/acme/tool@0123456789abcdef0123456789abcdef01234567"),
+                ),
+                built,
+            ],
+            Some(environment()),
+        );
+        assert_eq!(validate(&lock, &environment()), Ok(()));
+    }
+
+    #[test]
+    fn test_every_violation_is_listed() {
+        let mut unpinned_build = locked("legacy", Some("sha256:bb"), Some("https://files.example/legacy-1.0.tar.gz"));
+        unpinned_build.build = Some(BuildPin {
+            backend: "setuptools.build_meta".to_string(),
+            requires: vec!["setuptools>=61".to_string()],
+        });
+        let mut locked_env = environment();
+        locked_env.insert("sys_platform".to_string(), "darwin".to_string());
+        let lock = lock(
+            vec![
+                locked("requests", None, None),
+                locked("tool", None, Some("git+ssh://git@github.com/acme/tool@main#egg=tool")),
+                locked("local", None, Some("file:///home/dev/src/local")),
+                unpinned_build,
+            ],
+            Some(locked_env),
+        );
+
+        let error = validate(&lock, &environment()).unwrap_err();
+        let kinds: Vec<(&str, ViolationKind)> =
+            error.violations.iter().map(|v| (v.package.as_str(), v.kind)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (ENVIRONMENT, ViolationKind::EnvironmentMismatch),
+                ("legacy 1.0", ViolationKind::UnpinnedBuild),
+                ("local 1.0", ViolationKind::LocalDirectory),
+                ("requests 1.0", ViolationKind::MissingHash),
+                ("tool 1.0", ViolationKind::UnpinnedVcsReference),
+            ]
+        );
+
+        let listing = error.to_string();
+        assert!(listing.starts_with("lock file is not reproducible under --strict-repro (5 violations):"));
+        assert!(listing.contains("sys_platform locked as 'darwin' but running 'linux'"));
+        assert!(listing.contains("pin to a commit: git+ssh://git@github.com/acme/tool@<sha>"));
+    }
+
+    #[test]
+    fn test_lock_without_environment_is_refused() {
+        let lock = lock(vec![locked("requests", Some("sha256:aa"), None)], None);
+        let error = validate(&lock, &environment()).unwrap_err();
+        assert_eq!(error.violations.len(), 1);
+        assert_eq!(error.violations[0].kind, ViolationKind::EnvironmentMismatch);
+    }
+}
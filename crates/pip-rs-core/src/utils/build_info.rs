@@ -0,0 +1,23 @@
+/// Build metadata reported by `pip version --verbose`
+///
+/// The binary fills this in from what its build script embedded; it also
+/// goes into `pip debug --dump-state` bundles so a report says exactly
+/// which build produced it.
+use serde::{Deserialize, Serialize};
+
+/// pip release whose command line and behavior pip-rs tracks
+pub const PIP_COMPATIBILITY: &str = "24.0";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildInfo {
+    pub version: String,
+    /// `git describe` of the source tree, or `unknown`
+    pub git_describe: String,
+    /// RFC 3339 build time (SOURCE_DATE_EPOCH when set)
+    pub build_date: String,
+    pub rustc: String,
+    pub target: String,
+    pub profile: String,
+    pub features: Vec<String>,
+    pub pip_compatibility: String,
+}
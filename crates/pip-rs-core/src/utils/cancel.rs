@@ -0,0 +1,55 @@
+/// Cooperative cancellation of a running command
+///
+/// A cancel request (from a `--progress-socket` consumer, or Ctrl-C) doesn't
+/// kill anything: the resolver stops between rounds, a download in flight is
+/// abandoned, a wheel being copied into place stops and is rolled back, and
+/// the installer starts no further package. Packages already installed stay.
+/// After Ctrl-C or SIGTERM the command exits with [`EXIT_INTERRUPTED`].
+use once_cell::sync::Lazy;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::Notify;
+
+/// Exit status after an interrupt, as shells report death by SIGINT
+pub const EXIT_INTERRUPTED: i32 = 130;
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+static NOTIFY: Lazy<Notify> = Lazy::new(Notify::new);
+
+/// Ask the running command to stop at its next safe point
+pub fn request() {
+    REQUESTED.store(true, Ordering::SeqCst);
+    NOTIFY.notify_waiters();
+}
+
+pub fn is_requested() -> bool {
+    REQUESTED.load(Ordering::SeqCst)
+}
+
+/// A cancel request from Ctrl-C or SIGTERM, which also sets the exit status
+pub fn interrupt() {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+    request();
+}
+
+pub fn is_interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Completes once cancellation is requested, for racing against work that
+/// can be dropped midway (downloads)
+pub async fn requested() {
+    loop {
+        // Registered before the check, so a request in between still wakes it
+        let notified = NOTIFY.notified();
+        if is_requested() {
+            return;
+        }
+        notified.await;
+    }
+}
+
+/// Forget an earlier request, for callers that run several commands
+pub fn reset() {
+    REQUESTED.store(false, Ordering::SeqCst);
+}
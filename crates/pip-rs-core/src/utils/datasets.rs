@@ -0,0 +1,357 @@
+/// Bundled data assets and their signed updates
+///
+/// Data tables such as the import-name map are compiled in with a version
+/// and publication date. `pip datasets update` installs refreshed copies in
+/// the data directory (`data-dir` in pip.conf, PIP_DATA_DIR) next to a
+/// manifest signed with the pip-rs datasets key (`data/datasets.pub`). An
+/// updated copy is used only while its manifest verifies and its sha256
+/// matches; anything else falls back to the bundled copy. Consumers read
+/// through [`Datasets::read`] on every use, so an update applies without a
+/// restart.
+use crate::config::config::Config;
+use crate::utils::minisign::{self, PublicKey};
+use anyhow::{Result, anyhow};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where `pip datasets update` fetches from unless `datasets-url` is set
+pub const DEFAULT_DATASETS_URL: &str = "https://raw.githubusercontent.com/yingkitw/pip-rs/main/datasets";
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+pub const SIGNATURE_FILE: &str = "manifest.json.minisig";
+
+/// Subdirectory of the data directory holding installed updates
+const DATASETS_DIR: &str = "datasets";
+
+/// Names the generation directory currently in use
+const CURRENT_FILE: &str = "CURRENT";
+
+/// Public key the published manifests are signed with
+const DATASETS_KEY: &str = include_str!("../../data/datasets.pub");
+
+/// A data table compiled into pip-rs
+#[derive(Debug, Clone, Copy)]
+pub struct BundledAsset {
+    pub name: &'static str,
+    pub file: &'static str,
+    pub version: &'static str,
+    /// Publication date (YYYY-MM-DD)
+    pub published: &'static str,
+    pub content: &'static str,
+}
+
+/// Every bundled asset, by name
+pub const BUNDLED: &[BundledAsset] = &[
+    BundledAsset {
+        name: "import-names",
+        file: "import-names.txt",
+        version: "2026.10",
+        published: "2026-10-01",
+        content: include_str!("../../data/import-names.txt"),
+    },
+    BundledAsset {
+        name: "top-packages",
+        file: "top-packages.txt",
+        version: "2026.10",
+        published: "2026-10-01",
+        content: include_str!("../../data/top-packages.txt"),
+    },
+];
+
+/// One asset in a published manifest
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub name: String,
+    pub file: String,
+    pub version: String,
+    pub published: String,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub assets: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    fn entry(&self, name: &str) -> Option<&ManifestEntry> {
+        self.assets.iter().find(|entry| entry.name == name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AssetSource {
+    Bundled,
+    Updated,
+}
+
+impl fmt::Display for AssetSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssetSource::Bundled => f.pad("bundled"),
+            AssetSource::Updated => f.pad("updated"),
+        }
+    }
+}
+
+/// The copy of an asset consumers should use
+#[derive(Debug, Clone)]
+pub struct Asset {
+    pub name: String,
+    pub version: String,
+    pub published: String,
+    pub source: AssetSource,
+    pub content: Cow<'static, str>,
+}
+
+impl Asset {
+    fn bundled(asset: &BundledAsset) -> Self {
+        Self {
+            name: asset.name.to_string(),
+            version: asset.version.to_string(),
+            published: asset.published.to_string(),
+            source: AssetSource::Bundled,
+            content: Cow::Borrowed(asset.content),
+        }
+    }
+
+    /// Days since publication, if the date parses
+    pub fn age_days(&self, today: NaiveDate) -> Option<i64> {
+        let published = NaiveDate::parse_from_str(&self.published, "%Y-%m-%d").ok()?;
+        Some((today - published).num_days())
+    }
+}
+
+/// Bundled assets plus any verified update in the data directory
+#[derive(Debug, Clone)]
+pub struct Datasets {
+    data_dir: Option<PathBuf>,
+    key: PublicKey,
+}
+
+impl Datasets {
+    pub fn new(data_dir: Option<PathBuf>, key: PublicKey) -> Self {
+        Self { data_dir, key }
+    }
+
+    /// The configured data directory, trusting the pip-rs datasets key
+    pub fn from_config(config: &Config) -> Self {
+        let key = PublicKey::parse(DATASETS_KEY).expect("bundled datasets key is valid");
+        Self::new(config.data_dir(), key)
+    }
+
+    fn root(&self) -> Option<PathBuf> {
+        self.data_dir.as_ref().map(|dir| dir.join(DATASETS_DIR))
+    }
+
+    /// The copy of `name` to use: a verified update, else the bundled one
+    pub fn read(&self, name: &str) -> Result<Asset> {
+        let bundled = BUNDLED
+            .iter()
+            .find(|asset| asset.name == name)
+            .ok_or_else(|| anyhow!("unknown dataset '{}'", name))?;
+        match self.read_updated(name) {
+            Ok(Some(asset)) => Ok(asset),
+            Ok(None) => Ok(Asset::bundled(bundled)),
+            Err(e) => {
+                tracing::warn!("Ignoring updated dataset '{}': {}", name, e);
+                Ok(Asset::bundled(bundled))
+            }
+        }
+    }
+
+    /// Every known asset as it would be read
+    pub fn list(&self) -> Vec<Asset> {
+        BUNDLED.iter().filter_map(|asset| self.read(asset.name).ok()).collect()
+    }
+
+    fn read_updated(&self, name: &str) -> Result<Option<Asset>> {
+        let Some((dir, manifest)) = self.current()? else {
+            return Ok(None);
+        };
+        let Some(entry) = manifest.entry(name) else {
+            return Ok(None);
+        };
+        let bytes = fs::read(dir.join(&entry.file))?;
+        if sha256_hex(&bytes) != entry.sha256 {
+            return Err(anyhow!("{} does not match the signed manifest", entry.file));
+        }
+        Ok(Some(Asset {
+            name: entry.name.clone(),
+            version: entry.version.clone(),
+            published: entry.published.clone(),
+            source: AssetSource::Updated,
+            content: Cow::Owned(String::from_utf8(bytes)?),
+        }))
+    }
+
+    /// The installed generation and its verified manifest
+    fn current(&self) -> Result<Option<(PathBuf, Manifest)>> {
+        let Some(root) = self.root() else {
+            return Ok(None);
+        };
+        let Ok(generation) = fs::read_to_string(root.join(CURRENT_FILE)) else {
+            return Ok(None);
+        };
+        let dir = root.join(generation.trim());
+        let manifest = fs::read(dir.join(MANIFEST_FILE))?;
+        let signature = fs::read_to_string(dir.join(SIGNATURE_FILE))?;
+        Ok(Some((dir, self.verify_manifest(&manifest, &signature)?)))
+    }
+
+    /// Check a manifest's signature and parse it
+    pub fn verify_manifest(&self, manifest: &[u8], signature: &str) -> Result<Manifest> {
+        minisign::verify(&self.key, manifest, signature).map_err(|e| anyhow!("manifest rejected: {}", e))?;
+        Ok(serde_json::from_slice(manifest)?)
+    }
+
+    /// Verify a fetched manifest and the files it lists (by file name), then
+    /// switch to them in one rename. Returns the installed manifest.
+    pub fn install(&self, manifest: &[u8], signature: &str, files: &BTreeMap<String, Vec<u8>>) -> Result<Manifest> {
+        let root = self.root().ok_or_else(|| anyhow!("no data directory configured"))?;
+        let parsed = self.verify_manifest(manifest, signature)?;
+        for entry in &parsed.assets {
+            let bytes = files.get(&entry.file).ok_or_else(|| anyhow!("{} was not downloaded", entry.file))?;
+            if sha256_hex(bytes) != entry.sha256 {
+                return Err(anyhow!("{} does not match the signed manifest", entry.file));
+            }
+            if entry.file.contains(['/', '\\']) || entry.file.starts_with('.') {
+                return Err(anyhow!("invalid file name in manifest: {}", entry.file));
+            }
+        }
+
+        // Stage a complete generation, then flip CURRENT to it
+        let generation = sha256_hex(manifest)[..16].to_string();
+        fs::create_dir_all(&root)?;
+        let staging = tempfile::Builder::new().prefix(".staging-").tempdir_in(&root)?;
+        fs::write(staging.path().join(MANIFEST_FILE), manifest)?;
+        fs::write(staging.path().join(SIGNATURE_FILE), signature)?;
+        for entry in &parsed.assets {
+            fs::write(staging.path().join(&entry.file), &files[&entry.file])?;
+        }
+        let target = root.join(&generation);
+        if target.exists() {
+            fs::remove_dir_all(&target)?;
+        }
+        fs::rename(staging.keep(), &target)?;
+
+        let pointer = tempfile::NamedTempFile::new_in(&root)?;
+        fs::write(pointer.path(), &generation)?;
+        pointer.persist(root.join(CURRENT_FILE)).map_err(|e| e.error)?;
+
+        remove_old_generations(&root, &generation);
+        Ok(parsed)
+    }
+}
+
+/// Best-effort removal of generations other than `keep`
+fn remove_old_generations(root: &Path, keep: &str) {
+    let Ok(entries) = fs::read_dir(root) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        if entry.path().is_dir() && name != keep {
+            let _ = fs::remove_dir_all(entry.path());
+        }
+    }
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::installer::import_names::CuratedImportMap;
+    use tempfile::TempDir;
+
+    const SEED: [u8; 32] = [42; 32];
+
+    /// A signed manifest for the import-name fixture
+    fn publish(content: &str, version: &str) -> (PublicKey, Vec<u8>, String, BTreeMap<String, Vec<u8>>) {
+        let manifest = Manifest {
+            assets: vec![ManifestEntry {
+                name: "import-names".to_string(),
+                file: "import-names.txt".to_string(),
+                version: version.to_string(),
+                published: "2026-10-10".to_string(),
+                sha256: sha256_hex(content.as_bytes()),
+            }],
+        };
+        let manifest = serde_json::to_vec_pretty(&manifest).unwrap();
+        let (public, signature) = minisign::sign(&SEED, &manifest, "file:manifest.json");
+        let files = BTreeMap::from([("import-names.txt".to_string(), content.as_bytes().to_vec())]);
+        (PublicKey::parse(&public).unwrap(), manifest, signature, files)
+    }
+
+    fn fixture() -> String {
+        fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/datasets/import-names.txt"))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_override_precedence() {
+        let dir = TempDir::new().unwrap();
+        let (key, manifest, signature, files) = publish(&fixture(), "2026.11");
+        let datasets = Datasets::new(Some(dir.path().to_path_buf()), key.clone());
+
+        // Nothing installed: the bundled copy
+        let asset = datasets.read("import-names").unwrap();
+        assert_eq!((asset.source, asset.version.as_str()), (AssetSource::Bundled, BUNDLED[0].version));
+        assert!(datasets.read("no-such-dataset").is_err());
+
+        datasets.install(&manifest, &signature, &files).unwrap();
+        let asset = datasets.read("import-names").unwrap();
+        assert_eq!((asset.source, asset.version.as_str()), (AssetSource::Updated, "2026.11"));
+        assert_eq!(asset.age_days(NaiveDate::from_ymd_opt(2026, 10, 16).unwrap()), Some(6));
+
+        // No data directory: always bundled
+        assert_eq!(Datasets::new(None, key).read("import-names").unwrap().source, AssetSource::Bundled);
+
+        // An edited file no longer matches the manifest
+        let generation = fs::read_to_string(dir.path().join("datasets/CURRENT")).unwrap();
+        fs::write(dir.path().join("datasets").join(generation).join("import-names.txt"), "cv2 = evil\n").unwrap();
+        assert_eq!(datasets.read("import-names").unwrap().source, AssetSource::Bundled);
+    }
+
+    #[test]
+    fn test_tampered_manifest_is_rejected() {
+        let dir = TempDir::new().unwrap();
+        let (key, manifest, signature, files) = publish(&fixture(), "2026.11");
+        let datasets = Datasets::new(Some(dir.path().to_path_buf()), key);
+
+        let tampered = String::from_utf8(manifest.clone()).unwrap().replace("2026.11", "2099.1");
+        let error = datasets.install(tampered.as_bytes(), &signature, &files).unwrap_err();
+        assert!(error.to_string().contains("manifest rejected"), "{}", error);
+        assert!(!dir.path().join("datasets/CURRENT").exists());
+
+        // Tampering after install falls back to the bundled copy
+        datasets.install(&manifest, &signature, &files).unwrap();
+        let generation = fs::read_to_string(dir.path().join("datasets/CURRENT")).unwrap();
+        fs::write(dir.path().join("datasets").join(generation).join(MANIFEST_FILE), &tampered).unwrap();
+        assert_eq!(datasets.read("import-names").unwrap().source, AssetSource::Bundled);
+    }
+
+    #[test]
+    fn test_consumer_sees_update_without_restart() {
+        let dir = TempDir::new().unwrap();
+        let (key, manifest, signature, files) = publish(&fixture(), "2026.11");
+        let datasets = Datasets::new(Some(dir.path().to_path_buf()), key);
+
+        assert!(CuratedImportMap::from_datasets(&datasets).lookup("fixture_module").is_none());
+        datasets.install(&manifest, &signature, &files).unwrap();
+        assert_eq!(
+            CuratedImportMap::from_datasets(&datasets).lookup("fixture_module").unwrap(),
+            ["fixture-dist"]
+        );
+    }
+}
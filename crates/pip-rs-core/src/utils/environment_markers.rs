@@ -3,6 +3,7 @@
 /// This module evaluates PEP 508 environment markers with support for
 /// platform overrides (--python-version, --platform, etc.)
 
+use crate::models::{Environment, EnvironmentOverride};
 use std::collections::HashMap;
 
 /// Environment marker context
@@ -54,9 +55,73 @@ impl EnvironmentContext {
         ctx
     }
 
+    /// Context for the target of an [`EnvironmentOverride`]. The platform
+    /// may be a wheel platform tag (`manylinux2014_aarch64`), which stands
+    /// for its OS and machine; `cp`/`pp` name CPython and PyPy.
+    pub fn from_override(environment: &EnvironmentOverride) -> Self {
+        let mut architecture = environment.architecture.clone();
+        let platform = environment.platform.as_deref().map(|platform| match super::tags::marker_platform(platform) {
+            Some((sys_platform, machine)) => {
+                architecture.get_or_insert(machine);
+                sys_platform.to_string()
+            }
+            None => platform.to_string(),
+        });
+        let python_version = environment.python_version.as_deref().map(|version| {
+            super::tags::parse_python_version(version)
+                .map_or_else(|_| version.to_string(), |(major, minor)| format!("{}.{}", major, minor))
+        });
+        let implementation = environment.implementation.as_deref().map(|name| {
+            match name.to_ascii_lowercase().as_str() {
+                "cp" => "cpython".to_string(),
+                "pp" => "pypy".to_string(),
+                other => other.to_string(),
+            }
+        });
+
+        let mut ctx = Self::with_overrides(python_version, platform, implementation);
+        if let Some(architecture) = architecture {
+            ctx.architecture = architecture;
+        }
+        let (os_name, system) = match ctx.platform.as_str() {
+            "linux" => ("posix", "Linux"),
+            "darwin" => ("posix", "Darwin"),
+            "win32" => ("nt", "Windows"),
+            _ => return ctx,
+        };
+        ctx.os_name = os_name.to_string();
+        ctx.system = system.to_string();
+        ctx
+    }
+
+    /// The resolver's marker environment for this context
+    pub fn marker_environment(&self) -> Environment {
+        let mut env = Environment::current();
+        // An overridden version has no patch level to go by
+        if env.python_version != self.python_version {
+            env.python_version = self.python_version.clone();
+            env.python_full_version = format!("{}.0", self.python_version);
+            env.implementation_version = env.python_full_version.clone();
+        }
+        env.sys_platform = self.platform.clone();
+        env.os_name = self.os_name.clone();
+        env.platform_system = self.system.clone();
+        env.platform_machine = self.architecture.clone();
+        if env.implementation_name != self.implementation {
+            env.implementation_version = env.python_full_version.clone();
+        }
+        env.implementation_name = self.implementation.clone();
+        env.platform_python_implementation = match self.implementation.as_str() {
+            "cpython" => "CPython".to_string(),
+            "pypy" => "PyPy".to_string(),
+            other => other.to_string(),
+        };
+        env
+    }
+
     /// Get Python version
     fn get_python_version() -> String {
-        format!("{}.{}", 3, 11) // Default to 3.11
+        super::python_env::detected().version()
     }
 
     /// Get platform
@@ -74,7 +139,7 @@ impl EnvironmentContext {
 
     /// Get implementation
     fn get_implementation() -> String {
-        "cpython".to_string()
+        super::python_env::detected().implementation_name.clone()
     }
 
     /// Get architecture
@@ -260,6 +325,28 @@ mod tests {
         assert_eq!(ctx.platform, "linux");
     }
 
+    #[test]
+    fn test_environment_context_from_override() {
+        let ctx = EnvironmentContext::from_override(&EnvironmentOverride {
+            python_version: Some("310".to_string()),
+            platform: Some("manylinux2014_aarch64".to_string()),
+            implementation: Some("cp".to_string()),
+            ..EnvironmentOverride::new()
+        });
+        assert_eq!((ctx.python_version.as_str(), ctx.platform.as_str()), ("3.10", "linux"));
+        assert_eq!((ctx.architecture.as_str(), ctx.system.as_str()), ("aarch64", "Linux"));
+
+        let env = EnvironmentContext::from_override(&EnvironmentOverride {
+            platform: Some("win_amd64".to_string()),
+            ..EnvironmentOverride::new()
+        })
+        .marker_environment();
+        assert_eq!(env.value("sys_platform"), Some("win32"));
+        assert_eq!(env.value("os_name"), Some("nt"));
+        assert_eq!(env.value("platform_machine"), Some("AMD64"));
+        assert_eq!(env.value("platform_python_implementation"), Some("CPython"));
+    }
+
     #[test]
     fn test_marker_evaluator_equal() {
         let ctx = EnvironmentContext::with_overrides(
@@ -2,7 +2,6 @@
 use anyhow::Result;
 use std::path::Path;
 use std::fs::File;
-use std::io::Read;
 
 /// Verify file hash using the specified algorithm
 pub async fn verify_hash(file_path: &Path, expected_hash: &str, algorithm: &str) -> Result<bool> {
@@ -12,28 +11,81 @@ pub async fn verify_hash(file_path: &Path, expected_hash: &str, algorithm: &str)
 
 /// Compute hash of a file
 pub async fn compute_hash(file_path: &Path, algorithm: &str) -> Result<String> {
+    digest_file(file_path, algorithm)
+}
+
+/// Hex digest of a file, read in chunks rather than all at once
+pub fn digest_file(file_path: &Path, algorithm: &str) -> Result<String> {
     let mut file = File::open(file_path)?;
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer)?;
-    
     match algorithm.to_lowercase().as_str() {
         "sha256" => {
             use sha2::{Sha256, Digest};
             let mut hasher = Sha256::new();
-            hasher.update(&buffer);
+            std::io::copy(&mut file, &mut hasher)?;
+            Ok(format!("{:x}", hasher.finalize()))
+        }
+        "sha1" => {
+            use sha1::{Sha1, Digest};
+            let mut hasher = Sha1::new();
+            std::io::copy(&mut file, &mut hasher)?;
+            Ok(format!("{:x}", hasher.finalize()))
+        }
+        "md5" => {
+            let mut context = md5::Context::new();
+            std::io::copy(&mut file, &mut context)?;
+            Ok(format!("{:x}", context.compute()))
+        }
+        _ => Err(anyhow::anyhow!("Unsupported hash algorithm: {}", algorithm)),
+    }
+}
+
+/// Check downloaded bytes against `<algorithm>:<digest>` values given with
+/// `--hash`; matching any one of them passes. A mismatch returns the
+/// actual `sha256:` digest for the error message.
+pub fn check_bytes(data: &[u8], expected: &[String]) -> std::result::Result<(), String> {
+    let matches = expected.iter().any(|hash| {
+        hash.split_once(':')
+            .is_some_and(|(algorithm, value)| digest(data, algorithm).is_ok_and(|d| d.eq_ignore_ascii_case(value)))
+    });
+    if matches {
+        return Ok(());
+    }
+    Err(format!("sha256:{}", digest(data, "sha256").unwrap_or_default()))
+}
+
+/// Like [`check_bytes`], for a downloaded file; the outer error is a
+/// failure to read it
+pub fn check_file(file_path: &Path, expected: &[String]) -> Result<std::result::Result<(), String>> {
+    for hash in expected {
+        if let Some((algorithm, value)) = hash.split_once(':')
+            && digest_file(file_path, algorithm).is_ok_and(|d| d.eq_ignore_ascii_case(value))
+        {
+            return Ok(Ok(()));
+        }
+    }
+    Ok(Err(format!("sha256:{}", digest_file(file_path, "sha256")?)))
+}
+
+/// Hex digest of `buffer`
+pub fn digest(buffer: &[u8], algorithm: &str) -> Result<String> {
+    match algorithm.to_lowercase().as_str() {
+        "sha256" => {
+            use sha2::{Sha256, Digest};
+            let mut hasher = Sha256::new();
+            hasher.update(buffer);
             let result = hasher.finalize();
             Ok(format!("{:x}", result))
         }
         "sha1" => {
             use sha1::{Sha1, Digest};
             let mut hasher = Sha1::new();
-            hasher.update(&buffer);
+            hasher.update(buffer);
             let result = hasher.finalize();
             Ok(format!("{:x}", result))
         }
         "md5" => {
             use md5;
-            let digest = md5::compute(&buffer);
+            let digest = md5::compute(buffer);
             Ok(format!("{:x}", digest))
         }
         _ => Err(anyhow::anyhow!("Unsupported hash algorithm: {}", algorithm)),
@@ -67,6 +119,22 @@ mod tests {
         let result = verify_hash(temp_file.path(), "invalid_hash", "sha256").await.unwrap();
         assert!(!result);
     }
+
+    #[test]
+    fn test_check_bytes_against_any_listed_hash() {
+        let sha256 = digest(b"wheel bytes", "sha256").unwrap();
+        let good = vec!["sha256:0000".to_string(), format!("sha256:{}", sha256.to_uppercase())];
+        assert_eq!(check_bytes(b"wheel bytes", &good), Ok(()));
+
+        let bad = vec!["sha256:0000".to_string(), "md5:1234".to_string()];
+        assert_eq!(check_bytes(b"wheel bytes", &bad), Err(format!("sha256:{}", sha256)));
+
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(b"wheel bytes").unwrap();
+        assert_eq!(check_file(file.path(), &good).unwrap(), Ok(()));
+        assert_eq!(check_file(file.path(), &bad).unwrap(), Err(format!("sha256:{}", sha256)));
+        assert_eq!(digest_file(file.path(), "md5").unwrap(), digest(b"wheel bytes", "md5").unwrap());
+    }
 }
 
 #[allow(dead_code)]
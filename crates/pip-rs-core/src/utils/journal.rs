@@ -0,0 +1,103 @@
+/// Journal of recent warnings and errors
+///
+/// At the end of every command the collected warnings and the final error
+/// (if any) are appended to `journal.jsonl` in the cache root, keeping the
+/// newest entries only. `pip debug --dump-state` includes the tail of it in
+/// bug-report bundles.
+use super::warnings::UserWarning;
+use crate::cache::layout;
+use crate::errors::PipError;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Entries kept in the journal file
+const MAX_ENTRIES: usize = 500;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub timestamp: String,
+    /// "warning" or "error"
+    pub kind: String,
+    pub code: String,
+    pub message: String,
+}
+
+/// Append entries to the journal in `cache_root`, dropping the oldest
+pub fn append(cache_root: &Path, entries: &[JournalEntry]) -> Result<()> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+    let path = cache_root.join(layout::JOURNAL_FILE);
+    let mut lines: Vec<String> = fs::read_to_string(&path)
+        .map(|content| content.lines().map(String::from).collect())
+        .unwrap_or_default();
+    for entry in entries {
+        lines.push(serde_json::to_string(entry)?);
+    }
+    let start = lines.len().saturating_sub(MAX_ENTRIES);
+    fs::write(&path, lines[start..].join("\n") + "\n")?;
+    Ok(())
+}
+
+/// The newest `limit` entries, oldest first; unreadable lines are skipped
+pub fn recent(cache_root: &Path, limit: usize) -> Vec<JournalEntry> {
+    let content = fs::read_to_string(cache_root.join(layout::JOURNAL_FILE)).unwrap_or_default();
+    let entries: Vec<JournalEntry> = content.lines().filter_map(|line| serde_json::from_str(line).ok()).collect();
+    let start = entries.len().saturating_sub(limit);
+    entries[start..].to_vec()
+}
+
+/// Record the outcome of a command run in the configured cache's journal
+pub fn record_run(warnings: &[UserWarning], error: Option<&PipError>) {
+    let Some(root) = layout::cache_root() else {
+        return;
+    };
+    let timestamp = chrono::Local::now().to_rfc3339();
+    let mut entries: Vec<JournalEntry> = warnings
+        .iter()
+        .map(|warning| JournalEntry {
+            timestamp: timestamp.clone(),
+            kind: "warning".to_string(),
+            code: warning.code.as_str().to_string(),
+            message: warning.message.clone(),
+        })
+        .collect();
+    if let Some(error) = error {
+        entries.push(JournalEntry {
+            timestamp,
+            kind: "error".to_string(),
+            code: error.code().to_string(),
+            message: error.to_string(),
+        });
+    }
+    if let Err(e) = append(&root, &entries) {
+        tracing::debug!("Failed to update the journal: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_journal_keeps_newest_entries() {
+        let root = TempDir::new().unwrap();
+        let entry = |n: usize| JournalEntry {
+            timestamp: "2024-01-01T00:00:00+00:00".to_string(),
+            kind: "warning".to_string(),
+            code: "unused-override".to_string(),
+            message: format!("warning {}", n),
+        };
+        let first: Vec<_> = (0..MAX_ENTRIES).map(entry).collect();
+        append(root.path(), &first).unwrap();
+        append(root.path(), &[entry(MAX_ENTRIES), entry(MAX_ENTRIES + 1)]).unwrap();
+
+        let tail = recent(root.path(), 3);
+        let messages: Vec<&str> = tail.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages, vec!["warning 499", "warning 500", "warning 501"]);
+        assert_eq!(recent(root.path(), usize::MAX).len(), MAX_ENTRIES);
+    }
+}
@@ -0,0 +1,161 @@
+/// Minisign signature verification
+///
+/// Checks detached `.minisig` signatures against a minisign public key.
+/// Both signature algorithms are accepted: legacy `Ed` signs the file
+/// itself, the default `ED` signs its BLAKE2b-512 digest. The global
+/// signature over the trusted comment is always checked, so the comment
+/// can't be swapped either.
+use anyhow::{Result, anyhow};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use blake2::{Blake2b512, Digest};
+use ring::signature::{ED25519, UnparsedPublicKey};
+
+const TRUSTED_COMMENT_PREFIX: &str = "trusted comment: ";
+
+/// A minisign Ed25519 public key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicKey {
+    key_id: [u8; 8],
+    key: [u8; 32],
+}
+
+impl PublicKey {
+    /// Parse a `.pub` file (comment line + key) or the bare base64 key
+    pub fn parse(text: &str) -> Result<Self> {
+        let line = text
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !line.starts_with("untrusted comment:"))
+            .ok_or_else(|| anyhow!("empty minisign public key"))?;
+        let bytes = BASE64.decode(line).map_err(|e| anyhow!("invalid minisign public key: {}", e))?;
+        if bytes.len() != 42 || &bytes[..2] != b"Ed" {
+            return Err(anyhow!("not a minisign Ed25519 public key"));
+        }
+        Ok(Self {
+            key_id: bytes[2..10].try_into()?,
+            key: bytes[10..].try_into()?,
+        })
+    }
+
+    /// Key ID as minisign prints it
+    pub fn key_id(&self) -> String {
+        self.key_id.iter().rev().map(|b| format!("{:02X}", b)).collect()
+    }
+}
+
+/// A parsed `.minisig` file
+#[derive(Debug, Clone)]
+struct Signature {
+    prehashed: bool,
+    key_id: [u8; 8],
+    signature: [u8; 64],
+    trusted_comment: String,
+    global_signature: [u8; 64],
+}
+
+impl Signature {
+    fn parse(text: &str) -> Result<Self> {
+        let mut lines = text.lines().map(str::trim_end).filter(|line| !line.is_empty());
+        let mut line = lines.next().ok_or_else(|| anyhow!("empty signature"))?;
+        if line.starts_with("untrusted comment:") {
+            line = lines.next().ok_or_else(|| anyhow!("signature line missing"))?;
+        }
+        let bytes = BASE64.decode(line).map_err(|e| anyhow!("invalid signature: {}", e))?;
+        if bytes.len() != 74 {
+            return Err(anyhow!("invalid signature length"));
+        }
+        let prehashed = match &bytes[..2] {
+            b"Ed" => false,
+            b"ED" => true,
+            _ => return Err(anyhow!("unsupported signature algorithm")),
+        };
+        let trusted_comment = lines
+            .next()
+            .and_then(|line| line.strip_prefix(TRUSTED_COMMENT_PREFIX))
+            .ok_or_else(|| anyhow!("trusted comment missing"))?
+            .to_string();
+        let global = lines.next().ok_or_else(|| anyhow!("global signature missing"))?;
+        let global = BASE64.decode(global).map_err(|e| anyhow!("invalid global signature: {}", e))?;
+        Ok(Self {
+            prehashed,
+            key_id: bytes[2..10].try_into()?,
+            signature: bytes[10..].try_into()?,
+            trusted_comment,
+            global_signature: global
+                .try_into()
+                .map_err(|_| anyhow!("invalid global signature length"))?,
+        })
+    }
+}
+
+/// Verify `data` against a detached minisign signature; returns the
+/// trusted comment on success
+pub fn verify(key: &PublicKey, data: &[u8], signature: &str) -> Result<String> {
+    let signature = Signature::parse(signature)?;
+    if signature.key_id != key.key_id {
+        return Err(anyhow!("signature was made with a different key"));
+    }
+    let verifier = UnparsedPublicKey::new(&ED25519, key.key);
+    let verified = if signature.prehashed {
+        verifier.verify(&Blake2b512::digest(data), &signature.signature)
+    } else {
+        verifier.verify(data, &signature.signature)
+    };
+    verified.map_err(|_| anyhow!("signature verification failed"))?;
+
+    let mut global = signature.signature.to_vec();
+    global.extend_from_slice(signature.trusted_comment.as_bytes());
+    verifier
+        .verify(&global, &signature.global_signature)
+        .map_err(|_| anyhow!("trusted comment signature verification failed"))?;
+    Ok(signature.trusted_comment)
+}
+
+/// Sign like `minisign -S` with a fixed seed; returns (public key, signature)
+#[cfg(test)]
+pub(crate) fn sign(seed: &[u8; 32], data: &[u8], trusted_comment: &str) -> (String, String) {
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+
+    let pair = Ed25519KeyPair::from_seed_unchecked(seed).unwrap();
+    let key_id = [seed[0], 1, 2, 3, 4, 5, 6, 7];
+    let mut public = b"Ed".to_vec();
+    public.extend_from_slice(&key_id);
+    public.extend_from_slice(pair.public_key().as_ref());
+
+    let signature = pair.sign(&Blake2b512::digest(data));
+    let mut line = b"ED".to_vec();
+    line.extend_from_slice(&key_id);
+    line.extend_from_slice(signature.as_ref());
+    let mut global = signature.as_ref().to_vec();
+    global.extend_from_slice(trusted_comment.as_bytes());
+
+    (
+        format!("untrusted comment: minisign public key\n{}\n", BASE64.encode(public)),
+        format!(
+            "untrusted comment: signature\n{}\n{}{}\n{}\n",
+            BASE64.encode(line),
+            TRUSTED_COMMENT_PREFIX,
+            trusted_comment,
+            BASE64.encode(pair.sign(&global))
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_and_reject_tampering() {
+        let (public, signature) = sign(&[7; 32], b"payload", "timestamp:1 file:payload");
+        let key = PublicKey::parse(&public).unwrap();
+        assert_eq!(verify(&key, b"payload", &signature).unwrap(), "timestamp:1 file:payload");
+
+        assert!(verify(&key, b"payl0ad", &signature).is_err());
+        let swapped_comment = signature.replace("file:payload", "file:other");
+        assert!(verify(&key, b"payload", &swapped_comment).is_err());
+        let (other, _) = sign(&[8; 32], b"payload", "x");
+        assert!(verify(&PublicKey::parse(&other).unwrap(), b"payload", &signature).is_err());
+    }
+}
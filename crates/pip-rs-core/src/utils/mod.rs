@@ -9,8 +9,22 @@ pub mod benchmark;
 pub mod environment_markers;
 pub mod archive_detector;
 pub mod requirements_parser;
+pub mod text_encoding;
 pub mod find_links_tracker;
 pub mod svn_handler;
 pub mod pep691_handler;
+pub mod tags;
 pub mod progress;
+pub mod warnings;
+pub mod cancel;
+pub mod resources;
+pub mod journal;
+pub mod redact;
+pub mod state_bundle;
+pub mod pager;
+pub mod minisign;
+pub mod datasets;
+pub mod build_info;
+pub mod python_env;
+pub mod names;
 
@@ -0,0 +1,122 @@
+use crate::cache::package_cache::PackageCache;
+use crate::config::config::Config;
+use crate::utils::datasets::Datasets;
+use std::collections::HashSet;
+
+/// PEP 503 normalized project name: lowercase, with every run of `-`, `_`
+/// and `.` collapsed to one `-`
+///
+/// Installers disagree on how a name is spelled on disk: `Flask_SQLAlchemy`,
+/// `flask_sqlalchemy` and `Flask-SQLAlchemy` are the same project, so every
+/// lookup of an installed distribution compares normalized names.
+pub fn canonicalize_name(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    for c in name.chars() {
+        if matches!(c, '-' | '_' | '.') {
+            if !out.ends_with('-') {
+                out.push('-');
+            }
+        } else {
+            out.push(c.to_ascii_lowercase());
+        }
+    }
+    out
+}
+
+/// Name and version of a `<name>-<version>.dist-info` directory name. The
+/// version starts at the first `-` followed by a digit, so legacy names that
+/// kept their dashes (`Flask-SQLAlchemy-3.1.1`) still split correctly; a
+/// directory without a version yields an empty one.
+pub fn split_dist_info(dir_name: &str) -> Option<(&str, &str)> {
+    let stem = dir_name.strip_suffix(".dist-info")?;
+    let version_start = stem
+        .char_indices()
+        .find(|&(i, c)| c == '-' && stem[i + 1..].starts_with(|next: char| next.is_ascii_digit()))
+        .map(|(i, _)| i);
+    let (name, version) = match version_start {
+        Some(i) => (&stem[..i], &stem[i + 1..]),
+        None => (stem, ""),
+    };
+    (!name.is_empty()).then_some((name, version))
+}
+
+/// Levenshtein distance, in characters
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Up to three of `candidates` within a few edits of `name`, for "did you
+/// mean" hints: closest first, then in the order given. Names compare
+/// normalized, and one edit is allowed per three characters.
+pub fn close_matches<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let name = canonicalize_name(name);
+    let allowed = (name.chars().count() / 3).max(1);
+    let mut seen = HashSet::new();
+    let mut matches: Vec<(usize, String)> = candidates
+        .into_iter()
+        .map(canonicalize_name)
+        .filter(|candidate| *candidate != name && seen.insert(candidate.clone()))
+        .map(|candidate| (edit_distance(&name, &candidate), candidate))
+        .filter(|(distance, _)| *distance <= allowed)
+        .collect();
+    matches.sort_by_key(|(distance, _)| *distance);
+    matches.into_iter().take(3).map(|(_, candidate)| candidate).collect()
+}
+
+/// Project names worth suggesting: `installed`, then the bundled list of
+/// popular PyPI projects (most downloaded first), then every project with
+/// cached metadata
+pub fn known_project_names(installed: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut names: Vec<String> = installed.into_iter().collect();
+    if let Ok(asset) = Datasets::from_config(Config::global()).read("top-packages") {
+        let popular = asset.content.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#'));
+        names.extend(popular.map(String::from));
+    }
+    if let Ok(cache) = PackageCache::new() {
+        names.extend(cache.names());
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonicalize_and_split_dist_info() {
+        assert_eq!(canonicalize_name("Flask_SQLAlchemy"), "flask-sqlalchemy");
+        assert_eq!(canonicalize_name("zope..Interface"), "zope-interface");
+        assert_eq!(split_dist_info("Flask_SQLAlchemy-3.1.1.dist-info"), Some(("Flask_SQLAlchemy", "3.1.1")));
+        assert_eq!(split_dist_info("Flask-SQLAlchemy-3.1.1.dist-info"), Some(("Flask-SQLAlchemy", "3.1.1")));
+        assert_eq!(split_dist_info("requests.dist-info"), Some(("requests", "")));
+        assert_eq!(split_dist_info("requests-2.31.0.egg-info"), None);
+    }
+    #[test]
+    fn test_close_matches() {
+        assert_eq!(edit_distance("reqests", "requests"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+
+        let known = ["requests", "requests-oauthlib", "numpy", "Flask_Login", "flask", "requests"];
+        assert_eq!(close_matches("reqests", known), ["requests"]);
+        assert_eq!(close_matches("flask-logn", known), ["flask-login"]);
+        assert_eq!(close_matches("numpi", known), ["numpy"]);
+        assert!(close_matches("tensorflow", known).is_empty());
+        // The name itself isn't a suggestion
+        assert!(close_matches("NumPy", known).is_empty());
+        assert_eq!(close_matches("lask", ["flask", "mask", "task", "ask", "flasks"]), ["flask", "mask", "task"]);
+
+        let bundled = known_project_names(vec!["my-tool".to_string()]);
+        assert_eq!(bundled[0], "my-tool");
+        assert_eq!(close_matches("beautifulsop4", bundled.iter().map(String::as_str)), ["beautifulsoup4"]);
+    }
+}
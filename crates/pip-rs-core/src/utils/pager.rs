@@ -0,0 +1,235 @@
+/// Paging and pipe-safe writing for long human-readable output
+///
+/// When stdout is a terminal and the output is taller than it, lines are
+/// handed to a pager (`pager` in pip.conf, then `$PAGER`, then `less -FRX`)
+/// unless paging is off (`--no-pager`, `pager = off`). Piped output is
+/// written line by line, and a reader that goes away (`pip list | head`)
+/// ends the process quietly with the conventional SIGPIPE exit code instead
+/// of the panic `println!` raises.
+use crate::config::config::Config;
+use std::io::{self, IsTerminal, Write};
+use std::process::{Command, Stdio};
+
+/// Exit code of a process killed by SIGPIPE (128 + 13)
+pub const EXIT_BROKEN_PIPE: i32 = 141;
+
+/// Pager used when neither the config nor `$PAGER` names one
+pub const DEFAULT_PAGER: &str = "less -FRX";
+
+/// What we know about stdout
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Terminal {
+    pub is_tty: bool,
+    /// Rows, when stdout is a terminal whose size is known
+    pub height: Option<usize>,
+}
+
+impl Terminal {
+    pub fn stdout() -> Self {
+        let is_tty = io::stdout().is_terminal();
+        Self {
+            is_tty,
+            height: if is_tty { terminal_height() } else { None },
+        }
+    }
+}
+
+/// Rows of the terminal on stdout, falling back to `$LINES`
+fn terminal_height() -> Option<usize> {
+    #[cfg(target_os = "linux")]
+    {
+        let mut size = libc::winsize {
+            ws_row: 0,
+            ws_col: 0,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        };
+        // SAFETY: TIOCGWINSZ only writes a winsize into the struct we pass
+        let result = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+        if result == 0 && size.ws_row > 0 {
+            return Some(size.ws_row as usize);
+        }
+    }
+    std::env::var("LINES").ok()?.trim().parse().ok().filter(|&rows| rows > 0)
+}
+
+/// The pager command for `lines` of output, or `None` to write directly.
+/// `setting` is the configured pager (`off` disables paging) and `env_pager`
+/// is `$PAGER`; an empty pager or `cat` also means no paging.
+pub fn pager_command(terminal: Terminal, lines: usize, setting: Option<&str>, env_pager: Option<&str>) -> Option<String> {
+    let command = match setting.map(str::trim) {
+        Some(off) if is_off(off) => return None,
+        Some(command) => command,
+        None => env_pager.map(str::trim).unwrap_or(DEFAULT_PAGER),
+    };
+    if !terminal.is_tty || command.is_empty() || command == "cat" {
+        return None;
+    }
+    // Without a known height there's nothing to exceed
+    let height = terminal.height?;
+    (lines >= height).then(|| command.to_string())
+}
+
+fn is_off(value: &str) -> bool {
+    matches!(value.to_lowercase().as_str(), "off" | "false" | "no" | "0")
+}
+
+/// Write lines, stopping at the first error (typically a closed pipe)
+pub fn write_lines<W: Write, S: AsRef<str>>(out: &mut W, lines: &[S]) -> io::Result<()> {
+    for line in lines {
+        writeln!(out, "{}", line.as_ref())?;
+    }
+    out.flush()
+}
+
+/// Exit code for a failed write to stdout: quiet 141 when the reader went
+/// away, 1 (after reporting it) for anything else
+pub fn write_error_exit_code(error: &io::Error) -> i32 {
+    if error.kind() == io::ErrorKind::BrokenPipe {
+        EXIT_BROKEN_PIPE
+    } else {
+        eprintln!("error: failed to write output: {}", error);
+        1
+    }
+}
+
+/// Human-readable output, paged when it doesn't fit the terminal
+pub struct PagedOutput {
+    terminal: Terminal,
+    pager: Option<String>,
+    /// Held back while it might still need paging
+    buffer: Vec<String>,
+}
+
+impl PagedOutput {
+    /// Output that may be paged, following the config's `pager` setting
+    pub fn new() -> Self {
+        let terminal = Terminal::stdout();
+        let config = Config::global();
+        // Buffer only if paging is possible; otherwise stream line by line
+        let pager = pager_command(terminal, usize::MAX, config.pager(), std::env::var("PAGER").ok().as_deref());
+        Self {
+            terminal,
+            pager,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Output that is never paged (machine-readable formats)
+    pub fn plain() -> Self {
+        Self {
+            terminal: Terminal::stdout(),
+            pager: None,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Emit one line
+    pub fn line(&mut self, line: impl Into<String>) {
+        let line = line.into();
+        if self.pager.is_some() {
+            self.buffer.push(line);
+        } else if let Err(e) = write_lines(&mut io::stdout().lock(), &[line]) {
+            std::process::exit(write_error_exit_code(&e));
+        }
+    }
+
+    /// Flush held-back output, through the pager if it's too tall
+    pub fn finish(self) {
+        let Some(pager) = self.pager else {
+            return;
+        };
+        let fits = pager_command(self.terminal, self.buffer.len(), Some(&pager), None).is_none();
+        if (fits || run_pager(&pager, &self.buffer).is_err())
+            && let Err(e) = write_lines(&mut io::stdout().lock(), &self.buffer)
+        {
+            std::process::exit(write_error_exit_code(&e));
+        }
+    }
+}
+
+impl Default for PagedOutput {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Feed lines to the pager and wait for the user to quit it
+fn run_pager(command: &str, lines: &[String]) -> io::Result<()> {
+    let mut shell = if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C");
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c");
+        cmd
+    };
+    if std::env::var_os("LESS").is_none() {
+        // Same defaults as git: quit if one screen, keep colors, no clearing
+        shell.env("LESS", "FRX");
+    }
+    let mut child = shell.arg(command).stdin(Stdio::piped()).spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // Quitting the pager early closes its stdin; that's not an error
+        match write_lines(&mut stdin, lines) {
+            Err(e) if e.kind() != io::ErrorKind::BrokenPipe => tracing::debug!("Pager input failed: {}", e),
+            _ => {}
+        }
+    }
+    child.wait()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pager_selection() {
+        let tty = Terminal {
+            is_tty: true,
+            height: Some(24),
+        };
+        assert_eq!(pager_command(tty, 100, None, None).as_deref(), Some(DEFAULT_PAGER));
+        assert_eq!(pager_command(tty, 100, None, Some("most")).as_deref(), Some("most"));
+        assert_eq!(pager_command(tty, 100, Some("bat -p"), Some("most")).as_deref(), Some("bat -p"));
+        // Fits on screen
+        assert_eq!(pager_command(tty, 10, None, None), None);
+        // Paging disabled
+        assert_eq!(pager_command(tty, 100, Some("off"), Some("most")), None);
+        assert_eq!(pager_command(tty, 100, None, Some("cat")), None);
+        assert_eq!(pager_command(tty, 100, None, Some("")), None);
+        // Piped or unknown size
+        let piped = Terminal {
+            is_tty: false,
+            height: None,
+        };
+        assert_eq!(pager_command(piped, 100, None, None), None);
+        let unsized_tty = Terminal {
+            is_tty: true,
+            height: None,
+        };
+        assert_eq!(pager_command(unsized_tty, 100, None, None), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_closed_pipe_stops_quietly() {
+        use std::os::fd::FromRawFd;
+
+        let mut fds = [0; 2];
+        // SAFETY: pipe() fills both descriptors; we own them from here on
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        // SAFETY: fds[0] is the read end we just created; closing it leaves
+        // the write end with no reader
+        unsafe { libc::close(fds[0]) };
+        // SAFETY: fds[1] is a valid, owned write end
+        let mut writer = unsafe { std::fs::File::from_raw_fd(fds[1]) };
+
+        let lines: Vec<String> = (0..10_000).map(|i| format!("package-{} 1.0", i)).collect();
+        let error = write_lines(&mut writer, &lines).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::BrokenPipe);
+        assert_eq!(write_error_exit_code(&error), EXIT_BROKEN_PIPE);
+    }
+}
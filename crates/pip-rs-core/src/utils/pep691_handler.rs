@@ -187,7 +187,7 @@ impl Pep691Handler {
     }
 
     /// Check if file is a package file
-    fn is_package_file(filename: &str) -> bool {
+    pub(crate) fn is_package_file(filename: &str) -> bool {
         filename.ends_with(".whl")
             || filename.ends_with(".tar.gz")
             || filename.ends_with(".zip")
@@ -1,7 +1,20 @@
 /// Progress bar utilities for better UX
+///
+/// Bars and spinners are drawn on stderr so stdout stays clean for
+/// machine-readable output. Those of one command share a [`MultiProgress`]
+/// (see [`add`]), so a download bar can sit under the install spinner
+/// without the two overwriting each other.
 use indicatif::{ProgressBar, ProgressStyle, MultiProgress};
+use once_cell::sync::Lazy;
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 
+static SHARED: Lazy<MultiProgress> = Lazy::new(MultiProgress::new);
+
+/// Set once stdout carries machine-readable output
+static STDOUT_RESERVED: AtomicBool = AtomicBool::new(false);
+
 /// Create a spinner for indeterminate progress
 pub fn spinner(message: &str) -> ProgressBar {
     let pb = ProgressBar::new_spinner();
@@ -41,6 +54,54 @@ pub fn download_bar(total_bytes: u64) -> ProgressBar {
     pb
 }
 
+/// Draw `pb` together with the process's other live bars
+pub fn add(pb: ProgressBar) -> ProgressBar {
+    SHARED.add(pb)
+}
+
+/// Print `line` to stderr above the live bars, so neither garbles the other
+pub fn println(line: &str) {
+    if SHARED.println(line).is_err() {
+        eprintln!("{}", line);
+    }
+}
+
+/// Keep stdout for machine-readable output (`--report -`): from now on
+/// [`status`] lines go to stderr
+pub fn reserve_stdout() {
+    STDOUT_RESERVED.store(true, Ordering::Relaxed);
+}
+
+/// Print a status line ("Collecting packages...") on stdout, or on stderr
+/// once stdout is reserved
+pub fn status(line: impl std::fmt::Display) {
+    if !STDOUT_RESERVED.load(Ordering::Relaxed) {
+        println!("{}", line);
+    } else if enabled() {
+        println(&line.to_string());
+    } else {
+        eprintln!("{}", line);
+    }
+}
+
+/// A download bar for one artifact, labelled with its file name; `None`
+/// when live progress isn't shown
+pub fn artifact_bar(filename: &str) -> Option<ProgressBar> {
+    if !enabled() {
+        return None;
+    }
+    let pb = add(download_bar(0));
+    pb.set_message(filename.to_string());
+    Some(pb)
+}
+
+/// Move `pb` to `written` bytes of `total`; a transfer of unknown length
+/// grows its bar as it goes
+pub fn track_download(pb: &ProgressBar, written: u64, total: Option<u64>) {
+    pb.set_length(total.unwrap_or(written).max(written));
+    pb.set_position(written);
+}
+
 /// Create a multi-progress container for parallel operations
 pub fn multi_progress() -> MultiProgress {
     MultiProgress::new()
@@ -66,6 +127,12 @@ pub fn finish_error(pb: &ProgressBar, message: &str) {
     pb.finish_with_message(message.to_string());
 }
 
+/// Whether live bars, spinners and the install summary are shown: not
+/// under `--quiet`/`PIP_QUIET`, and only when stderr is a terminal
+pub fn enabled() -> bool {
+    std::env::var_os("PIP_QUIET").is_none() && std::io::stderr().is_terminal()
+}
+
 /// Check if progress bars should be hidden (quiet mode or non-TTY)
 pub fn is_quiet() -> bool {
     std::env::var("PIP_QUIET").is_ok() || !atty_check()
@@ -93,4 +160,14 @@ mod tests {
         pb.inc(50);
         pb.finish();
     }
+
+    #[test]
+    fn test_track_download_with_and_without_length() {
+        let pb = ProgressBar::hidden();
+        track_download(&pb, 512, Some(2048));
+        assert_eq!((pb.position(), pb.length()), (512, Some(2048)));
+        // No Content-Length: the bar is always full
+        track_download(&pb, 4096, None);
+        assert_eq!((pb.position(), pb.length()), (4096, Some(4096)));
+    }
 }
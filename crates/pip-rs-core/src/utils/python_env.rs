@@ -0,0 +1,350 @@
+/// The Python of the environment pip-rs installs into
+///
+/// Marker evaluation, wheel tags and install reports all need to know which
+/// interpreter they target, and they must agree. Every one of them reads
+/// [`detected`], which works it out once per process:
+///
+/// 0. with `--python` given, that interpreter describes itself (see
+///    [`select`]) and `VIRTUAL_ENV` is ignored;
+/// 1. with `VIRTUAL_ENV` set, the `version` (or virtualenv's `version_info`)
+///    and `implementation` recorded in its `pyvenv.cfg`, else whatever the
+///    environment's own interpreter reports;
+/// 2. otherwise `python3` (`python` on Windows) on PATH is asked;
+/// 3. failing both, [`FALLBACK_VERSION`] of CPython, with a debug log.
+///
+/// `platform_release` and `platform_version` come from the kernel where that
+/// is a file read (Linux), and are empty elsewhere, as `platform.release()`
+/// is when Python can't tell.
+use crate::errors::PipError;
+use once_cell::sync::{Lazy, OnceCell};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Version assumed when no interpreter can be found
+pub const FALLBACK_VERSION: &str = "3.11.0";
+
+/// Prints the full version, implementation name and implementation version
+const PROBE: &str = "import sys; v = sys.implementation.version; \
+    print('.'.join(map(str, sys.version_info[:3]))); print(sys.implementation.name); \
+    print('%d.%d.%d' % (v.major, v.minor, v.micro))";
+
+/// Prints what [`select`] needs to know of an interpreter as one JSON object
+const QUERY: &str = "import sys, sysconfig, json; v = sys.implementation.version; paths = sysconfig.get_paths(); \
+    print(json.dumps({'version': '%d.%d.%d' % sys.version_info[:3], 'implementation': sys.implementation.name, \
+    'implementation_version': '%d.%d.%d' % (v.major, v.minor, v.micro), 'executable': sys.executable, \
+    'prefix': sys.prefix, 'base_prefix': getattr(sys, 'base_prefix', sys.prefix), \
+    'purelib': paths['purelib'], 'scripts': paths['scripts']}))";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PythonInfo {
+    /// `X.Y.Z`, the `python_full_version` marker
+    pub full_version: String,
+    /// `sys.implementation.name`: `cpython`, `pypy`, ...
+    pub implementation_name: String,
+    /// `sys.implementation.version` (PyPy's differs from the language version)
+    pub implementation_version: String,
+    /// `platform.release()`: the kernel release
+    pub platform_release: String,
+    /// `platform.version()`: the kernel build
+    pub platform_version: String,
+}
+
+impl PythonInfo {
+    /// `X.Y`, the `python_version` marker
+    pub fn version(&self) -> String {
+        self.full_version.split('.').take(2).collect::<Vec<_>>().join(".")
+    }
+
+    /// `platform_python_implementation`: `CPython`, `PyPy`, ...
+    pub fn platform_python_implementation(&self) -> String {
+        match self.implementation_name.as_str() {
+            "cpython" => "CPython".to_string(),
+            "pypy" => "PyPy".to_string(),
+            "ironpython" => "IronPython".to_string(),
+            other => other.to_string(),
+        }
+    }
+
+    fn cpython(full_version: String) -> Self {
+        let (platform_release, platform_version) = kernel();
+        Self {
+            implementation_version: full_version.clone(),
+            full_version,
+            implementation_name: "cpython".to_string(),
+            platform_release,
+            platform_version,
+        }
+    }
+}
+
+/// An interpreter given with `--python`, as it describes itself
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Interpreter {
+    /// `sys.executable`; scripts installed for it start this
+    pub executable: PathBuf,
+    pub info: PythonInfo,
+    /// `sys.prefix`
+    pub prefix: PathBuf,
+    /// `sys.base_prefix`; differs from `prefix` in a virtualenv
+    pub base_prefix: PathBuf,
+    /// sysconfig's `purelib`: the site-packages pip-rs installs into
+    pub purelib: PathBuf,
+    /// sysconfig's `scripts`
+    pub scripts: PathBuf,
+}
+
+impl Interpreter {
+    /// Ask the interpreter at `python` (a path, or a name looked up on
+    /// PATH) for its version, prefix and install paths
+    pub fn query(python: &Path) -> Result<Self, PipError> {
+        let invalid = |reason: String| PipError::InvalidInterpreter {
+            path: python.display().to_string(),
+            reason,
+        };
+        if python.is_dir() {
+            return Err(invalid("it is a directory, not a python executable".to_string()));
+        }
+        let output = Command::new(python).args(["-c", QUERY]).output().map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => invalid("no such file".to_string()),
+            _ => invalid(format!("cannot run it: {}", e)),
+        })?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let described = serde_json::from_str::<Described>(stdout.trim()).ok().filter(|_| output.status.success());
+        let Some(described) = described else {
+            return Err(invalid("it is not a Python 3 interpreter".to_string()));
+        };
+        let (platform_release, platform_version) = kernel();
+        Ok(Self {
+            executable: described.executable.filter(|e| !e.as_os_str().is_empty()).unwrap_or_else(|| python.into()),
+            info: PythonInfo {
+                full_version: described.version,
+                implementation_name: described.implementation,
+                implementation_version: described.implementation_version,
+                platform_release,
+                platform_version,
+            },
+            prefix: described.prefix,
+            base_prefix: described.base_prefix,
+            purelib: described.purelib,
+            scripts: described.scripts,
+        })
+    }
+
+    /// The virtualenv the interpreter belongs to, if it runs in one
+    pub fn virtual_env(&self) -> Option<&Path> {
+        (self.prefix != self.base_prefix).then_some(self.prefix.as_path())
+    }
+}
+
+/// [`QUERY`]'s output
+#[derive(Deserialize)]
+struct Described {
+    version: String,
+    implementation: String,
+    implementation_version: String,
+    executable: Option<PathBuf>,
+    prefix: PathBuf,
+    base_prefix: PathBuf,
+    purelib: PathBuf,
+    scripts: PathBuf,
+}
+
+static SELECTED: OnceCell<Interpreter> = OnceCell::new();
+
+/// Work on the environment of the interpreter at `python` for the rest of
+/// the process; call once at startup, before anything reads [`detected`]
+pub fn select(python: &Path) -> Result<&'static Interpreter, PipError> {
+    let interpreter = Interpreter::query(python)?;
+    tracing::debug!("Using {} ({})", interpreter.executable.display(), interpreter.info.full_version);
+    Ok(SELECTED.get_or_init(|| interpreter))
+}
+
+/// The interpreter given with `--python`, if any
+pub fn selected() -> Option<&'static Interpreter> {
+    SELECTED.get()
+}
+
+/// The virtualenv pip-rs works in: that of the `--python` interpreter
+/// when one was given (none for a base interpreter), else `VIRTUAL_ENV`
+pub fn virtual_env() -> Option<PathBuf> {
+    match selected() {
+        Some(python) => python.virtual_env().map(Path::to_path_buf),
+        None => std::env::var_os("VIRTUAL_ENV").map(PathBuf::from),
+    }
+}
+
+static DETECTED: Lazy<PythonInfo> = Lazy::new(|| match selected() {
+    Some(python) => python.info.clone(),
+    None => detect(virtual_env().as_deref()),
+});
+
+/// The active environment's Python, detected once
+pub fn detected() -> &'static PythonInfo {
+    &DETECTED
+}
+
+/// The Python of the virtualenv at `virtual_env`, or of `python3` on PATH
+pub fn detect(virtual_env: Option<&Path>) -> PythonInfo {
+    if let Some(venv) = virtual_env {
+        if let Some(info) = from_pyvenv_cfg(venv) {
+            return info;
+        }
+        let python = venv_python(venv);
+        if let Some(info) = probe(&python) {
+            return info;
+        }
+        tracing::debug!("Could not tell the Python version of {}", venv.display());
+    }
+    let python = if cfg!(target_os = "windows") { "python" } else { "python3" };
+    probe(Path::new(python)).unwrap_or_else(|| {
+        tracing::debug!("No Python interpreter found; assuming {}", FALLBACK_VERSION);
+        PythonInfo::cpython(FALLBACK_VERSION.to_string())
+    })
+}
+
+/// The interpreter [`detect`] asks: the one given with `--python`, else
+/// the virtualenv's, else the first `python3` (`python` on Windows) on PATH
+pub fn interpreter(virtual_env: Option<&Path>) -> Option<PathBuf> {
+    if let Some(python) = selected() {
+        return Some(python.executable.clone());
+    }
+    if let Some(python) = virtual_env.map(venv_python).filter(|python| python.is_file()) {
+        return Some(python);
+    }
+    let name = if cfg!(target_os = "windows") { "python.exe" } else { "python3" };
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(name))
+        .find(|python| python.is_file())
+}
+
+/// `X.Y` of the virtualenv at `venv`: from `pyvenv.cfg`, else its interpreter
+pub fn venv_version(venv: &Path) -> Option<String> {
+    from_pyvenv_cfg(venv).or_else(|| probe(&venv_python(venv))).map(|info| info.version())
+}
+
+fn venv_python(venv: &Path) -> PathBuf {
+    if cfg!(target_os = "windows") {
+        venv.join("Scripts").join("python.exe")
+    } else {
+        venv.join("bin").join("python3")
+    }
+}
+
+/// What `pyvenv.cfg` records: venv writes `version`, virtualenv and uv
+/// `version_info` and `implementation`
+fn from_pyvenv_cfg(venv: &Path) -> Option<PythonInfo> {
+    let content = fs::read_to_string(venv.join("pyvenv.cfg")).ok()?;
+    let config: BTreeMap<String, String> = content
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim().to_lowercase(), value.trim().to_string()))
+        .collect();
+    let version = config.get("version").or_else(|| config.get("version_info"))?;
+    // version_info may carry a release level: 3.12.1.final.0
+    let numeric: Vec<&str> = version.split('.').take_while(|part| part.parse::<u32>().is_ok()).take(3).collect();
+    if numeric.len() < 2 {
+        return None;
+    }
+    let mut info = PythonInfo::cpython(numeric.join("."));
+    if let Some(implementation) = config.get("implementation") {
+        info.implementation_name = implementation.to_lowercase();
+    }
+    Some(info)
+}
+
+/// Ask the interpreter at `python`
+fn probe(python: &Path) -> Option<PythonInfo> {
+    let output = Command::new(python).args(["-c", PROBE]).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut lines = stdout.lines().map(str::trim);
+    let mut info = PythonInfo::cpython(lines.next().filter(|v| !v.is_empty())?.to_string());
+    if let Some(name) = lines.next().filter(|n| !n.is_empty()) {
+        info.implementation_name = name.to_string();
+    }
+    if let Some(version) = lines.next().filter(|v| !v.is_empty()) {
+        info.implementation_version = version.to_string();
+    }
+    Some(info)
+}
+
+/// Kernel release and build, as `uname -r` and `uname -v` print them
+fn kernel() -> (String, String) {
+    let read = |name: &str| {
+        fs::read_to_string(Path::new("/proc/sys/kernel").join(name))
+            .map(|value| value.trim().to_string())
+            .unwrap_or_default()
+    };
+    if cfg!(target_os = "linux") { (read("osrelease"), read("version")) } else { Default::default() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pyvenv_cfg_is_preferred() {
+        let temp = tempfile::tempdir().unwrap();
+        fs::write(temp.path().join("pyvenv.cfg"), "home = /usr/bin\nversion = 3.9.18\n").unwrap();
+        let info = detect(Some(temp.path()));
+        assert_eq!(info.full_version, "3.9.18");
+        assert_eq!(info.version(), "3.9");
+        assert_eq!(info.implementation_name, "cpython");
+        assert_eq!(venv_version(temp.path()).as_deref(), Some("3.9"));
+
+        fs::write(
+            temp.path().join("pyvenv.cfg"),
+            "home = /opt/pypy/bin\nimplementation = PyPy\nversion_info = 3.10.14.final.0\n",
+        )
+        .unwrap();
+        let info = detect(Some(temp.path()));
+        assert_eq!(info.full_version, "3.10.14");
+        assert_eq!(info.platform_python_implementation(), "PyPy");
+    }
+
+    #[cfg(unix)]
+    fn fake_python(dir: &Path, name: &str, stdout: &str) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+        let path = dir.join(name);
+        fs::write(&path, format!("#!/bin/sh\necho '{}'\n", stdout)).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_interpreter_describes_itself() {
+        let temp = tempfile::tempdir().unwrap();
+        let description = r#"{"version": "3.11.9", "implementation": "cpython", "implementation_version": "3.11.9",
+            "executable": "/envs/app/bin/python", "prefix": "/envs/app", "base_prefix": "/usr",
+            "purelib": "/envs/app/lib/python3.11/site-packages", "scripts": "/envs/app/bin"}"#;
+        let python = fake_python(temp.path(), "python3", &description.replace('\n', ""));
+        let interpreter = Interpreter::query(&python).unwrap();
+        assert_eq!(interpreter.executable, Path::new("/envs/app/bin/python"));
+        assert_eq!(interpreter.info.version(), "3.11");
+        assert_eq!(interpreter.purelib, Path::new("/envs/app/lib/python3.11/site-packages"));
+        assert_eq!(interpreter.virtual_env(), Some(Path::new("/envs/app")));
+
+        let base = Interpreter { base_prefix: interpreter.prefix.clone(), ..interpreter };
+        assert_eq!(base.virtual_env(), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_rejects_what_is_not_an_interpreter() {
+        let temp = tempfile::tempdir().unwrap();
+        let reason = |python: &Path| match Interpreter::query(python) {
+            Err(PipError::InvalidInterpreter { reason, .. }) => reason,
+            other => panic!("unexpected {:?}", other),
+        };
+        assert_eq!(reason(&temp.path().join("missing")), "no such file");
+        assert!(reason(temp.path()).contains("directory"));
+        let not_python = fake_python(temp.path(), "python", "Python 2.7.18");
+        assert_eq!(reason(&not_python), "it is not a Python 3 interpreter");
+    }
+}
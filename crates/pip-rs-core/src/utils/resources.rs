@@ -0,0 +1,211 @@
+/// Resource budget for concurrent work
+///
+/// `--jobs` (PIP_RS_JOBS, `jobs` in pip.conf) is a ceiling from which every
+/// worker pool is sized, so a 1-CPU CI container can ask for strictly
+/// sequential installs. Without it the ceiling comes from the CPUs the
+/// process may actually use: a cgroup v2 `cpu.max` quota wins over the raw
+/// core count, and `memory.max` lowers it further so small containers don't
+/// hit the OOM killer.
+use crate::config::config::Config;
+use once_cell::sync::OnceCell;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Mount point of the cgroup v2 hierarchy
+pub const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+/// Network requests in flight per job (they mostly wait on the wire)
+const NETWORK_PER_JOB: usize = 2;
+
+/// Upper bound for concurrent requests against one index
+const MAX_NETWORK: usize = 16;
+
+/// Upper bound for ranges fetched at once for one download
+const MAX_DOWNLOAD_CHUNKS: usize = 4;
+
+/// Memory assumed per job (extraction buffers, resolver state)
+const MEMORY_PER_JOB: u64 = 256 * 1024 * 1024;
+
+/// Limits of the cgroup the process runs in (None = unlimited or unknown)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CgroupLimits {
+    /// CPUs allowed by `cpu.max`, rounded up
+    pub cpus: Option<usize>,
+    /// Bytes allowed by `memory.max`
+    pub memory: Option<u64>,
+}
+
+impl CgroupLimits {
+    /// Read `cpu.max` and `memory.max` from a cgroup directory
+    pub fn read(dir: &Path) -> Self {
+        let read = |name: &str| fs::read_to_string(dir.join(name)).ok();
+        let cpus = read("cpu.max").and_then(|content| {
+            let mut fields = content.split_whitespace();
+            let quota: u64 = fields.next()?.parse().ok()?;
+            let period: u64 = fields.next()?.parse().ok().filter(|&p| p > 0)?;
+            Some(quota.div_ceil(period).max(1) as usize)
+        });
+        let memory = read("memory.max").and_then(|content| content.trim().parse().ok());
+        Self { cpus, memory }
+    }
+
+    /// Limits of this process's own cgroup
+    pub fn current() -> Self {
+        Self::read(&own_cgroup_dir())
+    }
+}
+
+/// `/sys/fs/cgroup/<path>` from the `0::<path>` line of /proc/self/cgroup
+fn own_cgroup_dir() -> PathBuf {
+    let path = fs::read_to_string("/proc/self/cgroup")
+        .ok()
+        .and_then(|content| {
+            content
+                .lines()
+                .find_map(|line| line.strip_prefix("0::").map(|p| p.trim_start_matches('/').to_string()))
+        })
+        .unwrap_or_default();
+    Path::new(CGROUP_ROOT).join(path)
+}
+
+/// Sizes of every worker pool, derived from one `jobs` ceiling
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ResourceBudget {
+    pub jobs: usize,
+    /// Concurrent metadata and download requests
+    pub network: usize,
+    /// Archive extraction workers
+    pub extraction: usize,
+    /// Byte-compilation workers
+    pub compile: usize,
+    /// Ranges fetched at once for a single large download
+    pub download_chunks: usize,
+}
+
+static GLOBAL: OnceCell<ResourceBudget> = OnceCell::new();
+
+impl ResourceBudget {
+    /// Size every pool from an explicit ceiling; `jobs = 1` is fully sequential
+    pub fn from_jobs(jobs: usize) -> Self {
+        let jobs = jobs.max(1);
+        if jobs == 1 {
+            return Self {
+                jobs,
+                network: 1,
+                extraction: 1,
+                compile: 1,
+                download_chunks: 1,
+            };
+        }
+        Self {
+            jobs,
+            network: (jobs * NETWORK_PER_JOB).min(MAX_NETWORK),
+            extraction: jobs,
+            compile: jobs,
+            download_chunks: (jobs / 2).clamp(1, MAX_DOWNLOAD_CHUNKS),
+        }
+    }
+
+    /// Heuristic ceiling from the visible CPUs and cgroup limits
+    pub fn detect(cpus: usize, limits: CgroupLimits) -> Self {
+        let mut jobs = limits.cpus.map_or(cpus, |quota| quota.min(cpus));
+        if let Some(memory) = limits.memory {
+            jobs = jobs.min((memory / MEMORY_PER_JOB) as usize);
+        }
+        Self::from_jobs(jobs)
+    }
+
+    /// `jobs` from the config, or the detected heuristic
+    pub fn from_config(config: &Config) -> Self {
+        match config.jobs() {
+            Some(jobs) => Self::from_jobs(jobs),
+            None => {
+                let cpus = std::thread::available_parallelism().map_or(1, |n| n.get());
+                Self::detect(cpus, CgroupLimits::current())
+            }
+        }
+    }
+
+    /// The budget for this process, computed once from the effective config
+    pub fn global() -> &'static Self {
+        GLOBAL.get_or_init(|| Self::from_config(Config::global()))
+    }
+
+    pub fn is_sequential(&self) -> bool {
+        self.jobs == 1
+    }
+}
+
+/// Put the calling thread in the idle IO class so background cache
+/// maintenance doesn't compete with installs. A no-op off Linux.
+pub fn lower_io_priority() {
+    #[cfg(target_os = "linux")]
+    {
+        const IOPRIO_WHO_PROCESS: libc::c_long = 1;
+        const IOPRIO_CLASS_IDLE: libc::c_long = 3;
+        const IOPRIO_CLASS_SHIFT: libc::c_long = 13;
+        // SAFETY: ioprio_set only reads its integer arguments; `who = 0`
+        // targets the calling thread. Failure just leaves the priority as is.
+        let result = unsafe {
+            libc::syscall(
+                libc::SYS_ioprio_set,
+                IOPRIO_WHO_PROCESS,
+                0 as libc::c_long,
+                IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT,
+            )
+        };
+        if result != 0 {
+            tracing::debug!("Could not lower IO priority: {}", std::io::Error::last_os_error());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> CgroupLimits {
+        CgroupLimits::read(&Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/cgroup").join(name))
+    }
+
+    #[test]
+    fn test_pool_sizes_follow_jobs() {
+        let sizes = |jobs| {
+            let b = ResourceBudget::from_jobs(jobs);
+            (b.jobs, b.network, b.extraction, b.compile, b.download_chunks)
+        };
+        assert_eq!(sizes(0), (1, 1, 1, 1, 1));
+        assert_eq!(sizes(1), (1, 1, 1, 1, 1));
+        assert_eq!(sizes(2), (2, 4, 2, 2, 1));
+        assert_eq!(sizes(4), (4, 8, 4, 4, 2));
+        assert_eq!(sizes(32), (32, 16, 32, 32, 4));
+        assert!(ResourceBudget::from_jobs(1).is_sequential());
+    }
+
+    #[test]
+    fn test_detect_from_cgroup_fixtures() {
+        assert_eq!(
+            fixture("one-cpu"),
+            CgroupLimits {
+                cpus: Some(1),
+                memory: Some(512 * 1024 * 1024)
+            }
+        );
+        assert_eq!(fixture("unlimited"), CgroupLimits::default());
+        assert_eq!(fixture("missing"), CgroupLimits::default());
+
+        // A 1-CPU quota on a 64-core host is sequential
+        assert!(ResourceBudget::detect(64, fixture("one-cpu")).is_sequential());
+        // 2.5 CPUs round up to 3, but 1 GiB only affords 4 jobs either way
+        assert_eq!(ResourceBudget::detect(64, fixture("fractional")).jobs, 3);
+        assert_eq!(ResourceBudget::detect(2, fixture("fractional")).jobs, 2);
+        assert_eq!(ResourceBudget::detect(8, fixture("unlimited")).jobs, 8);
+        // Memory is the binding limit on a big quota
+        let tight = CgroupLimits {
+            cpus: Some(16),
+            memory: Some(768 * 1024 * 1024),
+        };
+        assert_eq!(ResourceBudget::detect(32, tight).jobs, 3);
+    }
+}
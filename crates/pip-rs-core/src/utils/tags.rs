@@ -0,0 +1,583 @@
+/// Wheel compatibility tags (PEP 425) for the environment being installed into
+///
+/// A release may ship `cp312-cp312-manylinux_2_17_x86_64`,
+/// `cp312-cp312-macosx_11_0_arm64` and `py3-none-any` wheels; which of them
+/// an environment can use, and which it should prefer, follows from the
+/// ordered list of tags it supports. The list is built the way
+/// `packaging.tags.sys_tags` builds it: the interpreter's own ABI first,
+/// then `abi3`, then pure-Python tags, each across the platform tags from
+/// most to least specific (glibc/musl tiers for Linux, OS versions for
+/// macOS). An [`EnvironmentOverride`] retargets it for cross-platform
+/// downloads.
+use crate::models::installation_report::EnvironmentOverride;
+use anyhow::{bail, Result};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::fmt;
+
+/// glibc assumed for Linux targets that can't be inspected: manylinux2014
+const BASELINE_GLIBC: u32 = 17;
+/// Python assumed when the detected version can't be parsed
+const DEFAULT_PYTHON: (u32, u32) = (3, 11);
+
+/// One `interpreter-abi-platform` triple
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Tag {
+    pub interpreter: String,
+    pub abi: String,
+    pub platform: String,
+}
+
+impl Tag {
+    pub fn new(interpreter: &str, abi: &str, platform: &str) -> Self {
+        Self {
+            interpreter: interpreter.to_string(),
+            abi: abi.to_string(),
+            platform: platform.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Tag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}-{}", self.interpreter, self.abi, self.platform)
+    }
+}
+
+/// The tags a wheel filename declares, with compressed tag sets
+/// (`py2.py3-none-any`) expanded; `None` for a malformed name
+pub fn wheel_tags(filename: &str) -> Option<Vec<Tag>> {
+    let parts: Vec<&str> = filename.strip_suffix(".whl")?.split('-').collect();
+    if !(5..=6).contains(&parts.len()) {
+        return None;
+    }
+    let [interpreters, abis, platforms] = [parts[parts.len() - 3], parts[parts.len() - 2], parts[parts.len() - 1]];
+    let mut tags = Vec::new();
+    for interpreter in interpreters.split('.') {
+        for abi in abis.split('.') {
+            for platform in platforms.split('.') {
+                tags.push(Tag::new(interpreter, abi, platform));
+            }
+        }
+    }
+    Some(tags)
+}
+
+/// Which C library a Linux target links against, and its minor version
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Libc {
+    /// glibc 2.x
+    Glibc(u32),
+    /// musl 1.x
+    Musl(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Os {
+    Linux(Libc),
+    Mac(u32, u32),
+    Windows,
+}
+
+/// The interpreter and platform wheels are chosen for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TargetEnvironment {
+    /// `cp`, `pp`, ...
+    pub implementation: String,
+    pub python: (u32, u32),
+    /// Platform tags, most specific first
+    pub platforms: Vec<String>,
+    /// `--abi`, in place of the interpreter's own ABI
+    pub abi: Option<String>,
+}
+
+impl fmt::Display for TargetEnvironment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}{}", self.implementation, self.python.0, self.python.1)?;
+        if let Some(abi) = &self.abi {
+            write!(f, "-{}", abi)?;
+        }
+        match self.platforms.first() {
+            Some(platform) => write!(f, " on {}", platform),
+            None => Ok(()),
+        }
+    }
+}
+
+impl TargetEnvironment {
+    /// The running system, with the Python of the active environment
+    pub fn current() -> Self {
+        let python = parse_python_version(&super::python_env::detected().version()).unwrap_or(DEFAULT_PYTHON);
+        Self {
+            implementation: "cp".to_string(),
+            python,
+            platforms: platforms(current_os(), current_arch()),
+            abi: None,
+        }
+    }
+
+    /// The running system with `--python-version`, `--platform`,
+    /// `--implementation` and the architecture replaced. The platform is
+    /// either a `sys.platform` value (`linux`, `darwin`, `win32`) or a
+    /// wheel platform tag (`manylinux2014_x86_64`, `macosx_11_0_arm64`);
+    /// a manylinux, musllinux or macOS tag also accepts the older tiers.
+    pub fn with_override(environment: &EnvironmentOverride) -> Self {
+        let mut target = Self::current();
+        if let Some(python) = environment.python_version.as_deref().and_then(|v| parse_python_version(v).ok()) {
+            target.python = python;
+        }
+        if let Some(implementation) = &environment.implementation {
+            target.implementation = implementation_tag(implementation);
+        }
+        target.abi = environment.abi.clone();
+        let arch = environment.architecture.as_deref().map(normalize_arch);
+        match environment.platform.as_deref() {
+            Some(platform @ ("linux" | "darwin" | "win32")) => {
+                let os = match platform {
+                    "linux" => Os::Linux(Libc::Glibc(BASELINE_GLIBC)),
+                    "darwin" => Os::Mac(11, 0),
+                    _ => Os::Windows,
+                };
+                // The running system's libc or OS version when it is the same OS
+                let os = match (os, current_os()) {
+                    (Os::Linux(_), current @ Os::Linux(_)) | (Os::Mac(..), current @ Os::Mac(..)) => current,
+                    _ => os,
+                };
+                target.platforms = platforms(os, arch.unwrap_or_else(current_arch));
+            }
+            Some(tag) => target.platforms = expand_platform_tag(tag),
+            None => {
+                if let Some(arch) = arch {
+                    target.platforms = platforms(current_os(), arch);
+                }
+            }
+        }
+        target
+    }
+
+    /// Every supported tag, most preferred first
+    pub fn tags(&self) -> Vec<Tag> {
+        let (major, minor) = self.python;
+        let interpreter = format!("{}{}{}", self.implementation, major, minor);
+        let mut tags = Vec::new();
+        let mut across_platforms = |interpreter: &str, abi: &str| {
+            for platform in &self.platforms {
+                tags.push(Tag::new(interpreter, abi, platform));
+            }
+        };
+
+        let abi = self.abi.clone().unwrap_or_else(|| interpreter.clone());
+        if self.implementation == "cp" {
+            across_platforms(&interpreter, &abi);
+            if abi != "abi3" {
+                across_platforms(&interpreter, "abi3");
+            }
+            across_platforms(&interpreter, "none");
+            // The stable ABI of older CPythons (abi3 started with 3.2)
+            for older in (2..minor).rev() {
+                across_platforms(&format!("cp{}{}", major, older), "abi3");
+            }
+        } else {
+            if self.abi.is_some() {
+                across_platforms(&interpreter, &abi);
+            }
+            across_platforms(&interpreter, "none");
+        }
+        let generic = python_tags(major, minor);
+        for python in &generic {
+            across_platforms(python, "none");
+        }
+        tags.push(Tag::new(&interpreter, "none", "any"));
+        for python in &generic {
+            tags.push(Tag::new(python, "none", "any"));
+        }
+        tags
+    }
+}
+
+/// Supported tags by preference, for scoring wheel filenames
+#[derive(Debug, Clone)]
+pub struct TagPriority {
+    ordered: Vec<Tag>,
+    ranks: HashMap<Tag, usize>,
+}
+
+impl TagPriority {
+    pub fn new(ordered: Vec<Tag>) -> Self {
+        let mut ranks = HashMap::with_capacity(ordered.len());
+        for (rank, tag) in ordered.iter().enumerate() {
+            ranks.entry(tag.clone()).or_insert(rank);
+        }
+        Self { ordered, ranks }
+    }
+
+    pub fn for_target(target: &TargetEnvironment) -> Self {
+        Self::new(target.tags())
+    }
+
+    /// How well a wheel fits, lower is better; `None` when none of its
+    /// tags is supported (or it isn't a wheel)
+    pub fn rank(&self, filename: &str) -> Option<usize> {
+        wheel_tags(filename)?.iter().filter_map(|tag| self.ranks.get(tag)).min().copied()
+    }
+
+    /// Every supported tag, most preferred first
+    pub fn tags(&self) -> &[Tag] {
+        &self.ordered
+    }
+
+    pub fn len(&self) -> usize {
+        self.ordered.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ordered.is_empty()
+    }
+
+    /// The most and least preferred tags, for error messages
+    pub fn summary(&self) -> String {
+        match self.ordered.as_slice() {
+            [] => "none".to_string(),
+            [first, second, third, .., last] if self.ordered.len() > 4 => {
+                format!("{}, {}, {}, ..., {} ({} tags)", first, second, third, last, self.ordered.len())
+            }
+            tags => tags.iter().map(Tag::to_string).collect::<Vec<_>>().join(", "),
+        }
+    }
+}
+
+static CURRENT: Lazy<TagPriority> = Lazy::new(|| TagPriority::for_target(&TargetEnvironment::current()));
+
+/// Tag priority of the running system, computed once
+pub fn current() -> &'static TagPriority {
+    &CURRENT
+}
+
+/// `py3X`, `py3`, then `py3(X-1)` down to `py30`
+fn python_tags(major: u32, minor: u32) -> Vec<String> {
+    let mut tags = vec![format!("py{}{}", major, minor), format!("py{}", major)];
+    tags.extend((0..minor).rev().map(|older| format!("py{}{}", major, older)));
+    tags
+}
+
+/// `3.12` or `312` (pip's `--python-version` accepts both)
+pub fn parse_python_version(version: &str) -> Result<(u32, u32)> {
+    let trimmed = version.trim();
+    let (major, minor) = match trimmed.split_once('.') {
+        Some((major, rest)) => (major, rest.split('.').next().unwrap_or_default()),
+        // The major version is the first character, whatever its width
+        None => trimmed.split_at(trimmed.chars().next().map_or(0, char::len_utf8)),
+    };
+    match (major.parse(), minor.parse()) {
+        (Ok(major), Ok(minor)) => Ok((major, minor)),
+        _ => bail!("Invalid Python version '{}': expected a version such as 3.12 or 312", version),
+    }
+}
+
+fn implementation_tag(implementation: &str) -> String {
+    match implementation.to_ascii_lowercase().as_str() {
+        "cpython" | "cp" => "cp".to_string(),
+        "pypy" | "pp" => "pp".to_string(),
+        "ironpython" | "ip" => "ip".to_string(),
+        "jython" | "jy" => "jy".to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// Machine names as wheel platform tags spell them on Linux
+fn normalize_arch(arch: &str) -> String {
+    match arch.to_ascii_lowercase().as_str() {
+        "amd64" | "x86_64" => "x86_64".to_string(),
+        "arm64" | "aarch64" => "aarch64".to_string(),
+        "x86" | "i386" | "i686" => "i686".to_string(),
+        other => other.to_string(),
+    }
+}
+
+fn current_arch() -> String {
+    normalize_arch(std::env::consts::ARCH)
+}
+
+fn current_os() -> Os {
+    if cfg!(target_os = "macos") {
+        let (major, minor) = macos_version().unwrap_or((11, 0));
+        Os::Mac(major, minor)
+    } else if cfg!(target_os = "windows") {
+        Os::Windows
+    } else {
+        Os::Linux(detect_libc().unwrap_or(Libc::Glibc(BASELINE_GLIBC)))
+    }
+}
+
+/// `sw_vers -productVersion`, e.g. `14.2.1`
+fn macos_version() -> Option<(u32, u32)> {
+    let output = std::process::Command::new("sw_vers").arg("-productVersion").output().ok()?;
+    let version = String::from_utf8_lossy(&output.stdout);
+    let mut parts = version.trim().split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next().and_then(|minor| minor.parse().ok()).unwrap_or(0);
+    Some((major, minor))
+}
+
+/// glibc or musl and its version, from `ldd --version` (musl prints its
+/// banner to stderr)
+fn detect_libc() -> Option<Libc> {
+    let output = std::process::Command::new("ldd").arg("--version").output().ok()?;
+    let text = format!("{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+    parse_ldd_version(&text)
+}
+
+fn parse_ldd_version(text: &str) -> Option<Libc> {
+    let minor_of = |version: &str, major: &str| -> Option<u32> {
+        version.trim().strip_prefix(major)?.strip_prefix('.')?.split('.').next()?.parse().ok()
+    };
+    if text.contains("musl") {
+        let version = text.lines().find_map(|line| line.trim().strip_prefix("Version "))?;
+        return minor_of(version, "1").map(Libc::Musl);
+    }
+    let first = text.lines().next()?;
+    minor_of(first.rsplit(' ').next()?, "2").map(Libc::Glibc)
+}
+
+fn platforms(os: Os, arch: String) -> Vec<String> {
+    match os {
+        Os::Linux(libc) => {
+            let mut platforms = linux_platforms(libc, &arch);
+            platforms.push(format!("linux_{}", arch));
+            platforms
+        }
+        Os::Mac(major, minor) => {
+            let arch = if arch == "aarch64" { "arm64".to_string() } else { arch };
+            macos_platforms((major, minor), &arch)
+        }
+        Os::Windows => vec![
+            match arch.as_str() {
+                "x86_64" => "win_amd64".to_string(),
+                "i686" => "win32".to_string(),
+                "aarch64" => "win_arm64".to_string(),
+                other => format!("win_{}", other),
+            },
+        ],
+    }
+}
+
+/// manylinux tiers from the target's glibc down, or musllinux tiers, with
+/// the legacy `manylinux2014`/`2010`/`1` aliases next to their equivalents
+fn linux_platforms(libc: Libc, arch: &str) -> Vec<String> {
+    let mut platforms = Vec::new();
+    match libc {
+        Libc::Glibc(minor) => {
+            // manylinux tiers below 2.17 only exist for x86
+            let floor = if matches!(arch, "x86_64" | "i686") { 5 } else { BASELINE_GLIBC };
+            for glibc in (floor..=minor).rev() {
+                platforms.push(format!("manylinux_2_{}_{}", glibc, arch));
+                let legacy = match glibc {
+                    17 => Some("manylinux2014"),
+                    12 => Some("manylinux2010"),
+                    5 => Some("manylinux1"),
+                    _ => None,
+                };
+                if let Some(legacy) = legacy {
+                    platforms.push(format!("{}_{}", legacy, arch));
+                }
+            }
+        }
+        Libc::Musl(minor) => {
+            for musl in (0..=minor).rev() {
+                platforms.push(format!("musllinux_1_{}_{}", musl, arch));
+            }
+        }
+    }
+    platforms
+}
+
+/// macOS versions from the target's down to 10.4 (Intel) or 11 (Apple
+/// silicon), each with the binary formats that run on the architecture
+fn macos_platforms((major, minor): (u32, u32), arch: &str) -> Vec<String> {
+    let formats: &[&str] = match arch {
+        "arm64" => &["arm64", "universal2"],
+        "x86_64" => &["x86_64", "intel", "fat64", "fat32", "universal2", "universal"],
+        other => return vec![format!("macosx_{}_{}_{}", major, minor, other)],
+    };
+    let mut versions = Vec::new();
+    if major >= 11 {
+        versions.extend((11..=major).rev().map(|major| (major, 0)));
+        if arch == "x86_64" {
+            versions.extend((4..=16).rev().map(|minor| (10, minor)));
+        }
+    } else {
+        versions.extend((4..=minor).rev().map(|minor| (10, minor)));
+    }
+    versions
+        .into_iter()
+        .flat_map(|(major, minor)| formats.iter().map(move |format| format!("macosx_{}_{}_{}", major, minor, format)))
+        .collect()
+}
+
+/// `sys.platform` and `platform.machine()` of a system a wheel platform
+/// tag is for, e.g. `linux`/`aarch64` for `manylinux2014_aarch64`
+pub fn marker_platform(tag: &str) -> Option<(&'static str, String)> {
+    match tag {
+        "win32" => return Some(("win32", "x86".to_string())),
+        "win_amd64" => return Some(("win32", "AMD64".to_string())),
+        "win_arm64" => return Some(("win32", "ARM64".to_string())),
+        _ => {}
+    }
+    let legacy = ["manylinux2014_", "manylinux2010_", "manylinux1_", "linux_"];
+    if let Some(arch) = legacy.iter().find_map(|prefix| tag.strip_prefix(prefix)) {
+        return Some(("linux", arch.to_string()));
+    }
+    let arch = |rest: &str| rest.splitn(3, '_').nth(2).map(String::from);
+    if let Some(rest) = tag.strip_prefix("manylinux_").or_else(|| tag.strip_prefix("musllinux_")) {
+        return Some(("linux", arch(rest)?));
+    }
+    Some(("darwin", arch(tag.strip_prefix("macosx_")?)?))
+}
+
+/// A `--platform` wheel tag with the older tiers it implies
+fn expand_platform_tag(tag: &str) -> Vec<String> {
+    let tiered = |rest: &str| -> Option<(u32, u32, String)> {
+        let mut parts = rest.splitn(3, '_');
+        Some((parts.next()?.parse().ok()?, parts.next()?.parse().ok()?, parts.next()?.to_string()))
+    };
+    let legacy = [("manylinux2014_", 17), ("manylinux2010_", 12), ("manylinux1_", 5)];
+    if let Some((prefix, glibc)) = legacy.iter().find(|(prefix, _)| tag.starts_with(prefix)) {
+        return linux_platforms(Libc::Glibc(*glibc), &tag[prefix.len()..]);
+    }
+    let expanded = if let Some(rest) = tag.strip_prefix("manylinux_") {
+        tiered(rest).filter(|(major, ..)| *major == 2).map(|(_, minor, arch)| linux_platforms(Libc::Glibc(minor), &arch))
+    } else if let Some(rest) = tag.strip_prefix("musllinux_") {
+        tiered(rest).filter(|(major, ..)| *major == 1).map(|(_, minor, arch)| linux_platforms(Libc::Musl(minor), &arch))
+    } else if let Some(rest) = tag.strip_prefix("macosx_") {
+        tiered(rest).map(|(major, minor, arch)| macos_platforms((major, minor), &arch))
+    } else {
+        None
+    };
+    expanded.unwrap_or_else(|| vec![tag.to_string()])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(platforms: Vec<String>) -> TargetEnvironment {
+        TargetEnvironment {
+            implementation: "cp".to_string(),
+            python: (3, 12),
+            platforms,
+            abi: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_python_version() {
+        assert_eq!(parse_python_version("3.12").unwrap(), (3, 12));
+        assert_eq!(parse_python_version("3.12.4").unwrap(), (3, 12));
+        assert_eq!(parse_python_version(" 312 ").unwrap(), (3, 12));
+        for invalid in ["", "3", "three.12", "é12", "3é"] {
+            assert!(parse_python_version(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_tag_order() {
+        let platforms = vec!["manylinux_2_17_x86_64".to_string(), "linux_x86_64".to_string()];
+        let tags: Vec<String> = target(platforms).tags().iter().map(Tag::to_string).collect();
+        assert_eq!(
+            &tags[..6],
+            [
+                "cp312-cp312-manylinux_2_17_x86_64",
+                "cp312-cp312-linux_x86_64",
+                "cp312-abi3-manylinux_2_17_x86_64",
+                "cp312-abi3-linux_x86_64",
+                "cp312-none-manylinux_2_17_x86_64",
+                "cp312-none-linux_x86_64",
+            ]
+        );
+        let position = |tag: &str| tags.iter().position(|t| t == tag).unwrap();
+        assert!(position("cp311-abi3-linux_x86_64") < position("py312-none-manylinux_2_17_x86_64"));
+        assert!(position("py3-none-linux_x86_64") < position("cp312-none-any"));
+        assert_eq!(tags.last().unwrap(), "py30-none-any");
+        assert!(tags.contains(&"cp32-abi3-linux_x86_64".to_string()));
+        assert!(!tags.contains(&"cp31-abi3-linux_x86_64".to_string()) && !tags.contains(&"cp312-abi3-any".to_string()));
+    }
+
+    #[test]
+    fn test_rank_picks_the_platform_wheel() {
+        let linux = TagPriority::for_target(&target(linux_platforms(Libc::Glibc(35), "x86_64")));
+        let files = [
+            "demo-1.0-cp312-cp312-macosx_11_0_arm64.whl",
+            "demo-1.0-py2.py3-none-any.whl",
+            "demo-1.0-cp312-cp312-manylinux_2_17_x86_64.manylinux2014_x86_64.whl",
+            "demo-1.0-cp312-cp312-manylinux_2_39_x86_64.whl",
+            "demo-1.0-cp311-cp311-manylinux_2_17_x86_64.whl",
+        ];
+        let ranks: Vec<Option<usize>> = files.iter().map(|file| linux.rank(file)).collect();
+        assert_eq!((ranks[0], ranks[3], ranks[4]), (None, None, None));
+        assert!(ranks[2].unwrap() < ranks[1].unwrap());
+        assert!(linux.summary().starts_with("cp312-cp312-manylinux_2_35_x86_64, cp312-cp312-manylinux_2_34_x86_64"));
+    }
+
+    #[test]
+    fn test_platforms() {
+        let macos = macos_platforms((14, 2), "arm64");
+        assert_eq!(&macos[..3], ["macosx_14_0_arm64", "macosx_14_0_universal2", "macosx_13_0_arm64"]);
+        assert_eq!(macos.last().unwrap(), "macosx_11_0_universal2");
+        assert!(macos_platforms((11, 0), "x86_64").contains(&"macosx_10_9_x86_64".to_string()));
+
+        let musl = platforms(Os::Linux(Libc::Musl(2)), "aarch64".to_string());
+        assert_eq!(musl, ["musllinux_1_2_aarch64", "musllinux_1_1_aarch64", "musllinux_1_0_aarch64", "linux_aarch64"]);
+        let manylinux = linux_platforms(Libc::Glibc(17), "aarch64");
+        assert_eq!(manylinux, ["manylinux_2_17_aarch64", "manylinux2014_aarch64"]);
+
+        assert_eq!(parse_ldd_version("ldd (GNU libc) 2.35\nCopyright (C) 2022"), Some(Libc::Glibc(35)));
+        assert_eq!(parse_ldd_version("musl libc (x86_64)\nVersion 1.2.4\n"), Some(Libc::Musl(2)));
+    }
+
+    #[test]
+    fn test_override_for_cross_platform_download() {
+        let environment = EnvironmentOverride {
+            python_version: Some("310".to_string()),
+            platform: Some("manylinux2014_x86_64".to_string()),
+            implementation: Some("cpython".to_string()),
+            architecture: None,
+            abi: None,
+        };
+        let target = TargetEnvironment::with_override(&environment);
+        assert_eq!(target.python, (3, 10));
+        assert_eq!(target.platforms.first().unwrap(), "manylinux_2_17_x86_64");
+        assert_eq!(target.platforms.last().unwrap(), "manylinux1_x86_64");
+        let priority = TagPriority::for_target(&target);
+        assert!(priority.rank("demo-1.0-cp310-cp310-manylinux_2_5_x86_64.manylinux1_x86_64.whl").is_some());
+        assert!(priority.rank("demo-1.0-cp310-cp310-win_amd64.whl").is_none());
+
+        let windows = TargetEnvironment::with_override(&EnvironmentOverride {
+            platform: Some("win32".to_string()),
+            architecture: Some("AMD64".to_string()),
+            ..EnvironmentOverride::new()
+        });
+        assert_eq!(windows.platforms, ["win_amd64"]);
+        let macos = TargetEnvironment::with_override(&EnvironmentOverride {
+            platform: Some("macosx_12_0_arm64".to_string()),
+            ..EnvironmentOverride::new()
+        });
+        assert_eq!(macos.platforms[..2], ["macosx_12_0_arm64", "macosx_12_0_universal2"]);
+
+        let debug = TargetEnvironment::with_override(&EnvironmentOverride {
+            python_version: Some("3.12".to_string()),
+            platform: Some("linux_aarch64".to_string()),
+            abi: Some("cp312d".to_string()),
+            ..EnvironmentOverride::new()
+        });
+        assert_eq!(debug.to_string(), "cp312-cp312d on linux_aarch64");
+        let priority = TagPriority::for_target(&debug);
+        assert!(priority.rank("demo-1.0-cp312-cp312d-linux_aarch64.whl").is_some());
+        assert!(priority.rank("demo-1.0-cp312-cp312-linux_aarch64.whl").is_none());
+        assert!(priority.rank("demo-1.0-cp39-abi3-linux_aarch64.whl").is_some());
+
+        assert_eq!(marker_platform("manylinux_2_28_aarch64"), Some(("linux", "aarch64".to_string())));
+        assert_eq!(marker_platform("macosx_11_0_arm64"), Some(("darwin", "arm64".to_string())));
+        assert_eq!(marker_platform("win_amd64"), Some(("win32", "AMD64".to_string())));
+        assert_eq!(marker_platform("linux"), None);
+    }
+}
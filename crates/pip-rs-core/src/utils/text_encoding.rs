@@ -0,0 +1,183 @@
+/// Decoding text files that weren't saved as plain UTF-8
+///
+/// Requirement files, pip.conf and lock files written on Windows often
+/// start with a UTF-8 byte order mark, or are UTF-16 outright (PowerShell's
+/// `pip freeze > requirements.txt`). These are decoded transparently: BOMs
+/// are stripped, UTF-16 is recognised by its BOM or by the null bytes of
+/// ASCII text, and a PEP 263 `# -*- coding: latin-1 -*-` line in the first
+/// two lines selects a legacy encoding. Anything that still can't be decoded
+/// is an error naming the encoding, rather than garbage requirement names.
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+/// Bytes sniffed for the UTF-16 null-byte heuristic
+const SNIFF_LEN: usize = 512;
+
+/// A file whose encoding was recognised but couldn't be decoded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncodingError {
+    /// What the file appears to be encoded as, e.g. `UTF-16LE`
+    pub encoding: String,
+    pub detail: String,
+}
+
+impl fmt::Display for EncodingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "file appears to be encoded as {} but {}; please save it as UTF-8",
+            self.encoding, self.detail
+        )
+    }
+}
+
+impl std::error::Error for EncodingError {}
+
+/// Read a text file, decoding it as described in the module docs.
+/// Undecodable content is an [`io::ErrorKind::InvalidData`] error that
+/// wraps an [`EncodingError`] and names the path.
+pub fn read_text(path: &Path) -> io::Result<String> {
+    let bytes = std::fs::read(path)?;
+    decode(&bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e)))
+}
+
+/// Decode file contents to a string
+pub fn decode(bytes: &[u8]) -> Result<String, EncodingError> {
+    if bytes.starts_with(&[0xFF, 0xFE, 0x00, 0x00]) || bytes.starts_with(&[0x00, 0x00, 0xFE, 0xFF]) {
+        return Err(EncodingError {
+            encoding: "UTF-32".to_string(),
+            detail: "UTF-32 is not supported".to_string(),
+        });
+    }
+    if let Some((encoding, bom_len)) = Encoding::for_bom(bytes) {
+        return decode_as(encoding, &bytes[bom_len..]);
+    }
+    if let Some(encoding) = sniff_utf16(bytes) {
+        return decode_as(encoding, bytes);
+    }
+    if let Some(label) = coding_declaration(bytes) {
+        let encoding = encoding_for_label(&label).ok_or_else(|| EncodingError {
+            encoding: label.clone(),
+            detail: "that encoding is not known".to_string(),
+        })?;
+        return decode_as(encoding, bytes);
+    }
+    String::from_utf8(bytes.to_vec()).map_err(|e| {
+        let offset = e.utf8_error().valid_up_to();
+        EncodingError {
+            encoding: "a legacy 8-bit encoding such as Windows-1252".to_string(),
+            detail: format!(
+                "it has no `# -*- coding: ... -*-` line and byte 0x{:02X} at offset {} is not UTF-8",
+                bytes[offset], offset
+            ),
+        }
+    })
+}
+
+fn decode_as(encoding: &'static Encoding, bytes: &[u8]) -> Result<String, EncodingError> {
+    let name = if encoding == UTF_8 { "UTF-8" } else { encoding.name() };
+    encoding
+        .decode_without_bom_handling_and_without_replacement(bytes)
+        .map(|text| text.into_owned())
+        .ok_or_else(|| EncodingError {
+            encoding: name.to_string(),
+            detail: "it contains byte sequences that are invalid in that encoding".to_string(),
+        })
+}
+
+/// Look up a Python codec name; encoding_rs knows the WHATWG labels, which
+/// cover most of them once `_` is spelled `-`
+fn encoding_for_label(label: &str) -> Option<&'static Encoding> {
+    let label = label.to_ascii_lowercase().replace('_', "-");
+    let label = match label.as_str() {
+        "latin-1" | "l1" => "latin1",
+        "utf-8-sig" => "utf-8",
+        other => other,
+    };
+    Encoding::for_label(label.as_bytes())
+}
+
+/// UTF-16 without a BOM: ASCII text has a null in every other byte
+fn sniff_utf16(bytes: &[u8]) -> Option<&'static Encoding> {
+    let sample = &bytes[..bytes.len().min(SNIFF_LEN) & !1];
+    if sample.is_empty() {
+        return None;
+    }
+    let pairs = sample.len() / 2;
+    let even = sample.iter().step_by(2).filter(|&&b| b == 0).count();
+    let odd = sample.iter().skip(1).step_by(2).filter(|&&b| b == 0).count();
+    if odd * 2 > pairs && even == 0 {
+        Some(UTF_16LE)
+    } else if even * 2 > pairs && odd == 0 {
+        Some(UTF_16BE)
+    } else {
+        None
+    }
+}
+
+/// The encoding named by a PEP 263 comment in the first two lines
+fn coding_declaration(bytes: &[u8]) -> Option<String> {
+    for line in bytes.split(|&b| b == b'\n').take(2) {
+        let line = String::from_utf8_lossy(line);
+        let line = line.trim_start_matches([' ', '\t', '\x0c']);
+        let Some(comment) = line.strip_prefix('#') else { continue };
+        let Some(at) = comment.find("coding") else { continue };
+        let rest = &comment[at + "coding".len()..];
+        let Some(rest) = rest.strip_prefix([':', '=']) else { continue };
+        let label: String = rest
+            .trim_start_matches([' ', '\t'])
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+            .collect();
+        if !label.is_empty() {
+            return Some(label);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::requirements_parser::RequirementsParser;
+
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/encodings");
+
+    #[test]
+    fn test_fixtures_parse_identically() {
+        let parse = |name: &str| {
+            let content = read_text(&Path::new(FIXTURES).join(name)).unwrap();
+            // Only the Latin-1 file has a coding line, which also shifts line numbers
+            RequirementsParser::parse_content(&content)
+                .into_iter()
+                .filter(|req| !req.requirement.contains("coding:"))
+                .map(|req| (req.requirement, req.is_editable, req.is_comment, req.hashes))
+                .collect::<Vec<_>>()
+        };
+        let expected = parse("utf8.txt");
+        assert!(!expected.is_empty());
+        for name in ["utf8-bom.txt", "utf16le.txt", "latin1-coding.txt"] {
+            assert_eq!(parse(name), expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_detection() {
+        let utf16be: Vec<u8> = "requests==2.31.0\n".encode_utf16().flat_map(u16::to_be_bytes).collect();
+        assert_eq!(decode(&utf16be).unwrap(), "requests==2.31.0\n");
+        assert_eq!(decode(b"\xEF\xBB\xBFflask\n").unwrap(), "flask\n");
+        assert_eq!(decode(b"# coding=latin-1\n# caf\xE9\n").unwrap(), "# coding=latin-1\n# caf\u{e9}\n");
+    }
+
+    #[test]
+    fn test_undecodable_names_encoding() {
+        let err = decode(b"# caf\xE9\nflask\n").unwrap_err();
+        assert!(err.to_string().contains("Windows-1252"));
+        assert!(err.to_string().contains("please save it as UTF-8"));
+
+        let err = decode(b"\xFF\xFEa\x00\x00\xD8").unwrap_err();
+        assert_eq!(err.encoding, "UTF-16LE");
+    }
+}
@@ -1,57 +1,205 @@
-/// Version comparison utilities
+/// PEP 440 versions
+///
+/// This is the one place pip-rs parses version strings. Resolution,
+/// specifier and Requires-Python checks, marker evaluation, `pip list
+/// --outdated` and the upgrade scan all order and match versions through
+/// [`Version`], so they agree on epochs and on pre-, post-, dev- and local
+/// versions. Parsing accepts every spelling PEP 440 normalizes (`v1.0`,
+/// `1.0-RC1`, `1.0.post`, `1.0-1`, `1.0+Ubuntu-1`) and `Display` prints the
+/// canonical form.
+use crate::models::{VersionOp, VersionSpec};
 use std::cmp::Ordering;
+use std::fmt;
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+/// Pre-release phase, in release order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PreRelease {
+    Alpha,
+    Beta,
+    Rc,
+}
+
+/// One dot-separated part of a local version label. Numeric parts sort
+/// after alphanumeric ones, as PEP 440 specifies.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LocalSegment {
+    Text(String),
+    Number(u64),
+}
+
+impl Ord for LocalSegment {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (LocalSegment::Number(a), LocalSegment::Number(b)) => a.cmp(b),
+            (LocalSegment::Text(a), LocalSegment::Text(b)) => a.cmp(b),
+            (LocalSegment::Number(_), LocalSegment::Text(_)) => Ordering::Greater,
+            (LocalSegment::Text(_), LocalSegment::Number(_)) => Ordering::Less,
+        }
+    }
+}
+
+impl PartialOrd for LocalSegment {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// A parsed PEP 440 version. Equality and ordering are PEP 440's: `1.0`
+/// equals `1.0.0`, and `1.0.dev1 < 1.0a1 < 1.0 < 1.0+local < 1.0.post1`.
+#[derive(Debug, Clone)]
 pub struct Version {
-    parts: Vec<u32>,
-    pre: Option<String>,
-    post: Option<String>,
+    pub epoch: u64,
+    pub release: Vec<u64>,
+    pub pre: Option<(PreRelease, u64)>,
+    pub post: Option<u64>,
+    pub dev: Option<u64>,
+    pub local: Vec<LocalSegment>,
 }
 
 impl Version {
     pub fn parse(s: &str) -> Result<Self, String> {
-        let s = s.trim();
-        
-        // Split on pre-release markers
-        let (version_part, pre) = if let Some(idx) = s.find("a") {
-            let (v, p) = s.split_at(idx);
-            (v, Some(p.to_string()))
-        } else if let Some(idx) = s.find("b") {
-            let (v, p) = s.split_at(idx);
-            (v, Some(p.to_string()))
-        } else if let Some(idx) = s.find("rc") {
-            let (v, p) = s.split_at(idx);
-            (v, Some(p.to_string()))
-        } else {
-            (s, None)
-        };
+        Parser::new(s).version().ok_or_else(|| format!("Invalid version: '{}'", s.trim()))
+    }
 
-        let parts: Result<Vec<u32>, _> = version_part
-            .split('.')
-            .map(|p| p.parse::<u32>().map_err(|_| "Invalid version".to_string()))
-            .collect();
+    /// Pre-releases and dev releases, which resolution skips unless asked
+    pub fn is_prerelease(&self) -> bool {
+        self.pre.is_some() || self.dev.is_some()
+    }
 
-        Ok(Version {
-            parts: parts?,
-            pre,
+    pub fn is_postrelease(&self) -> bool {
+        self.post.is_some()
+    }
+
+    pub fn is_devrelease(&self) -> bool {
+        self.dev.is_some()
+    }
+
+    /// The version without its `+local` label
+    pub fn public(&self) -> Version {
+        Version {
+            local: Vec::new(),
+            ..self.clone()
+        }
+    }
+
+    /// Epoch and release only, e.g. `1!2.0` for `1!2.0rc1.post3+cpu`
+    pub fn base(&self) -> Version {
+        Version {
+            epoch: self.epoch,
+            release: self.release.clone(),
+            pre: None,
             post: None,
-        })
+            dev: None,
+            local: Vec::new(),
+        }
     }
 
-    #[allow(dead_code)]
-    pub fn compare(&self, other: &Version) -> Ordering {
-        for i in 0..self.parts.len().max(other.parts.len()) {
-            let a = self.parts.get(i).copied().unwrap_or(0);
-            let b = other.parts.get(i).copied().unwrap_or(0);
-            match a.cmp(&b) {
-                Ordering::Equal => continue,
-                other => return other,
+    /// Whether this version satisfies one specifier clause. `===` compares
+    /// the canonical form; [`satisfies`] compares the string as written.
+    pub fn satisfies(&self, spec: &VersionSpec) -> bool {
+        let spec_version = spec.version.trim();
+        if spec.op == VersionOp::ArbitraryEq {
+            return self.to_string().eq_ignore_ascii_case(spec_version);
+        }
+
+        if let Some(prefix) = spec_version.strip_suffix(".*") {
+            let Ok(prefix) = Version::parse(prefix) else {
+                return false;
+            };
+            let hit = self.epoch == prefix.epoch && self.release_starts_with(&prefix.release);
+            return match spec.op {
+                VersionOp::Eq => hit,
+                VersionOp::NotEq => !hit,
+                // Only == and != take a wildcard; anything else matches nothing
+                _ => false,
+            };
+        }
+
+        let Ok(bound) = Version::parse(spec_version) else {
+            return false;
+        };
+        // A local label on the candidate is ignored unless the spec has one
+        let candidate = if bound.local.is_empty() { self.public() } else { self.clone() };
+        let cmp = candidate.cmp(&bound);
+        match spec.op {
+            VersionOp::Eq => cmp == Ordering::Equal,
+            VersionOp::NotEq => cmp != Ordering::Equal,
+            VersionOp::LtEq => cmp != Ordering::Greater,
+            VersionOp::GtEq => cmp != Ordering::Less,
+            // <V excludes pre-releases of V itself, unless V is a pre-release
+            VersionOp::Lt => {
+                cmp == Ordering::Less
+                    && !(candidate.is_prerelease() && !bound.is_prerelease() && candidate.base() == bound.base())
             }
+            // >V excludes post-releases of V itself, unless V is a post-release
+            VersionOp::Gt => {
+                cmp == Ordering::Greater
+                    && !(candidate.is_postrelease() && !bound.is_postrelease() && candidate.base() == bound.base())
+            }
+            // ~=2.2 means >=2.2, ==2.*; a single release segment is invalid
+            VersionOp::Compatible => {
+                bound.release.len() >= 2
+                    && cmp != Ordering::Less
+                    && candidate.epoch == bound.epoch
+                    && candidate.release_starts_with(&bound.release[..bound.release.len() - 1])
+            }
+            VersionOp::ArbitraryEq => unreachable!("handled above"),
         }
-        Ordering::Equal
+    }
+
+    /// Whether the release segment, zero-padded, starts with `prefix`
+    fn release_starts_with(&self, prefix: &[u64]) -> bool {
+        prefix
+            .iter()
+            .enumerate()
+            .all(|(i, part)| self.release.get(i).copied().unwrap_or(0) == *part)
+    }
+
+    /// Where the pre-release segment sorts: a bare dev release sorts before
+    /// every pre-release of its release, a final release after all of them
+    fn pre_key(&self) -> (u8, Option<(PreRelease, u64)>) {
+        match (self.pre, self.post, self.dev) {
+            (None, None, Some(_)) => (0, None),
+            (Some(pre), _, _) => (1, Some(pre)),
+            (None, _, _) => (2, None),
+        }
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let release = (0..self.release.len().max(other.release.len()))
+            .map(|i| {
+                let a = self.release.get(i).copied().unwrap_or(0);
+                a.cmp(&other.release.get(i).copied().unwrap_or(0))
+            })
+            .find(|cmp| cmp.is_ne())
+            .unwrap_or(Ordering::Equal);
+        self.epoch
+            .cmp(&other.epoch)
+            .then(release)
+            .then_with(|| self.pre_key().cmp(&other.pre_key()))
+            // No post release sorts first, no dev release last
+            .then_with(|| self.post.cmp(&other.post))
+            .then_with(|| (self.dev.is_none(), self.dev).cmp(&(other.dev.is_none(), other.dev)))
+            .then_with(|| self.local.cmp(&other.local))
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
     }
 }
 
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Version {}
+
 impl std::str::FromStr for Version {
     type Err = String;
 
@@ -60,20 +208,413 @@ impl std::str::FromStr for Version {
     }
 }
 
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.epoch != 0 {
+            write!(f, "{}!", self.epoch)?;
+        }
+        let release: Vec<String> = self.release.iter().map(u64::to_string).collect();
+        f.write_str(&release.join("."))?;
+        if let Some((phase, n)) = self.pre {
+            let phase = match phase {
+                PreRelease::Alpha => "a",
+                PreRelease::Beta => "b",
+                PreRelease::Rc => "rc",
+            };
+            write!(f, "{}{}", phase, n)?;
+        }
+        if let Some(post) = self.post {
+            write!(f, ".post{}", post)?;
+        }
+        if let Some(dev) = self.dev {
+            write!(f, ".dev{}", dev)?;
+        }
+        if !self.local.is_empty() {
+            let local: Vec<String> = self
+                .local
+                .iter()
+                .map(|segment| match segment {
+                    LocalSegment::Text(text) => text.clone(),
+                    LocalSegment::Number(n) => n.to_string(),
+                })
+                .collect();
+            write!(f, "+{}", local.join("."))?;
+        }
+        Ok(())
+    }
+}
+
+/// Order two version strings. Valid versions compare by PEP 440; if
+/// either isn't one, the strings are compared as written, which is also
+/// what PEP 508 prescribes for markers.
+pub fn compare(a: &str, b: &str) -> Ordering {
+    match (Version::parse(a), Version::parse(b)) {
+        (Ok(a), Ok(b)) => a.cmp(&b),
+        _ => a.trim().cmp(b.trim()),
+    }
+}
+
+/// Whether a version string is a pre- or dev release; strings that aren't
+/// versions aren't
+pub fn is_prerelease(version: &str) -> bool {
+    Version::parse(version).is_ok_and(|v| v.is_prerelease())
+}
+
+/// Whether a version string satisfies one specifier clause. `===` is a
+/// string comparison, so it also matches versions that aren't PEP 440;
+/// every other operator needs a valid version.
+pub fn satisfies(version: &str, spec: &VersionSpec) -> bool {
+    if spec.op == VersionOp::ArbitraryEq {
+        return version.trim().eq_ignore_ascii_case(spec.version.trim());
+    }
+    Version::parse(version).is_ok_and(|v| v.satisfies(spec))
+}
+
+/// Whether a version string satisfies every clause of a specifier set;
+/// an empty set matches anything
+pub fn satisfies_all(version: &str, specs: &[VersionSpec]) -> bool {
+    specs.iter().all(|spec| satisfies(version, spec))
+}
+
+/// The normalized spelling of a version (`1.0-RC1` -> `1.0rc1`), or the
+/// trimmed input when it isn't a PEP 440 version
+pub fn canonicalize(version: &str) -> String {
+    match Version::parse(version) {
+        Ok(v) => v.to_string(),
+        Err(_) => version.trim().to_string(),
+    }
+}
+
+/// Recursive-descent parser over the lowercased version string, following
+/// the grammar of the regular expression in PEP 440's appendix
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn new(s: &str) -> Self {
+        Self {
+            chars: s.trim().to_ascii_lowercase().chars().collect(),
+            pos: 0,
+        }
+    }
+
+    fn version(mut self) -> Option<Version> {
+        self.eat("v");
+        let first = self.number()?;
+        let (epoch, first) = if self.eat("!") { (first, self.number()?) } else { (0, first) };
+        let mut release = vec![first];
+        while self.peek() == Some('.') && self.chars.get(self.pos + 1).is_some_and(char::is_ascii_digit) {
+            self.pos += 1;
+            release.push(self.number()?);
+        }
+
+        let pre = self.labelled(&[
+            ("alpha", PreRelease::Alpha),
+            ("a", PreRelease::Alpha),
+            ("beta", PreRelease::Beta),
+            ("b", PreRelease::Beta),
+            ("preview", PreRelease::Rc),
+            ("pre", PreRelease::Rc),
+            ("rc", PreRelease::Rc),
+            ("c", PreRelease::Rc),
+        ]);
+        // `1.0-1` is an implicit post release
+        let start = self.pos;
+        let post = match (self.eat("-"), self.number()) {
+            (true, Some(n)) => Some(n),
+            _ => {
+                self.pos = start;
+                self.labelled(&[("post", ()), ("rev", ()), ("r", ())]).map(|((), n)| n)
+            }
+        };
+        let dev = self.labelled(&[("dev", ())]).map(|((), n)| n);
+
+        let mut local = Vec::new();
+        if self.eat("+") {
+            loop {
+                let start = self.pos;
+                while self.peek().is_some_and(|c| c.is_ascii_alphanumeric()) {
+                    self.pos += 1;
+                }
+                if start == self.pos {
+                    return None;
+                }
+                let segment: String = self.chars[start..self.pos].iter().collect();
+                local.push(match segment.parse() {
+                    Ok(n) if segment.bytes().all(|b| b.is_ascii_digit()) => LocalSegment::Number(n),
+                    _ => LocalSegment::Text(segment),
+                });
+                if !self.separator() {
+                    break;
+                }
+            }
+        }
+
+        (self.pos == self.chars.len()).then_some(Version {
+            epoch,
+            release,
+            pre,
+            post,
+            dev,
+            local,
+        })
+    }
+
+    /// `[-_.]? label [-_.]? N?`, where a missing number means 0; leaves the
+    /// position untouched when no label matches
+    fn labelled<T: Copy>(&mut self, labels: &[(&str, T)]) -> Option<(T, u64)> {
+        let start = self.pos;
+        self.separator();
+        let Some(&(_, value)) = labels.iter().find(|(label, _)| self.eat(label)) else {
+            self.pos = start;
+            return None;
+        };
+        let before_number = self.pos;
+        self.separator();
+        match self.number() {
+            Some(n) => Some((value, n)),
+            None => {
+                self.pos = before_number;
+                Some((value, 0))
+            }
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, literal: &str) -> bool {
+        let len = literal.chars().count();
+        let matches = self.chars.len() >= self.pos + len
+            && self.chars[self.pos..self.pos + len].iter().copied().eq(literal.chars());
+        if matches {
+            self.pos += len;
+        }
+        matches
+    }
+
+    fn separator(&mut self) -> bool {
+        let found = matches!(self.peek(), Some('.' | '-' | '_'));
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn number(&mut self) -> Option<u64> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        let digits: String = self.chars[start..self.pos].iter().collect();
+        match digits.parse() {
+            Ok(n) => Some(n),
+            Err(_) => {
+                self.pos = start;
+                None
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// packaging's ordering vectors (`tests/test_version.py`), ascending
+    const ORDERED: &[&str] = &[
+        "1.0.dev456", "1.0a1", "1.0a2.dev456", "1.0a12.dev456", "1.0a12", "1.0b1.dev456", "1.0b2",
+        "1.0b2.post345.dev456", "1.0b2.post345", "1.0b2-346", "1.0c1.dev456", "1.0c1", "1.0rc2", "1.0c3",
+        "1.0", "1.0.post456.dev34", "1.0.post456", "1.1.dev1", "1.2+123abc", "1.2+123abc456", "1.2+abc",
+        "1.2+abc123", "1.2+abc123def", "1.2+1234.abc", "1.2+123456", "1.2.r32+123456", "1.2.rev33+123456",
+        "1!1.0.dev456", "1!1.0a1", "1!1.0a2.dev456", "1!1.0a12.dev456", "1!1.0a12", "1!1.0b1.dev456",
+        "1!1.0b2", "1!1.0b2.post345.dev456", "1!1.0b2.post345", "1!1.0b2-346", "1!1.0c1.dev456", "1!1.0c1",
+        "1!1.0rc2", "1!1.0c3", "1!1.0", "1!1.0.post456.dev34", "1!1.0.post456", "1!1.1.dev1",
+        "1!1.2+123abc", "1!1.2+123abc456", "1!1.2+abc", "1!1.2+abc123", "1!1.2+abc123def",
+        "1!1.2+1234.abc", "1!1.2+123456", "1!1.2.r32+123456", "1!1.2.rev33+123456",
+    ];
+
     #[test]
-    fn test_version_parse() {
-        let v = Version::parse("1.2.3").unwrap();
-        assert_eq!(v.parts, vec![1, 2, 3]);
+    fn test_ordering_matches_packaging() {
+        let versions: Vec<Version> = ORDERED.iter().map(|v| Version::parse(v).unwrap()).collect();
+        for (i, a) in versions.iter().enumerate() {
+            for (j, b) in versions.iter().enumerate() {
+                assert_eq!(a.cmp(b), i.cmp(&j), "{} vs {}", ORDERED[i], ORDERED[j]);
+            }
+        }
     }
 
     #[test]
-    fn test_version_compare() {
-        let v1 = Version::parse("1.2.3").unwrap();
-        let v2 = Version::parse("1.2.4").unwrap();
-        assert_eq!(v1.compare(&v2), Ordering::Less);
+    fn test_ordering_is_total_and_consistent() {
+        // Every pair and triple of a mixed set: antisymmetric, transitive,
+        // and equal exactly when the canonical forms are
+        let mut versions: Vec<Version> = ORDERED
+            .iter()
+            .chain(&["1.0.0", "1.0.0.0", "01.0", "1.0-1", "1.0.post1", "1.0+ABC", "1.0+abc", "v1.0", "0!1.0"])
+            .map(|v| Version::parse(v).unwrap())
+            .collect();
+        for a in &versions {
+            for b in &versions {
+                assert_eq!(a.cmp(b), b.cmp(a).reverse());
+                assert_eq!(a == b, a.cmp(b) == Ordering::Equal);
+                if a == b {
+                    assert_eq!(Version::parse(&a.to_string()).unwrap(), Version::parse(&b.to_string()).unwrap());
+                }
+                for c in &versions {
+                    if a <= b && b <= c {
+                        assert!(a <= c, "{} <= {} <= {}", a, b, c);
+                    }
+                }
+            }
+        }
+        // Sorting is stable under reparsing the canonical form
+        versions.sort();
+        let reparsed: Vec<Version> = versions.iter().map(|v| Version::parse(&v.to_string()).unwrap()).collect();
+        assert!(reparsed.windows(2).all(|w| w[0] <= w[1]));
+    }
+
+    #[test]
+    fn test_normalization() {
+        for (input, canonical) in [
+            ("1.0", "1.0"),
+            ("v1.0", "1.0"),
+            ("  1.0\n", "1.0"),
+            ("01.02", "1.2"),
+            ("1.0a", "1.0a0"),
+            ("1.0alpha1", "1.0a1"),
+            ("1.0-a-1", "1.0a1"),
+            ("1.0_b_2", "1.0b2"),
+            ("1.0.beta.3", "1.0b3"),
+            ("1.0c1", "1.0rc1"),
+            ("1.0.0-C1", "1.0.0rc1"),
+            ("1.0PREVIEW2", "1.0rc2"),
+            ("1.0pre", "1.0rc0"),
+            ("1.0-post", "1.0.post0"),
+            ("1.0post1", "1.0.post1"),
+            ("1.0r", "1.0.post0"),
+            ("1.0-rev4", "1.0.post4"),
+            ("1.0-1", "1.0.post1"),
+            ("1.0-dev", "1.0.dev0"),
+            ("1.0dev2", "1.0.dev2"),
+            ("1.0+ubuntu-1", "1.0+ubuntu.1"),
+            ("1.0+Ubuntu_1", "1.0+ubuntu.1"),
+            ("0!1.0", "1.0"),
+            ("2!1.0rc1.post2.dev3+local", "2!1.0rc1.post2.dev3+local"),
+        ] {
+            assert_eq!(canonicalize(input), canonical, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_invalid_versions() {
+        for invalid in [
+            "", "french toast", "1.0+", "1.0+a+", "1.0++", "1.0+_foobar", "1.0+foo&asd", "1.0+1+1", "1.", ".1",
+            "1.0.dev1.post1", "1!",
+        ] {
+            assert!(Version::parse(invalid).is_err(), "{:?}", invalid);
+        }
+        assert_eq!(canonicalize("foobar"), "foobar");
+    }
+
+    #[test]
+    fn test_string_helpers() {
+        assert_eq!(compare("1.9.9", "1.10.0"), Ordering::Less);
+        assert_eq!(compare("1.0", "1.0.0"), Ordering::Equal);
+        // Not versions: compared as strings, like PEP 508 markers
+        assert_eq!(compare("5.15.0-azure", "5.4.0-azure"), Ordering::Less);
+
+        assert!(is_prerelease("1.0.dev3") && is_prerelease("2.0RC1") && is_prerelease("1.0a1.post1"));
+        assert!(!is_prerelease("1.0.post2+local.a") && !is_prerelease("foobar"));
+
+        let spec = |op, version: &str| VersionSpec { op, version: version.to_string() };
+        assert!(satisfies("foobar", &spec(VersionOp::ArbitraryEq, "FooBar")));
+        assert!(!satisfies("foobar", &spec(VersionOp::Eq, "foobar")));
+        assert!(satisfies("1.0-1", &spec(VersionOp::Eq, "1.0.post1")));
+        assert!(Version::parse("1.0").unwrap().satisfies(&spec(VersionOp::ArbitraryEq, "1.0")));
+        let range = [spec(VersionOp::GtEq, "2.0"), spec(VersionOp::Lt, "3")];
+        assert!(satisfies_all("2.31.0", &range) && !satisfies_all("3.0", &range) && satisfies_all("0.1", &[]));
+    }
+
+    /// (version, specifier, what pip's `SpecifierSet(spec).contains(version,
+    /// prereleases=True)` answers)
+    const CASES: &[(&str, &str, bool)] = &[
+        // Final releases and zero padding
+        ("1.0", "==1.0.0", true),
+        ("1.0.0", ">1.0", false),
+        ("1.10", ">1.9", true),
+        // Pre-releases
+        ("1.0rc1", "<1.0", false),
+        ("1.0rc1", "<=1.0", true),
+        ("1.0rc1", ">=1.0", false),
+        ("0.9", "<1.0", true),
+        ("1.0a1", "<1.0b1", true),
+        ("1.0b2", ">1.0b1", true),
+        ("1.0rc1", "==1.0rc1", true),
+        ("1.0.0rc1", ">0.9", true),
+        // Post-releases
+        ("2.0.post1", ">2.0", false),
+        ("2.0.post1", ">=2.0", true),
+        ("2.0.post2", ">2.0.post1", true),
+        ("2.0.post1", "==2.0", false),
+        ("2.1", ">2.0", true),
+        // Dev releases sort before pre-releases
+        ("1.0.dev3", "<1.0a1", true),
+        ("1.0.dev3", "<1.0", false),
+        ("1.0.dev3", ">=1.0.dev1", true),
+        // Local versions
+        ("1.0+cpu", "==1.0", true),
+        ("1.0+cpu", "==1.0+cpu", true),
+        ("1.0+cpu", "==1.0+gpu", false),
+        ("1.0+cpu", ">1.0", false),
+        ("1.0+cpu", "<=1.0", true),
+        // Epochs outrank everything else
+        ("1!1.0", ">2.0", true),
+        ("1!1.0", "<2.0", false),
+        ("2.0", "<1!1.0", true),
+        ("1!2.0", "==2.0", false),
+        ("1!1.4.2", "==1!1.4.*", true),
+        ("1.4.2", "==1!1.4.*", false),
+        // Compatible release
+        ("2.3", "~=2.2", true),
+        ("3.0", "~=2.2", false),
+        ("2.2.1", "~=2.2.0", true),
+        ("2.3.0", "~=2.2.0", false),
+        ("2.2.post3", "~=2.2", true),
+        ("1.4.5", "~=1.4.5a4", true),
+        ("1.5.0", "~=1.4.5a4", false),
+        // Prefix matching
+        ("1.4.5", "==1.4.*", true),
+        ("1.4", "==1.4.*", true),
+        ("1.4rc1", "==1.4.*", true),
+        ("1.40", "==1.4.*", false),
+        ("1.5.0", "!=1.4.*", true),
+        ("1.4.0.post1", "!=1.4.*", false),
+        ("1.4.0", ">=1.4.*", false),
+        // Arbitrary equality is a string comparison
+        ("1.0", "===1.0", true),
+        ("1.0.0", "===1.0", false),
+        ("foobar", "===FooBar", true),
+    ];
+
+    #[test]
+    fn test_specifiers_match_pip() {
+        for &(version, spec, expected) in CASES {
+            let spec: crate::models::Requirement = format!("pkg{}", spec).parse().unwrap();
+            assert_eq!(satisfies(version, &spec.specs[0]), expected, "{} in {}", version, spec.specs[0]);
+        }
+    }
+
+    #[test]
+    fn test_version_parse() {
+        let v = Version::parse("1!2.3.4rc5.post6.dev7+ab.8").unwrap();
+        assert_eq!(v.epoch, 1);
+        assert_eq!(v.release, vec![2, 3, 4]);
+        assert_eq!(v.pre, Some((PreRelease::Rc, 5)));
+        assert_eq!((v.post, v.dev), (Some(6), Some(7)));
+        assert_eq!(v.local, vec![LocalSegment::Text("ab".to_string()), LocalSegment::Number(8)]);
+        assert_eq!(v.base().to_string(), "1!2.3.4");
     }
 }
@@ -0,0 +1,262 @@
+/// User-facing warnings collected over a command run
+///
+/// Warnings print inline when emitted (unless --quiet) and are also
+/// accumulated, deduplicated, and re-printed in a "Warnings (N):" block at the
+/// end of the command so they don't get lost in progress output.
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::sync::Mutex;
+
+/// Maximum number of distinct warnings kept; further ones are only counted
+const MAX_DISTINCT_WARNINGS: usize = 50;
+
+/// Stable warning codes (used in JSON output)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WarningCode {
+    /// A declared override never matched a dependency edge
+    UnusedOverride,
+    /// A requirement or constraint line could not be parsed and was ignored
+    InvalidRequirement,
+    /// A package was left out of the resolution
+    PackageSkipped,
+    /// The outdated scan hit its time limit
+    ScanTimeout,
+    /// Dependencies were deliberately not installed (--no-deps)
+    DependenciesSkipped,
+    /// An option was recognized but isn't supported (or enforced) yet
+    UnsupportedOption,
+    /// An uninstall removes import names other installed packages rely on
+    ImportNameInUse,
+    /// The pre-flight scan found damaged dist-info directories
+    DegradedEnvironment,
+    /// An editable install was left in place instead of being replaced
+    EditableKept,
+    /// A deprecated option was used
+    DeprecatedOption,
+    /// The `--progress-socket` consumer went away mid-run
+    ProgressSocketClosed,
+    /// A broken artifact was replaced by another file of the same release
+    ArtifactFallback,
+    /// A selected release declares itself deprecated or unmaintained
+    DeprecatedPackage,
+    /// A simple-API-only index doesn't serve a release's core metadata
+    MetadataUnavailable,
+    /// A lock made for another Python or platform is installed anyway
+    EnvironmentMismatch,
+    /// A requested extra the package doesn't declare
+    UnknownExtra,
+    /// Requests were slowed down by the rate limit or an index's 429s
+    RateLimited,
+    /// A connection to a `--trusted-host` skipped certificate verification
+    InsecureConnection,
+    /// Scripts were installed to a directory that isn't on PATH
+    ScriptsNotOnPath,
+    /// A yanked release was installed because the requirement pins it
+    YankedRelease,
+}
+
+impl WarningCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WarningCode::UnusedOverride => "unused-override",
+            WarningCode::InvalidRequirement => "invalid-requirement",
+            WarningCode::PackageSkipped => "package-skipped",
+            WarningCode::ScanTimeout => "scan-timeout",
+            WarningCode::DependenciesSkipped => "dependencies-skipped",
+            WarningCode::UnsupportedOption => "unsupported-option",
+            WarningCode::ImportNameInUse => "import-name-in-use",
+            WarningCode::DegradedEnvironment => "degraded-environment",
+            WarningCode::EditableKept => "editable-kept",
+            WarningCode::DeprecatedOption => "deprecated-option",
+            WarningCode::ProgressSocketClosed => "progress-socket-closed",
+            WarningCode::ArtifactFallback => "artifact-fallback",
+            WarningCode::DeprecatedPackage => "deprecated-package",
+            WarningCode::MetadataUnavailable => "metadata-unavailable",
+            WarningCode::EnvironmentMismatch => "environment-mismatch",
+            WarningCode::UnknownExtra => "unknown-extra",
+            WarningCode::RateLimited => "rate-limited",
+            WarningCode::InsecureConnection => "insecure-connection",
+            WarningCode::ScriptsNotOnPath => "scripts-not-on-path",
+            WarningCode::YankedRelease => "yanked-release",
+        }
+    }
+}
+
+/// A deduplicated warning with its occurrence count
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UserWarning {
+    pub code: WarningCode,
+    pub message: String,
+    pub count: usize,
+}
+
+/// Accumulates warnings in first-seen order
+#[derive(Debug, Default)]
+pub struct WarningCollector {
+    warnings: Vec<UserWarning>,
+    suppressed: usize,
+}
+
+impl WarningCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a warning. Returns true the first time this warning is seen
+    /// (i.e. when it should be printed inline).
+    pub fn record(&mut self, code: WarningCode, message: &str) -> bool {
+        if let Some(existing) = self
+            .warnings
+            .iter_mut()
+            .find(|w| w.code == code && w.message == message)
+        {
+            existing.count += 1;
+            return false;
+        }
+
+        if self.warnings.len() >= MAX_DISTINCT_WARNINGS {
+            self.suppressed += 1;
+            return false;
+        }
+
+        self.warnings.push(UserWarning {
+            code,
+            message: message.to_string(),
+            count: 1,
+        });
+        true
+    }
+
+    pub fn warnings(&self) -> &[UserWarning] {
+        &self.warnings
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.warnings.is_empty() && self.suppressed == 0
+    }
+
+    /// The consolidated block printed at the end of a command
+    pub fn summary_block(&self) -> Option<String> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let total: usize = self.warnings.iter().map(|w| w.count).sum::<usize>() + self.suppressed;
+        let mut block = format!("Warnings ({}):", total);
+        for warning in &self.warnings {
+            block.push_str(&format!("\n  - {}", warning.message));
+            if warning.count > 1 {
+                block.push_str(&format!(" (x{})", warning.count));
+            }
+        }
+        if self.suppressed > 0 {
+            block.push_str(&format!("\n  ... and {} more", self.suppressed));
+        }
+        Some(block)
+    }
+
+    /// Warnings as a JSON array for machine-readable reports
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(&self.warnings).unwrap_or_else(|_| serde_json::json!([]))
+    }
+}
+
+static COLLECTOR: Lazy<Mutex<WarningCollector>> = Lazy::new(|| Mutex::new(WarningCollector::new()));
+
+/// Emit a user-facing warning: printed inline (unless quiet) and collected
+/// for the end-of-command summary
+pub fn warn_user(code: WarningCode, message: impl AsRef<str>) {
+    let message = message.as_ref();
+    tracing::debug!("warning [{}]: {}", code.as_str(), message);
+
+    let first = COLLECTOR
+        .lock()
+        .map(|mut collector| collector.record(code, message))
+        .unwrap_or(true);
+    if first && !super::progress::is_quiet() {
+        eprintln!("WARNING: {}", message);
+    }
+    crate::installer::events::emit(crate::installer::events::InstallEvent::Warning {
+        code,
+        message: message.to_string(),
+    });
+}
+
+/// Print the consolidated warnings block (no-op when there were none)
+pub fn print_summary() {
+    if super::progress::is_quiet() {
+        return;
+    }
+    if let Ok(collector) = COLLECTOR.lock()
+        && let Some(block) = collector.summary_block()
+    {
+        eprintln!("\n{}", block);
+    }
+}
+
+/// Warnings collected so far
+pub fn collected() -> Vec<UserWarning> {
+    COLLECTOR
+        .lock()
+        .map(|collector| collector.warnings().to_vec())
+        .unwrap_or_default()
+}
+
+/// Warnings collected so far, as a JSON array
+pub fn warnings_json() -> serde_json::Value {
+    COLLECTOR
+        .lock()
+        .map(|collector| collector.to_json())
+        .unwrap_or_else(|_| serde_json::json!([]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dedup_counts_and_block_order() {
+        let mut collector = WarningCollector::new();
+        // Simulated pipeline: resolution, then lock generation, then resolution again
+        assert!(collector.record(WarningCode::PackageSkipped, "Skipping foo: HTTP 404"));
+        assert!(collector.record(WarningCode::UnusedOverride, "Override six>=1.16 never matched"));
+        assert!(!collector.record(WarningCode::PackageSkipped, "Skipping foo: HTTP 404"));
+        assert!(collector.record(WarningCode::InvalidRequirement, "Ignoring bad line 'x=1'"));
+        assert!(!collector.record(WarningCode::PackageSkipped, "Skipping foo: HTTP 404"));
+
+        assert_eq!(collector.warnings().len(), 3);
+        assert_eq!(collector.warnings()[0].count, 3);
+
+        let block = collector.summary_block().unwrap();
+        assert_eq!(
+            block,
+            "Warnings (5):\n  - Skipping foo: HTTP 404 (x3)\n  - Override six>=1.16 never matched\n  - Ignoring bad line 'x=1'"
+        );
+    }
+
+    #[test]
+    fn test_json_array() {
+        let mut collector = WarningCollector::new();
+        assert_eq!(collector.to_json(), serde_json::json!([]));
+        collector.record(WarningCode::UnusedOverride, "Override six>=1.16 never matched");
+        collector.record(WarningCode::UnusedOverride, "Override six>=1.16 never matched");
+
+        assert_eq!(
+            collector.to_json(),
+            serde_json::json!([
+                {"code": "unused-override", "message": "Override six>=1.16 never matched", "count": 2}
+            ])
+        );
+    }
+
+    #[test]
+    fn test_cap_limits_distinct_warnings() {
+        let mut collector = WarningCollector::new();
+        for i in 0..(MAX_DISTINCT_WARNINGS + 5) {
+            collector.record(WarningCode::PackageSkipped, &format!("Skipping pkg{}", i));
+        }
+        assert_eq!(collector.warnings().len(), MAX_DISTINCT_WARNINGS);
+        assert!(collector.summary_block().unwrap().ends_with("... and 5 more"));
+    }
+}
@@ -0,0 +1,142 @@
+/// Activation scripts installed into a virtual environment
+///
+/// Each shell gets its own script from `data/activate/`: `activate` for
+/// bash and zsh, `activate.fish`, `Activate.ps1` for PowerShell, and on
+/// Windows `activate.bat` and `deactivate.bat` for cmd.exe. Activating puts
+/// the environment's bin directory first on PATH, sets `VIRTUAL_ENV`,
+/// prefixes the prompt with the environment's name and defines a
+/// `deactivate` that undoes all of it. Paths are substituted quoted for
+/// the shell reading them, so an environment under a directory with spaces
+/// or quotes in its name activates correctly.
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const BASH_TEMPLATE: &str = include_str!("../../data/activate/activate");
+const FISH_TEMPLATE: &str = include_str!("../../data/activate/activate.fish");
+const POWERSHELL_TEMPLATE: &str = include_str!("../../data/activate/Activate.ps1");
+const BATCH_TEMPLATE: &str = include_str!("../../data/activate/activate.bat");
+const DEACTIVATE_BATCH_TEMPLATE: &str = include_str!("../../data/activate/deactivate.bat");
+
+pub struct ActivationScript {
+    venv_path: PathBuf,
+}
+
+impl ActivationScript {
+    pub fn new(venv_path: PathBuf) -> Self {
+        Self { venv_path }
+    }
+
+    /// `activate`, for bash and zsh
+    pub fn generate_bash(&self) -> String {
+        self.render(BASH_TEMPLATE, sh_quote)
+    }
+
+    /// `activate.fish`
+    pub fn generate_fish(&self) -> String {
+        self.render(FISH_TEMPLATE, fish_quote)
+    }
+
+    /// `Activate.ps1`
+    pub fn generate_powershell(&self) -> String {
+        self.render(POWERSHELL_TEMPLATE, powershell_quote)
+    }
+
+    /// `activate.bat`, for cmd.exe
+    pub fn generate_batch(&self) -> String {
+        self.render(BATCH_TEMPLATE, batch_escape)
+    }
+
+    /// `deactivate.bat`, for cmd.exe
+    pub fn generate_batch_deactivate(&self) -> String {
+        self.render(DEACTIVATE_BATCH_TEMPLATE, batch_escape)
+    }
+
+    /// Write the scripts for this platform's shells into `bin_dir`,
+    /// returning the files written
+    pub fn install(&self, bin_dir: &Path) -> Result<Vec<PathBuf>> {
+        let mut scripts = vec![
+            ("activate", self.generate_bash()),
+            ("activate.fish", self.generate_fish()),
+            ("Activate.ps1", self.generate_powershell()),
+        ];
+        if cfg!(target_os = "windows") {
+            scripts.push(("activate.bat", self.generate_batch()));
+            scripts.push(("deactivate.bat", self.generate_batch_deactivate()));
+        }
+
+        fs::create_dir_all(bin_dir)?;
+        let mut written = Vec::new();
+        for (name, content) in scripts {
+            let path = bin_dir.join(name);
+            fs::write(&path, content)?;
+            written.push(path);
+        }
+        Ok(written)
+    }
+
+    /// The prompt prefix: the environment directory's name
+    fn prompt(&self) -> String {
+        self.venv_path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "venv".to_string())
+    }
+
+    fn render(&self, template: &str, quote: fn(&str) -> String) -> String {
+        template
+            .replace("{{virtual_env}}", &quote(&self.venv_path.to_string_lossy()))
+            .replace("{{prompt}}", &quote(&self.prompt()))
+            .replace("{{bin_name}}", bin_name())
+    }
+}
+
+/// Name of the scripts directory of an environment on this platform
+pub fn bin_name() -> &'static str {
+    if cfg!(target_os = "windows") { "Scripts" } else { "bin" }
+}
+
+/// Single-quoted for `sh`
+fn sh_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Single-quoted for fish, where `\` escapes inside single quotes too
+fn fish_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+/// Single-quoted for PowerShell
+fn powershell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// For the inside of a cmd.exe `set "NAME=value"`
+fn batch_escape(value: &str) -> String {
+    value.replace('%', "%%")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scripts_quote_the_environment_path() {
+        let scripts = ActivationScript::new(PathBuf::from("/home/me/it's here/.venv"));
+
+        let bash = scripts.generate_bash();
+        assert!(bash.contains("VIRTUAL_ENV='/home/me/it'\\''s here/.venv'\n"));
+        assert!(bash.contains(&format!("PATH=\"$VIRTUAL_ENV/{}:$PATH\"", bin_name())));
+        assert!(bash.contains("VIRTUAL_ENV_PROMPT='.venv'\n"));
+        assert!(bash.contains("deactivate nondestructive"));
+
+        let fish = scripts.generate_fish();
+        assert!(fish.contains("set -gx VIRTUAL_ENV '/home/me/it\\'s here/.venv'\n"));
+
+        let powershell = scripts.generate_powershell();
+        assert!(powershell.contains("$env:VIRTUAL_ENV = '/home/me/it''s here/.venv'\n"));
+
+        assert!(!bash.contains("{{") && !fish.contains("{{") && !powershell.contains("{{"));
+        assert!(!scripts.generate_batch().contains("{{") && !scripts.generate_batch_deactivate().contains("{{"));
+    }
+}
@@ -0,0 +1,281 @@
+/// A virtual environment on disk
+///
+/// The layout matches what `python -m venv` creates, so the interpreter
+/// recognises it and pip-rs' own environment detection finds it:
+///
+/// ```text
+/// <env>/pyvenv.cfg
+/// <env>/bin/{python, python3, python3.X, activate, ...}      (Unix)
+/// <env>/lib/python3.X/site-packages/
+/// <env>/Scripts/{python.exe, pythonw.exe, activate.bat, ...} (Windows)
+/// <env>/Lib/site-packages/
+/// ```
+///
+/// On Unix the interpreter is symlinked into `bin`; on Windows, where
+/// symlinks need privileges, it is copied together with the DLLs beside it.
+/// `pyvenv.cfg` records the base interpreter's directory as `home`, which
+/// is how Python finds its standard library from inside the environment.
+use super::activation::{self, ActivationScript};
+use super::interpreter::Interpreter;
+use anyhow::{Result, anyhow};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const CONFIG_FILE: &str = "pyvenv.cfg";
+
+pub struct VirtualEnvironment {
+    path: PathBuf,
+    python_version: String,
+    site_packages: PathBuf,
+    bin: PathBuf,
+}
+
+impl VirtualEnvironment {
+    /// An environment at `path` for Python `python_version` (`X.Y` or
+    /// `X.Y.Z`); nothing is created until [`create`](Self::create)
+    pub fn new(path: PathBuf, python_version: String) -> Self {
+        let bin = path.join(activation::bin_name());
+        let site_packages = site_packages_for(&path, &python_version);
+        Self {
+            path,
+            python_version,
+            site_packages,
+            bin,
+        }
+    }
+
+    /// The existing environment at `path`, described by its `pyvenv.cfg`
+    pub fn open(path: &Path) -> Result<Self> {
+        let config = read_config(&path.join(CONFIG_FILE))
+            .map_err(|_| anyhow!("{} is not a virtual environment (no {})", path.display(), CONFIG_FILE))?;
+        // `version` from venv, `version_info` from virtualenv and uv
+        let python_version = config
+            .get("version")
+            .or_else(|| config.get("version_info"))
+            .cloned()
+            .unwrap_or_default();
+        let mut env = Self::new(path.to_path_buf(), python_version);
+        if let Some(existing) = existing_site_packages(path) {
+            env.site_packages = existing;
+        }
+        Ok(env)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn python_version(&self) -> &str {
+        &self.python_version
+    }
+
+    pub fn get_site_packages_path(&self) -> &Path {
+        &self.site_packages
+    }
+
+    pub fn get_bin_path(&self) -> &Path {
+        &self.bin
+    }
+
+    /// The environment's `python`
+    pub fn get_python_path(&self) -> PathBuf {
+        let name = if cfg!(target_os = "windows") { "python.exe" } else { "python" };
+        self.bin.join(name)
+    }
+
+    /// Create the environment with the interpreter for its Python version
+    /// found on PATH; without one only the directory layout, `pyvenv.cfg`
+    /// and activation scripts are written
+    pub fn create(&self) -> Result<()> {
+        let interpreter = Interpreter::find(Some(&self.python_version)).ok();
+        self.create_with(interpreter.as_ref())
+    }
+
+    /// Create the environment around `interpreter`. An existing environment
+    /// at the path is updated in place; any other non-empty directory is an
+    /// error.
+    pub fn create_with(&self, interpreter: Option<&Interpreter>) -> Result<()> {
+        if self.path.is_file() {
+            return Err(anyhow!("{} already exists and is not a directory", self.path.display()));
+        }
+        let occupied = fs::read_dir(&self.path).is_ok_and(|mut entries| entries.next().is_some());
+        if occupied && !self.path.join(CONFIG_FILE).is_file() {
+            return Err(anyhow!("{} already exists and is not a virtual environment", self.path.display()));
+        }
+
+        fs::create_dir_all(&self.bin)?;
+        fs::create_dir_all(&self.site_packages)?;
+        let include = if cfg!(target_os = "windows") { "Include" } else { "include" };
+        fs::create_dir_all(self.path.join(include))?;
+        // 64-bit Linux interpreters may look in lib64
+        #[cfg(all(target_os = "linux", target_pointer_width = "64"))]
+        {
+            let lib64 = self.path.join("lib64");
+            if fs::symlink_metadata(&lib64).is_err() {
+                std::os::unix::fs::symlink("lib", &lib64)?;
+            }
+        }
+
+        // Without an interpreter an existing pyvenv.cfg is the better record
+        let config = self.path.join(CONFIG_FILE);
+        if let Some(interpreter) = interpreter {
+            self.link_interpreter(interpreter)?;
+            fs::write(&config, self.render_config(Some(interpreter)))?;
+        } else if !config.is_file() {
+            fs::write(&config, self.render_config(None))?;
+        }
+        // Activation must work from any directory, so the scripts get an absolute path
+        ActivationScript::new(std::path::absolute(&self.path)?).install(&self.bin)?;
+        Ok(())
+    }
+
+    /// Put `interpreter` in the bin directory under the names venv uses
+    #[cfg(not(target_os = "windows"))]
+    fn link_interpreter(&self, interpreter: &Interpreter) -> Result<()> {
+        let names = ["python".to_string(), "python3".to_string(), format!("python{}", interpreter.major_minor())];
+        for name in names {
+            let link = self.bin.join(name);
+            if fs::symlink_metadata(&link).is_ok() {
+                fs::remove_file(&link)?;
+            }
+            std::os::unix::fs::symlink(&interpreter.executable, &link)?;
+        }
+        Ok(())
+    }
+
+    /// Copy `interpreter`, `pythonw.exe` and the DLLs they load
+    #[cfg(target_os = "windows")]
+    fn link_interpreter(&self, interpreter: &Interpreter) -> Result<()> {
+        fs::copy(&interpreter.executable, self.get_python_path())?;
+        let Some(home) = interpreter.executable.parent() else {
+            return Ok(());
+        };
+        for entry in fs::read_dir(home)?.flatten() {
+            let name = entry.file_name().to_string_lossy().to_lowercase();
+            let runtime = name.ends_with(".dll") && (name.starts_with("python") || name.starts_with("vcruntime"));
+            if name == "pythonw.exe" || runtime {
+                fs::copy(entry.path(), self.bin.join(entry.file_name()))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn render_config(&self, interpreter: Option<&Interpreter>) -> String {
+        let mut config = String::new();
+        if let Some(home) = interpreter.and_then(|i| i.executable.parent()) {
+            config.push_str(&format!("home = {}\n", home.display()));
+        }
+        config.push_str("include-system-site-packages = false\n");
+        let version = interpreter.map_or(self.python_version.as_str(), |i| i.version.as_str());
+        config.push_str(&format!("version = {}\n", version));
+        if let Some(interpreter) = interpreter {
+            config.push_str(&format!("executable = {}\n", interpreter.executable.display()));
+        }
+        config
+    }
+
+    /// The `key = value` pairs of `pyvenv.cfg`
+    pub fn config(&self) -> Result<BTreeMap<String, String>> {
+        read_config(&self.path.join(CONFIG_FILE))
+    }
+
+    /// Whether the environment has its `pyvenv.cfg`, bin directory and
+    /// site-packages
+    pub fn is_valid(&self) -> bool {
+        self.path.join(CONFIG_FILE).is_file() && self.bin.is_dir() && self.site_packages.is_dir()
+    }
+
+    /// Delete the environment. A directory without `pyvenv.cfg` is left
+    /// alone, so a mistyped path can't remove something else.
+    pub fn remove(&self) -> Result<()> {
+        if !self.path.join(CONFIG_FILE).is_file() {
+            return Err(anyhow!("{} is not a virtual environment (no {})", self.path.display(), CONFIG_FILE));
+        }
+        fs::remove_dir_all(&self.path)?;
+        Ok(())
+    }
+}
+
+/// Where site-packages goes in an environment for `python_version`
+fn site_packages_for(path: &Path, python_version: &str) -> PathBuf {
+    if cfg!(target_os = "windows") {
+        return path.join("Lib").join("site-packages");
+    }
+    let major_minor = python_version.split('.').take(2).collect::<Vec<_>>().join(".");
+    path.join("lib").join(format!("python{}", major_minor)).join("site-packages")
+}
+
+/// The site-packages an existing environment has, whatever its version
+fn existing_site_packages(path: &Path) -> Option<PathBuf> {
+    let windows = path.join("Lib").join("site-packages");
+    if windows.is_dir() {
+        return Some(windows);
+    }
+    fs::read_dir(path.join("lib"))
+        .ok()?
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("python"))
+        .map(|entry| entry.path().join("site-packages"))
+        .find(|site| site.is_dir())
+}
+
+fn read_config(path: &Path) -> Result<BTreeMap<String, String>> {
+    let content = fs::read_to_string(path)?;
+    Ok(content
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_create_open_and_remove() -> Result<()> {
+        let temp = TempDir::new()?;
+        let path = temp.path().join(".venv");
+        let env = VirtualEnvironment::new(path.clone(), "3.12.1".to_string());
+        let interpreter = Interpreter {
+            executable: temp.path().join("base").join("python3.12"),
+            version: "3.12.1".to_string(),
+        };
+        fs::create_dir_all(temp.path().join("base"))?;
+        fs::write(&interpreter.executable, "")?;
+        env.create_with(Some(&interpreter))?;
+
+        assert!(env.is_valid());
+        assert!(env.get_bin_path().join("activate").is_file());
+        assert!(env.get_python_path().exists());
+        let config = env.config()?;
+        assert_eq!(config["home"], temp.path().join("base").display().to_string());
+        assert_eq!(config["include-system-site-packages"], "false");
+        assert_eq!(config["version"], "3.12.1");
+        if !cfg!(target_os = "windows") {
+            assert!(path.join("lib/python3.12/site-packages").is_dir());
+            assert!(env.get_bin_path().join("python3.12").exists());
+        }
+
+        let opened = VirtualEnvironment::open(&path)?;
+        assert_eq!(opened.python_version(), "3.12.1");
+        assert_eq!(opened.get_site_packages_path(), env.get_site_packages_path());
+
+        // Recreating in place is fine; a foreign directory is not touched
+        env.create_with(None)?;
+        assert_eq!(env.config()?["version"], "3.12.1");
+        let other = temp.path().join("project");
+        fs::create_dir_all(&other)?;
+        fs::write(other.join("main.py"), "")?;
+        let error = VirtualEnvironment::new(other.clone(), "3.12".to_string()).create_with(None).unwrap_err();
+        assert!(error.to_string().contains("is not a virtual environment"));
+        assert!(VirtualEnvironment::new(other.clone(), "3.12".to_string()).remove().is_err());
+        assert!(other.join("main.py").exists());
+
+        env.remove()?;
+        assert!(!path.exists());
+        Ok(())
+    }
+}
@@ -0,0 +1,161 @@
+/// Finding the Python interpreter a virtual environment is built on
+///
+/// A request is a version prefix (`3`, `3.12`, `3.12.1`) or a path to an
+/// interpreter. Versions are looked up on PATH as `pythonX.Y`, then
+/// `python3` and `python`, and each candidate is asked for its version and
+/// real executable, so shims (pyenv, the Windows store aliases) resolve to
+/// the interpreter they run.
+use anyhow::{Result, anyhow};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Prints the version and the resolved executable, one per line
+const PROBE: &str = "import sys; print('.'.join(map(str, sys.version_info[:3]))); print(sys.executable)";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Interpreter {
+    pub executable: PathBuf,
+    /// Full version, e.g. `3.12.1`
+    pub version: String,
+}
+
+/// No interpreter matching the request was found
+#[derive(Debug)]
+pub struct InterpreterNotFound {
+    pub requested: Option<String>,
+    /// Names looked up on PATH
+    pub tried: Vec<String>,
+    /// Interpreters found that have another version: (name, version)
+    pub others: Vec<(String, String)>,
+}
+
+impl std::fmt::Display for InterpreterNotFound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.requested {
+            Some(requested) => write!(f, "Python {} was not found on PATH", requested)?,
+            None => write!(f, "No Python interpreter was found on PATH")?,
+        }
+        write!(f, " (tried {})", self.tried.join(", "))?;
+        if !self.others.is_empty() {
+            let others: Vec<String> = self.others.iter().map(|(name, version)| format!("{} is {}", name, version)).collect();
+            write!(f, "; {}", others.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for InterpreterNotFound {}
+
+impl Interpreter {
+    /// Ask the interpreter at `candidate` what it is
+    pub fn probe(candidate: &Path) -> Result<Self> {
+        let output = Command::new(candidate).args(["-c", PROBE]).output()?;
+        if !output.status.success() {
+            return Err(anyhow!("{} exited with {}", candidate.display(), output.status));
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let mut lines = stdout.lines().map(str::trim);
+        let version = lines.next().filter(|v| !v.is_empty()).ok_or_else(|| anyhow!("no version reported"))?;
+        let executable = lines.next().filter(|e| !e.is_empty()).map_or_else(|| candidate.to_path_buf(), PathBuf::from);
+        Ok(Self {
+            executable,
+            version: version.to_string(),
+        })
+    }
+
+    /// The interpreter `requested` names, or the first one on PATH
+    pub fn find(requested: Option<&str>) -> Result<Self, InterpreterNotFound> {
+        if let Some(path) = requested.filter(|r| r.contains(['/', '\\'])) {
+            return Self::probe(Path::new(path)).map_err(|_| InterpreterNotFound {
+                requested: Some(path.to_string()),
+                tried: vec![path.to_string()],
+                others: Vec::new(),
+            });
+        }
+
+        // `--python python3.12` asks for the same thing as `--python 3.12`
+        let requested = requested.map(|r| r.trim_start_matches("python")).filter(|r| !r.is_empty());
+        let tried = candidate_names(requested);
+        let mut others = Vec::new();
+        for name in &tried {
+            let Some(path) = on_path(name) else {
+                continue;
+            };
+            match Self::probe(&path) {
+                Ok(found) if requested.is_none_or(|requested| version_matches(&found.version, requested)) => {
+                    return Ok(found);
+                }
+                Ok(found) => others.push((name.clone(), found.version)),
+                Err(e) => tracing::debug!("Skipping {}: {}", path.display(), e),
+            }
+        }
+        Err(InterpreterNotFound {
+            requested: requested.map(String::from),
+            tried,
+            others,
+        })
+    }
+
+    /// `X.Y` of the version
+    pub fn major_minor(&self) -> String {
+        self.version.split('.').take(2).collect::<Vec<_>>().join(".")
+    }
+}
+
+/// Executable names to look for, most specific first
+fn candidate_names(requested: Option<&str>) -> Vec<String> {
+    let mut names = Vec::new();
+    if let Some(requested) = requested {
+        // `python3.12` for 3.12 or 3.12.1; `python3` covers a bare 3
+        let major_minor: Vec<&str> = requested.split('.').take(2).collect();
+        names.push(format!("python{}", major_minor.join(".")));
+    }
+    for name in ["python3", "python"] {
+        if !names.iter().any(|n| n == name) {
+            names.push(name.to_string());
+        }
+    }
+    names
+}
+
+/// Whether `version` is `requested` or a release of it (`3.12` matches `3.12.1`)
+pub fn version_matches(version: &str, requested: &str) -> bool {
+    version == requested || version.starts_with(&format!("{}.", requested))
+}
+
+/// First executable called `name` on PATH
+fn on_path(name: &str) -> Option<PathBuf> {
+    let file_name = if cfg!(target_os = "windows") { format!("{}.exe", name) } else { name.to_string() };
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(&file_name))
+        .find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_candidates_and_version_matching() {
+        assert_eq!(candidate_names(Some("3.12")), ["python3.12", "python3", "python"]);
+        assert_eq!(candidate_names(Some("3.11.7")), ["python3.11", "python3", "python"]);
+        assert_eq!(candidate_names(Some("3")), ["python3", "python"]);
+        assert_eq!(candidate_names(None), ["python3", "python"]);
+
+        assert!(version_matches("3.12.1", "3.12"));
+        assert!(version_matches("3.12.1", "3"));
+        assert!(version_matches("3.12.1", "3.12.1"));
+        assert!(!version_matches("3.1.2", "3.12"));
+        assert!(!version_matches("3.11.7", "3.12"));
+
+        let missing = InterpreterNotFound {
+            requested: Some("3.12".to_string()),
+            tried: candidate_names(Some("3.12")),
+            others: vec![("python3".to_string(), "3.11.7".to_string())],
+        };
+        assert_eq!(
+            missing.to_string(),
+            "Python 3.12 was not found on PATH (tried python3.12, python3, python); python3 is 3.11.7"
+        );
+    }
+}
@@ -0,0 +1,8 @@
+/// Virtual environment creation and inspection (`pip venv`)
+pub mod activation;
+pub mod environment;
+pub mod interpreter;
+
+pub use activation::ActivationScript;
+pub use environment::VirtualEnvironment;
+pub use interpreter::{Interpreter, InterpreterNotFound};
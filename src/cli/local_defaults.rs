@@ -0,0 +1,144 @@
+/// Default command options from directory-local `.pip-rs.toml` files
+///
+/// A `[install]` table sets defaults for `pip install`, and so on per command;
+/// `[defaults]` keys apply to every command that has an option of that name.
+/// Defaults are added to the command line as long options, so clap validates
+/// them like typed flags. An option given on the command line is left alone.
+use crate::config::local::{DEFAULTS_SECTION, LocalConfig, LocalValue};
+use crate::errors::PipError;
+use std::collections::BTreeMap;
+
+/// `args` (including the program name) with the local defaults for the
+/// invoked command added. Commands with their own subcommands, and command
+/// lines clap rejects, are returned unchanged.
+pub fn apply(cli: &clap::Command, args: Vec<String>, local: &LocalConfig) -> Result<Vec<String>, PipError> {
+    if local.is_empty() {
+        return Ok(args);
+    }
+    let Ok(matches) = cli.clone().try_get_matches_from(&args) else {
+        return Ok(args);
+    };
+    let Some(command) = matches.subcommand_name().and_then(|name| cli.find_subcommand(name)) else {
+        return Ok(args);
+    };
+    if command.has_subcommands() {
+        return Ok(args);
+    }
+    let name = command.get_name();
+
+    // The command's own table wins over [defaults]
+    let mut settings: BTreeMap<&str, (&LocalValue, bool)> = BTreeMap::new();
+    for (key, value) in local.section(DEFAULTS_SECTION) {
+        settings.insert(key, (value, false));
+    }
+    for (key, value) in local.section(name) {
+        settings.insert(key, (value, true));
+    }
+
+    let mut injected = Vec::new();
+    for (key, (value, explicit)) in settings {
+        let Some(arg) = command.get_arguments().find(|arg| arg.get_long() == Some(key)) else {
+            if explicit {
+                return Err(config_error(value, format!("[{}] {} is not an option of `pip {}`", name, key, name)));
+            }
+            continue;
+        };
+        if given_on_command_line(&args, arg) {
+            continue;
+        }
+        if arg.get_action().takes_values() {
+            injected.extend(value.values.iter().map(|v| format!("--{}={}", key, v)));
+        } else {
+            match value.values.as_slice() {
+                [flag] if flag == "true" => injected.push(format!("--{}", key)),
+                [flag] if flag == "false" => {}
+                _ => return Err(config_error(value, format!("{} is a flag and must be true or false", key))),
+            }
+        }
+    }
+
+    if injected.is_empty() {
+        return Ok(args);
+    }
+    tracing::debug!("Local config defaults for `pip {}`: {}", name, injected.join(" "));
+    let mut args = args;
+    let at = args.iter().position(|arg| arg == "--").unwrap_or(args.len());
+    args.splice(at..at, injected);
+    Ok(args)
+}
+
+fn given_on_command_line(args: &[String], arg: &clap::Arg) -> bool {
+    let long = arg.get_long().map(|long| format!("--{}", long));
+    let short = arg.get_short().map(|short| format!("-{}", short));
+    args.iter().take_while(|a| *a != "--").any(|a| {
+        long.as_ref().is_some_and(|long| a == long || a.starts_with(&format!("{}=", long)))
+            || short.as_ref().is_some_and(|short| a.starts_with(short.as_str()))
+    })
+}
+
+fn config_error(value: &LocalValue, message: String) -> PipError {
+    PipError::ConfigError {
+        message: format!("{}: {}", value.source.display(), message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::{Arg, ArgAction, Command};
+
+    fn cli() -> Command {
+        Command::new("pip").subcommand(
+            Command::new("install")
+                .arg(Arg::new("packages").num_args(0..))
+                .arg(Arg::new("constraints").long("constraints").short('c'))
+                .arg(Arg::new("trusted-host").long("trusted-host").action(ArgAction::Append))
+                .arg(Arg::new("keep-going").long("keep-going").action(ArgAction::SetTrue))
+                .arg(Arg::new("upgrade").long("upgrade").action(ArgAction::SetTrue)),
+        )
+    }
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_apply_local_defaults() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let file = temp.path().join(crate::config::local::FILE_NAME);
+        std::fs::write(
+            &file,
+            "[defaults]\nkeep-going = true\ntimeout = 5\n\n[install]\nconstraints = \"c.txt\"\nupgrade = false\ntrusted-host = [\"a.example\", \"b.example\"]\n",
+        )
+        .unwrap();
+        let trusted = [temp.path().to_path_buf()];
+        let local = LocalConfig::from_files(std::slice::from_ref(&file), &trusted).unwrap();
+        let constraints = temp.path().join("c.txt").display().to_string();
+
+        let result = apply(&cli(), args("pip install requests"), &local).unwrap();
+        assert_eq!(
+            result,
+            [
+                "pip".to_string(),
+                "install".into(),
+                "requests".into(),
+                format!("--constraints={}", constraints),
+                "--keep-going".into(),
+                "--trusted-host=a.example".into(),
+                "--trusted-host=b.example".into(),
+            ]
+        );
+
+        // Command-line options win, and defaults go before `--`
+        let result = apply(&cli(), args("pip install -c mine.txt -- requests"), &local).unwrap();
+        assert_eq!(result[..4], args("pip install -c mine.txt")[..]);
+        assert_eq!(result.last().unwrap(), "requests");
+        assert!(!result.iter().any(|a| a.starts_with("--constraints")));
+
+        // A command table key the command doesn't have is an error
+        std::fs::write(&file, "[install]\nonly-binary = [\":all:\"]\n").unwrap();
+        let local = LocalConfig::from_files(&[file], &[]).unwrap();
+        let error = apply(&cli(), args("pip install requests"), &local).unwrap_err();
+        assert!(error.to_string().contains("only-binary is not an option of `pip install`"), "{}", error);
+    }
+}
@@ -1,3 +1,6 @@
 /// Command-line interface utilities
+pub mod local_defaults;
 pub mod parser;
+pub mod progress_socket;
+pub mod signals;
 
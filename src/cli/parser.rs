@@ -6,10 +6,105 @@ use std::path::PathBuf;
 pub struct InstallOptions {
     pub packages: Vec<String>,
     pub requirements: Option<PathBuf>,
+    /// `-c` files, merged in the order given
+    pub constraints: Vec<PathBuf>,
+    pub trusted_hosts: Vec<String>,
+    /// `--index-url`: replaces the configured primary index
+    pub index_url: Option<String>,
+    /// `--extra-index-url`: consulted after the primary index
+    pub extra_index_urls: Vec<String>,
+    /// `--find-links`: directories or pages of distributions
+    pub find_links: Vec<String>,
+    /// `--no-index`: look packages up in `find_links` only
+    pub no_index: bool,
     pub target: Option<PathBuf>,
+    /// `--user`: install into the per-user site-packages
+    pub user: bool,
+    pub override_file: Option<PathBuf>,
+    pub index_policy: Option<PathBuf>,
     pub upgrade: bool,
     pub force_reinstall: bool,
     pub no_deps: bool,
+    pub only_deps: bool,
+    pub keep_going: bool,
+    /// Undo the packages already installed when one fails
+    pub rollback_on_failure: bool,
+    pub report: Option<PathBuf>,
+    pub allow_unset_env: bool,
+    pub allow_degraded_environment: bool,
+    pub show_sizes: bool,
+    /// Refuse requirements without a pinned version and `--hash`
+    pub require_hashes: bool,
+    pub locked: Option<PathBuf>,
+    pub strict_repro: bool,
+    /// With `--locked`: install even if the lock is for another environment
+    pub ignore_environment: bool,
+    /// Abort rather than replace files running processes have mapped
+    pub refuse_if_in_use: bool,
+    /// Resolve and print the plan without downloading or installing
+    pub dry_run: bool,
+    /// `--no-deprecated`: refuse releases that declare themselves deprecated
+    pub no_deprecated: bool,
+    /// `-v`: also list dependencies skipped by environment markers
+    pub verbose: bool,
+    pub resolution: ResolutionOptions,
+    /// `--progress-socket`: stream install events to this socket
+    pub progress_socket: Option<PathBuf>,
+}
+
+/// How long resolution may run, and whether to report what it explored
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ResolutionOptions {
+    /// `--resolution-timeout`, in seconds
+    pub timeout: Option<u64>,
+    /// `--resolution-stats`
+    pub stats: bool,
+}
+
+impl ResolutionOptions {
+    pub fn budget(&self) -> pip_rs_core::resolver::ResolutionBudget {
+        let mut budget = pip_rs_core::resolver::ResolutionBudget::default();
+        if let Some(secs) = self.timeout {
+            budget.timeout = std::time::Duration::from_secs(secs);
+        }
+        budget
+    }
+}
+
+/// The environment `pip download` fetches for, when it isn't this one
+#[derive(Debug, Clone, Default)]
+pub struct TargetOptions {
+    /// `--platform`: `sys.platform` value or wheel platform tag
+    pub platform: Option<String>,
+    /// `--python-version`, e.g. `3.12` or `312`
+    pub python_version: Option<String>,
+    /// `--implementation`, e.g. `cp` or `pp`
+    pub implementation: Option<String>,
+    /// `--abi`, e.g. `cp312`
+    pub abi: Option<String>,
+    /// `--only-binary`: `:all:` or package names
+    pub only_binary: Vec<String>,
+}
+
+impl TargetOptions {
+    pub fn environment_override(&self) -> pip_rs_core::models::EnvironmentOverride {
+        pip_rs_core::models::EnvironmentOverride {
+            python_version: self.python_version.clone(),
+            platform: self.platform.clone(),
+            implementation: self.implementation.clone(),
+            architecture: None,
+            abi: self.abi.clone(),
+        }
+    }
+
+    /// Whether `--only-binary` covers `name`
+    pub fn only_binary(&self, name: &str) -> bool {
+        use pip_rs_core::utils::names::canonicalize_name;
+        self.only_binary
+            .iter()
+            .flat_map(|entry| entry.split(','))
+            .any(|entry| entry == ":all:" || canonicalize_name(entry) == canonicalize_name(name))
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -24,10 +119,36 @@ impl Default for InstallOptions {
         Self {
             packages: Vec::new(),
             requirements: None,
+            constraints: Vec::new(),
+            trusted_hosts: Vec::new(),
+            index_url: None,
+            extra_index_urls: Vec::new(),
+            find_links: Vec::new(),
+            no_index: false,
             target: None,
+            user: false,
+            override_file: None,
+            index_policy: None,
             upgrade: false,
             force_reinstall: false,
             no_deps: false,
+            only_deps: false,
+            keep_going: false,
+            rollback_on_failure: false,
+            report: None,
+            allow_unset_env: false,
+            allow_degraded_environment: false,
+            show_sizes: false,
+            require_hashes: false,
+            locked: None,
+            strict_repro: false,
+            ignore_environment: false,
+            refuse_if_in_use: false,
+            dry_run: false,
+            no_deprecated: false,
+            verbose: false,
+            resolution: ResolutionOptions::default(),
+            progress_socket: None,
         }
     }
 }
@@ -0,0 +1,246 @@
+/// `--progress-socket`: install events for IDEs and other drivers
+///
+/// pip-rs connects to a socket the caller is listening on (a Unix domain
+/// socket, or a named pipe on Windows) and writes every
+/// [`InstallEvent`](pip_rs_core::installer::events::InstallEvent) as one line
+/// of JSON, alongside the normal terminal output. The caller may write one
+/// line back, `{"command": "cancel"}` (or just `cancel`), to stop the
+/// install at the next package boundary.
+///
+/// Failing to connect is an error, since the caller asked for the events;
+/// losing the connection later only stops the events, never the install.
+use crate::errors::PipError;
+use pip_rs_core::installer::events::{self, EventEnvelope};
+use pip_rs_core::utils::cancel;
+use pip_rs_core::utils::warnings::{self, WarningCode};
+use std::path::Path;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+/// A connected event stream; [`ProgressSocket::close`] flushes it
+pub struct ProgressSocket {
+    shutdown: oneshot::Sender<()>,
+    writer: JoinHandle<()>,
+    reader: JoinHandle<()>,
+}
+
+impl ProgressSocket {
+    pub async fn connect(path: &Path) -> Result<Self, PipError> {
+        let stream = open(path).await.map_err(|e| PipError::FileSystemError {
+            path: path.display().to_string(),
+            operation: "connect to progress socket".to_string(),
+            reason: e.to_string(),
+        })?;
+        Ok(Self::start(stream))
+    }
+
+    fn start<S: AsyncRead + AsyncWrite + Send + 'static>(stream: S) -> Self {
+        let (read_half, write_half) = tokio::io::split(stream);
+        let (shutdown, shutdown_rx) = oneshot::channel();
+        Self {
+            writer: tokio::spawn(forward(events::subscribe(), write_half, shutdown_rx)),
+            reader: tokio::spawn(listen(read_half)),
+            shutdown,
+        }
+    }
+
+    /// Send the events emitted so far and disconnect
+    pub async fn close(self) {
+        let _ = self.shutdown.send(());
+        let _ = self.writer.await;
+        self.reader.abort();
+    }
+}
+
+#[cfg(unix)]
+async fn open(path: &Path) -> std::io::Result<tokio::net::UnixStream> {
+    tokio::net::UnixStream::connect(path).await
+}
+
+#[cfg(windows)]
+async fn open(path: &Path) -> std::io::Result<tokio::net::windows::named_pipe::NamedPipeClient> {
+    tokio::net::windows::named_pipe::ClientOptions::new().open(path)
+}
+
+/// Write events until shut down, then drain what's queued
+async fn forward(
+    mut events: mpsc::UnboundedReceiver<EventEnvelope>,
+    mut socket: impl AsyncWrite + Unpin,
+    mut shutdown: oneshot::Receiver<()>,
+) {
+    loop {
+        let envelope = tokio::select! {
+            biased;
+            Some(envelope) = events.recv() => envelope,
+            _ = &mut shutdown => break,
+        };
+        if let Err(e) = write_event(&mut socket, &envelope).await {
+            drop(events);
+            warnings::warn_user(
+                WarningCode::ProgressSocketClosed,
+                format!("progress socket disconnected ({}); no further events will be sent", e),
+            );
+            return;
+        }
+    }
+    while let Ok(envelope) = events.try_recv() {
+        if write_event(&mut socket, &envelope).await.is_err() {
+            return;
+        }
+    }
+    let _ = socket.shutdown().await;
+}
+
+async fn write_event(socket: &mut (impl AsyncWrite + Unpin), envelope: &EventEnvelope) -> std::io::Result<()> {
+    let mut line = serde_json::to_vec(envelope)?;
+    line.push(b'\n');
+    socket.write_all(&line).await?;
+    socket.flush().await
+}
+
+/// Honour a cancel request from the consumer; anything else is ignored
+async fn listen(socket: impl AsyncRead + Unpin) {
+    let mut lines = BufReader::new(socket).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if is_cancel(&line) {
+            cancel::request();
+            return;
+        }
+    }
+}
+
+fn is_cancel(line: &str) -> bool {
+    let line = line.trim();
+    line == "cancel"
+        || serde_json::from_str::<serde_json::Value>(line).is_ok_and(|message| message["command"] == "cancel")
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use pip_rs_core::errors::Phase;
+    use pip_rs_core::installer::batch::BatchInstall;
+    use pip_rs_core::installer::events::InstallEvent;
+    use pip_rs_core::models::Package;
+    use std::collections::HashMap;
+    use tokio::net::UnixListener;
+
+    /// The events an install of `app` (which needs `lib`) emits, with a
+    /// cancel check between packages the way `pip install` makes it
+    async fn mocked_install(after_first: impl std::future::Future<Output = ()>) -> i32 {
+        let app = Package::new("sock-app".to_string(), "1.0".to_string());
+        let lib = Package::new("sock-lib".to_string(), "2.0".to_string());
+        let edges = HashMap::from([("sock-app".to_string(), vec!["sock-lib".to_string()])]);
+        let resolved = [app, lib];
+        let mut batch = BatchInstall::new(&resolved, &edges);
+
+        events::phase(Phase::Resolution, None);
+        events::emit(InstallEvent::ResolutionProgress { round: 1, resolved: 2, pending: 0 });
+        warnings::warn_user(WarningCode::PackageSkipped, "sock-test: skipped an optional extra");
+        let mut after_first = Some(after_first);
+        for pkg in batch.install_order() {
+            if cancel::is_requested() {
+                events::emit(InstallEvent::Cancelled);
+                batch.record_skipped(&pkg, "cancelled".to_string());
+                continue;
+            }
+            events::phase(Phase::Download, Some(&pkg.name));
+            events::emit(InstallEvent::Downloaded {
+                package: pkg.name.clone(),
+                version: pkg.version.clone(),
+                bytes: 1024,
+                seconds: 0.5,
+            });
+            batch.record_installed(&pkg, 4096);
+            if let Some(after_first) = after_first.take() {
+                after_first.await;
+            }
+        }
+        let report = batch.into_report();
+        events::emit(InstallEvent::Report { report: serde_json::to_value(&report).unwrap() });
+        report.exit_code()
+    }
+
+    #[tokio::test]
+    async fn test_event_stream_and_cancel_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("progress.sock");
+        let listener = UnixListener::bind(&path).unwrap();
+        let socket = ProgressSocket::connect(&path).await.unwrap();
+        let (server, _) = listener.accept().await.unwrap();
+        let (server_read, mut server_write) = tokio::io::split(server);
+        let mut lines = BufReader::new(server_read).lines();
+
+        // The driver cancels as soon as it sees the first package installed
+        let mut seen = Vec::new();
+        let exit_code = mocked_install(async {
+            loop {
+                let line = lines.next_line().await.unwrap().unwrap();
+                let installed = line.contains("\"event\":\"installed\"");
+                seen.push(line);
+                if installed {
+                    break;
+                }
+            }
+            server_write.write_all(b"{\"command\": \"cancel\"}\n").await.unwrap();
+            while !cancel::is_requested() {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await;
+        socket.close().await;
+        cancel::reset();
+        assert_eq!(exit_code, pip_rs_core::installer::batch::EXIT_PARTIAL_FAILURE);
+
+        while let Ok(Some(line)) = lines.next_line().await {
+            seen.push(line);
+        }
+        // Other tests in this process may warn concurrently; only ours count
+        let received: Vec<serde_json::Value> = seen
+            .iter()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .filter(|event| event["event"] != "warning" || event["message"].as_str().unwrap().starts_with("sock-test"))
+            .collect();
+        let kinds: Vec<String> = received
+            .iter()
+            .map(|event| {
+                let package = event["package"].as_str().or(event["name"].as_str());
+                match package {
+                    Some(package) => format!("{} {}", event["event"].as_str().unwrap(), package),
+                    None => event["event"].as_str().unwrap().to_string(),
+                }
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                "phase",
+                "resolution-progress",
+                "warning",
+                "phase sock-lib",
+                "downloaded sock-lib",
+                "installed sock-lib",
+                "cancelled",
+                "skipped sock-app",
+                "report",
+            ]
+        );
+        assert!(received.iter().all(|event| event["schema_version"] == events::SCHEMA_VERSION));
+        let seqs: Vec<u64> = received.iter().map(|event| event["seq"].as_u64().unwrap()).collect();
+        assert!(seqs.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(received[0]["phase"], "resolution");
+        assert_eq!(received[4]["bytes"], 1024);
+        assert_eq!(received[5]["size"], 4096);
+        assert_eq!(received[7]["due_to"], "cancelled");
+        assert_eq!(received[8]["report"]["installed"][0]["name"], "sock-lib");
+        assert_eq!(received[8]["report"]["skipped"][0]["name"], "sock-app");
+    }
+
+    #[tokio::test]
+    async fn test_connect_failure_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let err = ProgressSocket::connect(&dir.path().join("nobody-listening.sock")).await.err().unwrap();
+        assert!(err.to_string().contains("connect to progress socket"));
+    }
+}
@@ -0,0 +1,58 @@
+/// Ctrl-C and SIGTERM (Ctrl-Break on Windows) for commands that change the
+/// environment
+///
+/// The first signal cancels the command cooperatively (see
+/// `pip_rs_core::utils::cancel`): downloads are abandoned, the package being
+/// copied into place is rolled back and no further package is started. If
+/// that takes longer than [`GRACE`], or a second signal arrives, pip-rs
+/// exits at once. Either way it prints "Operation cancelled" and exits with
+/// `EXIT_INTERRUPTED`.
+use pip_rs_core::utils::cancel;
+use std::sync::Once;
+use std::time::Duration;
+
+/// How long cleanup may run after the first signal
+pub const GRACE: Duration = Duration::from_secs(10);
+
+static INSTALLED: Once = Once::new();
+
+/// Start handling interrupts; only the first call has an effect
+pub fn install() {
+    INSTALLED.call_once(|| {
+        tokio::spawn(async {
+            if next_signal().await.is_err() {
+                return;
+            }
+            cancel::interrupt();
+            eprintln!("\nCancelling... (press Ctrl-C again to stop immediately)");
+            tokio::select! {
+                _ = next_signal() => {}
+                _ = tokio::time::sleep(GRACE) => {
+                    eprintln!("Cleanup did not finish within {}s", GRACE.as_secs());
+                }
+            }
+            eprintln!("Operation cancelled");
+            std::process::exit(cancel::EXIT_INTERRUPTED);
+        });
+    });
+}
+
+#[cfg(unix)]
+async fn next_signal() -> std::io::Result<()> {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut terminate = signal(SignalKind::terminate())?;
+    tokio::select! {
+        result = tokio::signal::ctrl_c() => result,
+        _ = terminate.recv() => Ok(()),
+    }
+}
+
+#[cfg(windows)]
+async fn next_signal() -> std::io::Result<()> {
+    let mut ctrl_break = tokio::signal::windows::ctrl_break()?;
+    tokio::select! {
+        result = tokio::signal::ctrl_c() => result,
+        _ = ctrl_break.recv() => Ok(()),
+    }
+}
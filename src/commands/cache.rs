@@ -0,0 +1,162 @@
+/// Cache command - inspect and clean the pip-rs cache
+use crate::errors::PipError;
+use pip_rs_core::cache::{WheelCache, layout};
+use pip_rs_core::installer::sizes::format_size;
+use pip_rs_core::{config, network, utils};
+use std::path::Path;
+
+/// Print the cache root
+pub async fn handle_cache_dir() -> Result<i32, PipError> {
+    let Some(root) = layout::cache_root() else {
+        eprintln!("ERROR: No cache directory available");
+        return Ok(1);
+    };
+    println!("{}", root.display());
+    Ok(0)
+}
+
+/// Print where each part of the cache lives and how much it holds
+pub async fn handle_cache_info() -> Result<i32, PipError> {
+    let Some(root) = layout::cache_root() else {
+        eprintln!("ERROR: No cache directory available");
+        return Ok(1);
+    };
+    let http = root.join(layout::HTTP_DIR);
+    let packages = root.join(layout::PACKAGES_DIR);
+    let wheels = WheelCache::new(root.join(layout::WHEELS_DIR));
+    let cached = wheels.list();
+
+    println!("Cache location: {}", root.display());
+    println!("HTTP cache size: {} ({} files)", format_size(dir_size(&http)), count_files(&http));
+    println!("Package metadata size: {} ({} files)", format_size(dir_size(&packages)), count_files(&packages));
+    println!("Wheels location: {}", wheels.root().display());
+    println!("Wheels size: {}", format_size(cached.iter().map(|wheel| wheel.size).sum()));
+    println!("Number of wheels: {}", cached.len());
+    Ok(0)
+}
+
+/// List cached wheels, all of them or those matching `pattern`
+pub async fn handle_cache_list(pattern: Option<String>, format: String) -> Result<i32, PipError> {
+    if !matches!(format.as_str(), "human" | "abspath") {
+        eprintln!("ERROR: Unknown format '{}' (expected human or abspath)", format);
+        return Ok(1);
+    }
+    let Some(wheels) = WheelCache::open() else {
+        eprintln!("ERROR: No cache directory available");
+        return Ok(1);
+    };
+    let cached = match &pattern {
+        Some(pattern) => wheels.matching(pattern),
+        None => wheels.list(),
+    };
+
+    if format == "abspath" {
+        for wheel in &cached {
+            println!("{}", wheel.path.display());
+        }
+    } else if cached.is_empty() {
+        println!("No cached wheels.");
+    } else {
+        println!("Cache contents:\n");
+        for wheel in &cached {
+            println!(" - {} ({})", wheel.filename, format_size(wheel.size));
+        }
+    }
+    Ok(0)
+}
+
+/// Remove cached wheels matching `pattern`
+pub async fn handle_cache_remove(pattern: String) -> Result<i32, PipError> {
+    let Some(wheels) = WheelCache::open() else {
+        eprintln!("ERROR: No cache directory available");
+        return Ok(1);
+    };
+    let removed = wheels.remove(&pattern).map_err(|e| PipError::FileSystemError {
+        path: wheels.root().display().to_string(),
+        operation: "remove".to_string(),
+        reason: e.to_string(),
+    })?;
+    if removed == 0 {
+        eprintln!("WARNING: No matching packages for pattern \"{}\"", pattern);
+    }
+    println!("Files removed: {}", removed);
+    Ok(0)
+}
+
+/// Remove cached HTTP responses and package metadata, plus partial
+/// downloads older than the configured `part-max-age`. Recent `.part`
+/// files are kept so a concurrent pip-rs can finish its download.
+pub async fn handle_cache_purge() -> Result<i32, PipError> {
+    let Some(root) = layout::cache_root() else {
+        eprintln!("ERROR: No cache directory available");
+        return Ok(1);
+    };
+    // Don't starve installs running alongside
+    utils::resources::lower_io_priority();
+
+    let mut removed = 0;
+    for namespace in [
+        layout::HTTP_DIR,
+        layout::PACKAGES_DIR,
+        layout::SNAPSHOTS_DIR,
+        layout::WHEELS_DIR,
+        layout::INDEXES_DIR,
+    ] {
+        let dir = root.join(namespace);
+        if !dir.exists() {
+            continue;
+        }
+        removed += count_files(&dir);
+        std::fs::remove_dir_all(&dir).map_err(|e| PipError::FileSystemError {
+            path: dir.display().to_string(),
+            operation: "remove".to_string(),
+            reason: e.to_string(),
+        })?;
+    }
+
+    let downloads = root.join(layout::DOWNLOADS_DIR);
+    let max_age = config::config::Config::global().part_max_age();
+    let parts = network::resume::purge_stale_parts(&downloads, max_age).map_err(|e| {
+        PipError::FileSystemError {
+            path: downloads.display().to_string(),
+            operation: "purge".to_string(),
+            reason: e.to_string(),
+        }
+    })?;
+
+    println!("Files removed: {}", removed + parts);
+    if parts > 0 {
+        println!("  Partial downloads removed: {}", parts);
+    }
+    Ok(0)
+}
+
+fn count_files(dir: &Path) -> usize {
+    let mut count = 0;
+    if let Ok(entries) = std::fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                count += count_files(&path);
+            } else {
+                count += 1;
+            }
+        }
+    }
+    count
+}
+
+fn dir_size(dir: &Path) -> u64 {
+    let mut size = 0;
+    if let Ok(entries) = std::fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                size += dir_size(&path);
+            } else {
+                size += entry.metadata().map(|m| m.len()).unwrap_or(0);
+            }
+        }
+    }
+    size
+}
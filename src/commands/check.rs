@@ -1,16 +1,55 @@
 /// Check command implementation
 use crate::errors::PipError;
 use crate::installer::SitePackages;
+use crate::installer::consistency;
+use crate::installer::uninstall;
+use crate::installer::preflight::EnvironmentScan;
+use crate::models::Environment;
 use crate::utils::color::get_color_output;
+use crate::utils::names::canonicalize_name;
 
-pub async fn handle_check(package: Option<String>) -> Result<i32, PipError> {
-    if let Some(pkg) = package {
-        check_package(&pkg).await
-    } else {
-        check_environment().await
+pub async fn handle_check(package: Option<String>, environment: bool) -> Result<i32, PipError> {
+    match (package, environment) {
+        (Some(pkg), true) => check_package(&pkg).await,
+        (None, true) => check_environment().await,
+        (package, false) => check_requirements(package.as_deref()),
     }
 }
 
+/// Verify installed packages' requirements against each other, as `pip
+/// check` does: one line per unmet requirement, exit 1 if there are any
+fn check_requirements(package: Option<&str>) -> Result<i32, PipError> {
+    let site_packages = SitePackages::default().map_err(|e| PipError::FileSystemError {
+        path: "site-packages".to_string(),
+        operation: "access".to_string(),
+        reason: e.to_string(),
+    })?;
+    let installed = site_packages.all_package_details().map_err(|e| PipError::FileSystemError {
+        path: site_packages.path().display().to_string(),
+        operation: "read metadata".to_string(),
+        reason: e.to_string(),
+    })?;
+
+    let mut broken = consistency::broken_requirements(&installed, &Environment::current());
+    if let Some(package) = package {
+        let wanted = canonicalize_name(package);
+        if !installed.iter().any(|details| canonicalize_name(&details.name) == wanted) {
+            get_color_output().print_error(&format!("Package '{}' is not installed", package));
+            return Ok(1);
+        }
+        broken.retain(|requirement| canonicalize_name(requirement.package()) == wanted);
+    }
+
+    if broken.is_empty() {
+        println!("No broken requirements found.");
+        return Ok(0);
+    }
+    for requirement in &broken {
+        println!("{}", requirement);
+    }
+    Ok(1)
+}
+
 /// Check a specific package for issues
 async fn check_package(package_name: &str) -> Result<i32, PipError> {
     let color = get_color_output();
@@ -44,6 +83,17 @@ async fn check_package(package_name: &str) -> Result<i32, PipError> {
     } else {
         color.print_warning(&format!("Metadata not found for package '{}'", package_name));
     }
+
+    // Structural problems in this package's dist-info (same scan as install)
+    if let Ok(scan) = EnvironmentScan::scan(site_packages.path()) {
+        let (affecting, _) = scan.partition(&[package_name.to_string()]);
+        if !affecting.is_empty() {
+            for anomaly in affecting {
+                color.print_error(&anomaly.to_string());
+            }
+            return Ok(1);
+        }
+    }
     
     Ok(0)
 }
@@ -70,6 +120,26 @@ async fn check_environment() -> Result<i32, PipError> {
                     issues += 1;
                 }
             }
+
+            let scan = EnvironmentScan::scan(site_packages.path()).and_then(|mut scan| {
+                for dir in uninstall::scripts_dirs(site_packages.path()) {
+                    scan.scan_scripts(&dir, site_packages.path())?;
+                }
+                Ok(scan)
+            });
+            match scan {
+                Ok(scan) if scan.is_clean() => color.print_success("Installed metadata and scripts: OK"),
+                Ok(scan) => {
+                    for anomaly in &scan.anomalies {
+                        color.print_error(&anomaly.to_string());
+                    }
+                    issues += scan.anomalies.len();
+                }
+                Err(e) => {
+                    color.print_error(&format!("Error scanning installed metadata: {}", e));
+                    issues += 1;
+                }
+            }
         }
         Err(e) => {
             color.print_error(&format!("Error accessing site-packages: {}", e));
@@ -86,7 +156,7 @@ async fn check_environment() -> Result<i32, PipError> {
     }
     
     // Check virtual environment
-    if std::env::var("VIRTUAL_ENV").is_ok() {
+    if crate::utils::python_env::virtual_env().is_some() {
         color.print_success("Virtual environment: Active");
     } else {
         color.print_warning("Virtual environment: Not active");
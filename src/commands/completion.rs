@@ -37,23 +37,27 @@ _pip_completion() {{
     cur="${{COMP_WORDS[COMP_CWORD]}}"
     prev="${{COMP_WORDS[COMP_CWORD-1]}}"
     
-    opts="install uninstall list show search check update freeze download lock debug help"
+    opts="install uninstall list show inspect search check update freeze download lock debug cache datasets version find-import explain-markers deps watch config venv help"
     
     case "${{prev}}" in
         pip)
             COMPREPLY=( $(compgen -W "${{opts}}" -- ${{cur}}) )
             return 0
             ;;
-        install|download)
+        install)
+            COMPREPLY=( $(compgen -W "-r --requirements -o --output -t --target --user -U --upgrade --dry-run --require-hashes --resolution-timeout --resolution-stats --progress-socket" -- ${{cur}}) )
+            return 0
+            ;;
+        download)
             COMPREPLY=( $(compgen -W "-r --requirements -o --output -t --target" -- ${{cur}}) )
             return 0
             ;;
         uninstall)
-            COMPREPLY=( $(compgen -W "-y --yes" -- ${{cur}}) )
+            COMPREPLY=( $(compgen -W "-y --yes --user" -- ${{cur}}) )
             return 0
             ;;
         list)
-            COMPREPLY=( $(compgen -W "--outdated" -- ${{cur}}) )
+            COMPREPLY=( $(compgen -W "--outdated --pre --format" -- ${{cur}}) )
             return 0
             ;;
         check)
@@ -65,11 +69,39 @@ _pip_completion() {{
             return 0
             ;;
         lock)
-            COMPREPLY=( $(compgen -W "-r --requirements -o --output" -- ${{cur}}) )
+            COMPREPLY=( $(compgen -W "-r --requirements -o --output --resolution-timeout --resolution-stats" -- ${{cur}}) )
+            return 0
+            ;;
+        venv)
+            COMPREPLY=( $(compgen -W "create info remove" -- ${{cur}}) )
+            return 0
+            ;;
+        debug)
+            COMPREPLY=( $(compgen -W "--dump-state --packages --include-bodies" -- ${{cur}}) )
+            return 0
+            ;;
+        datasets)
+            COMPREPLY=( $(compgen -W "list update" -- ${{cur}}) )
+            return 0
+            ;;
+        explain-markers)
+            COMPREPLY=( $(compgen -W "--env" -- ${{cur}}) )
+            return 0
+            ;;
+        deps)
+            COMPREPLY=( $(compgen -W "--flat --json --licenses --env" -- ${{cur}}) )
+            return 0
+            ;;
+        config)
+            COMPREPLY=( $(compgen -W "list" -- ${{cur}}) )
+            return 0
+            ;;
+        help)
+            COMPREPLY=( $(compgen -W "requirements lockfiles offline venv migration-from-pip --all ${{opts}}" -- ${{cur}}) )
             return 0
             ;;
         *)
-            COMPREPLY=( $(compgen -W "${{opts}}" -- ${{cur}}) )
+            COMPREPLY=( $(compgen -W "${{opts}} --ignore-local-config" -- ${{cur}}) )
             ;;
     esac
     
@@ -95,6 +127,7 @@ _pip_commands() {{
         'uninstall:Uninstall packages'
         'list:List installed packages'
         'show:Show package information'
+        'inspect:Print installed distributions and the environment as JSON'
         'search:Search for packages'
         'check:Check for outdated packages'
         'update:Update packages'
@@ -102,14 +135,40 @@ _pip_commands() {{
         'download:Download packages'
         'lock:Generate lock file'
         'debug:Display debug information'
-        'help:Print help'
+        'cache:Inspect and manage the pip-rs cache'
+        'datasets:List and update bundled data tables'
+        'version:Show version and build information'
+        'find-import:Find which distribution provides an import name'
+        'explain-markers:Show how environment markers decide dependencies'
+        'deps:Show the dependency closure of a requirement without installing'
+        'watch:Keep the environment in sync with a requirements or lock file'
+        'config:Show configuration values and the files that set them'
+        'venv:Create, inspect and remove virtual environments'
+        'help:Explain a workflow with examples, or show command help'
     )
     _describe 'command' commands
 }}
 
 _pip_options() {{
     case "${{words[2]}}" in
-        install|download)
+        install)
+            _arguments \
+                '-r[Requirements file]' \
+                '--requirements[Requirements file]' \
+                '-o[Output directory]' \
+                '--output[Output directory]' \
+                '-t[Target directory]' \
+                '--target[Target directory]' \
+                '--user[Install into the per-user site-packages]' \
+                '-U[Upgrade installed packages]' \
+                '--upgrade[Upgrade installed packages]' \
+                '--dry-run[Show what would be installed without installing]' \
+                '--require-hashes[Require a pinned version and hash for every package]' \
+                '--resolution-timeout[Give up resolving after this many seconds]' \
+                '--resolution-stats[Print how many candidates and backtracks resolution took]' \
+                '--progress-socket[Stream install events as JSON lines to this socket]:path:_files'
+            ;;
+        download)
             _arguments \
                 '-r[Requirements file]' \
                 '--requirements[Requirements file]' \
@@ -121,11 +180,14 @@ _pip_options() {{
         uninstall)
             _arguments \
                 '-y[Assume yes]' \
-                '--yes[Assume yes]'
+                '--yes[Assume yes]' \
+                '--user[Uninstall from the per-user site-packages]'
             ;;
         list)
             _arguments \
-                '--outdated[Show outdated packages]'
+                '--outdated[Show outdated packages]' \
+                '--pre[Include pre-release versions]' \
+                '--format[Output format]:format:(columns json freeze)'
             ;;
         check)
             _arguments \
@@ -142,7 +204,13 @@ _pip_options() {{
                 '-r[Requirements file]' \
                 '--requirements[Requirements file]' \
                 '-o[Output file]' \
-                '--output[Output file]'
+                '--output[Output file]' \
+                '--resolution-timeout[Give up resolving after this many seconds]' \
+                '--resolution-stats[Print how many candidates and backtracks resolution took]'
+            ;;
+        config)
+            _arguments \
+                '1:action:(list)'
             ;;
     esac
 }}
@@ -165,13 +233,14 @@ fn print_fish_completion() {
         r#"# fish completion for pip
 # To install: pip completion fish | sudo tee /usr/share/fish/vendor_completions.d/pip.fish
 
-complete -c pip -f -n "__fish_use_subcommand_from_list install uninstall list show search check update freeze download lock debug" -d "Subcommand"
+complete -c pip -f -n "__fish_use_subcommand_from_list install uninstall list show inspect search check update freeze download lock debug cache datasets version find-import explain-markers deps watch config venv help" -d "Subcommand"
 
 # Commands
 complete -c pip -n "__fish_use_subcommand_from_list" -a install -d "Install packages"
 complete -c pip -n "__fish_use_subcommand_from_list" -a uninstall -d "Uninstall packages"
 complete -c pip -n "__fish_use_subcommand_from_list" -a list -d "List installed packages"
 complete -c pip -n "__fish_use_subcommand_from_list" -a show -d "Show package information"
+complete -c pip -n "__fish_use_subcommand_from_list" -a inspect -d "Print installed distributions and the environment as JSON"
 complete -c pip -n "__fish_use_subcommand_from_list" -a search -d "Search for packages"
 complete -c pip -n "__fish_use_subcommand_from_list" -a check -d "Check for outdated packages"
 complete -c pip -n "__fish_use_subcommand_from_list" -a update -d "Update packages"
@@ -179,17 +248,37 @@ complete -c pip -n "__fish_use_subcommand_from_list" -a freeze -d "Generate requ
 complete -c pip -n "__fish_use_subcommand_from_list" -a download -d "Download packages"
 complete -c pip -n "__fish_use_subcommand_from_list" -a lock -d "Generate lock file"
 complete -c pip -n "__fish_use_subcommand_from_list" -a debug -d "Display debug information"
+complete -c pip -n "__fish_use_subcommand_from_list" -a cache -d "Inspect and manage the pip-rs cache"
+complete -c pip -n "__fish_use_subcommand_from_list" -a datasets -d "List and update bundled data tables"
+complete -c pip -n "__fish_use_subcommand_from_list" -a version -d "Show version and build information"
+complete -c pip -n "__fish_use_subcommand_from_list" -a find-import -d "Find which distribution provides an import name"
+complete -c pip -n "__fish_use_subcommand_from_list" -a explain-markers -d "Show how environment markers decide dependencies"
+complete -c pip -n "__fish_use_subcommand_from_list" -a deps -d "Show the dependency closure of a requirement without installing"
+complete -c pip -n "__fish_use_subcommand_from_list" -a watch -d "Keep the environment in sync with a requirements or lock file"
+complete -c pip -n "__fish_use_subcommand_from_list" -a config -d "Show configuration values and the files that set them"
+complete -c pip -n "__fish_use_subcommand_from_list" -a venv -d "Create, inspect and remove virtual environments"
+complete -c pip -n "__fish_use_subcommand_from_list" -a help -d "Explain a workflow with examples"
 
 # Options for install
 complete -c pip -n "__fish_seen_subcommand_from install" -s r -l requirements -d "Requirements file"
 complete -c pip -n "__fish_seen_subcommand_from install" -s o -l output -d "Output directory"
 complete -c pip -n "__fish_seen_subcommand_from install" -s t -l target -d "Target directory"
+complete -c pip -n "__fish_seen_subcommand_from install" -l user -d "Install into the per-user site-packages"
+complete -c pip -n "__fish_seen_subcommand_from install" -s U -l upgrade -d "Upgrade installed packages"
+complete -c pip -n "__fish_seen_subcommand_from install" -l dry-run -d "Show what would be installed without installing"
+complete -c pip -n "__fish_seen_subcommand_from install" -l require-hashes -d "Require a pinned version and hash for every package"
+complete -c pip -n "__fish_seen_subcommand_from install lock" -l resolution-timeout -d "Give up resolving after this many seconds"
+complete -c pip -n "__fish_seen_subcommand_from install lock" -l resolution-stats -d "Print how many candidates and backtracks resolution took"
+complete -c pip -n "__fish_seen_subcommand_from install" -l progress-socket -r -d "Stream install events as JSON lines to this socket"
 
 # Options for uninstall
 complete -c pip -n "__fish_seen_subcommand_from uninstall" -s y -l yes -d "Assume yes"
+complete -c pip -n "__fish_seen_subcommand_from uninstall" -l user -d "Uninstall from the per-user site-packages"
 
 # Options for list
 complete -c pip -n "__fish_seen_subcommand_from list" -l outdated -d "Show outdated packages"
+complete -c pip -n "__fish_seen_subcommand_from list" -l pre -d "Include pre-release versions"
+complete -c pip -n "__fish_seen_subcommand_from list" -l format -xa "columns json freeze" -d "Output format"
 
 # Options for check
 complete -c pip -n "__fish_seen_subcommand_from check" -s p -l package -d "Package name"
@@ -201,9 +290,37 @@ complete -c pip -n "__fish_seen_subcommand_from freeze" -s o -l output -d "Outpu
 complete -c pip -n "__fish_seen_subcommand_from lock" -s r -l requirements -d "Requirements file"
 complete -c pip -n "__fish_seen_subcommand_from lock" -s o -l output -d "Output file"
 
+# Options for debug
+complete -c pip -n "__fish_seen_subcommand_from debug" -l dump-state -d "Write a redacted state bundle"
+complete -c pip -n "__fish_seen_subcommand_from debug" -l packages -d "Packages to include"
+complete -c pip -n "__fish_seen_subcommand_from debug" -l include-bodies -d "Include cached bodies"
+
+# Options for version
+complete -c pip -n "__fish_seen_subcommand_from version" -l json -d "Emit JSON"
+
+# Options for datasets
+complete -c pip -n "__fish_seen_subcommand_from datasets" -a "list update" -d "Datasets action"
+
+# Options for explain-markers
+complete -c pip -n "__fish_seen_subcommand_from explain-markers" -l env -d "Override a marker variable (VARIABLE=VALUE)"
+
+# Options for deps
+complete -c pip -n "__fish_seen_subcommand_from deps" -l flat -d "List packages instead of a tree"
+complete -c pip -n "__fish_seen_subcommand_from deps" -l json -d "Print the closure and its edges as JSON"
+complete -c pip -n "__fish_seen_subcommand_from deps" -l licenses -d "Include licenses"
+complete -c pip -n "__fish_seen_subcommand_from deps" -l env -d "Override a marker variable (VARIABLE=VALUE)"
+
+# Options for config
+complete -c pip -n "__fish_seen_subcommand_from config" -a "list" -d "Config action"
+
+# Topics for help
+complete -c pip -n "__fish_seen_subcommand_from help" -a "requirements lockfiles offline venv migration-from-pip" -d "Help topic"
+complete -c pip -n "__fish_seen_subcommand_from help" -l all -d "Print every topic"
+
 # Global options
 complete -c pip -s h -l help -d "Print help"
 complete -c pip -s V -l version -d "Print version"
+complete -c pip -l ignore-local-config -d "Ignore .pip-rs.toml files in this directory and its parents"
 "#
     );
 }
@@ -220,7 +337,7 @@ $scriptblock = {{
     $command = $commandAst.CommandElements[0].Value
     $words = $commandAst.CommandElements | ForEach-Object {{ $_.Value }}
     
-    $commands = @('install', 'uninstall', 'list', 'show', 'search', 'check', 'update', 'freeze', 'download', 'lock', 'debug', 'help')
+    $commands = @('install', 'uninstall', 'list', 'show', 'inspect', 'search', 'check', 'update', 'freeze', 'download', 'lock', 'debug', 'cache', 'datasets', 'version', 'explain-markers', 'deps', 'config', 'venv', 'help')
     
     if ($words.Count -eq 1) {{
         $commands | Where-Object {{ $_ -like "$wordToComplete*" }} | ForEach-Object {{
@@ -231,13 +348,16 @@ $scriptblock = {{
         $subcommand = $words[1]
         
         $options = switch ($subcommand) {{
-            'install' {{ @('-r', '--requirements', '-o', '--output', '-t', '--target') }}
-            'uninstall' {{ @('-y', '--yes') }}
-            'list' {{ @('--outdated') }}
+            'install' {{ @('-r', '--requirements', '-o', '--output', '-t', '--target', '--user', '-U', '--upgrade', '--dry-run', '--require-hashes', '--resolution-timeout', '--resolution-stats', '--progress-socket') }}
+            'uninstall' {{ @('-y', '--yes', '--user') }}
+            'list' {{ @('--outdated', '--pre', '--format') }}
             'check' {{ @('-p', '--package') }}
             'freeze' {{ @('-o', '--output') }}
-            'lock' {{ @('-r', '--requirements', '-o', '--output') }}
-            default {{ @() }}
+            'lock' {{ @('-r', '--requirements', '-o', '--output', '--resolution-timeout', '--resolution-stats') }}
+            'deps' {{ @('--flat', '--json', '--licenses', '--env') }}
+            'config' {{ @('list') }}
+            'help' {{ @('requirements', 'lockfiles', 'offline', 'venv', 'migration-from-pip', '--all') }}
+            default {{ @('--ignore-local-config') }}
         }}
         
         $options | Where-Object {{ $_ -like "$wordToComplete*" }} | ForEach-Object {{
@@ -0,0 +1,38 @@
+/// Config command - show configuration values and where they come from
+use crate::errors::PipError;
+use pip_rs_core::config::config::Config;
+use pip_rs_core::config::local::LocalConfig;
+use pip_rs_core::utils::redact::redact_url_credentials;
+
+/// Print every setting from pip.conf files and directory-local
+/// `.pip-rs.toml` files, each with the file that set it. Directory values
+/// listed later win over pip.conf values for the same key.
+pub async fn handle_config_list(ignore_local: bool) -> Result<i32, PipError> {
+    let config = Config::global();
+
+    for path in Config::standard_locations() {
+        let entries = Config::read_entries(&path).map_err(|e| PipError::ConfigError {
+            message: format!("{}: {}", path.display(), e),
+        })?;
+        for (section, key, value) in entries {
+            let section = if section.is_empty() { "global" } else { section.as_str() };
+            print_entry(section, &key, &value, &path.display().to_string());
+        }
+    }
+
+    if ignore_local {
+        return Ok(0);
+    }
+    let cwd = std::env::current_dir().map_err(|e| PipError::ConfigError { message: e.to_string() })?;
+    let local = LocalConfig::load(&cwd, config.trusted_local_configs())
+        .map_err(|e| PipError::ConfigError { message: e.to_string() })?;
+    for (section, key, value) in local.entries() {
+        print_entry(section, key, &value.values.join(", "), &value.source.display().to_string());
+    }
+    Ok(0)
+}
+
+fn print_entry(section: &str, key: &str, value: &str, source: &str) {
+    let value = if key == "token" { "****".to_string() } else { redact_url_credentials(value) };
+    println!("{}.{}='{}'  # {}", section, key, value, source);
+}
@@ -0,0 +1,64 @@
+/// Datasets command - list and update the bundled data tables
+use crate::errors::PipError;
+use pip_rs_core::utils::datasets::{self, Datasets};
+use pip_rs_core::{config, network};
+use std::collections::BTreeMap;
+
+/// Show each dataset's source (bundled or updated), version and age
+pub async fn handle_datasets_list() -> Result<i32, PipError> {
+    let datasets = Datasets::from_config(config::config::Config::global());
+    let today = chrono::Local::now().date_naive();
+
+    println!("{:<20} {:<10} {:<12} {:<12} {:>8}", "Dataset", "Source", "Version", "Published", "Age");
+    println!("{}", "-".repeat(66));
+    for asset in datasets.list() {
+        let age = asset
+            .age_days(today)
+            .map(|days| format!("{}d", days))
+            .unwrap_or_else(|| "?".to_string());
+        println!(
+            "{:<20} {:<10} {:<12} {:<12} {:>8}",
+            asset.name, asset.source, asset.version, asset.published, age
+        );
+    }
+    Ok(0)
+}
+
+/// Fetch the signed manifest and its assets, verify them and switch the
+/// data directory over to them
+pub async fn handle_datasets_update(url: Option<String>) -> Result<i32, PipError> {
+    let config = config::config::Config::global();
+    let base = url
+        .or_else(|| config.datasets_url().map(String::from))
+        .unwrap_or_else(|| datasets::DEFAULT_DATASETS_URL.to_string());
+    let base = base.trim_end_matches('/');
+    let datasets = Datasets::from_config(config);
+
+    let client = network::PackageClient::new();
+    let fetch = |file: String| {
+        let client = &client;
+        let url = format!("{}/{}", base, file);
+        async move { client.download_package(&url).await.map_err(PipError::from) }
+    };
+
+    let manifest = fetch(datasets::MANIFEST_FILE.to_string()).await?;
+    let signature = fetch(datasets::SIGNATURE_FILE.to_string()).await?;
+    let signature = String::from_utf8_lossy(&signature).into_owned();
+    let verified = datasets.verify_manifest(&manifest, &signature).map_err(|e| PipError::ConfigError {
+        message: e.to_string(),
+    })?;
+
+    let mut files = BTreeMap::new();
+    for entry in &verified.assets {
+        let bytes = fetch(entry.file.clone()).await?;
+        files.insert(entry.file.clone(), bytes.to_vec());
+    }
+    let installed = datasets.install(&manifest, &signature, &files).map_err(|e| PipError::ConfigError {
+        message: e.to_string(),
+    })?;
+
+    for entry in &installed.assets {
+        println!("Updated {} to {} ({})", entry.name, entry.version, entry.published);
+    }
+    Ok(0)
+}
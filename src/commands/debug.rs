@@ -1,176 +1,395 @@
-/// Debug command - display system and environment information
+/// Debug command - what pip-rs sees of the system, for bug reports
+///
+/// Prints the build, the Python interpreter and platform, site-packages,
+/// every config source with the values it set, the indexes, network mode,
+/// the cache and the compatible wheel tags; `--format json` emits the same
+/// sections as one object. Credentials in URLs and tokens are masked.
+///
+/// `--dump-state` writes a redacted state bundle for bug reports instead;
+/// the hidden `dev-state` command loads one into a sandbox and can replay
+/// a resolution against it offline.
 use crate::errors::PipError;
 use crate::installer::SitePackages;
+use pip_rs_core::cache::disk_cache::CacheStats;
+use pip_rs_core::cache::{DiskCache, WheelCache};
+use pip_rs_core::config::config::{Config, ConfigSource};
+use pip_rs_core::installer::sizes::format_size;
+use pip_rs_core::network::proxy::ProxyConfig;
+use pip_rs_core::utils::build_info::BuildInfo;
+use pip_rs_core::utils::environment_markers::EnvironmentContext;
+use pip_rs_core::utils::redact::redact_url_credentials;
+use pip_rs_core::utils::state_bundle::{self, DumpSources};
+use pip_rs_core::utils::{python_env, tags};
+use pip_rs_core::{cache::layout, config, models, network, resolver};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-pub async fn handle_debug() -> Result<i32, PipError> {
-    println!("pip-rs debug information\n");
+/// Environment variables captured in a bundle besides `PIP_*`
+const BUNDLED_VARIABLES: &[&str] = &["VIRTUAL_ENV", "PYTHON_VERSION", "HTTP_PROXY", "HTTPS_PROXY", "NO_PROXY"];
 
-    // System information
-    println!("=== System Information ===");
-    print_system_info();
+/// Compatible tags shown without `--verbose`
+const SHOWN_TAGS: usize = 10;
 
-    // Python information
-    println!("\n=== Python Information ===");
-    print_python_info();
+/// How `pip debug` prints its report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum DebugFormat {
+    /// One titled block per section
+    #[default]
+    Human,
+    /// One object with a key per section
+    Json,
+}
+
+#[derive(Debug, Serialize)]
+struct DebugReport {
+    pip_rs: BuildInfo,
+    python: PythonSection,
+    environment: EnvironmentSection,
+    /// In load order
+    config: Vec<ConfigSource>,
+    indexes: IndexSection,
+    network: NetworkSection,
+    /// `None` when no cache directory is usable
+    cache: Option<CacheSection>,
+    /// Most preferred first
+    compatible_tags: Vec<String>,
+}
 
-    // pip-rs configuration
-    println!("\n=== pip-rs Configuration ===");
-    print_pip_config();
+#[derive(Debug, Serialize)]
+struct PythonSection {
+    interpreter: Option<PathBuf>,
+    version: String,
+    implementation: String,
+    sys_platform: String,
+    platform_machine: String,
+}
 
-    // Installed packages
-    println!("\n=== Installed Packages ===");
-    print_installed_packages().await?;
+#[derive(Debug, Serialize)]
+struct EnvironmentSection {
+    virtual_env: Option<PathBuf>,
+    site_packages: Vec<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    site_packages_error: Option<String>,
+}
 
-    // Network information
-    println!("\n=== Network Information ===");
-    print_network_info();
+#[derive(Debug, Serialize)]
+struct IndexSection {
+    index_url: String,
+    extra_index_urls: Vec<String>,
+    find_links: Vec<String>,
+    trusted_hosts: Vec<String>,
+    no_index: bool,
+}
 
-    Ok(0)
+#[derive(Debug, Serialize)]
+struct NetworkSection {
+    offline: bool,
+    /// How requests to the index are routed
+    route: String,
+    /// DNS lookup of pypi.org; skipped offline
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pypi_resolution: Option<String>,
 }
 
-fn print_system_info() {
-    println!("OS: {}", std::env::consts::OS);
-    println!("Architecture: {}", std::env::consts::ARCH);
-    println!("Family: {}", std::env::consts::FAMILY);
+#[derive(Debug, Serialize)]
+struct CacheSection {
+    directory: PathBuf,
+    layout_version: Option<u32>,
+    http: CacheStats,
+    packages: CacheStats,
+    wheels: CacheStats,
+}
 
-    #[cfg(target_os = "macos")]
-    {
-        println!("Platform: macOS");
+impl DebugReport {
+    fn collect() -> Self {
+        let config = Config::global();
+        let environment = models::Environment::current();
+        let python = python_env::detected();
+        let virtual_env = python_env::virtual_env();
+        let (site_packages, site_packages_error) = match SitePackages::default() {
+            Ok(site_packages) => (site_packages.get_all_directories(), None),
+            Err(e) => (Vec::new(), Some(e.to_string())),
+        };
+        let offline = network::is_offline();
+        let redact_all = |urls: &[String]| urls.iter().map(|url| redact_url_credentials(url)).collect();
+        Self {
+            pip_rs: super::version::build_info(),
+            python: PythonSection {
+                interpreter: python_env::interpreter(virtual_env.as_deref()),
+                version: python.full_version.clone(),
+                implementation: python.platform_python_implementation(),
+                sys_platform: environment.sys_platform,
+                platform_machine: environment.platform_machine,
+            },
+            environment: EnvironmentSection { virtual_env, site_packages, site_packages_error },
+            config: config.sources().iter().map(redact_source).collect(),
+            indexes: IndexSection {
+                index_url: redact_url_credentials(config.index_url()),
+                extra_index_urls: redact_all(config.extra_index_urls()),
+                find_links: redact_all(config.find_links()),
+                trusted_hosts: config.trusted_hosts().to_vec(),
+                no_index: config.no_index(),
+            },
+            network: NetworkSection {
+                offline,
+                route: ProxyConfig::new(config.proxy()).describe(config.index_url()),
+                pypi_resolution: (!offline).then(resolve_pypi),
+            },
+            cache: layout::cache_root().map(|root| cache_section(root, config)),
+            compatible_tags: tags::current().tags().iter().map(ToString::to_string).collect(),
+        }
     }
-    #[cfg(target_os = "linux")]
-    {
-        println!("Platform: Linux");
+}
+
+pub async fn handle_debug(format: DebugFormat, verbose: bool) -> Result<i32, PipError> {
+    let report = DebugReport::collect();
+    if format == DebugFormat::Json {
+        let json = serde_json::to_string_pretty(&report).map_err(|e| PipError::InvalidPackage {
+            name: "debug".to_string(),
+            reason: e.to_string(),
+        })?;
+        println!("{}", json);
+        return Ok(0);
     }
-    #[cfg(target_os = "windows")]
-    {
-        println!("Platform: Windows");
+
+    println!("pip-rs debug information");
+
+    let build = &report.pip_rs;
+    println!("\n=== pip-rs ===");
+    println!("Version: {} ({})", build.version, build.git_describe);
+    println!("Built with: {} for {} ({})", build.rustc, build.target, build.profile);
+
+    let python = &report.python;
+    println!("\n=== Python ===");
+    match &python.interpreter {
+        Some(interpreter) => println!("Interpreter: {}", interpreter.display()),
+        None => println!("Interpreter: not found"),
     }
+    println!("Version: {} ({})", python.version, python.implementation);
+    println!("sys.platform: {}", python.sys_platform);
+    println!("Machine: {}", python.platform_machine);
 
-    if let Ok(home) = std::env::var("HOME") {
-        println!("Home: {}", home);
+    let environment = &report.environment;
+    println!("\n=== Environment ===");
+    match &environment.virtual_env {
+        Some(venv) => println!("Virtual environment: {}", venv.display()),
+        None => println!("Virtual environment: not active"),
+    }
+    if let Some(error) = &environment.site_packages_error {
+        println!("Site-packages: not found ({})", error);
+    }
+    for path in &environment.site_packages {
+        println!("Site-packages: {}", path.display());
     }
 
-    if let Ok(path) = std::env::var("PATH") {
-        let paths: Vec<&str> = path.split(':').collect();
-        println!("PATH entries: {}", paths.len());
+    println!("\n=== Configuration (later sources win) ===");
+    if report.config.is_empty() {
+        println!("No config files or PIP_* variables; built-in defaults apply");
+    }
+    for source in &report.config {
+        println!("{}:", source.origin);
+        for setting in &source.settings {
+            println!("  {} = {}", setting.key, setting.value);
+        }
+    }
+
+    let indexes = &report.indexes;
+    println!("\n=== Indexes ===");
+    if indexes.no_index {
+        println!("Index: disabled (no-index)");
+    } else {
+        println!("Index URL: {}", indexes.index_url);
+    }
+    for url in &indexes.extra_index_urls {
+        println!("Extra index URL: {}", url);
+    }
+    for link in &indexes.find_links {
+        println!("Find links: {}", link);
+    }
+    if !indexes.trusted_hosts.is_empty() {
+        println!("Trusted hosts: {}", indexes.trusted_hosts.join(", "));
     }
-}
 
-fn print_python_info() {
-    // Get Python version from environment
-    if let Ok(version) = std::env::var("PYTHON_VERSION") {
-        println!("Python version: {}", version);
+    let network = &report.network;
+    println!("\n=== Network ===");
+    if network.offline {
+        println!("Offline mode: active (only cached responses and wheels are used)");
     } else {
-        println!("Python version: Not set");
+        println!("Offline mode: inactive");
+    }
+    println!("Route to the index: {}", network.route);
+    if let Some(resolution) = &network.pypi_resolution {
+        println!("PyPI resolution: {}", resolution);
     }
 
-    // Check site-packages
-    match SitePackages::default() {
-        Ok(site_packages) => {
-            println!("\nSite Packages:");
-            for path in site_packages.get_all_directories() {
-                println!("  - {}", path.display());
+    println!("\n=== Cache ===");
+    match &report.cache {
+        Some(cache) => {
+            println!("Directory: {}", cache.directory.display());
+            match cache.layout_version {
+                Some(version) => println!("Layout version: {}", version),
+                None => println!("Layout version: unknown"),
+            }
+            for (label, stats) in
+                [("HTTP responses", cache.http), ("Package metadata", cache.packages), ("Wheels", cache.wheels)]
+            {
+                println!("{}: {} entries ({})", label, stats.entries, format_size(stats.total_size));
             }
         }
-        Err(e) => {
-            println!("\nSite Packages: Error detection ({})", e);
-        }
+        None => println!("Directory: not available"),
     }
 
-    // Virtual environment detection
-    if std::env::var("VIRTUAL_ENV").is_ok() {
-        println!("Virtual environment: Active");
-    } else {
-        println!("Virtual environment: Not active");
+    let tags = &report.compatible_tags;
+    println!("\n=== Compatible tags: {} ===", tags.len());
+    let shown = if verbose { tags.len() } else { SHOWN_TAGS.min(tags.len()) };
+    for tag in &tags[..shown] {
+        println!("  {}", tag);
     }
+    if shown < tags.len() {
+        println!("  ...[First {} tags shown. Pass --verbose to show all.]", shown);
+    }
+    Ok(0)
 }
 
-fn print_pip_config() {
-    println!("pip-rs version: 0.1.0");
-    println!("Rust edition: 2021");
+/// `source` with URL credentials and tokens masked
+fn redact_source(source: &ConfigSource) -> ConfigSource {
+    let mut source = source.clone();
+    for setting in &mut source.settings {
+        let key = setting.key.to_lowercase();
+        setting.value = if key.ends_with("token") || key.contains("password") {
+            "****".to_string()
+        } else {
+            redact_url_credentials(&setting.value)
+        };
+    }
+    source
+}
 
-    // Check for pip.conf
-    let home = std::env::var("HOME").unwrap_or_default();
-    let pip_conf = format!("{}/.pip/pip.conf", home);
-    if std::path::Path::new(&pip_conf).exists() {
-        println!("pip.conf: Found at {}", pip_conf);
-    } else {
-        println!("pip.conf: Not found");
+fn resolve_pypi() -> String {
+    match std::net::ToSocketAddrs::to_socket_addrs(&("pypi.org", 443)) {
+        Ok(addrs) => format!("OK ({} addresses)", addrs.count()),
+        Err(e) => format!("Failed - {}", e),
     }
+}
 
-    // Check for pyproject.toml
-    if std::path::Path::new("pyproject.toml").exists() {
-        println!("pyproject.toml: Found");
-    } else {
-        println!("pyproject.toml: Not found");
+fn cache_section(root: PathBuf, config: &Config) -> CacheSection {
+    let stats = |dir: &str| {
+        DiskCache::new(&root.join(dir), config.cache_ttl()).map(|cache| cache.stats()).unwrap_or_default()
+    };
+    let wheels = WheelCache::new(root.join(layout::WHEELS_DIR)).list();
+    CacheSection {
+        layout_version: layout::read_layout_version(&root).ok(),
+        http: stats(layout::HTTP_DIR),
+        packages: stats(layout::PACKAGES_DIR),
+        wheels: CacheStats { entries: wheels.len() as u64, total_size: wheels.iter().map(|wheel| wheel.size).sum() },
+        directory: root,
     }
+}
 
-    // Check for requirements.txt
-    if std::path::Path::new("requirements.txt").exists() {
-        println!("requirements.txt: Found");
-    } else {
-        println!("requirements.txt: Not found");
-    }
-}
-
-async fn print_installed_packages() -> Result<(), PipError> {
-    match SitePackages::default() {
-        Ok(site_packages) => {
-            match site_packages.get_installed_packages() {
-                Ok(packages) => {
-                    println!("Total packages: {}", packages.len());
-                    if packages.len() <= 10 {
-                        for pkg in packages {
-                            println!("  - {}", pkg);
-                        }
-                    } else {
-                        for pkg in packages.iter().take(5) {
-                            println!("  - {}", pkg);
-                        }
-                        println!("  ... and {} more", packages.len() - 5);
-                    }
-                }
-                Err(e) => {
-                    println!("Error listing packages: {}", e);
-                }
-            }
-        }
-        Err(e) => {
-            println!("Error accessing site-packages: {}", e);
+/// Write a redacted state bundle to `dir` for the given packages
+pub async fn handle_dump_state(dir: String, packages: Vec<String>, include_bodies: bool) -> Result<i32, PipError> {
+    let config = config::config::Config::global();
+    let cache_root = layout::cache_root();
+    let variables = std::env::vars()
+        .filter(|(name, _)| name.starts_with("PIP_") || BUNDLED_VARIABLES.contains(&name.as_str()))
+        .collect();
+    let sources = DumpSources {
+        cache_root: cache_root.as_deref(),
+        config,
+        installed: installed_packages(),
+        markers: EnvironmentContext::default().to_marker_vars().into_iter().collect(),
+        variables,
+        build: Some(super::version::build_info()),
+    };
+
+    let manifest = state_bundle::dump(Path::new(&dir), &sources, &packages, include_bodies).map_err(|e| {
+        PipError::FileSystemError {
+            path: dir.clone(),
+            operation: "dump state".to_string(),
+            reason: format!("{:#}", e),
         }
+    })?;
+
+    println!("State bundle written to {}", dir);
+    for file in &manifest.files {
+        println!("  - {}", file);
+    }
+    if !include_bodies && !packages.is_empty() {
+        println!("Cache bodies were not included; re-run with --include-bodies to allow offline replay");
     }
+    println!("Secrets and user names have been redacted; review the files before sharing them.");
+    Ok(0)
+}
 
-    Ok(())
+fn installed_packages() -> Vec<(String, String)> {
+    let Ok(site_packages) = SitePackages::default() else {
+        return Vec::new();
+    };
+    site_packages
+        .get_installed_packages()
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|dir| dir.strip_suffix(".dist-info")?.rsplit_once('-'))
+        .map(|(name, version)| (name.to_string(), version.to_string()))
+        .collect()
 }
 
-fn print_network_info() {
-    // Check network connectivity
-    println!("PyPI URL: https://pypi.org/simple/");
-    println!("Network: Checking connectivity...");
+/// Rebuild a bundle's cache and config in a sandbox and optionally replay
+/// a resolution of `resolve` against it
+pub async fn handle_load_state(bundle: String, sandbox: Option<String>, resolve: Vec<String>) -> Result<i32, PipError> {
+    let sandbox = sandbox
+        .map(PathBuf::from)
+        .unwrap_or_else(|| std::env::temp_dir().join(format!("pip-rs-state-{}", std::process::id())));
+    let loaded = state_bundle::load(Path::new(&bundle), &sandbox).map_err(|e| PipError::InvalidPackage {
+        name: bundle.clone(),
+        reason: format!("{:#}", e),
+    })?;
 
-    // Try to resolve PyPI
-    match std::net::ToSocketAddrs::to_socket_addrs(&("pypi.org", 443)) {
-        Ok(addrs) => {
-            let count = addrs.count();
-            println!("PyPI resolution: OK ({} addresses)", count);
-        }
-        Err(e) => {
-            println!("PyPI resolution: Failed - {}", e);
-        }
+    let manifest = &loaded.manifest;
+    println!(
+        "Loaded bundle from pip-rs {} ({}/{}), created {}",
+        manifest.pip_rs_version, manifest.os, manifest.arch, manifest.created_at
+    );
+    if let Some(build) = &manifest.build {
+        println!("  Build: {} ({}, {})", build.git_describe, build.build_date, build.rustc);
     }
-
-    // Check for proxy settings
-    if let Ok(proxy) = std::env::var("HTTP_PROXY") {
-        println!("HTTP_PROXY: {}", proxy);
+    println!("  Cache entries restored: {}", loaded.restored);
+    if loaded.metadata_only > 0 {
+        println!("  Entries without bodies: {}", loaded.metadata_only);
     }
-    if let Ok(proxy) = std::env::var("HTTPS_PROXY") {
-        println!("HTTPS_PROXY: {}", proxy);
+    println!("\nTo run pip-rs against this state:");
+    println!("  export PIP_CACHE_DIR={}", loaded.cache_root.display());
+    println!("  export HOME={}", sandbox.display());
+
+    if resolve.is_empty() {
+        return Ok(0);
     }
+    let requirements = resolve
+        .iter()
+        .map(|r| r.parse::<models::Requirement>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| PipError::InvalidRequirement {
+            spec: resolve.join(" "),
+            reason: e.to_string(),
+        })?;
+    let provider = resolver::CachedProvider::new(loaded.cache_root.join(layout::PACKAGES_DIR)).map_err(|e| {
+        PipError::FileSystemError {
+            path: loaded.cache_root.display().to_string(),
+            operation: "open cache".to_string(),
+            reason: e.to_string(),
+        }
+    })?;
+    let mut resolver = resolver::Resolver::new();
+    resolver.set_provider(Arc::new(provider));
+    let mut resolved = resolver.resolve(requirements).await?;
+    resolved.sort_by(|a, b| a.name.cmp(&b.name));
 
-    // Check for pip index settings
-    if let Ok(index) = std::env::var("PIP_INDEX_URL") {
-        println!("PIP_INDEX_URL: {}", index);
+    println!("\nReplayed resolution:");
+    for package in resolved {
+        println!("  {}=={}", package.name, package.version);
     }
+    Ok(0)
 }
 
 #[cfg(test)]
@@ -179,8 +398,34 @@ mod tests {
 
     #[tokio::test]
     async fn test_debug_command() {
-        let result = handle_debug().await;
+        let result = handle_debug(DebugFormat::Human, false).await;
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), 0);
     }
+
+    #[test]
+    fn test_report_has_every_section() {
+        let json = serde_json::to_value(DebugReport::collect()).unwrap();
+        for section in ["pip_rs", "python", "environment", "config", "indexes", "network", "compatible_tags"] {
+            assert!(json.get(section).is_some(), "missing {}", section);
+        }
+        assert_eq!(json["pip_rs"]["version"], env!("CARGO_PKG_VERSION"));
+        assert!(json["python"]["sys_platform"].is_string());
+        assert!(json["compatible_tags"].as_array().is_some_and(|tags| !tags.is_empty()));
+    }
+
+    #[test]
+    fn test_config_values_are_redacted() {
+        let setting = |key: &str, value: &str| config::config::ConfigSetting {
+            key: key.to_string(),
+            value: value.to_string(),
+        };
+        let source = ConfigSource {
+            origin: "pip.conf".to_string(),
+            settings: vec![setting("index-url", "https://u:pw@host/simple"), setting("index \"corp\".token", "s3cret")],
+        };
+        let redacted = redact_source(&source);
+        assert_eq!(redacted.settings[0].value, "https://****@host/simple");
+        assert_eq!(redacted.settings[1].value, "****");
+    }
 }
@@ -0,0 +1,398 @@
+/// Deps command - the dependency closure of one requirement, without installing
+///
+/// Resolves the requirement the way `pip install` would (same indexes,
+/// caches and marker evaluation, optionally against `--env` overrides) and
+/// prints the closure as a tree or flat list with aggregate stats: package
+/// count, total wheel download size and the release date range. Only
+/// metadata is fetched; wheel sizes come from the index's file listing.
+use crate::errors::{Phase, PipError};
+use crate::utils::pager::PagedOutput;
+use async_trait::async_trait;
+use pip_rs_core::installer::sizes::format_size;
+use pip_rs_core::models::{Package, Requirement};
+use pip_rs_core::network::ReleaseFile;
+use pip_rs_core::resolver::Resolver;
+use pip_rs_core::utils::names::canonicalize_name;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Where release file metadata comes from (the index, or a fixture)
+#[async_trait]
+pub trait ReleaseSource: Send + Sync {
+    async fn release_files(&self, name: &str) -> anyhow::Result<Vec<ReleaseFile>>;
+}
+
+/// The configured index's JSON API
+pub struct IndexReleases;
+
+#[async_trait]
+impl ReleaseSource for IndexReleases {
+    async fn release_files(&self, name: &str) -> anyhow::Result<Vec<ReleaseFile>> {
+        pip_rs_core::network::get_release_files(name).await
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct ClosurePackage {
+    name: String,
+    version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    license: Option<String>,
+    /// Download size of the wheel an install would pick
+    wheel_size: Option<u64>,
+    /// When the release was uploaded (ISO 8601)
+    uploaded: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct Edge {
+    from: String,
+    to: String,
+}
+
+/// A resolved closure with its edges
+#[derive(Debug, Clone, Serialize)]
+struct Closure {
+    requirement: String,
+    root: String,
+    packages: Vec<ClosurePackage>,
+    edges: Vec<Edge>,
+    total_wheel_size: u64,
+    /// Packages without a wheel on the index
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    without_wheels: Vec<String>,
+}
+
+pub async fn handle_deps(
+    spec: String,
+    flat: bool,
+    json: bool,
+    licenses: bool,
+    env_overrides: Vec<String>,
+) -> Result<i32, PipError> {
+    let requirement: Requirement = spec.parse().map_err(|reason| PipError::InvalidRequirement {
+        spec: spec.clone(),
+        reason,
+    })?;
+    let env = super::explain_markers::environment_with(&env_overrides)?;
+    let resolver = Resolver::with_environment(env);
+    let closure = resolve_closure(&spec, requirement, resolver, &IndexReleases).await?;
+
+    let mut out = PagedOutput::new();
+    if json {
+        let closure = if licenses { closure } else { closure.without_licenses() };
+        let body = serde_json::to_string_pretty(&closure).map_err(|e| PipError::InvalidPackage {
+            name: spec.clone(),
+            reason: e.to_string(),
+        })?;
+        out.line(body);
+    } else {
+        let lines = if flat { closure.flat_lines(licenses) } else { closure.tree_lines(licenses) };
+        for line in lines {
+            out.line(line);
+        }
+        out.line(String::new());
+        for line in closure.stats_lines(licenses) {
+            out.line(line);
+        }
+    }
+    out.finish();
+    Ok(0)
+}
+
+/// Resolve `requirement` and attach release file metadata to every package
+async fn resolve_closure(
+    spec: &str,
+    requirement: Requirement,
+    mut resolver: Resolver,
+    releases: &dyn ReleaseSource,
+) -> Result<Closure, PipError> {
+    let root_name = requirement.name.clone();
+    let resolved = resolver
+        .resolve(vec![requirement])
+        .await
+        .map_err(|e| PipError::from(e).in_phase(Phase::Resolution, Some(&root_name)))?;
+    super::install::check_not_found(&resolver)?;
+    if !resolved.iter().any(|p| canonicalize_name(&p.name) == canonicalize_name(&root_name)) {
+        return Err(PipError::DependencyResolutionError {
+            package: root_name.clone(),
+            reason: format!("no release of {} satisfies {}", root_name, spec),
+        }
+        .in_phase(Phase::Resolution, Some(&root_name)));
+    }
+
+    let files = futures::future::join_all(resolved.iter().map(|p| releases.release_files(&p.name))).await;
+    let mut packages: Vec<ClosurePackage> = resolved
+        .iter()
+        .zip(files)
+        .map(|(package, files)| closure_package(package, files.unwrap_or_default()))
+        .collect();
+    packages.sort_by_key(|p| canonicalize_name(&p.name));
+
+    // Edges between resolved packages, under their resolved names
+    let names: HashMap<String, String> =
+        packages.iter().map(|p| (canonicalize_name(&p.name), p.name.clone())).collect();
+    let mut edges: Vec<Edge> = resolver
+        .dependency_edges()
+        .iter()
+        .flat_map(|(from, deps)| deps.iter().map(move |to| (from, to)))
+        .filter_map(|(from, to)| {
+            Some(Edge {
+                from: names.get(&canonicalize_name(from))?.clone(),
+                to: names.get(&canonicalize_name(to))?.clone(),
+            })
+        })
+        .collect();
+    edges.sort_by(|a, b| (&a.from, &a.to).cmp(&(&b.from, &b.to)));
+    edges.dedup();
+
+    let root = names.get(&canonicalize_name(&root_name)).cloned().unwrap_or(root_name);
+    Ok(Closure {
+        requirement: spec.to_string(),
+        root,
+        total_wheel_size: packages.iter().filter_map(|p| p.wheel_size).sum(),
+        without_wheels: packages.iter().filter(|p| p.wheel_size.is_none()).map(|p| p.name.clone()).collect(),
+        packages,
+        edges,
+    })
+}
+
+fn closure_package(package: &Package, files: Vec<ReleaseFile>) -> ClosurePackage {
+    let wheel = ReleaseFile::preferred_wheel(&files);
+    ClosurePackage {
+        name: package.name.clone(),
+        version: package.version.clone(),
+        license: package.license.clone().filter(|l| !l.trim().is_empty()),
+        wheel_size: wheel.and_then(|w| w.size),
+        uploaded: files.iter().filter_map(|f| f.uploaded.clone()).min(),
+    }
+}
+
+impl Closure {
+    fn without_licenses(mut self) -> Self {
+        for package in &mut self.packages {
+            package.license = None;
+        }
+        self
+    }
+
+    fn package(&self, name: &str) -> Option<&ClosurePackage> {
+        self.packages.iter().find(|p| p.name == name)
+    }
+
+    fn label(&self, name: &str, licenses: bool) -> String {
+        let Some(package) = self.package(name) else {
+            return name.to_string();
+        };
+        let mut label = format!("{} {}", package.name, package.version);
+        if licenses {
+            label.push_str(&format!(" [{}]", package.license.as_deref().unwrap_or("unknown license")));
+        }
+        label
+    }
+
+    /// `root` and its dependencies, each repeated subtree shown once and
+    /// marked `(*)` afterwards
+    fn tree_lines(&self, licenses: bool) -> Vec<String> {
+        let mut children: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+        for edge in &self.edges {
+            children.entry(edge.from.as_str()).or_default().push(edge.to.as_str());
+        }
+        let mut lines = vec![self.label(&self.root, licenses)];
+        let mut expanded = HashSet::from([self.root.as_str()]);
+        self.tree_children(&self.root, "", &children, &mut expanded, licenses, &mut lines);
+        lines
+    }
+
+    fn tree_children<'a>(
+        &'a self,
+        parent: &str,
+        prefix: &str,
+        children: &BTreeMap<&'a str, Vec<&'a str>>,
+        expanded: &mut HashSet<&'a str>,
+        licenses: bool,
+        lines: &mut Vec<String>,
+    ) {
+        let Some(deps) = children.get(parent) else {
+            return;
+        };
+        for (i, dep) in deps.iter().enumerate() {
+            let last = i + 1 == deps.len();
+            let repeated = !expanded.insert(dep) && children.contains_key(dep);
+            lines.push(format!(
+                "{}{}{}{}",
+                prefix,
+                if last { "└── " } else { "├── " },
+                self.label(dep, licenses),
+                if repeated { " (*)" } else { "" }
+            ));
+            if !repeated {
+                let prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+                self.tree_children(dep, &prefix, children, expanded, licenses, lines);
+            }
+        }
+    }
+
+    fn flat_lines(&self, licenses: bool) -> Vec<String> {
+        self.packages
+            .iter()
+            .map(|p| {
+                let size = p.wheel_size.map(format_size).unwrap_or_else(|| "-".to_string());
+                let mut line = format!("{:<40} {:<15} {:>10}", p.name, p.version, size);
+                if licenses {
+                    line.push_str(&format!("  {}", p.license.as_deref().unwrap_or("unknown")));
+                }
+                line
+            })
+            .collect()
+    }
+
+    fn stats_lines(&self, licenses: bool) -> Vec<String> {
+        let mut lines = vec![
+            format!("Packages: {}", self.packages.len()),
+            format!("Total wheel download size: {}", format_size(self.total_wheel_size)),
+        ];
+        if !self.without_wheels.is_empty() {
+            lines.push(format!("No wheel on the index: {}", self.without_wheels.join(", ")));
+        }
+        let dated: Vec<(&str, &ClosurePackage)> = self
+            .packages
+            .iter()
+            .filter_map(|p| Some((p.uploaded.as_deref()?, p)))
+            .collect();
+        if let (Some(oldest), Some(newest)) = (dated.iter().min_by_key(|d| d.0), dated.iter().max_by_key(|d| d.0)) {
+            let day = |date: &str| date.get(..10).unwrap_or(date).to_string();
+            lines.push(format!("Newest release: {} {} ({})", newest.1.name, newest.1.version, day(newest.0)));
+            lines.push(format!("Oldest release: {} {} ({})", oldest.1.name, oldest.1.version, day(oldest.0)));
+        }
+        if licenses {
+            let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+            for package in &self.packages {
+                *counts.entry(package.license.as_deref().unwrap_or("unknown")).or_default() += 1;
+            }
+            let summary: Vec<String> = counts.iter().map(|(license, n)| format!("{} ({})", license, n)).collect();
+            lines.push(format!("Licenses: {}", summary.join(", ")));
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pip_rs_core::resolver::provider::MetadataProvider;
+    use std::sync::Arc;
+
+    /// (name, version, requires, uploaded, [(filename, size)])
+    type FixtureRelease = (&'static str, &'static str, &'static [&'static str], &'static str, &'static [(&'static str, u64)]);
+
+    /// Index fixture: package metadata plus each release's files
+    struct FixtureIndex {
+        packages: HashMap<String, Package>,
+        files: HashMap<String, Vec<ReleaseFile>>,
+    }
+
+    impl FixtureIndex {
+        fn new() -> Arc<Self> {
+            let graph: &[FixtureRelease] = &[
+                ("web", "2.0", &["http>=1", "json-lib", "log; sys_platform == \"win32\""], "2026-03-01T10:00:00Z",
+                    &[("web-2.0-py3-none-any.whl", 1000), ("web-2.0.tar.gz", 9000)]),
+                ("http", "1.4", &["json-lib>=1", "sockets"], "2025-06-15T08:00:00Z",
+                    &[("http-1.4-cp311-cp311-manylinux_2_17_s390x.whl", 5000), ("http-1.4-py3-none-any.whl", 2500)]),
+                ("json-lib", "1.1", &[], "2024-01-20T00:00:00Z", &[("json_lib-1.1-py3-none-any.whl", 300)]),
+                ("sockets", "0.9", &[], "2023-11-05T00:00:00Z", &[("sockets-0.9.tar.gz", 700)]),
+                ("log", "1.0", &[], "2026-01-01T00:00:00Z", &[("log-1.0-py3-none-any.whl", 50)]),
+            ];
+            let mut packages = HashMap::new();
+            let mut files = HashMap::new();
+            for (name, version, deps, uploaded, release_files) in graph {
+                let mut package = Package::new(name.to_string(), version.to_string())
+                    .with_requires(deps.iter().map(|d| d.to_string()).collect());
+                package.license = Some(if *name == "sockets" { "BSD" } else { "MIT" }.to_string());
+                packages.insert(name.to_string(), package);
+                files.insert(
+                    name.to_string(),
+                    release_files
+                        .iter()
+                        .map(|(filename, size)| ReleaseFile {
+                            filename: filename.to_string(),
+                            url: format!("https://files.example/{}", filename),
+                            size: Some(*size),
+                            uploaded: Some(uploaded.to_string()),
+                            sha256: None,
+                        })
+                        .collect(),
+                );
+            }
+            Arc::new(Self { packages, files })
+        }
+    }
+
+    #[async_trait]
+    impl MetadataProvider for FixtureIndex {
+        async fn get_package(&self, name: &str) -> anyhow::Result<Package> {
+            self.packages.get(name).cloned().ok_or_else(|| anyhow::anyhow!("{} not found", name))
+        }
+    }
+
+    #[async_trait]
+    impl ReleaseSource for FixtureIndex {
+        async fn release_files(&self, name: &str) -> anyhow::Result<Vec<ReleaseFile>> {
+            Ok(self.files.get(name).cloned().unwrap_or_default())
+        }
+    }
+
+    async fn resolve_fixture(spec: &str) -> Result<Closure, PipError> {
+        let index = FixtureIndex::new();
+        let mut env = pip_rs_core::models::Environment::current();
+        env.set("sys_platform", "linux").unwrap();
+        let mut resolver = Resolver::with_environment(env);
+        resolver.set_provider(index.clone());
+        resolve_closure(spec, spec.parse().unwrap(), resolver, index.as_ref()).await
+    }
+
+    #[tokio::test]
+    async fn test_tree_and_size_aggregation() {
+        let closure = resolve_fixture("web>=1.0").await.unwrap();
+        assert_eq!(
+            closure.tree_lines(false),
+            [
+                "web 2.0",
+                "├── http 1.4",
+                "│   ├── json-lib 1.1",
+                "│   └── sockets 0.9",
+                "└── json-lib 1.1",
+            ]
+        );
+        assert_eq!(closure.tree_lines(true)[4], "└── json-lib 1.1 [MIT]");
+
+        // Preferred wheels only: 1000 + 2500 (pure wheel over one for another platform) + 300
+        assert_eq!(closure.total_wheel_size, 3800);
+        assert_eq!(closure.without_wheels, ["sockets"]);
+        let stats = closure.stats_lines(true);
+        assert_eq!(stats[0], "Packages: 4");
+        assert!(stats.contains(&"Newest release: web 2.0 (2026-03-01)".to_string()), "{:?}", stats);
+        assert!(stats.contains(&"Oldest release: sockets 0.9 (2023-11-05)".to_string()), "{:?}", stats);
+        assert!(stats.contains(&"Licenses: BSD (1), MIT (3)".to_string()), "{:?}", stats);
+    }
+
+    #[tokio::test]
+    async fn test_json_edge_list_and_unsatisfiable_spec() {
+        let closure = resolve_fixture("web").await.unwrap();
+        let json = serde_json::to_value(closure.without_licenses()).unwrap();
+        let edges: Vec<(&str, &str)> = json["edges"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| (e["from"].as_str().unwrap(), e["to"].as_str().unwrap()))
+            .collect();
+        assert_eq!(edges, [("http", "json-lib"), ("http", "sockets"), ("web", "http"), ("web", "json-lib")]);
+        assert_eq!(json["root"], "web");
+        assert_eq!(json["packages"][0]["name"], "http");
+        assert_eq!(json["packages"][0]["wheel_size"], 2500);
+        assert!(json["packages"][0].get("license").is_none());
+
+        let err = resolve_fixture("web>=3").await.unwrap_err().to_string();
+        assert!(err.contains("no release of web satisfies web>=3"), "{}", err);
+    }
+}
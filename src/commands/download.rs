@@ -1,42 +1,166 @@
 /// Download command - download packages without installing
+///
+/// With `--platform`, `--python-version`, `--implementation` or `--abi`
+/// the files are picked for that environment instead of this one: markers
+/// are evaluated for it and wheels ranked by its tags. Sdists can't be
+/// built for another environment, so those options need `--only-binary
+/// :all:`, and a package without a fitting wheel fails the download.
+///
+/// `--no-deps` downloads only the named packages and `--only-deps` only
+/// their dependencies, as for install.
+use crate::cli::parser::TargetOptions;
 use crate::errors::PipError;
-use anyhow::{Result, anyhow};
+use anyhow::Result;
 use std::path::Path;
+use pip_rs_core::network::{FallbackPolicy, ReleaseFile};
+use pip_rs_core::utils::redact::redact_url_credentials;
+use pip_rs_core::utils::requirements_parser::{FileOptions, RequirementsFile};
+use pip_rs_core::utils::environment_markers::EnvironmentContext;
+use pip_rs_core::utils::tags::{TagPriority, TargetEnvironment};
 use pip_rs_core::{models, resolver, network};
 
 pub async fn handle_download(
     packages: Vec<String>,
     requirements: Option<String>,
     destination: Option<String>,
+    cli_options: FileOptions,
+    target: TargetOptions,
+    no_deps: bool,
+    only_deps: bool,
 ) -> Result<i32, PipError> {
+    let dependency_mode = super::install::dependency_mode(no_deps, only_deps)?;
     if packages.is_empty() && requirements.is_none() {
         return Err(PipError::InvalidRequirement {
             spec: "None".to_string(),
             reason: "You must give at least one requirement to download".to_string(),
         });
     }
+    let environment = target.environment_override();
+    if environment.is_set() && !target.only_binary.iter().any(|entry| entry == ":all:") {
+        return Err(PipError::ConfigError {
+            message: "--platform, --python-version, --implementation and --abi require --only-binary :all:; \
+                      sdists can't be built for another environment"
+                .to_string(),
+        });
+    }
+    if let Some(version) = &target.python_version {
+        pip_rs_core::utils::tags::parse_python_version(version)
+            .map_err(|e| PipError::ConfigError { message: e.to_string() })?;
+    }
+
+    // Parse requirements file if provided, including its option lines and -r/-c includes
+    let requirements_file = match requirements {
+        Some(req_file) => Some(super::install::load_requirements_file(Path::new(&req_file), false)?),
+        None => None,
+    };
+    let mut config = pip_rs_core::config::config::Config::global().clone();
+    match &requirements_file {
+        Some(file) => {
+            let options = file.options.merged_with_cli(&cli_options);
+            super::install::apply_file_options(&mut config, &options, &|text| file.mask(text));
+        }
+        None => super::install::apply_file_options(&mut config, &cli_options, &redact_url_credentials),
+    }
+    network::configure_indexes(&config);
 
-    let mut all_requirements = Vec::new();
+    let resolver = if environment.is_set() {
+        println!("Target environment: {}", environment.describe());
+        resolver::Resolver::with_environment(EnvironmentContext::from_override(&environment).marker_environment())
+    } else {
+        resolver::Resolver::new()
+    };
+    let resolved = resolve(resolver, packages, requirements_file.as_ref(), dependency_mode).await?;
 
-    // Parse package arguments
-    for pkg in packages {
-        all_requirements.push(pkg);
+    println!("Successfully resolved {} packages:", resolved.len());
+    for pkg in &resolved {
+        println!("  - {} {}", pkg.name, pkg.version);
     }
 
-    // Parse requirements file if provided
-    if let Some(req_file) = requirements {
-        let contents = std::fs::read_to_string(&req_file).map_err(|e| PipError::FileSystemError {
-            path: req_file.clone(),
-            operation: "read".to_string(),
+    // Determine destination directory
+    let dest_dir = destination.unwrap_or_else(|| ".".to_string());
+    let dest_path = Path::new(&dest_dir);
+    
+    if !dest_path.exists() {
+        std::fs::create_dir_all(dest_path).map_err(|e| PipError::FileSystemError {
+            path: dest_dir.clone(),
+            operation: "create_dir".to_string(),
             reason: e.to_string(),
         })?;
-        for line in contents.lines() {
-            let line = line.trim();
-            if !line.is_empty() && !line.starts_with('#') {
-                all_requirements.push(line.to_string());
+    }
+
+    // Pick every file before fetching any, so a package without a wheel
+    // for the target fails the bundle instead of leaving a partial one
+    let target_env = TargetEnvironment::with_override(&environment);
+    let tags = TagPriority::for_target(&target_env);
+    let mut selected = Vec::new();
+    let mut incompatible = Vec::new();
+    for pkg in &resolved {
+        let trace = network::release_selection_for(&pkg.name, &pkg.version, &tags).await.map_err(|e| {
+            PipError::NetworkError {
+                message: format!("Failed to look up the files of {} {}", pkg.name, pkg.version),
+                retries: 0,
+                last_error: e.to_string(),
+            }
+        })?;
+        let policy = FallbackPolicy {
+            only_binary: target.only_binary(&pkg.name),
+            pinned_hashes: None,
+        };
+        match trace.best(&policy) {
+            Ok(file) => selected.push((pkg, file.clone())),
+            Err(e) => {
+                eprintln!("✗ {}", e);
+                incompatible.push(e);
             }
         }
     }
+    if let Some(error) = incompatible.into_iter().next() {
+        return Err(error);
+    }
+
+    println!("\nDownloading packages to {}...", dest_dir);
+
+    let mut downloaded = Vec::new();
+    let mut failed_count = 0;
+
+    for (pkg, file) in &selected {
+        match download_file(file, dest_path).await {
+            Ok(()) => {
+                println!("✓ Downloaded {} {} as {}", pkg.name, pkg.version, file.filename);
+                downloaded.push(file.filename.as_str());
+            }
+            Err(e) => {
+                eprintln!("✗ Failed to download {} {}: {}", pkg.name, pkg.version, e);
+                failed_count += 1;
+            }
+        }
+    }
+
+    println!("\nDownload complete!");
+    println!("  Successfully downloaded {} files for {}:", downloaded.len(), target_env);
+    for filename in &downloaded {
+        println!("    {}", filename);
+    }
+    if failed_count > 0 {
+        println!("  Failed: {}", failed_count);
+        return Ok(1);
+    }
+
+    Ok(0)
+}
+
+/// Resolve the named packages and the requirements file's entries, with
+/// the file's constraints, keeping what `dependency_mode` asks for
+async fn resolve(
+    mut resolver: resolver::Resolver,
+    packages: Vec<String>,
+    requirements_file: Option<&RequirementsFile>,
+    dependency_mode: resolver::DependencyMode,
+) -> Result<Vec<models::Package>, PipError> {
+    let mut all_requirements = packages;
+    if let Some(file) = requirements_file {
+        all_requirements.extend(file.requirements.iter().filter(|r| !r.is_comment).map(|r| r.requirement.clone()));
+    }
 
     println!("Collecting packages...");
 
@@ -66,76 +190,98 @@ pub async fn handle_download(
 
     // Resolve dependencies
     println!("\nResolving dependencies...");
-    let mut resolver = resolver::Resolver::new();
-    let resolved = resolver.resolve(parsed_reqs).await.map_err(|e| PipError::InstallationFailed {
-        package: "dependencies".to_string(),
-        reason: e.to_string(),
-    })?;
-
-    println!("Successfully resolved {} packages:", resolved.len());
-    for pkg in &resolved {
-        println!("  - {} {}", pkg.name, pkg.version);
+    resolver.set_dependency_mode(dependency_mode);
+    if let Some(file) = requirements_file {
+        resolver.set_constraints(super::install::file_constraints(file)?);
     }
+    let resolved = resolver.resolve(parsed_reqs).await?;
+    super::install::check_not_found(&resolver)?;
+    super::install::check_unresolved(&resolver)?;
+    Ok(resolved)
+}
 
-    // Determine destination directory
-    let dest_dir = destination.unwrap_or_else(|| ".".to_string());
-    let dest_path = Path::new(&dest_dir);
-    
-    if !dest_path.exists() {
-        std::fs::create_dir_all(dest_path).map_err(|e| PipError::FileSystemError {
-            path: dest_dir.clone(),
-            operation: "create_dir".to_string(),
-            reason: e.to_string(),
-        })?;
-    }
+/// Fetch one release file into `dest_dir` under its published filename
+async fn download_file(file: &ReleaseFile, dest_dir: &Path) -> Result<()> {
+    eprintln!("  Downloading {}", file.url);
+    network::PackageClient::new()
+        .download_to_file_with(&file.url, &dest_dir.join(&file.filename), file.sha256.as_deref(), None)
+        .await?;
+    Ok(())
+}
 
-    // Download packages
-    println!("\nDownloading packages to {}...", dest_dir);
-    
-    let mut downloaded_count = 0;
-    let mut failed_count = 0;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use pip_rs_core::resolver::provider::MetadataProvider;
+    use std::collections::HashMap;
+    use std::sync::Arc;
 
-    for pkg in &resolved {
-        match download_package(pkg, dest_path).await {
-            Ok(filename) => {
-                println!("✓ Downloaded {} to {}", pkg.name, filename);
-                downloaded_count += 1;
-            }
-            Err(e) => {
-                eprintln!("✗ Failed to download {} {}: {}", pkg.name, pkg.version, e);
-                failed_count += 1;
-            }
+    /// In-memory index: the CLI names `cli`, the requirements file pulls in
+    /// `web[socks]` through an `-r` include
+    struct FixtureIndex {
+        packages: HashMap<String, models::Package>,
+    }
+
+    #[async_trait]
+    impl MetadataProvider for FixtureIndex {
+        async fn get_package(&self, name: &str) -> anyhow::Result<models::Package> {
+            self.packages.get(name).cloned().ok_or_else(|| anyhow::anyhow!("{} not found", name))
         }
     }
 
-    println!("\nDownload complete!");
-    println!("  Successfully downloaded: {}", downloaded_count);
-    if failed_count > 0 {
-        println!("  Failed: {}", failed_count);
-        return Ok(1);
+    fn fixture_resolver() -> resolver::Resolver {
+        let graph: &[(&str, &str, &[&str])] = &[
+            ("cli", "1.0", &["click>=8"]),
+            ("click", "8.1.7", &[]),
+            ("web", "2.0", &["http>=1", "pysocks>=1.7; extra == \"socks\""]),
+            ("http", "1.4", &["idna"]),
+            ("idna", "3.7", &[]),
+            ("pysocks", "1.7.1", &[]),
+        ];
+        let packages = graph
+            .iter()
+            .map(|(name, version, deps)| {
+                let package = models::Package::new(name.to_string(), version.to_string())
+                    .with_requires(deps.iter().map(|d| d.to_string()).collect());
+                (name.to_string(), package)
+            })
+            .collect();
+        let mut resolver = resolver::Resolver::new();
+        resolver.set_provider(Arc::new(FixtureIndex { packages }));
+        resolver
     }
 
-    Ok(0)
-}
+    async fn resolved_names(mode: resolver::DependencyMode) -> Vec<String> {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("base.txt"), "web[socks]>=2.0\n").unwrap();
+        std::fs::write(dir.path().join("requirements.txt"), "# app\n-r base.txt\n").unwrap();
+        let file = super::super::install::load_requirements_file(&dir.path().join("requirements.txt"), false).unwrap();
 
-/// Download a single package wheel
-async fn download_package(pkg: &models::Package, dest_dir: &Path) -> Result<String> {
-    // Find wheel URL
-    let wheel_url = network::find_wheel_url(&pkg.name, &pkg.version).await?;
-    
-    // Download wheel
-    eprintln!("  Downloading {} from {}", pkg.name, wheel_url);
-    let wheel_data = network::PackageClient::new().download_package(&wheel_url).await?;
-    
-    // Extract filename from URL
-    let filename = wheel_url
-        .split('/')
-        .last()
-        .ok_or_else(|| anyhow!("Invalid wheel URL"))?;
-    
-    // Save wheel to destination
-    let wheel_path = dest_dir.join(filename);
-    std::fs::write(&wheel_path, wheel_data)?;
-    
-    Ok(filename.to_string())
+        let resolved = resolve(fixture_resolver(), vec!["cli".to_string()], Some(&file), mode).await.unwrap();
+        let mut names: Vec<String> = resolved.into_iter().map(|p| p.name).collect();
+        names.sort();
+        names
+    }
+
+    #[tokio::test]
+    async fn test_dependency_modes_with_requirements_file_and_extras() {
+        assert_eq!(
+            resolved_names(resolver::DependencyMode::All).await,
+            ["cli", "click", "http", "idna", "pysocks", "web"]
+        );
+        assert_eq!(resolved_names(resolver::DependencyMode::NoDeps).await, ["cli", "web"]);
+        assert_eq!(
+            resolved_names(resolver::DependencyMode::OnlyDeps).await,
+            ["click", "http", "idna", "pysocks"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unsatisfiable_requirement_fails() {
+        let mode = resolver::DependencyMode::All;
+        let error = resolve(fixture_resolver(), vec!["cli>=2".to_string()], None, mode).await.unwrap_err();
+        assert_eq!(error.code(), "resolution-failed");
+        assert!(error.to_string().contains("Failed to resolve dependencies for cli: cli: "), "{}", error);
+    }
 }
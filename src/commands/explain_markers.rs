@@ -0,0 +1,128 @@
+/// Explain-markers command - why a package's dependencies apply here or not
+///
+/// Evaluates the environment markers on a package's dependencies against
+/// the running environment, or one with `--env VAR=VALUE` overrides, and
+/// shows each comparison with the value that decided it. Installed
+/// metadata is used when the package is installed, the index otherwise.
+use crate::errors::PipError;
+use crate::utils::pager::PagedOutput;
+use pip_rs_core::installer::site_packages::SitePackages;
+use pip_rs_core::models::{Environment, Marker, Requirement};
+use pip_rs_core::resolver::MarkerSkip;
+
+pub async fn handle_explain_markers(package: &str, env_overrides: Vec<String>) -> Result<i32, PipError> {
+    let env = environment_with(&env_overrides)?;
+
+    let installed = SitePackages::default()
+        .ok()
+        .and_then(|site_packages| site_packages.get_package_details(package).ok().flatten());
+    let (name, version, requires) = match installed {
+        Some(details) => (details.name, details.version, details.requires),
+        None => {
+            let metadata = crate::network::get_package_metadata(package, "latest").await.map_err(|e| {
+                tracing::debug!("Metadata for {} unavailable: {}", package, e);
+                PipError::PackageNotFound {
+                    name: package.to_string(),
+                    version: None,
+                }
+            })?;
+            (metadata.name, metadata.version, metadata.requires_dist)
+        }
+    };
+
+    let mut out = PagedOutput::new();
+    if !env_overrides.is_empty() {
+        out.line(format!("Environment overrides: {}", env_overrides.join(", ")));
+    }
+    for line in explain(&name, &version, &requires, &env) {
+        out.line(line);
+    }
+    out.finish();
+    Ok(0)
+}
+
+/// The running environment with `--env VARIABLE=VALUE` overrides applied
+pub(crate) fn environment_with(env_overrides: &[String]) -> Result<Environment, PipError> {
+    let mut env = Environment::current();
+    for assignment in env_overrides {
+        let (variable, value) = assignment.split_once('=').ok_or_else(|| PipError::InvalidRequirement {
+            spec: assignment.clone(),
+            reason: "expected VARIABLE=VALUE, e.g. sys_platform=win32".to_string(),
+        })?;
+        env.set(variable.trim(), value.trim()).map_err(|reason| PipError::InvalidRequirement {
+            spec: assignment.clone(),
+            reason,
+        })?;
+    }
+    Ok(env)
+}
+
+/// Each marker-carrying dependency, whether it applies, and the truth
+/// value of every comparison in its marker
+fn explain(name: &str, version: &str, requires: &[String], env: &Environment) -> Vec<String> {
+    let marked: Vec<(Requirement, Marker)> = requires
+        .iter()
+        .filter_map(|spec| {
+            let requirement: Requirement = spec.parse().ok()?;
+            let marker = Marker::parse(requirement.marker.as_deref()?).ok()?;
+            Some((requirement, marker))
+        })
+        .collect();
+    if marked.is_empty() {
+        return vec![format!("{} {} has no dependencies with environment markers", name, version)];
+    }
+
+    let mut lines = vec![format!("{} {}: {} dependencies with environment markers", name, version, marked.len())];
+    for (requirement, marker) in &marked {
+        let outcome = if MarkerSkip::is_extra_marker(marker) {
+            "only with an extra"
+        } else if marker.evaluate(env) {
+            "included"
+        } else {
+            "skipped"
+        };
+        lines.push(format!("  {} ({}) -> {}", requirement.name, marker.expression, outcome));
+        for clause in marker.clauses(env) {
+            lines.push(format!(
+                "    {:<40} {} = {:<12} {}",
+                clause.clause,
+                clause.variable,
+                if clause.value.is_empty() { "\"\"" } else { &clause.value },
+                clause.result
+            ));
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_win32_marker_explained_on_linux() {
+        let mut env = Environment::current();
+        env.set("sys_platform", "linux").unwrap();
+        env.set("python_version", "3.11").unwrap();
+        let requires = vec![
+            "pywin32>=306; sys_platform == \"win32\"".to_string(),
+            "tomli; python_version < \"3.11\" or sys_platform == \"linux\"".to_string(),
+            "idna".to_string(),
+        ];
+
+        let lines = explain("app", "1.0.0", &requires, &env);
+        assert_eq!(lines[0], "app 1.0.0: 2 dependencies with environment markers");
+        assert_eq!(lines[1], "  pywin32 (sys_platform == \"win32\") -> skipped");
+        let words = |line: &str| line.split_whitespace().map(String::from).collect::<Vec<_>>();
+        assert_eq!(words(&lines[2]), ["sys_platform", "==", "\"win32\"", "sys_platform", "=", "linux", "false"]);
+        assert_eq!(lines[3], "  tomli (python_version < \"3.11\" or sys_platform == \"linux\") -> included");
+        assert_eq!(words(&lines[4]), ["python_version", "<", "\"3.11\"", "python_version", "=", "3.11", "false"]);
+        assert_eq!(words(&lines[5]), ["sys_platform", "==", "\"linux\"", "sys_platform", "=", "linux", "true"]);
+        assert_eq!(lines.len(), 6);
+
+        assert_eq!(
+            explain("idna", "3.7", &[], &env),
+            vec!["idna 3.7 has no dependencies with environment markers"]
+        );
+    }
+}
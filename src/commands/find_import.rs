@@ -0,0 +1,49 @@
+/// Find-import command - which distribution provides an import name
+use crate::errors::PipError;
+use pip_rs_core::config;
+use pip_rs_core::installer::import_names::{CuratedImportMap, InstalledImportIndex};
+use pip_rs_core::installer::site_packages::SitePackages;
+use pip_rs_core::utils::names::canonicalize_name;
+
+/// Report installed providers of `module` first, then the curated table
+/// (plus the user's `import-map`), and finally a best guess from the name.
+pub async fn handle_find_import(module: &str) -> Result<i32, PipError> {
+    let top_level = module.split('.').next().unwrap_or(module);
+
+    if let Ok(site_packages) = SitePackages::default()
+        && let Ok(index) = InstalledImportIndex::build(site_packages.path())
+    {
+        let providers = index.providers(top_level);
+        if !providers.is_empty() {
+            println!("'{}' is provided by installed distribution(s):", top_level);
+            for dist in providers {
+                println!("  {} {}", dist.name, dist.version);
+            }
+            return Ok(0);
+        }
+    }
+
+    let config = config::config::Config::global();
+    let curated = CuratedImportMap::with_override_file(config.import_map()).map_err(|e| {
+        PipError::ConfigError {
+            message: e.to_string(),
+        }
+    })?;
+
+    match curated.lookup(top_level) {
+        Some(dists) => {
+            println!("'{}' is not installed. It is provided by:", top_level);
+            for dist in dists {
+                println!("  pip install {}", dist);
+            }
+            Ok(0)
+        }
+        None => {
+            let guess = canonicalize_name(top_level);
+            println!("'{}' is not installed and not in the import-name table.", top_level);
+            println!("The distribution is usually named after the module; try:");
+            println!("  pip install {}", guess);
+            Ok(1)
+        }
+    }
+}
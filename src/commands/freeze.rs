@@ -2,54 +2,271 @@
 use crate::errors::PipError;
 use anyhow::Result;
 use std::fs;
+use std::path::{Path, PathBuf};
+use pip_rs_core::cache::layout;
 use pip_rs_core::installer;
+use pip_rs_core::installer::provenance::{self, ArtifactHash, FrozenPackage};
+use pip_rs_core::network;
+use pip_rs_core::resolver::DirectUrlJson;
+use pip_rs_core::utils::names::canonicalize_name;
 
-pub async fn handle_freeze(output: Option<String>) -> Result<i32, PipError> {
+/// Build tools `pip freeze` leaves out unless `--all` is given
+const BUILD_PACKAGES: &[&str] = &["pip", "setuptools", "wheel", "distribute"];
+
+/// Which installed distributions `pip freeze` prints
+#[derive(Debug, Clone, Default)]
+pub struct FreezeFilter {
+    /// Include pip, setuptools, wheel and distribute
+    pub all: bool,
+    /// Names to leave out (`--exclude`)
+    pub exclude: Vec<String>,
+    pub exclude_editable: bool,
+}
+
+impl FreezeFilter {
+    fn keeps(&self, frozen: &FrozenRequirement) -> bool {
+        let name = canonicalize_name(&frozen.name);
+        if !self.all && BUILD_PACKAGES.contains(&name.as_str()) {
+            return false;
+        }
+        if self.exclude_editable && frozen.editable {
+            return false;
+        }
+        !self.exclude.iter().any(|excluded| canonicalize_name(excluded) == name)
+    }
+}
+
+/// An installed distribution as `pip freeze` prints it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrozenRequirement {
+    pub name: String,
+    /// Empty for editables found only through an `.egg-link`
+    pub version: String,
+    /// `name==version`, `name @ <url>`, or `-e <location>` for editables
+    pub requirement: String,
+    pub editable: bool,
+    pub dist_info: Option<PathBuf>,
+}
+
+impl FrozenRequirement {
+    fn from_dist_info(name: &str, version: &str, dist_info: &Path) -> Self {
+        let direct_url = DirectUrlJson::read(dist_info);
+        let pth_location = dist_info.parent().and_then(|site_packages| editable_pth(site_packages, name, version));
+        let (requirement, editable) = match (direct_url, pth_location) {
+            (Some(direct_url), _) => (direct_url.requirement(name), direct_url.is_editable()),
+            (None, Some(location)) => (format!("-e {}", location), true),
+            (None, None) => (format!("{}=={}", name, version), false),
+        };
+        Self {
+            name: name.to_string(),
+            version: version.to_string(),
+            requirement,
+            editable,
+            dist_info: Some(dist_info.to_path_buf()),
+        }
+    }
+}
+
+pub async fn handle_freeze(
+    output: Option<String>,
+    hashes: bool,
+    compute_missing: bool,
+    filter: FreezeFilter,
+) -> Result<i32, PipError> {
     // Get installed packages
     let site_packages = installer::SitePackages::default().map_err(|e| PipError::InstallationFailed {
         package: "site-packages".to_string(),
         reason: e.to_string(),
     })?;
-    let packages = site_packages.get_installed_packages().map_err(|e| PipError::InstallationFailed {
-        package: "site-packages".to_string(),
-        reason: e.to_string(),
-    })?;
+    let frozen = installed_requirements(site_packages.path(), &filter);
 
-    if packages.is_empty() {
+    if frozen.is_empty() {
         println!("No packages installed");
         return Ok(0);
     }
 
-    // Format as requirements
-    let mut requirements = Vec::new();
-    for pkg_info in packages {
-        // Parse package name and version from dist-info directory name
-        // Format: package_name-version.dist-info
-        if let Some(name_version) = pkg_info.strip_suffix(".dist-info") {
-            if let Some(last_dash) = name_version.rfind('-') {
-                let pkg_name = &name_version[..last_dash];
-                let version = &name_version[last_dash + 1..];
-                requirements.push(format!("{}=={}", pkg_name, version));
-            }
-        }
+    if hashes {
+        let output_text = freeze_with_hashes(&frozen, compute_missing).await;
+        return write_output(output, &output_text);
     }
 
-    // Sort for consistency
-    requirements.sort();
+    let output_text = frozen.iter().map(|f| f.requirement.as_str()).collect::<Vec<_>>().join("\n");
+    write_output(output, &output_text)
+}
 
-    // Output
-    let output_text = requirements.join("\n");
-    
+/// Every distribution in `site_packages` that `filter` keeps, sorted by
+/// name without regard to case. Editables show up as `-e` lines, whether
+/// `direct_url.json`, an `__editable__` `.pth` file or a legacy
+/// `.egg-link` marks them.
+pub fn installed_requirements(site_packages: &Path, filter: &FreezeFilter) -> Vec<FrozenRequirement> {
+    let mut frozen: Vec<FrozenRequirement> = fs::read_dir(site_packages)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            // Format: package_name-version.dist-info
+            let (name, version) = file_name.strip_suffix(".dist-info")?.rsplit_once('-')?;
+            Some(FrozenRequirement::from_dist_info(name, version, &entry.path()))
+        })
+        .collect();
+
+    for egg_link in installer::EggLinkHandler::find_all_egg_links(site_packages).unwrap_or_default() {
+        let name = canonicalize_name(&egg_link.package_name);
+        frozen.retain(|f| canonicalize_name(&f.name) != name);
+        frozen.push(FrozenRequirement {
+            name: egg_link.package_name.clone(),
+            version: String::new(),
+            requirement: format!("-e {}", egg_link.get_absolute_location().display()),
+            editable: true,
+            dist_info: None,
+        });
+    }
+
+    frozen.retain(|f| filter.keeps(f));
+    frozen.sort_by_key(|f| f.name.to_lowercase());
+    frozen
+}
+
+/// Sorted requirement lines for installed distributions: `name==version`,
+/// or the URL recorded in `direct_url.json` for URL, VCS and editable
+/// installs. `pip list --format=freeze` prints these too so the two can't
+/// drift apart.
+pub fn requirement_lines<P: AsRef<Path>>(pins: &[(&str, &str, P)]) -> Vec<String> {
+    let mut frozen: Vec<FrozenRequirement> = pins
+        .iter()
+        .map(|(name, version, dist_info)| FrozenRequirement::from_dist_info(name, version, dist_info.as_ref()))
+        .collect();
+    frozen.sort_by_key(|f| f.name.to_lowercase());
+    frozen.into_iter().map(|f| f.requirement).collect()
+}
+
+/// Source tree of a setuptools editable install whose
+/// `__editable__.<name>-<version>.pth` adds it to `sys.path`; finder-hook
+/// installs (an `import` line) have no path to report here
+fn editable_pth(site_packages: &Path, name: &str, version: &str) -> Option<String> {
+    let content = fs::read_to_string(site_packages.join(format!("__editable__.{}-{}.pth", name, version))).ok()?;
+    let line = content.lines().map(str::trim).find(|line| !line.is_empty() && !line.starts_with('#'))?;
+    (!line.starts_with("import ")).then(|| line.to_string())
+}
+
+fn write_output(output: Option<String>, output_text: &str) -> Result<i32, PipError> {
     if let Some(output_file) = output {
-        fs::write(&output_file, &output_text).map_err(|e| PipError::FileSystemError {
+        fs::write(&output_file, output_text).map_err(|e| PipError::FileSystemError {
             path: output_file.clone(),
             operation: "write".to_string(),
             reason: e.to_string(),
         })?;
         println!("Wrote requirements to {}", output_file);
     } else {
-        println!("{}", output_text);
+        let mut out = crate::utils::pager::PagedOutput::plain();
+        out.line(output_text);
+        out.finish();
     }
 
     Ok(0)
 }
+
+/// Pins with `--hash` lines, recovered from provenance, `direct_url.json`
+/// or the wheel cache; `compute_missing` downloads whatever is left.
+/// Editables known only from an `.egg-link` have no artifact and are skipped.
+async fn freeze_with_hashes(packages: &[FrozenRequirement], compute_missing: bool) -> String {
+    let wheel_cache = layout::cache_root().map(|root| root.join(layout::WHEELS_DIR));
+    let mut frozen = Vec::new();
+
+    for package in packages {
+        let Some(dist_info) = &package.dist_info else {
+            continue;
+        };
+        let (name, version) = (package.name.as_str(), package.version.as_str());
+        let mut hash = provenance::artifact_hash(dist_info, name, version, wheel_cache.as_deref());
+        if hash == ArtifactHash::Unknown && compute_missing {
+            hash = download_hash(name, version).await;
+        }
+        frozen.push(FrozenPackage {
+            name: name.to_string(),
+            version: version.to_string(),
+            hash,
+        });
+    }
+
+    frozen.sort_by_key(|p| p.name.to_lowercase());
+    provenance::render_with_hashes(&frozen)
+}
+
+/// Download the wheel for `name==version`; the client keeps it in the
+/// wheel cache for the next freeze
+async fn download_hash(name: &str, version: &str) -> ArtifactHash {
+    let data = match network::find_wheel_url(name, version).await {
+        Ok(url) => match network::PackageClient::new().download_package(&url).await {
+            Ok(data) => data,
+            Err(e) => {
+                tracing::debug!("Failed to download {}=={}: {}", name, version, e);
+                return ArtifactHash::Unknown;
+            }
+        },
+        Err(e) => {
+            tracing::debug!("No wheel found for {}=={}: {}", name, version, e);
+            return ArtifactHash::Unknown;
+        }
+    };
+
+    ArtifactHash::Downloaded(provenance::sha256_hex(&data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    /// requests from an index, mylib in development mode, plus pip
+    fn site_packages() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let site = dir.path();
+        write(&site.join("requests-2.31.0.dist-info/METADATA"), "Name: requests\nVersion: 2.31.0\n");
+        write(&site.join("pip-24.0.dist-info/METADATA"), "Name: pip\nVersion: 24.0\n");
+        write(&site.join("mylib.egg-link"), "/src/mylib\n.\n");
+        dir
+    }
+
+    #[test]
+    fn test_editable_and_normal_package() {
+        let site = site_packages();
+        let lines: Vec<String> = installed_requirements(site.path(), &FreezeFilter::default())
+            .into_iter()
+            .map(|f| f.requirement)
+            .collect();
+        assert_eq!(lines, ["-e /src/mylib", "requests==2.31.0"]);
+    }
+
+    #[test]
+    fn test_all_exclude_and_exclude_editable() {
+        let site = site_packages();
+        write(&site.path().join("Zope-5.0.dist-info/METADATA"), "Name: Zope\nVersion: 5.0\n");
+        write(&site.path().join("__editable__.Zope-5.0.pth"), "/src/zope\n");
+        let names = |filter: FreezeFilter| -> Vec<String> {
+            installed_requirements(site.path(), &filter).into_iter().map(|f| f.name).collect()
+        };
+
+        assert_eq!(names(FreezeFilter::default()), ["mylib", "requests", "Zope"]);
+        let all = FreezeFilter {
+            all: true,
+            ..Default::default()
+        };
+        assert_eq!(names(all), ["mylib", "pip", "requests", "Zope"]);
+        let excluded = FreezeFilter {
+            exclude: vec!["Requests".to_string()],
+            exclude_editable: true,
+            ..Default::default()
+        };
+        assert!(names(excluded).is_empty());
+        assert_eq!(
+            installed_requirements(site.path(), &FreezeFilter::default())[2].requirement,
+            "-e /src/zope"
+        );
+    }
+}
@@ -0,0 +1,326 @@
+/// Help command - curated topics with runnable examples
+///
+/// `pip help <topic>` explains a workflow in a few paragraphs and shows
+/// example commands with what each one does. Topics are plain data
+/// compiled into the binary, so a test can run every example through the
+/// real argument parser and the docs can't drift from the CLI. A command
+/// name as the topic shows that command's `--help` instead.
+use crate::errors::PipError;
+use crate::utils::color::{ColorConfig, ColorOutput};
+use crate::utils::pager::PagedOutput;
+use crate::utils::names::edit_distance;
+
+/// Whether an example may be run as written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExampleKind {
+    /// Only reads the environment or the index
+    ReadOnly,
+    /// Installs, removes or overwrites something; tooling only parses it
+    Modifies,
+    /// Not a pip command (e.g. creating a venv); not parsed
+    Shell,
+}
+
+/// An example command line and what it does
+#[derive(Debug)]
+pub struct Example {
+    pub command: &'static str,
+    pub note: &'static str,
+    pub kind: ExampleKind,
+}
+
+/// A help topic: title, prose paragraphs, examples
+#[derive(Debug)]
+pub struct Topic {
+    pub name: &'static str,
+    pub title: &'static str,
+    pub paragraphs: &'static [&'static str],
+    pub examples: &'static [Example],
+}
+
+const fn read(command: &'static str, note: &'static str) -> Example {
+    Example { command, note, kind: ExampleKind::ReadOnly }
+}
+
+const fn modifies(command: &'static str, note: &'static str) -> Example {
+    Example { command, note, kind: ExampleKind::Modifies }
+}
+
+const fn shell(command: &'static str, note: &'static str) -> Example {
+    Example { command, note, kind: ExampleKind::Shell }
+}
+
+pub const TOPICS: &[Topic] = &[
+    Topic {
+        name: "requirements",
+        title: "Requirements files",
+        paragraphs: &[
+            "A requirements file lists one requirement per line, in the same syntax as the command line: \
+             pins (requests==2.31.0), ranges, extras and environment markers. Lines may also carry options \
+             such as --index-url, --extra-index-url and --trusted-host, which apply to the whole install; \
+             options given on the command line win.",
+            "${VAR} references are filled in from the environment, so tokens for private indexes don't have \
+             to be committed. Values of variables whose names look secret (TOKEN, PASSWORD, KEY, ...) are \
+             masked in every message. An unset variable is an error unless --allow-unset-env is given.",
+            "A file may include others with -r other.txt and -c constraints.txt, relative to itself; a file \
+             that ends up including itself is an error, as is an option line missing its value.",
+            "A constraints file (-c) caps versions without adding packages, dependencies of dependencies \
+             included; -c may be given several times and the files merge. Constraints name a package and \
+             versions only: extras, direct URLs and editables in a constraints file are errors.",
+            "A line may pin hashes with --hash=sha256:...; any hash, or --require-hashes, turns on \
+             hash-checking mode, in which every package must be pinned with == and every download must match \
+             its hash.",
+        ],
+        examples: &[
+            modifies("pip install -r requirements.txt", "install everything the file lists"),
+            modifies("pip install -r requirements.txt -c constraints.txt", "cap versions with a constraints file"),
+            read("pip install -r requirements.txt --dry-run", "show what would be installed, without installing"),
+            modifies("pip install -r requirements.txt --require-hashes", "refuse anything without a pinned hash"),
+            modifies("pip freeze --hashes -o requirements.txt", "write the installed set with hashes (overwrites the file)"),
+        ],
+    },
+    Topic {
+        name: "lockfiles",
+        title: "Lock files",
+        paragraphs: &[
+            "A lock file records the exact version of every package a resolution picked, so later installs \
+             get the same set without resolving again. pip lock writes one from a requirements file, or \
+             converts an existing poetry.lock or uv.lock.",
+            "pip install --locked installs exactly what the lock file pins, checking each file against its \
+             recorded hash; a lock made for another Python version or platform is refused unless \
+             --ignore-environment is given. With --strict-repro it also refuses entries that are unpinned or \
+             unhashed. pip sync additionally removes packages the lock doesn't list and exits 2 when it \
+             changed anything, 0 when the environment already matched. pip watch keeps an environment in \
+             sync with a lock file as it changes.",
+        ],
+        examples: &[
+            modifies("pip lock -r requirements.txt -o pip-lock.json", "resolve and write the lock file"),
+            modifies("pip lock --import poetry.lock -o pip-lock.json", "convert a Poetry lock file"),
+            modifies("pip install --locked pip-lock.json", "install exactly the pinned set"),
+            modifies("pip install --locked pip-lock.json --strict-repro", "also refuse anything non-reproducible"),
+            modifies("pip sync pip-lock.json", "install the pinned set and remove everything else"),
+            read("pip watch --locked pip-lock.json --check-only", "report drift from the lock file without changing anything"),
+        ],
+    },
+    Topic {
+        name: "offline",
+        title: "Working offline",
+        paragraphs: &[
+            "Index responses and downloaded wheels are cached, so repeated installs of the same versions \
+             need little network; a cached wheel is re-hashed before each reuse. To prepare a machine without network access, download the wheels on a \
+             connected machine first, and pin the set with a lock file so nothing needs resolving later.",
+            "--offline (or PIP_RS_OFFLINE=1) never opens a network connection. Index responses come from \
+             the cache however old they are, and wheels from the wheel cache or --find-links directories; \
+             anything else fails with \"offline mode: ... not available in cache\". pip debug --offline \
+             shows what the caches hold.",
+            "pip debug --dump-state writes a bundle of the cache and configuration; with --include-bodies \
+             it holds enough to replay a resolution offline, which is useful for bug reports.",
+        ],
+        examples: &[
+            modifies("pip download -r requirements.txt -d wheels", "fetch every wheel into ./wheels"),
+            modifies("pip lock -r requirements.txt -o pip-lock.json", "pin the set while online"),
+            modifies("pip install --locked pip-lock.json --offline", "install the pinned set from the caches"),
+            modifies("pip install --offline --find-links wheels flask", "install from ./wheels without network"),
+            read("pip deps requests --flat", "list what a requirement pulls in before downloading it"),
+            modifies("pip debug --dump-state bundle --include-bodies", "write a replayable state bundle to ./bundle"),
+            read("pip cache list", "show the wheels kept for reuse"),
+            modifies("pip cache remove numpy", "drop cached numpy wheels"),
+            modifies("pip cache purge", "remove cached responses and stale partial downloads"),
+        ],
+    },
+    Topic {
+        name: "venv",
+        title: "Virtual environments",
+        paragraphs: &[
+            "pip-rs installs into the active virtual environment: when VIRTUAL_ENV is set, its \
+             site-packages is used. Otherwise it falls back to the interpreter's site-packages, which is \
+             usually not what you want.",
+            "--python PATH manages another interpreter's environment instead: its site-packages, Python \
+             version and script shebangs come from that interpreter, and VIRTUAL_ENV is ignored.",
+            "Set PIP_REQUIRE_VIRTUALENV=1, or pass --require-venv, to refuse installs outside a virtual \
+             environment. An explicit --target directory is always allowed.",
+            "A system Python whose distribution marks it EXTERNALLY-MANAGED (PEP 668), as Debian, Ubuntu \
+             and Homebrew do, is refused outright with the distribution's own advice. Pass \
+             --break-system-packages, set PIP_BREAK_SYSTEM_PACKAGES=1 or break-system-packages = true in \
+             pip.conf to change it anyway; virtual environments are never affected.",
+            "pip venv create makes one with the same layout as python -m venv, including activate scripts \
+             for bash, zsh, fish and PowerShell (and cmd.exe on Windows).",
+        ],
+        examples: &[
+            modifies("pip venv create .venv", "create a virtual environment with python3 from PATH"),
+            modifies("pip venv create .venv --python 3.12", "use a specific Python version"),
+            shell(". .venv/bin/activate", "activate it in the current shell"),
+            modifies("pip --python .venv/bin/python install requests", "install for another interpreter"),
+            modifies("pip install --require-venv requests", "refuse to install unless a venv is active"),
+            modifies("pip install --target ./vendor requests", "install into a plain directory instead"),
+            modifies("pip install --user httpie", "install for this user only, scripts in ~/.local/bin"),
+            read("pip venv info .venv", "show its Python version and site-packages"),
+            read("pip debug", "show which site-packages directory is in use"),
+        ],
+    },
+    Topic {
+        name: "migration-from-pip",
+        title: "Coming from pip",
+        paragraphs: &[
+            "Most commands and flags match pip: install, uninstall, list, show, freeze and download work \
+             the way you expect, and requirements files are read the same way.",
+            "Some things differ. pip-rs installs wheels only; a package without a compatible wheel is \
+             reported instead of built from source. pip update (alias upgrade) upgrades outdated packages, \
+             and -i picks them in a terminal UI. pip check --environment also diagnoses site-packages and \
+             scripts instead of checking requirements. --keep-going installs what it can when one package \
+             fails, --rollback-on-failure undoes the whole install instead, and --report writes a JSON \
+             summary (--report - prints it).",
+            "There are also commands pip doesn't have: pip deps shows a dependency tree without \
+             installing, pip explain-markers shows why a dependency applies here or not, and pip \
+             find-import finds the distribution that provides an import name.",
+        ],
+        examples: &[
+            modifies("pip install -U requests", "upgrade one package, as with pip"),
+            modifies("pip update", "upgrade every outdated package"),
+            modifies("pip update -i", "choose which outdated packages to upgrade"),
+            modifies("pip install -r requirements.txt --keep-going --report report.json", "install what can be installed and report the rest"),
+            read("pip deps \"fastapi[all]>=0.110\"", "show the dependency tree of a requirement"),
+            read("pip explain-markers pywin32 --env sys_platform=win32", "evaluate markers as if on Windows"),
+            read("pip find-import cv2", "find the package that provides `import cv2`"),
+        ],
+    },
+];
+
+pub async fn handle_help(topic: Option<String>, all: bool, cli: clap::Command) -> Result<i32, PipError> {
+    let color = if console::Term::stdout().is_term() {
+        ColorOutput::new(ColorConfig::from_env())
+    } else {
+        ColorOutput::new(ColorConfig { enabled: false })
+    };
+    let width = console::Term::stdout()
+        .size_checked()
+        .map(|(_, columns)| columns as usize)
+        .unwrap_or(80)
+        .clamp(40, 100);
+
+    let mut out = PagedOutput::new();
+    match topic {
+        _ if all => {
+            for (i, topic) in TOPICS.iter().enumerate() {
+                if i > 0 {
+                    out.line("");
+                }
+                for line in render_topic(topic, width, &color) {
+                    out.line(line);
+                }
+            }
+        }
+        None => {
+            for line in topic_index(&color) {
+                out.line(line);
+            }
+        }
+        Some(name) => {
+            if let Some(topic) = TOPICS.iter().find(|t| t.name == name) {
+                for line in render_topic(topic, width, &color) {
+                    out.line(line);
+                }
+            } else if let Some(mut command) = cli.find_subcommand(&name).cloned() {
+                out.line(command.render_long_help().to_string());
+            } else {
+                let names: Vec<&str> = TOPICS.iter().map(|t| t.name).collect();
+                let mut message = format!("Unknown help topic '{}'.", name);
+                if let Some(closest) = closest_topic(&name) {
+                    message.push_str(&format!(" Did you mean '{}'?", closest));
+                }
+                eprintln!("{}\nTopics: {}", message, names.join(", "));
+                return Ok(1);
+            }
+        }
+    }
+    out.finish();
+    Ok(0)
+}
+
+/// `pip help` without a topic: the list of topics
+fn topic_index(color: &ColorOutput) -> Vec<String> {
+    let mut lines = vec!["Help topics (pip help <topic>):".to_string()];
+    let width = TOPICS.iter().map(|t| t.name.len()).max().unwrap_or(0);
+    for topic in TOPICS {
+        lines.push(format!("  {}  {}", color.highlight(&format!("{:<width$}", topic.name)), topic.title));
+    }
+    lines.push(String::new());
+    lines.push("pip help <command> shows a command's options; pip help --all prints every topic.".to_string());
+    lines
+}
+
+/// A topic wrapped to `width` columns
+fn render_topic(topic: &Topic, width: usize, color: &ColorOutput) -> Vec<String> {
+    let mut lines = vec![color.highlight(topic.title), String::new()];
+    for paragraph in topic.paragraphs {
+        lines.extend(wrap(paragraph, width, ""));
+        lines.push(String::new());
+    }
+    lines.push(color.highlight("Examples:"));
+    for example in topic.examples {
+        // Commands that change something stand out from the ones that are safe to try
+        let command = match example.kind {
+            ExampleKind::ReadOnly => color.info(example.command),
+            ExampleKind::Modifies => color.warning(example.command),
+            ExampleKind::Shell => color.muted(example.command),
+        };
+        lines.push(format!("  {}", command));
+        lines.extend(wrap(example.note, width, "      # "));
+    }
+    lines
+}
+
+/// Greedy word wrap; each line starts with `indent`
+fn wrap(text: &str, width: usize, indent: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = indent.to_string();
+    for word in text.split_whitespace() {
+        if line.len() > indent.len() && line.len() + 1 + word.len() > width {
+            lines.push(std::mem::replace(&mut line, indent.to_string()));
+        }
+        if line.len() > indent.len() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    lines.push(line);
+    lines
+}
+
+/// The topic a mistyped name most likely meant, if any is close
+fn closest_topic(name: &str) -> Option<&'static str> {
+    let name = name.to_lowercase();
+    TOPICS
+        .iter()
+        .map(|topic| {
+            let distance = if topic.name.starts_with(&name) || name.starts_with(topic.name) {
+                0
+            } else {
+                edit_distance(&name, topic.name)
+            };
+            (distance, topic.name)
+        })
+        .filter(|(distance, topic)| *distance <= topic.len() / 3 + 1)
+        .min()
+        .map(|(_, topic)| topic)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_wraps_and_closest_topic() {
+        let plain = ColorOutput::new(ColorConfig { enabled: false });
+        let lines = render_topic(&TOPICS[0], 40, &plain);
+        assert_eq!(lines[0], "Requirements files");
+        assert!(lines.iter().all(|line| line.len() <= 40 || !line.contains(' ') || line.starts_with("  pip")));
+        assert!(lines.contains(&"  pip install -r requirements.txt".to_string()));
+        assert_eq!(wrap("one two three", 9, "  # "), ["  # one", "  # two", "  # three"]);
+
+        assert_eq!(closest_topic("lockfile"), Some("lockfiles"));
+        assert_eq!(closest_topic("requirments"), Some("requirements"));
+        assert_eq!(closest_topic("migration"), Some("migration-from-pip"));
+        assert_eq!(closest_topic("kubernetes"), None);
+    }
+}
@@ -0,0 +1,29 @@
+/// Inspect command - installed distributions and the environment as JSON
+///
+/// Prints pip's inspect report (see `pip_rs_core::installer::inspect`) on
+/// stdout. Reads the dist-info directories only; never touches the network.
+use crate::errors::PipError;
+use pip_rs_core::installer::SitePackages;
+use pip_rs_core::installer::inspect;
+use pip_rs_core::models::Environment;
+use std::path::PathBuf;
+
+/// Inspect the active environment's site-packages, or `path`
+pub async fn handle_inspect(path: Option<PathBuf>) -> Result<i32, PipError> {
+    let site_packages = match path {
+        Some(path) => SitePackages::new(path),
+        None => SitePackages::default(),
+    }
+    .map_err(|e| PipError::FileSystemError {
+        path: "site-packages".to_string(),
+        operation: "access".to_string(),
+        reason: e.to_string(),
+    })?;
+    let report = inspect::inspect(&site_packages, Environment::current()).map_err(|e| PipError::FileSystemError {
+        path: site_packages.path().display().to_string(),
+        operation: "read".to_string(),
+        reason: e.to_string(),
+    })?;
+    println!("{}", serde_json::to_string_pretty(&report).unwrap_or_else(|_| "{}".to_string()));
+    Ok(0)
+}
@@ -1,17 +1,86 @@
 /// Install command implementation
-use crate::errors::PipError;
-use crate::utils::progress;
+use crate::cli::parser::InstallOptions;
+use crate::cli::progress_socket::ProgressSocket;
+use crate::errors::{MissingPackage, Phase, PipError};
+use crate::utils::{progress, warnings};
 use tempfile::TempDir;
-use std::path::Path;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use pip_rs_core::utils::names::canonicalize_name;
 use pip_rs_core::{installer, models, config, resolver, network};
+use pip_rs_core::utils::requirements_parser;
+use pip_rs_core::utils::cancel;
+use pip_rs_core::utils::names;
+use pip_rs_core::utils::redact::redact_url_credentials;
+use pip_rs_core::installer::events::{self, InstallEvent};
+use pip_rs_core::installer::existing::InstalledDist;
+use pip_rs_core::installer::source_tree::SourceTree;
+use pip_rs_core::vcs::{Checkout, VcsUrl};
+use pip_rs_core::installer::batch::BatchReport;
+use pip_rs_core::installer::transaction::Snapshot;
+use std::collections::{HashMap, HashSet};
+use pip_rs_core::utils::version;
+use pip_rs_core::utils::performance::PerformanceTracker;
+use std::time::{Duration, Instant};
+
+pub async fn handle_install(options: InstallOptions) -> Result<i32, PipError> {
+    if options.user
+        && let Err(message) = check_user_install(options.target.is_some())
+    {
+        eprintln!("ERROR: {}", message);
+        return Ok(1);
+    }
+    let socket = match &options.progress_socket {
+        Some(path) => Some(ProgressSocket::connect(path).await?),
+        None => None,
+    };
+    let result = match &options.locked {
+        Some(lock_file) => {
+            let lock_file = lock_file.display().to_string();
+            super::lock::handle_lock_install(lock_file, options.strict_repro, options.ignore_environment, false).await
+        }
+        None => install_report(options).await.map(|report| report.exit_code()),
+    };
+    if let Some(socket) = socket {
+        socket.close().await;
+    }
+    result
+}
+
+/// Resolve and install, returning what happened to each package
+pub(crate) async fn install_report(options: InstallOptions) -> Result<installer::batch::BatchReport, PipError> {
+    let InstallOptions {
+        packages,
+        requirements,
+        constraints,
+        trusted_hosts,
+        index_url,
+        extra_index_urls,
+        find_links,
+        no_index,
+        target,
+        user,
+        override_file,
+        index_policy,
+        upgrade,
+        no_deps,
+        only_deps,
+        keep_going,
+        rollback_on_failure,
+        report,
+        allow_unset_env,
+        allow_degraded_environment,
+        show_sizes,
+        require_hashes,
+        force_reinstall,
+        refuse_if_in_use,
+        dry_run,
+        no_deprecated,
+        verbose,
+        resolution,
+        ..
+    } = options;
 
-pub async fn handle_install(
-    packages: Vec<String>,
-    requirements: Option<String>,
-    constraints: Option<String>,
-    trusted_hosts: Vec<String>,
-    _target: Option<String>,
-) -> Result<i32, PipError> {
     if packages.is_empty() && requirements.is_none() {
         return Err(PipError::InvalidRequirement {
             spec: "None".to_string(),
@@ -19,50 +88,60 @@ pub async fn handle_install(
         });
     }
 
+    let dependency_mode = dependency_mode(no_deps, only_deps)?;
+    // `--report -` prints the report; everything else goes to stderr
+    if report.as_deref() == Some(Path::new("-")) {
+        progress::reserve_stdout();
+    }
+
     let mut all_requirements = Vec::new();
 
-    // Parse package arguments
+    let mut local_projects = Vec::new();
+
+    // Parse package arguments; directories are local projects (e.g. `.`)
     for pkg in packages {
-        all_requirements.push(pkg);
+        if Path::new(&pkg).is_dir() {
+            local_projects.push(load_local_project(Path::new(&pkg))?);
+        } else {
+            all_requirements.push(pkg);
+        }
     }
 
-    // Parse requirements file if provided
-    if let Some(req_file) = requirements {
-        let contents = std::fs::read_to_string(&req_file).map_err(|e| PipError::FileSystemError {
-            path: req_file.clone(),
-            operation: "read".to_string(),
-            reason: e.to_string(),
-        })?;
-        for line in contents.lines() {
-            let line = line.trim();
-            if !line.is_empty() && !line.starts_with('#') {
-                all_requirements.push(line.to_string());
-            }
+    // Parse requirements file if provided, including its option lines
+    let requirements_file = match requirements {
+        Some(req_file) => Some(load_requirements_file(&req_file, allow_unset_env)?),
+        None => None,
+    };
+    let mask = |text: &str| match &requirements_file {
+        Some(file) => file.mask(text),
+        None => text.to_string(),
+    };
+    if let Some(file) = &requirements_file {
+        for req in file.requirements.iter().filter(|r| !r.is_comment) {
+            all_requirements.push(req.requirement.clone());
         }
     }
 
-    // Parse constraints file if provided
-    let mut constraint_reqs = Vec::new();
-    if let Some(constraints_file) = constraints {
-        let contents = std::fs::read_to_string(&constraints_file).map_err(|e| PipError::FileSystemError {
-            path: constraints_file.clone(),
-            operation: "read".to_string(),
-            reason: e.to_string(),
-        })?;
-        for line in contents.lines() {
-            let line = line.trim();
-            if !line.is_empty() && !line.starts_with('#') {
-                match line.parse::<models::Requirement>() {
-                    Ok(req) => constraint_reqs.push(req),
-                    Err(e) => {
-                        tracing::warn!("Invalid constraint: {} - {}", line, e);
-                    }
-                }
-            }
-        }
+    // `git+` URLs, bare or as `name @ git+...`, are checked out up front
+    let (vcs_specs, all_requirements): (Vec<String>, Vec<String>) =
+        all_requirements.into_iter().partition(|spec| is_vcs_requirement(spec));
+    let checkouts_dir = TempDir::new().map_err(|e| PipError::FileSystemError {
+        path: "temp".to_string(),
+        operation: "create directory".to_string(),
+        reason: e.to_string(),
+    })?;
+    let mut vcs_projects = HashMap::new();
+    for (i, spec) in vcs_specs.iter().enumerate() {
+        let project = checkout_vcs(spec, &checkouts_dir.path().join(i.to_string()))
+            .map_err(|e| e.in_phase(Phase::Download, None))?;
+        vcs_projects.insert(canonicalize_name(&project.tree.name), project);
     }
 
-    println!("Collecting packages...");
+    // Constraints the requirements file includes come first, then each -c file
+    let mut constraint_reqs = requirements_file.as_ref().map(file_constraints).transpose()?.unwrap_or_default();
+    constraint_reqs.extend(load_constraint_files(&constraints, allow_unset_env)?);
+
+    progress::status("Collecting packages...");
 
     // Parse requirements
     let mut parsed_reqs = Vec::new();
@@ -70,54 +149,238 @@ pub async fn handle_install(
         match req_str.parse::<models::Requirement>() {
             Ok(req) => {
                 if req.extras.is_empty() {
-                    println!("  - {}", req.name);
+                    progress::status(format!("  - {}", req.name));
                 } else {
-                    println!("  - {}[{}]", req.name, req.extras.join(","));
+                    progress::status(format!("  - {}[{}]", req.name, req.extras.join(",")));
                 }
                 parsed_reqs.push(req);
             }
             Err(e) => {
                 return Err(PipError::InvalidRequirement {
-                    spec: req_str,
+                    spec: mask(&req_str),
                     reason: e.to_string(),
                 });
             }
         }
     }
 
+    // Hashes pinned in the requirements file, by canonical name. Like pip,
+    // any hash turns on hash-checking mode for the whole install.
+    let pinned_hashes: HashMap<String, Vec<String>> = requirements_file
+        .iter()
+        .flat_map(|file| &file.requirements)
+        .filter(|r| !r.hashes.is_empty())
+        .filter_map(|r| {
+            let req: models::Requirement = r.requirement.parse().ok()?;
+            Some((canonicalize_name(&req.name), r.hashes.clone()))
+        })
+        .collect();
+    let hash_checking = require_hashes
+        || requirements_file.as_ref().is_some_and(|file| file.options.require_hashes)
+        || !pinned_hashes.is_empty();
+    if hash_checking {
+        check_hash_pins(&parsed_reqs, &pinned_hashes)?;
+    }
+    let local_names: Vec<String> = local_projects.iter().map(|p| canonicalize_name(&p.name)).collect();
+
+    // Local projects resolve from their pyproject.toml; only their
+    // dependencies can be installed until pip-rs can build them
+    for project in &local_projects {
+        if dependency_mode != resolver::DependencyMode::OnlyDeps {
+            return Err(PipError::InvalidPackage {
+                name: project.name.clone(),
+                reason: "building local projects is not supported; use --only-deps to install its dependencies".to_string(),
+            }
+            .in_phase(Phase::Build, Some(&project.name)));
+        }
+        progress::status(format!("  - {} (local project)", project.name));
+        parsed_reqs.push(models::Requirement {
+            name: project.name.clone(),
+            specs: Vec::new(),
+            extras: Vec::new(),
+            marker: None,
+            url: None,
+        });
+    }
+
+    // VCS projects are built and installed like any other package
+    if hash_checking && !vcs_projects.is_empty() {
+        return Err(PipError::InvalidRequirement {
+            spec: vcs_projects.values().map(|project| project.url.original.clone()).collect::<Vec<_>>().join(", "),
+            reason: "hash-checking mode can't verify version control checkouts; pin a wheel or sdist with --hash instead"
+                .to_string(),
+        });
+    }
+    for project in vcs_projects.values() {
+        progress::status(format!(
+            "  - {} (from {} at {})",
+            project.tree.name,
+            redact_url_credentials(&mask(&project.url.original)),
+            &project.checkout.commit_id[..project.checkout.commit_id.len().min(12)]
+        ));
+        local_projects.push(
+            models::Package::new(canonicalize_name(&project.tree.name), project.tree.version.clone())
+                .with_requires(project.tree.requires_dist.clone()),
+        );
+        parsed_reqs.push(models::Requirement {
+            name: canonicalize_name(&project.tree.name),
+            specs: Vec::new(),
+            extras: Vec::new(),
+            marker: None,
+            url: None,
+        });
+    }
+
     if parsed_reqs.is_empty() {
         return Err(PipError::InvalidRequirement {
             spec: "None".to_string(),
             reason: "No valid requirements found".to_string(),
         });
     }
+    let requested: HashMap<String, models::Requirement> = parsed_reqs
+        .iter()
+        .map(|req| (canonicalize_name(&req.name), req.clone()))
+        .collect();
+
+    // Load config and merge file options with CLI flags (CLI wins)
+    let mut config = config::config::Config::global().clone();
+    let cli_options = requirements_parser::FileOptions {
+        index_url,
+        extra_index_urls,
+        trusted_hosts,
+        find_links,
+        no_index,
+        ..Default::default()
+    };
+    let file_options = match &requirements_file {
+        Some(file) => file.options.merged_with_cli(&cli_options),
+        None => cli_options,
+    };
+    apply_file_options(&mut config, &file_options, &mask);
+    network::configure_indexes(&config);
 
-    // Load config and merge trusted hosts
-    let mut config = config::config::Config::new();
-    for host in trusted_hosts {
-        config.add_trusted_host(host);
+    // --target is an explicit destination and exempt from PIP_REQUIRE_VIRTUALENV;
+    // a dry run changes nothing, so it may look at any environment
+    if !dry_run {
+        ensure_virtualenv(&config, target.is_some())?;
+        ensure_not_externally_managed(&config, target.is_some())?;
     }
     
-    // Smart defaults: Auto-detect venv
-    let venv_path = std::env::var("VIRTUAL_ENV").ok();
+    // Smart defaults: Auto-detect venv; a --target directory stands on its own
+    let venv_path = crate::utils::python_env::virtual_env().filter(|_| target.is_none());
     if let Some(ref venv) = venv_path {
-        tracing::debug!("Detected virtual environment: {}", venv);
+        tracing::debug!("Detected virtual environment: {}", venv.display());
     }
 
     // Resolve dependencies
-    println!("\nResolving dependencies...");
+    progress::status("\nResolving dependencies...");
     let mut resolver = resolver::Resolver::new();
+    resolver.set_dependency_mode(dependency_mode);
+    resolver.set_resolution_budget(resolution.budget());
+    for project in local_projects {
+        resolver.add_local_package(project);
+    }
     if !constraint_reqs.is_empty() {
         resolver.set_constraints(constraint_reqs);
     }
-    let resolved = resolver.resolve(parsed_reqs).await.map_err(|e| PipError::InstallationFailed {
-        package: "dependencies".to_string(),
-        reason: e.to_string(),
+    if let Some(overrides) = load_overrides(override_file.as_deref(), &config)? {
+        resolver.set_overrides(overrides);
+    }
+    events::phase(Phase::Resolution, None);
+    let timings = PerformanceTracker::new();
+    let resolving = Instant::now();
+    let spinner = resolution_spinner();
+    let resolved = resolver.resolve(parsed_reqs).await;
+    if let Some((spinner, watcher)) = spinner {
+        watcher.abort();
+        spinner.finish_and_clear();
+    }
+    timings.record(RESOLVE.to_string(), resolving.elapsed(), 0);
+    let resolved = resolved.map_err(|e| {
+        if cancel::is_requested() {
+            events::emit(InstallEvent::Cancelled);
+        }
+        PipError::from(e).in_phase(Phase::Resolution, None)
     })?;
+    check_not_found(&resolver)?;
+    check_unresolved(&resolver)?;
 
-    println!("Successfully resolved {} packages:", resolved.len());
+    if hash_checking {
+        let unhashed: Vec<String> = resolved
+            .iter()
+            .map(|p| (p, canonicalize_name(&p.name)))
+            .filter(|(_, name)| !local_names.contains(name) && !pinned_hashes.contains_key(name))
+            .map(|(p, _)| format!("{}=={}", p.name, p.version))
+            .collect();
+        if !unhashed.is_empty() {
+            return Err(PipError::InvalidRequirement {
+                spec: unhashed.join(", "),
+                reason: "hash-checking mode needs a --hash for every installed package, dependencies included; \
+                         add these to the requirements file with their hashes"
+                    .to_string(),
+            }
+            .in_phase(Phase::Resolution, None));
+        }
+    }
+
+    progress::status(format!("Successfully resolved {} packages:", resolved.len()));
     for pkg in &resolved {
-        println!("  - {} {}", pkg.name, pkg.version);
+        progress::status(format!("  - {} {}", pkg.name, pkg.version));
+    }
+    print_override_summary(&resolver);
+    if resolution.stats {
+        print_resolution_stats(&resolver);
+    }
+    if verbose {
+        print_marker_skips(&resolver);
+    }
+    if target.is_none() && !dry_run {
+        let names: Vec<String> = resolved.iter().map(|p| p.name.clone()).collect();
+        preflight_environment(Some(&names), allow_degraded_environment)?;
+    }
+    if let Some(policy) = load_index_policy(index_policy.as_deref(), &config)? {
+        enforce_index_policy(&policy, &resolver, &resolved)?;
+    }
+    let deprecated = if no_deprecated || config.warn_deprecated(std::io::stderr().is_terminal()) {
+        let mut local = local_names.clone();
+        local.extend(vcs_projects.keys().cloned());
+        check_deprecations(&resolved, &resolver, &local, no_deprecated).await?
+    } else {
+        Vec::new()
+    };
+    if dependency_mode == resolver::DependencyMode::NoDeps {
+        warnings::warn_user(
+            warnings::WarningCode::DependenciesSkipped,
+            "--no-deps: dependencies were not installed; the environment may be incomplete",
+        );
+    }
+
+    if dry_run {
+        let site_path = match &target {
+            Some(target) => Some(target.clone()),
+            None => installer::SitePackages::default().ok().map(|site| site.path().to_path_buf()),
+        };
+        let plan = plan_install(&resolved, site_path.as_deref(), &requested, upgrade, force_reinstall);
+        progress::status("");
+        for line in dry_run_lines(&plan) {
+            progress::status(line);
+        }
+        let mut dry_report = dry_run_report(&plan, site_path.as_deref(), &resolver);
+        dry_report.skipped_by_markers = resolver.skipped_by_markers().to_vec();
+        dry_report.deprecated = deprecated.clone();
+        dry_report.dependency_mode = dependency_mode;
+        events::emit(InstallEvent::Report {
+            report: serde_json::to_value(&dry_report).unwrap_or_default(),
+        });
+        if let Some(report_path) = report {
+            write_report(&report_path, &dry_report)?;
+        }
+        return Ok(installer::batch::BatchReport {
+            skipped_by_markers: resolver.skipped_by_markers().to_vec(),
+            deprecated,
+            dependency_mode,
+            ..Default::default()
+        });
     }
 
     // Download and install packages
@@ -127,97 +390,1480 @@ pub async fn handle_install(
         reason: e.to_string(),
     })?;
     
-    let total = resolved.len();
-    let pb = if progress::is_quiet() {
-        None
-    } else {
-        Some(progress::progress_bar(total as u64, "Installing"))
+    let destination = Destination::new(target.as_deref(), user)?;
+    // Held until every package is in place; another pip-rs install waits
+    let _lock = lock_environment(destination.path())?;
+    installer::transaction::remove_leftovers(destination.path());
+    let overwrite = installer::Overwrite {
+        refuse_if_in_use,
+        force_reinstall,
     };
-    
-    let mut installed_count = 0;
-    let mut failed_count = 0;
+    let total = resolved.len();
+    let pb = progress::enabled().then(|| progress::add(progress::spinner("Installing")));
+    // Downloads report their size and duration as events
+    let mut downloads = events::subscribe();
+    let installing = Instant::now();
 
-    for pkg in &resolved {
+    // Dependencies first; with --keep-going a failure skips only its dependents
+    let mut batch = installer::batch::BatchInstall::new(&resolved, resolver.dependency_edges());
+    // What `--report` lists: installed and kept packages, in install order
+    let mut reported = Vec::new();
+    // With --rollback-on-failure: each package installed, and the version it replaced
+    let mut undo: Vec<(models::Package, Option<Snapshot>)> = Vec::new();
+    let mut cancelled = false;
+    for (position, pkg) in batch.install_order().into_iter().enumerate() {
         if let Some(prog) = &pb {
-            prog.set_message(format!("{} {}", pkg.name, pkg.version));
+            prog.set_message(format!("Installing {}/{}: {} {}", position + 1, total, pkg.name, pkg.version));
         }
-        
-        match install_package(pkg, temp_dir.path()).await {
-            Ok(_) => {
-                installed_count += 1;
+
+        // A cancel request stops before the next package; the rest are skipped
+        if cancel::is_requested() {
+            if !cancelled {
+                cancelled = true;
+                eprintln!("Cancelled: not starting any more packages");
+                events::emit(InstallEvent::Cancelled);
             }
-            Err(_e) => {
-                if !progress::is_quiet() {
-                    eprintln!("✗ Failed to install {} {}: {}", pkg.name, pkg.version, _e);
+            batch.record_skipped(&pkg, "cancelled".to_string());
+        } else if let Some(failed) = batch.blocked_by(&pkg.name) {
+            batch.record_skipped(&pkg, failed);
+        } else {
+            let installed = destination.find_installed(&pkg.name);
+            let replaces = match existing_action(
+                installed.as_ref(),
+                &pkg.version,
+                requested.get(&canonicalize_name(&pkg.name)),
+                upgrade,
+                force_reinstall,
+            ) {
+                ExistingAction::Install => None,
+                ExistingAction::Replace => {
+                    if let Some(installed) = &installed {
+                        progress::status(format!(
+                            "Found existing installation: {} {}",
+                            installed.name, installed.version
+                        ));
+                    }
+                    installed.as_ref()
+                }
+                ExistingAction::Satisfied => {
+                    if let Some(installed) = &installed {
+                        progress::status(format!(
+                            "Requirement already satisfied: {} {}",
+                            installed.name, installed.version
+                        ));
+                        reported.push(kept_package(installed));
+                    }
+                    continue;
+                }
+                ExistingAction::KeepEditable => {
+                    if let Some(installed) = &installed {
+                        warnings::warn_user(
+                            warnings::WarningCode::EditableKept,
+                            format!(
+                                "{} {} is an editable install; leaving it in place instead of installing {}",
+                                installed.name, installed.version, pkg.version
+                            ),
+                        );
+                        reported.push(kept_package(installed));
+                    }
+                    continue;
+                }
+            };
+            let mut kept = None;
+            let replaceable = destination.check_replaceable(&pkg, replaces).and_then(|()| {
+                if rollback_on_failure {
+                    kept = keep_for_rollback(&pkg, replaces, &destination)?;
+                }
+                Ok(())
+            });
+            let installing = async {
+                match (replaceable, vcs_projects.get(&canonicalize_name(&pkg.name))) {
+                    (Err(e), _) => Err(e),
+                    (Ok(()), Some(project)) => install_vcs(&pkg, project, temp_dir.path(), &destination, overwrite)
+                        .await
+                        .map(|size| (size, None, None)),
+                    (Ok(()), None) => {
+                        let index = resolver.index_source(&pkg.name);
+                        let hashes = pinned_hashes.get(&canonicalize_name(&pkg.name)).map(Vec::as_slice);
+                        let policy = network::FallbackPolicy {
+                            only_binary: only_binary(&file_options, &pkg.name),
+                            pinned_hashes: hashes,
+                        };
+                        let direct_url = resolver.direct_url(&pkg.name);
+                        install_package(&pkg, temp_dir.path(), direct_url, &policy, index, &destination, overwrite)
+                            .await
+                            .map(|(size, provenance, fallback)| (size, Some(provenance), fallback))
+                    }
+                }
+            };
+            // Ctrl-C abandons a download in flight; a wheel being copied into
+            // place rolls itself back
+            let install = tokio::select! {
+                result = installing => result,
+                _ = cancel::requested() => Err(PipError::InstallationFailed {
+                    package: pkg.name.clone(),
+                    reason: "cancelled".to_string(),
+                }),
+            };
+            match install.and_then(|installed| remove_replaced(&pkg, replaces, &destination).map(|_| installed)) {
+                Ok((size, provenance, fallback)) => {
+                    batch.record_installed_from(&pkg, size, provenance.as_ref());
+                    reported.push(models::InstalledPackage {
+                        name: pkg.name.clone(),
+                        version: pkg.version.clone(),
+                        location: destination.path().display().to_string(),
+                        direct_url: resolver.direct_url(&pkg.name).map(str::to_string),
+                        download_url: provenance.as_ref().map(|p| p.url.clone()),
+                        sha256: provenance.map(|p| p.sha256),
+                        size: Some(size),
+                        ..Default::default()
+                    });
+                    if let Some(fallback) = fallback {
+                        batch.record_fallback(fallback);
+                    }
+                    if rollback_on_failure {
+                        undo.push((pkg.clone(), kept));
+                    }
+                }
+                Err(_) if cancel::is_requested() => batch.record_skipped(&pkg, "cancelled".to_string()),
+                Err(e) => {
+                    if !progress::is_quiet() {
+                        eprintln!("✗ Failed to install {} {}: {}", pkg.name, pkg.version, e);
+                    }
+                    batch.record_failed(&pkg, &e);
+                    // A tampered artifact stops everything, --keep-going or not
+                    if !keep_going || e.code() == "hash-mismatch" {
+                        break;
+                    }
                 }
-                failed_count += 1;
             }
         }
-        
-        if let Some(prog) = &pb {
-            prog.inc(1);
+
+    }
+    timings.record(INSTALL.to_string(), installing.elapsed(), 0);
+    let mut downloaded_bytes = 0;
+    while let Ok(envelope) = downloads.try_recv() {
+        if let InstallEvent::Downloaded { bytes, seconds, .. } = envelope.event {
+            timings.record(DOWNLOAD.to_string(), Duration::from_secs_f64(seconds), 0);
+            downloaded_bytes += bytes;
         }
     }
+    drop(downloads);
+    let mut batch_report = batch.into_report();
+    if rollback_on_failure && !batch_report.failed.is_empty() && !undo.is_empty() {
+        roll_back(undo, &destination, &mut batch_report);
+        reported.retain(|package| {
+            !batch_report.rolled_back.iter().any(|rolled_back| rolled_back.name == package.name)
+        });
+    }
+    if user {
+        warn_scripts_not_on_path(&destination, &batch_report.installed);
+    }
+    batch_report.skipped_by_markers = resolver.skipped_by_markers().to_vec();
+    batch_report.deprecated = deprecated;
+    batch_report.dependency_mode = dependency_mode;
 
     if let Some(pb) = pb {
-        if failed_count > 0 {
-            progress::finish_error(&pb, &format!("Installed {} packages ({} failed)", installed_count, failed_count));
-        } else {
+        let installed_count = batch_report.installed.len();
+        if batch_report.failed.is_empty() {
             progress::finish_success(&pb, &format!("Installed {} packages", installed_count));
+        } else {
+            progress::finish_error(
+                &pb,
+                &format!("Installed {} packages ({} failed)", installed_count, batch_report.failed.len()),
+            );
         }
     }
 
-    if failed_count > 0 {
-        return Ok(1);
+    let summary = install_summary(&timings, downloaded_bytes);
+    if progress::enabled()
+        && let Some(line) = summary.line(&batch_report.installed)
+    {
+        progress::println(&line);
+    }
+    batch_report.summary = Some(summary);
+
+    if keep_going && let Some(summary) = batch_report.failure_summary() {
+        eprintln!("\n{}", summary);
+    }
+
+    if show_sizes && !batch_report.installed.is_empty() {
+        let (sizes, total) = batch_report.sizes();
+        progress::status(format!("\n{:<45} {:<15} {:>12}", "Package", "Version", "Size"));
+        progress::status("-".repeat(74));
+        for size in &sizes {
+            let bytes = installer::sizes::format_size(size.bytes);
+            progress::status(format!("{:<45} {:<15} {:>12}", size.name, size.version, bytes));
+        }
+        progress::status(format!("{:<61} {:>12}", "Total", installer::sizes::format_size(total)));
     }
 
-    Ok(0)
+    let mut full_report = models::InstallationReport::new();
+    for package in reported {
+        full_report.add_package(package);
+    }
+    full_report.set_outcome(&batch_report);
+    events::emit(InstallEvent::Report {
+        report: serde_json::to_value(&full_report).unwrap_or_default(),
+    });
+    if let Some(report_path) = report {
+        write_report(&report_path, &full_report)?;
+    }
+
+    Ok(batch_report)
 }
 
-/// Install a single package by downloading and extracting its wheel
-async fn install_package(pkg: &models::Package, temp_dir: &Path) -> Result<(), PipError> {
-    // Find wheel URL
-    let wheel_url = network::find_wheel_url(&pkg.name, &pkg.version)
-        .await
-        .map_err(|_e| PipError::PackageNotFound {
-            name: pkg.name.clone(),
-            version: Some(pkg.version.clone()),
-        })?;
-    
-    // Download wheel
-    // eprintln!("  Downloading {} from {}", pkg.name, wheel_url);
-    let wheel_data = network::PackageClient::new()
-        .download_package(&wheel_url)
-        .await
-        .map_err(|e| PipError::NetworkError {
-            message: format!("Failed to download {}", pkg.name),
-            retries: 0,
-            last_error: e.to_string(),
-        })?;
-    
-    // Save wheel to temp directory
-    let wheel_filename = format!("{}-{}.whl", pkg.name, pkg.version);
-    let wheel_path = temp_dir.join(&wheel_filename);
-    std::fs::write(&wheel_path, wheel_data).map_err(|e| PipError::FileSystemError {
-        path: wheel_path.to_string_lossy().to_string(),
+/// Names the phases are recorded under in the install's [`PerformanceTracker`]
+const RESOLVE: &str = "resolve";
+const DOWNLOAD: &str = "download";
+const INSTALL: &str = "install";
+
+/// "Resolving (N packages)..." on stderr, kept current from the resolver's
+/// progress events until the returned task is aborted
+fn resolution_spinner() -> Option<(indicatif::ProgressBar, tokio::task::JoinHandle<()>)> {
+    if !progress::enabled() {
+        return None;
+    }
+    let spinner = progress::add(progress::spinner("Resolving..."));
+    let mut events = events::subscribe();
+    let updated = spinner.clone();
+    let watcher = tokio::spawn(async move {
+        while let Some(envelope) = events.recv().await {
+            if let InstallEvent::ResolutionProgress { resolved, pending, .. } = envelope.event {
+                updated.set_message(format!("Resolving ({} packages)...", resolved + pending));
+            }
+        }
+    });
+    Some((spinner, watcher))
+}
+
+/// Totals of the phases recorded in `timings`
+fn install_summary(timings: &PerformanceTracker, downloaded_bytes: u64) -> installer::batch::InstallSummary {
+    let phases = timings.get_summary();
+    let millis = |phase: &str| phases.get(phase).map_or(0, |p| p.total_duration.as_millis() as u64);
+    installer::batch::InstallSummary {
+        downloads: phases.get(DOWNLOAD).map_or(0, |p| p.count as usize),
+        downloaded_bytes,
+        download_ms: millis(DOWNLOAD),
+        resolve_ms: millis(RESOLVE),
+        install_ms: millis(INSTALL),
+    }
+}
+
+/// Write `--report` JSON, to stdout for `-`
+fn write_report(path: &Path, report: &models::InstallationReport) -> Result<(), PipError> {
+    let json = serde_json::to_string_pretty(report).map_err(|e| PipError::InvalidPackage {
+        name: "report".to_string(),
+        reason: e.to_string(),
+    })?;
+    if path == Path::new("-") {
+        println!("{}", json);
+        return Ok(());
+    }
+    std::fs::write(path, json).map_err(|e| PipError::FileSystemError {
+        path: path.display().to_string(),
         operation: "write".to_string(),
         reason: e.to_string(),
+    })
+}
+
+/// Take the lock of `site_packages` for a command's changes, waiting up to
+/// `lock-timeout` for another process holding it
+pub(crate) fn lock_environment(site_packages: &Path) -> Result<installer::env_lock::EnvironmentLock, PipError> {
+    installer::env_lock::EnvironmentLock::acquire(site_packages).map_err(|e| {
+        match e.downcast::<installer::env_lock::Contended>() {
+            Ok(contended) => PipError::EnvironmentLocked {
+                site_packages: contended.site_packages.display().to_string(),
+                holder: contended.holder.map(|holder| holder.to_string()),
+                waited_secs: contended.waited.as_secs(),
+            },
+            Err(e) => PipError::FileSystemError {
+                path: site_packages.join(installer::env_lock::LOCK_FILE).display().to_string(),
+                operation: "lock".to_string(),
+                reason: e.to_string(),
+            },
+        }
+    })
+}
+
+/// Refuse to modify site-packages outside a virtualenv when one is required
+pub(crate) fn ensure_virtualenv(
+    config: &config::config::Config,
+    explicit_target: bool,
+) -> Result<(), PipError> {
+    if !config.require_virtualenv() || explicit_target {
+        return Ok(());
+    }
+
+    let site_packages = installer::SitePackages::default().map_err(|e| PipError::InstallationFailed {
+        package: "site-packages".to_string(),
+        reason: e.to_string(),
     })?;
-    
-    // Extract and install wheel
-    let wheel = installer::wheel::WheelFile::new(wheel_path).map_err(|e| PipError::InstallationFailed {
-        package: pkg.name.clone(),
+    installer::venv_guard::check_current(true, explicit_target, site_packages.path()).map_err(|_| {
+        PipError::VirtualEnvRequired {
+            site_packages: site_packages.path().display().to_string(),
+        }
+    })
+}
+
+/// Refuse to modify a system Python its distribution manages (PEP 668),
+/// unless --break-system-packages (or the config key) allows it
+pub(crate) fn ensure_not_externally_managed(
+    config: &config::config::Config,
+    explicit_target: bool,
+) -> Result<(), PipError> {
+    if explicit_target {
+        return Ok(());
+    }
+    let Ok(site_packages) = installer::SitePackages::default() else {
+        return Ok(());
+    };
+    let Some(managed) = installer::externally_managed::check_current(site_packages.path()) else {
+        return Ok(());
+    };
+    if config.break_system_packages() {
+        tracing::debug!("Ignoring {} (--break-system-packages)", managed.marker.display());
+        return Ok(());
+    }
+    Err(PipError::ExternallyManaged {
+        marker: managed.marker.display().to_string(),
+        message: managed.message,
+    })
+}
+
+/// Scan site-packages for damaged dist-info directories before changing it.
+/// Anomalies in `projects` (all of them when `None`) refuse the operation
+/// unless `allow_degraded` is set; unrelated ones only warn.
+pub(crate) fn preflight_environment(projects: Option<&[String]>, allow_degraded: bool) -> Result<(), PipError> {
+    let Ok(site_packages) = installer::SitePackages::default() else {
+        return Ok(());
+    };
+    let scan = match installer::preflight::EnvironmentScan::scan(site_packages.path()) {
+        Ok(scan) => scan,
+        Err(e) => {
+            tracing::debug!("Pre-flight scan of {} failed: {}", site_packages.path().display(), e);
+            return Ok(());
+        }
+    };
+    if scan.is_clean() {
+        return Ok(());
+    }
+
+    let (affecting, unrelated) = match projects {
+        Some(projects) => scan.partition(projects),
+        None => (scan.anomalies.iter().collect(), Vec::new()),
+    };
+    for anomaly in unrelated {
+        warnings::warn_user(warnings::WarningCode::DegradedEnvironment, anomaly.to_string());
+    }
+    if affecting.is_empty() {
+        return Ok(());
+    }
+    if allow_degraded {
+        for anomaly in affecting {
+            warnings::warn_user(warnings::WarningCode::DegradedEnvironment, anomaly.to_string());
+        }
+        return Ok(());
+    }
+    Err(PipError::DegradedEnvironment {
+        anomalies: affecting.iter().map(|a| a.to_string()).collect(),
+    })
+}
+
+/// Read a requirements file, interpolating `${VAR}` references and
+/// collecting its option lines
+pub(crate) fn load_requirements_file(
+    path: &Path,
+    allow_unset_env: bool,
+) -> Result<requirements_parser::RequirementsFile, PipError> {
+    let file = requirements_parser::RequirementsParser::load(path, allow_unset_env).map_err(|reason| {
+        PipError::InvalidRequirement {
+            spec: path.display().to_string(),
+            reason,
+        }
+    })?;
+    for (source, line_number, option) in &file.ignored_options {
+        warnings::warn_user(
+            warnings::WarningCode::UnsupportedOption,
+            format!("{}:{}: ignoring unsupported option '{}'", source, line_number, option),
+        );
+    }
+    Ok(file)
+}
+
+/// Constraints from the `-c` files a requirements file includes
+pub(crate) fn file_constraints(
+    file: &requirements_parser::RequirementsFile,
+) -> Result<Vec<models::Requirement>, PipError> {
+    parse_constraints(file, &file.constraints)
+}
+
+/// Constraints of the files given with `-c`, merged in order. Everything a
+/// constraints file names, including what it includes with `-r`, is a
+/// constraint.
+pub(crate) fn load_constraint_files(paths: &[PathBuf], allow_unset_env: bool) -> Result<Vec<models::Requirement>, PipError> {
+    let mut constraints = Vec::new();
+    for path in paths {
+        let file = load_requirements_file(path, allow_unset_env)?;
+        constraints.extend(parse_constraints(&file, &file.requirements)?);
+        constraints.extend(parse_constraints(&file, &file.constraints)?);
+    }
+    Ok(constraints)
+}
+
+/// Parse constraint lines. Editables, extras and direct URLs are refused as
+/// pip refuses them: a constraint can only narrow the versions of a named
+/// package.
+fn parse_constraints(
+    file: &requirements_parser::RequirementsFile,
+    lines: &[requirements_parser::ParsedRequirement],
+) -> Result<Vec<models::Requirement>, PipError> {
+    let mut constraints = Vec::new();
+    for line in lines.iter().filter(|r| !r.is_comment) {
+        let location = format!("{}:{}", line.source, line.line_number);
+        let refuse = |problem: &str| PipError::InvalidRequirement {
+            spec: file.mask(&line.requirement),
+            reason: format!("{} ({})", problem, location),
+        };
+        if line.is_editable {
+            return Err(refuse("Editable requirements are not allowed as constraints"));
+        }
+        let req = match line.requirement.parse::<models::Requirement>() {
+            Ok(req) => req,
+            Err(_) if line.requirement.contains("://") || line.requirement.starts_with(['.', '/']) => {
+                return Err(refuse("Unnamed requirements are not allowed as constraints"));
+            }
+            Err(e) => {
+                warnings::warn_user(
+                    warnings::WarningCode::InvalidRequirement,
+                    format!("{}: ignoring invalid constraint '{}': {}", location, file.mask(&line.requirement), e),
+                );
+                continue;
+            }
+        };
+        if !req.extras.is_empty() {
+            return Err(refuse("Constraints cannot have extras"));
+        }
+        if req.url.is_some() {
+            return Err(refuse("Links are not allowed as constraints"));
+        }
+        constraints.push(req);
+    }
+    Ok(constraints)
+}
+
+/// Apply requirements-file/CLI options to the configuration
+pub(crate) fn apply_file_options(
+    config: &mut config::config::Config,
+    options: &requirements_parser::FileOptions,
+    mask: &dyn Fn(&str) -> String,
+) {
+    if let Some(index_url) = &options.index_url {
+        tracing::debug!("Using index {}", mask(index_url));
+        config.set_index_url(index_url.clone());
+    }
+    for url in &options.extra_index_urls {
+        tracing::debug!("Using extra index {}", mask(url));
+        config.add_extra_index_url(url.clone());
+    }
+    for host in &options.trusted_hosts {
+        config.add_trusted_host(host.clone());
+    }
+    for link in &options.find_links {
+        tracing::debug!("Looking for packages in {}", mask(link));
+        config.add_find_links(link.clone());
+    }
+    if options.no_index {
+        config.set_no_index(true);
+    }
+    if !options.no_binary.is_empty() {
+        warnings::warn_user(
+            warnings::WarningCode::UnsupportedOption,
+            "--no-binary is not supported yet; packages are installed from wheels",
+        );
+    }
+}
+
+/// Point metadata lookups at `--index-url`/`--extra-index-url` on top of
+/// the configured indexes, for commands without a requirements file
+pub(crate) fn use_cli_indexes(index_url: Option<String>, extra_index_urls: Vec<String>) {
+    let mut config = config::config::Config::global().clone();
+    let options = requirements_parser::FileOptions {
+        index_url,
+        extra_index_urls,
+        ..Default::default()
+    };
+    apply_file_options(&mut config, &options, &redact_url_credentials);
+    network::configure_indexes(&config);
+}
+
+/// Validate --no-deps / --only-deps into a resolver dependency mode
+pub(crate) fn dependency_mode(
+    no_deps: bool,
+    only_deps: bool,
+) -> Result<resolver::DependencyMode, PipError> {
+    resolver::DependencyMode::from_flags(no_deps, only_deps).map_err(|e| PipError::InvalidRequirement {
+        spec: "--no-deps --only-deps".to_string(),
         reason: e.to_string(),
+    })
+}
+
+/// Read a local project's name, version and dependencies from its pyproject.toml
+pub(crate) fn load_local_project(dir: &Path) -> Result<models::Package, PipError> {
+    let pyproject_path = dir.join("pyproject.toml");
+    let pyproject = config::pyproject::PyProject::load(&pyproject_path).map_err(|e| {
+        PipError::FileSystemError {
+            path: pyproject_path.display().to_string(),
+            operation: "read".to_string(),
+            reason: e.to_string(),
+        }
     })?;
-    let site_packages = installer::SitePackages::default().map_err(|e| PipError::InstallationFailed {
-        package: pkg.name.clone(),
+    let name = pyproject.get_name().ok_or_else(|| PipError::InvalidPackage {
+        name: dir.display().to_string(),
+        reason: "pyproject.toml has no [project] name".to_string(),
+    })?;
+    let version = pyproject.get_version().unwrap_or_else(|| "0".to_string());
+
+    Ok(models::Package::new(canonicalize_name(&name), version)
+        .with_requires(pyproject.get_dependencies()))
+}
+
+/// Load the overrides file given on the command line, falling back to config
+pub(crate) fn load_overrides(
+    override_file: Option<&Path>,
+    config: &config::config::Config,
+) -> Result<Option<resolver::OverrideSet>, PipError> {
+    let path = match override_file {
+        Some(path) => path.to_path_buf(),
+        None => match config.override_file() {
+            Some(path) => path.to_path_buf(),
+            None => return Ok(None),
+        },
+    };
+
+    let overrides = resolver::OverrideSet::load(&path).map_err(|e| PipError::ConfigError {
+        message: e.to_string(),
+    })?;
+    tracing::debug!("Loaded {} overrides from {}", overrides.len(), path.display());
+    Ok(Some(overrides))
+}
+
+/// Load the index policy given on the command line, falling back to config
+pub(crate) fn load_index_policy(
+    index_policy: Option<&Path>,
+    config: &config::config::Config,
+) -> Result<Option<resolver::IndexPolicy>, PipError> {
+    let Some(path) = index_policy.or(config.index_policy()) else {
+        return Ok(None);
+    };
+    let policy = resolver::IndexPolicy::load(path).map_err(|e| PipError::ConfigError {
+        message: e.to_string(),
+    })?;
+    tracing::debug!("Loaded index policy from {}", path.display());
+    Ok(Some(policy))
+}
+
+/// Refuse the install before anything is downloaded if a resolved package
+/// was served by an index the policy doesn't allow for it
+pub(crate) fn enforce_index_policy(
+    policy: &resolver::IndexPolicy,
+    resolver: &resolver::Resolver,
+    resolved: &[models::Package],
+) -> Result<(), PipError> {
+    for pkg in resolved {
+        let Some(index) = resolver.index_source(&pkg.name) else {
+            continue;
+        };
+        policy.check(&pkg.name, index).map_err(|violation| {
+            PipError::IndexPolicyViolation {
+                package: violation.package,
+                index: violation.index.name,
+                url: violation.index.url,
+                allowed: violation.allowed,
+            }
+            .in_phase(Phase::Resolution, Some(&pkg.name))
+        })?;
+    }
+    Ok(())
+}
+
+/// Fail, naming every required package that no index has, with close
+/// matches among the installed, popular and cached project names
+pub(crate) fn check_not_found(resolver: &resolver::Resolver) -> Result<(), PipError> {
+    let missing = resolver.not_found();
+    if missing.is_empty() {
+        return Ok(());
+    }
+    let installed: Vec<String> = installer::SitePackages::default()
+        .and_then(|site_packages| site_packages.installed_distributions())
+        .map(|dists| dists.into_values().map(|dist| dist.name).collect())
+        .unwrap_or_default();
+    let known = names::known_project_names(installed);
+    let packages = missing
+        .iter()
+        .map(|name| MissingPackage {
+            name: name.to_string(),
+            suggestions: names::close_matches(name, known.iter().map(String::as_str)),
+        })
+        .collect();
+    Err(PipError::PackagesNotFound { packages })
+}
+
+/// Fail, with each reason, when a required package was left out of the
+/// resolution: going on would install or fetch only part of what was asked
+pub(crate) fn check_unresolved(resolver: &resolver::Resolver) -> Result<(), PipError> {
+    let unresolved = resolver.unresolved();
+    if unresolved.is_empty() {
+        return Ok(());
+    }
+    let names: Vec<&str> = unresolved.iter().map(|(name, _)| *name).collect();
+    let reasons: Vec<String> = unresolved.iter().map(|(name, reason)| format!("{}: {}", name, reason)).collect();
+    Err(PipError::DependencyResolutionError {
+        package: names.join(", "),
+        reason: reasons.join("; "),
+    }
+    .in_phase(Phase::Resolution, None))
+}
+
+/// Print the overrides applied during resolution and warn about unused ones
+pub(crate) fn print_override_summary(resolver: &resolver::Resolver) {
+    let applied = resolver.applied_overrides();
+    if !applied.is_empty() {
+        progress::status(format!("\nApplied {} override(s):", applied.len()));
+        for over in applied {
+            progress::status(format!("  - {}", over));
+        }
+    }
+
+    for over in resolver.unused_overrides() {
+        warnings::warn_user(
+            warnings::WarningCode::UnusedOverride,
+            format!("Override {} was declared but never matched a dependency", over),
+        );
+    }
+}
+
+/// Print what resolution explored (`--resolution-stats`)
+pub(crate) fn print_resolution_stats(resolver: &resolver::Resolver) {
+    let stats = resolver.resolution_stats();
+    progress::status(format!("\nResolution: {}", stats.headline()));
+    print!("{}", stats.details());
+}
+
+/// List the dependency edges environment markers excluded (`-v`)
+pub(crate) fn print_marker_skips(resolver: &resolver::Resolver) {
+    let section = resolver::markers::skipped_section(resolver.skipped_by_markers());
+    if !section.is_empty() {
+        progress::status("");
+        for line in section {
+            progress::status(line);
+        }
+    }
+}
+
+/// What to do with a resolved package given what is already installed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExistingAction {
+    /// Not installed yet
+    Install,
+    /// Keep the installed version
+    Satisfied,
+    /// Install the resolved version, then remove the installed one
+    Replace,
+    /// A different version is resolved, but the installed one is editable
+    KeepEditable,
+}
+
+/// Without `--upgrade`, an installed version that satisfies the requirement
+/// naming it is kept; with it, any other resolved version replaces it,
+/// downgrades to an older pin included. `--force-reinstall` also installs
+/// the resolved version over an identical one. Editable installs are never
+/// replaced.
+fn existing_action(
+    installed: Option<&InstalledDist>,
+    resolved_version: &str,
+    requested: Option<&models::Requirement>,
+    upgrade: bool,
+    force_reinstall: bool,
+) -> ExistingAction {
+    let Some(installed) = installed else {
+        return ExistingAction::Install;
+    };
+    if version::compare(&installed.version, resolved_version).is_eq() {
+        // Same dist-info: written over in place, nothing to remove after
+        if force_reinstall && !installed.editable {
+            ExistingAction::Install
+        } else {
+            ExistingAction::Satisfied
+        }
+    } else if installed.editable {
+        ExistingAction::KeepEditable
+    } else if !upgrade
+        && !force_reinstall
+        && requested.is_some_and(|req| version::satisfies_all(&installed.version, &req.specs))
+    {
+        ExistingAction::Satisfied
+    } else {
+        ExistingAction::Replace
+    }
+}
+
+/// A resolved package and what installing it would do
+struct Planned<'a> {
+    package: &'a models::Package,
+    action: ExistingAction,
+    installed: Option<InstalledDist>,
+}
+
+/// What an install of `resolved` into `site_packages` would do, package by package
+fn plan_install<'a>(
+    resolved: &'a [models::Package],
+    site_packages: Option<&Path>,
+    requested: &HashMap<String, models::Requirement>,
+    upgrade: bool,
+    force_reinstall: bool,
+) -> Vec<Planned<'a>> {
+    resolved
+        .iter()
+        .map(|package| {
+            let installed = site_packages.and_then(|site| InstalledDist::find(site, &package.name));
+            let action = existing_action(
+                installed.as_ref(),
+                &package.version,
+                requested.get(&canonicalize_name(&package.name)),
+                upgrade,
+                force_reinstall,
+            );
+            Planned { package, action, installed }
+        })
+        .collect()
+}
+
+/// The `--dry-run` summary: what would be installed or replaced, and what is kept
+fn dry_run_lines(plan: &[Planned]) -> Vec<String> {
+    let (changes, kept): (Vec<&Planned>, Vec<&Planned>) = plan
+        .iter()
+        .partition(|p| matches!(p.action, ExistingAction::Install | ExistingAction::Replace));
+
+    let mut lines = Vec::new();
+    if changes.is_empty() {
+        lines.push("Would install nothing; all requirements are already satisfied".to_string());
+    } else {
+        lines.push(format!("Would install {} package(s):", changes.len()));
+        for planned in &changes {
+            let mut line = format!("  - {} {}", planned.package.name, planned.package.version);
+            if let Some(installed) = &planned.installed {
+                line.push_str(&format!(" (replacing {})", installed.version));
+            }
+            lines.push(line);
+        }
+    }
+    if !kept.is_empty() {
+        lines.push("Already satisfied:".to_string());
+        for planned in &kept {
+            let Some(installed) = &planned.installed else {
+                continue;
+            };
+            let mut line = format!("  - {} {}", installed.name, installed.version);
+            if planned.action == ExistingAction::KeepEditable {
+                line.push_str(&format!(" (editable, kept instead of {})", planned.package.version));
+            }
+            lines.push(line);
+        }
+    }
+    lines.push("Dry run: nothing was downloaded or installed".to_string());
+    lines
+}
+
+/// The `--report` of a dry run: packages that would be installed, and those kept
+fn dry_run_report(
+    plan: &[Planned],
+    site_packages: Option<&Path>,
+    resolver: &resolver::Resolver,
+) -> models::InstallationReport {
+    let location = site_packages.map(|site| site.display().to_string()).unwrap_or_default();
+    let mut report = models::InstallationReport::new();
+    report.dry_run = true;
+    for planned in plan {
+        match (planned.action, &planned.installed) {
+            (ExistingAction::Install | ExistingAction::Replace, _) => {
+                report.add_package(models::InstalledPackage {
+                    name: planned.package.name.clone(),
+                    version: planned.package.version.clone(),
+                    location: location.clone(),
+                    direct_url: resolver.direct_url(&planned.package.name).map(str::to_string),
+                    ..Default::default()
+                })
+            }
+            (_, Some(installed)) => report.add_package(kept_package(installed)),
+            (_, None) => {}
+        }
+    }
+    report
+}
+
+/// The report entry of an installed distribution that is kept, with the
+/// artifact it came from when its provenance is recorded
+fn kept_package(installed: &InstalledDist) -> models::InstalledPackage {
+    let provenance = installer::provenance::Provenance::read(&installed.dist_info);
+    models::InstalledPackage {
+        name: installed.name.clone(),
+        version: installed.version.clone(),
+        location: installed.dist_info.parent().map(|site| site.display().to_string()).unwrap_or_default(),
+        editable: installed.editable,
+        already_satisfied: true,
+        download_url: provenance.as_ref().map(|p| p.url.clone()),
+        sha256: provenance.map(|p| p.sha256),
+        ..Default::default()
+    }
+}
+
+/// Advisory check of the selected index releases for deprecation notices;
+/// with `--no-deprecated` any finding stops the install
+async fn check_deprecations(
+    resolved: &[models::Package],
+    resolver: &resolver::Resolver,
+    local: &[String],
+    strict: bool,
+) -> Result<Vec<resolver::DeprecationNotice>, PipError> {
+    let releases: Vec<(String, String)> = resolved
+        .iter()
+        .filter(|p| !local.contains(&canonicalize_name(&p.name)) && resolver.direct_url(&p.name).is_none())
+        .map(|p| (p.name.clone(), p.version.clone()))
+        .collect();
+    let notices = resolver::deprecation::scan(&releases).await;
+    if let Some(message) = resolver::deprecation::warning(&notices) {
+        if strict {
+            return Err(PipError::InvalidRequirement {
+                spec: notices.iter().map(|n| format!("{}=={}", n.package, n.version)).collect::<Vec<_>>().join(", "),
+                reason: format!("--no-deprecated refuses deprecated packages: {}", message),
+            }
+            .in_phase(Phase::Resolution, None));
+        }
+        warnings::warn_user(warnings::WarningCode::DeprecatedPackage, message);
+    }
+    Ok(notices)
+}
+
+/// In hash-checking mode every requirement must name one exact version
+/// (or a direct URL) and carry a hash; checked before resolving anything
+fn check_hash_pins(
+    requirements: &[models::Requirement],
+    pinned_hashes: &HashMap<String, Vec<String>>,
+) -> Result<(), PipError> {
+    for req in requirements {
+        let pinned = req.url.is_some()
+            || (req.specs.len() == 1
+                && req.specs[0].op == models::VersionOp::Eq
+                && !req.specs[0].version.contains('*'));
+        let reason = if !pinned {
+            "hash-checking mode requires versions pinned with ==, e.g. requests==2.31.0"
+        } else if !pinned_hashes.contains_key(&canonicalize_name(&req.name)) {
+            "hash-checking mode requires a --hash for every requirement"
+        } else {
+            continue;
+        };
+        return Err(PipError::InvalidRequirement {
+            spec: req.to_string(),
+            reason: reason.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Refuse a downloaded file that matches none of the hashes pinned for `pkg`
+fn verify_download(pkg: &models::Package, path: &Path, expected: &[String]) -> Result<(), PipError> {
+    let checked = pip_rs_core::utils::hash::check_file(path, expected).map_err(|e| PipError::FileSystemError {
+        path: path.display().to_string(),
+        operation: "read".to_string(),
         reason: e.to_string(),
     })?;
-    let installer = installer::PackageInstaller::new(site_packages);
-    installer.install_wheel(&wheel).await.map_err(|e| PipError::InstallationFailed {
+    checked.map_err(|actual| PipError::HashMismatch {
         package: pkg.name.clone(),
-        reason: e.to_string(),
+        version: pkg.version.clone(),
+        expected: expected.to_vec(),
+        actual,
+    })
+}
+
+/// Remove the files of the version `pkg` just replaced
+fn remove_replaced(
+    pkg: &models::Package,
+    replaced: Option<&InstalledDist>,
+    destination: &Destination,
+) -> Result<(), PipError> {
+    let Some(old) = replaced else {
+        return Ok(());
+    };
+    let site_path = destination.path();
+    // A --user install shadows the system copy; that one isn't ours to remove
+    if !old.dist_info.starts_with(site_path) {
+        let location = old.dist_info.parent().unwrap_or(&old.dist_info);
+        progress::status(format!(
+            "Not uninstalling {} at {}, outside {}",
+            old.name,
+            location.display(),
+            site_path.display()
+        ));
+        return Ok(());
+    }
+    let keep = InstalledDist::find_version(site_path, &pkg.name, &pkg.version)
+        .map(|new| new.recorded_files(site_path))
+        .unwrap_or_default();
+    old.remove(site_path, &keep).map_err(|e| {
+        PipError::InstallationFailed {
+            package: pkg.name.clone(),
+            reason: format!("installed {} but could not remove {} {}: {}", pkg.version, old.name, old.version, e),
+        }
+        .in_phase(Phase::Install, Some(&pkg.name))
     })?;
-    
+    progress::status(format!("Successfully uninstalled {} {}", old.name, old.version));
     Ok(())
 }
+
+/// Links to the files of the version `pkg` replaces, so a failed batch can
+/// put it back; nothing to keep for a fresh install or one outside `destination`
+fn keep_for_rollback(
+    pkg: &models::Package,
+    replaced: Option<&InstalledDist>,
+    destination: &Destination,
+) -> Result<Option<Snapshot>, PipError> {
+    let Some(old) = replaced.filter(|old| old.dist_info.starts_with(destination.path())) else {
+        return Ok(None);
+    };
+    Snapshot::take(old, destination.path()).map(Some).map_err(|e| {
+        PipError::InstallationFailed {
+            package: pkg.name.clone(),
+            reason: format!("could not keep {} {} for --rollback-on-failure: {}", old.name, old.version, e),
+        }
+        .in_phase(Phase::Install, Some(&pkg.name))
+    })
+}
+
+/// Undo a failed batch, newest first: remove each package it installed and
+/// restore the version that package replaced. A package that can't be
+/// undone stays listed as installed.
+fn roll_back(undo: Vec<(models::Package, Option<Snapshot>)>, destination: &Destination, report: &mut BatchReport) {
+    let site_path = destination.path();
+    progress::status(format!("Rolling back {} installed package(s)", undo.len()));
+    for (pkg, kept) in undo.into_iter().rev() {
+        let restored = match InstalledDist::find_version(site_path, &pkg.name, &pkg.version) {
+            Some(new) => new.remove(site_path, &HashSet::new()).map(drop),
+            None => Ok(()),
+        }
+        .and_then(|()| kept.map_or(Ok(()), Snapshot::restore));
+        match restored {
+            Ok(()) => {
+                progress::status(format!("Rolled back {} {}", pkg.name, pkg.version));
+                report.record_rolled_back(&pkg.name);
+            }
+            Err(e) => eprintln!("ERROR: Could not roll back {} {}: {}", pkg.name, pkg.version, e),
+        }
+    }
+}
+
+/// Whether the requirements file's `--only-binary` covers `name`
+fn only_binary(options: &requirements_parser::FileOptions, name: &str) -> bool {
+    options
+        .only_binary
+        .iter()
+        .any(|entry| entry == ":all:" || canonicalize_name(entry) == canonicalize_name(name))
+}
+
+/// Install a single package by downloading and extracting its wheel.
+/// Errors are tagged with the phase (download or install) they came from.
+/// A broken wheel from the index is replaced once by the next file of the
+/// release `policy` allows.
+async fn install_package(
+    pkg: &models::Package,
+    temp_dir: &Path,
+    direct_url: Option<&str>,
+    policy: &network::FallbackPolicy<'_>,
+    index: Option<&resolver::IndexSource>,
+    destination: &Destination,
+    overwrite: installer::Overwrite,
+) -> Result<(u64, installer::provenance::Provenance, Option<installer::fallback::ArtifactFallback>), PipError> {
+    let Some(direct_url) = direct_url else {
+        let trace = network::release_selection(&pkg.name, &pkg.version).await.map_err(|_e| {
+            PipError::PackageNotFound {
+                name: pkg.name.clone(),
+                version: Some(pkg.version.clone()),
+            }
+            .in_phase(Phase::Download, Some(&pkg.name))
+        })?;
+        let site_path = destination.path();
+        let installed = installer::fallback::ArtifactInstaller {
+            site_packages: site_path,
+            scheme: destination.scheme.as_ref(),
+            work_dir: temp_dir,
+            overwrite,
+        }
+        .install(pkg, &trace, &installer::fallback::NetworkFetcher, policy)
+        .await?;
+        let provenance = installed.provenance.with_indexes(index.cloned(), index.cloned());
+        record_origin(pkg, site_path, Some(&provenance), None);
+        return Ok((installed.size, provenance, installed.fallback));
+    };
+
+    events::phase(Phase::Download, Some(&pkg.name));
+    let (wheel_path, provenance) = download_wheel(pkg, temp_dir, direct_url, policy.pinned_hashes)
+        .await
+        .map_err(|e| e.in_phase(Phase::Download, Some(&pkg.name)))?;
+    // A direct URL bypasses the index for the artifact
+    let provenance = provenance.with_indexes(index.cloned(), None);
+    // Requirements pinned to a URL are recorded per PEP 610 so freeze can
+    // reproduce them
+    let direct_url = resolver::DirectUrl::parse(direct_url).map(|url| url.to_json(Some(&provenance.sha256)));
+    events::phase(Phase::Install, Some(&pkg.name));
+    let size = install_downloaded(pkg, wheel_path, Some(&provenance), direct_url.as_ref(), destination, overwrite)
+        .await
+        .map_err(|e| e.in_phase(Phase::Install, Some(&pkg.name)))?;
+    Ok((size, provenance, None))
+}
+
+/// Fetch the wheel at `wheel_url` into `temp_dir`, recording its provenance
+/// alongside for the install step. With `hashes`, the file must match one
+/// of them before it is installed.
+async fn download_wheel(
+    pkg: &models::Package,
+    temp_dir: &Path,
+    wheel_url: &str,
+    hashes: Option<&[String]>,
+) -> Result<(std::path::PathBuf, installer::provenance::Provenance), PipError> {
+    let wheel_filename = format!("{}-{}.whl", pkg.name, pkg.version);
+    let wheel_path = temp_dir.join(&wheel_filename);
+    let started = Instant::now();
+    let bar = progress::artifact_bar(&wheel_filename);
+    let report = |written, total| {
+        if let Some(bar) = &bar {
+            progress::track_download(bar, written, total);
+        }
+    };
+    let report = bar.as_ref().map(|_| &report as &network::resume::Progress<'_>);
+    let fetched = network::PackageClient::new().download_to_file_with(wheel_url, &wheel_path, None, report).await;
+    if let Some(bar) = bar {
+        bar.finish_and_clear();
+    }
+    fetched?;
+    let read_error = |reason: String| PipError::FileSystemError {
+        path: wheel_path.to_string_lossy().to_string(),
+        operation: "read".to_string(),
+        reason,
+    };
+
+    let bytes = std::fs::metadata(&wheel_path).map_err(|e| read_error(e.to_string()))?.len();
+    events::emit(InstallEvent::Downloaded {
+        package: pkg.name.clone(),
+        version: pkg.version.clone(),
+        bytes,
+        seconds: started.elapsed().as_secs_f64(),
+    });
+    if let Some(expected) = hashes {
+        verify_download(pkg, &wheel_path, expected)?;
+    }
+    let provenance =
+        installer::provenance::Provenance::from_file(wheel_url, &wheel_path).map_err(|e| read_error(e.to_string()))?;
+    Ok((wheel_path, provenance))
+}
+
+/// Install a wheel from disk; `provenance` is recorded for downloaded
+/// artifacts, not for wheels pip-rs built itself, and `direct_url` for
+/// artifacts that didn't come from an index
+async fn install_downloaded(
+    pkg: &models::Package,
+    wheel_path: std::path::PathBuf,
+    provenance: Option<&installer::provenance::Provenance>,
+    direct_url: Option<&resolver::DirectUrlJson>,
+    destination: &Destination,
+    overwrite: installer::Overwrite,
+) -> Result<u64, PipError> {
+    let site_path = destination.path();
+    let artifact = wheel_path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+    let scheme = destination.scheme.as_ref();
+    let size = installer::fallback::install_wheel(site_path, scheme, pkg, wheel_path, &artifact, overwrite).await?;
+    record_origin(pkg, site_path, provenance, direct_url);
+    Ok(size)
+}
+
+/// Where an install writes: the active environment's site-packages; for
+/// `--target`, a directory that holds everything -- code, dist-info,
+/// scripts in `bin/`, data files -- and is never mixed with the environment;
+/// for `--user`, the per-user scheme
+struct Destination {
+    site_packages: installer::SitePackages,
+    /// The target's or user's own scheme; `None` derives it from the environment
+    scheme: Option<installer::scheme::Scheme>,
+    /// Further site-packages whose distributions count as installed (the
+    /// system one behind a user install); never changed
+    shadowed: Vec<std::path::PathBuf>,
+    /// Whether this is the per-user scheme (`--target` is not)
+    user: bool,
+}
+
+impl Destination {
+    fn new(target: Option<&Path>, user: bool) -> Result<Self, PipError> {
+        let site_error = |e: anyhow::Error| PipError::InstallationFailed {
+            package: "site-packages".to_string(),
+            reason: e.to_string(),
+        };
+        if user {
+            let base = installer::scheme::user_base()
+                .ok_or_else(|| site_error(anyhow::anyhow!("could not tell the user base directory (HOME is unset)")))?;
+            let version = pip_rs_core::utils::python_env::detected().version();
+            // User scripts run the interpreter the user site belongs to
+            let interpreter = pip_rs_core::venv::Interpreter::find(Some(&version)).ok().map(|python| python.executable);
+            let scheme = installer::scheme::Scheme::for_user(&base, &version, interpreter);
+            let site_packages = installer::SitePackages::new(scheme.purelib.clone()).map_err(site_error)?;
+            std::fs::create_dir_all(&scheme.scripts).map_err(|e| site_error(e.into()))?;
+            let shadowed = installer::SitePackages::default().map(|site| site.path().to_path_buf()).into_iter().collect();
+            return Ok(Self {
+                site_packages,
+                scheme: Some(scheme),
+                shadowed,
+                user,
+            });
+        }
+        let site_packages = match target {
+            Some(target) => installer::SitePackages::new(target.to_path_buf()),
+            None => installer::SitePackages::default(),
+        };
+        let site_packages = site_packages.map_err(site_error)?;
+        // Scripts in a target don't belong to any environment, so they run
+        // whichever python is on PATH
+        let scheme = target.map(|target| installer::scheme::Scheme::for_target(target, None));
+        Ok(Self {
+            site_packages,
+            scheme,
+            shadowed: Vec::new(),
+            user,
+        })
+    }
+
+    fn path(&self) -> &Path {
+        self.site_packages.path()
+    }
+
+    /// The installed distribution of `name`, looked up in sys.path order:
+    /// the destination first, then the site-packages it shadows
+    fn find_installed(&self, name: &str) -> Option<InstalledDist> {
+        std::iter::once(self.path())
+            .chain(self.shadowed.iter().map(std::path::PathBuf::as_path))
+            .find_map(|site| InstalledDist::find(site, name))
+    }
+
+    /// A `--target` install only ever replaces what lives in the target:
+    /// a distribution whose RECORD reaches outside it is left alone
+    fn check_replaceable(&self, pkg: &models::Package, replaced: Option<&InstalledDist>) -> Result<(), PipError> {
+        let (Some(_), Some(old), false) = (&self.scheme, replaced, self.user) else {
+            return Ok(());
+        };
+        let outside = old.recorded_files(self.path()).into_iter().find(|path| !self.site_packages.contains(path));
+        match outside {
+            Some(path) => Err(PipError::InstallationFailed {
+                package: pkg.name.clone(),
+                reason: format!(
+                    "{} {} in {} installed {}, outside the target; not replacing it",
+                    old.name,
+                    old.version,
+                    self.path().display(),
+                    path.display()
+                ),
+            }
+            .in_phase(Phase::Install, Some(&pkg.name))),
+            None => Ok(()),
+        }
+    }
+}
+
+/// pip's errors for `--user` where user site-packages can't be used
+pub(crate) fn check_user_install(target: bool) -> Result<(), &'static str> {
+    if target {
+        return Err("Can not combine '--user' and '--target'");
+    }
+    let virtual_env = crate::utils::python_env::virtual_env();
+    let in_virtualenv = installer::SitePackages::default()
+        .ok()
+        .and_then(|site| installer::venv_guard::owning_virtualenv(site.path(), virtual_env.as_deref()))
+        .is_some();
+    if in_virtualenv {
+        return Err("Can not perform a '--user' install. User site-packages are not visible in this virtualenv.");
+    }
+    Ok(())
+}
+
+/// Warn, as pip does, when scripts went to a directory that isn't on PATH
+fn warn_scripts_not_on_path(destination: &Destination, installed: &[installer::batch::InstalledPackage]) {
+    let Some(scheme) = &destination.scheme else {
+        return;
+    };
+    let on_path = std::env::var_os("PATH")
+        .is_some_and(|path| std::env::split_paths(&path).any(|dir| dir == scheme.scripts));
+    if on_path {
+        return;
+    }
+    let scripts_dir = installer::site_packages::canonical_path(&scheme.scripts);
+    let mut scripts: Vec<String> = installed
+        .iter()
+        .filter_map(|pkg| InstalledDist::find_version(destination.path(), &pkg.name, &pkg.version))
+        .flat_map(|dist| dist.recorded_files(destination.path()))
+        .filter(|path| path.parent().is_some_and(|dir| installer::site_packages::canonical_path(dir) == scripts_dir))
+        .filter_map(|path| path.file_name().map(|name| name.to_string_lossy().into_owned()))
+        .collect();
+    if scripts.is_empty() {
+        return;
+    }
+    scripts.sort();
+    let noun = if scripts.len() == 1 { "script" } else { "scripts" };
+    warnings::warn_user(
+        warnings::WarningCode::ScriptsNotOnPath,
+        format!(
+            "The {} {} {} installed in '{}' which is not on PATH",
+            noun,
+            scripts.join(", "),
+            if scripts.len() == 1 { "is" } else { "are" },
+            scheme.scripts.display()
+        ),
+    );
+}
+
+/// Record where the artifact came from (used by `pip freeze`)
+pub(crate) fn record_origin(
+    pkg: &models::Package,
+    site_path: &Path,
+    provenance: Option<&installer::provenance::Provenance>,
+    direct_url: Option<&resolver::DirectUrlJson>,
+) {
+    if provenance.is_none() && direct_url.is_none() {
+        return;
+    }
+    let Some(dist_info) = installer::provenance::find_dist_info(site_path, &pkg.name, &pkg.version) else {
+        tracing::debug!("No dist-info found for {} {}; provenance not recorded", pkg.name, pkg.version);
+        return;
+    };
+    if let Some(Err(e)) = provenance.map(|provenance| provenance.write(&dist_info)) {
+        tracing::debug!("Failed to record provenance for {}: {}", pkg.name, e);
+    }
+    if let Some(Err(e)) = direct_url.map(|direct_url| direct_url.write(&dist_info)) {
+        tracing::debug!("Failed to write direct_url.json for {}: {}", pkg.name, e);
+    }
+}
+
+/// A `git+` requirement, checked out and read
+struct VcsProject {
+    url: VcsUrl,
+    checkout: Checkout,
+    tree: SourceTree,
+}
+
+/// A bare `git+...` URL, or `name @ git+...`
+fn is_vcs_requirement(spec: &str) -> bool {
+    VcsUrl::is_vcs_url(spec)
+        || spec
+            .parse::<models::Requirement>()
+            .ok()
+            .and_then(|req| req.url)
+            .is_some_and(|url| VcsUrl::is_vcs_url(&url))
+}
+
+/// Clone a VCS requirement into `dest` and read the project's metadata
+fn checkout_vcs(spec: &str, dest: &Path) -> Result<VcsProject, PipError> {
+    let (name, url) = match spec.parse::<models::Requirement>().ok().and_then(|req| Some((req.name, req.url?))) {
+        Some((name, url)) => (Some(name), url),
+        None => (None, spec.trim().to_string()),
+    };
+    let vcs_error = |reason: String| PipError::VcsError {
+        url: redact_url_credentials(&url),
+        reason,
+    };
+    let url = VcsUrl::parse(&url).map_err(|e| vcs_error(e.to_string()))?;
+    progress::status(format!("Cloning {}", redact_url_credentials(&url.original)));
+    let checkout = url.checkout(dest).map_err(|e| vcs_error(e.to_string()))?;
+    let tree = SourceTree::load(&checkout.project_dir).map_err(|e| vcs_error(e.to_string()))?;
+    if let Some(name) = name
+        && canonicalize_name(&name) != canonicalize_name(&tree.name)
+    {
+        return Err(vcs_error(format!("the repository contains {}, not {}", tree.name, name)));
+    }
+    Ok(VcsProject { url, checkout, tree })
+}
+
+/// Build a wheel from a checked-out project and install it, recording the
+/// checkout in `direct_url.json`
+async fn install_vcs(
+    pkg: &models::Package,
+    project: &VcsProject,
+    temp_dir: &Path,
+    destination: &Destination,
+    overwrite: installer::Overwrite,
+) -> Result<u64, PipError> {
+    events::phase(Phase::Build, Some(&pkg.name));
+    let direct_url = project.url.direct_url_json(&project.checkout).to_json_string();
+    let wheel_path = project
+        .tree
+        .build_wheel(temp_dir, &[(resolver::direct_url::DIRECT_URL_FILE, direct_url)])
+        .map_err(|e| {
+            PipError::VcsError {
+                url: redact_url_credentials(&project.url.original),
+                reason: e.to_string(),
+            }
+            .in_phase(Phase::Build, Some(&pkg.name))
+        })?;
+    events::phase(Phase::Install, Some(&pkg.name));
+    install_downloaded(pkg, wheel_path, None, None, destination, overwrite)
+        .await
+        .map_err(|e| e.in_phase(Phase::Install, Some(&pkg.name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn installed(version: &str, editable: bool) -> InstalledDist {
+        InstalledDist {
+            name: "requests".to_string(),
+            version: version.to_string(),
+            dist_info: format!("/site/requests-{}.dist-info", version).into(),
+            editable,
+        }
+    }
+
+    #[test]
+    fn test_existing_action_upgrade_and_pins() {
+        let unpinned: models::Requirement = "requests".parse().unwrap();
+        let pinned: models::Requirement = "requests==2.28.0".parse().unwrap();
+        let current = installed("2.31.0", false);
+
+        assert_eq!(existing_action(None, "2.32.3", Some(&unpinned), false, false), ExistingAction::Install);
+        // Plain install keeps what satisfies the request; --upgrade replaces it
+        assert_eq!(existing_action(Some(&current), "2.32.3", Some(&unpinned), false, false), ExistingAction::Satisfied);
+        assert_eq!(existing_action(Some(&current), "2.32.3", Some(&unpinned), true, false), ExistingAction::Replace);
+        assert_eq!(existing_action(Some(&current), "2.31.0", Some(&unpinned), true, false), ExistingAction::Satisfied);
+        // A pin below the installed version downgrades, with or without --upgrade
+        assert_eq!(existing_action(Some(&current), "2.28.0", Some(&pinned), true, false), ExistingAction::Replace);
+        assert_eq!(existing_action(Some(&current), "2.28.0", Some(&pinned), false, false), ExistingAction::Replace);
+        // Editable installs stay put
+        let editable = installed("2.31.0", true);
+        assert_eq!(existing_action(Some(&editable), "2.28.0", Some(&pinned), true, false), ExistingAction::KeepEditable);
+        // --force-reinstall writes the same version over itself and replaces satisfied ones
+        assert_eq!(existing_action(Some(&current), "2.31.0", Some(&unpinned), false, true), ExistingAction::Install);
+        assert_eq!(existing_action(Some(&current), "2.32.3", Some(&unpinned), false, true), ExistingAction::Replace);
+        assert_eq!(existing_action(Some(&editable), "2.31.0", Some(&unpinned), false, true), ExistingAction::Satisfied);
+    }
+
+    #[test]
+    fn test_user_site_shadows_system_site() {
+        let dir = tempfile::tempdir().unwrap();
+        let user_site = dir.path().join("home/.local/lib/python3.12/site-packages");
+        let system_site = dir.path().join("usr/lib/python3.12/site-packages");
+        for (site, version) in [(&system_site, "2.28.0"), (&user_site, "2.31.0")] {
+            std::fs::create_dir_all(site.join(format!("requests-{}.dist-info", version))).unwrap();
+        }
+        let destination = Destination {
+            site_packages: installer::SitePackages::new(user_site.clone()).unwrap(),
+            scheme: None,
+            shadowed: vec![system_site.clone()],
+            user: true,
+        };
+        assert_eq!(destination.find_installed("requests").unwrap().version, "2.31.0");
+
+        // Only in the system site: found, but an upgrade leaves it in place
+        std::fs::remove_dir_all(user_site.join("requests-2.31.0.dist-info")).unwrap();
+        let system = destination.find_installed("requests").unwrap();
+        assert_eq!(system.version, "2.28.0");
+        let pkg = models::Package::new("requests".to_string(), "2.32.3".to_string());
+        remove_replaced(&pkg, Some(&system), &destination).unwrap();
+        assert!(system.dist_info.is_dir());
+        assert_eq!(check_user_install(true), Err("Can not combine '--user' and '--target'"));
+    }
+
+    #[test]
+    fn test_hash_checking_mode() {
+        let hashes: HashMap<String, Vec<String>> =
+            [("requests".to_string(), vec!["sha256:0000".to_string()])].into();
+        let req = |spec: &str| -> models::Requirement { spec.parse().unwrap() };
+
+        assert!(check_hash_pins(&[req("requests==2.31.0")], &hashes).is_ok());
+        let unpinned = check_hash_pins(&[req("requests>=2.31")], &hashes).unwrap_err().to_string();
+        assert!(unpinned.contains("pinned with =="), "{}", unpinned);
+        let unhashed = check_hash_pins(&[req("requests==2.31.0"), req("idna==3.7")], &hashes)
+            .unwrap_err()
+            .to_string();
+        assert!(unhashed.contains("idna==3.7") && unhashed.contains("--hash"), "{}", unhashed);
+
+        let pkg = models::Package::new("requests".to_string(), "2.31.0".to_string());
+        let dir = tempfile::tempdir().unwrap();
+        let wheel = dir.path().join("requests-2.31.0-py3-none-any.whl");
+        std::fs::write(&wheel, b"tampered").unwrap();
+        let err = verify_download(&pkg, &wheel, &hashes["requests"]).unwrap_err();
+        assert_eq!(err.code(), "hash-mismatch");
+        let message = err.to_string();
+        assert!(message.starts_with("Hash mismatch for requests 2.31.0: expected sha256:0000, got sha256:"), "{}", message);
+    }
+
+    #[test]
+    fn test_constraint_files_merge_and_refuse_extras_and_links() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, content: &str| {
+            let path = dir.path().join(name);
+            std::fs::write(&path, content).unwrap();
+            path
+        };
+        let first = write("first.txt", "urllib3<2\n-r pins.txt\n");
+        write("pins.txt", "idna==3.6\n");
+        let second = write("second.txt", "# comment\ncertifi>=2023\n");
+        let constraints: Vec<String> = load_constraint_files(&[first, second], false)
+            .unwrap()
+            .iter()
+            .map(|req| req.to_string())
+            .collect();
+        assert_eq!(constraints, ["urllib3<2", "idna==3.6", "certifi>=2023"]);
+
+        for (content, problem) in [
+            ("requests[socks]<3\n", "Constraints cannot have extras"),
+            ("pkg @ https://example.com/pkg-1.0-py3-none-any.whl\n", "Links are not allowed as constraints"),
+            ("-e ./src\n", "Editable requirements are not allowed as constraints"),
+        ] {
+            let file = write("bad.txt", content);
+            let err = load_constraint_files(&[file], false).unwrap_err().to_string();
+            assert!(err.contains(problem) && err.contains("bad.txt:1"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_dry_run_plan_against_site_packages() {
+        let dir = tempfile::tempdir().unwrap();
+        let site = dir.path();
+        for dist_info in ["requests-2.31.0.dist-info", "certifi-2024.2.2.dist-info"] {
+            std::fs::create_dir_all(site.join(dist_info)).unwrap();
+        }
+        let resolved = vec![
+            models::Package::new("requests".to_string(), "2.32.3".to_string()),
+            models::Package::new("certifi".to_string(), "2024.2.2".to_string()),
+            models::Package::new("idna".to_string(), "3.7".to_string()),
+        ];
+        let requested: HashMap<String, models::Requirement> =
+            [("requests".to_string(), "requests>=2.32".parse().unwrap())].into();
+
+        let plan = plan_install(&resolved, Some(site), &requested, false, false);
+        assert_eq!(
+            dry_run_lines(&plan),
+            [
+                "Would install 2 package(s):",
+                "  - requests 2.32.3 (replacing 2.31.0)",
+                "  - idna 3.7",
+                "Already satisfied:",
+                "  - certifi 2024.2.2",
+                "Dry run: nothing was downloaded or installed",
+            ]
+        );
+        // Nothing was touched
+        assert!(site.join("requests-2.31.0.dist-info").is_dir());
+
+        let report = serde_json::to_value(dry_run_report(&plan, Some(site), &resolver::Resolver::new())).unwrap();
+        assert_eq!(report["installed"][1]["version"], "2024.2.2");
+        assert_eq!(report["installed"][1]["already_satisfied"], true);
+        assert_eq!(report["installed"][2]["name"], "idna");
+        assert_eq!(report["installed"][2]["already_satisfied"], false);
+        assert_eq!(report["dry_run"], true);
+    }
+}
@@ -1,50 +1,181 @@
 /// List command implementation
 use anyhow::Result;
-use std::path::Path;
-use std::fs;
-use std::cmp::Ordering;
+use std::path::PathBuf;
+use crate::utils::pager::PagedOutput;
+use crate::models::Environment;
+use crate::resolver::requires_python::select_release;
 use crate::utils::progress;
+use crate::utils::version;
+use pip_rs_core::cache::layout;
+use pip_rs_core::installer::site_packages::{canonical_path, SitePackages};
+use pip_rs_core::utils::names::canonicalize_name;
+use pip_rs_core::installer::sizes::{self, PackageSize, SizeSnapshot};
 
 #[derive(Debug, Clone)]
 struct Package {
     name: String,
     version: String,
     latest_version: Option<String>,
+    /// `wheel` or `sdist`: what the latest release would install from
+    latest_filetype: Option<&'static str>,
+    /// The index couldn't be asked about this package
+    check_failed: bool,
+    dist_info: PathBuf,
 }
 
-fn compare_versions(current: &str, latest: &str) -> Ordering {
-    // Use PEP 440 version parsing for proper comparison
-    match (pep440::Version::parse(current), pep440::Version::parse(latest)) {
-        (Some(v1), Some(v2)) => v1.cmp(&v2),
-        // Fallback to string comparison if parsing fails
-        _ => {
-            // Simple fallback: try numeric comparison on first parts
-            let current_parts: Vec<&str> = current.split('.').collect();
-            let latest_parts: Vec<&str> = latest.split('.').collect();
-
-            for i in 0..current_parts.len().max(latest_parts.len()) {
-                let curr = current_parts.get(i)
-                    .and_then(|s| s.parse::<u32>().ok())
-                    .unwrap_or(0);
-                let lat = latest_parts.get(i)
-                    .and_then(|s| s.parse::<u32>().ok())
-                    .unwrap_or(0);
-                
-                match curr.cmp(&lat) {
-                    Ordering::Equal => continue,
-                    other => return other,
-                }
-            }
-            Ordering::Equal
+/// Shown in the Latest column when a package couldn't be checked
+const UNKNOWN: &str = "unknown";
+
+/// How `pip list` prints its result
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ListFormat {
+    /// Aligned table for people
+    #[default]
+    Columns,
+    /// Array of objects, as `pip list --format=json` prints
+    Json,
+    /// `name==version` lines, as `pip freeze` prints
+    Freeze,
+}
+
+/// One `pip list --format=json` entry; the `latest_*` keys only appear
+/// with `--outdated`, as in pip
+#[derive(Debug, serde::Serialize)]
+struct JsonEntry<'a> {
+    name: &'a str,
+    version: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    latest_version: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    latest_filetype: Option<&'a str>,
+}
+
+fn render_json(packages: &[Package], outdated: bool) -> String {
+    let entries: Vec<JsonEntry> = packages
+        .iter()
+        .map(|pkg| JsonEntry {
+            name: &pkg.name,
+            version: &pkg.version,
+            latest_version: pkg
+                .latest_version
+                .as_deref()
+                .or(pkg.check_failed.then_some(UNKNOWN))
+                .filter(|_| outdated),
+            latest_filetype: pkg.latest_filetype.filter(|_| outdated),
+        })
+        .collect();
+    serde_json::to_string(&entries).unwrap_or_else(|_| "[]".to_string())
+}
+
+/// The newest release in a JSON API response that isn't yanked and
+/// supports `python`, and whether it ships a wheel; pre-releases only
+/// with `pre` or when nothing else qualifies
+fn latest_release(info: &serde_json::Value, python: &str, pre: bool) -> Option<(String, &'static str)> {
+    let releases = crate::network::releases_from_json(info);
+    let latest = select_release(&releases, python, pre)?.version.clone();
+    let filetype = release_filetype(info, &latest);
+    Some((latest, filetype))
+}
+
+/// `wheel` when the release's files include one, else `sdist`
+fn release_filetype(info: &serde_json::Value, version: &str) -> &'static str {
+    let has_wheel = info["releases"][version]
+        .as_array()
+        .is_some_and(|files| files.iter().any(|file| file["packagetype"] == "bdist_wheel"));
+    if has_wheel { "wheel" } else { "sdist" }
+}
+
+/// Package, Version, Latest and Type columns, each as wide as its widest cell
+fn outdated_table(packages: &[Package]) -> Vec<String> {
+    let rows: Vec<[&str; 4]> = packages
+        .iter()
+        .map(|pkg| {
+            let latest = pkg.latest_version.as_deref().unwrap_or(UNKNOWN);
+            [pkg.name.as_str(), pkg.version.as_str(), latest, pkg.latest_filetype.unwrap_or("")]
+        })
+        .collect();
+    let header = ["Package", "Version", "Latest", "Type"];
+    let mut widths = header.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
         }
     }
+    let line = |cells: [&str; 4]| {
+        let padded: Vec<String> = cells.iter().zip(widths).map(|(cell, width)| format!("{:<width$}", cell)).collect();
+        padded.join(" ").trim_end().to_string()
+    };
+    let dashes = widths.map(|width| "-".repeat(width));
+    let mut lines = vec![line(header), line(dashes.each_ref().map(String::as_str))];
+    lines.extend(rows.into_iter().map(line));
+    lines
 }
 
 use crate::errors::PipError;
 
 // ... (rest of the file)
 
-pub async fn handle_list(outdated: bool) -> Result<i32, PipError> {
+/// Print installed sizes largest first, reusing the cached snapshot for
+/// distributions whose RECORD hasn't changed
+fn print_sizes(packages: &[Package], out: &mut PagedOutput) {
+    let snapshot_dir = layout::cache_root().map(|root| root.join(layout::SNAPSHOTS_DIR));
+    let mut snapshots: std::collections::HashMap<PathBuf, SizeSnapshot> = std::collections::HashMap::new();
+    let mut package_sizes = Vec::with_capacity(packages.len());
+
+    for pkg in packages {
+        let Some(site_packages) = pkg.dist_info.parent() else {
+            continue;
+        };
+        let snapshot = snapshots.entry(site_packages.to_path_buf()).or_insert_with(|| match &snapshot_dir {
+            Some(dir) => SizeSnapshot::load(&SizeSnapshot::path_for(dir, site_packages)),
+            None => SizeSnapshot::default(),
+        });
+        let bytes = match snapshot.size_of(site_packages, &pkg.dist_info) {
+            Ok(bytes) => bytes,
+            // No RECORD: measure the dist-info itself rather than guess
+            Err(_) => sizes::tree_size(&pkg.dist_info),
+        };
+        package_sizes.push(PackageSize {
+            name: pkg.name.clone(),
+            version: pkg.version.clone(),
+            bytes,
+        });
+    }
+    for snapshot in snapshots.values() {
+        if let Err(e) = snapshot.save() {
+            tracing::debug!("Failed to save size snapshot: {}", e);
+        }
+    }
+
+    let total = sizes::sort_by_size(&mut package_sizes);
+    out.line("");
+    out.line(format!("{:<45} {:<15} {:>12}", "Package", "Version", "Size"));
+    out.line("-".repeat(74));
+    for size in &package_sizes {
+        out.line(format!("{:<45} {:<15} {:>12}", size.name, size.version, sizes::format_size(size.bytes)));
+    }
+    out.line(format!("{:<61} {:>12}", format!("Total ({} packages)", package_sizes.len()), sizes::format_size(total)));
+}
+
+/// JSON or freeze output: exactly the data on stdout, never paged
+fn print_machine_readable(packages: &[Package], outdated: bool, format: ListFormat) {
+    let mut out = PagedOutput::plain();
+    match format {
+        ListFormat::Json => out.line(render_json(packages, outdated)),
+        ListFormat::Freeze | ListFormat::Columns => {
+            let pins: Vec<(&str, &str, &PathBuf)> = packages
+                .iter()
+                .map(|pkg| (pkg.name.as_str(), pkg.version.as_str(), &pkg.dist_info))
+                .collect();
+            for line in super::freeze::requirement_lines(&pins) {
+                out.line(line);
+            }
+        }
+    }
+    out.finish();
+}
+
+pub async fn handle_list(outdated: bool, pre: bool, show_sizes: bool, format: ListFormat) -> Result<i32, PipError> {
     // Check common site-packages locations
     let site_packages_paths = vec![
         // macOS user site-packages (checked first as it's most common)
@@ -68,124 +199,113 @@ pub async fn handle_list(outdated: bool) -> Result<i32, PipError> {
     use std::collections::HashSet;
     let mut seen_packages = HashSet::new();
 
-    for path_str in site_packages_paths {
-        let expanded_path = if path_str.starts_with('~') {
-            shellexpand::tilde(path_str).to_string()
-        } else {
-            path_str.to_string()
-        };
+    // The environment install and uninstall use comes first; a directory
+    // reached again through a symlink is only listed once
+    let mut directories: Vec<PathBuf> = SitePackages::default()
+        .map(|site| vec![site.path().to_path_buf()])
+        .unwrap_or_default();
+    directories.extend(site_packages_paths.iter().map(|path| PathBuf::from(shellexpand::tilde(path).to_string())));
+    let mut seen_directories = HashSet::new();
 
-        let path = Path::new(&expanded_path);
-        if path.exists() {
-            // List .dist-info directories
-            let entries = fs::read_dir(path).map_err(|e| PipError::FileSystemError {
-                path: path.to_string_lossy().to_string(),
-                operation: "read".to_string(),
-                reason: e.to_string(),
-            })?;
-            for entry in entries {
-                let entry = entry.map_err(|e| PipError::FileSystemError {
-                    path: "site-packages".to_string(),
-                    operation: "read entry".to_string(),
+    for path in &directories {
+        if path.exists() && seen_directories.insert(canonical_path(path)) {
+            let installed = SitePackages::new(path.clone())
+                .and_then(|site| site.installed_distributions())
+                .map_err(|e| PipError::FileSystemError {
+                    path: path.to_string_lossy().to_string(),
+                    operation: "read".to_string(),
                     reason: e.to_string(),
                 })?;
-                let entry_path = entry.path();
-                if let Some(name) = entry_path.file_name() {
-                    if let Some(name_str) = name.to_str() {
-                        if name_str.ends_with(".dist-info") {
-                            // Parse package name and version from .dist-info directory name
-                            // Format: {name}-{version}.dist-info
-                            // The version starts with a digit, so find the last dash before a digit
-                            let pkg_info = name_str.trim_end_matches(".dist-info");
-                            
-                            // Find the version part (starts with a digit)
-                            let mut version_start = pkg_info.len();
-                            for (i, ch) in pkg_info.char_indices().rev() {
-                                if ch == '-' && i + 1 < pkg_info.len() {
-                                    if let Some(next_ch) = pkg_info[i + 1..].chars().next() {
-                                        if next_ch.is_ascii_digit() {
-                                            version_start = i;
-                                            break;
-                                        }
-                                    }
-                                }
-                            }
-                            
-                            if version_start < pkg_info.len() {
-                                let pkg_name = pkg_info[..version_start].to_string();
-                                let version = pkg_info[version_start + 1..].to_string();
-                                
-                                // Only add if we haven't seen this package before
-                                // (prefer earlier paths in the list)
-                                let pkg_key = pkg_name.to_lowercase();
-                                if !seen_packages.contains(&pkg_key) {
-                                    seen_packages.insert(pkg_key);
-                                    packages.push(Package { 
-                                        name: pkg_name, 
-                                        version,
-                                        latest_version: None,
-                                    });
-                                }
-                            }
-                        }
-                    }
+            for (key, dist) in installed {
+                // The first directory to have a project wins, and a dist-info
+                // directory without a version in its name isn't listed
+                if dist.version.is_empty() || !seen_packages.insert(key) {
+                    continue;
                 }
+                packages.push(Package {
+                    name: dist.name,
+                    version: dist.version,
+                    latest_version: None,
+                    latest_filetype: None,
+                    check_failed: false,
+                    dist_info: dist.dist_info,
+                });
             }
         }
     }
 
     if packages.is_empty() {
-        println!("No packages found in site-packages");
+        if format == ListFormat::Columns {
+            println!("No packages found in site-packages");
+        } else {
+            print_machine_readable(&packages, outdated, format);
+        }
         return Ok(0);
     }
 
+    // Long listings are paged on a terminal and pipe-safe otherwise
+    let mut out = PagedOutput::new();
+
     // Sort packages by name
-    packages.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+    packages.sort_by_key(|package| canonicalize_name(&package.name));
 
     // If outdated flag is set, fetch latest versions
     if outdated {
-        use crate::network::get_package_metadata;
         use std::sync::Arc;
         use tokio::sync::Semaphore;
         use futures::future::join_all;
 
         let total = packages.len();
         
-        // Create progress bar (hidden in quiet mode)
-        let pb = if progress::is_quiet() {
+        // Create progress bar (hidden in quiet mode and machine-readable output)
+        let pb = if progress::is_quiet() || format != ListFormat::Columns {
             None
         } else {
             Some(progress::progress_bar(total as u64, "Checking packages"))
         };
 
-        // Fetch latest versions in parallel (10 concurrent)
-        let semaphore = Arc::new(Semaphore::new(10));
+        // Fetch release lists in parallel, within the --jobs budget
+        let concurrency = crate::utils::resources::ResourceBudget::global().network;
+        let semaphore = Arc::new(Semaphore::new(concurrency));
+        let python = Environment::current().target_python().to_string();
         let mut handles = vec![];
 
-        for (idx, pkg) in packages.iter_mut().enumerate() {
+        for pkg in packages.iter() {
             let semaphore_clone = semaphore.clone();
             let pkg_name = pkg.name.clone();
-            
+            let python = python.clone();
+
             let handle = tokio::spawn(async move {
                 let _permit = semaphore_clone.acquire().await.ok();
-                match get_package_metadata(&pkg_name, "latest").await {
-                    Ok(metadata) => Some((metadata.name, metadata.version, idx)),
-                    Err(_) => None,
-                }
+                let info = match crate::network::package_info(&pkg_name).await {
+                    Ok(info) => info,
+                    Err(e) => {
+                        tracing::debug!("Failed to fetch releases of {}: {}", pkg_name, e);
+                        return None;
+                    }
+                };
+                let name = info["info"]["name"].as_str().unwrap_or(&pkg_name).to_string();
+                Some((name, latest_release(&info, &python, pre)))
             });
             handles.push(handle);
         }
 
         // Collect results with progress feedback
         let results = join_all(handles).await;
-        for result in results.into_iter() {
+        for (pkg, result) in packages.iter_mut().zip(results) {
             if let Some(prog) = &pb {
                 prog.inc(1);
             }
-            
-            if let Ok(Some((canonical_name, latest, idx))) = result {
-                packages[idx].name = canonical_name;
-                packages[idx].latest_version = Some(latest);
+
+            match result {
+                Ok(Some((canonical_name, latest))) => {
+                    pkg.name = canonical_name;
+                    if let Some((version, filetype)) = latest {
+                        pkg.latest_version = Some(version);
+                        pkg.latest_filetype = Some(filetype);
+                    }
+                }
+                _ => pkg.check_failed = true,
             }
         }
         
@@ -193,39 +313,111 @@ pub async fn handle_list(outdated: bool) -> Result<i32, PipError> {
             progress::finish_success(&pb, &format!("Checked {} packages", total));
         }
 
-        // Filter to only outdated packages
-        packages.retain(|pkg| {
-            if let Some(latest) = &pkg.latest_version {
-                compare_versions(&pkg.version, latest) == Ordering::Less
-            } else {
-                false
-            }
+        // Keep outdated packages, and those that couldn't be checked
+        packages.retain(|pkg| match &pkg.latest_version {
+            Some(latest) => version::compare(&pkg.version, latest).is_lt(),
+            None => pkg.check_failed,
         });
 
+        if format != ListFormat::Columns {
+            print_machine_readable(&packages, outdated, format);
+            return Ok(0);
+        }
         if packages.is_empty() {
             println!("✓ All packages are up-to-date!\n");
             return Ok(0);
         }
 
-        // Display outdated packages
-        println!("{:<45} {:<15} {:<15}", "Package", "Current", "Latest");
-        println!("{}", "-".repeat(75));
-        
-        for pkg in packages {
-            if let Some(latest) = pkg.latest_version {
-                println!("{:<45} {:<15} {:<15}", pkg.name, pkg.version, latest);
-            }
+        for line in outdated_table(&packages) {
+            out.line(line);
         }
+    } else if format != ListFormat::Columns {
+        print_machine_readable(&packages, outdated, format);
+        return Ok(0);
+    } else if show_sizes {
+        print_sizes(&packages, &mut out);
     } else {
         // Display all packages
-        println!("\n{:<50} {:<20}", "Package", "Version");
-        println!("{}", "-".repeat(70));
+        out.line("");
+        out.line(format!("{:<50} {:<20}", "Package", "Version"));
+        out.line("-".repeat(70));
         
         for pkg in packages {
-            println!("{:<50} {:<20}", pkg.name, pkg.version);
+            out.line(format!("{:<50} {:<20}", pkg.name, pkg.version));
         }
     }
     
-    println!();
+    out.line("");
+    out.finish();
     Ok(0)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(name: &str, version: &str, latest: Option<(&str, &'static str)>) -> Package {
+        Package {
+            name: name.to_string(),
+            version: version.to_string(),
+            latest_version: latest.map(|(v, _)| v.to_string()),
+            latest_filetype: latest.map(|(_, t)| t),
+            check_failed: false,
+            dist_info: PathBuf::from(format!("{}-{}.dist-info", name, version)),
+        }
+    }
+
+    #[test]
+    fn test_json_matches_pip_schema() {
+        let packages = [package("requests", "2.31.0", Some(("2.32.3", "wheel")))];
+        let plain: serde_json::Value = serde_json::from_str(&render_json(&packages, false)).unwrap();
+        assert_eq!(plain, serde_json::json!([{"name": "requests", "version": "2.31.0"}]));
+
+        let outdated: serde_json::Value = serde_json::from_str(&render_json(&packages, true)).unwrap();
+        assert_eq!(
+            outdated,
+            serde_json::json!([{
+                "name": "requests",
+                "version": "2.31.0",
+                "latest_version": "2.32.3",
+                "latest_filetype": "wheel",
+            }])
+        );
+        assert_eq!(render_json(&[], true), "[]");
+    }
+
+    #[test]
+    fn test_latest_release_skips_prereleases_yanked_and_unsupported() {
+        let file = |packagetype: &str, requires_python: &str, yanked: bool| {
+            serde_json::json!({"packagetype": packagetype, "requires_python": requires_python, "yanked": yanked})
+        };
+        let info = serde_json::json!({"releases": {
+            "1.0": [file("bdist_wheel", ">=3.8", false), file("sdist", ">=3.8", false)],
+            "1.1": [file("sdist", ">=3.8", false)],
+            "1.1.1": [file("bdist_wheel", ">=3.8", true)],
+            "1.2": [file("bdist_wheel", ">=99", false)],
+            "2.0rc1": [file("bdist_wheel", ">=3.8", false)],
+        }});
+        assert_eq!(latest_release(&info, "3.11.4", false), Some(("1.1".to_string(), "sdist")));
+        assert_eq!(latest_release(&info, "3.11.4", true), Some(("2.0rc1".to_string(), "wheel")));
+        assert_eq!(latest_release(&serde_json::json!({}), "3.11.4", false), None);
+    }
+
+    #[test]
+    fn test_outdated_table_columns() {
+        let mut unchecked = package("left-pad", "0.1", None);
+        unchecked.check_failed = true;
+        let packages = [package("requests", "2.31.0", Some(("2.32.3", "wheel"))), unchecked];
+        assert_eq!(
+            outdated_table(&packages),
+            [
+                "Package  Version Latest  Type",
+                "-------- ------- ------- -----",
+                "requests 2.31.0  2.32.3  wheel",
+                "left-pad 0.1     unknown",
+            ]
+        );
+        let json: serde_json::Value = serde_json::from_str(&render_json(&packages[1..], true)).unwrap();
+        assert_eq!(json, serde_json::json!([{"name": "left-pad", "version": "0.1", "latest_version": "unknown"}]));
+    }
+}
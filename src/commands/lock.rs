@@ -1,13 +1,30 @@
 /// Lock command - generate lock files for reproducible installs
-use crate::errors::PipError;
+use crate::errors::{Phase, PipError};
 use anyhow::Result;
 use std::path::Path;
-use pip_rs_core::{models, resolver, network, installer};
+use pip_rs_core::utils::names::canonicalize_name;
+use pip_rs_core::{config, models, resolver, network, installer};
+use pip_rs_core::installer::sync_plan::{Change, EXIT_CHANGED, Pin, SYNC_KEEPS, SyncPlan};
+use pip_rs_core::resolver::lockfile::LockedPackage;
+use pip_rs_core::utils::environment_markers::{EnvironmentContext, MarkerEvaluator};
+use pip_rs_core::utils::warnings;
+use std::collections::BTreeMap;
+
+/// Marker variables of the running environment, as recorded in locks
+fn running_environment() -> BTreeMap<String, String> {
+    EnvironmentContext::default().to_marker_vars().into_iter().collect()
+}
 
 pub async fn handle_lock(
     requirements: Option<String>,
     output: Option<String>,
+    override_file: Option<String>,
+    no_deps: bool,
+    only_deps: bool,
+    allow_unset_env: bool,
+    resolution: crate::cli::parser::ResolutionOptions,
 ) -> Result<i32, PipError> {
+    let dependency_mode = super::install::dependency_mode(no_deps, only_deps)?;
     if requirements.is_none() {
         eprintln!("ERROR: You must provide a requirements file with -r/--requirements");
         return Ok(1);
@@ -21,20 +38,14 @@ pub async fn handle_lock(
 
     println!("Reading requirements from {}...", req_file);
 
-    // Parse requirements file
-    let contents = std::fs::read_to_string(&req_file).map_err(|e| PipError::FileSystemError {
-        path: req_file.clone(),
-        operation: "read".to_string(),
-        reason: e.to_string(),
-    })?;
-    let mut all_requirements = Vec::new();
-
-    for line in contents.lines() {
-        let line = line.trim();
-        if !line.is_empty() && !line.starts_with('#') {
-            all_requirements.push(line.to_string());
-        }
-    }
+    // Parse requirements file, including its option lines
+    let requirements_file = super::install::load_requirements_file(Path::new(&req_file), allow_unset_env)?;
+    let all_requirements: Vec<String> = requirements_file
+        .requirements
+        .iter()
+        .filter(|r| !r.is_comment)
+        .map(|r| r.requirement.clone())
+        .collect();
 
     if all_requirements.is_empty() {
         eprintln!("ERROR: No requirements found in {}", req_file);
@@ -52,7 +63,10 @@ pub async fn handle_lock(
                 parsed_reqs.push(req);
             }
             Err(e) => {
-                eprintln!("Warning: Failed to parse requirement '{}': {}", req_str, e);
+                warnings::warn_user(
+                    warnings::WarningCode::InvalidRequirement,
+                    format!("Failed to parse requirement '{}': {}", requirements_file.mask(&req_str), e),
+                );
             }
         }
     }
@@ -65,20 +79,48 @@ pub async fn handle_lock(
     // Resolve dependencies
     println!("\nResolving dependencies...");
     let mut resolver = resolver::Resolver::new();
-    let resolved = resolver.resolve(parsed_reqs).await.map_err(|e| PipError::DependencyResolutionError {
-        package: "requirements".to_string(),
-        reason: e.to_string(),
-    })?;
+    resolver.set_dependency_mode(dependency_mode);
+    resolver.set_resolution_budget(resolution.budget());
+    let constraints = super::install::file_constraints(&requirements_file)?;
+    if !constraints.is_empty() {
+        resolver.set_constraints(constraints);
+    }
+    let mut config = config::config::Config::global().clone();
+    super::install::apply_file_options(&mut config, &requirements_file.options, &|text| {
+        requirements_file.mask(text)
+    });
+    network::configure_indexes(&config);
+    if let Some(overrides) = super::install::load_overrides(override_file.as_deref().map(Path::new), &config)? {
+        resolver.set_overrides(overrides);
+    }
+    let resolved = resolver
+        .resolve(parsed_reqs.clone())
+        .await
+        .map_err(|e| PipError::from(e).in_phase(Phase::Resolution, None))?;
+    super::install::check_not_found(&resolver)?;
+    // A lock missing part of the closure would install a different set
+    super::install::check_unresolved(&resolver)?;
 
     println!("Successfully resolved {} packages:", resolved.len());
     for pkg in &resolved {
         println!("  - {} {}", pkg.name, pkg.version);
     }
+    super::install::print_override_summary(&resolver);
+    if resolution.stats {
+        super::install::print_resolution_stats(&resolver);
+    }
 
     // Create lock file
     println!("\nGenerating lock file...");
     let python_version = format!("{}.{}", 3, 11); // Default to 3.11
-    let lockfile = resolver::LockFile::from_packages(resolved, python_version);
+    let mut lockfile = resolver::LockFile::from_packages(resolved, python_version)
+        .with_pip_rs_version(env!("CARGO_PKG_VERSION"))
+        .with_overrides(resolver.applied_overrides().to_vec())
+        .with_marker_skips(resolver.skipped_by_markers().to_vec())
+        .with_dependency_mode(dependency_mode)
+        .with_environment(running_environment());
+    lockfile.record_requirements(&parsed_reqs);
+    pin_artifacts(&mut lockfile, &resolver).await?;
 
     // Validate lock file
     lockfile.validate().map_err(|e| PipError::InvalidPackage {
@@ -102,9 +144,93 @@ pub async fn handle_lock(
     Ok(0)
 }
 
-#[allow(dead_code)]
+/// Record the file each locked package installs from, its sha256 and the
+/// index it came from. Direct URLs are kept as given; a release whose
+/// files can't be listed fails the lock.
+async fn pin_artifacts(lockfile: &mut resolver::LockFile, resolver: &resolver::Resolver) -> Result<(), PipError> {
+    let locked: Vec<(String, String)> =
+        lockfile.packages.values().map(|p| (p.name.clone(), p.version.clone())).collect();
+    for (name, version) in locked {
+        if resolver.is_local(&name) {
+            continue;
+        }
+        let direct_url = resolver.direct_url(&name).map(String::from);
+        let file = match &direct_url {
+            Some(_) => None,
+            None => {
+                let trace = network::release_selection(&name, &version).await.map_err(|e| {
+                    PipError::DependencyResolutionError {
+                        package: name.clone(),
+                        reason: format!("couldn't list the files of {}: {}", version, e),
+                    }
+                    .in_phase(Phase::Resolution, Some(&name))
+                })?;
+                let file = trace
+                    .best(&network::FallbackPolicy::default())
+                    .map_err(|e| e.in_phase(Phase::Resolution, Some(&name)))?;
+                Some(file.clone())
+            }
+        };
+        let index_url = resolver.index_source(&name).map(|source| source.url.clone());
+        let Some(package) = lockfile.package_mut(&name, &version) else {
+            continue;
+        };
+        match file {
+            Some(file) => {
+                package.hash = file.sha256.map(|sha256| format!("sha256:{}", sha256));
+                package.url = Some(file.url);
+                package.index_url = index_url;
+            }
+            None => package.url = direct_url,
+        }
+    }
+    Ok(())
+}
+
+/// Convert a poetry.lock or uv.lock into a pip-rs lock file
+pub async fn handle_lock_import(import: String, output: Option<String>) -> Result<i32, PipError> {
+    let imported = resolver::foreign::import_lock(Path::new(&import)).map_err(|e| PipError::InvalidPackage {
+        name: import.clone(),
+        reason: format!("{:#}", e),
+    })?;
+    let summary = &imported.summary;
+    println!(
+        "Imported {} packages from {} ({} format version {})",
+        summary.imported,
+        import,
+        summary.format.as_str(),
+        summary.format_version
+    );
+
+    if !summary.dropped.is_empty() {
+        println!("\nNot carried over ({}):", summary.dropped.len());
+        for note in &summary.dropped {
+            println!("  - {}", note);
+        }
+    }
+
+    let lock_path = output.unwrap_or_else(|| "pip-lock.json".to_string());
+    imported.lock.save(Path::new(&lock_path)).map_err(|e| PipError::FileSystemError {
+        path: lock_path.clone(),
+        operation: "save".to_string(),
+        reason: e.to_string(),
+    })?;
+
+    println!("\n✓ Lock file generated: {}", lock_path);
+    println!("  Install it with: pip install --locked {}", lock_path);
+    Ok(0)
+}
+
+/// Install exactly what a lock file pins, without resolving: packages
+/// that are missing or at another version are installed from the locked
+/// file, checked against its recorded hash. With `sync`, installed
+/// packages the lock doesn't list are removed too, and the exit status is
+/// 0 when nothing had to change and [`EXIT_CHANGED`] when something did.
 pub async fn handle_lock_install(
     lock_file: String,
+    strict_repro: bool,
+    ignore_environment: bool,
+    sync: bool,
 ) -> Result<i32, PipError> {
     if !Path::new(&lock_file).exists() {
         eprintln!("ERROR: Lock file not found: {}", lock_file);
@@ -126,93 +252,180 @@ pub async fn handle_lock_install(
         reason: e.to_string(),
     })?;
 
+    // Refuse anything non-deterministic before downloading a single file
+    let running = running_environment();
+    if strict_repro {
+        resolver::strict::validate(&lockfile, &running).map_err(|e| PipError::InvalidPackage {
+            name: lock_file.clone(),
+            reason: e.to_string(),
+        })?;
+    }
+    let mismatch = lockfile.environment_mismatch(&running);
+    if !mismatch.is_empty() {
+        let message = format!("the lock was resolved for another environment: {}", mismatch.join("; "));
+        if !ignore_environment {
+            return Err(PipError::InvalidPackage {
+                name: lock_file.clone(),
+                reason: format!("{}; pass --ignore-environment to install it anyway", message),
+            });
+        }
+        warnings::warn_user(warnings::WarningCode::EnvironmentMismatch, message);
+    }
+
     println!("Lock file validated");
     println!("  Packages: {}", lockfile.packages.len());
     println!("  Python version: {}", lockfile.python_version);
     println!("  Generated at: {}", lockfile.generated_at);
 
-    // Convert to packages
-    let packages = lockfile.to_packages();
+    // Skip entries whose markers exclude this environment
+    let evaluator = MarkerEvaluator::new(EnvironmentContext::default());
+    let (entries, excluded): (Vec<&LockedPackage>, Vec<&LockedPackage>) = lockfile
+        .packages
+        .values()
+        .partition(|p| p.markers.as_deref().is_none_or(|m| evaluator.evaluate(m)));
+    if !excluded.is_empty() {
+        let names: Vec<&str> = excluded.iter().map(|p| p.name.as_str()).collect();
+        println!("  Skipped (markers don't match this environment): {}", names.join(", "));
+    }
+
+    let pins: Vec<Pin> = entries
+        .iter()
+        .map(|p| Pin {
+            name: p.name.clone(),
+            version: Some(p.version.clone()),
+        })
+        .collect();
+    let installed = super::watch::installed_versions().map_err(|reason| PipError::InstallationFailed {
+        package: "environment".to_string(),
+        reason,
+    })?;
+    let plan = if sync {
+        SyncPlan::converge(&pins, &installed, SYNC_KEEPS)
+    } else {
+        SyncPlan::compute(&pins, &installed)
+    };
+    if plan.is_empty() {
+        println!("\nEnvironment already matches {}", lock_file);
+        return Ok(0);
+    }
+
+    println!("\nApplying {} change{}:", plan.changes.len(), if plan.changes.len() == 1 { "" } else { "s" });
+    for change in &plan.changes {
+        println!("  {}", change);
+    }
 
-    // Install packages
-    println!("\nInstalling {} packages from lock file...", packages.len());
-    
+    let site_packages = installer::SitePackages::default().map_err(|e| PipError::InstallationFailed {
+        package: "environment".to_string(),
+        reason: e.to_string(),
+    })?;
+    let site = site_packages.path();
     let temp_dir = tempfile::TempDir::new().map_err(|e| PipError::FileSystemError {
         path: "temp".to_string(),
         operation: "create".to_string(),
         reason: e.to_string(),
     })?;
-    let mut installed_count = 0;
+    let locked = |name: &str| {
+        let name = canonicalize_name(name);
+        entries.iter().copied().find(|p| canonicalize_name(&p.name) == name)
+    };
+    let _lock = super::install::lock_environment(site)?;
     let mut failed_count = 0;
+    let mut applied = 0;
 
-    for pkg in &packages {
-        match install_package(pkg, temp_dir.path()).await {
-            Ok(_) => {
-                println!("✓ Successfully installed {} {}", pkg.name, pkg.version);
-                installed_count += 1;
+    for change in &plan.changes {
+        // Interrupted: what was applied stays, the rest is left for the next sync
+        if pip_rs_core::utils::cancel::is_requested() {
+            break;
+        }
+        let result = match change {
+            Change::Remove { name, .. } => remove_installed(site, name),
+            Change::Replace { name, .. } => match (remove_installed(site, name), locked(name)) {
+                (Ok(()), Some(entry)) => install_locked(entry, site, temp_dir.path()).await,
+                (Err(e), _) => Err(e),
+                (Ok(()), None) => Ok(()),
+            },
+            Change::Install { name, .. } => match locked(name) {
+                Some(entry) => install_locked(entry, site, temp_dir.path()).await,
+                None => Ok(()),
+            },
+        };
+        match result {
+            Ok(()) => {
+                println!("✓ {}", change);
+                applied += 1;
             }
             Err(e) => {
-                eprintln!("✗ Failed to install {} {}: {}", pkg.name, pkg.version, e);
+                eprintln!("✗ {}: {}", change, e);
                 failed_count += 1;
             }
         }
     }
-    
+
     // Cleanup happens automatically when TempDir is dropped
-    
-    println!("\nInstallation complete!");
-    println!("  Successfully installed: {}", installed_count);
+
+    println!("\n{} complete!", if sync { "Sync" } else { "Installation" });
+    println!("  Changes applied: {}", applied);
     if failed_count > 0 {
         println!("  Failed: {}", failed_count);
         return Ok(1);
     }
 
-    Ok(0)
+    Ok(if sync { EXIT_CHANGED } else { 0 })
 }
 
-/// Install a single package by downloading and extracting its wheel
-#[allow(dead_code)]
-async fn install_package(pkg: &models::Package, temp_dir: &std::path::Path) -> Result<(), PipError> {
-    // Find wheel URL
-    let wheel_url = network::find_wheel_url(&pkg.name, &pkg.version).await.map_err(|_e| PipError::PackageNotFound {
-        name: pkg.name.clone(),
-        version: Some(pkg.version.clone()),
-    })?;
-    
-    // Download wheel
-    eprintln!("  Downloading {} from {}", pkg.name, wheel_url);
-    let wheel_data = network::PackageClient::new()
-        .download_package(&wheel_url)
-        .await
-        .map_err(|e| PipError::NetworkError {
-            message: format!("Failed to download {}", pkg.name),
-            retries: 0,
-            last_error: e.to_string(),
-        })?;
-    
-    // Save wheel to temp directory
-    let wheel_filename = format!("{}-{}.whl", pkg.name, pkg.version);
-    let wheel_path = temp_dir.join(&wheel_filename);
-    std::fs::write(&wheel_path, wheel_data).map_err(|e| PipError::FileSystemError {
-        path: wheel_path.to_string_lossy().to_string(),
-        operation: "write".to_string(),
-        reason: e.to_string(),
-    })?;
-    
-    // Extract and install wheel
-    let wheel = installer::wheel::WheelFile::new(wheel_path).map_err(|e| PipError::InvalidPackage {
-        name: pkg.name.clone(),
-        reason: e.to_string(),
-    })?;
-    let site_packages = installer::SitePackages::default().map_err(|e| PipError::InstallationFailed {
-        package: pkg.name.clone(),
-        reason: e.to_string(),
-    })?;
-    let installer = installer::PackageInstaller::new(site_packages);
-    installer.install_wheel(&wheel).await.map_err(|e| PipError::InstallationFailed {
-        package: pkg.name.clone(),
-        reason: e.to_string(),
+/// Uninstall whatever version of `name` is installed
+fn remove_installed(site_packages: &Path, name: &str) -> Result<(), PipError> {
+    let scripts_dirs = installer::uninstall::scripts_dirs(site_packages);
+    let Some(plan) = installer::uninstall::UninstallPlan::new(site_packages, &scripts_dirs, name) else {
+        return Ok(());
+    };
+    plan.execute(site_packages, false).map_err(|e| {
+        PipError::UninstallationFailed {
+            package: name.to_string(),
+            reason: e.to_string(),
+        }
+        .in_phase(Phase::Install, Some(name))
     })?;
-    
+    Ok(())
+}
+
+/// Install a locked entry from the file the lock names, verified against
+/// its recorded hash; entries without a file go through the index
+async fn install_locked(entry: &LockedPackage, site_packages: &Path, temp_dir: &Path) -> Result<(), PipError> {
+    let pkg = models::Package::new(entry.name.clone(), entry.version.clone());
+    let trace = match &entry.url {
+        Some(url) => {
+            let filename = url.split(['#', '?']).next().unwrap_or(url).rsplit('/').next().unwrap_or(url);
+            let file = network::ReleaseFile {
+                filename: filename.to_string(),
+                url: url.clone(),
+                size: None,
+                uploaded: None,
+                sha256: entry.hash.as_deref().and_then(|hash| hash.strip_prefix("sha256:")).map(String::from),
+            };
+            network::SelectionTrace::new(&pkg.name, &pkg.version, vec![file])
+        }
+        None => network::release_selection(&pkg.name, &pkg.version).await.map_err(|_e| {
+            PipError::PackageNotFound {
+                name: pkg.name.clone(),
+                version: Some(pkg.version.clone()),
+            }
+            .in_phase(Phase::Download, Some(&pkg.name))
+        })?,
+    };
+    let pinned: Vec<String> = entry.hash.iter().cloned().collect();
+    let policy = network::FallbackPolicy {
+        only_binary: false,
+        pinned_hashes: entry.hash.as_ref().map(|_| pinned.as_slice()),
+    };
+    let installed = installer::fallback::ArtifactInstaller {
+        site_packages,
+        scheme: None,
+        work_dir: temp_dir,
+        overwrite: installer::Overwrite::default(),
+    }
+    .install(&pkg, &trace, &installer::fallback::NetworkFetcher, &policy)
+    .await?;
+    super::install::record_origin(&pkg, site_packages, Some(&installed.provenance), None);
     Ok(())
 }
@@ -11,3 +11,14 @@ pub mod download;
 pub mod lock;
 pub mod debug;
 pub mod completion;
+pub mod cache;
+pub mod find_import;
+pub mod inspect;
+pub mod explain_markers;
+pub mod deps;
+pub mod watch;
+pub mod datasets;
+pub mod version;
+pub mod help;
+pub mod config;
+pub mod venv;
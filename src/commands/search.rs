@@ -1,28 +1,97 @@
 /// Search command implementation
 use crate::errors::PipError;
-use pip_rs_core::network;
+use pip_rs_core::network::search::{self, NotEnumerable, SearchHit};
 
-pub async fn handle_search(query: &str) -> Result<i32, PipError> {
-    println!("Searching for packages matching '{}'...", query);
-    
-    match network::search_package(query).await {
-        Ok(packages) => {
-            if packages.is_empty() {
-                println!("No packages found");
-            } else {
-                println!("Found {} packages:", packages.len());
-                for pkg in packages {
-                    println!("  {} - {}", pkg.name, pkg.summary.unwrap_or_default());
-                }
-            }
-            Ok(0)
+/// How `pip search` prints its hits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum SearchFormat {
+    /// One line per package: name, version and summary
+    #[default]
+    Human,
+    /// Array of objects with name, match, version and summary
+    Json,
+}
+
+pub async fn handle_search(
+    query: &str,
+    index_url: Option<String>,
+    extra_index_urls: Vec<String>,
+    limit: usize,
+    format: SearchFormat,
+    names_only: bool,
+) -> Result<i32, PipError> {
+    super::install::use_cli_indexes(index_url, extra_index_urls);
+
+    let hits = match search::search(query, limit, !names_only).await {
+        Ok(hits) => hits,
+        Err(e) if e.is::<NotEnumerable>() => {
+            eprintln!("ERROR: {}", e);
+            return Ok(1);
         }
         Err(e) => {
-            Err(PipError::NetworkError {
+            return Err(PipError::NetworkError {
                 message: "Search failed".to_string(),
                 retries: 0,
                 last_error: e.to_string(),
-            })
+            });
+        }
+    };
+
+    match format {
+        SearchFormat::Json => println!("{}", serde_json::to_string(&hits).unwrap_or_else(|_| "[]".to_string())),
+        SearchFormat::Human if hits.is_empty() => println!("No packages found matching '{}'", query),
+        SearchFormat::Human => {
+            for line in render_human(&hits) {
+                println!("{}", line);
+            }
         }
     }
+    Ok(0)
+}
+
+/// `name (version)` padded to a common width, then the summary
+fn render_human(hits: &[SearchHit]) -> Vec<String> {
+    let label = |hit: &SearchHit| match &hit.version {
+        Some(version) => format!("{} ({})", hit.name, version),
+        None => hit.name.clone(),
+    };
+    let width = hits.iter().map(|hit| label(hit).len()).max().unwrap_or(0);
+    hits.iter()
+        .map(|hit| match &hit.summary {
+            Some(summary) => format!("{:<width$}  - {}", label(hit), summary, width = width),
+            None => label(hit),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pip_rs_core::network::search::MatchKind;
+
+    #[test]
+    fn test_render_human_and_json() {
+        let hits = vec![
+            SearchHit {
+                name: "flask".to_string(),
+                kind: MatchKind::Exact,
+                version: Some("3.0.3".to_string()),
+                summary: Some("A simple framework for building complex web applications.".to_string()),
+            },
+            SearchHit {
+                name: "flask-cors".to_string(),
+                kind: MatchKind::Prefix,
+                version: None,
+                summary: None,
+            },
+        ];
+        assert_eq!(
+            render_human(&hits),
+            ["flask (3.0.3)  - A simple framework for building complex web applications.", "flask-cors"]
+        );
+        assert_eq!(
+            serde_json::to_value(&hits[1]).unwrap(),
+            serde_json::json!({"name": "flask-cors", "match": "prefix", "version": null, "summary": null})
+        );
+    }
 }
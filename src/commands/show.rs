@@ -1,34 +1,117 @@
 /// Show command implementation
+///
+/// Reads installed metadata only: `Requires` lists the dependencies that
+/// apply in this environment and `Required-by` the installed distributions
+/// that depend on the package, both matched by normalized name.
 use crate::errors::PipError;
-use pip_rs_core::installer::site_packages::SitePackages;
+use pip_rs_core::installer::import_names::InstalledImportIndex;
+use pip_rs_core::installer::site_packages::{PackageDetails, SitePackages};
+use pip_rs_core::models::Environment;
+use pip_rs_core::resolver::DirectUrlJson;
+use pip_rs_core::utils::names::canonicalize_name;
 
-pub async fn handle_show(package: &str) -> Result<i32, PipError> {
-    println!("Fetching information for package: {}", package);
-    
-    // Get site packages
+pub async fn handle_show(packages: &[String], provides: bool) -> Result<i32, PipError> {
     let site_packages = SitePackages::default().map_err(|e| PipError::FileSystemError {
         path: "site-packages".to_string(),
         operation: "access".to_string(),
         reason: e.to_string(),
     })?;
-    
-    match site_packages.get_package_details(package) {
-        Ok(Some(info)) => {
-            println!("Name: {}", info.name);
-            println!("Version: {}", info.version);
-            println!("Location: {}", info.location.display());
-            if !info.requires.is_empty() {
-                println!("Requires: {}", info.requires.join(", "));
-            }
-            Ok(0)
+    let installed = site_packages.all_package_details().map_err(|e| PipError::FileSystemError {
+        path: site_packages.path().display().to_string(),
+        operation: "read".to_string(),
+        reason: e.to_string(),
+    })?;
+    let env = Environment::current();
+    let imports = if provides { InstalledImportIndex::build(site_packages.path()).ok() } else { None };
+
+    let mut missing = Vec::new();
+    let mut shown = 0;
+    for package in packages {
+        let Some(info) = find(&installed, package) else {
+            missing.push(package.as_str());
+            continue;
+        };
+        if shown > 0 {
+            println!("---");
         }
-        Ok(None) => {
-            eprintln!("Package '{}' not found", package);
-            Ok(1)
+        shown += 1;
+        for line in describe(info, &installed, &env) {
+            println!("{}", line);
         }
-        Err(e) => {
-            eprintln!("Error getting package info for '{}': {}", package, e);
-            Ok(1)
+        if provides {
+            let names: Vec<String> = imports
+                .as_ref()
+                .and_then(|index| index.get(&info.name).map(|d| d.import_names.iter().cloned().collect()))
+                .unwrap_or_default();
+            println!("Provides: {}", names.join(", "));
+        }
+    }
+
+    if !missing.is_empty() {
+        eprintln!("WARNING: Package(s) not found: {}", missing.join(", "));
+        return Ok(1);
+    }
+    Ok(0)
+}
+
+fn find<'a>(installed: &'a [PackageDetails], package: &str) -> Option<&'a PackageDetails> {
+    let wanted = canonicalize_name(package);
+    installed.iter().find(|details| canonicalize_name(&details.name) == wanted)
+}
+
+/// The `pip show` fields for one installed distribution
+fn describe(info: &PackageDetails, installed: &[PackageDetails], env: &Environment) -> Vec<String> {
+    let mut lines = vec![
+        format!("Name: {}", info.name),
+        format!("Version: {}", info.version),
+        format!("Location: {}", info.location.display()),
+    ];
+    match DirectUrlJson::read(&info.dist_info) {
+        Some(direct_url) if direct_url.is_editable() => {
+            let project = direct_url.url.strip_prefix("file://").unwrap_or(&direct_url.url);
+            lines.push(format!("Editable project location: {}", project));
         }
+        Some(direct_url) => lines.push(format!("Direct URL: {}", direct_url.pep508_url())),
+        None => {}
+    }
+    lines.push(format!("Requires: {}", info.dependencies(env).join(", ")));
+    lines.push(format!("Required-by: {}", info.required_by(installed, env).join(", ")));
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn details(name: &str, requires: &[&str]) -> PackageDetails {
+        PackageDetails {
+            name: name.to_string(),
+            version: "1.0".to_string(),
+            location: PathBuf::from("/site"),
+            requires: requires.iter().map(|r| r.to_string()).collect(),
+            dist_info: PathBuf::from(format!("/site/{}-1.0.dist-info", name)),
+        }
+    }
+
+    #[test]
+    fn test_describe_lists_requires_and_required_by() {
+        let installed = vec![
+            details("requests", &["charset_normalizer<4,>=2", "urllib3<3,>=1.21.1", "PySocks!=1.5.7 ; extra == \"socks\""]),
+            details("charset-normalizer", &[]),
+            details("urllib3", &[]),
+        ];
+        let env = Environment::current();
+
+        let lines = describe(&installed[0], &installed, &env);
+        assert_eq!(lines[3], "Requires: charset_normalizer, urllib3");
+        assert_eq!(lines[4], "Required-by: ");
+
+        let lines = describe(&installed[1], &installed, &env);
+        assert_eq!(lines[3], "Requires: ");
+        assert_eq!(lines[4], "Required-by: requests");
+
+        assert_eq!(find(&installed, "Charset_Normalizer").map(|d| d.name.as_str()), Some("charset-normalizer"));
+        assert!(find(&installed, "idna").is_none());
     }
 }
@@ -1,9 +1,18 @@
 /// Uninstall command implementation
 use crate::errors::PipError;
 use std::io::{self, BufRead};
-use pip_rs_core::installer;
+use pip_rs_core::installer::import_names::InstalledImportIndex;
+use pip_rs_core::installer::uninstall::{self, UninstallPlan};
+use pip_rs_core::utils::warnings::{self, WarningCode};
+use pip_rs_core::{config, installer};
 
-pub async fn handle_uninstall(packages: Vec<String>, yes: bool) -> Result<i32, PipError> {
+pub async fn handle_uninstall(
+    packages: Vec<String>,
+    yes: bool,
+    force: bool,
+    user: bool,
+    allow_degraded_environment: bool,
+) -> Result<i32, PipError> {
     if packages.is_empty() {
         return Err(PipError::InvalidRequirement {
             spec: "None".to_string(),
@@ -11,9 +20,63 @@ pub async fn handle_uninstall(packages: Vec<String>, yes: bool) -> Result<i32, P
         });
     }
 
-    println!("The following packages will be removed:");
+    let config = config::config::Config::global();
+    super::install::ensure_virtualenv(config, false)?;
+    super::install::ensure_not_externally_managed(config, false)?;
+    super::install::preflight_environment(Some(&packages), allow_degraded_environment)?;
+
+    let site_packages = if user {
+        if let Err(message) = super::install::check_user_install(false) {
+            eprintln!("ERROR: {}", message);
+            return Ok(1);
+        }
+        user_site_packages()
+    } else {
+        installer::SitePackages::default()
+    };
+    let site_packages = site_packages.map_err(|e| PipError::InstallationFailed {
+        package: "site-packages".to_string(),
+        reason: e.to_string(),
+    })?;
+    let site = site_packages.path();
+    let scripts_dirs = uninstall::scripts_dirs(site);
+
+    // Work out every removal up front so it can be shown before asking
+    let mut plans = Vec::new();
+    let mut failed_count = 0;
     for pkg in &packages {
-        println!("  - {}", pkg);
+        match UninstallPlan::new(site, &scripts_dirs, pkg) {
+            Some(plan) => match plan.refusal().filter(|_| !force) {
+                Some(refusal) => {
+                    eprintln!("✗ Refusing to uninstall {}: {}", pkg, refusal);
+                    failed_count += 1;
+                }
+                None => plans.push(plan),
+            },
+            None => {
+                eprintln!("✗ Failed to uninstall {}: Package {} not found", pkg, pkg);
+                failed_count += 1;
+            }
+        }
+    }
+    if plans.is_empty() {
+        return Ok(1);
+    }
+
+    println!("The following packages will be removed:");
+    for plan in &plans {
+        println!("  - {}", plan.label());
+        println!("    Would remove:");
+        for line in plan.preview(site) {
+            println!("      {}", line);
+        }
+    }
+
+    // Warn when other installed packages still need what is being removed
+    if let Ok(index) = InstalledImportIndex::build(site) {
+        for warning in index.uninstall_warnings(&packages) {
+            warnings::warn_user(WarningCode::ImportNameInUse, warning);
+        }
     }
 
     // Get confirmation if not --yes flag
@@ -34,24 +97,20 @@ pub async fn handle_uninstall(packages: Vec<String>, yes: bool) -> Result<i32, P
         }
     }
 
-    // Uninstall packages
-    let site_packages = installer::SitePackages::default().map_err(|e| PipError::InstallationFailed {
-        package: "site-packages".to_string(),
-        reason: e.to_string(),
-    })?;
-    let installer = installer::PackageInstaller::new(site_packages);
-    
+    crate::cli::signals::install();
+    let _lock = super::install::lock_environment(site)?;
     let mut uninstalled_count = 0;
-    let mut failed_count = 0;
-
-    for pkg_name in packages {
-        match installer.uninstall(&pkg_name).await {
+    for plan in plans {
+        if pip_rs_core::utils::cancel::is_requested() {
+            break;
+        }
+        match plan.execute(site, force) {
             Ok(_) => {
-                println!("✓ Successfully uninstalled {}", pkg_name);
+                println!("✓ Successfully uninstalled {}", plan.label());
                 uninstalled_count += 1;
             }
             Err(e) => {
-                eprintln!("✗ Failed to uninstall {}: {}", pkg_name, e);
+                eprintln!("✗ Failed to uninstall {}: {}", plan.name, e);
                 failed_count += 1;
             }
         }
@@ -66,3 +125,10 @@ pub async fn handle_uninstall(packages: Vec<String>, yes: bool) -> Result<i32, P
 
     Ok(0)
 }
+
+/// The per-user site-packages of the detected Python version
+fn user_site_packages() -> anyhow::Result<installer::SitePackages> {
+    let base = installer::scheme::user_base().ok_or_else(|| anyhow::anyhow!("HOME is unset"))?;
+    let version = pip_rs_core::utils::python_env::detected().version();
+    installer::SitePackages::new(installer::scheme::Scheme::for_user(&base, &version, None).purelib)
+}
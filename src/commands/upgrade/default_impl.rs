@@ -1,7 +1,15 @@
 /// Default implementations of upgrade traits
 use super::traits::*;
 use super::detector::{self, InstalledPackage};
+use super::listing::{SIMPLE_JSON_ACCEPT, latest_from_listing, releases_from_listing};
+use crate::installer::sizes::format_size;
+use crate::models::Requirement;
+use crate::resolver::requires_python::Release;
 use std::cmp::Ordering;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering as AtomicOrdering};
+use tokio::sync::Semaphore;
+use std::time::Duration;
 use async_trait::async_trait;
 use anyhow::Result;
 
@@ -15,40 +23,306 @@ impl PackageDetector for DefaultPackageDetector {
     }
     
     fn compare_versions(&self, current: &str, latest: &str) -> Ordering {
-        detector::compare_versions(current, latest)
+        crate::utils::version::compare(current, latest)
+    }
+
+    fn installed_requirements(&self) -> Vec<(String, Requirement)> {
+        crate::installer::SitePackages::default()
+            .map(|site_packages| super::interactive::installed_requirements(site_packages.path()))
+            .unwrap_or_default()
+    }
+}
+
+/// How old a cached listing or JSON document may be and still answer the
+/// scan; anything older is fetched again so new releases show up
+const SCAN_CACHE_MAX_AGE: Duration = Duration::from_secs(300);
+
+/// Index reads through the shared HTTP client and its disk cache
+pub struct HttpTransport;
+
+#[async_trait]
+impl IndexTransport for HttpTransport {
+    async fn get(&self, url: &str, accept: &str) -> Result<Vec<u8>> {
+        Ok(crate::network::global_client().fetch_fresh(url, accept).await?.to_vec())
+    }
+
+    fn cached(&self, url: &str, max_age: Duration) -> Option<Vec<u8>> {
+        crate::network::global_client().http_cache()?.get_within(url, max_age)
+    }
+
+    fn cached_size(&self, url: &str) -> Option<u64> {
+        crate::network::global_client().http_cache()?.entry_size(url)
+    }
+
+    fn store(&self, url: &str, body: &[u8]) {
+        if let Some(cache) = crate::network::global_client().http_cache() {
+            let _ = cache.set(url, body);
+        }
+    }
+
+    fn simple_url(&self, name: &str) -> String {
+        crate::network::global_client().simple_project_url(name)
+    }
+
+    fn json_url(&self, name: &str) -> String {
+        crate::network::global_client().package_json_url(name)
+    }
+}
+
+/// Byte and request counts for one scan
+#[derive(Debug, Default)]
+pub struct TransferStats {
+    pub requests: AtomicU64,
+    pub bytes: AtomicU64,
+    pub cache_hits: AtomicU64,
+    pub fallbacks: AtomicU64,
+    /// Lower bound on what the legacy JSON API would have cost on top
+    pub saved: AtomicU64,
+}
+
+impl TransferStats {
+    fn fetched(&self, bytes: usize) {
+        self.requests.fetch_add(1, AtomicOrdering::Relaxed);
+        self.bytes.fetch_add(bytes as u64, AtomicOrdering::Relaxed);
     }
 }
 
 /// Default metadata fetcher implementation
-pub struct DefaultMetadataFetcher;
+///
+/// Reads the compact PEP 691 listing instead of the legacy JSON document,
+/// which carries every release's files and metadata. Recent cache entries,
+/// including JSON documents stored by resolutions, answer without a
+/// request; the JSON API is only used for indexes whose listing lacks the
+/// PEP 700 version list.
+pub struct DefaultMetadataFetcher<T: IndexTransport = HttpTransport> {
+    transport: T,
+    stats: TransferStats,
+}
+
+impl DefaultMetadataFetcher {
+    pub fn new() -> Self {
+        Self::with_transport(HttpTransport)
+    }
+}
+
+impl Default for DefaultMetadataFetcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: IndexTransport> DefaultMetadataFetcher<T> {
+    pub fn with_transport(transport: T) -> Self {
+        Self {
+            transport,
+            stats: TransferStats::default(),
+        }
+    }
+
+    pub fn stats(&self) -> &TransferStats {
+        &self.stats
+    }
+
+    fn from_json(body: &[u8]) -> Option<String> {
+        let json: serde_json::Value = serde_json::from_slice(body).ok()?;
+        json["info"]["version"].as_str().map(String::from)
+    }
+
+    fn releases_from_json(body: &[u8]) -> Option<Vec<Release>> {
+        let json: serde_json::Value = serde_json::from_slice(body).ok()?;
+        json["releases"].is_object().then(|| crate::network::releases_from_json(&json))
+    }
+
+    /// Read `name`'s listing, or its JSON document when the listing can't
+    /// answer, through the cache, with `from_listing` and `from_json`
+    async fn fetch_with<R>(
+        &self,
+        name: &str,
+        from_listing: impl Fn(&[u8]) -> Option<R> + Send,
+        from_json: impl Fn(&[u8]) -> Option<R> + Send,
+    ) -> Result<R> {
+        let name = crate::utils::names::canonicalize_name(name);
+        let simple_url = self.transport.simple_url(&name);
+        let json_url = self.transport.json_url(&name);
+        let json_size = self.transport.cached_size(&json_url);
+
+        if let Some(body) = self.transport.cached(&simple_url, SCAN_CACHE_MAX_AGE)
+            && let Some(found) = from_listing(&body)
+        {
+            self.stats.cache_hits.fetch_add(1, AtomicOrdering::Relaxed);
+            self.stats.saved.fetch_add(json_size.unwrap_or(body.len() as u64), AtomicOrdering::Relaxed);
+            return Ok(found);
+        }
+        if let Some(body) = self.transport.cached(&json_url, SCAN_CACHE_MAX_AGE)
+            && let Some(found) = from_json(&body)
+        {
+            self.stats.cache_hits.fetch_add(1, AtomicOrdering::Relaxed);
+            self.stats.saved.fetch_add(body.len() as u64, AtomicOrdering::Relaxed);
+            return Ok(found);
+        }
+
+        match self.transport.get(&simple_url, SIMPLE_JSON_ACCEPT).await {
+            Ok(body) => {
+                self.stats.fetched(body.len());
+                if let Some(found) = from_listing(&body) {
+                    self.transport.store(&simple_url, &body);
+                    if let Some(size) = json_size {
+                        self.stats.saved.fetch_add(size.saturating_sub(body.len() as u64), AtomicOrdering::Relaxed);
+                    }
+                    return Ok(found);
+                }
+            }
+            Err(e) => tracing::debug!("Simple API listing for {} unavailable: {}", name, e),
+        }
+
+        let body = self.transport.get(&json_url, "application/json").await?;
+        self.stats.fetched(body.len());
+        self.stats.fallbacks.fetch_add(1, AtomicOrdering::Relaxed);
+        self.transport.store(&json_url, &body);
+        from_json(&body).ok_or_else(|| anyhow::anyhow!("No version found"))
+    }
+}
 
 #[async_trait]
-impl MetadataFetcher for DefaultMetadataFetcher {
+impl<T: IndexTransport> MetadataFetcher for DefaultMetadataFetcher<T> {
     async fn fetch_latest(&self, name: &str) -> Result<String> {
-        // For version checking, we need fresh data from PyPI, not cached
-        // Use get_latest_version which bypasses cache for accurate outdated detection
-        use crate::network::pypi::get_latest_version;
-        get_latest_version(name).await
+        self.fetch_with(name, latest_from_listing, Self::from_json).await
+    }
+
+    async fn fetch_releases(&self, name: &str) -> Result<Vec<Release>> {
+        self.fetch_with(name, releases_from_listing, Self::releases_from_json).await
+    }
+
+    fn transfer_summary(&self) -> Option<String> {
+        let stats = self.stats();
+        let load = |counter: &AtomicU64| counter.load(AtomicOrdering::Relaxed);
+        let requests = load(&stats.requests);
+        Some(format!(
+            "Scan transferred {} in {} request{} ({} answered from cache, {} via the JSON API); saved at least {} over the JSON API",
+            format_size(load(&stats.bytes)),
+            requests,
+            if requests == 1 { "" } else { "s" },
+            load(&stats.cache_hits),
+            load(&stats.fallbacks),
+            format_size(load(&stats.saved)),
+        ))
+    }
+}
+
+/// Installs through pip-rs's own pipeline (`pip install name==version`);
+/// wheels are first downloaded into the wheel cache, several at a time
+#[derive(Default)]
+pub struct NativeBackend {
+    /// Installs share one site-packages, so they run one at a time
+    site_packages: tokio::sync::Mutex<()>,
+}
+
+#[async_trait]
+impl InstallBackend for NativeBackend {
+    async fn install(&self, name: &str, version: &str) -> std::result::Result<(), String> {
+        let _guard = self.site_packages.lock().await;
+        super::installer::install_exact(name, version).await
+    }
+
+    async fn prefetch(&self, name: &str, version: &str) {
+        if let Err(e) = super::installer::prefetch_wheel(name, version).await {
+            tracing::debug!("Could not prefetch {}=={}: {}", name, version, e);
+        }
+    }
+}
+
+/// Default package installer implementation: upgrades each package through
+/// an [`InstallBackend`] so one failure doesn't take the others down, and
+/// stops starting new ones after Ctrl-C
+pub struct DefaultPackageInstaller<B: InstallBackend = NativeBackend> {
+    backend: B,
+    /// Set on Ctrl-C; packages not yet started are then left alone
+    cancelled: Arc<AtomicBool>,
+}
+
+impl DefaultPackageInstaller {
+    pub fn new() -> Self {
+        Self::with_backend(NativeBackend::default())
     }
 }
 
-/// Default package installer implementation
-pub struct DefaultPackageInstaller;
+impl Default for DefaultPackageInstaller {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<B: InstallBackend + 'static> DefaultPackageInstaller<B> {
+    pub fn with_backend(backend: B) -> Self {
+        Self {
+            backend,
+            cancelled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    async fn upgrade_one(&self, name: &str, current: &str, latest: &str) -> UpgradeResult {
+        if self.cancelled.load(AtomicOrdering::SeqCst) {
+            return UpgradeResult::new(name, current, latest, Err("cancelled before it started".to_string()));
+        }
+        UpgradeResult::new(name, current, latest, self.backend.install(name, latest).await)
+    }
+}
 
 #[async_trait]
-impl PackageInstaller for DefaultPackageInstaller {
+impl<B: InstallBackend + 'static> PackageInstaller for DefaultPackageInstaller<B> {
     async fn upgrade(&self, name: &str, current: &str, latest: &str) -> UpgradeResult {
-        // Use fast native upgrade (async)
-        crate::commands::upgrade::installer::upgrade_package_fast(name, current, latest).await
+        self.upgrade_one(name, current, latest).await
     }
-    
+
+    async fn upgrade_parallel(
+        &self,
+        packages: Vec<(String, String, String)>,
+        concurrency: usize,
+    ) -> Vec<UpgradeResult> {
+        let cancelled = self.cancelled.clone();
+        let interrupt = tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                eprintln!("\n  Interrupted: finishing in-flight upgrades, skipping the rest");
+                cancelled.store(true, AtomicOrdering::SeqCst);
+            }
+        });
+
+        // Downloads and installs have separate budgets, so a package waiting
+        // its turn to install doesn't hold up the next download
+        let downloads = Semaphore::new(concurrency.max(1));
+        let installs = Semaphore::new(concurrency.max(1));
+        let results = futures::future::join_all(packages.iter().map(|(name, current, latest)| async {
+            if let Ok(_permit) = downloads.acquire().await
+                && !self.cancelled.load(AtomicOrdering::SeqCst)
+            {
+                self.backend.prefetch(name, latest).await;
+            }
+            let _permit = installs.acquire().await.ok();
+            self.upgrade_one(name, current, latest).await
+        }))
+        .await;
+        interrupt.abort();
+        results
+    }
+}
+
+/// Deprecated: upgrades by running the system `pip install --upgrade`
+/// (`--use-system-pip`, kept for one release)
+pub struct SystemPipInstaller;
+
+#[async_trait]
+impl PackageInstaller for SystemPipInstaller {
+    async fn upgrade(&self, name: &str, current: &str, latest: &str) -> UpgradeResult {
+        super::installer::upgrade_package(name, current, latest)
+    }
+
     async fn upgrade_parallel(
         &self,
         packages: Vec<(String, String, String)>,
         _concurrency: usize,
     ) -> Vec<UpgradeResult> {
         // Try batch upgrade first (fastest), fall back to parallel if needed
-        crate::commands::upgrade::installer::upgrade_packages_batch(packages).await
+        super::installer::upgrade_packages_batch(packages).await
     }
 }
 
@@ -99,14 +373,126 @@ impl ProgressReporter for DefaultProgressReporter {
         }
     }
     
-    fn report_summary(&self, upgraded: usize, failed: usize) {
+    fn report_summary(&self, upgraded: usize, failed: usize, held_back: usize) {
         let separator = "  ".to_string() + &"─".repeat(88);
         println!("\n{}", separator);
-        if failed == 0 {
-            println!("  ✅ Success! {} package{} updated", upgraded, if upgraded == 1 { "" } else { "s" });
-        } else {
-            println!("  ⚠️  Completed with issues: {} updated, {} failed", upgraded, failed);
-        }
+        let icon = if failed == 0 { "✅" } else { "⚠️ " };
+        println!("  {} {} upgraded, {} failed, {} held back", icon, upgraded, failed, held_back);
         println!("{}\n", separator);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Mutex, OnceLock};
+    use std::sync::atomic::AtomicUsize;
+
+    /// In-process backend: records every install, fails the listed names,
+    /// and can raise the cancel flag when it reaches a given package
+    #[derive(Default)]
+    struct MockBackend {
+        failing: Vec<&'static str>,
+        cancel_at: Option<&'static str>,
+        cancel: OnceLock<Arc<AtomicBool>>,
+        calls: Mutex<Vec<String>>,
+        in_flight: AtomicUsize,
+        max_in_flight: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl InstallBackend for MockBackend {
+        async fn install(&self, name: &str, version: &str) -> std::result::Result<(), String> {
+            let now = self.in_flight.fetch_add(1, AtomicOrdering::SeqCst) + 1;
+            self.max_in_flight.fetch_max(now, AtomicOrdering::SeqCst);
+            self.calls.lock().unwrap().push(format!("{}=={}", name, version));
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            if self.cancel_at == Some(name) {
+                self.cancel.get().unwrap().store(true, AtomicOrdering::SeqCst);
+            }
+            self.in_flight.fetch_sub(1, AtomicOrdering::SeqCst);
+            if self.failing.contains(&name) {
+                return Err(format!("No matching distribution found for {}=={}", name, version));
+            }
+            Ok(())
+        }
+    }
+
+    /// Backend that installs one package at a time, as `NativeBackend` does
+    #[derive(Default)]
+    struct SerialBackend {
+        site_packages: tokio::sync::Mutex<()>,
+        prefetching: AtomicUsize,
+        max_prefetching: AtomicUsize,
+        prefetched: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl InstallBackend for SerialBackend {
+        async fn install(&self, name: &str, version: &str) -> std::result::Result<(), String> {
+            let _guard = self.site_packages.lock().await;
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            if !self.prefetched.lock().unwrap().contains(&format!("{}=={}", name, version)) {
+                return Err(format!("{} was not prefetched", name));
+            }
+            Ok(())
+        }
+
+        async fn prefetch(&self, name: &str, version: &str) {
+            let now = self.prefetching.fetch_add(1, AtomicOrdering::SeqCst) + 1;
+            self.max_prefetching.fetch_max(now, AtomicOrdering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            self.prefetched.lock().unwrap().push(format!("{}=={}", name, version));
+            self.prefetching.fetch_sub(1, AtomicOrdering::SeqCst);
+        }
+    }
+
+    fn outdated(names: &[&str]) -> Vec<(String, String, String)> {
+        names.iter().map(|n| (n.to_string(), "1.0".to_string(), "2.0".to_string())).collect()
+    }
+
+    #[tokio::test]
+    async fn test_failures_are_isolated_per_package() {
+        let installer = DefaultPackageInstaller::with_backend(MockBackend {
+            failing: vec!["beta"],
+            ..MockBackend::default()
+        });
+        let results = installer.upgrade_parallel(outdated(&["alpha", "beta", "gamma", "delta"]), 2).await;
+
+        let outcomes: Vec<(&str, bool)> = results.iter().map(|r| (r.name.as_str(), r.success)).collect();
+        assert_eq!(outcomes, [("alpha", true), ("beta", false), ("gamma", true), ("delta", true)]);
+        assert_eq!(results[1].error_msg.as_deref(), Some("No matching distribution found for beta==2.0"));
+        assert!(results.iter().filter(|r| r.success).all(|r| r.error_msg.is_none()));
+
+        // Every package went through the in-process backend, within the budget
+        let mut calls = installer.backend.calls.lock().unwrap().clone();
+        calls.sort();
+        assert_eq!(calls, ["alpha==2.0", "beta==2.0", "delta==2.0", "gamma==2.0"]);
+        assert!(installer.backend.max_in_flight.load(AtomicOrdering::SeqCst) <= 2);
+    }
+
+    #[tokio::test]
+    async fn test_downloads_overlap_while_installs_take_turns() {
+        let installer = DefaultPackageInstaller::with_backend(SerialBackend::default());
+        let results = installer.upgrade_parallel(outdated(&["alpha", "beta", "gamma", "delta"]), 3).await;
+
+        assert!(results.iter().all(|r| r.success), "{:?}", results);
+        let max_prefetching = installer.backend.max_prefetching.load(AtomicOrdering::SeqCst);
+        assert!((2..=3).contains(&max_prefetching), "{} prefetches at once", max_prefetching);
+    }
+
+    #[tokio::test]
+    async fn test_cancellation_skips_packages_not_started() {
+        let installer = DefaultPackageInstaller::with_backend(MockBackend {
+            cancel_at: Some("beta"),
+            ..MockBackend::default()
+        });
+        installer.backend.cancel.set(installer.cancelled.clone()).unwrap();
+        let results = installer.upgrade_parallel(outdated(&["alpha", "beta", "gamma", "delta"]), 1).await;
+
+        let outcomes: Vec<(&str, bool)> = results.iter().map(|r| (r.name.as_str(), r.success)).collect();
+        assert_eq!(outcomes, [("alpha", true), ("beta", true), ("gamma", false), ("delta", false)]);
+        assert_eq!(results[2].error_msg.as_deref(), Some("cancelled before it started"));
+        assert_eq!(*installer.backend.calls.lock().unwrap(), ["alpha==2.0", "beta==2.0"]);
+    }
+}
@@ -1,10 +1,15 @@
 /// Upgrade command handler with dependency injection
 use super::traits::*;
+use super::detector::InstalledPackage;
+use super::interactive;
+use crate::models::{Environment, Marker, Requirement, VersionSpec};
+use crate::resolver::requires_python::{Release, select_release};
+use crate::utils::names::canonicalize_name;
 use anyhow::Result;
 use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::{Semaphore, mpsc};
-use futures::future::join_all;
 
 /// Upgrade handler with injectable dependencies
 pub struct UpgradeHandler<D, M, I, P>
@@ -19,6 +24,8 @@ where
     installer: Arc<I>,
     reporter: Arc<P>,
     config: UpgradeConfig,
+    /// Interpreter version target releases' Requires-Python must admit
+    python: String,
 }
 
 impl<D, M, I, P> UpgradeHandler<D, M, I, P>
@@ -42,14 +49,13 @@ where
             installer: Arc::new(installer),
             reporter: Arc::new(reporter),
             config,
+            python: Environment::current().target_python().to_string(),
         }
     }
 
     /// Execute upgrade for all outdated packages
     pub async fn upgrade_all(&self) -> Result<i32> {
-        println!("╔════════════════════════════════════════════════════════════════╗");
-        println!("║           pip-rs Package Update Tool                           ║");
-        println!("╚════════════════════════════════════════════════════════════════╝\n");
+        print_banner();
 
         // Get installed packages
         let packages = self.detector.get_installed().await?;
@@ -63,96 +69,118 @@ where
         let mut packages = packages;
         packages.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
 
+        let packages = self.skip_excluded(packages);
+        if packages.is_empty() {
+            println!("✗ Every installed package is excluded. Nothing to do.");
+            return Ok(0);
+        }
         println!("📦 Scanning {} installed packages for updates...\n", packages.len());
+        self.scan_and_upgrade(packages, "All packages are up-to-date!").await
+    }
 
-        // Create channel for real-time result streaming
-        let (tx, mut rx) = mpsc::channel(100);
-        let total_packages = packages.len();
+    /// Execute upgrade for specific packages
+    pub async fn upgrade_packages(&self, packages_to_upgrade: Vec<String>) -> Result<i32> {
+        print_banner();
 
-        // Spawn task to fetch packages
-        let packages_clone = packages.clone();
-        let fetcher = self.fetcher.clone();
-        let detector = self.detector.clone();
-
-        let scan_task = tokio::spawn(async move {
-            // Reduced to 15 to avoid PyPI rate limiting while still being fast
-            let semaphore = Arc::new(Semaphore::new(15));
-            let mut handles = vec![];
-
-            // Spawn all tasks at once for real-time streaming
-            for pkg in packages_clone.iter() {
-                let semaphore_clone = semaphore.clone();
-                let tx_clone = tx.clone();
-                let name = pkg.name.clone();
-                let version = pkg.version.clone();
-                let fetcher_clone = fetcher.clone();
-                let detector_clone = detector.clone();
-
-                let handle = tokio::spawn(async move {
-                    let _permit = semaphore_clone.acquire().await.ok();
-                    match fetcher_clone.fetch_latest(&name).await {
-                        Ok(latest) => {
-                            let is_outdated =
-                                detector_clone.compare_versions(&version, &latest) == Ordering::Less;
-                            let _ = tx_clone.send((name, version, latest, is_outdated)).await;
-                        }
-                        Err(e) => {
-                            // Log error in debug mode only, don't spam stderr
-                            tracing::debug!("Failed to fetch latest version for {}: {}", name, e);
-                            // Send a dummy message to indicate task completed
-                            let _ = tx_clone.send((String::new(), String::new(), String::new(), false)).await;
-                        }
-                    }
-                });
-                handles.push(handle);
-            }
+        // Get installed packages
+        let installed_packages = self.detector.get_installed().await?;
 
-            // Wait for all tasks to complete
-            let _ = join_all(handles).await;
-        });
+        if installed_packages.is_empty() {
+            println!("✗ No packages found in site-packages");
+            return Ok(0);
+        }
 
-        // Collect all outdated packages first
-        let mut checked_count = 0;
-        let mut outdated_packages = Vec::new();
-        
-        while let Some((name, version, latest, is_outdated)) = rx.recv().await {
-            // Skip empty messages from failed requests
-            if name.is_empty() {
-                checked_count += 1;
-                continue;
-            }
-            
-            checked_count += 1;
-            
-            if is_outdated {
-                outdated_packages.push((name, version, latest));
-            } else {
-                self.reporter
-                    .report_scanning(checked_count, total_packages, &name, false);
-            }
+        // Filter to only packages requested (normalize names for comparison)
+        let packages: Vec<_> = installed_packages
+            .into_iter()
+            .filter(|p| {
+                let normalized_name = canonicalize_name(&p.name);
+                packages_to_upgrade.iter().any(|req| canonicalize_name(req) == normalized_name)
+            })
+            .collect();
 
-            // Break if all packages checked
-            if checked_count >= total_packages {
-                break;
-            }
+        if packages.is_empty() {
+            println!("✗ None of the requested packages are installed. Nothing to do.");
+            return Ok(0);
         }
 
-        // Ensure the scan task completes
-        let _ = scan_task.await;
+        let packages = self.skip_excluded(packages);
+        if packages.is_empty() {
+            println!("✗ Every requested package is excluded. Nothing to do.");
+            return Ok(0);
+        }
+        println!("📦 Scanning {} requested packages for updates...\n", packages.len());
+        self.scan_and_upgrade(packages, "All requested packages are up-to-date!").await
+    }
+
+    /// Drop the packages `config.exclude` names, reporting each as skipped
+    fn skip_excluded(&self, packages: Vec<InstalledPackage>) -> Vec<InstalledPackage> {
+        let excluded: Vec<String> = self.config.exclude.iter().map(|name| canonicalize_name(name)).collect();
+        let (skipped, kept): (Vec<_>, Vec<_>) =
+            packages.into_iter().partition(|pkg| excluded.contains(&canonicalize_name(&pkg.name)));
+        for pkg in &skipped {
+            println!("  ⏭ {} {} skipped (excluded)", pkg.name, pkg.version);
+        }
+        if !skipped.is_empty() {
+            println!();
+        }
+        kept
+    }
+
+    /// Scan packages for updates and upgrade the outdated ones
+    async fn scan_and_upgrade(&self, packages: Vec<InstalledPackage>, up_to_date_msg: &str) -> Result<i32> {
+        let constraints = self.load_constraints()?;
+        let mut scan = self.scan(&packages, &constraints).await;
+        // The picker shows these holds itself and lets the user force them
+        if !self.config.interactive && !self.config.force {
+            scan.hold_for_dependents(&self.detector.installed_requirements());
+        }
 
         eprintln!("\r{}", " ".repeat(100));
 
-        if outdated_packages.is_empty() {
-            println!("\n  ✓ All packages are up-to-date!\n");
+        if let Some(summary) = scan.failure_summary() {
+            println!("  ⚠ {}", summary);
+        }
+        if let Some(summary) = scan.held_back_summary() {
+            println!("  ⏸ {}", summary);
+        }
+        if self.config.verbose
+            && let Some(transfer) = self.fetcher.transfer_summary()
+        {
+            println!("  {}", transfer);
+        }
+
+        if scan.outdated.is_empty() {
+            if scan.held_back.is_empty() {
+                println!("\n  ✓ {}\n", up_to_date_msg);
+            } else {
+                println!("\n  ✓ Nothing to upgrade; {} held back\n", scan.held_back.len());
+            }
             return Ok(0);
         }
 
         // Display outdated packages found
-        self.reporter.report_scan_complete(packages.len(), outdated_packages.len());
+        self.reporter.report_scan_complete(packages.len(), scan.outdated.len());
+
+        let outdated = if self.config.interactive {
+            match self.pick(&scan.outdated)? {
+                Some(selected) => selected,
+                None => {
+                    println!("  Aborted; nothing was upgraded.\n");
+                    return Ok(0);
+                }
+            }
+        } else {
+            scan.outdated
+        };
+        if outdated.is_empty() {
+            println!("  Nothing selected; nothing was upgraded.\n");
+            return Ok(0);
+        }
 
         // Fast batch upgrade - installs all packages in one command for maximum speed
-        println!("  ⚡ Upgrading {} packages using fast batch installation...\n", outdated_packages.len());
-        let results = self.installer.upgrade_parallel(outdated_packages, self.config.concurrency).await;
+        println!("  ⚡ Upgrading {} packages using fast batch installation...\n", outdated.len());
+        let results = self.installer.upgrade_parallel(outdated, self.config.concurrency).await;
         
         // Display results with better formatting
         let (upgraded_count, failed_count) = results.iter().fold((0, 0), |(up, fail), result| {
@@ -176,164 +204,283 @@ where
             }
         });
 
-        self.reporter.report_summary(upgraded_count, failed_count);
+        self.reporter.report_summary(upgraded_count, failed_count, scan.held_back.len());
         Ok(upgraded_count as i32)
     }
 
-    /// Execute upgrade for specific packages
-    pub async fn upgrade_packages(&self, packages_to_upgrade: Vec<String>) -> Result<i32> {
-        println!("╔════════════════════════════════════════════════════════════════╗");
-        println!("║           pip-rs Package Update Tool                           ║");
-        println!("╚════════════════════════════════════════════════════════════════╝\n");
-
-        // Get installed packages
-        let installed_packages = self.detector.get_installed().await?;
-
-        if installed_packages.is_empty() {
-            println!("✗ No packages found in site-packages");
-            return Ok(0);
+    /// Version specifiers of `config.constraints` by normalized name,
+    /// leaving out lines whose marker doesn't apply here
+    fn load_constraints(&self) -> Result<HashMap<String, Vec<VersionSpec>>> {
+        let mut constraints: HashMap<String, Vec<VersionSpec>> = HashMap::new();
+        let Some(path) = &self.config.constraints else {
+            return Ok(constraints);
+        };
+        let environment = Environment::current();
+        for constraint in crate::commands::install::load_constraint_files(std::slice::from_ref(path), false)? {
+            let applies = constraint
+                .marker
+                .as_deref()
+                .is_none_or(|marker| Marker::parse(marker).is_ok_and(|marker| marker.evaluate(&environment)));
+            if applies {
+                constraints.entry(canonicalize_name(&constraint.name)).or_default().extend(constraint.specs);
+            }
         }
+        Ok(constraints)
+    }
 
-        // Filter to only packages requested (normalize names for comparison)
-        let packages: Vec<_> = installed_packages
-            .into_iter()
-            .filter(|p| {
-                let normalized_name = p.name.to_lowercase().replace('_', "-");
-                packages_to_upgrade.iter().any(|req| {
-                    let normalized_req = req.to_lowercase().replace('_', "-");
-                    normalized_name == normalized_req
-                })
-            })
-            .collect();
-
-        if packages.is_empty() {
-            println!("✗ None of the requested packages are installed. Nothing to do.");
-            return Ok(0);
-        }
-        
-        println!("📦 Scanning {} requested packages for updates...\n", packages.len());
+    /// Let the user choose from the outdated packages; `None` if aborted
+    fn pick(&self, outdated: &[(String, String, String)]) -> Result<Option<Vec<(String, String, String)>>> {
+        let candidates = interactive::candidates(outdated, &self.detector.installed_requirements());
+        Ok(match interactive::run(candidates)? {
+            interactive::Outcome::Confirmed(selected) => Some(
+                selected
+                    .into_iter()
+                    .map(|c| (c.name, c.current, c.latest))
+                    .collect(),
+            ),
+            interactive::Outcome::Aborted => None,
+        })
+    }
 
-        // Create channel for real-time result streaming
+    /// Check every package for a newer version: the newest release that
+    /// meets the package's `constraints` and whose Requires-Python admits
+    /// the interpreter.
+    ///
+    /// Each package produces exactly one `ScanMessage`. The receiving loop ends
+    /// when the channel closes (all senders dropped, including tasks that
+    /// panicked), and a watchdog bounds the whole scan; packages that never
+    /// reported are listed as failures rather than silently dropped.
+    pub async fn scan(
+        &self,
+        packages: &[InstalledPackage],
+        constraints: &HashMap<String, Vec<VersionSpec>>,
+    ) -> ScanOutcome {
         let (tx, mut rx) = mpsc::channel(100);
         let total_packages = packages.len();
+        let semaphore = Arc::new(Semaphore::new(self.config.concurrency.max(1)));
+        let mut handles = Vec::with_capacity(total_packages);
 
-        // Spawn task to fetch packages
-        let packages_clone = packages.clone();
-        let fetcher = self.fetcher.clone();
-        let detector = self.detector.clone();
-
-        let scan_task = tokio::spawn(async move {
-            // Reduced to 15 to avoid PyPI rate limiting while still being fast
-            let semaphore = Arc::new(Semaphore::new(15));
-            let mut handles = vec![];
-
-            // Spawn all tasks at once for real-time streaming
-            for pkg in packages_clone.iter() {
-                let semaphore_clone = semaphore.clone();
-                let tx_clone = tx.clone();
-                let name = pkg.name.clone();
-                let version = pkg.version.clone();
-                let fetcher_clone = fetcher.clone();
-                let detector_clone = detector.clone();
-
-                let handle = tokio::spawn(async move {
-                    let _permit = semaphore_clone.acquire().await.ok();
-                    match fetcher_clone.fetch_latest(&name).await {
-                        Ok(latest) => {
-                            let is_outdated =
-                                detector_clone.compare_versions(&version, &latest) == Ordering::Less;
-                            let _ = tx_clone.send((name, version, latest, is_outdated)).await;
-                        }
-                        Err(e) => {
-                            // Log error in debug mode only, don't spam stderr
-                            tracing::debug!("Failed to fetch latest version for {}: {}", name, e);
-                            // Send a dummy message to indicate task completed
-                            let _ = tx_clone.send((String::new(), String::new(), String::new(), false)).await;
-                        }
-                    }
-                });
-                handles.push(handle);
-            }
+        // Spawn all tasks at once for real-time streaming
+        for pkg in packages {
+            let semaphore = semaphore.clone();
+            let tx = tx.clone();
+            let name = pkg.name.clone();
+            let version = pkg.version.clone();
+            let fetcher = self.fetcher.clone();
+            let detector = self.detector.clone();
+            let specs = constraints.get(&canonicalize_name(&name)).cloned().unwrap_or_default();
+            let python = self.python.clone();
+            let pre = self.config.pre;
 
-            // Wait for all tasks to complete
-            let _ = join_all(handles).await;
-        });
+            handles.push(tokio::spawn(async move {
+                let _permit = semaphore.acquire().await.ok();
+                let message = match fetcher.fetch_releases(&name).await {
+                    Ok(releases) => check(name, version, &releases, &specs, &python, pre, |current, candidate| {
+                        detector.compare_versions(current, candidate)
+                    }),
+                    Err(e) => {
+                        // Log error in debug mode only, don't spam stderr
+                        tracing::debug!("Failed to fetch latest version for {}: {}", name, e);
+                        ScanMessage::Failed { name, error: e.to_string() }
+                    }
+                };
+                let _ = tx.send(message).await;
+            }));
+        }
+        // Only the tasks hold senders now, so the channel closes when they finish
+        drop(tx);
 
-        // Collect all outdated packages first
+        let mut outcome = ScanOutcome::default();
+        let mut reported = std::collections::HashSet::new();
         let mut checked_count = 0;
-        let mut outdated_packages = Vec::new();
-        
-        while let Some((name, version, latest, is_outdated)) = rx.recv().await {
-            // Skip empty messages from failed requests
-            if name.is_empty() {
+
+        let receive = async {
+            while let Some(message) = rx.recv().await {
                 checked_count += 1;
-                continue;
+                match message {
+                    ScanMessage::Checked { name, current_version, latest_version, is_outdated } => {
+                        reported.insert(name.clone());
+                        if is_outdated {
+                            outcome.outdated.push((name, current_version, latest_version));
+                        } else {
+                            self.reporter.report_scanning(checked_count, total_packages, &name, false);
+                        }
+                    }
+                    ScanMessage::HeldBack { name, current_version, latest_version, reason } => {
+                        reported.insert(name.clone());
+                        self.reporter.report_scanning(checked_count, total_packages, &name, false);
+                        outcome.held_back.push(HeldBack { name, current_version, latest_version, reason });
+                    }
+                    ScanMessage::Failed { name, error } => {
+                        reported.insert(name.clone());
+                        outcome.failed.push((name, error));
+                    }
+                }
             }
-            
-            checked_count += 1;
-            
-            if is_outdated {
-                outdated_packages.push((name, version, latest));
-            } else {
-                self.reporter
-                    .report_scanning(checked_count, total_packages, &name, false);
+        };
+
+        if tokio::time::timeout(self.config.scan_timeout, receive).await.is_err() {
+            crate::utils::warnings::warn_user(
+                crate::utils::warnings::WarningCode::ScanTimeout,
+                format!("Update scan timed out after {:?}", self.config.scan_timeout),
+            );
+            for handle in &handles {
+                handle.abort();
             }
+        }
 
-            // Break if all packages checked
-            if checked_count >= total_packages {
-                break;
+        // Anything that never reported (timed out or panicked) could not be checked
+        for pkg in packages {
+            if !reported.contains(&pkg.name) {
+                outcome.failed.push((pkg.name.clone(), "no result before the scan ended".to_string()));
             }
         }
 
-        // Ensure the scan task completes
-        let _ = scan_task.await;
+        // Keep output stable regardless of completion order
+        outcome.outdated.sort_by_key(|(name, _, _)| name.to_lowercase());
+        outcome.failed.sort_by_key(|(name, _)| name.to_lowercase());
+        outcome.held_back.sort_by_key(|held| held.name.to_lowercase());
+        outcome
+    }
+}
 
-        eprintln!("\r{}", " ".repeat(100));
+/// The scan's verdict on `name` at `current` given its releases: outdated
+/// when a newer release meets `specs` and supports `python`, held back when
+/// only releases that don't are newer. Pre-releases count only with `pre`,
+/// when a specifier names one, or when nothing else was published.
+fn check(
+    name: String,
+    current: String,
+    releases: &[Release],
+    specs: &[VersionSpec],
+    python: &str,
+    pre: bool,
+    compare: impl Fn(&str, &str) -> Ordering,
+) -> ScanMessage {
+    if releases.is_empty() {
+        return ScanMessage::Failed { name, error: "No version found".to_string() };
+    }
+    let allowed: Vec<Release> = releases
+        .iter()
+        .filter(|release| crate::utils::version::satisfies_all(&release.version, specs))
+        .cloned()
+        .collect();
+    let allow_prereleases = pre || specs.iter().any(|spec| crate::utils::version::is_prerelease(&spec.version));
+    let target = select_release(&allowed, python, allow_prereleases);
+    // What an unconstrained upgrade on any interpreter would pick
+    let any_python: Vec<Release> =
+        releases.iter().map(|release| Release { requires_python: None, ..release.clone() }).collect();
+    let latest = select_release(&any_python, python, pre);
 
-        if outdated_packages.is_empty() {
-            println!("\n  ✓ All requested packages are up-to-date!\n");
-            return Ok(0);
-        }
+    if let Some(target) = target
+        && compare(&current, &target.version) == Ordering::Less
+    {
+        return ScanMessage::Checked {
+            name,
+            current_version: current,
+            latest_version: target.version.clone(),
+            is_outdated: true,
+        };
+    }
+    if let Some(latest) = latest
+        && compare(&current, &latest.version) == Ordering::Less
+    {
+        let reason = if crate::utils::version::satisfies_all(&latest.version, specs) {
+            let requires_python = releases
+                .iter()
+                .find(|release| release.version == latest.version)
+                .and_then(|release| release.requires_python.clone())
+                .unwrap_or_default();
+            format!("requires Python {}", requires_python)
+        } else {
+            let specs: Vec<String> = specs.iter().map(|spec| spec.to_string()).collect();
+            format!("constrained to {}", specs.join(","))
+        };
+        return ScanMessage::HeldBack { name, current_version: current, latest_version: latest.version.clone(), reason };
+    }
+    let latest_version = target.or(latest).map_or_else(|| current.clone(), |release| release.version.clone());
+    ScanMessage::Checked { name, current_version: current, latest_version, is_outdated: false }
+}
 
-        // Display outdated packages found
-        self.reporter.report_scan_complete(packages.len(), outdated_packages.len());
+/// A package with a newer release that the constraints, the interpreter or
+/// an installed dependent rule out
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeldBack {
+    pub name: String,
+    pub current_version: String,
+    pub latest_version: String,
+    /// e.g. "constrained to <5" or "pandas 2.0.3 requires numpy<2"
+    pub reason: String,
+}
 
-        // Fast batch upgrade - installs all packages in one command for maximum speed
-        println!("  ⚡ Upgrading {} packages using fast batch installation...\n", outdated_packages.len());
-        let results = self.installer.upgrade_parallel(outdated_packages, self.config.concurrency).await;
-        
-        // Display results with better formatting
-        let (upgraded_count, failed_count) = results.iter().fold((0, 0), |(up, fail), result| {
-            let status_icon = if result.success { "✅" } else { "❌" };
-            let status_text = if result.success { "UPGRADED" } else { "FAILED" };
-            
-            // Truncate package name if too long
-            let pkg_name = if result.name.len() > 45 {
-                format!("{}...", &result.name[..42])
-            } else {
-                result.name.clone()
-            };
-            
-            println!("  {status_icon} {:<45} {:<15} {:<15} {}", 
-                pkg_name, result.current_version, result.latest_version, status_text);
-            
-            if result.success {
-                (up + 1, fail)
-            } else {
-                (up, fail + 1)
+/// Result of scanning installed packages for updates
+#[derive(Debug, Default)]
+pub struct ScanOutcome {
+    /// (name, current, latest) for packages with a newer release
+    pub outdated: Vec<(String, String, String)>,
+    /// (name, error) for packages whose latest version couldn't be determined
+    pub failed: Vec<(String, String)>,
+    /// Packages kept below their latest release
+    pub held_back: Vec<HeldBack>,
+}
+
+impl ScanOutcome {
+    /// Summary line for packages that couldn't be checked, if any
+    pub fn failure_summary(&self) -> Option<String> {
+        if self.failed.is_empty() {
+            return None;
+        }
+        let names: Vec<&str> = self.failed.iter().map(|(name, _)| name.as_str()).collect();
+        let noun = if names.len() == 1 { "package" } else { "packages" };
+        Some(format!("{} {} could not be checked: {}", names.len(), noun, names.join(", ")))
+    }
+
+    /// Move upgrades that would break an installed dependent to `held_back`
+    pub fn hold_for_dependents(&mut self, requirements: &[(String, Requirement)]) {
+        let mut kept = Vec::new();
+        for (name, current, latest) in self.outdated.drain(..) {
+            match interactive::held_by(&name, &latest, requirements) {
+                Some(reason) => self.held_back.push(HeldBack {
+                    name,
+                    current_version: current,
+                    latest_version: latest,
+                    reason,
+                }),
+                None => kept.push((name, current, latest)),
             }
-        });
+        }
+        self.outdated = kept;
+        self.held_back.sort_by_key(|held| held.name.to_lowercase());
+    }
 
-        self.reporter.report_summary(upgraded_count, failed_count);
-        Ok(upgraded_count as i32)
+    /// Summary line for packages held back below their latest release, if any
+    pub fn held_back_summary(&self) -> Option<String> {
+        if self.held_back.is_empty() {
+            return None;
+        }
+        let entries: Vec<String> = self
+            .held_back
+            .iter()
+            .map(|held| {
+                format!("{} {} ({} available, {})", held.name, held.current_version, held.latest_version, held.reason)
+            })
+            .collect();
+        let noun = if entries.len() == 1 { "package" } else { "packages" };
+        Some(format!("{} {} held back: {}", entries.len(), noun, entries.join(", ")))
     }
 }
 
+fn print_banner() {
+    println!("╔════════════════════════════════════════════════════════════════╗");
+    println!("║           pip-rs Package Update Tool                           ║");
+    println!("╚════════════════════════════════════════════════════════════════╝\n");
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::commands::upgrade::detector::InstalledPackage;
     use std::sync::Mutex;
+    use std::time::Duration;
 
     /// Mock detector for testing
     struct MockDetector {
@@ -411,7 +558,7 @@ mod tests {
 
         fn report_scan_complete(&self, _total: usize, _outdated_count: usize) {}
 
-        fn report_summary(&self, _upgraded: usize, _failed: usize) {}
+        fn report_summary(&self, _upgraded: usize, _failed: usize, _held_back: usize) {}
     }
 
     #[tokio::test]
@@ -437,4 +584,443 @@ mod tests {
 
         assert_eq!(handler.config.concurrency, 15);
     }
+
+    /// Fetcher that fails for some names and answers others after a delay
+    struct FlakyFetcher {
+        failing: Vec<&'static str>,
+        delays_ms: std::collections::HashMap<&'static str, u64>,
+    }
+
+    #[async_trait::async_trait]
+    impl MetadataFetcher for FlakyFetcher {
+        async fn fetch_latest(&self, name: &str) -> Result<String> {
+            if let Some(delay) = self.delays_ms.get(name) {
+                tokio::time::sleep(Duration::from_millis(*delay)).await;
+            }
+            if self.failing.contains(&name) {
+                anyhow::bail!("HTTP 503 for {}", name);
+            }
+            Ok("2.0.0".to_string())
+        }
+    }
+
+    fn flaky_handler(
+        packages: &[&str],
+        fetcher: FlakyFetcher,
+        config: UpgradeConfig,
+    ) -> UpgradeHandler<MockDetector, FlakyFetcher, MockInstaller, MockReporter> {
+        let detector = MockDetector {
+            packages: packages
+                .iter()
+                .map(|name| InstalledPackage {
+                    name: name.to_string(),
+                    version: "1.0.0".to_string(),
+                })
+                .collect(),
+        };
+        let reporter = MockReporter {
+            results: Mutex::new(Vec::new()),
+        };
+        UpgradeHandler::new(detector, fetcher, MockInstaller, reporter, config)
+    }
+
+    #[tokio::test]
+    async fn test_scan_reports_every_package_despite_failures_and_delays() {
+        let names = ["alpha", "beta", "gamma", "delta", "epsilon", "zeta"];
+        let fetcher = FlakyFetcher {
+            // Failures finish first, so they race ahead of the slow successes
+            failing: vec!["beta", "delta", "zeta"],
+            delays_ms: [("alpha", 80), ("gamma", 40), ("epsilon", 120)].into_iter().collect(),
+        };
+        let handler = flaky_handler(&names, fetcher, UpgradeConfig::default());
+
+        let packages = handler.detector.get_installed().await.unwrap();
+        let outcome = handler.scan(&packages, &HashMap::new()).await;
+
+        let outdated: Vec<&str> = outcome.outdated.iter().map(|(n, _, _)| n.as_str()).collect();
+        assert_eq!(outdated, vec!["alpha", "epsilon", "gamma"]);
+        let failed: Vec<&str> = outcome.failed.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(failed, vec!["beta", "delta", "zeta"]);
+        assert_eq!(
+            outcome.failure_summary().unwrap(),
+            "3 packages could not be checked: beta, delta, zeta"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_scan_watchdog_stops_hung_fetches() {
+        let fetcher = FlakyFetcher {
+            failing: vec![],
+            delays_ms: [("stuck", 60_000)].into_iter().collect(),
+        };
+        let config = UpgradeConfig {
+            scan_timeout: Duration::from_millis(200),
+            ..UpgradeConfig::default()
+        };
+        let handler = flaky_handler(&["fast", "stuck"], fetcher, config);
+
+        let packages = handler.detector.get_installed().await.unwrap();
+        let started = std::time::Instant::now();
+        let outcome = handler.scan(&packages, &HashMap::new()).await;
+
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(outcome.outdated.len(), 1);
+        assert_eq!(outcome.outdated[0].0, "fast");
+        assert_eq!(
+            outcome.failure_summary().unwrap(),
+            "1 package could not be checked: stuck"
+        );
+    }
+
+    /// Fetcher serving fixed release lists
+    struct ReleaseFetcher(HashMap<&'static str, Vec<Release>>);
+
+    #[async_trait::async_trait]
+    impl MetadataFetcher for ReleaseFetcher {
+        async fn fetch_latest(&self, _name: &str) -> Result<String> {
+            unreachable!("the scan reads release lists")
+        }
+
+        async fn fetch_releases(&self, name: &str) -> Result<Vec<Release>> {
+            Ok(self.0.get(name).cloned().unwrap_or_default())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scan_honors_constraints_and_requires_python() {
+        let release = |version: &str, requires_python: Option<&str>| Release {
+            version: version.to_string(),
+            requires_python: requires_python.map(String::from),
+            yanked: false,
+            yanked_reason: None,
+        };
+        let fetcher = ReleaseFetcher(
+            [
+                ("django", vec![release("4.1", None), release("4.2", None), release("5.0", None)]),
+                ("flask", vec![release("2.0", None), release("3.0", None)]),
+                ("numpy", vec![release("1.0", None), release("2.0", Some(">=99"))]),
+                ("requests", vec![release("1.0", None), release("2.0", None)]),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        let temp = tempfile::tempdir().unwrap();
+        let constraints = temp.path().join("constraints.txt");
+        std::fs::write(&constraints, "Django<5\nflask<3\nrequests<2 ; python_version < '1'\n").unwrap();
+        let config = UpgradeConfig { constraints: Some(constraints), ..UpgradeConfig::default() };
+        let detector = MockDetector {
+            packages: [("django", "4.1"), ("flask", "2.0"), ("numpy", "1.0"), ("requests", "1.0")]
+                .into_iter()
+                .map(|(name, version)| InstalledPackage { name: name.to_string(), version: version.to_string() })
+                .collect(),
+        };
+        let reporter = MockReporter { results: Mutex::new(Vec::new()) };
+        let handler = UpgradeHandler::new(detector, fetcher, MockInstaller, reporter, config);
+
+        let packages = handler.detector.get_installed().await.unwrap();
+        let outcome = handler.scan(&packages, &handler.load_constraints().unwrap()).await;
+        assert!(outcome.failed.is_empty());
+        assert_eq!(
+            outcome.outdated,
+            [("django".into(), "4.1".into(), "4.2".into()), ("requests".into(), "1.0".into(), "2.0".into())]
+        );
+        let held_back: Vec<(&str, &str, &str)> = outcome
+            .held_back
+            .iter()
+            .map(|held| (held.name.as_str(), held.latest_version.as_str(), held.reason.as_str()))
+            .collect();
+        assert_eq!(held_back, [("flask", "3.0", "constrained to <3"), ("numpy", "2.0", "requires Python >=99")]);
+        assert_eq!(
+            outcome.held_back_summary().unwrap(),
+            "2 packages held back: flask 2.0 (3.0 available, constrained to <3), \
+             numpy 1.0 (2.0 available, requires Python >=99)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_release_candidates_need_pre() {
+        let releases = ["1.0", "1.1", "2.0rc1"].map(|version| Release {
+            version: version.to_string(),
+            requires_python: None,
+            yanked: false,
+            yanked_reason: None,
+        });
+        let scan = |pre: bool| {
+            let fetcher = ReleaseFetcher([("demo", releases.to_vec())].into_iter().collect());
+            let detector = MockDetector {
+                packages: vec![InstalledPackage { name: "demo".to_string(), version: "1.1".to_string() }],
+            };
+            let reporter = MockReporter { results: Mutex::new(Vec::new()) };
+            let config = UpgradeConfig { pre, ..UpgradeConfig::default() };
+            async move {
+                let handler = UpgradeHandler::new(detector, fetcher, MockInstaller, reporter, config);
+                let packages = handler.detector.get_installed().await.unwrap();
+                handler.scan(&packages, &HashMap::new()).await
+            }
+        };
+
+        let outcome = scan(false).await;
+        assert!(outcome.outdated.is_empty() && outcome.held_back.is_empty() && outcome.failed.is_empty());
+        let outcome = scan(true).await;
+        assert_eq!(outcome.outdated, [("demo".into(), "1.1".into(), "2.0rc1".into())]);
+    }
+
+    #[tokio::test]
+    async fn test_excluded_packages_are_skipped() {
+        let handler = flaky_handler(
+            &["protobuf", "requests", "urllib3"],
+            FlakyFetcher { failing: vec![], delays_ms: HashMap::new() },
+            UpgradeConfig { exclude: vec!["ProtoBuf".to_string(), "URLLIB3".to_string()], ..UpgradeConfig::default() },
+        );
+        assert_eq!(handler.upgrade_all().await.unwrap(), 1);
+        assert_eq!(handler.upgrade_packages(vec!["protobuf".to_string()]).await.unwrap(), 0);
+    }
+
+    /// Detector for an environment where pandas pins numpy below 2
+    struct PinnedDetector;
+
+    #[async_trait::async_trait]
+    impl PackageDetector for PinnedDetector {
+        async fn get_installed(&self) -> Result<Vec<InstalledPackage>> {
+            Ok([("numpy", "1.26.4"), ("pandas", "2.0.3"), ("requests", "2.31.0")]
+                .into_iter()
+                .map(|(name, version)| InstalledPackage { name: name.to_string(), version: version.to_string() })
+                .collect())
+        }
+
+        fn compare_versions(&self, current: &str, latest: &str) -> Ordering {
+            crate::utils::version::compare(current, latest)
+        }
+
+        fn installed_requirements(&self) -> Vec<(String, Requirement)> {
+            vec![("pandas 2.0.3".to_string(), "numpy<2,>=1.21".parse().unwrap())]
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upgrades_that_break_a_dependent_are_held_back() {
+        let handler = |force: bool| {
+            let fetcher = MockFetcher {
+                versions: [("numpy", "2.0.0"), ("pandas", "2.0.3"), ("requests", "2.32.3")]
+                    .into_iter()
+                    .map(|(name, version)| (name.to_string(), version.to_string()))
+                    .collect(),
+            };
+            let reporter = MockReporter { results: Mutex::new(Vec::new()) };
+            let config = UpgradeConfig { force, ..UpgradeConfig::default() };
+            UpgradeHandler::new(PinnedDetector, fetcher, MockInstaller, reporter, config)
+        };
+        assert_eq!(handler(false).upgrade_all().await.unwrap(), 1);
+        assert_eq!(handler(true).upgrade_all().await.unwrap(), 2);
+
+        let held = handler(false);
+        let packages = held.detector.get_installed().await.unwrap();
+        let mut outcome = held.scan(&packages, &HashMap::new()).await;
+        outcome.hold_for_dependents(&held.detector.installed_requirements());
+        assert_eq!(outcome.outdated, [("requests".into(), "2.31.0".into(), "2.32.3".into())]);
+        assert_eq!(
+            outcome.held_back_summary().unwrap(),
+            "1 package held back: numpy 1.26.4 (2.0.0 available, pandas 2.0.3 requires numpy<2,>=1.21)"
+        );
+    }
+
+    /// Index fixture serving both the legacy JSON API and PEP 691 listings,
+    /// counting the bytes it hands out
+    #[derive(Default)]
+    struct FixtureIndex {
+        bodies: std::collections::HashMap<String, Vec<u8>>,
+        cache: Mutex<std::collections::HashMap<String, Vec<u8>>>,
+        served: std::sync::atomic::AtomicU64,
+    }
+
+    impl FixtureIndex {
+        fn served(&self) -> u64 {
+            self.served.load(std::sync::atomic::Ordering::Relaxed)
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl IndexTransport for &FixtureIndex {
+        async fn get(&self, url: &str, _accept: &str) -> Result<Vec<u8>> {
+            let body = self.bodies.get(url).cloned().ok_or_else(|| anyhow::anyhow!("404 {}", url))?;
+            self.served.fetch_add(body.len() as u64, std::sync::atomic::Ordering::Relaxed);
+            Ok(body)
+        }
+
+        fn cached(&self, url: &str, _max_age: Duration) -> Option<Vec<u8>> {
+            self.cache.lock().unwrap().get(url).cloned()
+        }
+
+        fn cached_size(&self, url: &str) -> Option<u64> {
+            self.cache.lock().unwrap().get(url).map(|body| body.len() as u64)
+        }
+
+        fn store(&self, url: &str, body: &[u8]) {
+            self.cache.lock().unwrap().insert(url.to_string(), body.to_vec());
+        }
+
+        fn simple_url(&self, name: &str) -> String {
+            fixture_simple_url(name)
+        }
+
+        fn json_url(&self, name: &str) -> String {
+            fixture_json_url(name)
+        }
+    }
+
+    fn fixture_simple_url(name: &str) -> String {
+        format!("https://index.test/simple/{}/", name)
+    }
+
+    fn fixture_json_url(name: &str) -> String {
+        format!("https://index.test/pypi/{}/json", name)
+    }
+
+    /// The previous scan path: one legacy JSON document per package
+    struct LegacyFetcher<'a>(&'a FixtureIndex);
+
+    #[async_trait::async_trait]
+    impl MetadataFetcher for LegacyFetcher<'static> {
+        async fn fetch_latest(&self, name: &str) -> Result<String> {
+            let body = self.0.get(&fixture_json_url(name), "application/json").await?;
+            let json: serde_json::Value = serde_json::from_slice(&body)?;
+            Ok(json["info"]["version"].as_str().unwrap().to_string())
+        }
+    }
+
+    /// 20 packages with pre-releases, yanked releases and a few indexes
+    /// that predate PEP 700; returns the index and the installed set
+    fn twenty_package_fixture() -> (FixtureIndex, Vec<InstalledPackage>) {
+        let mut index = FixtureIndex::default();
+        let mut installed = Vec::new();
+        for i in 0..20 {
+            let name = format!("pkg-{:02}", i);
+            let mut versions: Vec<String> = (0..3 + i % 5).map(|k| format!("1.{}.0", k)).collect();
+            let stable = versions.clone();
+            if i % 4 == 1 {
+                versions.push("2.0.0rc1".to_string());
+            }
+            let yanked = (i % 4 == 2).then(|| stable.last().unwrap().clone());
+            // What PyPI reports as info.version
+            let latest = stable.iter().rev().find(|v| Some(*v) != yanked.as_ref()).unwrap().clone();
+
+            let file = |version: &String, wheel: bool| {
+                let filename = if wheel {
+                    format!("{}-{}-py3-none-any.whl", name.replace('-', "_"), version)
+                } else {
+                    format!("{}-{}.tar.gz", name, version)
+                };
+                (filename, Some(version) == yanked.as_ref())
+            };
+            let files: Vec<(String, &String, bool)> = versions
+                .iter()
+                .flat_map(|v| [file(v, true), file(v, false)].map(|(f, y)| (f, v, y)))
+                .collect();
+
+            let mut listing = serde_json::json!({
+                "meta": {"api-version": "1.1", "_last-serial": 1000 + i},
+                "name": name,
+                "versions": versions,
+                "files": files.iter().map(|(filename, _, yanked)| serde_json::json!({
+                    "filename": filename,
+                    "url": format!("https://files.test/{}", filename),
+                    "hashes": {"sha256": "ab".repeat(32)},
+                    "requires-python": ">=3.8",
+                    "yanked": if *yanked { serde_json::json!("regression") } else { serde_json::json!(false) },
+                })).collect::<Vec<_>>(),
+            });
+            if i % 7 == 3 {
+                listing.as_object_mut().unwrap().remove("versions");
+            }
+            let mut releases = serde_json::Map::new();
+            for version in &versions {
+                let entries: Vec<_> = files
+                    .iter()
+                    .filter(|(_, v, _)| *v == version)
+                    .map(|(filename, _, yanked)| serde_json::json!({
+                        "filename": filename,
+                        "url": format!("https://files.test/{}", filename),
+                        "digests": {"md5": "cd".repeat(16), "sha256": "ab".repeat(32), "blake2b_256": "ef".repeat(32)},
+                        "md5_digest": "cd".repeat(16),
+                        "packagetype": if filename.ends_with(".whl") { "bdist_wheel" } else { "sdist" },
+                        "python_version": "py3",
+                        "requires_python": ">=3.8",
+                        "size": 48_213,
+                        "upload_time": "2026-01-02T03:04:05",
+                        "upload_time_iso_8601": "2026-01-02T03:04:05.123456Z",
+                        "yanked": yanked,
+                        "yanked_reason": if *yanked { serde_json::json!("regression") } else { serde_json::Value::Null },
+                    }))
+                    .collect();
+                releases.insert(version.clone(), serde_json::Value::Array(entries));
+            }
+            let legacy = serde_json::json!({
+                "info": {
+                    "name": name,
+                    "version": latest,
+                    "summary": "A fixture package",
+                    "description": "Long README text. ".repeat(200),
+                    "requires_dist": ["requests>=2", "click>=8"],
+                    "requires_python": ">=3.8",
+                    "classifiers": vec!["Programming Language :: Python :: 3"; 12],
+                },
+                "last_serial": 1000 + i,
+                "releases": releases,
+                "urls": [],
+                "vulnerabilities": [],
+            });
+
+            index.bodies.insert(fixture_simple_url(&name), listing.to_string().into_bytes());
+            index.bodies.insert(fixture_json_url(&name), legacy.to_string().into_bytes());
+            let version = if i % 2 == 0 { "1.0.0".to_string() } else { latest };
+            installed.push(InstalledPackage { name, version });
+        }
+        (index, installed)
+    }
+
+    #[tokio::test]
+    async fn test_compact_listing_scan_matches_legacy_with_fewer_bytes() {
+        let (legacy_index, packages) = twenty_package_fixture();
+        let (compact_index, _) = twenty_package_fixture();
+        let legacy_index: &'static FixtureIndex = Box::leak(Box::new(legacy_index));
+        let compact_index: &'static FixtureIndex = Box::leak(Box::new(compact_index));
+        let detector = || MockDetector { packages: packages.clone() };
+        let reporter = || MockReporter { results: Mutex::new(Vec::new()) };
+
+        let legacy = UpgradeHandler::new(
+            detector(),
+            LegacyFetcher(legacy_index),
+            MockInstaller,
+            reporter(),
+            UpgradeConfig::default(),
+        );
+        let compact = UpgradeHandler::new(
+            detector(),
+            super::super::default_impl::DefaultMetadataFetcher::with_transport(compact_index),
+            MockInstaller,
+            reporter(),
+            UpgradeConfig::default(),
+        );
+
+        let expected = legacy.scan(&packages, &HashMap::new()).await;
+        let outcome = compact.scan(&packages, &HashMap::new()).await;
+        assert!(expected.failed.is_empty() && outcome.failed.is_empty());
+        assert_eq!(outcome.outdated, expected.outdated);
+        assert_eq!(outcome.outdated.len(), 10);
+        // pkg-06's newest release is yanked, pkg-09's newest is a pre-release
+        assert!(outcome.outdated.contains(&("pkg-06".into(), "1.0.0".into(), "1.2.0".into())));
+        assert!(!outcome.outdated.iter().any(|(name, _, _)| name == "pkg-09"));
+
+        let stats = compact.fetcher.stats();
+        let fallbacks = stats.fallbacks.load(std::sync::atomic::Ordering::Relaxed);
+        assert_eq!(fallbacks, 3, "pkg-03, pkg-10 and pkg-17 predate PEP 700");
+        let (old, new) = (legacy_index.served(), compact_index.served());
+        assert!(new * 2 < old, "compact scan moved {} bytes, legacy {}", new, old);
+
+        // A second scan is answered from the entries the first one stored
+        let rescan = compact.scan(&packages, &HashMap::new()).await;
+        assert_eq!(rescan.outdated, expected.outdated);
+        assert_eq!(compact_index.served(), new);
+        assert_eq!(stats.cache_hits.load(std::sync::atomic::Ordering::Relaxed), 20);
+    }
 }
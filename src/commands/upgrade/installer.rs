@@ -1,33 +1,50 @@
 /// Package installation/upgrade functionality
+///
+/// Upgrades go through pip-rs's own install pipeline: the resolver,
+/// downloader and wheel installer `pip install` uses, with its index,
+/// trusted-host and pre-flight checks. The functions that drive a system
+/// pip subprocess remain only for the deprecated `--use-system-pip` flag.
 use std::process::Command;
 use super::traits::UpgradeResult;
+use crate::cli::parser::InstallOptions;
+use crate::commands::install::install_report;
 
-/// Fast upgrade using pip-rs native installation (no subprocess overhead)
-pub async fn upgrade_package_fast(name: &str, _current: &str, latest: &str) -> UpgradeResult {
-    // Use pip-rs native installation for maximum speed
-    use crate::commands::install::handle_install;
-    
-    let package_spec = format!("{}=={}", name, latest);
-    match handle_install(vec![package_spec], None, None, Vec::new(), None).await {
-        Ok(_) => UpgradeResult {
-            name: name.to_string(),
-            current_version: _current.to_string(),
-            latest_version: latest.to_string(),
-            success: true,
-            error_msg: None,
-        },
-        Err(e) => UpgradeResult {
-            name: name.to_string(),
-            current_version: _current.to_string(),
-            latest_version: latest.to_string(),
-            success: false,
-            error_msg: Some(format!("{}", e)),
-        },
+/// Install exactly `name==version` natively, replacing what is installed;
+/// the error is the install's own message for the package
+pub async fn install_exact(name: &str, version: &str) -> Result<(), String> {
+    let options = InstallOptions {
+        packages: vec![format!("{}=={}", name, version)],
+        upgrade: true,
+        // `pip update` already ran the pre-flight scan for its packages
+        allow_degraded_environment: true,
+        ..Default::default()
+    };
+    let report = install_report(options).await.map_err(|e| e.to_string())?;
+    if let Some(failed) = report.failed.first() {
+        return Err(failed.error.clone());
     }
+    if let Some(skipped) = report.skipped.first() {
+        return Err(format!("{} was not installed because {} failed", skipped.name, skipped.due_to));
+    }
+    Ok(())
+}
+
+/// Download the wheel `install_exact` will pick for `name==version` into
+/// the wheel cache, leaving the environment alone
+pub async fn prefetch_wheel(name: &str, version: &str) -> anyhow::Result<()> {
+    if crate::cache::WheelCache::open().is_none() {
+        return Ok(());
+    }
+    let trace = crate::network::release_selection(name, version).await?;
+    let wheel = trace.best_wheel()?;
+    let work_dir = tempfile::tempdir()?;
+    crate::network::PackageClient::new()
+        .download_to_file_with(&wheel.url, &work_dir.path().join(&wheel.filename), wheel.sha256.as_deref(), None)
+        .await?;
+    Ok(())
 }
 
 /// Legacy upgrade using pip subprocess (kept for compatibility)
-#[allow(dead_code)]
 pub fn upgrade_package(name: &str, current: &str, latest: &str) -> UpgradeResult {
     let package_spec = format!("{}=={}", name, latest);
     
@@ -43,7 +60,7 @@ pub fn upgrade_package(name: &str, current: &str, latest: &str) -> UpgradeResult
                     current_version: current.to_string(),
                     latest_version: latest.to_string(),
                     success: false,
-                    error_msg: Some("Installation failed".to_string()),
+                    error_msg: Some(String::from_utf8_lossy(&result.stderr).trim().to_string()),
                 };
             }
             
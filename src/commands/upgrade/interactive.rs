@@ -0,0 +1,398 @@
+/// Interactive upgrade picker (`pip update --interactive`)
+///
+/// `Picker` holds the selection state and reacts to `Key`s; rendering and
+/// terminal I/O live in `run`, so the selection rules are testable without
+/// a terminal.
+use crate::models::Requirement;
+use crate::utils::names::canonicalize_name;
+use crate::utils::version;
+use std::fs;
+use std::path::Path;
+
+/// Size of the release jump, by the first component that changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpgradeKind {
+    Major,
+    Minor,
+    Patch,
+    Other,
+}
+
+impl UpgradeKind {
+    pub fn classify(current: &str, latest: &str) -> Self {
+        let release = |v: &str| -> Vec<u64> {
+            v.split('.')
+                .map_while(|part| part.parse::<u64>().ok())
+                .collect()
+        };
+        let (current, latest) = (release(current), release(latest));
+        if current.is_empty() || latest.is_empty() {
+            return UpgradeKind::Other;
+        }
+        let at = |parts: &[u64], i: usize| parts.get(i).copied().unwrap_or(0);
+        if at(&current, 0) != at(&latest, 0) {
+            UpgradeKind::Major
+        } else if at(&current, 1) != at(&latest, 1) {
+            UpgradeKind::Minor
+        } else if at(&current, 2) != at(&latest, 2) {
+            UpgradeKind::Patch
+        } else {
+            UpgradeKind::Other
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UpgradeKind::Major => "major",
+            UpgradeKind::Minor => "minor",
+            UpgradeKind::Patch => "patch",
+            UpgradeKind::Other => "other",
+        }
+    }
+}
+
+/// One upgradable package
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    pub name: String,
+    pub current: String,
+    pub latest: String,
+    pub kind: UpgradeKind,
+    /// Why the upgrade is held back (an installed package's requirement
+    /// excludes the latest version)
+    pub held: Option<String>,
+}
+
+/// Keyboard input, decoupled from the terminal library
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Up,
+    Down,
+    /// Space: toggle the current row
+    Toggle,
+    /// `a`: select every patch and minor upgrade that isn't held
+    SelectSafe,
+    /// `f`: allow (or disallow again) selecting a held package
+    Force,
+    /// Enter
+    Confirm,
+    /// `q` / Esc
+    Abort,
+    Other,
+}
+
+/// What the picker ended with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Confirmed(Vec<Candidate>),
+    Aborted,
+}
+
+/// Selection state of the picker
+#[derive(Debug, Clone)]
+pub struct Picker {
+    candidates: Vec<Candidate>,
+    selected: Vec<bool>,
+    forced: Vec<bool>,
+    cursor: usize,
+    message: Option<String>,
+}
+
+impl Picker {
+    pub fn new(candidates: Vec<Candidate>) -> Self {
+        let len = candidates.len();
+        Self {
+            candidates,
+            selected: vec![false; len],
+            forced: vec![false; len],
+            cursor: 0,
+            message: None,
+        }
+    }
+
+    /// Apply a key; returns the outcome once the user confirms or aborts
+    pub fn handle(&mut self, key: Key) -> Option<Outcome> {
+        self.message = None;
+        let last = self.candidates.len().saturating_sub(1);
+        match key {
+            Key::Up => self.cursor = self.cursor.saturating_sub(1),
+            Key::Down => self.cursor = (self.cursor + 1).min(last),
+            Key::Toggle => self.toggle(self.cursor),
+            Key::SelectSafe => {
+                for i in 0..self.candidates.len() {
+                    let candidate = &self.candidates[i];
+                    if matches!(candidate.kind, UpgradeKind::Patch | UpgradeKind::Minor) && self.selectable(i) {
+                        self.selected[i] = true;
+                    }
+                }
+            }
+            Key::Force => {
+                if self.candidates.get(self.cursor).is_some_and(|c| c.held.is_some()) {
+                    self.forced[self.cursor] = !self.forced[self.cursor];
+                    if !self.forced[self.cursor] {
+                        self.selected[self.cursor] = false;
+                    }
+                }
+            }
+            Key::Confirm => return Some(Outcome::Confirmed(self.selection())),
+            Key::Abort => return Some(Outcome::Aborted),
+            Key::Other => {}
+        }
+        None
+    }
+
+    fn selectable(&self, index: usize) -> bool {
+        self.candidates[index].held.is_none() || self.forced[index]
+    }
+
+    fn toggle(&mut self, index: usize) {
+        if index >= self.candidates.len() {
+            return;
+        }
+        if self.selectable(index) {
+            self.selected[index] = !self.selected[index];
+        } else {
+            self.message = Some(format!("{} is held; press f to force it", self.candidates[index].name));
+        }
+    }
+
+    /// Currently selected candidates, in list order
+    pub fn selection(&self) -> Vec<Candidate> {
+        self.candidates
+            .iter()
+            .zip(&self.selected)
+            .filter(|(_, selected)| **selected)
+            .map(|(candidate, _)| candidate.clone())
+            .collect()
+    }
+
+    /// Lines to draw for the current state
+    pub fn render(&self) -> Vec<String> {
+        let mut lines = vec![
+            "Select upgrades: ↑/↓ move, space toggle, a patch+minor, f force held, enter confirm, q abort"
+                .to_string(),
+            String::new(),
+        ];
+        for (i, candidate) in self.candidates.iter().enumerate() {
+            let pointer = if i == self.cursor { '>' } else { ' ' };
+            let checkbox = if self.selected[i] { "[x]" } else { "[ ]" };
+            let mut line = format!(
+                "{} {} {:<30} {:>12} → {:<12} {:<6}",
+                pointer,
+                checkbox,
+                candidate.name,
+                candidate.current,
+                candidate.latest,
+                candidate.kind.as_str()
+            );
+            if let Some(reason) = &candidate.held {
+                let tag = if self.forced[i] { "forced" } else { "held" };
+                line.push_str(&format!("  ({}: {})", tag, reason));
+            }
+            lines.push(line);
+        }
+        lines.push(String::new());
+        lines.push(self.message.clone().unwrap_or_else(|| {
+            format!("{} of {} selected", self.selected.iter().filter(|s| **s).count(), self.candidates.len())
+        }));
+        lines
+    }
+}
+
+/// Build candidates from the scan, holding back upgrades that an installed
+/// package's requirements exclude
+pub fn candidates(outdated: &[(String, String, String)], requirements: &[(String, Requirement)]) -> Vec<Candidate> {
+    outdated
+        .iter()
+        .map(|(name, current, latest)| Candidate {
+            name: name.clone(),
+            current: current.clone(),
+            latest: latest.clone(),
+            kind: UpgradeKind::classify(current, latest),
+            held: held_by(name, latest, requirements),
+        })
+        .collect()
+}
+
+/// Why upgrading `name` to `version` would break an installed package,
+/// e.g. "pandas 2.0.3 requires numpy<2"
+pub fn held_by(name: &str, version: &str, requirements: &[(String, Requirement)]) -> Option<String> {
+    let normalized = canonicalize_name(name);
+    requirements
+        .iter()
+        .find(|(_, req)| canonicalize_name(&req.name) == normalized && !version::satisfies_all(version, &req.specs))
+        .map(|(dependent, req)| format!("{} requires {}", dependent, req))
+}
+
+/// ("name version" of the dependent, requirement) for every unconditional
+/// Requires-Dist in `site_packages`
+pub fn installed_requirements(site_packages: &Path) -> Vec<(String, Requirement)> {
+    let Ok(entries) = fs::read_dir(site_packages) else {
+        return Vec::new();
+    };
+    let mut requirements = Vec::new();
+    for entry in entries.flatten() {
+        if !entry.file_name().to_string_lossy().ends_with(".dist-info") {
+            continue;
+        }
+        let Ok(metadata) = fs::read_to_string(entry.path().join("METADATA")) else {
+            continue;
+        };
+        let (mut name, mut version) = ("", "");
+        let mut found = Vec::new();
+        for line in metadata.lines() {
+            if let Some(value) = line.strip_prefix("Name: ") {
+                name = value.trim();
+            } else if let Some(value) = line.strip_prefix("Version: ") {
+                version = value.trim();
+            } else if let Some(value) = line.strip_prefix("Requires-Dist: ") {
+                // `name (>=1.0)` is the older spelling of `name>=1.0`
+                let value = value.replace(['(', ')'], "");
+                if let Ok(req) = value.parse::<Requirement>()
+                    && req.marker.as_deref().is_none_or(|m| !m.contains("extra"))
+                {
+                    found.push(req);
+                }
+            } else if line.is_empty() {
+                break;
+            }
+        }
+        let dependent = format!("{} {}", name, version).trim().to_string();
+        requirements.extend(found.into_iter().map(|req| (dependent.clone(), req)));
+    }
+    requirements
+}
+
+/// Show the picker on the terminal and return the user's choice
+pub fn run(candidates: Vec<Candidate>) -> std::io::Result<Outcome> {
+    use console::{Key as TermKey, Term};
+
+    let term = Term::stderr();
+    let mut picker = Picker::new(candidates);
+    let mut drawn = 0;
+    term.hide_cursor()?;
+    let outcome = loop {
+        term.clear_last_lines(drawn)?;
+        let lines = picker.render();
+        for line in &lines {
+            term.write_line(line)?;
+        }
+        drawn = lines.len();
+
+        let key = match term.read_key()? {
+            TermKey::ArrowUp | TermKey::Char('k') => Key::Up,
+            TermKey::ArrowDown | TermKey::Char('j') => Key::Down,
+            TermKey::Char(' ') => Key::Toggle,
+            TermKey::Char('a') => Key::SelectSafe,
+            TermKey::Char('f') => Key::Force,
+            TermKey::Enter => Key::Confirm,
+            TermKey::Char('q') | TermKey::Escape => Key::Abort,
+            _ => Key::Other,
+        };
+        if let Some(outcome) = picker.handle(key) {
+            break outcome;
+        }
+    };
+    term.clear_last_lines(drawn)?;
+    term.show_cursor()?;
+    Ok(outcome)
+}
+
+/// The picker needs a terminal on both ends
+pub fn is_interactive_terminal() -> bool {
+    console::Term::stderr().is_term() && console::user_attended_stderr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(name: &str, current: &str, latest: &str, held: Option<&str>) -> Candidate {
+        Candidate {
+            name: name.to_string(),
+            current: current.to_string(),
+            latest: latest.to_string(),
+            kind: UpgradeKind::classify(current, latest),
+            held: held.map(String::from),
+        }
+    }
+
+    fn picker() -> Picker {
+        Picker::new(vec![
+            candidate("django", "4.2.1", "5.0.0", None),
+            candidate("requests", "2.31.0", "2.32.3", None),
+            candidate("urllib3", "2.0.7", "2.0.9", Some("botocore requires urllib3<2.0.8")),
+            candidate("idna", "3.6", "3.6.1", None),
+        ])
+    }
+
+    fn names(outcome: Option<Outcome>) -> Vec<String> {
+        match outcome {
+            Some(Outcome::Confirmed(selected)) => selected.into_iter().map(|c| c.name).collect(),
+            other => panic!("expected confirmation, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_toggle_select_safe_and_confirm() {
+        assert_eq!(UpgradeKind::classify("4.2.1", "5.0.0"), UpgradeKind::Major);
+        assert_eq!(UpgradeKind::classify("2.31.0", "2.32.3"), UpgradeKind::Minor);
+        assert_eq!(UpgradeKind::classify("3.6", "3.6.1"), UpgradeKind::Patch);
+
+        let mut picker = picker();
+        // `a` takes minor + patch upgrades but leaves the major and the held one
+        assert_eq!(picker.handle(Key::SelectSafe), None);
+        // Toggle django (major) on and requests back off
+        picker.handle(Key::Toggle);
+        picker.handle(Key::Down);
+        picker.handle(Key::Toggle);
+        assert_eq!(names(picker.handle(Key::Confirm)), vec!["django", "idna"]);
+    }
+
+    #[test]
+    fn test_abort_selects_nothing() {
+        let mut picker = picker();
+        picker.handle(Key::SelectSafe);
+        assert_eq!(picker.handle(Key::Abort), Some(Outcome::Aborted));
+    }
+
+    #[test]
+    fn test_held_package_needs_force() {
+        let mut picker = picker();
+        picker.handle(Key::Down);
+        picker.handle(Key::Down);
+        picker.handle(Key::Toggle);
+        assert!(picker.selection().is_empty());
+        assert!(picker.render().last().unwrap().contains("urllib3 is held; press f to force it"));
+
+        picker.handle(Key::Force);
+        picker.handle(Key::Toggle);
+        assert!(picker.render().iter().any(|l| l.contains("[x]") && l.contains("forced: botocore")));
+        // Un-forcing drops it from the selection again
+        picker.handle(Key::Force);
+        assert!(names(picker.handle(Key::Confirm)).is_empty());
+    }
+
+    #[test]
+    fn test_installed_requirements_hold_upgrades() {
+        let site = tempfile::TempDir::new().unwrap();
+        let dist_info = site.path().join("botocore-1.34.0.dist-info");
+        fs::create_dir_all(&dist_info).unwrap();
+        fs::write(
+            dist_info.join("METADATA"),
+            "Name: botocore\nVersion: 1.34.0\nRequires-Dist: urllib3 (<2.0.8,>=1.25.4)\nRequires-Dist: jmespath~=1.0\n\
+             Requires-Dist: docs-theme ; extra == \"docs\"\n",
+        )
+        .unwrap();
+
+        let outdated = vec![
+            ("urllib3".to_string(), "2.0.7".to_string(), "2.2.1".to_string()),
+            ("jmespath".to_string(), "1.0.0".to_string(), "1.0.1".to_string()),
+            ("docs-theme".to_string(), "1.0".to_string(), "3.0".to_string()),
+        ];
+        let candidates = candidates(&outdated, &installed_requirements(site.path()));
+        assert_eq!(candidates[0].held.as_deref(), Some("botocore 1.34.0 requires urllib3<2.0.8,>=1.25.4"));
+        assert_eq!(candidates[1].held, None);
+        assert_eq!(candidates[2].held, None);
+    }
+}
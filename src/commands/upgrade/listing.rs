@@ -0,0 +1,186 @@
+/// Latest-version selection from PEP 691 project listings
+///
+/// The simple API's JSON form lists every file with its yanked flag and
+/// Requires-Python and, since PEP 700, every version, which is all the
+/// outdated scan needs. Selection mirrors what the legacy JSON API reports as `info.version`:
+/// the highest release that isn't a pre-release and isn't yanked, else
+/// the highest pre-release, else the highest version at all.
+use crate::resolver::requires_python::Release;
+use crate::utils::version::{compare as compare_versions, is_prerelease};
+use serde::Deserialize;
+use std::collections::HashSet;
+
+/// Accept header asking an index for the PEP 691 JSON form
+pub const SIMPLE_JSON_ACCEPT: &str = "application/vnd.pypi.simple.v1+json";
+
+#[derive(Debug, Deserialize)]
+struct Listing {
+    /// PEP 700; indexes that predate it can't tell file-less versions apart
+    versions: Option<Vec<String>>,
+    #[serde(default)]
+    files: Vec<ListingFile>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListingFile {
+    filename: String,
+    #[serde(default)]
+    yanked: serde_json::Value,
+    #[serde(default, rename = "requires-python")]
+    requires_python: Option<String>,
+}
+
+impl ListingFile {
+    /// `yanked` is `false` or a reason string (or `true`)
+    fn is_yanked(&self) -> bool {
+        !matches!(self.yanked, serde_json::Value::Null | serde_json::Value::Bool(false))
+    }
+
+    /// Version component of a wheel, egg or sdist filename
+    fn version(&self) -> Option<&str> {
+        let name = self.filename.as_str();
+        if name.ends_with(".whl") || name.ends_with(".egg") {
+            return name.split('-').nth(1);
+        }
+        let stem = [".tar.gz", ".tar.bz2", ".tar.xz", ".tgz", ".zip"]
+            .iter()
+            .find_map(|ext| name.strip_suffix(ext))?;
+        stem.rsplit_once('-').map(|(_, version)| version)
+    }
+}
+
+/// Latest version from a PEP 691 JSON body; `None` when the body isn't
+/// one or lacks the PEP 700 `versions` key, so the caller can fall back
+pub fn latest_from_listing(body: &[u8]) -> Option<String> {
+    let listing: Listing = serde_json::from_slice(body).ok()?;
+    let versions = listing.versions?;
+
+    // A version is yanked only when every one of its files is
+    let mut yanked: HashSet<&str> = HashSet::new();
+    let mut available: HashSet<&str> = HashSet::new();
+    for file in &listing.files {
+        if let Some(version) = file.version() {
+            if file.is_yanked() {
+                yanked.insert(version);
+            } else {
+                available.insert(version);
+            }
+        }
+    }
+    let is_yanked = |v: &str| yanked.contains(v) && !available.contains(v);
+
+    let highest = |candidates: Vec<&String>| {
+        candidates
+            .into_iter()
+            .max_by(|a, b| compare_versions(a, b))
+            .cloned()
+    };
+    let live: Vec<&String> = versions.iter().filter(|v| !is_yanked(v)).collect();
+    highest(live.iter().copied().filter(|v| !is_prerelease(v)).collect())
+        .or_else(|| highest(live))
+        .or_else(|| highest(versions.iter().collect()))
+}
+
+/// Every release of a PEP 691 JSON body with its Requires-Python, as the
+/// JSON API would list them; `None` when the body isn't one or lacks the
+/// PEP 700 `versions` key. Versions without files can't be installed and
+/// are left out.
+pub fn releases_from_listing(body: &[u8]) -> Option<Vec<Release>> {
+    let listing: Listing = serde_json::from_slice(body).ok()?;
+    let releases = listing
+        .versions?
+        .into_iter()
+        .filter_map(|version| {
+            let files: Vec<&ListingFile> =
+                listing.files.iter().filter(|file| file.version() == Some(version.as_str())).collect();
+            if files.is_empty() {
+                return None;
+            }
+            Some(Release {
+                requires_python: files
+                    .iter()
+                    .find_map(|file| file.requires_python.as_deref())
+                    .filter(|spec| !spec.trim().is_empty())
+                    .map(String::from),
+                yanked: files.iter().all(|file| file.is_yanked()),
+                yanked_reason: files.iter().find_map(|file| file.yanked.as_str()).map(String::from),
+                version,
+            })
+        })
+        .collect();
+    Some(releases)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listing(versions: &[&str], files: &[(&str, bool)]) -> Vec<u8> {
+        serde_json::json!({
+            "meta": {"api-version": "1.1"},
+            "name": "demo",
+            "versions": versions,
+            "files": files.iter().map(|(name, yanked)| serde_json::json!({
+                "filename": name,
+                "url": format!("https://files.example/{}", name),
+                "hashes": {},
+                "yanked": if *yanked { serde_json::json!("broken") } else { serde_json::json!(false) },
+            })).collect::<Vec<_>>(),
+        })
+        .to_string()
+        .into_bytes()
+    }
+
+    #[test]
+    fn test_prerelease_and_yanked_rules() {
+        let body = listing(
+            &["1.0.0", "1.1.0", "1.2.0", "2.0.0rc1"],
+            &[
+                ("demo-1.1.0-py3-none-any.whl", false),
+                ("demo-1.2.0-py3-none-any.whl", true),
+                ("demo-1.2.0.tar.gz", true),
+                ("demo-2.0.0rc1.tar.gz", false),
+            ],
+        );
+        assert_eq!(latest_from_listing(&body).as_deref(), Some("1.1.0"));
+
+        // Partially yanked releases still count
+        let body = listing(&["1.2.0"], &[("demo-1.2.0.tar.gz", true), ("demo-1.2.0-py3-none-any.whl", false)]);
+        assert_eq!(latest_from_listing(&body).as_deref(), Some("1.2.0"));
+
+        // Only pre-releases: the highest one
+        let body = listing(&["0.1.0a1", "0.2.0b1"], &[("demo-0.2.0b1.tar.gz", false)]);
+        assert_eq!(latest_from_listing(&body).as_deref(), Some("0.2.0b1"));
+
+        assert!(is_prerelease("1.0.dev3") && is_prerelease("2.0RC1") && !is_prerelease("1.0.post2+local.a"));
+    }
+
+    #[test]
+    fn test_releases_from_listing() {
+        let mut body: serde_json::Value = serde_json::from_slice(&listing(
+            &["1.0.0", "1.1.0", "2.0.0"],
+            &[
+                ("demo-1.0.0.tar.gz", false),
+                ("demo-1.1.0-py3-none-any.whl", true),
+                ("demo-1.1.0.tar.gz", true),
+                ("demo-2.0.0-py3-none-any.whl", false),
+            ],
+        ))
+        .unwrap();
+        body["files"][3]["requires-python"] = serde_json::json!(">=3.12");
+        let releases = releases_from_listing(body.to_string().as_bytes()).unwrap();
+        let summary: Vec<(&str, Option<&str>, bool)> = releases
+            .iter()
+            .map(|r| (r.version.as_str(), r.requires_python.as_deref(), r.yanked))
+            .collect();
+        assert_eq!(summary, [("1.0.0", None, false), ("1.1.0", None, true), ("2.0.0", Some(">=3.12"), false)]);
+    }
+
+    #[test]
+    fn test_pre_pep700_listing_falls_back() {
+        let body = br#"{"meta": {"api-version": "1.0"}, "name": "demo", "files": []}"#;
+        assert_eq!(latest_from_listing(body), None);
+        assert_eq!(releases_from_listing(body), None);
+        assert_eq!(latest_from_listing(b"<html></html>"), None);
+    }
+}
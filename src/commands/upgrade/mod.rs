@@ -5,6 +5,8 @@ pub mod installer;
 pub mod traits;
 pub mod default_impl;
 pub mod handler;
+pub mod interactive;
+pub mod listing;
 
 
 use anyhow::Result;
@@ -22,7 +24,7 @@ pub async fn handle_upgrade(package_name: &str, _target: Option<&str>) -> Result
     let latest_package = get_package_metadata(package_name, "latest").await?;
 
     // Compare versions
-    if detector::compare_versions(current_version, &latest_package.version) == Ordering::Less {
+    if crate::utils::version::compare(current_version, &latest_package.version) == Ordering::Less {
         println!(
             "Upgrade available: {} -> {}",
             current_version, latest_package.version
@@ -37,42 +39,106 @@ pub async fn handle_upgrade(package_name: &str, _target: Option<&str>) -> Result
 
 use crate::errors::PipError;
 
-pub async fn handle_upgrade_all() -> Result<i32, PipError> {
+/// `--interactive` draws a picker, which needs a terminal
+fn check_interactive(interactive: bool) -> Result<(), PipError> {
+    if interactive && !interactive::is_interactive_terminal() {
+        return Err(PipError::InvalidRequirement {
+            spec: "--interactive".to_string(),
+            reason: "needs an interactive terminal; name the packages to update instead (pip update <package>...)"
+                .to_string(),
+        });
+    }
+    Ok(())
+}
+
+pub async fn handle_upgrade_all(
+    config: traits::UpgradeConfig,
+    allow_degraded_environment: bool,
+    use_system_pip: bool,
+) -> Result<i32, PipError> {
     use default_impl::*;
     use traits::UpgradeConfig;
     use handler::UpgradeHandler;
 
+    check_interactive(config.interactive)?;
+    let environment_config = crate::config::config::Config::global();
+    crate::commands::install::ensure_virtualenv(environment_config, false)?;
+    crate::commands::install::ensure_not_externally_managed(environment_config, false)?;
+    crate::commands::install::preflight_environment(None, allow_degraded_environment)?;
+
     let detector = DefaultPackageDetector;
-    let fetcher = DefaultMetadataFetcher;
-    let installer = DefaultPackageInstaller;
+    let fetcher = DefaultMetadataFetcher::new();
     let reporter = DefaultProgressReporter::new(false);
-    let config = UpgradeConfig::default();
-
-    let upgrade_handler = UpgradeHandler::new(detector, fetcher, installer, reporter, config);
-    upgrade_handler.upgrade_all().await.map_err(|e| PipError::InstallationFailed {
-        package: "all packages".to_string(),
-        reason: e.to_string(),
+    let config = UpgradeConfig {
+        concurrency: crate::utils::resources::ResourceBudget::global().network,
+        ..config
+    };
+
+    let result = if use_system_pip {
+        warn_system_pip();
+        UpgradeHandler::new(detector, fetcher, SystemPipInstaller, reporter, config).upgrade_all().await
+    } else {
+        UpgradeHandler::new(detector, fetcher, DefaultPackageInstaller::new(), reporter, config).upgrade_all().await
+    };
+    result.map_err(|e| {
+        PipError::from_anyhow(e).unwrap_or_else(|e| PipError::InstallationFailed {
+            package: "all packages".to_string(),
+            reason: e.to_string(),
+        })
     })
 }
 
-pub async fn handle_upgrade_packages(packages: Vec<String>) -> Result<i32, PipError> {
+pub async fn handle_upgrade_packages(
+    packages: Vec<String>,
+    config: traits::UpgradeConfig,
+    allow_degraded_environment: bool,
+    use_system_pip: bool,
+) -> Result<i32, PipError> {
     use default_impl::*;
     use traits::UpgradeConfig;
     use handler::UpgradeHandler;
 
+    check_interactive(config.interactive)?;
+    let environment_config = crate::config::config::Config::global();
+    crate::commands::install::ensure_virtualenv(environment_config, false)?;
+    crate::commands::install::ensure_not_externally_managed(environment_config, false)?;
+    crate::commands::install::preflight_environment(Some(&packages), allow_degraded_environment)?;
+
     let detector = DefaultPackageDetector;
-    let fetcher = DefaultMetadataFetcher;
-    let installer = DefaultPackageInstaller;
+    let fetcher = DefaultMetadataFetcher::new();
     let reporter = DefaultProgressReporter::new(false);
-    let config = UpgradeConfig::default();
-
-    let upgrade_handler = UpgradeHandler::new(detector, fetcher, installer, reporter, config);
-    upgrade_handler.upgrade_packages(packages).await.map_err(|e| PipError::InstallationFailed {
-        package: "requested packages".to_string(),
-        reason: e.to_string(),
+    let config = UpgradeConfig {
+        concurrency: crate::utils::resources::ResourceBudget::global().network,
+        ..config
+    };
+
+    let result = if use_system_pip {
+        warn_system_pip();
+        UpgradeHandler::new(detector, fetcher, SystemPipInstaller, reporter, config)
+            .upgrade_packages(packages)
+            .await
+    } else {
+        UpgradeHandler::new(detector, fetcher, DefaultPackageInstaller::new(), reporter, config)
+            .upgrade_packages(packages)
+            .await
+    };
+    result.map_err(|e| {
+        PipError::from_anyhow(e).unwrap_or_else(|e| PipError::InstallationFailed {
+            package: "requested packages".to_string(),
+            reason: e.to_string(),
+        })
     })
 }
 
+/// `--use-system-pip` is an escape hatch slated for removal
+fn warn_system_pip() {
+    crate::utils::warnings::warn_user(
+        crate::utils::warnings::WarningCode::DeprecatedOption,
+        "--use-system-pip is deprecated and will be removed in the next release; \
+         it ignores pip-rs's index, trusted-host and pre-install checks",
+    );
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -2,8 +2,12 @@
 use async_trait::async_trait;
 use anyhow::Result;
 use std::cmp::Ordering;
+use std::path::PathBuf;
+use std::time::Duration;
 
 use super::detector::InstalledPackage;
+use crate::models::Requirement;
+use crate::resolver::requires_python::Release;
 
 /// Trait for package detection
 #[async_trait]
@@ -13,6 +17,12 @@ pub trait PackageDetector: Send + Sync {
     
     /// Compare two versions
     fn compare_versions(&self, current: &str, latest: &str) -> Ordering;
+
+    /// ("name version" of the dependent, requirement) for every
+    /// unconditional Requires-Dist of the installed packages
+    fn installed_requirements(&self) -> Vec<(String, Requirement)> {
+        Vec::new()
+    }
 }
 
 /// Trait for package metadata fetching
@@ -20,6 +30,53 @@ pub trait PackageDetector: Send + Sync {
 pub trait MetadataFetcher: Send + Sync {
     /// Fetch latest version for a package
     async fn fetch_latest(&self, name: &str) -> Result<String>;
+
+    /// Fetch every release with its Requires-Python; fetchers that only
+    /// know the latest version report just that one
+    async fn fetch_releases(&self, name: &str) -> Result<Vec<Release>> {
+        let version = self.fetch_latest(name).await?;
+        Ok(vec![Release { version, requires_python: None, yanked: false, yanked_reason: None }])
+    }
+
+    /// What the scan transferred so far, for the `-v` summary
+    fn transfer_summary(&self) -> Option<String> {
+        None
+    }
+}
+
+/// Trait for raw index reads made by the metadata fetcher
+#[async_trait]
+pub trait IndexTransport: Send + Sync {
+    /// Fresh body of `url`, requested with the given Accept header
+    async fn get(&self, url: &str, accept: &str) -> Result<Vec<u8>>;
+
+    /// Cached body of `url` written within `max_age`, if any
+    fn cached(&self, url: &str, max_age: Duration) -> Option<Vec<u8>>;
+
+    /// Size of any cached body of `url`, however old
+    fn cached_size(&self, url: &str) -> Option<u64>;
+
+    /// Remember a body fetched for `url`
+    fn store(&self, url: &str, body: &[u8]);
+
+    /// PEP 691 project URL for a (normalized) name
+    fn simple_url(&self, name: &str) -> String;
+
+    /// Legacy JSON API URL for a (normalized) name
+    fn json_url(&self, name: &str) -> String;
+}
+
+/// Installs one exact version into the environment
+#[async_trait]
+pub trait InstallBackend: Send + Sync {
+    /// Install `name==version`, replacing the installed version; the error
+    /// explains why this package failed
+    async fn install(&self, name: &str, version: &str) -> std::result::Result<(), String>;
+
+    /// Fetch what `install` will need without touching the environment, so
+    /// downloads can overlap while installs take turns; failures are left
+    /// for `install` to report
+    async fn prefetch(&self, _name: &str, _version: &str) {}
 }
 
 /// Trait for package installation
@@ -46,7 +103,7 @@ pub trait ProgressReporter: Send + Sync {
     fn report_scan_complete(&self, total: usize, outdated_count: usize);
     
     /// Report final summary
-    fn report_summary(&self, upgraded: usize, failed: usize);
+    fn report_summary(&self, upgraded: usize, failed: usize, held_back: usize);
 }
 
 /// Upgrade result
@@ -60,12 +117,61 @@ pub struct UpgradeResult {
     pub error_msg: Option<String>,
 }
 
+impl UpgradeResult {
+    /// Outcome of upgrading `name` from `current` to `latest`
+    pub fn new(name: &str, current: &str, latest: &str, outcome: std::result::Result<(), String>) -> Self {
+        Self {
+            name: name.to_string(),
+            current_version: current.to_string(),
+            latest_version: latest.to_string(),
+            success: outcome.is_ok(),
+            error_msg: outcome.err(),
+        }
+    }
+}
+
+/// Message sent by a scan task for each package it checks
+#[derive(Clone, Debug)]
+pub enum ScanMessage {
+    /// Releases were fetched; `latest_version` is the newest one the
+    /// constraints and the interpreter allow
+    Checked {
+        name: String,
+        current_version: String,
+        latest_version: String,
+        is_outdated: bool,
+    },
+    /// A newer release exists, but the constraints or its Requires-Python
+    /// rule it out
+    HeldBack {
+        name: String,
+        current_version: String,
+        latest_version: String,
+        reason: String,
+    },
+    /// Latest version could not be determined
+    Failed { name: String, error: String },
+}
+
 /// Upgrade command configuration
 #[derive(Clone)]
 pub struct UpgradeConfig {
     pub concurrency: usize,
-    #[allow(dead_code)]
+    /// Print what the scan transferred
     pub verbose: bool,
+    /// Upper bound for the whole outdated scan
+    pub scan_timeout: Duration,
+    /// Let the user pick which outdated packages to upgrade
+    pub interactive: bool,
+    /// Requirements or constraints file capping the versions upgraded to
+    pub constraints: Option<PathBuf>,
+    /// Packages left alone, matched by canonical name
+    pub exclude: Vec<String>,
+    /// Consider pre-release versions when looking for upgrades
+    pub pre: bool,
+    /// Upgrade even when an installed package's requirements exclude the
+    /// new version
+    pub force: bool,
 }
 
 impl Default for UpgradeConfig {
@@ -73,6 +179,12 @@ impl Default for UpgradeConfig {
         Self {
             concurrency: 15, // Balanced for speed without rate limiting
             verbose: false,
+            scan_timeout: Duration::from_secs(300),
+            interactive: false,
+            constraints: None,
+            exclude: Vec::new(),
+            pre: false,
+            force: false,
         }
     }
 }
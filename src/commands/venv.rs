@@ -0,0 +1,106 @@
+/// Venv command - create, inspect and remove virtual environments
+use crate::errors::PipError;
+use pip_rs_core::venv::{Interpreter, VirtualEnvironment};
+use std::io::{self, BufRead};
+use std::path::PathBuf;
+
+/// Create a virtual environment at `path` using the interpreter `python`
+/// names (a version like `3.12` or a path), else the first on PATH
+pub async fn handle_venv_create(path: PathBuf, python: Option<String>) -> Result<i32, PipError> {
+    let interpreter = match Interpreter::find(python.as_deref()) {
+        Ok(interpreter) => interpreter,
+        Err(e) => {
+            eprintln!("ERROR: {}", e);
+            return Ok(1);
+        }
+    };
+    let env = VirtualEnvironment::new(path.clone(), interpreter.major_minor());
+    if let Err(e) = env.create_with(Some(&interpreter)) {
+        eprintln!("ERROR: Could not create virtual environment: {}", e);
+        return Ok(1);
+    }
+
+    println!(
+        "Created virtual environment at {} with Python {} ({})",
+        path.display(),
+        interpreter.version,
+        interpreter.executable.display()
+    );
+    let bin = env.get_bin_path();
+    if cfg!(target_os = "windows") {
+        println!("Activate with: {}\\activate.bat (cmd) or {}\\Activate.ps1 (PowerShell)", bin.display(), bin.display());
+    } else {
+        let activate = bin.join("activate").display().to_string();
+        if activate.contains(char::is_whitespace) {
+            println!("Activate with: source \"{}\"", activate);
+        } else {
+            println!("Activate with: source {}", activate);
+        }
+    }
+    Ok(0)
+}
+
+/// Print an environment's interpreter, site-packages and whether it is
+/// complete
+pub async fn handle_venv_info(path: PathBuf) -> Result<i32, PipError> {
+    let env = match VirtualEnvironment::open(&path) {
+        Ok(env) => env,
+        Err(e) => {
+            eprintln!("ERROR: {}", e);
+            return Ok(1);
+        }
+    };
+    let config = env.config().unwrap_or_default();
+    let none = String::from("(unknown)");
+
+    println!("Location: {}", env.path().display());
+    // The environment's own interpreter is the authority; pyvenv.cfg can be stale
+    match Interpreter::probe(&env.get_python_path()) {
+        Ok(interpreter) => println!("Python: {}", interpreter.version),
+        Err(_) => println!("Python: {} (interpreter not runnable)", config.get("version").unwrap_or(&none)),
+    }
+    println!("Interpreter: {}", env.get_python_path().display());
+    println!("Home: {}", config.get("home").unwrap_or(&none));
+    println!("Site-packages: {}", env.get_site_packages_path().display());
+    println!("Scripts: {}", env.get_bin_path().display());
+    println!(
+        "System site-packages: {}",
+        config.get("include-system-site-packages").map_or("false", String::as_str)
+    );
+    println!("Valid: {}", if env.is_valid() { "yes" } else { "no" });
+    Ok(if env.is_valid() { 0 } else { 1 })
+}
+
+/// Delete the environment at `path` after confirmation
+pub async fn handle_venv_remove(path: PathBuf, yes: bool) -> Result<i32, PipError> {
+    let env = match VirtualEnvironment::open(&path) {
+        Ok(env) => env,
+        Err(e) => {
+            eprintln!("ERROR: {}", e);
+            return Ok(1);
+        }
+    };
+
+    if !yes {
+        println!("Would remove the virtual environment at {}", path.display());
+        println!("\nProceed (y/n)? ");
+        let mut line = String::new();
+        io::stdin().lock().read_line(&mut line).map_err(|e| PipError::FileSystemError {
+            path: "stdin".to_string(),
+            operation: "read".to_string(),
+            reason: e.to_string(),
+        })?;
+        let response = line.trim().to_lowercase();
+        if response != "y" && response != "yes" {
+            println!("Aborted");
+            return Ok(0);
+        }
+    }
+
+    if let Err(e) = env.remove() {
+        eprintln!("ERROR: Could not remove {}: {}", path.display(), e);
+        return Ok(1);
+    }
+    println!("Removed virtual environment at {}", path.display());
+    Ok(0)
+}
@@ -0,0 +1,115 @@
+/// Version command - the pip-rs version, plus build metadata on request
+///
+/// Plain output is a single line; `--verbose` adds what the build script
+/// embedded and the config files and cache directory in effect, `--json`
+/// emits the same for bug-report tooling. Only file existence is checked:
+/// nothing is loaded, migrated or probed to produce it.
+use crate::errors::PipError;
+use pip_rs_core::cache::layout;
+use pip_rs_core::config::config::Config;
+use pip_rs_core::utils::build_info::{BuildInfo, PIP_COMPATIBILITY};
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// Metadata of this binary, as embedded by build.rs
+pub fn build_info() -> BuildInfo {
+    BuildInfo {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        git_describe: env!("PIP_RS_GIT_DESCRIBE").to_string(),
+        build_date: env!("PIP_RS_BUILD_DATE").to_string(),
+        rustc: env!("PIP_RS_RUSTC_VERSION").to_string(),
+        target: env!("PIP_RS_TARGET").to_string(),
+        profile: env!("PIP_RS_PROFILE").to_string(),
+        features: env!("PIP_RS_FEATURES")
+            .split(',')
+            .filter(|f| !f.is_empty())
+            .map(String::from)
+            .collect(),
+        pip_compatibility: PIP_COMPATIBILITY.to_string(),
+    }
+}
+
+/// The one-line form, as `pip --version` prints it
+pub fn plain_version() -> String {
+    format!("pip {}", env!("CARGO_PKG_VERSION"))
+}
+
+#[derive(Debug, Serialize)]
+struct VersionReport {
+    #[serde(flatten)]
+    build: BuildInfo,
+    /// pip.conf files that exist, in load order
+    config_files: Vec<PathBuf>,
+    cache_dir: Option<PathBuf>,
+}
+
+impl VersionReport {
+    fn collect() -> Self {
+        Self {
+            build: build_info(),
+            config_files: Config::standard_locations().into_iter().filter(|p| p.exists()).collect(),
+            cache_dir: layout::configured_root(),
+        }
+    }
+}
+
+pub async fn handle_version(verbose: bool, json: bool) -> Result<i32, PipError> {
+    if json {
+        let report = serde_json::to_string_pretty(&VersionReport::collect()).map_err(|e| PipError::InvalidPackage {
+            name: "version".to_string(),
+            reason: e.to_string(),
+        })?;
+        let mut out = crate::utils::pager::PagedOutput::plain();
+        out.line(report);
+        out.finish();
+        return Ok(0);
+    }
+
+    println!("{}", plain_version());
+    if !verbose {
+        return Ok(0);
+    }
+    let report = VersionReport::collect();
+    let build = &report.build;
+    println!("Commit: {}", build.git_describe);
+    println!("Built: {}", build.build_date);
+    println!("Compiler: {}", build.rustc);
+    println!("Target: {} ({})", build.target, build.profile);
+    if build.features.is_empty() {
+        println!("Features: none");
+    } else {
+        println!("Features: {}", build.features.join(", "));
+    }
+    println!("pip compatibility: {}", build.pip_compatibility);
+    if report.config_files.is_empty() {
+        println!("Config files: none");
+    } else {
+        println!("Config files:");
+        for path in &report.config_files {
+            println!("  - {}", path.display());
+        }
+    }
+    match &report.cache_dir {
+        Some(dir) => println!("Cache directory: {}", dir.display()),
+        None => println!("Cache directory: not available"),
+    }
+    Ok(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_contains_build_fields() {
+        let json = serde_json::to_value(VersionReport::collect()).unwrap();
+        assert_eq!(json["version"], env!("CARGO_PKG_VERSION"));
+        for field in ["git_describe", "build_date", "rustc", "target", "profile", "pip_compatibility"] {
+            assert!(json[field].as_str().is_some_and(|v| !v.is_empty()), "missing {}", field);
+        }
+        assert!(json["rustc"].as_str().unwrap().starts_with("rustc "));
+        assert!(json["features"].is_array());
+        assert!(json["config_files"].is_array());
+        assert!(chrono::DateTime::parse_from_rfc3339(json["build_date"].as_str().unwrap()).is_ok());
+    }
+}
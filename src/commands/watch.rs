@@ -0,0 +1,339 @@
+/// Watch command - keep the environment in sync while requirements change
+///
+/// Watches a requirements file or lock file and, after each burst of
+/// changes settles, diffs it against the environment and either applies
+/// the plan or (with `--check-only`) just reports it. A status line shows
+/// the last sync time and the current drift. Failed syncs are reported and
+/// the watcher keeps running; Ctrl-C stops it.
+use crate::cli::parser::InstallOptions;
+use crate::errors::PipError;
+use console::Term;
+use notify::{RecursiveMode, Watcher};
+use pip_rs_core::installer::sync_plan::{Pin, SyncPlan};
+use pip_rs_core::utils::environment_markers::{EnvironmentContext, MarkerEvaluator};
+use pip_rs_core::{installer, models, resolver};
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Quiet period after the last change before syncing (editors write in bursts)
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// What a sync found or did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncOutcome {
+    InSync,
+    /// Changes were applied
+    Applied(usize),
+    /// Changes are pending (`--check-only`)
+    Drift(usize),
+}
+
+/// State behind the status line
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WatchStatus {
+    pub syncs: usize,
+    pub last_sync: Option<String>,
+    pub outcome: Option<SyncOutcome>,
+    pub last_error: Option<String>,
+}
+
+impl WatchStatus {
+    pub fn render(&self) -> String {
+        let when = self.last_sync.as_deref().unwrap_or("never");
+        let state = match (&self.last_error, self.outcome) {
+            (Some(error), _) => format!("last sync failed: {}", error),
+            (None, Some(SyncOutcome::InSync)) => "in sync".to_string(),
+            (None, Some(SyncOutcome::Applied(n))) => format!("in sync (applied {} change{})", n, plural(n)),
+            (None, Some(SyncOutcome::Drift(n))) => format!("drift: {} change{} pending", n, plural(n)),
+            (None, None) => "waiting".to_string(),
+        };
+        format!("[watch] last sync {} · {}", when, state)
+    }
+}
+
+fn plural(n: usize) -> &'static str {
+    if n == 1 { "" } else { "s" }
+}
+
+/// Sync once, then again after every debounced burst of `events`, until
+/// the channel closes. Errors from `sync` are recorded, not fatal.
+pub async fn watch_loop<S, F>(
+    mut events: mpsc::UnboundedReceiver<()>,
+    debounce: Duration,
+    mut sync: S,
+    mut report: impl FnMut(&WatchStatus),
+) -> WatchStatus
+where
+    S: FnMut() -> F,
+    F: Future<Output = Result<SyncOutcome, String>>,
+{
+    let mut status = WatchStatus::default();
+    let mut closed = false;
+    loop {
+        let result = sync().await;
+        status.syncs += 1;
+        status.last_sync = Some(chrono::Local::now().format("%H:%M:%S").to_string());
+        match result {
+            Ok(outcome) => {
+                status.outcome = Some(outcome);
+                status.last_error = None;
+            }
+            Err(error) => status.last_error = Some(error),
+        }
+        report(&status);
+
+        if closed || events.recv().await.is_none() {
+            return status;
+        }
+        // Wait until no change has arrived for `debounce`
+        loop {
+            match tokio::time::timeout(debounce, events.recv()).await {
+                Ok(Some(())) => continue,
+                Ok(None) => {
+                    closed = true;
+                    break;
+                }
+                Err(_) => break,
+            }
+        }
+    }
+}
+
+/// What the watcher keeps the environment in line with
+#[derive(Debug, Clone)]
+enum Source {
+    Requirements(PathBuf),
+    Locked(PathBuf),
+}
+
+impl Source {
+    fn path(&self) -> &Path {
+        match self {
+            Source::Requirements(path) | Source::Locked(path) => path,
+        }
+    }
+
+    /// Desired pins, read fresh on every sync
+    fn pins(&self) -> Result<Vec<Pin>, String> {
+        match self {
+            Source::Requirements(path) => {
+                let file = super::install::load_requirements_file(path, false).map_err(|e| e.to_string())?;
+                let mut pins = Vec::new();
+                for line in file.requirements.iter().filter(|r| !r.is_comment) {
+                    let requirement: models::Requirement = line.requirement.parse()?;
+                    let version = match requirement.specs.as_slice() {
+                        [spec] if spec.op == models::VersionOp::Eq => Some(spec.version.clone()),
+                        _ => None,
+                    };
+                    pins.push(Pin {
+                        name: requirement.name,
+                        version,
+                    });
+                }
+                Ok(pins)
+            }
+            Source::Locked(path) => {
+                let lock = resolver::LockFile::load(path).map_err(|e| e.to_string())?;
+                let evaluator = MarkerEvaluator::new(EnvironmentContext::default());
+                Ok(lock
+                    .packages
+                    .into_values()
+                    .filter(|p| p.markers.as_deref().is_none_or(|m| evaluator.evaluate(m)))
+                    .map(|p| Pin {
+                        name: p.name,
+                        version: Some(p.version),
+                    })
+                    .collect())
+            }
+        }
+    }
+}
+
+pub async fn handle_watch(
+    requirements: Option<String>,
+    locked: Option<String>,
+    check_only: bool,
+) -> Result<i32, PipError> {
+    let source = match (requirements, locked) {
+        (_, Some(lock)) => Source::Locked(PathBuf::from(lock)),
+        (Some(requirements), None) => Source::Requirements(PathBuf::from(requirements)),
+        (None, None) => {
+            eprintln!("ERROR: You must provide a requirements file with -r or a lock file with --locked");
+            return Ok(1);
+        }
+    };
+    let path = source.path().to_path_buf();
+    if !path.exists() {
+        eprintln!("ERROR: File not found: {}", path.display());
+        return Ok(1);
+    }
+
+    // Editors often save by renaming, so watch the directory and filter
+    let (sender, events) = mpsc::unbounded_channel();
+    let watched = path.canonicalize().unwrap_or_else(|_| path.clone());
+    let file_name = watched.file_name().map(|n| n.to_os_string());
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if let Ok(event) = event
+            && event.paths.iter().any(|p| p.file_name().map(|n| n.to_os_string()) == file_name)
+        {
+            let _ = sender.send(());
+        }
+    })
+    .map_err(|e| watch_error(&path, e))?;
+    let directory = watched.parent().unwrap_or(Path::new(".")).to_path_buf();
+    watcher
+        .watch(&directory, RecursiveMode::NonRecursive)
+        .map_err(|e| watch_error(&path, e))?;
+
+    println!(
+        "Watching {} ({}); press Ctrl-C to stop",
+        path.display(),
+        if check_only { "reporting drift" } else { "applying changes" }
+    );
+
+    let term = Term::stderr();
+    let report = |status: &WatchStatus| {
+        if term.is_term() {
+            let _ = term.clear_line();
+            let _ = term.write_str(&status.render());
+        } else {
+            eprintln!("{}", status.render());
+        }
+    };
+    let sync = || sync_once(&source, check_only);
+
+    tokio::select! {
+        _ = watch_loop(events, DEBOUNCE, sync, report) => {}
+        _ = tokio::signal::ctrl_c() => {}
+    }
+    drop(watcher);
+    if term.is_term() {
+        let _ = term.write_line("");
+    }
+    println!("Stopped watching {}", path.display());
+    Ok(0)
+}
+
+fn watch_error(path: &Path, e: notify::Error) -> PipError {
+    PipError::FileSystemError {
+        path: path.display().to_string(),
+        operation: "watch".to_string(),
+        reason: e.to_string(),
+    }
+}
+
+/// Diff the source against the environment and apply (or report) the plan
+async fn sync_once(source: &Source, check_only: bool) -> Result<SyncOutcome, String> {
+    let pins = source.pins()?;
+    let plan = SyncPlan::compute(&pins, &installed_versions()?);
+    if plan.is_empty() {
+        return Ok(SyncOutcome::InSync);
+    }
+
+    println!();
+    for change in &plan.changes {
+        println!("  {}", change);
+    }
+    if check_only {
+        return Ok(SyncOutcome::Drift(plan.changes.len()));
+    }
+
+    let options = InstallOptions {
+        packages: plan.requirements(),
+        ..Default::default()
+    };
+    match super::install::handle_install(options).await {
+        Ok(0) => Ok(SyncOutcome::Applied(plan.changes.len())),
+        Ok(code) => Err(format!("install exited with status {}", code)),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+pub(crate) fn installed_versions() -> Result<HashMap<String, String>, String> {
+    let site_packages = installer::SitePackages::default().map_err(|e| e.to_string())?;
+    let packages = site_packages.get_installed_packages().map_err(|e| e.to_string())?;
+    Ok(packages
+        .iter()
+        .filter_map(|dir| dir.strip_suffix(".dist-info")?.rsplit_once('-'))
+        .map(|(name, version)| (name.to_string(), version.to_string()))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    const WINDOW: Duration = Duration::from_millis(50);
+
+    #[tokio::test]
+    async fn test_bursts_are_debounced_into_one_sync() {
+        let (sender, events) = mpsc::unbounded_channel();
+        let calls = Arc::new(Mutex::new(0));
+        let counter = calls.clone();
+        let feeder = tokio::spawn(async move {
+            // One editor save burst, a pause, then a second save
+            for _ in 0..5 {
+                sender.send(()).unwrap();
+            }
+            tokio::time::sleep(WINDOW * 4).await;
+            sender.send(()).unwrap();
+        });
+
+        let status = watch_loop(
+            events,
+            WINDOW,
+            || {
+                *counter.lock().unwrap() += 1;
+                async { Ok(SyncOutcome::InSync) }
+            },
+            |_| {},
+        )
+        .await;
+        feeder.await.unwrap();
+
+        // Initial sync, one per burst
+        assert_eq!(*calls.lock().unwrap(), 3);
+        assert_eq!(status.syncs, 3);
+        assert_eq!(status.outcome, Some(SyncOutcome::InSync));
+    }
+
+    #[tokio::test]
+    async fn test_failed_sync_keeps_watching() {
+        let (sender, events) = mpsc::unbounded_channel();
+        let outcomes = Mutex::new(vec![
+            Ok(SyncOutcome::Drift(2)),
+            Err("network unreachable".to_string()),
+            Ok(SyncOutcome::Applied(2)),
+        ]);
+        let rendered = Mutex::new(Vec::new());
+        let feeder = tokio::spawn(async move {
+            for _ in 0..2 {
+                tokio::time::sleep(WINDOW * 3).await;
+                sender.send(()).unwrap();
+            }
+        });
+
+        let status = watch_loop(
+            events,
+            WINDOW,
+            || {
+                let outcome = outcomes.lock().unwrap().remove(0);
+                async move { outcome }
+            },
+            |status| rendered.lock().unwrap().push(status.render()),
+        )
+        .await;
+        feeder.await.unwrap();
+
+        let rendered = rendered.into_inner().unwrap();
+        assert_eq!(rendered.len(), 3);
+        assert!(rendered[0].ends_with("drift: 2 changes pending"));
+        assert!(rendered[1].ends_with("last sync failed: network unreachable"));
+        assert!(rendered[2].ends_with("in sync (applied 2 changes)"));
+        assert_eq!(status.last_error, None);
+    }
+}
@@ -13,12 +13,14 @@ pub use pip_rs_core::venv;
 pub use pip_rs_core::config;
 
 use clap::{Parser, Subcommand};
+use std::path::PathBuf;
 use std::process;
 
 #[derive(Parser)]
 #[command(name = "pip")]
 #[command(about = "The fastest pip-compatible package installer", long_about = None)]
-#[command(version = "1.0.0")]
+#[command(version)]
+#[command(disable_help_subcommand = true)]
 struct Cli {
     /// Enable verbose logging
     #[arg(short, long, global = true)]
@@ -28,6 +30,54 @@ struct Cli {
     #[arg(short, long, global = true)]
     quiet: bool,
 
+    /// Refuse to modify environments outside a virtualenv (PIP_REQUIRE_VIRTUALENV)
+    #[arg(long, global = true)]
+    require_venv: bool,
+
+    /// Modify an externally managed (PEP 668) system Python anyway (PIP_BREAK_SYSTEM_PACKAGES)
+    #[arg(long, global = true)]
+    break_system_packages: bool,
+
+    /// Use a fresh cache directory instead of migrating an old cache layout
+    #[arg(long, global = true)]
+    no_cache_migration: bool,
+
+    /// Ceiling for parallel work such as downloads and extraction (PIP_RS_JOBS)
+    #[arg(long, global = true, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    jobs: Option<u32>,
+
+    /// Seconds a request to an index may take (PIP_TIMEOUT)
+    #[arg(long, global = true, value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..))]
+    timeout: Option<u64>,
+
+    /// Further attempts after a failed request (PIP_RETRIES)
+    #[arg(long, global = true, value_name = "N")]
+    retries: Option<u32>,
+
+    /// Send every request through this proxy, [scheme://][user:pass@]host:port (PIP_PROXY);
+    /// otherwise HTTPS_PROXY/HTTP_PROXY apply. NO_PROXY hosts are reached directly either way
+    #[arg(long, global = true, value_name = "URL")]
+    proxy: Option<String>,
+
+    /// Never open a network connection: use cached index responses however old, and wheels from
+    /// the wheel cache or --find-links directories (PIP_RS_OFFLINE=1)
+    #[arg(long, global = true)]
+    offline: bool,
+
+    /// Never pipe long output through a pager (same as `pager = off`)
+    #[arg(long, global = true)]
+    no_pager: bool,
+
+    /// Ignore .pip-rs.toml files in this directory and its parents
+    #[arg(long, global = true)]
+    ignore_local_config: bool,
+
+    /// Manage this interpreter's environment instead of the active one: its site-packages, its
+    /// version for markers and wheel tags, and script shebangs; VIRTUAL_ENV is ignored. For
+    /// `venv create`, the Python to create it with: a version (3.12) or an interpreter path
+    #[arg(long, global = true, value_name = "PYTHON")]
+    python: Option<String>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -43,17 +93,129 @@ enum Commands {
         #[arg(short, long)]
         requirements: Option<String>,
 
-        /// Constraints file
+        /// Constraints file: caps versions without adding packages (can be specified multiple times)
         #[arg(short = 'c', long)]
-        constraints: Option<String>,
+        constraints: Vec<String>,
 
         /// Trusted host (can be specified multiple times)
         #[arg(long)]
         trusted_host: Vec<String>,
 
-        /// Target directory for installation
+        /// Base URL of the package index (default: the configured index-url)
+        #[arg(short = 'i', long)]
+        index_url: Option<String>,
+
+        /// Extra index to look packages up on (can be specified multiple times)
+        #[arg(long)]
+        extra_index_url: Vec<String>,
+
+        /// Directory or HTML page of distributions to look in (can be specified multiple times)
+        #[arg(short = 'f', long, value_name = "PATH_OR_URL")]
+        find_links: Vec<String>,
+
+        /// Don't use any package index; look packages up in --find-links only
+        #[arg(long)]
+        no_index: bool,
+
+        /// Install into this directory instead of the environment: code, dist-info, scripts (in bin/)
+        /// and data files all go under it
         #[arg(short, long)]
         target: Option<String>,
+
+        /// Install into the per-user site-packages (~/.local on Unix), scripts into ~/.local/bin
+        #[arg(long)]
+        user: bool,
+
+        /// TOML file of dependency overrides applied during resolution
+        #[arg(long)]
+        override_file: Option<String>,
+
+        /// TOML index policy restricting which index may serve each package
+        #[arg(long, value_name = "FILE")]
+        index_policy: Option<String>,
+
+        /// Upgrade installed packages to the resolved version (downgrades to pins too)
+        #[arg(short = 'U', long)]
+        upgrade: bool,
+
+        /// Don't install package dependencies
+        #[arg(long, conflicts_with = "only_deps")]
+        no_deps: bool,
+
+        /// Install only the dependencies of the given packages or projects
+        #[arg(long)]
+        only_deps: bool,
+
+        /// Keep installing unrelated packages when one fails
+        #[arg(long)]
+        keep_going: bool,
+
+        /// When a package fails, remove the packages this install already
+        /// put in place and restore the versions they replaced
+        #[arg(long, conflicts_with = "keep_going")]
+        rollback_on_failure: bool,
+
+        /// Write a JSON report of installed, kept, failed and skipped packages
+        /// (with --dry-run: of the packages that would be installed); `-`
+        /// prints it on stdout and moves other output to stderr
+        #[arg(long, value_name = "FILE")]
+        report: Option<String>,
+
+        /// Leave unset ${VAR} references in requirements files literal
+        #[arg(long)]
+        allow_unset_env: bool,
+
+        /// Proceed even if the packages involved have damaged metadata
+        #[arg(long)]
+        allow_degraded_environment: bool,
+
+        /// Print the installed size of each package, largest first
+        #[arg(long)]
+        show_sizes: bool,
+
+        /// Require every package to be pinned with == and a --hash in the requirements file
+        #[arg(long)]
+        require_hashes: bool,
+
+        /// Install exactly the packages pinned in a lock file
+        #[arg(long, value_name = "LOCK_FILE", conflicts_with_all = ["packages", "requirements"])]
+        locked: Option<String>,
+
+        /// With --locked: refuse unhashed, unpinned or environment-mismatched entries
+        #[arg(long, requires = "locked")]
+        strict_repro: bool,
+
+        /// With --locked: install a lock made for another Python version or platform
+        #[arg(long, requires = "locked")]
+        ignore_environment: bool,
+
+        /// Reinstall packages even when up to date, replacing console scripts other packages installed
+        #[arg(long)]
+        force_reinstall: bool,
+
+        /// Abort instead of replacing files that running processes have mapped
+        #[arg(long)]
+        refuse_if_in_use: bool,
+
+        /// Resolve and show what would be installed, without downloading or installing
+        #[arg(long, conflicts_with = "locked")]
+        dry_run: bool,
+
+        /// Fail instead of warning when a selected release declares itself deprecated
+        #[arg(long)]
+        no_deprecated: bool,
+
+        /// Give up resolving after this many seconds (default: 300)
+        #[arg(long, value_name = "SECS")]
+        resolution_timeout: Option<u64>,
+
+        /// Print how many candidates and backtracks resolution took
+        #[arg(long)]
+        resolution_stats: bool,
+
+        /// Stream install events as JSON lines to this Unix socket (named pipe on Windows)
+        #[arg(long, value_name = "PATH")]
+        progress_socket: Option<String>,
     },
     /// Uninstall packages
     Uninstall {
@@ -63,40 +225,210 @@ enum Commands {
         /// Assume yes to all prompts
         #[arg(short, long)]
         yes: bool,
+
+        /// Also remove recorded files outside site-packages and the scripts directory
+        #[arg(long)]
+        force: bool,
+
+        /// Uninstall from the per-user site-packages
+        #[arg(long)]
+        user: bool,
+
+        /// Proceed even if the packages involved have damaged metadata
+        #[arg(long)]
+        allow_degraded_environment: bool,
     },
     /// List installed packages
     List {
         /// Show outdated packages
         #[arg(long)]
         outdated: bool,
+
+        /// With --outdated: include pre-release versions
+        #[arg(long, requires = "outdated")]
+        pre: bool,
+
+        /// Show the installed size of each package, largest first
+        #[arg(long, conflicts_with_all = ["outdated", "format"])]
+        sizes: bool,
+
+        /// Output format: columns, json or freeze
+        #[arg(long, value_enum, default_value_t = commands::list::ListFormat::Columns)]
+        format: commands::list::ListFormat,
     },
     /// Show package information
     Show {
-        /// Package name
+        /// Installed packages to show
+        #[arg(required = true)]
+        packages: Vec<String>,
+        /// Also list the top-level import names the package provides
+        #[arg(long)]
+        provides: bool,
+    },
+    /// Print every installed distribution and the environment as JSON
+    Inspect {
+        /// site-packages directory to inspect instead of the active environment's
+        #[arg(long, value_name = "DIR")]
+        path: Option<String>,
+    },
+    /// Find which distribution provides an import name
+    FindImport {
+        /// Module name as used in `import` (e.g. cv2, yaml, PIL.Image)
+        module: String,
+    },
+    /// Show how environment markers decide a package's dependencies here
+    ExplainMarkers {
+        /// Package whose dependency markers to evaluate
         package: String,
+
+        /// Evaluate with a marker variable overridden (repeatable), e.g. sys_platform=win32
+        #[arg(long = "env", value_name = "VARIABLE=VALUE")]
+        env: Vec<String>,
+    },
+    /// Show the dependency closure of a requirement without installing
+    Deps {
+        /// Requirement to resolve, e.g. "fastapi[all]>=0.110"
+        spec: String,
+
+        /// List packages instead of a tree
+        #[arg(long, conflicts_with = "json")]
+        flat: bool,
+
+        /// Print the closure and its edge list as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Include each package's license
+        #[arg(long)]
+        licenses: bool,
+
+        /// Resolve with a marker variable overridden (repeatable), e.g. sys_platform=win32
+        #[arg(long = "env", value_name = "VARIABLE=VALUE")]
+        env: Vec<String>,
+    },
+    /// Make the environment match a lock file exactly: install what it pins, remove the rest.
+    /// Exits 0 when nothing changed and 2 when packages were installed or removed.
+    Sync {
+        /// Lock file to sync to
+        #[arg(value_name = "LOCK_FILE", default_value = "pip-lock.json")]
+        lock_file: String,
+
+        /// Refuse unhashed, unpinned or environment-mismatched entries
+        #[arg(long)]
+        strict_repro: bool,
+
+        /// Sync to a lock made for another Python version or platform
+        #[arg(long)]
+        ignore_environment: bool,
+    },
+    /// Keep the environment in sync with a requirements or lock file
+    Watch {
+        /// Requirements file to watch
+        #[arg(short, long, required_unless_present = "locked")]
+        requirements: Option<String>,
+
+        /// Lock file to watch
+        #[arg(long, value_name = "LOCK_FILE", conflicts_with = "requirements")]
+        locked: Option<String>,
+
+        /// Report drift instead of applying changes
+        #[arg(long)]
+        check_only: bool,
     },
     /// Search for packages
     Search {
-        /// Search query
+        /// Search query: matched against project names
         query: String,
+
+        /// Show at most this many packages
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+
+        /// Output format: human or json
+        #[arg(long, value_enum, default_value_t = commands::search::SearchFormat::Human)]
+        format: commands::search::SearchFormat,
+
+        /// List matching names without looking up their version and summary
+        #[arg(long)]
+        names_only: bool,
+
+        /// Base URL of the package index (default: the configured index-url)
+        #[arg(short = 'i', long)]
+        index_url: Option<String>,
+
+        /// Extra index to look packages up on (can be specified multiple times)
+        #[arg(long)]
+        extra_index_url: Vec<String>,
     },
-    /// Check for outdated packages
+    /// Verify installed packages have compatible dependencies
     Check {
-        /// Package name
+        /// Only check this package's requirements
         #[arg(short, long)]
         package: Option<String>,
+
+        /// Diagnose the environment's health (site-packages, metadata, scripts) instead
+        #[arg(long)]
+        environment: bool,
     },
     /// Update packages (check for outdated and show upgrade instructions)
     #[command(alias = "upgrade")]
     Update {
         /// Package names to update (if empty, update all outdated)
         packages: Vec<String>,
+
+        /// Proceed even if the packages involved have damaged metadata
+        #[arg(long)]
+        allow_degraded_environment: bool,
+
+        /// Choose which outdated packages to upgrade in a terminal picker
+        #[arg(short, long)]
+        interactive: bool,
+
+        /// Requirements or constraints file capping the versions upgraded to
+        #[arg(short = 'c', long)]
+        constraint: Option<String>,
+
+        /// Leave this package alone (can be specified multiple times)
+        #[arg(long, value_name = "NAME")]
+        exclude: Vec<String>,
+
+        /// Include pre-release versions when looking for upgrades
+        #[arg(long)]
+        pre: bool,
+
+        /// Upgrade even when an installed package's requirements exclude the new version
+        #[arg(long)]
+        force: bool,
+
+        /// Upgrade by running the system pip instead (deprecated)
+        #[arg(long)]
+        use_system_pip: bool,
     },
     /// Generate requirements.txt from installed packages
     Freeze {
         /// Output file (if not specified, prints to stdout)
         #[arg(short, long)]
         output: Option<String>,
+
+        /// Emit --hash=sha256:... lines for every installed artifact
+        #[arg(long)]
+        hashes: bool,
+
+        /// With --hashes: download and hash artifacts whose hash isn't recorded
+        #[arg(long, requires = "hashes")]
+        compute_missing: bool,
+
+        /// Include pip, setuptools, wheel and distribute in the output
+        #[arg(long)]
+        all: bool,
+
+        /// Leave a package out of the output (repeatable)
+        #[arg(long, value_name = "PACKAGE")]
+        exclude: Vec<String>,
+
+        /// Leave editable installs out of the output
+        #[arg(long)]
+        exclude_editable: bool,
     },
     /// Download packages without installing
     Download {
@@ -110,6 +442,50 @@ enum Commands {
         /// Destination directory for downloads
         #[arg(short, long)]
         destination: Option<String>,
+
+        /// Base URL of the package index (default: the configured index-url)
+        #[arg(short = 'i', long)]
+        index_url: Option<String>,
+
+        /// Extra index to look packages up on (can be specified multiple times)
+        #[arg(long)]
+        extra_index_url: Vec<String>,
+
+        /// Directory or HTML page of distributions to look in (can be specified multiple times)
+        #[arg(short = 'f', long, value_name = "PATH_OR_URL")]
+        find_links: Vec<String>,
+
+        /// Don't use any package index; look packages up in --find-links only
+        #[arg(long)]
+        no_index: bool,
+
+        /// Download wheels for this platform, e.g. manylinux2014_aarch64 or win_amd64
+        #[arg(long)]
+        platform: Option<String>,
+
+        /// Download for this Python version, e.g. 3.12
+        #[arg(long)]
+        python_version: Option<String>,
+
+        /// Download for this Python implementation, e.g. cp or pp
+        #[arg(long)]
+        implementation: Option<String>,
+
+        /// Download wheels for this ABI, e.g. cp312
+        #[arg(long)]
+        abi: Option<String>,
+
+        /// Only download wheels for these packages (":all:" for every package)
+        #[arg(long)]
+        only_binary: Vec<String>,
+
+        /// Don't download package dependencies
+        #[arg(long, conflicts_with = "only_deps")]
+        no_deps: bool,
+
+        /// Download only the dependencies of the given packages
+        #[arg(long)]
+        only_deps: bool,
     },
     /// Generate lock file for reproducible installs
     Lock {
@@ -120,14 +496,227 @@ enum Commands {
         /// Output lock file path
         #[arg(short, long)]
         output: Option<String>,
+
+        /// TOML file of dependency overrides applied during resolution
+        #[arg(long)]
+        override_file: Option<String>,
+        /// Don't install package dependencies
+        #[arg(long, conflicts_with = "only_deps")]
+        no_deps: bool,
+
+        /// Install only the dependencies of the given packages or projects
+        #[arg(long)]
+        only_deps: bool,
+
+        /// Leave unset ${VAR} references in requirements files literal
+        #[arg(long)]
+        allow_unset_env: bool,
+
+        /// Give up resolving after this many seconds (default: 300)
+        #[arg(long, value_name = "SECS")]
+        resolution_timeout: Option<u64>,
+
+        /// Print how many candidates and backtracks resolution took
+        #[arg(long)]
+        resolution_stats: bool,
+
+        /// Convert an existing poetry.lock or uv.lock instead of resolving
+        #[arg(long = "import", value_name = "LOCK_FILE", conflicts_with_all = ["requirements", "override_file", "no_deps", "only_deps"])]
+        import: Option<String>,
     },
     /// Display debug information
-    Debug,
+    Debug {
+        /// Write a redacted state bundle for bug reports into DIR
+        #[arg(long, value_name = "DIR")]
+        dump_state: Option<String>,
+
+        /// Packages whose cache entries go into the bundle (comma-separated)
+        #[arg(long, value_delimiter = ',', requires = "dump_state")]
+        packages: Vec<String>,
+
+        /// Include cached response bodies so the bundle can be replayed
+        #[arg(long, requires = "dump_state")]
+        include_bodies: bool,
+
+        /// Output format: human or json
+        #[arg(long, value_enum, default_value_t = commands::debug::DebugFormat::Human, conflicts_with = "dump_state")]
+        format: commands::debug::DebugFormat,
+    },
+    /// Load a state bundle into a sandbox (for maintainers)
+    #[command(hide = true)]
+    DevState {
+        /// Bundle written by `pip debug --dump-state`
+        #[arg(long, value_name = "DIR")]
+        load_state: String,
+
+        /// Directory to rebuild the state in (default: a new temp directory)
+        #[arg(long)]
+        sandbox: Option<String>,
+
+        /// Replay a resolution of these requirements against the bundle
+        #[arg(long, value_delimiter = ',')]
+        resolve: Vec<String>,
+    },
     /// Generate shell completion
     Completion {
         /// Shell type (bash, zsh, fish, powershell)
         shell: String,
     },
+    /// Inspect and manage the pip-rs cache
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+    /// Show the pip-rs version; with --verbose, build metadata and active paths
+    Version {
+        /// Emit the extended version information as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// List and update bundled data tables (e.g. the import-name map)
+    Datasets {
+        #[command(subcommand)]
+        action: DatasetsAction,
+    },
+    /// Show configuration values and the files that set them
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Create, inspect and remove virtual environments
+    Venv {
+        #[command(subcommand)]
+        action: VenvAction,
+    },
+    /// Explain a workflow with examples, or show a command's help
+    Help {
+        /// Topic (requirements, lockfiles, offline, venv, migration-from-pip) or command name
+        topic: Option<String>,
+
+        /// Print every topic
+        #[arg(long, conflicts_with = "topic")]
+        all: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum CacheAction {
+    /// Show the cache directory
+    Dir,
+    /// Show the cache location and the size of each part of it
+    Info,
+    /// List cached wheels, optionally those matching a pattern
+    List {
+        /// Package name or glob (`requests`, `requests-2.*`)
+        pattern: Option<String>,
+        /// Output format: human (default) or abspath
+        #[arg(long, default_value = "human")]
+        format: String,
+    },
+    /// Remove cached wheels matching a pattern
+    Remove {
+        /// Package name or glob (`requests`, `requests-2.*`)
+        pattern: String,
+    },
+    /// Remove cached responses and stale partial downloads
+    Purge,
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// List settings from pip.conf and .pip-rs.toml files with their source
+    List,
+}
+
+#[derive(Subcommand)]
+enum VenvAction {
+    /// Create a virtual environment
+    Create {
+        /// Directory to create it in; --python picks the interpreter, default: python3 on PATH
+        path: PathBuf,
+    },
+    /// Show an environment's Python version, site-packages and whether it is valid
+    Info {
+        /// The environment's directory
+        path: PathBuf,
+    },
+    /// Delete a virtual environment
+    Remove {
+        /// The environment's directory
+        path: PathBuf,
+        /// Don't ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum DatasetsAction {
+    /// Show each dataset's source, version and age
+    List,
+    /// Fetch, verify and install refreshed datasets
+    Update {
+        /// Base URL to fetch the signed manifest from (default: `datasets-url` config)
+        #[arg(long)]
+        url: Option<String>,
+    },
+}
+
+/// The command line with defaults from `.pip-rs.toml` files added, when
+/// any apply. A file that can't be used is fatal, so a bad checkout can't
+/// silently change what a command does.
+fn with_local_defaults() -> Option<Vec<String>> {
+    use clap::CommandFactory;
+
+    let cwd = std::env::current_dir().ok()?;
+    let trusted = config::config::Config::new().trusted_local_configs().to_vec();
+    let args: Vec<String> = std::env::args().collect();
+    let applied = config::local::LocalConfig::load(&cwd, &trusted)
+        .map_err(|e| errors::PipError::ConfigError { message: e.to_string() })
+        .and_then(|local| cli::local_defaults::apply(&Cli::command(), args.clone(), &local));
+    match applied {
+        Ok(applied) => (applied != args).then_some(applied),
+        Err(e) => {
+            eprintln!("{}", errors::format_pip_error(&e));
+            eprintln!("Run with --ignore-local-config to skip directory config files");
+            process::exit(1);
+        }
+    }
+}
+
+/// The configuration every command reads: pip.conf, `.pip-rs.toml` files
+/// and `PIP_*` variables, with the global command-line flags on top
+fn effective_config(cli: &Cli) -> config::config::Config {
+    let mut config = if cli.ignore_local_config {
+        config::config::Config::without_local()
+    } else {
+        config::config::Config::new()
+    };
+    if cli.require_venv {
+        config.set_require_virtualenv(true);
+    }
+    if cli.break_system_packages {
+        config.set_break_system_packages(true);
+    }
+    if let Some(jobs) = cli.jobs {
+        config.set_jobs(jobs as usize);
+    }
+    if cli.no_pager {
+        config.set_pager("off".to_string());
+    }
+    if let Some(timeout) = cli.timeout {
+        config.set_timeout(timeout);
+    }
+    if let Some(retries) = cli.retries {
+        config.set_retries(retries);
+    }
+    if let Some(proxy) = &cli.proxy {
+        config.set_proxy(proxy.clone());
+    }
+    if cli.offline {
+        config.set_offline(true);
+    }
+    config
 }
 
 /// Initialize logging with appropriate verbosity level
@@ -168,61 +757,368 @@ fn init_logging(verbose: bool, quiet: bool) {
 
 #[tokio::main]
 async fn main() {
-    let cli = Cli::parse();
+    let mut cli = Cli::parse();
+
+    if !cli.ignore_local_config
+        && let Some(args) = with_local_defaults()
+    {
+        cli = Cli::parse_from(args);
+    }
 
     // Initialize logging based on verbose/quiet flags
     init_logging(cli.verbose, cli.quiet);
 
+    let config = effective_config(&cli);
+    network::init_global_client(config.clone());
+    config.init_global();
+
+    // `venv create --python` names the Python to create with, which may be a bare version;
+    // everywhere else it is the interpreter whose environment is managed
+    let creates_venv = matches!(cli.command, Commands::Venv { action: VenvAction::Create { .. } });
+    if let Some(python) = cli.python.as_deref().filter(|_| !creates_venv) {
+        if let Err(e) = utils::python_env::select(std::path::Path::new(python)) {
+            eprintln!("{}", format_pip_error(&e));
+            process::exit(e.exit_code());
+        }
+    }
+
+    // Check (and migrate if needed) the cache layout before anything reads it.
+    // Other cache problems just disable caching; an unknown layout is fatal.
+    if let Err(e) = cache::layout::prepare_cache_root(!cli.no_cache_migration) {
+        match e.downcast::<cache::layout::UnsupportedLayoutError>() {
+            Ok(layout) => {
+                let error = errors::PipError::from(cache::CacheError::from(layout));
+                eprintln!("{}", format_pip_error(&error));
+                process::exit(error.exit_code());
+            }
+            Err(e) => tracing::debug!("Cache unavailable: {}", e),
+        }
+    }
+
+    // Uninstall starts handling interrupts itself, once it has asked for confirmation
+    if matches!(cli.command, Commands::Install { .. } | Commands::Sync { .. } | Commands::Update { .. }) {
+        cli::signals::install();
+    }
+
     let result = match cli.command {
         Commands::Install {
             packages,
             requirements,
             constraints,
             trusted_host,
+            index_url,
+            extra_index_url,
+            find_links,
+            no_index,
             target,
+            user,
+            override_file,
+            index_policy,
+            upgrade,
+            no_deps,
+            only_deps,
+            keep_going,
+            rollback_on_failure,
+            report,
+            allow_unset_env,
+            allow_degraded_environment,
+            show_sizes,
+            require_hashes,
+            locked,
+            strict_repro,
+            ignore_environment,
+            force_reinstall,
+            refuse_if_in_use,
+            dry_run,
+            no_deprecated,
+            resolution_timeout,
+            resolution_stats,
+            progress_socket,
         } => {
-            commands::install::handle_install(packages, requirements, constraints, trusted_host, target).await
+            commands::install::handle_install(cli::parser::InstallOptions {
+                packages,
+                requirements: requirements.map(PathBuf::from),
+                constraints: constraints.into_iter().map(PathBuf::from).collect(),
+                trusted_hosts: trusted_host,
+                index_url,
+                extra_index_urls: extra_index_url,
+                find_links,
+                no_index,
+                target: target.map(PathBuf::from),
+                user,
+                override_file: override_file.map(PathBuf::from),
+                index_policy: index_policy.map(PathBuf::from),
+                upgrade,
+                no_deps,
+                only_deps,
+                keep_going,
+                rollback_on_failure,
+                report: report.map(PathBuf::from),
+                allow_unset_env,
+                allow_degraded_environment,
+                show_sizes,
+                require_hashes,
+                locked: locked.map(PathBuf::from),
+                strict_repro,
+                ignore_environment,
+                force_reinstall,
+                refuse_if_in_use,
+                dry_run,
+                no_deprecated,
+                verbose: cli.verbose,
+                resolution: cli::parser::ResolutionOptions {
+                    timeout: resolution_timeout,
+                    stats: resolution_stats,
+                },
+                progress_socket: progress_socket.map(PathBuf::from),
+                ..Default::default()
+            })
+            .await
+        }
+        Commands::Uninstall { packages, yes, force, user, allow_degraded_environment } => {
+            commands::uninstall::handle_uninstall(packages, yes, force, user, allow_degraded_environment).await
+        }
+        Commands::List { outdated, pre, sizes, format } => {
+            commands::list::handle_list(outdated, pre, sizes, format).await
+        }
+        Commands::Show { packages, provides } => commands::show::handle_show(&packages, provides).await,
+        Commands::Inspect { path } => commands::inspect::handle_inspect(path.map(PathBuf::from)).await,
+        Commands::FindImport { module } => commands::find_import::handle_find_import(&module).await,
+        Commands::ExplainMarkers { package, env } => {
+            commands::explain_markers::handle_explain_markers(&package, env).await
         }
-        Commands::Uninstall { packages, yes } => {
-            commands::uninstall::handle_uninstall(packages, yes).await
+        Commands::Deps { spec, flat, json, licenses, env } => {
+            commands::deps::handle_deps(spec, flat, json, licenses, env).await
         }
-        Commands::List { outdated } => commands::list::handle_list(outdated).await,
-        Commands::Show { package } => commands::show::handle_show(&package).await,
-        Commands::Search { query } => commands::search::handle_search(&query).await,
-        Commands::Check { package } => commands::check::handle_check(package).await,
-        Commands::Update { packages } => {
+        Commands::Sync { lock_file, strict_repro, ignore_environment } => {
+            commands::lock::handle_lock_install(lock_file, strict_repro, ignore_environment, true).await
+        }
+        Commands::Watch { requirements, locked, check_only } => {
+            commands::watch::handle_watch(requirements, locked, check_only).await
+        }
+        Commands::Search { query, limit, format, names_only, index_url, extra_index_url } => {
+            commands::search::handle_search(&query, index_url, extra_index_url, limit, format, names_only).await
+        }
+        Commands::Check { package, environment } => commands::check::handle_check(package, environment).await,
+        Commands::Update {
+            packages,
+            allow_degraded_environment,
+            interactive,
+            constraint,
+            exclude,
+            pre,
+            force,
+            use_system_pip,
+        } => {
+            let config = commands::upgrade::traits::UpgradeConfig {
+                interactive,
+                verbose: cli.verbose,
+                constraints: constraint.map(PathBuf::from),
+                exclude,
+                pre,
+                force,
+                ..Default::default()
+            };
             if packages.is_empty() {
                 // Update all outdated packages
-                commands::upgrade::handle_upgrade_all().await
+                commands::upgrade::handle_upgrade_all(config, allow_degraded_environment, use_system_pip).await
             } else {
                 // Update specific packages
-                commands::upgrade::handle_upgrade_packages(packages).await
+                commands::upgrade::handle_upgrade_packages(packages, config, allow_degraded_environment, use_system_pip)
+                    .await
             }
         }
-        Commands::Freeze { output } => commands::freeze::handle_freeze(output).await,
+        Commands::Freeze { output, hashes, compute_missing, all, exclude, exclude_editable } => {
+            let filter = commands::freeze::FreezeFilter { all, exclude, exclude_editable };
+            commands::freeze::handle_freeze(output, hashes, compute_missing, filter).await
+        }
         Commands::Download {
             packages,
             requirements,
             destination,
-        } => commands::download::handle_download(packages, requirements, destination).await,
+            index_url,
+            extra_index_url,
+            find_links,
+            no_index,
+            platform,
+            python_version,
+            implementation,
+            abi,
+            only_binary,
+            no_deps,
+            only_deps,
+        } => {
+            let target = cli::parser::TargetOptions { platform, python_version, implementation, abi, only_binary };
+            let indexes = pip_rs_core::utils::requirements_parser::FileOptions {
+                index_url,
+                extra_index_urls: extra_index_url,
+                find_links,
+                no_index,
+                ..Default::default()
+            };
+            commands::download::handle_download(
+                packages,
+                requirements,
+                destination,
+                indexes,
+                target,
+                no_deps,
+                only_deps,
+            )
+            .await
+        }
         Commands::Lock {
             requirements,
             output,
-        } => commands::lock::handle_lock(requirements, output).await,
-        Commands::Debug => commands::debug::handle_debug().await,
+            override_file,
+            no_deps,
+            only_deps,
+            allow_unset_env,
+            resolution_timeout,
+            resolution_stats,
+            import,
+        } => match import {
+            Some(import) => commands::lock::handle_lock_import(import, output).await,
+            None => {
+                let resolution = cli::parser::ResolutionOptions {
+                    timeout: resolution_timeout,
+                    stats: resolution_stats,
+                };
+                commands::lock::handle_lock(requirements, output, override_file, no_deps, only_deps, allow_unset_env, resolution)
+                    .await
+            }
+        },
+        Commands::Debug { dump_state, packages, include_bodies, format } => match dump_state {
+            Some(dir) => commands::debug::handle_dump_state(dir, packages, include_bodies).await,
+            None => commands::debug::handle_debug(format, cli.verbose).await,
+        },
+        Commands::DevState { load_state, sandbox, resolve } => {
+            commands::debug::handle_load_state(load_state, sandbox, resolve).await
+        }
         Commands::Completion { shell } => commands::completion::handle_completion(shell).await,
+        Commands::Cache { action } => match action {
+            CacheAction::Dir => commands::cache::handle_cache_dir().await,
+            CacheAction::Info => commands::cache::handle_cache_info().await,
+            CacheAction::List { pattern, format } => commands::cache::handle_cache_list(pattern, format).await,
+            CacheAction::Remove { pattern } => commands::cache::handle_cache_remove(pattern).await,
+            CacheAction::Purge => commands::cache::handle_cache_purge().await,
+        },
+        Commands::Version { json } => commands::version::handle_version(cli.verbose, json).await,
+        Commands::Datasets { action } => match action {
+            DatasetsAction::List => commands::datasets::handle_datasets_list().await,
+            DatasetsAction::Update { url } => commands::datasets::handle_datasets_update(url).await,
+        },
+        Commands::Venv { action } => match action {
+            VenvAction::Create { path } => commands::venv::handle_venv_create(path, cli.python).await,
+            VenvAction::Info { path } => commands::venv::handle_venv_info(path).await,
+            VenvAction::Remove { path, yes } => commands::venv::handle_venv_remove(path, yes).await,
+        },
+        Commands::Config { action } => match action {
+            ConfigAction::List => commands::config::handle_config_list(cli.ignore_local_config).await,
+        },
+        Commands::Help { topic, all } => {
+            use clap::CommandFactory;
+            commands::help::handle_help(topic, all, Cli::command()).await
+        }
     };
 
-use errors::format_error_with_suggestion;
+use errors::format_pip_error;
 
 // ... (rest of the file)
 
+    // Re-print collected warnings so they aren't lost in progress output
+    utils::warnings::print_summary();
+    utils::journal::record_run(&utils::warnings::collected(), result.as_ref().err());
+
+    if utils::cancel::is_interrupted() {
+        if let Err(e) = &result {
+            tracing::debug!("Interrupted command ended with: {}", e);
+        }
+        eprintln!("Operation cancelled");
+        process::exit(utils::cancel::EXIT_INTERRUPTED);
+    }
     match result {
         Ok(code) => process::exit(code),
         Err(e) => {
-            eprintln!("{}", format_error_with_suggestion(&e.to_string()));
+            eprintln!("{}", format_pip_error(&e));
             eprintln!("Run with RUST_LOG=debug for more details");
-            process::exit(1);
+            process::exit(e.exit_code());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_version_flag_matches_crate_version() {
+        assert_eq!(Cli::command().render_version().trim_end(), commands::version::plain_version());
+    }
+
+    #[test]
+    fn test_help_topic_examples_parse() {
+        use commands::help::{ExampleKind, TOPICS};
+
+        for topic in TOPICS {
+            for example in topic.examples.iter().filter(|e| e.kind != ExampleKind::Shell) {
+                let args = shell_words(example.command);
+                assert_eq!(args[0], "pip", "{}: {}", topic.name, example.command);
+                if let Err(e) = Cli::try_parse_from(&args) {
+                    panic!("help topic '{}' example `{}` does not parse:\n{}", topic.name, example.command, e);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_global_flags_reach_the_configuration() {
+        let cli = Cli::parse_from([
+            "pip",
+            "--require-venv",
+            "--break-system-packages",
+            "--jobs",
+            "1",
+            "--no-pager",
+            "--timeout",
+            "5",
+            "--retries",
+            "0",
+            "--proxy",
+            "http://proxy.example:3128",
+            "--offline",
+            "list",
+        ]);
+        let config = effective_config(&cli);
+        assert!(config.require_virtualenv());
+        assert!(config.break_system_packages());
+        assert_eq!(config.jobs(), Some(1));
+        assert_eq!(config.pager(), Some("off"));
+        assert_eq!((config.timeout(), config.retries()), (5, 0));
+        assert_eq!(config.proxy(), Some("http://proxy.example:3128"));
+        assert!(config.offline());
+    }
+
+    /// Split a command line on whitespace, honoring double quotes
+    fn shell_words(line: &str) -> Vec<String> {
+        let mut words = Vec::new();
+        let mut word = String::new();
+        let mut quoted = false;
+        for c in line.chars() {
+            match c {
+                '"' => quoted = !quoted,
+                c if c.is_whitespace() && !quoted => {
+                    if !word.is_empty() {
+                        words.push(std::mem::take(&mut word));
+                    }
+                }
+                c => word.push(c),
+            }
+        }
+        if !word.is_empty() {
+            words.push(word);
         }
+        words
     }
 }
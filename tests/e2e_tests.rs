@@ -433,3 +433,217 @@ fn test_e2e_performance_tracking() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+/// Build a pure-Python `demo-pkg` wheel with a console script and a data
+/// file into `links`
+fn build_demo_wheel(dir: &TempDir, version: &str, links: &std::path::Path) -> Result<(), Box<dyn std::error::Error>> {
+    let src = dir.path().join(format!("demo-src-{}", version));
+    fs::create_dir_all(src.join("demo_pkg").join("data"))?;
+    fs::write(
+        src.join("pyproject.toml"),
+        format!(
+            "[project]\nname = \"demo-pkg\"\nversion = \"{}\"\n\n[project.scripts]\ndemo = \"demo_pkg:main\"\n",
+            version
+        ),
+    )?;
+    fs::write(src.join("demo_pkg").join("__init__.py"), format!("VERSION = '{}'\ndef main(): pass\n", version))?;
+    fs::write(src.join("demo_pkg").join("data").join("config.json"), "{}\n")?;
+    fs::create_dir_all(links)?;
+    pip_rs::installer::source_tree::SourceTree::load(&src)?.build_wheel(links, &[])?;
+    Ok(())
+}
+
+#[test]
+fn test_e2e_install_target_is_self_contained() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let root = temp_dir.path();
+    let links = root.join("links");
+    build_demo_wheel(&temp_dir, "1.0", &links)?;
+
+    // An active virtualenv that --target must leave alone
+    let venv = root.join("venv");
+    let venv_site = venv.join("lib").join("python3.11").join("site-packages");
+    fs::create_dir_all(&venv_site)?;
+    fs::create_dir_all(venv.join("bin"))?;
+    fs::write(venv.join("pyvenv.cfg"), "home = /usr/bin\nversion = 3.11.4\n")?;
+
+    let install = |extra: &[&str]| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_pip-rs"))
+            .current_dir(root)
+            .env("HOME", root.join("home"))
+            .env("PIP_CACHE_DIR", root.join("cache"))
+            .env("VIRTUAL_ENV", &venv)
+            .args(["install", "--no-index", "--find-links", "links", "--target", "vendor"])
+            .args(extra)
+            .arg("demo-pkg")
+            .output()
+    };
+    let output = install(&[])?;
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let target = root.join("vendor");
+    assert!(target.join("demo_pkg").join("__init__.py").is_file());
+    assert!(target.join("demo_pkg").join("data").join("config.json").is_file());
+    assert!(target.join("bin").join("demo").is_file());
+    let record = fs::read_to_string(target.join("demo_pkg-1.0.dist-info").join("RECORD"))?;
+    assert!(record.lines().any(|line| line.starts_with("bin/demo,")));
+    assert!(record.lines().all(|line| !line.starts_with("..") && !line.starts_with('/')), "{}", record);
+
+    // Nothing outside the target changed
+    assert_eq!(fs::read_dir(&venv_site)?.count(), 0);
+    assert_eq!(fs::read_dir(venv.join("bin"))?.count(), 0);
+    let mut entries: Vec<String> =
+        fs::read_dir(root)?.flatten().map(|entry| entry.file_name().to_string_lossy().into_owned()).collect();
+    entries.sort();
+    entries.retain(|name| !name.starts_with("demo-src-") && name != "home" && name != "cache");
+    assert_eq!(entries, ["links", "vendor", "venv"]);
+
+    // --upgrade replaces the copy inside the target
+    build_demo_wheel(&temp_dir, "2.0", &links)?;
+    let output = install(&["--upgrade"])?;
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(!target.join("demo_pkg-1.0.dist-info").exists());
+    assert!(target.join("demo_pkg-2.0.dist-info").join("RECORD").is_file());
+    assert!(fs::read_to_string(target.join("demo_pkg").join("__init__.py"))?.contains("2.0"));
+    assert_eq!(fs::read_dir(&venv_site)?.count(), 0);
+
+    Ok(())
+}
+
+#[test]
+fn test_e2e_rollback_on_failure_restores_replaced_versions() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let root = temp_dir.path();
+    let links = root.join("links");
+    build_demo_wheel(&temp_dir, "1.0", &links)?;
+
+    let install = |extra: &[&str]| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_pip-rs"))
+            .current_dir(root)
+            .env("HOME", root.join("home"))
+            .env("PIP_CACHE_DIR", root.join("cache"))
+            .env_remove("VIRTUAL_ENV")
+            .args(["install", "--no-index", "--find-links", "links", "--target", "vendor"])
+            .args(extra)
+            .output()
+    };
+    let output = install(&["demo-pkg"])?;
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    // broken-pkg needs demo-pkg 2.0, and its module collides with a directory
+    build_demo_wheel(&temp_dir, "2.0", &links)?;
+    let src = root.join("broken-src");
+    fs::create_dir_all(src.join("broken_pkg"))?;
+    fs::write(
+        src.join("pyproject.toml"),
+        "[project]\nname = \"broken-pkg\"\nversion = \"1.0\"\ndependencies = [\"demo-pkg>=2\"]\n",
+    )?;
+    fs::write(src.join("broken_pkg").join("__init__.py"), "")?;
+    pip_rs::installer::source_tree::SourceTree::load(&src)?.build_wheel(&links, &[])?;
+    let target = root.join("vendor");
+    fs::create_dir_all(target.join("broken_pkg").join("__init__.py"))?;
+
+    let output = install(&["--rollback-on-failure", "--report", "report.json", "broken-pkg"])?;
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(target.join("demo_pkg-1.0.dist-info").join("RECORD").is_file(), "{}", stderr);
+    assert!(!target.join("demo_pkg-2.0.dist-info").exists());
+    assert!(fs::read_to_string(target.join("demo_pkg").join("__init__.py"))?.contains("1.0"));
+    assert!(target.join("bin").join("demo").is_file());
+    // No staging directory, snapshot or backup left behind
+    for dir in [target.clone(), target.join("bin"), target.join("demo_pkg")] {
+        let names: Vec<String> =
+            fs::read_dir(&dir)?.flatten().map(|entry| entry.file_name().to_string_lossy().into_owned()).collect();
+        assert!(names.iter().all(|name| !name.starts_with(".pip-rs-")), "{:?}", names);
+    }
+
+    let report: serde_json::Value = serde_json::from_str(&fs::read_to_string(root.join("report.json"))?)?;
+    assert_eq!(report["rolled_back"][0]["name"], "demo-pkg");
+    assert_eq!(report["failed"][0]["name"], "broken-pkg");
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_e2e_install_for_another_interpreter() -> Result<(), Box<dyn std::error::Error>> {
+    use std::os::unix::fs::PermissionsExt;
+    let temp_dir = TempDir::new()?;
+    let root = temp_dir.path();
+    let links = root.join("links");
+    build_demo_wheel(&temp_dir, "1.0", &links)?;
+
+    // A virtualenv's interpreter that only knows how to describe itself
+    let env = root.join("env");
+    let env_site = env.join("lib").join("python3.11").join("site-packages");
+    fs::create_dir_all(&env_site)?;
+    fs::create_dir_all(env.join("bin"))?;
+    let python = env.join("bin").join("python");
+    let description = serde_json::json!({
+        "version": "3.11.9", "implementation": "cpython", "implementation_version": "3.11.9",
+        "executable": python, "prefix": env, "base_prefix": "/usr", "purelib": env_site, "scripts": env.join("bin"),
+    });
+    fs::write(&python, format!("#!/bin/sh\necho '{}'\n", description))?;
+    fs::set_permissions(&python, fs::Permissions::from_mode(0o755))?;
+
+    // The active virtualenv, which --python overrides
+    let decoy = root.join("decoy");
+    let decoy_site = decoy.join("lib").join("python3.12").join("site-packages");
+    fs::create_dir_all(&decoy_site)?;
+    fs::write(decoy.join("pyvenv.cfg"), "home = /usr/bin\nversion = 3.12.1\n")?;
+
+    let install = |python: &std::path::Path| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_pip-rs"))
+            .current_dir(root)
+            .env("HOME", root.join("home"))
+            .env("PIP_CACHE_DIR", root.join("cache"))
+            .env("VIRTUAL_ENV", &decoy)
+            .arg("--python")
+            .arg(python)
+            .args(["install", "--no-index", "--find-links", "links", "demo-pkg"])
+            .output()
+    };
+    let output = install(&python)?;
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(env_site.join("demo_pkg").join("__init__.py").is_file());
+    assert!(env_site.join("demo_pkg-1.0.dist-info").join("RECORD").is_file());
+    let script = fs::read_to_string(env.join("bin").join("demo"))?;
+    assert_eq!(script.lines().next(), Some(format!("#!{}", python.display()).as_str()));
+    assert_eq!(fs::read_dir(&decoy_site)?.count(), 0);
+
+    let output = install(&root.join("missing").join("python"))?;
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Cannot use") && stderr.contains("no such file"), "{}", stderr);
+
+    Ok(())
+}
+
+#[test]
+fn test_e2e_ignore_local_config_reaches_every_reader() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let root = temp_dir.path();
+    fs::write(root.join(".pip-rs.toml"), "[defaults]\nindex-url = \"https://corp.example/simple\"\n")?;
+
+    let run = |args: &[&str]| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_pip-rs"))
+            .current_dir(root)
+            .env("HOME", root.join("home"))
+            .env("PIP_CACHE_DIR", root.join("cache"))
+            .env_remove("PIP_INDEX_URL")
+            .args(args)
+            .output()
+    };
+    for command in [&["debug"][..], &["config", "list"]] {
+        let output = run(command)?;
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        assert!(String::from_utf8_lossy(&output.stdout).contains("https://corp.example/simple"));
+
+        let ignored = [&["--ignore-local-config"][..], command].concat();
+        let output = run(&ignored)?;
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        assert!(!String::from_utf8_lossy(&output.stdout).contains("corp.example"));
+    }
+
+    Ok(())
+}
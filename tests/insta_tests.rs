@@ -1,5 +1,5 @@
 use insta::assert_debug_snapshot;
-use pip_rs::commands::upgrade::detector::compare_versions;
+use pip_rs::utils::version::compare as compare_versions;
 use std::cmp::Ordering;
 
 #[test]
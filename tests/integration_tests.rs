@@ -207,3 +207,166 @@ fn test_entry_point_generation_workflow() -> Result<(), Box<dyn std::error::Erro
 
     Ok(())
 }
+
+#[test]
+fn test_generated_script_runs_with_and_without_its_package() -> Result<(), Box<dyn std::error::Error>> {
+    // Needs a Python interpreter to execute the script
+    let python = "python3";
+    if std::process::Command::new(python).arg("--version").output().is_err() {
+        return Ok(());
+    }
+
+    let temp_dir = TempDir::new()?;
+    let site_packages = temp_dir.path().join("site-packages");
+    let package = site_packages.join("fixturetool");
+    std::fs::create_dir_all(&package)?;
+    std::fs::write(package.join("__init__.py"), "")?;
+    std::fs::write(package.join("cli.py"), "def main():\n    print('fixturetool ran')\n    return 0\n")?;
+
+    let ep = pip_rs::installer::entry_point::EntryPoint::new(
+        "fixturetool".to_string(),
+        "fixturetool.cli".to_string(),
+        "main".to_string(),
+    )
+    .with_owner("fixturetool", &site_packages);
+    let script = ep.install(&temp_dir.path().join("bin"))?.remove(0);
+    let run = || {
+        std::process::Command::new(python)
+            .arg(&script)
+            .env("PYTHONPATH", &site_packages)
+            .output()
+    };
+
+    let output = run()?;
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "fixturetool ran");
+
+    std::fs::remove_dir_all(&package)?;
+    let output = run()?;
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(
+        stderr.trim(),
+        format!(
+            "The 'fixturetool' entry point is installed but its package 'fixturetool' is missing from this \
+             environment (expected at {}); reinstall with: pip install fixturetool",
+            site_packages.display()
+        )
+    );
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_script_runs_through_sh_wrapper_from_dir_with_spaces() -> Result<(), Box<dyn std::error::Error>> {
+    // Needs a Python interpreter to link into the fake venv
+    let Ok(output) = std::process::Command::new("python3")
+        .args(["-c", "import sys; print(sys.executable)"])
+        .output()
+    else {
+        return Ok(());
+    };
+    let real_python = String::from_utf8(output.stdout)?.trim().to_string();
+    if !output.status.success() || real_python.is_empty() {
+        return Ok(());
+    }
+
+    let temp_dir = TempDir::new()?;
+    let venv = temp_dir.path().join("dir with spaces").join("venv-josé");
+    let python = venv.join("bin").join("python");
+    std::fs::create_dir_all(python.parent().unwrap())?;
+    std::os::unix::fs::symlink(&real_python, &python)?;
+    let site_packages = temp_dir.path().join("site-packages");
+    std::fs::create_dir_all(site_packages.join("spacetool"))?;
+    std::fs::write(site_packages.join("spacetool/__init__.py"), "")?;
+    std::fs::write(
+        site_packages.join("spacetool/cli.py"),
+        "import sys\ndef main():\n    print('spacetool ran', sys.argv[1:])\n    return 0\n",
+    )?;
+
+    let ep = pip_rs::installer::entry_point::EntryPoint::new(
+        "spacetool".to_string(),
+        "spacetool.cli".to_string(),
+        "main".to_string(),
+    )
+    .with_owner("spacetool", &site_packages)
+    .with_interpreter(&python);
+    let script = ep.install(&venv.join("bin"))?.remove(0);
+    assert!(std::fs::read_to_string(&script)?.starts_with("#!/bin/sh\n"));
+
+    let output = std::process::Command::new(&script)
+        .arg("an arg")
+        .env("PYTHONPATH", &site_packages)
+        .output()?;
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "spacetool ran ['an arg']");
+
+    Ok(())
+}
+
+/// A fixed index: (name, version, dependencies), the last release of each
+/// name being its latest
+struct FixtureIndex(Vec<(&'static str, &'static str, &'static [&'static str])>);
+
+impl FixtureIndex {
+    fn package(&self, name: &str, version: &str) -> Option<pip_rs::models::Package> {
+        self.0.iter().find(|(n, v, _)| *n == name && *v == version).map(|(name, version, deps)| {
+            pip_rs::models::Package::new(name.to_string(), version.to_string())
+                .with_requires(deps.iter().map(|d| d.to_string()).collect())
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl pip_rs::resolver::MetadataProvider for FixtureIndex {
+    async fn get_package(&self, name: &str) -> anyhow::Result<pip_rs::models::Package> {
+        let (_, version, _) =
+            self.0.iter().rfind(|(n, _, _)| *n == name).ok_or_else(|| anyhow::anyhow!("{} not found", name))?;
+        Ok(self.package(name, version).unwrap())
+    }
+
+    async fn get_releases(&self, name: &str) -> anyhow::Result<Vec<pip_rs::resolver::Release>> {
+        Ok(self
+            .0
+            .iter()
+            .filter(|(n, _, _)| *n == name)
+            .map(|(_, version, _)| pip_rs::resolver::Release {
+                version: version.to_string(),
+                requires_python: None,
+                yanked: false,
+                yanked_reason: None,
+            })
+            .collect())
+    }
+
+    async fn get_package_version(&self, name: &str, version: &str) -> anyhow::Result<pip_rs::models::Package> {
+        self.package(name, version).ok_or_else(|| anyhow::anyhow!("{} {} not found", name, version))
+    }
+}
+
+#[tokio::test]
+async fn test_constraints_cap_transitive_dependencies_without_adding_packages() -> Result<(), Box<dyn std::error::Error>> {
+    let mut resolver = pip_rs::resolver::Resolver::new();
+    resolver.set_provider(std::sync::Arc::new(FixtureIndex(vec![
+        ("app", "1.0", &["requests>=2"]),
+        ("requests", "2.31.0", &["urllib3<3,>=1.21.1", "idna"]),
+        ("urllib3", "1.26.18", &[]),
+        ("urllib3", "2.2.1", &[]),
+        ("idna", "3.6", &[]),
+        ("six", "1.16.0", &[]),
+    ])));
+    // Two constraints files, merged; the names are spelled differently from the dependencies
+    resolver.set_constraints(vec!["URLLib3<2".parse()?]);
+    resolver.set_constraints(vec!["six==1.16.0".parse()?, "IDNA>=3".parse()?]);
+
+    let mut resolved: Vec<String> = resolver
+        .resolve(vec!["app".parse()?])
+        .await?
+        .into_iter()
+        .map(|package| format!("{}=={}", package.name, package.version))
+        .collect();
+    resolved.sort();
+    assert_eq!(resolved, ["app==1.0", "idna==3.6", "requests==2.31.0", "urllib3==1.26.18"]);
+    Ok(())
+}
@@ -2,7 +2,7 @@
 use std::cmp::Ordering;
 
 // Import the compare_versions function from the library
-use pip_rs::commands::upgrade::detector::compare_versions;
+use pip_rs::utils::version::compare as compare_versions;
 
 #[test]
 fn test_pep440_prerelease_comparison() {